@@ -0,0 +1,35 @@
+//! A pack suite as an external pack repository would write it with `greentic_integration::testkit`.
+//!
+//! Start a bridge with your pack loaded (`greentic-integration serve`) and point its messaging
+//! provider at the sink this prints, then run:
+//!
+//! ```text
+//! BRIDGE_URL=http://localhost:8080 cargo run -p greentic-integration --example testkit_pack_suite
+//! ```
+
+use std::time::Duration;
+
+use greentic_integration::testkit::{BridgeClient, ProviderSink, SimUser};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let bridge = std::env::var("BRIDGE_URL").unwrap_or_else(|_| "http://localhost:8080".into());
+    let sink = ProviderSink::start("target/testkit/outbound.json").await?;
+    println!("provider sink listening on {}", sink.send_url());
+
+    let mut user = SimUser::new(BridgeClient::new(&bridge), "testkit-user").tenant("dev");
+    let replies = user.start("menu", "hi").await?;
+    println!("menu replied with {} message(s)", replies.len());
+    if !replies.is_empty() {
+        user.reply("About").await?;
+    }
+    for line in user.transcript() {
+        println!("{line}");
+    }
+
+    match sink.wait_for(1, Duration::from_secs(2)).await {
+        Ok(outbound) => println!("provider received {} delivery(ies)", outbound.len()),
+        Err(err) => println!("no provider deliveries: {err}"),
+    }
+    sink.shutdown().await
+}
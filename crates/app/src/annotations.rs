@@ -0,0 +1,175 @@
+//! Harness results as GitHub Checks annotations.
+//!
+//! Scenario runs, suites and invariant checks collect failures into an [`AnnotationReport`]
+//! and write it next to their other artifacts as `annotations.json`: a JSON array in the shape
+//! of the Checks API `output.annotations` field. A CI wrapper can pass it to
+//! `POST /repos/{owner}/{repo}/check-runs` unchanged, so pack and flow failures show up inline on
+//! the pull request instead of only in the job log.
+
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnotationLevel {
+    Notice,
+    Warning,
+    Failure,
+}
+
+/// One Checks annotation. `path` is relative to the repository root; results that have no
+/// line of their own point at line 1 of the file they concern.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub path: String,
+    pub start_line: u32,
+    pub end_line: u32,
+    pub annotation_level: AnnotationLevel,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+impl Annotation {
+    pub fn new(level: AnnotationLevel, path: impl AsRef<Path>, message: impl Into<String>) -> Self {
+        Self {
+            path: path.as_ref().to_string_lossy().replace('\\', "/"),
+            start_line: 1,
+            end_line: 1,
+            annotation_level: level,
+            message: message.into(),
+            title: None,
+        }
+    }
+
+    pub fn failure(path: impl AsRef<Path>, message: impl Into<String>) -> Self {
+        Self::new(AnnotationLevel::Failure, path, message)
+    }
+
+    pub fn warning(path: impl AsRef<Path>, message: impl Into<String>) -> Self {
+        Self::new(AnnotationLevel::Warning, path, message)
+    }
+
+    /// Point at 1-based `line`.
+    pub fn line(mut self, line: u32) -> Self {
+        self.start_line = line.max(1);
+        self.end_line = self.start_line;
+        self
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AnnotationReport {
+    annotations: Vec<Annotation>,
+}
+
+impl AnnotationReport {
+    pub fn push(&mut self, annotation: Annotation) {
+        self.annotations.push(annotation);
+    }
+
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.annotations.is_empty()
+    }
+
+    pub fn failures(&self) -> usize {
+        self.annotations
+            .iter()
+            .filter(|annotation| annotation.annotation_level == AnnotationLevel::Failure)
+            .count()
+    }
+
+    /// Write the report to `out` as a pretty JSON array. Paths under `root` are made relative
+    /// to it, since GitHub resolves annotation paths against the repository root. An empty
+    /// report is written too, so a wrapper can tell a clean run from one that never reported.
+    pub fn write(&self, out: impl AsRef<Path>, root: impl AsRef<Path>) -> Result<()> {
+        let (out, root) = (out.as_ref(), root.as_ref());
+        let mut annotations = self.annotations.clone();
+        for annotation in &mut annotations {
+            if let Ok(relative) = Path::new(&annotation.path).strip_prefix(root) {
+                annotation.path = relative.to_string_lossy().replace('\\', "/");
+            }
+        }
+        if let Some(parent) = out.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        fs::write(out, serde_json::to_vec_pretty(&annotations)?)
+            .with_context(|| format!("failed to write annotations {}", out.display()))
+    }
+}
+
+impl Extend<Annotation> for AnnotationReport {
+    fn extend<I: IntoIterator<Item = Annotation>>(&mut self, iter: I) {
+        self.annotations.extend(iter);
+    }
+}
+
+/// 1-based line holding the `nth` (0-based) occurrence of `needle` in `text`, for pointing an
+/// annotation at a value inside a fixture.
+pub fn line_of(text: &str, needle: &str, nth: usize) -> Option<u32> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| line.contains(needle))
+        .nth(nth)
+        .map(|(idx, _)| idx as u32 + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+
+    #[test]
+    fn writes_checks_api_annotations_relative_to_the_root() {
+        let tmp = tempfile::tempdir().unwrap();
+        let golden = tmp.path().join("packs/chat/goldens/greeting.json");
+        let mut report = AnnotationReport::default();
+        report.push(
+            Annotation::failure(&golden, "expected \"BOT: hi\"")
+                .line(line_of("[\n  \"BOT: hi\",\n  \"BOT: hi\"\n]", "BOT: hi", 1).unwrap())
+                .title("chat/greeting"),
+        );
+        report.push(Annotation::warning(
+            "fixtures/soak.json",
+            "p99 latency 600ms",
+        ));
+        assert_eq!(report.failures(), 1);
+
+        let out = tmp.path().join("artifacts/annotations.json");
+        report.write(&out, tmp.path()).unwrap();
+        let written: Value = serde_json::from_slice(&fs::read(&out).unwrap()).unwrap();
+        assert_eq!(
+            written,
+            json!([
+                {
+                    "path": "packs/chat/goldens/greeting.json",
+                    "start_line": 3,
+                    "end_line": 3,
+                    "annotation_level": "failure",
+                    "message": "expected \"BOT: hi\"",
+                    "title": "chat/greeting"
+                },
+                {
+                    "path": "fixtures/soak.json",
+                    "start_line": 1,
+                    "end_line": 1,
+                    "annotation_level": "warning",
+                    "message": "p99 latency 600ms"
+                }
+            ])
+        );
+    }
+}
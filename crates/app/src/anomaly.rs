@@ -0,0 +1,353 @@
+//! `[runner.anomaly]`: analyzers watching the recorded runner event stream. Each
+//! [`EventAnalyzer`] sees every event as it is recorded and may report [`Anomaly`]s, which the
+//! bridge records as synthetic events on the `anomaly` flow (and optionally posts to a
+//! webhook), so soak runs flag misbehavior without anyone reading logs.
+//!
+//! The built-in [`ThresholdAnalyzer`] reports, per flow, more than `max_events_per_flow` events
+//! or `max_errors_per_flow` error events within `window_secs` of arrival, and events whose
+//! timestamp falls more than `max_clock_skew_ms` behind the newest one seen for the flow.
+
+use std::collections::{HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::RunnerEvent;
+
+/// Flow of the synthetic events raised for anomalies; analyzers never see them.
+pub const ANOMALY_FLOW: &str = "anomaly";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnomalyConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Sliding window the rate and error thresholds count over.
+    #[serde(default = "default_window_secs")]
+    pub window_secs: u64,
+    /// Events of one flow per window before a `rate_spike` is raised.
+    #[serde(default)]
+    pub max_events_per_flow: Option<usize>,
+    /// Events of one flow with `result.status = "error"` per window before an `error_burst`.
+    #[serde(default)]
+    pub max_errors_per_flow: Option<usize>,
+    /// How far an event's timestamp may trail the flow's newest before it is out of sequence.
+    #[serde(default)]
+    pub max_clock_skew_ms: Option<u64>,
+    /// The same anomaly for the same flow is raised at most once per cooldown.
+    #[serde(default = "default_cooldown_secs")]
+    pub cooldown_secs: u64,
+    /// POST every anomaly as JSON to this URL.
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+}
+
+impl Default for AnomalyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window_secs: default_window_secs(),
+            max_events_per_flow: None,
+            max_errors_per_flow: None,
+            max_clock_skew_ms: None,
+            cooldown_secs: default_cooldown_secs(),
+            webhook_url: None,
+        }
+    }
+}
+
+fn default_window_secs() -> u64 {
+    60
+}
+
+fn default_cooldown_secs() -> u64 {
+    300
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyKind {
+    RateSpike,
+    ErrorBurst,
+    Sequence,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Anomaly {
+    pub analyzer: &'static str,
+    pub kind: AnomalyKind,
+    pub flow: String,
+    pub tenant: Option<String>,
+    pub message: String,
+    pub detected_at_ms: u64,
+    /// Analyzer-specific figures (counts, limits, timestamps).
+    pub details: Value,
+}
+
+impl Anomaly {
+    /// The synthetic runner event recorded for this anomaly.
+    pub fn to_event(&self) -> RunnerEvent {
+        RunnerEvent {
+            timestamp_ms: self.detected_at_ms,
+            flow: ANOMALY_FLOW.to_string(),
+            tenant: self.tenant.clone(),
+            team: None,
+            user: None,
+            payload: serde_json::to_value(self).unwrap_or_default(),
+            result: json!({
+                "status": "anomaly",
+                "kind": self.kind,
+                "flow": self.flow,
+            }),
+            sequence: None,
+            instance: None,
+        }
+    }
+}
+
+/// Watches recorded runner events. `observe` is called once per event, in recording order,
+/// with the time the event was recorded.
+pub trait EventAnalyzer: Send {
+    fn name(&self) -> &'static str;
+    fn observe(&mut self, event: &RunnerEvent, now_ms: u64) -> Vec<Anomaly>;
+}
+
+/// Runs every analyzer over each event, keeping anomaly events away from them.
+#[derive(Default)]
+pub struct AnomalyDetector {
+    analyzers: Vec<Box<dyn EventAnalyzer>>,
+}
+
+impl AnomalyDetector {
+    /// The built-in analyzers for `config`; `None` when detection is disabled.
+    pub fn from_config(config: &AnomalyConfig) -> Option<Self> {
+        config
+            .enabled
+            .then(|| Self::default().with_analyzer(ThresholdAnalyzer::new(config.clone())))
+    }
+
+    pub fn with_analyzer(mut self, analyzer: impl EventAnalyzer + 'static) -> Self {
+        self.analyzers.push(Box::new(analyzer));
+        self
+    }
+
+    pub fn observe(&mut self, event: &RunnerEvent, now_ms: u64) -> Vec<Anomaly> {
+        if event.flow == ANOMALY_FLOW {
+            return Vec::new();
+        }
+        self.analyzers
+            .iter_mut()
+            .flat_map(|analyzer| analyzer.observe(event, now_ms))
+            .collect()
+    }
+}
+
+#[derive(Debug, Default)]
+struct FlowWindow {
+    arrivals: VecDeque<u64>,
+    errors: VecDeque<u64>,
+    newest_timestamp_ms: u64,
+}
+
+/// Fixed per-flow thresholds from [`AnomalyConfig`].
+#[derive(Debug)]
+pub struct ThresholdAnalyzer {
+    config: AnomalyConfig,
+    flows: HashMap<String, FlowWindow>,
+    last_raised: HashMap<(AnomalyKind, String), u64>,
+}
+
+impl ThresholdAnalyzer {
+    pub fn new(config: AnomalyConfig) -> Self {
+        Self {
+            config,
+            flows: HashMap::new(),
+            last_raised: HashMap::new(),
+        }
+    }
+
+    /// `anomaly` unless the same kind was raised for the flow within the cooldown.
+    fn raise(&mut self, anomaly: Anomaly) -> Option<Anomaly> {
+        let key = (anomaly.kind, anomaly.flow.clone());
+        let cooldown_ms = self.config.cooldown_secs.saturating_mul(1000);
+        if let Some(last) = self.last_raised.get(&key)
+            && anomaly.detected_at_ms.saturating_sub(*last) < cooldown_ms
+        {
+            return None;
+        }
+        self.last_raised.insert(key, anomaly.detected_at_ms);
+        Some(anomaly)
+    }
+}
+
+impl EventAnalyzer for ThresholdAnalyzer {
+    fn name(&self) -> &'static str {
+        "threshold"
+    }
+
+    fn observe(&mut self, event: &RunnerEvent, now_ms: u64) -> Vec<Anomaly> {
+        let analyzer = self.name();
+        let window_ms = self.config.window_secs.max(1).saturating_mul(1000);
+        let since = now_ms.saturating_sub(window_ms);
+        let is_error = event.result.get("status").and_then(Value::as_str) == Some("error");
+        let flow = self.flows.entry(event.flow.clone()).or_default();
+        flow.arrivals.push_back(now_ms);
+        if is_error {
+            flow.errors.push_back(now_ms);
+        }
+        for queue in [&mut flow.arrivals, &mut flow.errors] {
+            while queue.front().is_some_and(|at| *at < since) {
+                queue.pop_front();
+            }
+        }
+        let behind_ms = flow.newest_timestamp_ms.saturating_sub(event.timestamp_ms);
+        let newest_timestamp_ms = flow.newest_timestamp_ms;
+        flow.newest_timestamp_ms = flow.newest_timestamp_ms.max(event.timestamp_ms);
+        let (events, errors) = (flow.arrivals.len(), flow.errors.len());
+
+        let anomaly = |kind, message: String, details| Anomaly {
+            analyzer,
+            kind,
+            flow: event.flow.clone(),
+            tenant: event.tenant.clone(),
+            message,
+            detected_at_ms: now_ms,
+            details,
+        };
+        let window_secs = self.config.window_secs;
+        let mut found = Vec::new();
+        if let Some(limit) = self
+            .config
+            .max_events_per_flow
+            .filter(|limit| events > *limit)
+        {
+            found.push(anomaly(
+                AnomalyKind::RateSpike,
+                format!(
+                    "{events} events for flow {} in {window_secs}s (limit {limit})",
+                    event.flow
+                ),
+                json!({ "events": events, "limit": limit, "window_secs": window_secs }),
+            ));
+        }
+        if is_error
+            && let Some(limit) = self
+                .config
+                .max_errors_per_flow
+                .filter(|limit| errors > *limit)
+        {
+            found.push(anomaly(
+                AnomalyKind::ErrorBurst,
+                format!(
+                    "{errors} errors for flow {} in {window_secs}s (limit {limit})",
+                    event.flow
+                ),
+                json!({ "errors": errors, "limit": limit, "window_secs": window_secs }),
+            ));
+        }
+        if let Some(skew) = self
+            .config
+            .max_clock_skew_ms
+            .filter(|skew| behind_ms > *skew)
+        {
+            found.push(anomaly(
+                AnomalyKind::Sequence,
+                format!(
+                    "event for flow {} is {behind_ms}ms older than the newest one (limit {skew}ms)",
+                    event.flow
+                ),
+                json!({
+                    "timestamp_ms": event.timestamp_ms,
+                    "newest_timestamp_ms": newest_timestamp_ms,
+                    "limit_ms": skew,
+                }),
+            ));
+        }
+        found
+            .into_iter()
+            .filter_map(|anomaly| self.raise(anomaly))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(flow: &str, timestamp_ms: u64, status: &str) -> RunnerEvent {
+        RunnerEvent {
+            timestamp_ms,
+            flow: flow.into(),
+            tenant: Some("acme".into()),
+            team: None,
+            user: None,
+            payload: Value::Null,
+            result: json!({ "status": status }),
+            sequence: None,
+            instance: None,
+        }
+    }
+
+    #[test]
+    fn threshold_analyzer_flags_spikes_bursts_and_regressions_once_per_cooldown() {
+        let config = AnomalyConfig {
+            enabled: true,
+            window_secs: 10,
+            max_events_per_flow: Some(3),
+            max_errors_per_flow: Some(1),
+            max_clock_skew_ms: Some(1_000),
+            cooldown_secs: 60,
+            webhook_url: None,
+        };
+        let mut detector = AnomalyDetector::from_config(&config).unwrap();
+        assert!(AnomalyDetector::from_config(&AnomalyConfig::default()).is_none());
+        let kinds = |anomalies: Vec<Anomaly>| -> Vec<AnomalyKind> {
+            anomalies.into_iter().map(|anomaly| anomaly.kind).collect()
+        };
+
+        assert!(detector.observe(&event("chat", 5_000, "ok"), 0).is_empty());
+        assert!(
+            detector
+                .observe(&event("chat", 5_100, "error"), 1_000)
+                .is_empty()
+        );
+        assert_eq!(
+            kinds(detector.observe(&event("chat", 5_200, "error"), 2_000)),
+            vec![AnomalyKind::ErrorBurst]
+        );
+        let spike = detector.observe(&event("chat", 3_000, "ok"), 3_000);
+        assert_eq!(
+            kinds(spike.clone()),
+            vec![AnomalyKind::RateSpike, AnomalyKind::Sequence]
+        );
+        assert_eq!(spike[1].details["newest_timestamp_ms"], 5_200);
+        // Other flows are counted separately; repeats are held back by the cooldown.
+        assert!(
+            detector
+                .observe(&event("billing", 1, "ok"), 3_000)
+                .is_empty()
+        );
+        assert!(
+            detector
+                .observe(&event("chat", 5_300, "error"), 4_000)
+                .is_empty()
+        );
+        // Once the window has moved on, the counts start over.
+        for at in [30_000, 31_000, 32_000] {
+            assert!(
+                detector
+                    .observe(&event("chat", at, "ok"), 70_000 + at)
+                    .is_empty()
+            );
+        }
+        assert_eq!(
+            kinds(detector.observe(&event("chat", 33_000, "ok"), 103_000)),
+            vec![AnomalyKind::RateSpike]
+        );
+
+        let raised = spike[0].to_event();
+        assert_eq!(raised.flow, ANOMALY_FLOW);
+        assert_eq!(raised.result["kind"], "rate_spike");
+        assert_eq!(raised.payload["flow"], "chat");
+        assert!(detector.observe(&raised, 103_000).is_empty());
+    }
+}
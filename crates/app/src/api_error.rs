@@ -0,0 +1,44 @@
+use axum::{
+    Json,
+    http::{StatusCode, header::RETRY_AFTER},
+    response::{IntoResponse, Response},
+};
+use serde_json::Value;
+
+/// Handler error: either a bare status (the common case) or a status with a JSON body
+/// for callers that need structured detail (e.g. schema violations).
+#[derive(Debug)]
+pub enum ApiError {
+    Status(StatusCode),
+    Json(StatusCode, Value),
+    /// `429` with a `Retry-After` of this many seconds.
+    RetryAfter(u64, Value),
+}
+
+impl ApiError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::Status(status) | ApiError::Json(status, _) => *status,
+            ApiError::RetryAfter(..) => StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+}
+
+impl From<StatusCode> for ApiError {
+    fn from(status: StatusCode) -> Self {
+        ApiError::Status(status)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        match self {
+            ApiError::Status(_) => status.into_response(),
+            ApiError::Json(_, body) => (status, Json(body)).into_response(),
+            ApiError::RetryAfter(secs, body) => {
+                (status, [(RETRY_AFTER, secs.to_string())], Json(body)).into_response()
+            }
+        }
+    }
+}
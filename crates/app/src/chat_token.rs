@@ -0,0 +1,119 @@
+//! Short-lived bearer tokens for the dev chat UI (`POST /dev/chat/session`). Each token is
+//! bound to one generated session key and its tenant/team/user, so the browser can call the
+//! session endpoints for that conversation without a long-lived admin token in page source.
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Prefix of every issued token; a bearer token with this prefix must be a live grant.
+pub const CHAT_TOKEN_PREFIX: &str = "gtc_";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DevChatConfig {
+    /// Serve `POST /dev/chat/session`; off unless a dev config turns it on.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Lifetime of an issued token.
+    #[serde(default = "default_token_ttl_secs")]
+    pub token_ttl_secs: u64,
+}
+
+impl Default for DevChatConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            token_ttl_secs: default_token_ttl_secs(),
+        }
+    }
+}
+
+fn default_token_ttl_secs() -> u64 {
+    900
+}
+
+/// What a token may act on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChatGrant {
+    pub session_key: String,
+    pub tenant: String,
+    pub team: Option<String>,
+    pub user: String,
+    pub expires_at_epoch_ms: u64,
+}
+
+impl ChatGrant {
+    /// Whether a request naming these owners stays within the grant; unset fields default
+    /// to the grant's own.
+    pub fn covers(&self, tenant: Option<&str>, team: Option<&str>, user: Option<&str>) -> bool {
+        tenant.is_none_or(|tenant| tenant == self.tenant)
+            && team.is_none_or(|team| Some(team) == self.team.as_deref())
+            && user.is_none_or(|user| user == self.user)
+    }
+}
+
+/// Live grants by token. Expired grants are dropped whenever a token is issued.
+#[derive(Debug, Clone, Default)]
+pub struct ChatTokens {
+    grants: Arc<Mutex<HashMap<String, ChatGrant>>>,
+}
+
+impl ChatTokens {
+    /// Mint a token for `grant`, valid for `ttl` from `now_ms`.
+    pub fn issue(&self, mut grant: ChatGrant, ttl: Duration, now_ms: u64) -> (String, ChatGrant) {
+        let token = format!(
+            "{CHAT_TOKEN_PREFIX}{}{}",
+            Uuid::new_v4().simple(),
+            Uuid::new_v4().simple()
+        );
+        grant.expires_at_epoch_ms = now_ms + u64::try_from(ttl.as_millis()).unwrap_or(u64::MAX);
+        let mut grants = self.grants.lock();
+        grants.retain(|_, grant| grant.expires_at_epoch_ms > now_ms);
+        grants.insert(token.clone(), grant.clone());
+        (token, grant)
+    }
+
+    /// The grant behind `token`, unless it is unknown or expired.
+    pub fn verify(&self, token: &str, now_ms: u64) -> Option<ChatGrant> {
+        self.grants
+            .lock()
+            .get(token)
+            .filter(|grant| grant.expires_at_epoch_ms > now_ms)
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_expire_and_stay_within_their_grant() {
+        let tokens = ChatTokens::default();
+        let grant = ChatGrant {
+            session_key: "key-1".into(),
+            tenant: "acme".into(),
+            team: None,
+            user: "web-1".into(),
+            expires_at_epoch_ms: 0,
+        };
+        let (token, issued) = tokens.issue(grant, Duration::from_secs(60), 1_000);
+        assert!(token.starts_with(CHAT_TOKEN_PREFIX));
+        assert_eq!(issued.expires_at_epoch_ms, 61_000);
+        assert_eq!(tokens.verify(&token, 60_999), Some(issued.clone()));
+        assert!(tokens.verify(&token, 61_000).is_none());
+        assert!(tokens.verify("gtc_unknown", 1_000).is_none());
+
+        assert!(issued.covers(None, None, None));
+        assert!(issued.covers(Some("acme"), None, Some("web-1")));
+        assert!(!issued.covers(Some("globex"), None, None));
+        assert!(!issued.covers(None, Some("ops"), None));
+        assert!(!issued.covers(None, None, Some("web-2")));
+
+        // Issuing prunes expired grants.
+        tokens.issue(issued, Duration::from_secs(1), 70_000);
+        assert_eq!(tokens.grants.lock().len(), 1);
+    }
+}
@@ -0,0 +1,646 @@
+//! Several bridge instances against shared stores (`[cluster]`). Each instance has an id
+//! (`instance_id`, default `<hostname>-<pid>`). With `redis_url` set, background tasks that must
+//! run once per deployment only run on the instance holding that task's lease, every instance
+//! heartbeats its membership, and runner events are published on a Redis channel so each
+//! instance's event log shows the whole cluster. Without it the instance runs standalone and
+//! every task runs locally.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    future::Future,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterConfig {
+    /// Redis used for leases, membership and the event channel; unset runs standalone.
+    #[serde(default)]
+    pub redis_url: Option<String>,
+    /// Key prefix and channel namespace (default `greentic:cluster`); instances sharing it
+    /// form one cluster.
+    #[serde(default)]
+    pub prefix: Option<String>,
+    /// This instance's id; defaults to `<hostname>-<pid>`.
+    #[serde(default)]
+    pub instance_id: Option<String>,
+    /// How long a lease or heartbeat outlives its holder. Holders renew every third of it.
+    #[serde(default = "default_lease_ttl_secs")]
+    pub lease_ttl_secs: u64,
+}
+
+impl Default for ClusterConfig {
+    fn default() -> Self {
+        Self {
+            redis_url: None,
+            prefix: None,
+            instance_id: None,
+            lease_ttl_secs: default_lease_ttl_secs(),
+        }
+    }
+}
+
+fn default_lease_ttl_secs() -> u64 {
+    15
+}
+
+/// Shared coordination state: task leases, instance heartbeats and a broadcast channel.
+pub trait Coordinator: Send + Sync {
+    /// Take `lease` for `instance`, or extend it when `instance` already holds it; true while
+    /// `instance` is the holder.
+    fn acquire(&self, lease: &str, instance: &str, ttl: Duration) -> Result<bool>;
+    /// Give `lease` up if `instance` still holds it.
+    fn release(&self, lease: &str, instance: &str) -> Result<()>;
+    fn holder(&self, lease: &str) -> Result<Option<String>>;
+    /// Record `instance` as alive for `ttl`.
+    fn heartbeat(&self, instance: &str, ttl: Duration) -> Result<()>;
+    /// Instances whose heartbeat has not run out, sorted.
+    fn members(&self) -> Result<Vec<String>>;
+    /// Send `message` to every subscriber, this instance's included.
+    fn publish(&self, message: &str) -> Result<()>;
+    /// Hand every published message to `deliver` until it returns false.
+    fn subscribe(&self, deliver: &mut dyn FnMut(String) -> bool) -> Result<()>;
+}
+
+/// `GET /cluster` response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ClusterStatus {
+    pub instance: String,
+    pub clustered: bool,
+    /// Instances with a live heartbeat; just this one when standalone.
+    pub members: Vec<String>,
+    /// Holder of each singleton task's lease (`None` while nobody holds it).
+    pub leases: BTreeMap<String, Option<String>>,
+}
+
+#[derive(Clone)]
+pub struct Cluster {
+    instance: String,
+    coordinator: Option<Arc<dyn Coordinator>>,
+    ttl: Duration,
+    /// Singleton tasks started on this instance, leading or not.
+    tasks: Arc<RwLock<BTreeSet<String>>>,
+}
+
+impl Cluster {
+    pub fn from_config(config: &ClusterConfig) -> Result<Self> {
+        let instance = config
+            .instance_id
+            .clone()
+            .unwrap_or_else(default_instance_id);
+        let coordinator = match &config.redis_url {
+            Some(url) => Some(RedisCoordinator::new(url, config.prefix.clone())? as _),
+            None => None,
+        };
+        Ok(Self::new(
+            instance,
+            coordinator,
+            Duration::from_secs(config.lease_ttl_secs.max(1)),
+        ))
+    }
+
+    pub fn new(
+        instance: impl Into<String>,
+        coordinator: Option<Arc<dyn Coordinator>>,
+        ttl: Duration,
+    ) -> Self {
+        Self {
+            instance: instance.into(),
+            coordinator,
+            ttl,
+            tasks: Arc::default(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn standalone(instance: impl Into<String>) -> Self {
+        Self::new(
+            instance,
+            None,
+            Duration::from_secs(default_lease_ttl_secs()),
+        )
+    }
+
+    pub fn instance_id(&self) -> &str {
+        &self.instance
+    }
+
+    pub fn is_clustered(&self) -> bool {
+        self.coordinator.is_some()
+    }
+
+    fn renew_every(&self) -> Duration {
+        self.ttl / 3
+    }
+
+    /// Run `start()` only while this instance holds the `name` lease; standalone it just runs.
+    /// A standby instance retries for the lease every third of its TTL. When a renewal fails
+    /// the task is dropped mid-flight, since another instance may already have taken over, and
+    /// this instance goes back to standing by. The lease is released when the task returns.
+    pub async fn leader_only<F, Fut>(self, name: &str, start: F) -> Result<()>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        let Some(coordinator) = self.coordinator.clone() else {
+            return start().await;
+        };
+        self.tasks.write().insert(name.to_string());
+        loop {
+            if !self.try_acquire(&coordinator, name).await {
+                tokio::time::sleep(self.renew_every()).await;
+                continue;
+            }
+            info!(task = name, instance = %self.instance, "took the task lease; running it here");
+            let outcome = {
+                let task = start();
+                tokio::pin!(task);
+                loop {
+                    tokio::select! {
+                        result = &mut task => break Some(result),
+                        _ = tokio::time::sleep(self.renew_every()) => {
+                            if !self.try_acquire(&coordinator, name).await {
+                                break None;
+                            }
+                        }
+                    }
+                }
+            };
+            match outcome {
+                Some(result) => {
+                    let (coordinator, lease, instance) =
+                        (coordinator.clone(), name.to_string(), self.instance.clone());
+                    let released =
+                        tokio::task::spawn_blocking(move || coordinator.release(&lease, &instance))
+                            .await?;
+                    if let Err(err) = released {
+                        warn!(?err, task = name, "failed to release the task lease");
+                    }
+                    return result;
+                }
+                None => {
+                    warn!(task = name, instance = %self.instance, "lost the task lease; standing by")
+                }
+            }
+        }
+    }
+
+    /// Whether this instance holds (or just took) the `name` lease; a failed request counts
+    /// as not holding it.
+    async fn try_acquire(&self, coordinator: &Arc<dyn Coordinator>, name: &str) -> bool {
+        let (coordinator, lease, instance, ttl) = (
+            coordinator.clone(),
+            name.to_string(),
+            self.instance.clone(),
+            self.ttl,
+        );
+        match tokio::task::spawn_blocking(move || coordinator.acquire(&lease, &instance, ttl)).await
+        {
+            Ok(Ok(held)) => held,
+            Ok(Err(err)) => {
+                warn!(?err, task = name, "task lease request failed");
+                false
+            }
+            Err(err) => {
+                warn!(?err, task = name, "task lease request panicked");
+                false
+            }
+        }
+    }
+
+    /// Keep this instance's membership alive; returns at once when standalone.
+    pub async fn heartbeat(self) -> Result<()> {
+        let Some(coordinator) = self.coordinator.clone() else {
+            return Ok(());
+        };
+        let mut ticker = tokio::time::interval(self.renew_every());
+        loop {
+            ticker.tick().await;
+            let (coordinator, instance, ttl) =
+                (coordinator.clone(), self.instance.clone(), self.ttl);
+            tokio::task::spawn_blocking(move || coordinator.heartbeat(&instance, ttl)).await??;
+        }
+    }
+
+    /// Broadcast `message` to every instance; a no-op when standalone.
+    pub fn publish(&self, message: &str) -> Result<()> {
+        match &self.coordinator {
+            Some(coordinator) => coordinator.publish(message),
+            None => Ok(()),
+        }
+    }
+
+    /// Block handing every broadcast message to `deliver`; returns at once when standalone.
+    pub fn subscribe(&self, deliver: &mut dyn FnMut(String) -> bool) -> Result<()> {
+        match &self.coordinator {
+            Some(coordinator) => coordinator.subscribe(deliver),
+            None => Ok(()),
+        }
+    }
+
+    pub fn status(&self) -> Result<ClusterStatus> {
+        let Some(coordinator) = &self.coordinator else {
+            return Ok(ClusterStatus {
+                instance: self.instance.clone(),
+                clustered: false,
+                members: vec![self.instance.clone()],
+                leases: BTreeMap::new(),
+            });
+        };
+        let tasks = self.tasks.read().clone();
+        Ok(ClusterStatus {
+            instance: self.instance.clone(),
+            clustered: true,
+            members: coordinator.members()?,
+            leases: tasks
+                .into_iter()
+                .map(|task| Ok((task.clone(), coordinator.holder(&task)?)))
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+fn default_instance_id() -> String {
+    let host = std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|host| host.trim().to_string())
+        .filter(|host| !host.is_empty())
+        .unwrap_or_else(|| "bridge".to_string());
+    format!("{host}-{}", std::process::id())
+}
+
+/// Leases as `<prefix>:lease:<task>` keys holding the instance id with a PX expiry,
+/// heartbeats as `<prefix>:member:<instance>` keys, and `<prefix>:events` as the channel.
+pub struct RedisCoordinator {
+    client: redis::Client,
+    prefix: String,
+}
+
+impl RedisCoordinator {
+    pub fn new(url: &str, prefix: Option<String>) -> Result<Arc<Self>> {
+        let client = redis::Client::open(url.to_string())
+            .with_context(|| format!("failed to create redis client for {url}"))?;
+        Ok(Arc::new(Self {
+            client,
+            prefix: prefix.unwrap_or_else(|| "greentic:cluster".to_string()),
+        }))
+    }
+
+    fn lease_key(&self, lease: &str) -> String {
+        format!("{}:lease:{lease}", self.prefix)
+    }
+
+    fn channel(&self) -> String {
+        format!("{}:events", self.prefix)
+    }
+
+    fn with_conn<T>(&self, f: impl FnOnce(&mut redis::Connection) -> Result<T>) -> Result<T> {
+        let mut conn = self.client.get_connection().with_context(|| {
+            format!(
+                "failed to connect to redis at {:?}",
+                self.client.get_connection_info()
+            )
+        })?;
+        f(&mut conn)
+    }
+}
+
+const ACQUIRE_LEASE_SCRIPT: &str = r#"
+local holder = redis.call("GET", KEYS[1])
+if holder == false or holder == ARGV[1] then
+    redis.call("SET", KEYS[1], ARGV[1], "PX", ARGV[2])
+    return 1
+end
+return 0
+"#;
+
+const RELEASE_LEASE_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("DEL", KEYS[1])
+end
+return 0
+"#;
+
+impl Coordinator for RedisCoordinator {
+    fn acquire(&self, lease: &str, instance: &str, ttl: Duration) -> Result<bool> {
+        let key = self.lease_key(lease);
+        self.with_conn(|conn| {
+            let held: i64 = redis::Script::new(ACQUIRE_LEASE_SCRIPT)
+                .key(&key)
+                .arg(instance)
+                .arg(ttl.as_millis().max(1) as u64)
+                .invoke(conn)
+                .with_context(|| format!("failed to acquire {key}"))?;
+            Ok(held == 1)
+        })
+    }
+
+    fn release(&self, lease: &str, instance: &str) -> Result<()> {
+        let key = self.lease_key(lease);
+        self.with_conn(|conn| {
+            redis::Script::new(RELEASE_LEASE_SCRIPT)
+                .key(&key)
+                .arg(instance)
+                .invoke::<i64>(conn)
+                .with_context(|| format!("failed to release {key}"))?;
+            Ok(())
+        })
+    }
+
+    fn holder(&self, lease: &str) -> Result<Option<String>> {
+        let key = self.lease_key(lease);
+        self.with_conn(|conn| {
+            redis::cmd("GET")
+                .arg(&key)
+                .query(conn)
+                .with_context(|| format!("failed to read {key}"))
+        })
+    }
+
+    fn heartbeat(&self, instance: &str, ttl: Duration) -> Result<()> {
+        let key = format!("{}:member:{instance}", self.prefix);
+        self.with_conn(|conn| {
+            redis::cmd("SET")
+                .arg(&key)
+                .arg(instance)
+                .arg("PX")
+                .arg(ttl.as_millis().max(1) as u64)
+                .query::<()>(conn)
+                .with_context(|| format!("failed to write {key}"))
+        })
+    }
+
+    fn members(&self) -> Result<Vec<String>> {
+        let pattern = format!("{}:member:*", self.prefix);
+        let strip = format!("{}:member:", self.prefix);
+        self.with_conn(|conn| {
+            let keys: Vec<String> = redis::cmd("KEYS")
+                .arg(&pattern)
+                .query(conn)
+                .with_context(|| format!("failed to list {pattern}"))?;
+            let mut members: Vec<String> = keys
+                .iter()
+                .filter_map(|key| key.strip_prefix(&strip).map(str::to_string))
+                .collect();
+            members.sort();
+            Ok(members)
+        })
+    }
+
+    fn publish(&self, message: &str) -> Result<()> {
+        let channel = self.channel();
+        self.with_conn(|conn| {
+            redis::cmd("PUBLISH")
+                .arg(&channel)
+                .arg(message)
+                .query::<i64>(conn)
+                .with_context(|| format!("failed to publish on {channel}"))?;
+            Ok(())
+        })
+    }
+
+    fn subscribe(&self, deliver: &mut dyn FnMut(String) -> bool) -> Result<()> {
+        let channel = self.channel();
+        self.with_conn(|conn| {
+            let mut pubsub = conn.as_pubsub();
+            pubsub
+                .subscribe(&channel)
+                .with_context(|| format!("failed to subscribe to {channel}"))?;
+            loop {
+                let message = pubsub
+                    .get_message()
+                    .with_context(|| format!("lost the subscription to {channel}"))?;
+                let payload: String = message.get_payload()?;
+                if !deliver(payload) {
+                    return Ok(());
+                }
+            }
+        })
+    }
+}
+
+/// In-process coordination for tests, standing in for Redis between several [`Cluster`]s.
+#[cfg(test)]
+pub mod memory {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, mpsc},
+        time::{Duration, Instant},
+    };
+
+    use anyhow::Result;
+    use parking_lot::Mutex;
+
+    use super::Coordinator;
+
+    #[derive(Default)]
+    pub struct MemoryCoordinator {
+        leases: Mutex<HashMap<String, (String, Instant)>>,
+        members: Mutex<HashMap<String, Instant>>,
+        subscribers: Mutex<Vec<mpsc::Sender<String>>>,
+    }
+
+    impl MemoryCoordinator {
+        pub fn new() -> Arc<Self> {
+            Arc::new(Self::default())
+        }
+
+        pub fn subscribers(&self) -> usize {
+            self.subscribers.lock().len()
+        }
+
+        /// End every `subscribe` call in progress.
+        pub fn close(&self) {
+            self.subscribers.lock().clear();
+        }
+    }
+
+    impl Coordinator for MemoryCoordinator {
+        fn acquire(&self, lease: &str, instance: &str, ttl: Duration) -> Result<bool> {
+            let mut leases = self.leases.lock();
+            let now = Instant::now();
+            match leases.get(lease) {
+                Some((holder, expires)) if holder != instance && *expires > now => Ok(false),
+                _ => {
+                    leases.insert(lease.to_string(), (instance.to_string(), now + ttl));
+                    Ok(true)
+                }
+            }
+        }
+
+        fn release(&self, lease: &str, instance: &str) -> Result<()> {
+            let mut leases = self.leases.lock();
+            if leases
+                .get(lease)
+                .is_some_and(|(holder, _)| holder == instance)
+            {
+                leases.remove(lease);
+            }
+            Ok(())
+        }
+
+        fn holder(&self, lease: &str) -> Result<Option<String>> {
+            Ok(self
+                .leases
+                .lock()
+                .get(lease)
+                .filter(|(_, expires)| *expires > Instant::now())
+                .map(|(holder, _)| holder.clone()))
+        }
+
+        fn heartbeat(&self, instance: &str, ttl: Duration) -> Result<()> {
+            self.members
+                .lock()
+                .insert(instance.to_string(), Instant::now() + ttl);
+            Ok(())
+        }
+
+        fn members(&self) -> Result<Vec<String>> {
+            let now = Instant::now();
+            let mut members: Vec<String> = self
+                .members
+                .lock()
+                .iter()
+                .filter(|(_, expires)| **expires > now)
+                .map(|(instance, _)| instance.clone())
+                .collect();
+            members.sort();
+            Ok(members)
+        }
+
+        fn publish(&self, message: &str) -> Result<()> {
+            self.subscribers
+                .lock()
+                .retain(|subscriber| subscriber.send(message.to_string()).is_ok());
+            Ok(())
+        }
+
+        fn subscribe(&self, deliver: &mut dyn FnMut(String) -> bool) -> Result<()> {
+            let (tx, rx) = mpsc::channel();
+            self.subscribers.lock().push(tx);
+            for message in rx {
+                if !deliver(message) {
+                    break;
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    };
+
+    use super::memory::MemoryCoordinator;
+    use super::*;
+
+    fn instance(name: &str, coordinator: &Arc<MemoryCoordinator>) -> Cluster {
+        Cluster::new(
+            name,
+            Some(coordinator.clone() as Arc<dyn Coordinator>),
+            Duration::from_millis(150),
+        )
+    }
+
+    #[tokio::test]
+    async fn one_instance_runs_a_singleton_task_and_another_takes_over() {
+        let coordinator = MemoryCoordinator::new();
+        let (a, b) = (instance("a", &coordinator), instance("b", &coordinator));
+        let starts = Arc::new(AtomicUsize::new(0));
+        let run = |cluster: Cluster| {
+            let starts = starts.clone();
+            tokio::spawn(cluster.leader_only("reaper", move || {
+                starts.fetch_add(1, Ordering::SeqCst);
+                std::future::pending()
+            }))
+        };
+        let first = run(a.clone());
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let second = run(b.clone());
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(starts.load(Ordering::SeqCst), 1);
+        let status = b.status().unwrap();
+        assert_eq!(status.leases["reaper"].as_deref(), Some("a"));
+
+        // `a` dies without releasing; `b` takes over once the lease runs out.
+        first.abort();
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert_eq!(starts.load(Ordering::SeqCst), 2);
+        assert_eq!(b.status().unwrap().leases["reaper"].as_deref(), Some("b"));
+        second.abort();
+    }
+
+    #[tokio::test]
+    async fn standalone_runs_tasks_directly_and_reports_itself() {
+        let cluster = Cluster::standalone("solo");
+        cluster
+            .clone()
+            .leader_only("reaper", || async { Ok(()) })
+            .await
+            .unwrap();
+        let status = cluster.status().unwrap();
+        assert!(!status.clustered);
+        assert_eq!(status.members, ["solo"]);
+        assert!(status.leases.is_empty());
+    }
+
+    #[test]
+    fn heartbeats_and_broadcasts_reach_every_instance() {
+        let coordinator = MemoryCoordinator::new();
+        let (a, b) = (instance("a", &coordinator), instance("b", &coordinator));
+        coordinator.heartbeat("b", Duration::from_secs(5)).unwrap();
+        coordinator.heartbeat("a", Duration::from_secs(5)).unwrap();
+        assert_eq!(a.status().unwrap().members, ["a", "b"]);
+
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            b.subscribe(&mut |message| {
+                tx.send(message).unwrap();
+                false
+            })
+        });
+        let received = loop {
+            a.publish("hello").unwrap();
+            if let Ok(message) = rx.recv_timeout(Duration::from_millis(20)) {
+                break message;
+            }
+        };
+        assert_eq!(received, "hello");
+    }
+
+    #[test]
+    fn redis_leases_exclude_other_instances() {
+        let url = match std::env::var("REDIS_URL") {
+            Ok(url) => url,
+            Err(_) => {
+                eprintln!("skipping redis_leases_exclude_other_instances: REDIS_URL not set");
+                return;
+            }
+        };
+        let prefix = format!("greentic:test:{}", uuid::Uuid::new_v4());
+        let coordinator = RedisCoordinator::new(&url, Some(prefix)).unwrap();
+        let ttl = Duration::from_secs(5);
+        assert!(coordinator.acquire("reaper", "a", ttl).unwrap());
+        assert!(coordinator.acquire("reaper", "a", ttl).unwrap());
+        assert!(!coordinator.acquire("reaper", "b", ttl).unwrap());
+        assert_eq!(coordinator.holder("reaper").unwrap().as_deref(), Some("a"));
+        coordinator.release("reaper", "b").unwrap();
+        assert_eq!(coordinator.holder("reaper").unwrap().as_deref(), Some("a"));
+        coordinator.release("reaper", "a").unwrap();
+        assert!(coordinator.acquire("reaper", "b", ttl).unwrap());
+        coordinator.release("reaper", "b").unwrap();
+
+        coordinator.heartbeat("a", ttl).unwrap();
+        assert_eq!(coordinator.members().unwrap(), ["a"]);
+    }
+}
@@ -0,0 +1,419 @@
+//! Wasmtime host for component artifacts shipped inside packs (feature `components`).
+//!
+//! Components are driven through `greentic:component/node@0.5.0#invoke` using dynamic
+//! component-model values, with a minimal set of host imports wired in: cooperative control,
+//! the deploy-plan API (`get-deployment-plan`/`emit-status`), and state get/set backed by the
+//! configured state store. Any other import (such as the WASI interfaces a `wasm32-wasip2`
+//! build links) traps when called, so those components still load as long as the operation
+//! invoked stays within the wired-in set.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{info, warn};
+use wasmtime::{
+    Engine, Store, StoreContextMut,
+    component::{Component, Linker, Val},
+};
+
+use crate::pack_sandbox::PackSandbox;
+use crate::path_safety::normalize_under_root;
+use crate::state_store::StateStore;
+
+const CONTROL_INTERFACE: &str = "greentic:component/control@0.5.0";
+const NODE_INTERFACE: &str = "greentic:component/node@0.5.0";
+const STATE_INTERFACE: &str = "greentic:state/state-store@1.0.0";
+const KV_INTERFACE: &str = "greentic:host/kv-v1@1.0.0";
+const PLAN_INTERFACE: &str = "greentic:deploy-plan/plan-api@1.0.0";
+
+/// Identity and position of the flow node invoking a component.
+#[derive(Debug, Clone, Default)]
+pub struct InvokeContext {
+    /// Pack that shipped the component, used to resolve its deployment plan.
+    pub pack_id: Option<String>,
+    pub tenant: String,
+    pub team: Option<String>,
+    pub user: Option<String>,
+    pub flow_id: String,
+    pub node_id: String,
+    /// Sandbox of the run; its state namespace replaces the configured store's.
+    pub sandbox: Option<Arc<PackSandbox>>,
+}
+
+/// Result of one `invoke` call plus everything the component reported through `emit-status`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Invocation {
+    pub output: Value,
+    pub statuses: Vec<String>,
+}
+
+/// App-side counterpart of the guest `PlanRuntime`: serves `greentic:deploy-plan/plan-api`.
+pub trait PlanHost: Send + Sync {
+    /// DeploymentPlan JSON for the invoking pack/tenant.
+    fn deployment_plan(&self, ctx: &InvokeContext) -> Result<String>;
+    fn emit_status(&self, ctx: &InvokeContext, message: &str);
+}
+
+#[derive(Debug, Deserialize)]
+struct ManifestComponents {
+    #[serde(default)]
+    components: Vec<ComponentRef>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ComponentRef {
+    id: String,
+    path: PathBuf,
+}
+
+/// Component id -> wasm path for every component declared by `pack_dir/pack.json`.
+pub fn pack_components(pack_dir: &Path) -> Result<BTreeMap<String, PathBuf>> {
+    let manifest_path = pack_dir.join("pack.json");
+    let raw = fs::read(&manifest_path)
+        .with_context(|| format!("failed to read {}", manifest_path.display()))?;
+    let manifest: ManifestComponents = serde_json::from_slice(&raw)
+        .with_context(|| format!("invalid pack manifest {}", manifest_path.display()))?;
+    manifest
+        .components
+        .into_iter()
+        .map(|component| {
+            let path = normalize_under_root(pack_dir, &component.path)?;
+            Ok((component.id, path))
+        })
+        .collect()
+}
+
+struct HostState {
+    state: Arc<dyn StateStore>,
+    plans: Option<Arc<dyn PlanHost>>,
+    ctx: InvokeContext,
+    statuses: Vec<String>,
+}
+
+impl HostState {
+    /// State is namespaced by the invoking tenant.
+    fn namespace(&self) -> &str {
+        &self.ctx.tenant
+    }
+
+    fn deployment_plan(&self) -> Result<String> {
+        let plans = self
+            .plans
+            .as_ref()
+            .ok_or_else(|| anyhow!("no deployment plan host is configured"))?;
+        plans.deployment_plan(&self.ctx)
+    }
+
+    fn emit_status(&mut self, message: String) {
+        info!(node = %self.ctx.node_id, %message, "component status");
+        if let Some(plans) = &self.plans {
+            plans.emit_status(&self.ctx, &message);
+        }
+        self.statuses.push(message);
+    }
+
+    /// `kv-v1` namespaces are scoped under the invoking tenant.
+    fn kv_get(&self, ns: &str, key: &str) -> Result<Option<String>> {
+        let value = self.state.get(&format!("{}/{ns}", self.namespace()), key)?;
+        Ok(value.map(|bytes| String::from_utf8_lossy(&bytes).into_owned()))
+    }
+
+    fn kv_put(&self, ns: &str, key: &str, value: String) -> Result<()> {
+        self.state.set(
+            &format!("{}/{ns}", self.namespace()),
+            key,
+            value.into_bytes(),
+        )
+    }
+
+    fn state_read(&self, params: &[Val]) -> Result<Val> {
+        let key = string_param(params)?;
+        Ok(match self.state.get(self.namespace(), &key) {
+            Ok(Some(bytes)) => Val::Result(Ok(Some(Box::new(Val::List(
+                bytes.into_iter().map(Val::U8).collect(),
+            ))))),
+            Ok(None) => host_error("not_found", format!("no state for key {key}")),
+            Err(err) => host_error("internal", err.to_string()),
+        })
+    }
+
+    fn state_write(&self, params: &[Val]) -> Result<Val> {
+        let key = string_param(params)?;
+        let bytes = match params.get(1) {
+            Some(Val::List(items)) => items
+                .iter()
+                .map(|item| match item {
+                    Val::U8(byte) => Ok(*byte),
+                    other => Err(anyhow!("expected u8 in state bytes, got {other:?}")),
+                })
+                .collect::<Result<Vec<u8>>>()?,
+            other => bail!("expected byte list for state write, got {other:?}"),
+        };
+        Ok(ack(self.state.set(self.namespace(), &key, bytes)))
+    }
+
+    fn state_delete(&self, params: &[Val]) -> Result<Val> {
+        let key = string_param(params)?;
+        Ok(ack(self.state.delete(self.namespace(), &key)))
+    }
+}
+
+pub struct ComponentHost {
+    engine: Engine,
+    compiled: Mutex<HashMap<PathBuf, Component>>,
+    state: Arc<dyn StateStore>,
+    plans: Option<Arc<dyn PlanHost>>,
+}
+
+impl ComponentHost {
+    pub fn new(state: Arc<dyn StateStore>) -> Self {
+        Self {
+            engine: Engine::default(),
+            compiled: Mutex::new(HashMap::new()),
+            state,
+            plans: None,
+        }
+    }
+
+    /// Serve `get-deployment-plan` and mirror `emit-status` through `plans`.
+    pub fn with_plan_host(mut self, plans: Arc<dyn PlanHost>) -> Self {
+        self.plans = Some(plans);
+        self
+    }
+
+    pub fn invoke(
+        &self,
+        wasm: &Path,
+        ctx: &InvokeContext,
+        op: &str,
+        input: &Value,
+    ) -> Result<Invocation> {
+        let component = self.component(wasm)?;
+        let mut linker = self.linker()?;
+        linker.define_unknown_imports_as_traps(&component)?;
+        let mut store = Store::new(
+            &self.engine,
+            HostState {
+                state: match &ctx.sandbox {
+                    Some(sandbox) => sandbox.clone(),
+                    None => self.state.clone(),
+                },
+                plans: self.plans.clone(),
+                ctx: ctx.clone(),
+                statuses: Vec::new(),
+            },
+        );
+        let instance = linker
+            .instantiate(&mut store, &component)
+            .map_err(|err| anyhow!("failed to instantiate {}: {err}", wasm.display()))?;
+        let node = instance
+            .get_export_index(&mut store, None, NODE_INTERFACE)
+            .ok_or_else(|| anyhow!("{} does not export {NODE_INTERFACE}", wasm.display()))?;
+        let invoke = instance
+            .get_export_index(&mut store, Some(&node), "invoke")
+            .and_then(|index| instance.get_func(&mut store, index))
+            .ok_or_else(|| anyhow!("{} does not export {NODE_INTERFACE}#invoke", wasm.display()))?;
+
+        let params = [
+            exec_ctx(ctx),
+            Val::String(op.to_string()),
+            Val::String(serde_json::to_string(input)?),
+        ];
+        let mut results = [Val::Bool(false)];
+        invoke
+            .call(&mut store, &params, &mut results)
+            .map_err(|err| anyhow!("component {} trapped: {err}", wasm.display()))?;
+
+        let output = match &results[0] {
+            Val::Variant(case, Some(payload)) if case == "ok" => match payload.as_ref() {
+                Val::String(json) => serde_json::from_str(json).with_context(|| {
+                    format!("component {} returned invalid JSON", wasm.display())
+                })?,
+                other => bail!("unexpected invoke payload {other:?}"),
+            },
+            Val::Variant(case, Some(error)) if case == "err" => {
+                bail!("component {} failed: {}", wasm.display(), node_error(error))
+            }
+            other => bail!("unexpected invoke result {other:?}"),
+        };
+        Ok(Invocation {
+            output,
+            statuses: std::mem::take(&mut store.data_mut().statuses),
+        })
+    }
+
+    fn component(&self, wasm: &Path) -> Result<Component> {
+        if let Some(component) = self.compiled.lock().get(wasm) {
+            return Ok(component.clone());
+        }
+        let component = Component::from_file(&self.engine, wasm)
+            .map_err(|err| anyhow!("{} is not a loadable wasm component: {err}", wasm.display()))?;
+        self.compiled
+            .lock()
+            .insert(wasm.to_path_buf(), component.clone());
+        Ok(component)
+    }
+
+    fn linker(&self) -> Result<Linker<HostState>> {
+        let mut linker = Linker::<HostState>::new(&self.engine);
+        {
+            let mut control = linker.instance(CONTROL_INTERFACE)?;
+            control.func_wrap("should-cancel", |_store, (): ()| Ok((false,)))?;
+            control.func_wrap("yield-now", |_store, (): ()| Ok(()))?;
+        }
+        {
+            let mut plan = linker.instance(PLAN_INTERFACE)?;
+            plan.func_wrap(
+                "get-deployment-plan",
+                |store: StoreContextMut<'_, HostState>, (): ()| {
+                    let plan = store.data().deployment_plan().map_err(host_trap)?;
+                    Ok((plan,))
+                },
+            )?;
+            plan.func_wrap(
+                "emit-status",
+                |mut store: StoreContextMut<'_, HostState>, (message,): (String,)| {
+                    store.data_mut().emit_status(message);
+                    Ok(())
+                },
+            )?;
+        }
+        {
+            let mut kv = linker.instance(KV_INTERFACE)?;
+            kv.func_wrap(
+                "get",
+                |store: StoreContextMut<'_, HostState>, (ns, key): (String, String)| {
+                    let value = store.data().kv_get(&ns, &key).map_err(host_trap)?;
+                    Ok((value,))
+                },
+            )?;
+            kv.func_wrap(
+                "put",
+                |store: StoreContextMut<'_, HostState>,
+                 (ns, key, value): (String, String, String)| {
+                    store.data().kv_put(&ns, &key, value).map_err(host_trap)
+                },
+            )?;
+        }
+        {
+            let mut state = linker.instance(STATE_INTERFACE)?;
+            state.func_new("read", |store, _ty, params, results| {
+                results[0] = store.data().state_read(params).map_err(host_trap)?;
+                Ok(())
+            })?;
+            state.func_new("write", |store, _ty, params, results| {
+                results[0] = store.data().state_write(params).map_err(host_trap)?;
+                Ok(())
+            })?;
+            state.func_new("delete", |store, _ty, params, results| {
+                results[0] = store.data().state_delete(params).map_err(host_trap)?;
+                Ok(())
+            })?;
+        }
+        Ok(linker)
+    }
+}
+
+/// Surface a host-side failure to wasmtime as a trap.
+fn host_trap(err: anyhow::Error) -> wasmtime::Error {
+    wasmtime::Error::from_anyhow(err)
+}
+
+fn string_param(params: &[Val]) -> Result<String> {
+    match params.first() {
+        Some(Val::String(value)) => Ok(value.clone()),
+        other => bail!("expected string state key, got {other:?}"),
+    }
+}
+
+fn host_error(code: &str, message: String) -> Val {
+    if code != "not_found" {
+        warn!(%code, %message, "state host binding failed");
+    }
+    Val::Result(Err(Some(Box::new(Val::Record(vec![
+        ("code".into(), Val::String(code.into())),
+        ("message".into(), Val::String(message)),
+    ])))))
+}
+
+fn ack(result: Result<()>) -> Val {
+    match result {
+        Ok(()) => Val::Result(Ok(Some(Box::new(Val::Enum("ok".into()))))),
+        Err(err) => host_error("internal", err.to_string()),
+    }
+}
+
+fn some_string(value: &Option<String>) -> Val {
+    Val::Option(value.clone().map(|v| Box::new(Val::String(v))))
+}
+
+fn exec_ctx(ctx: &InvokeContext) -> Val {
+    let tenant = Val::Record(vec![
+        ("tenant".into(), Val::String(ctx.tenant.clone())),
+        ("team".into(), some_string(&ctx.team)),
+        ("user".into(), some_string(&ctx.user)),
+        ("trace-id".into(), Val::Option(None)),
+        ("correlation-id".into(), Val::Option(None)),
+        ("deadline-unix-ms".into(), Val::Option(None)),
+        ("attempt".into(), Val::U32(0)),
+        ("idempotency-key".into(), Val::Option(None)),
+    ]);
+    Val::Record(vec![
+        ("tenant".into(), tenant),
+        ("flow-id".into(), Val::String(ctx.flow_id.clone())),
+        ("node-id".into(), some_string(&Some(ctx.node_id.clone()))),
+    ])
+}
+
+fn node_error(error: &Val) -> String {
+    let Val::Record(fields) = error else {
+        return format!("{error:?}");
+    };
+    let field = |name: &str| {
+        fields.iter().find_map(|(key, value)| match value {
+            Val::String(text) if key == name => Some(text.as_str()),
+            _ => None,
+        })
+    };
+    format!(
+        "{}: {}",
+        field("code").unwrap_or("unknown"),
+        field("message").unwrap_or_default()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state_store::InMemoryStateStore;
+
+    #[test]
+    fn placeholder_artifacts_are_rejected_with_context() {
+        let tmp = tempfile::tempdir().unwrap();
+        let wasm = tmp.path().join("placeholder.wasm");
+        fs::write(&wasm, "not a real module").unwrap();
+        let host = ComponentHost::new(InMemoryStateStore::new());
+        let err = host
+            .invoke(&wasm, &InvokeContext::default(), "invoke", &Value::Null)
+            .unwrap_err();
+        assert!(err.to_string().contains("not a loadable wasm component"));
+    }
+
+    #[test]
+    fn lists_components_declared_by_pack() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../packs/integration-demos");
+        let components = pack_components(&root).unwrap();
+        assert!(
+            components["demo.worker.repo_assistant"]
+                .ends_with("components/demo_worker_repo_assistant.wasm")
+        );
+    }
+}
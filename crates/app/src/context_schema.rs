@@ -0,0 +1,122 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::{Context, Result, anyhow};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::path_safety::normalize_under_root;
+
+/// A single schema failure located by JSON pointer into the session context.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ContextViolation {
+    pub pointer: String,
+    pub message: String,
+}
+
+/// Load the `context_schemas` map of a pack manifest. Values are either inline schemas or
+/// paths (relative to the pack directory) to JSON schema files.
+pub fn load_context_schemas(pack_dir: &Path, manifest: &Value) -> Result<BTreeMap<String, Value>> {
+    load_schema_map(pack_dir, manifest, "context_schemas")
+}
+
+/// Load a manifest `field` mapping keys (flow ids, event types) to inline schemas or schema
+/// file paths relative to the pack directory. Every schema must compile.
+pub fn load_schema_map(
+    pack_dir: &Path,
+    manifest: &Value,
+    field: &str,
+) -> Result<BTreeMap<String, Value>> {
+    let Some(declared) = manifest.get(field) else {
+        return Ok(BTreeMap::new());
+    };
+    let declared = declared
+        .as_object()
+        .ok_or_else(|| anyhow!("{field} in {} must be an object", pack_dir.display()))?;
+
+    let mut schemas = BTreeMap::new();
+    for (key, entry) in declared {
+        let schema = match entry {
+            Value::String(relative) => {
+                let path = normalize_under_root(pack_dir, Path::new(relative))?;
+                let raw = fs::read(&path)
+                    .with_context(|| format!("failed to read schema {}", path.display()))?;
+                serde_json::from_slice(&raw)
+                    .with_context(|| format!("invalid JSON in schema {}", path.display()))?
+            }
+            Value::Object(_) | Value::Bool(_) => entry.clone(),
+            other => {
+                return Err(anyhow!(
+                    "{field} entry {key} must be an object or path, got {other}"
+                ));
+            }
+        };
+        jsonschema::validator_for(&schema)
+            .map_err(|err| anyhow!("invalid schema for {key} in {field}: {err}"))?;
+        schemas.insert(key.clone(), schema);
+    }
+    Ok(schemas)
+}
+
+/// Validate `context` against `schema`, returning every violation (empty when valid).
+pub fn validate_context(schema: &Value, context: &Value) -> Result<Vec<ContextViolation>> {
+    let validator = jsonschema::validator_for(schema)
+        .map_err(|err| anyhow!("invalid context schema: {err}"))?;
+    Ok(validator
+        .iter_errors(context)
+        .map(|err| ContextViolation {
+            pointer: err.instance_path().as_str().to_string(),
+            message: err.to_string(),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reports_pointer_level_violations() {
+        let schema = json!({
+            "type": "object",
+            "required": ["ticket"],
+            "properties": {
+                "ticket": {
+                    "type": "object",
+                    "properties": { "id": { "type": "integer" } }
+                }
+            }
+        });
+        assert!(
+            validate_context(&schema, &json!({"ticket": {"id": 7}}))
+                .unwrap()
+                .is_empty()
+        );
+
+        let violations = validate_context(&schema, &json!({"ticket": {"id": "seven"}})).unwrap();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].pointer, "/ticket/id");
+    }
+
+    #[test]
+    fn loads_inline_and_file_schemas() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(
+            tmp.path().join("wait.schema.json"),
+            r#"{"type": "object", "required": ["waiting"]}"#,
+        )
+        .unwrap();
+        let manifest = json!({
+            "context_schemas": {
+                "flow_inline": {"type": "object"},
+                "flow_file": "wait.schema.json"
+            }
+        });
+        let schemas = load_context_schemas(tmp.path(), &manifest).unwrap();
+        assert_eq!(schemas.len(), 2);
+        assert_eq!(schemas["flow_file"]["required"], json!(["waiting"]));
+
+        let escaping = json!({"context_schemas": {"flow": "../outside.json"}});
+        assert!(load_context_schemas(tmp.path(), &escaping).is_err());
+    }
+}
@@ -0,0 +1,220 @@
+//! Spreadsheet-friendly CSV of sessions and runner events (`?format=csv`, `--format csv`):
+//! one flattened row per item, quoted per RFC 4180 with CRLF line ends. Timestamps appear both
+//! as epoch milliseconds and as RFC 3339 UTC, which Excel and Sheets parse as dates. The body
+//! starts with a UTF-8 byte order mark so Excel does not read it as a legacy code page, and a
+//! cell a spreadsheet would evaluate as a formula is prefixed with `'` so it stays text.
+
+use serde_json::Value;
+
+use crate::{RunnerEvent, SessionView};
+
+pub const CONTENT_TYPE: &str = "text/csv; charset=utf-8";
+
+pub const BOM: &str = "\u{feff}";
+
+const SESSION_COLUMNS: &[&str] = &[
+    "key",
+    "tenant",
+    "team",
+    "user",
+    "flow",
+    "node",
+    "pack_id",
+    "flow_version",
+    "needs_upgrade",
+    "locale",
+    "updated_at_epoch_ms",
+    "updated_at",
+    "ttl_ms",
+];
+
+const RUNNER_EVENT_COLUMNS: &[&str] = &[
+    "timestamp_ms",
+    "timestamp",
+    "sequence",
+    "instance",
+    "tenant",
+    "team",
+    "user",
+    "flow",
+    "node",
+    "status",
+    "payload",
+    "result",
+];
+
+pub fn sessions(sessions: &[SessionView]) -> String {
+    render(
+        SESSION_COLUMNS,
+        sessions.iter().map(|session| {
+            vec![
+                session.key.clone(),
+                session.tenant.clone(),
+                optional(&session.team),
+                optional(&session.user),
+                optional(&session.cursor.flow_id),
+                optional(&session.cursor.node_id),
+                optional(&session.pack_id),
+                optional(&session.flow_version),
+                session.needs_upgrade.to_string(),
+                optional(&session.locale),
+                session.updated_at_epoch_ms.to_string(),
+                rfc3339(session.updated_at_epoch_ms),
+                session
+                    .ttl_ms
+                    .map(|ttl| ttl.to_string())
+                    .unwrap_or_default(),
+            ]
+        }),
+    )
+}
+
+/// `payload` and `result` stay compact JSON; `node` is the last node the run visited (or the
+/// payload's `node_id`/`node`) and `status` is `result.status`.
+pub fn runner_events(events: &[RunnerEvent]) -> String {
+    render(
+        RUNNER_EVENT_COLUMNS,
+        events.iter().map(|event| {
+            vec![
+                event.timestamp_ms.to_string(),
+                rfc3339(event.timestamp_ms),
+                event
+                    .sequence
+                    .map(|seq| seq.to_string())
+                    .unwrap_or_default(),
+                optional(&event.instance),
+                optional(&event.tenant),
+                optional(&event.team),
+                optional(&event.user),
+                event.flow.clone(),
+                event_node(event).unwrap_or_default(),
+                event.result["status"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                event.payload.to_string(),
+                event.result.to_string(),
+            ]
+        }),
+    )
+}
+
+fn event_node(event: &RunnerEvent) -> Option<String> {
+    event.result["outcome"]["trace"]
+        .as_array()
+        .and_then(|trace| trace.last())
+        .or_else(|| event.payload.get("node_id"))
+        .or_else(|| event.payload.get("node"))
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+fn render(columns: &[&str], rows: impl Iterator<Item = Vec<String>>) -> String {
+    let mut out = String::from(BOM);
+    push_row(&mut out, columns.iter().copied());
+    for row in rows {
+        push_row(&mut out, row.iter().map(String::as_str));
+    }
+    out
+}
+
+fn push_row<'a>(out: &mut String, fields: impl Iterator<Item = &'a str>) {
+    for (idx, field) in fields.enumerate() {
+        if idx > 0 {
+            out.push(',');
+        }
+        let formula = field.starts_with(['=', '+', '-', '@', '\t', '\r']);
+        if formula || field.contains([',', '"', '\r', '\n']) {
+            out.push('"');
+            if formula {
+                out.push('\'');
+            }
+            out.push_str(&field.replace('"', "\"\""));
+            out.push('"');
+        } else {
+            out.push_str(field);
+        }
+    }
+    out.push_str("\r\n");
+}
+
+fn optional(value: &Option<String>) -> String {
+    value.clone().unwrap_or_default()
+}
+
+fn rfc3339(epoch_ms: u64) -> String {
+    chrono::DateTime::from_timestamp_millis(epoch_ms as i64)
+        .map(|at| at.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn quotes_fields_with_separators_quotes_and_newlines() {
+        let csv = render(
+            &["a", "b"],
+            [
+                vec!["plain".to_string(), "with,comma".to_string()],
+                vec!["say \"hi\"".to_string(), "two\nlines".to_string()],
+            ]
+            .into_iter(),
+        );
+        assert_eq!(
+            csv,
+            "\u{feff}a,b\r\nplain,\"with,comma\"\r\n\"say \"\"hi\"\"\",\"two\nlines\"\r\n"
+        );
+    }
+
+    #[test]
+    fn cells_a_spreadsheet_would_evaluate_stay_text() {
+        let csv = render(
+            &["cell"],
+            [
+                "=HYPERLINK(\"x\")",
+                "+1",
+                "-2",
+                "@SUM(A1)",
+                "\tcmd",
+                "\rcmd",
+                "a=b",
+            ]
+            .into_iter()
+            .map(|cell| vec![cell.to_string()]),
+        );
+        assert_eq!(
+            csv,
+            "\u{feff}cell\r\n\"'=HYPERLINK(\"\"x\"\")\"\r\n\"'+1\"\r\n\"'-2\"\r\n\
+             \"'@SUM(A1)\"\r\n\"'\tcmd\"\r\n\"'\rcmd\"\r\na=b\r\n"
+        );
+    }
+
+    #[test]
+    fn runner_events_flatten_node_status_and_timestamps() {
+        let event = RunnerEvent {
+            timestamp_ms: 1_700_000_000_123,
+            flow: "welcome".into(),
+            tenant: Some("acme".into()),
+            team: None,
+            user: Some("u-1".into()),
+            payload: json!({"text": "hi, there"}),
+            result: json!({"status": "ok", "outcome": {"trace": ["start", "greet"]}}),
+            sequence: Some(7),
+            instance: None,
+        };
+        let csv = runner_events(&[event]);
+        let mut lines = csv.strip_prefix(BOM).unwrap().split("\r\n");
+        assert_eq!(lines.next().unwrap(), RUNNER_EVENT_COLUMNS.join(","));
+        assert_eq!(
+            lines.next().unwrap(),
+            "1700000000123,2023-11-14T22:13:20.123Z,7,,acme,,u-1,welcome,greet,ok,\
+             \"{\"\"text\"\":\"\"hi, there\"\"}\",\
+             \"{\"\"outcome\"\":{\"\"trace\"\":[\"\"start\"\",\"\"greet\"\"]},\"\"status\"\":\"\"ok\"\"}\""
+        );
+        assert_eq!(lines.next(), Some(""));
+    }
+}
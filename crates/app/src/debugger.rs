@@ -0,0 +1,273 @@
+//! Node breakpoints for embedded flow runs (`[server.debug]`, `/debug/*`). A run that reaches a
+//! node marked with `POST /debug/breakpoints` parks before executing it; `GET /debug/paused`
+//! shows the payload about to enter the node and `POST /debug/continue` resumes the run,
+//! optionally with a replacement payload and with `step` set to stop again at the next node.
+#![cfg_attr(not(feature = "mini-runner"), allow(dead_code))]
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+    time::Duration,
+};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::oneshot;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::session::current_timestamp_ms;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebugConfig {
+    /// Serve the `/debug` endpoints; off unless a dev config turns it on.
+    #[serde(default)]
+    pub enabled: bool,
+    /// A paused run continues unchanged after this long without `POST /debug/continue`.
+    #[serde(default = "default_pause_timeout_secs")]
+    pub pause_timeout_secs: u64,
+}
+
+impl Default for DebugConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            pause_timeout_secs: default_pause_timeout_secs(),
+        }
+    }
+}
+
+fn default_pause_timeout_secs() -> u64 {
+    300
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Breakpoint {
+    pub flow: String,
+    pub node: String,
+}
+
+/// A run parked at a node, as shown on `GET /debug/paused`.
+#[derive(Debug, Clone, Serialize)]
+pub struct PausedRun {
+    pub id: String,
+    pub flow: String,
+    pub node: String,
+    pub session_id: String,
+    pub tenant: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    /// Payload the node is about to receive.
+    pub payload: Value,
+    /// True when the run stopped here because it was stepping, not on a breakpoint.
+    pub stepped: bool,
+    pub paused_at_ms: u64,
+}
+
+/// How a paused run continues.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Resume {
+    /// Replacement for the pending payload.
+    #[serde(default)]
+    pub payload: Option<Value>,
+    /// Stop again before the next node.
+    #[serde(default)]
+    pub step: bool,
+}
+
+/// Where a run is about to go.
+pub struct Stop<'a> {
+    pub flow: &'a str,
+    pub node: &'a str,
+    pub session_id: &'a str,
+    pub tenant: &'a str,
+    pub user: Option<&'a str>,
+}
+
+struct Pending {
+    run: PausedRun,
+    resume: oneshot::Sender<Resume>,
+}
+
+/// Breakpoints and paused runs, shared by the HTTP handlers and the embedded runner.
+#[derive(Clone)]
+pub struct Debugger {
+    breakpoints: Arc<Mutex<BTreeSet<Breakpoint>>>,
+    paused: Arc<Mutex<BTreeMap<String, Pending>>>,
+    timeout: Duration,
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(default_pause_timeout_secs()))
+    }
+}
+
+impl Debugger {
+    /// Paused runs continue on their own after `timeout`.
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            breakpoints: Arc::default(),
+            paused: Arc::default(),
+            timeout,
+        }
+    }
+
+    /// Returns false when the breakpoint was already set.
+    pub fn add(&self, breakpoint: Breakpoint) -> bool {
+        self.breakpoints.lock().insert(breakpoint)
+    }
+
+    pub fn remove(&self, breakpoint: &Breakpoint) -> bool {
+        self.breakpoints.lock().remove(breakpoint)
+    }
+
+    /// Remove every breakpoint; runs already paused stay paused.
+    pub fn clear(&self) -> usize {
+        std::mem::take(&mut *self.breakpoints.lock()).len()
+    }
+
+    pub fn breakpoints(&self) -> Vec<Breakpoint> {
+        self.breakpoints.lock().iter().cloned().collect()
+    }
+
+    /// Paused runs, oldest first.
+    pub fn paused(&self) -> Vec<PausedRun> {
+        let mut runs: Vec<_> = self
+            .paused
+            .lock()
+            .values()
+            .map(|pending| pending.run.clone())
+            .collect();
+        runs.sort_by_key(|run| run.paused_at_ms);
+        runs
+    }
+
+    /// Continue the paused run `id`; returns false when no run is paused under that id.
+    pub fn resume(&self, id: &str, resume: Resume) -> bool {
+        match self.paused.lock().remove(id) {
+            Some(pending) => pending.resume.send(resume).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Park the run before `stop` when a breakpoint is set there or it is `stepping`, until
+    /// it is resumed or the pause times out. Returns the payload to continue with and whether
+    /// to stop at the next node too.
+    pub async fn checkpoint(
+        &self,
+        stop: Stop<'_>,
+        payload: Value,
+        stepping: bool,
+    ) -> (Value, bool) {
+        let hit = stepping || {
+            let breakpoints = self.breakpoints.lock();
+            !breakpoints.is_empty()
+                && breakpoints.contains(&Breakpoint {
+                    flow: stop.flow.to_string(),
+                    node: stop.node.to_string(),
+                })
+        };
+        if !hit {
+            return (payload, false);
+        }
+        let id = Uuid::new_v4().simple().to_string();
+        let (resume, resumed) = oneshot::channel();
+        self.paused.lock().insert(
+            id.clone(),
+            Pending {
+                run: PausedRun {
+                    id: id.clone(),
+                    flow: stop.flow.to_string(),
+                    node: stop.node.to_string(),
+                    session_id: stop.session_id.to_string(),
+                    tenant: stop.tenant.to_string(),
+                    user: stop.user.map(str::to_string),
+                    payload: payload.clone(),
+                    stepped: stepping,
+                    paused_at_ms: current_timestamp_ms(),
+                },
+                resume,
+            },
+        );
+        info!(%id, flow = %stop.flow, node = %stop.node, "flow run paused at breakpoint");
+        match tokio::time::timeout(self.timeout, resumed).await {
+            Ok(Ok(resume)) => (resume.payload.unwrap_or(payload), resume.step),
+            _ => {
+                self.paused.lock().remove(&id);
+                warn!(%id, flow = %stop.flow, node = %stop.node, "paused flow run timed out; continuing");
+                (payload, false)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn stop<'a>(node: &'a str) -> Stop<'a> {
+        Stop {
+            flow: "chat",
+            node,
+            session_id: "sess-1",
+            tenant: "dev",
+            user: None,
+        }
+    }
+
+    async fn wait_for_pause(debugger: &Debugger) -> PausedRun {
+        for _ in 0..100 {
+            if let Some(run) = debugger.paused().into_iter().next() {
+                return run;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("run never paused");
+    }
+
+    #[tokio::test]
+    async fn pauses_at_breakpoints_and_resumes_with_the_edited_payload() {
+        let debugger = Debugger::default();
+        assert_eq!(
+            debugger.checkpoint(stop("respond"), json!(1), false).await,
+            (json!(1), false)
+        );
+        assert!(debugger.add(Breakpoint {
+            flow: "chat".into(),
+            node: "respond".into(),
+        }));
+
+        let run = tokio::spawn({
+            let debugger = debugger.clone();
+            async move {
+                debugger
+                    .checkpoint(stop("respond"), json!({"text": "hi"}), false)
+                    .await
+            }
+        });
+        let paused = wait_for_pause(&debugger).await;
+        assert_eq!(paused.node, "respond");
+        assert_eq!(paused.payload, json!({"text": "hi"}));
+        assert!(!debugger.resume("unknown", Resume::default()));
+        assert!(debugger.resume(
+            &paused.id,
+            Resume {
+                payload: Some(json!({"text": "edited"})),
+                step: true,
+            },
+        ));
+        assert_eq!(run.await.unwrap(), (json!({"text": "edited"}), true));
+        assert!(debugger.paused().is_empty());
+    }
+
+    #[tokio::test]
+    async fn paused_runs_continue_unchanged_after_the_timeout() {
+        let debugger = Debugger::new(Duration::from_millis(20));
+        let (payload, step) = debugger.checkpoint(stop("any"), json!(2), true).await;
+        assert_eq!((payload, step), (json!(2), false));
+        assert!(debugger.paused().is_empty());
+    }
+}
@@ -0,0 +1,567 @@
+//! `[email]`: inbound email as a messaging channel. `serve` accepts mail over SMTP on
+//! `smtp_listen`, routes each recipient to a tenant and flow by the `[[email.routes]]` rules,
+//! and runs the flow with a canonical inbound message (`channel: "email"`). Replies are kept on
+//! one `thread_id` through their `Message-ID`/`In-Reply-To`/`References` headers, so packs that
+//! send email notifications can be tested end to end against a local mail hop. IMAP polling is
+//! not supported; point the sender (or a forwarding rule) at the SMTP listener instead.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{Result, anyhow};
+use mail_parser::{HeaderValue, MessageParser};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+    net::TcpListener,
+};
+use tracing::{info, warn};
+
+/// Message-IDs remembered for thread mapping; the oldest are forgotten first.
+const THREAD_MEMORY: usize = 10_000;
+/// Longest SMTP command line accepted (RFC 5321 allows 512 octets).
+const MAX_COMMAND_LINE: u64 = 2048;
+/// A client silent for this long is disconnected.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailConfig {
+    /// Address the SMTP receiver listens on (e.g. `127.0.0.1:2525`); unset disables it.
+    #[serde(default)]
+    pub smtp_listen: Option<String>,
+    /// Name announced in the SMTP greeting.
+    #[serde(default = "default_hostname")]
+    pub hostname: String,
+    /// Messages larger than this are refused with `552`.
+    #[serde(default = "default_max_message_bytes")]
+    pub max_message_bytes: usize,
+    /// Which tenant and flow each recipient belongs to; the first matching rule wins.
+    #[serde(default)]
+    pub routes: Vec<EmailRoute>,
+}
+
+impl Default for EmailConfig {
+    fn default() -> Self {
+        Self {
+            smtp_listen: None,
+            hostname: default_hostname(),
+            max_message_bytes: default_max_message_bytes(),
+            routes: Vec::new(),
+        }
+    }
+}
+
+fn default_hostname() -> String {
+    "greentic-integration".into()
+}
+
+fn default_max_message_bytes() -> usize {
+    10 * 1024 * 1024
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmailRoute {
+    /// Recipient address (`support@acme.test`) or a whole domain (`@acme.test`), matched
+    /// case-insensitively.
+    pub address: String,
+    pub tenant: String,
+    #[serde(default)]
+    pub team: Option<String>,
+    pub flow: String,
+}
+
+impl EmailRoute {
+    fn matches(&self, recipient: &str) -> bool {
+        let (rule, recipient) = (
+            self.address.to_ascii_lowercase(),
+            recipient.to_ascii_lowercase(),
+        );
+        match rule.strip_prefix('@') {
+            Some(domain) => recipient
+                .rsplit_once('@')
+                .is_some_and(|(_, rest)| rest == domain),
+            None => rule == recipient,
+        }
+    }
+}
+
+/// An email normalized into the fields flows see.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InboundEmail {
+    pub from: String,
+    pub to: Vec<String>,
+    pub subject: Option<String>,
+    /// Plain-text body, falling back to the subject for empty messages.
+    pub text: String,
+    pub message_id: Option<String>,
+    pub in_reply_to: Option<String>,
+    pub references: Vec<String>,
+    /// Root message of the conversation; replies share it.
+    pub thread_id: String,
+}
+
+impl InboundEmail {
+    /// Canonical inbound message payload handed to the flow.
+    pub fn payload(&self) -> Value {
+        json!({
+            "channel": "email",
+            "provider": "email",
+            "text": self.text,
+            "subject": self.subject,
+            "from": self.from,
+            "to": self.to,
+            "message_id": self.message_id,
+            "in_reply_to": self.in_reply_to,
+            "references": self.references,
+            "thread_id": self.thread_id,
+        })
+    }
+}
+
+/// Maps Message-IDs to the thread they belong to, so a reply that only names its parent
+/// (`In-Reply-To` without `References`) still lands on the conversation's root.
+#[derive(Default)]
+struct ThreadMap {
+    threads: HashMap<String, String>,
+    order: VecDeque<String>,
+}
+
+impl ThreadMap {
+    fn assign(
+        &mut self,
+        message_id: Option<&str>,
+        in_reply_to: Option<&str>,
+        references: &[String],
+    ) -> String {
+        let known = references
+            .iter()
+            .map(String::as_str)
+            .chain(in_reply_to)
+            .find_map(|id| self.threads.get(id).cloned());
+        let thread = known
+            .or_else(|| references.first().cloned())
+            .or_else(|| in_reply_to.map(str::to_string))
+            .or_else(|| message_id.map(str::to_string))
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        if let Some(id) = message_id
+            && self
+                .threads
+                .insert(id.to_string(), thread.clone())
+                .is_none()
+        {
+            self.order.push_back(id.to_string());
+            if self.order.len() > THREAD_MEMORY
+                && let Some(oldest) = self.order.pop_front()
+            {
+                self.threads.remove(&oldest);
+            }
+        }
+        thread
+    }
+}
+
+/// Parses, threads and routes inbound mail.
+#[derive(Clone)]
+pub struct EmailIngress {
+    config: Arc<EmailConfig>,
+    threads: Arc<Mutex<ThreadMap>>,
+}
+
+impl EmailIngress {
+    pub fn new(config: EmailConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            threads: Arc::default(),
+        }
+    }
+
+    pub fn route(&self, recipient: &str) -> Option<&EmailRoute> {
+        self.config
+            .routes
+            .iter()
+            .find(|route| route.matches(recipient))
+    }
+
+    /// Normalize a raw RFC 5322 message received for `recipients`.
+    pub fn parse(&self, raw: &[u8], recipients: &[String]) -> Result<InboundEmail> {
+        let message = MessageParser::default()
+            .parse(raw)
+            .ok_or_else(|| anyhow!("message is not valid RFC 5322"))?;
+        let from = message
+            .from()
+            .and_then(|from| from.first())
+            .and_then(|addr| addr.address())
+            .map(str::to_ascii_lowercase)
+            .ok_or_else(|| anyhow!("message has no From address"))?;
+        let to = match message.to() {
+            Some(to) => to
+                .iter()
+                .filter_map(|addr| addr.address())
+                .map(str::to_ascii_lowercase)
+                .collect(),
+            None => recipients.to_vec(),
+        };
+        let ids = |value: &HeaderValue<'_>| -> Vec<String> {
+            value
+                .as_text_list()
+                .unwrap_or_default()
+                .iter()
+                .map(|id| id.to_string())
+                .collect()
+        };
+        let message_id = message.message_id().map(str::to_string);
+        let in_reply_to = ids(message.in_reply_to()).pop();
+        let references = ids(message.references());
+        let subject = message.subject().map(str::to_string);
+        let text = message
+            .body_text(0)
+            .map(|body| body.trim().to_string())
+            .filter(|body| !body.is_empty())
+            .or_else(|| subject.clone())
+            .unwrap_or_default();
+        let thread_id =
+            self.threads
+                .lock()
+                .assign(message_id.as_deref(), in_reply_to.as_deref(), &references);
+        Ok(InboundEmail {
+            from,
+            to,
+            subject,
+            text,
+            message_id,
+            in_reply_to,
+            references,
+            thread_id,
+        })
+    }
+
+    /// Accept SMTP connections on `listener` until the task is dropped, handing every
+    /// accepted message to `deliver` once per matching route.
+    pub async fn serve_smtp<D, F>(self, listener: Arc<TcpListener>, deliver: D) -> Result<()>
+    where
+        D: Fn(EmailRoute, InboundEmail) -> F + Clone + Send + Sync + 'static,
+        F: Future<Output = Result<()>> + Send,
+    {
+        loop {
+            let (stream, peer) = listener.accept().await?;
+            let (ingress, deliver) = (self.clone(), deliver.clone());
+            tokio::spawn(async move {
+                let (read, write) = stream.into_split();
+                if let Err(err) = ingress.smtp_session(read, write, deliver).await {
+                    warn!(?err, %peer, "smtp session ended with an error");
+                }
+            });
+        }
+    }
+
+    async fn smtp_session<R, W, D, F>(&self, read: R, mut write: W, deliver: D) -> Result<()>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+        D: Fn(EmailRoute, InboundEmail) -> F,
+        F: Future<Output = Result<()>>,
+    {
+        let mut reader = BufReader::new(read);
+        let host = &self.config.hostname;
+        reply(
+            &mut write,
+            &format!("220 {host} ESMTP greentic-integration"),
+        )
+        .await?;
+        let mut sender: Option<String> = None;
+        let mut recipients: Vec<String> = Vec::new();
+        loop {
+            let Some(line) = read_line(&mut reader, MAX_COMMAND_LINE).await? else {
+                return Ok(());
+            };
+            let line = String::from_utf8_lossy(&line);
+            let (verb, arg) = line
+                .split_once(' ')
+                .map_or((line.as_ref(), ""), |(verb, arg)| (verb, arg.trim()));
+            let response = match verb.to_ascii_uppercase().as_str() {
+                "EHLO" => format!(
+                    "250-{host}\r\n250-SIZE {}\r\n250 8BITMIME",
+                    self.config.max_message_bytes
+                ),
+                "HELO" => format!("250 {host}"),
+                "MAIL" => match mailbox(arg, "FROM:") {
+                    Some(from) => {
+                        sender = Some(from);
+                        recipients.clear();
+                        "250 2.1.0 OK".into()
+                    }
+                    None => "501 5.5.4 expected MAIL FROM:<address>".into(),
+                },
+                "RCPT" if sender.is_none() => "503 5.5.1 MAIL first".into(),
+                "RCPT" => match mailbox(arg, "TO:") {
+                    Some(to) if self.route(&to).is_some() => {
+                        recipients.push(to);
+                        "250 2.1.5 OK".into()
+                    }
+                    Some(to) => format!("550 5.1.1 no route for <{to}>"),
+                    None => "501 5.5.4 expected RCPT TO:<address>".into(),
+                },
+                "DATA" if recipients.is_empty() => "503 5.5.1 RCPT first".into(),
+                "DATA" => {
+                    reply(&mut write, "354 end data with <CR><LF>.<CR><LF>").await?;
+                    let response =
+                        match read_data(&mut reader, self.config.max_message_bytes).await? {
+                            Some(raw) => self.accept(&raw, &recipients, &deliver).await,
+                            None => "552 5.3.4 message exceeds the size limit".into(),
+                        };
+                    sender = None;
+                    recipients.clear();
+                    response
+                }
+                "RSET" => {
+                    sender = None;
+                    recipients.clear();
+                    "250 2.0.0 OK".into()
+                }
+                "NOOP" => "250 2.0.0 OK".into(),
+                "QUIT" => {
+                    reply(&mut write, "221 2.0.0 bye").await?;
+                    return Ok(());
+                }
+                _ => "502 5.5.2 command not recognized".into(),
+            };
+            reply(&mut write, &response).await?;
+        }
+    }
+
+    /// Parse one message and deliver it to each route its recipients resolve to.
+    async fn accept<D, F>(&self, raw: &[u8], recipients: &[String], deliver: &D) -> String
+    where
+        D: Fn(EmailRoute, InboundEmail) -> F,
+        F: Future<Output = Result<()>>,
+    {
+        let email = match self.parse(raw, recipients) {
+            Ok(email) => email,
+            Err(err) => return format!("554 5.6.0 {err}"),
+        };
+        let mut routes: Vec<EmailRoute> = Vec::new();
+        for route in recipients.iter().filter_map(|to| self.route(to)) {
+            if !routes.contains(route) {
+                routes.push(route.clone());
+            }
+        }
+        for route in routes {
+            info!(
+                tenant = %route.tenant,
+                flow = %route.flow,
+                from = %email.from,
+                thread_id = %email.thread_id,
+                "inbound email"
+            );
+            if let Err(err) = deliver(route, email.clone()).await {
+                warn!(?err, from = %email.from, "inbound email delivery failed");
+                return format!("451 4.3.0 delivery failed: {err}");
+            }
+        }
+        "250 2.0.0 OK".into()
+    }
+}
+
+/// The address in `FROM:<a@b>` / `TO:<a@b>` (parameters after it are ignored).
+fn mailbox(arg: &str, prefix: &str) -> Option<String> {
+    let rest = arg
+        .get(..prefix.len())?
+        .eq_ignore_ascii_case(prefix)
+        .then(|| &arg[prefix.len()..])?;
+    let rest = rest.trim_start();
+    let address = match rest.strip_prefix('<') {
+        Some(inner) => inner.split_once('>')?.0,
+        None => rest.split_whitespace().next()?,
+    };
+    Some(address.to_ascii_lowercase())
+}
+
+async fn reply(write: &mut (impl AsyncWrite + Unpin), line: &str) -> Result<()> {
+    write.write_all(line.as_bytes()).await?;
+    write.write_all(b"\r\n").await?;
+    write.flush().await?;
+    Ok(())
+}
+
+/// One line without its line ending; `None` at end of stream. Longer lines are an error.
+async fn read_line<R: AsyncRead + Unpin>(
+    reader: &mut BufReader<R>,
+    limit: u64,
+) -> Result<Option<Vec<u8>>> {
+    let mut line = Vec::new();
+    let read = tokio::time::timeout(
+        IDLE_TIMEOUT,
+        (&mut *reader).take(limit + 1).read_until(b'\n', &mut line),
+    )
+    .await
+    .map_err(|_| anyhow!("client idle for {}s", IDLE_TIMEOUT.as_secs()))??;
+    if read == 0 {
+        return Ok(None);
+    }
+    if line.last() != Some(&b'\n') {
+        return Err(anyhow!("line longer than {limit} bytes"));
+    }
+    while matches!(line.last(), Some(b'\n' | b'\r')) {
+        line.pop();
+    }
+    Ok(Some(line))
+}
+
+/// The message after `DATA`, dot-unstuffed, up to the lone `.` line. `None` when it exceeds
+/// `max_bytes`; the rest of it is still consumed so the session can continue.
+async fn read_data<R: AsyncRead + Unpin>(
+    reader: &mut BufReader<R>,
+    max_bytes: usize,
+) -> Result<Option<Vec<u8>>> {
+    let mut data = Vec::new();
+    let mut oversized = false;
+    loop {
+        let line = read_line(reader, max_bytes as u64 + 2)
+            .await?
+            .ok_or_else(|| anyhow!("connection closed during DATA"))?;
+        if line == b"." {
+            return Ok((!oversized).then_some(data));
+        }
+        let line = line.strip_prefix(b".").unwrap_or(&line);
+        if data.len() + line.len() + 2 > max_bytes {
+            oversized = true;
+            data.clear();
+        }
+        if !oversized {
+            data.extend_from_slice(line);
+            data.extend_from_slice(b"\r\n");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncBufReadExt;
+
+    fn ingress() -> EmailIngress {
+        EmailIngress::new(EmailConfig {
+            routes: vec![
+                EmailRoute {
+                    address: "support@acme.test".into(),
+                    tenant: "acme".into(),
+                    team: Some("help".into()),
+                    flow: "support-mail".into(),
+                },
+                EmailRoute {
+                    address: "@globex.test".into(),
+                    tenant: "globex".into(),
+                    team: None,
+                    flow: "inbox".into(),
+                },
+            ],
+            ..EmailConfig::default()
+        })
+    }
+
+    #[test]
+    fn routes_by_address_or_domain_and_threads_replies() {
+        let ingress = ingress();
+        assert_eq!(ingress.route("Support@ACME.test").unwrap().tenant, "acme");
+        assert_eq!(ingress.route("sales@globex.test").unwrap().flow, "inbox");
+        assert!(ingress.route("sales@acme.test").is_none());
+
+        let first = ingress
+            .parse(
+                b"From: Ann <ann@example.test>\r\nTo: support@acme.test\r\nSubject: Help\r\n\
+                  Message-ID: <m1@example.test>\r\n\r\nMy order is late.\r\n",
+                &[],
+            )
+            .unwrap();
+        assert_eq!(first.from, "ann@example.test");
+        assert_eq!(first.text, "My order is late.");
+        assert_eq!(first.thread_id, "m1@example.test");
+        let payload = first.payload();
+        assert_eq!(payload["channel"], "email");
+        assert_eq!(payload["to"], json!(["support@acme.test"]));
+
+        let reply = ingress
+            .parse(
+                b"From: bot@acme.test\r\nSubject: Re: Help\r\nMessage-ID: <m2@acme.test>\r\n\
+                  In-Reply-To: <m1@example.test>\r\n\r\nOn it.\r\n",
+                &["ann@example.test".into()],
+            )
+            .unwrap();
+        assert_eq!(reply.to, ["ann@example.test"]);
+        // Only the parent is named, yet the thread resolves through it to the root.
+        let second_reply = ingress
+            .parse(
+                b"From: ann@example.test\r\nSubject: Re: Help\r\nMessage-ID: <m3@example.test>\r\n\
+                  In-Reply-To: <m2@acme.test>\r\n\r\n\r\n",
+                &[],
+            )
+            .unwrap();
+        assert_eq!(reply.thread_id, "m1@example.test");
+        assert_eq!(second_reply.thread_id, "m1@example.test");
+        assert_eq!(
+            second_reply.text, "Re: Help",
+            "empty bodies fall back to the subject"
+        );
+    }
+
+    #[tokio::test]
+    async fn smtp_session_delivers_routed_messages() {
+        let listener = Arc::new(TcpListener::bind("127.0.0.1:0").await.unwrap());
+        let addr = listener.local_addr().unwrap();
+        let delivered = Arc::new(Mutex::new(Vec::new()));
+        let sink = delivered.clone();
+        let server = tokio::spawn(ingress().serve_smtp(listener, move |route, email| {
+            let sink = sink.clone();
+            async move {
+                sink.lock().push((route.tenant, email));
+                Ok(())
+            }
+        }));
+
+        let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let (read, mut write) = stream.into_split();
+        let mut lines = BufReader::new(read).lines();
+        let mut expect = async |sent: &str, code: &str| {
+            if !sent.is_empty() {
+                write
+                    .write_all(format!("{sent}\r\n").as_bytes())
+                    .await
+                    .unwrap();
+            }
+            loop {
+                let line = lines.next_line().await.unwrap().unwrap();
+                if line.as_bytes().get(3) != Some(&b'-') {
+                    assert!(line.starts_with(code), "{sent:?} -> {line}");
+                    return;
+                }
+            }
+        };
+        expect("", "220").await;
+        expect("EHLO client.test", "250").await;
+        expect("RCPT TO:<support@acme.test>", "503").await;
+        expect("MAIL FROM:<ann@example.test>", "250").await;
+        expect("RCPT TO:<nobody@nowhere.test>", "550").await;
+        expect("RCPT TO:<Support@acme.test>", "250").await;
+        expect("DATA", "354").await;
+        expect(
+            "From: ann@example.test\r\nTo: support@acme.test\r\nMessage-ID: <s1@example.test>\r\n\
+             Subject: Hi\r\n\r\n..leading dot\r\n.",
+            "250",
+        )
+        .await;
+        expect("QUIT", "221").await;
+
+        let delivered = delivered.lock().clone();
+        assert_eq!(delivered.len(), 1);
+        assert_eq!(delivered[0].0, "acme");
+        assert_eq!(delivered[0].1.text, ".leading dot");
+        assert_eq!(delivered[0].1.thread_id, "s1@example.test");
+        server.abort();
+    }
+}
@@ -0,0 +1,168 @@
+use std::{fs::File, io::Write, path::Path, sync::Arc};
+
+use anyhow::{Context, Result};
+use parquet::{
+    basic::Compression,
+    data_type::{ByteArray, ByteArrayType, Int64Type},
+    file::{properties::WriterProperties, writer::SerializedFileWriter},
+    schema::parser::parse_message_type,
+};
+
+use crate::RunnerEvent;
+
+/// Columnar layout of exported runner events. `payload`/`result` stay JSON so DuckDB
+/// (`json_extract`) or pandas can dig into them without a fixed schema.
+const RUNNER_EVENT_SCHEMA: &str = "
+message runner_event {
+    REQUIRED INT64 timestamp_ms (TIMESTAMP(MILLIS,true));
+    REQUIRED BINARY flow (STRING);
+    OPTIONAL BINARY tenant (STRING);
+    OPTIONAL BINARY team (STRING);
+    OPTIONAL BINARY user (STRING);
+    REQUIRED BINARY payload (JSON);
+    REQUIRED BINARY result (JSON);
+    OPTIONAL INT64 sequence (INTEGER(64,false));
+}
+";
+
+/// Write `events` as a single snappy-compressed row group.
+pub fn write_parquet(events: &[RunnerEvent], out: &Path) -> Result<()> {
+    let schema = Arc::new(parse_message_type(RUNNER_EVENT_SCHEMA)?);
+    let props = Arc::new(
+        WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build(),
+    );
+    let file = File::create(out).with_context(|| format!("failed to create {}", out.display()))?;
+    let mut writer = SerializedFileWriter::new(file, schema, props)?;
+    let mut row_group = writer.next_row_group()?;
+    let mut column_idx = 0;
+    while let Some(mut column) = row_group.next_column()? {
+        match column_idx {
+            0 => {
+                let values: Vec<i64> = events.iter().map(|e| e.timestamp_ms as i64).collect();
+                column
+                    .typed::<Int64Type>()
+                    .write_batch(&values, None, None)?;
+            }
+            1 => write_strings(&mut column, events.iter().map(|e| e.flow.clone()))?,
+            2 => write_optional(&mut column, events.iter().map(|e| e.tenant.as_deref()))?,
+            3 => write_optional(&mut column, events.iter().map(|e| e.team.as_deref()))?,
+            4 => write_optional(&mut column, events.iter().map(|e| e.user.as_deref()))?,
+            5 => write_strings(&mut column, events.iter().map(|e| e.payload.to_string()))?,
+            6 => write_strings(&mut column, events.iter().map(|e| e.result.to_string()))?,
+            _ => {
+                let def_levels: Vec<i16> = events
+                    .iter()
+                    .map(|e| i16::from(e.sequence.is_some()))
+                    .collect();
+                let values: Vec<i64> = events
+                    .iter()
+                    .filter_map(|e| e.sequence)
+                    .map(|s| s as i64)
+                    .collect();
+                column
+                    .typed::<Int64Type>()
+                    .write_batch(&values, Some(&def_levels), None)?;
+            }
+        }
+        column.close()?;
+        column_idx += 1;
+    }
+    row_group.close()?;
+    writer.close()?;
+    Ok(())
+}
+
+/// One JSON object per line, for tools that prefer row-oriented input.
+pub fn write_jsonl(events: &[RunnerEvent], out: &Path) -> Result<()> {
+    let mut file =
+        File::create(out).with_context(|| format!("failed to create {}", out.display()))?;
+    for event in events {
+        serde_json::to_writer(&mut file, event)?;
+        file.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+fn write_strings(
+    writer: &mut parquet::file::writer::SerializedColumnWriter<'_>,
+    values: impl Iterator<Item = String>,
+) -> Result<()> {
+    let values: Vec<ByteArray> = values
+        .map(|value| ByteArray::from(value.into_bytes()))
+        .collect();
+    writer
+        .typed::<ByteArrayType>()
+        .write_batch(&values, None, None)?;
+    Ok(())
+}
+
+fn write_optional<'a>(
+    writer: &mut parquet::file::writer::SerializedColumnWriter<'_>,
+    values: impl Iterator<Item = Option<&'a str>>,
+) -> Result<()> {
+    let mut present = Vec::new();
+    let mut def_levels = Vec::new();
+    for value in values {
+        def_levels.push(i16::from(value.is_some()));
+        present.extend(value.map(ByteArray::from));
+    }
+    writer
+        .typed::<ByteArrayType>()
+        .write_batch(&present, Some(&def_levels), None)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use serde_json::json;
+
+    #[test]
+    fn parquet_export_round_trips_columns() {
+        let tmp = tempfile::tempdir().unwrap();
+        let out = tmp.path().join("events.parquet");
+        let events = vec![
+            RunnerEvent {
+                timestamp_ms: 1_700_000_000_000,
+                flow: "welcome".into(),
+                tenant: Some("acme".into()),
+                team: None,
+                user: Some("u1".into()),
+                payload: json!({"text": "hi"}),
+                result: json!({"status": "ok"}),
+                sequence: Some(3),
+                instance: None,
+            },
+            RunnerEvent {
+                timestamp_ms: 1_700_000_000_250,
+                flow: "deploy".into(),
+                tenant: None,
+                team: Some("ops".into()),
+                user: None,
+                payload: json!(null),
+                result: json!({"status": "component_status"}),
+                sequence: None,
+                instance: None,
+            },
+        ];
+        write_parquet(&events, &out).unwrap();
+
+        let reader = SerializedFileReader::new(File::open(&out).unwrap()).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 2);
+        let rows: Vec<String> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|row| row.unwrap().to_string())
+            .collect();
+        assert!(rows[0].contains("flow: \"welcome\""));
+        assert!(rows[0].contains("tenant: \"acme\""));
+        assert!(rows[0].contains("team: null"));
+        assert!(rows[0].contains("sequence: 3"), "{}", rows[0]);
+        assert!(rows[1].contains("sequence: null"));
+        assert!(rows[1].contains("team: \"ops\""));
+        assert!(rows[1].contains("component_status"));
+    }
+}
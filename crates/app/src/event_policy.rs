@@ -0,0 +1,192 @@
+//! `[runner.event_policy]`: which runner events the bridge keeps and what it strips from them
+//! before they reach the event log, `GET /runner/events`, exports or the runner's activity
+//! feed. High-volume flows can be sampled down to a percentage, and configured JSON paths are
+//! scrubbed so semi-real traffic does not persist user content verbatim.
+
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::RunnerEvent;
+
+/// Replacement written over scrubbed values.
+pub const SCRUBBED: &str = "[scrubbed]";
+
+/// Event fields a scrub path may start at.
+const SCRUB_ROOTS: &[&str] = &["payload", "result", "user"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EventPolicyConfig {
+    /// Percentage (0–100) of each flow's events to keep; `*` covers flows not listed.
+    /// Events whose `result.status` is `error` are always kept.
+    #[serde(default)]
+    pub sample_percent: BTreeMap<String, f64>,
+    /// Dotted paths (`payload.text`, `result.outcome.messages.*.text`) whose values are
+    /// replaced by `"[scrubbed]"`; `*` matches every key or array element.
+    #[serde(default)]
+    pub scrub: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EventPolicy {
+    sample_percent: BTreeMap<String, f64>,
+    scrub: Vec<Vec<String>>,
+}
+
+impl EventPolicy {
+    pub fn from_config(config: &EventPolicyConfig) -> Result<Self> {
+        for (flow, percent) in &config.sample_percent {
+            if !(0.0..=100.0).contains(percent) {
+                bail!("runner.event_policy.sample_percent.{flow} must be between 0 and 100");
+            }
+        }
+        let scrub = config
+            .scrub
+            .iter()
+            .map(|path| parse_scrub_path(path))
+            .collect::<Result<_>>()
+            .context("invalid runner.event_policy.scrub")?;
+        Ok(Self {
+            sample_percent: config.sample_percent.clone(),
+            scrub,
+        })
+    }
+
+    /// Sampling decision for `event`; dropped events are still delivered, just not logged.
+    pub fn keep(&self, event: &RunnerEvent) -> bool {
+        self.keep_with_roll(event, (Uuid::new_v4().as_u128() % 10_000) as f64 / 100.0)
+    }
+
+    /// `roll` is uniform in `[0, 100)`.
+    fn keep_with_roll(&self, event: &RunnerEvent, roll: f64) -> bool {
+        let Some(percent) = self
+            .sample_percent
+            .get(&event.flow)
+            .or_else(|| self.sample_percent.get("*"))
+        else {
+            return true;
+        };
+        let failed = event.result.get("status").and_then(Value::as_str) == Some("error");
+        failed || roll < *percent
+    }
+
+    /// `event` with every configured path replaced by [`SCRUBBED`].
+    pub fn scrub(&self, event: RunnerEvent) -> RunnerEvent {
+        if self.scrub.is_empty() {
+            return event;
+        }
+        let mut value = serde_json::to_value(&event).expect("runner events serialize to JSON");
+        for path in &self.scrub {
+            scrub_path(&mut value, path);
+        }
+        serde_json::from_value(value).unwrap_or(event)
+    }
+}
+
+fn parse_scrub_path(raw: &str) -> Result<Vec<String>> {
+    let segments: Vec<String> = raw.split('.').map(str::to_string).collect();
+    if segments.iter().any(String::is_empty) {
+        bail!("scrub path {raw:?} has an empty segment");
+    }
+    if !SCRUB_ROOTS.contains(&segments[0].as_str()) {
+        bail!("scrub path {raw:?} must start with payload, result or user");
+    }
+    Ok(segments)
+}
+
+fn scrub_path(value: &mut Value, path: &[String]) {
+    let Some((head, rest)) = path.split_first() else {
+        if !value.is_null() {
+            *value = Value::String(SCRUBBED.into());
+        }
+        return;
+    };
+    match value {
+        Value::Object(map) if head == "*" => {
+            map.values_mut().for_each(|child| scrub_path(child, rest));
+        }
+        Value::Object(map) => {
+            if let Some(child) = map.get_mut(head) {
+                scrub_path(child, rest);
+            }
+        }
+        Value::Array(items) if head == "*" => {
+            items.iter_mut().for_each(|child| scrub_path(child, rest));
+        }
+        Value::Array(items) => {
+            if let Some(child) = head
+                .parse::<usize>()
+                .ok()
+                .and_then(|idx| items.get_mut(idx))
+            {
+                scrub_path(child, rest);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn event(flow: &str, result: Value) -> RunnerEvent {
+        RunnerEvent {
+            timestamp_ms: 1,
+            flow: flow.into(),
+            tenant: Some("acme".into()),
+            team: None,
+            user: Some("alice@example.com".into()),
+            payload: json!({"text": "my card is 4111", "channel": "webchat"}),
+            result,
+            sequence: None,
+            instance: None,
+        }
+    }
+
+    fn policy(sample: &[(&str, f64)], scrub: &[&str]) -> Result<EventPolicy> {
+        EventPolicy::from_config(&EventPolicyConfig {
+            sample_percent: sample
+                .iter()
+                .map(|(flow, percent)| (flow.to_string(), *percent))
+                .collect(),
+            scrub: scrub.iter().map(|path| path.to_string()).collect(),
+        })
+    }
+
+    #[test]
+    fn scrubs_configured_paths_and_wildcards() {
+        let policy = policy(&[], &["payload.text", "result.messages.*.text", "user"]).unwrap();
+        let scrubbed = policy.scrub(event(
+            "chat",
+            json!({"status": "ok", "messages": [{"text": "hi alice"}, {"text": "bye"}]}),
+        ));
+        assert_eq!(scrubbed.payload["text"], SCRUBBED);
+        assert_eq!(scrubbed.payload["channel"], "webchat");
+        assert_eq!(scrubbed.result["messages"][1]["text"], SCRUBBED);
+        assert_eq!(scrubbed.result["status"], "ok");
+        assert_eq!(scrubbed.user.as_deref(), Some(SCRUBBED));
+        assert_eq!(scrubbed.tenant.as_deref(), Some("acme"));
+    }
+
+    #[test]
+    fn samples_by_flow_but_keeps_errors() {
+        let policy = policy(&[("chat", 10.0), ("*", 100.0)], &[]).unwrap();
+        let ok = event("chat", json!({"status": "ok"}));
+        assert!(policy.keep_with_roll(&ok, 5.0));
+        assert!(!policy.keep_with_roll(&ok, 50.0));
+        assert!(policy.keep_with_roll(&event("chat", json!({"status": "error"})), 50.0));
+        assert!(policy.keep_with_roll(&event("build", json!({"status": "ok"})), 99.0));
+    }
+
+    #[test]
+    fn rejects_invalid_config() {
+        assert!(policy(&[("chat", 120.0)], &[]).is_err());
+        assert!(policy(&[], &["timestamp_ms"]).is_err());
+        assert!(policy(&[], &["payload..text"]).is_err());
+    }
+}
@@ -0,0 +1,290 @@
+//! `[runner.event_retention]`: age- and count-based limits on recorded runner events. A
+//! supervised reaper applies them every `interval_secs` to the in-memory log and the durable
+//! `[stores.events]` store, and `POST /runner/events/prune` (`runner prune`) runs a pass on
+//! demand, so a long-lived environment's event store stops growing without bound. Pass counts
+//! are kept for `GET /runner/events/retention`.
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::session::current_timestamp_ms;
+use crate::{RunnerEvent, RunnerEventLog};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventRetentionConfig {
+    /// Events recorded longer ago than this are removed.
+    #[serde(default)]
+    pub max_age_secs: Option<u64>,
+    /// Only the latest this many events are kept.
+    #[serde(default)]
+    pub max_events: Option<usize>,
+    /// Seconds between reaper passes.
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+}
+
+impl Default for EventRetentionConfig {
+    fn default() -> Self {
+        Self {
+            max_age_secs: None,
+            max_events: None,
+            interval_secs: default_interval_secs(),
+        }
+    }
+}
+
+fn default_interval_secs() -> u64 {
+    300
+}
+
+impl EventRetentionConfig {
+    /// True when at least one limit is set; otherwise the reaper does not run.
+    pub fn is_enabled(&self) -> bool {
+        self.max_age_secs.is_some() || self.max_events.is_some()
+    }
+}
+
+/// What one pass removed. Counted in the durable store when one is configured, since it
+/// holds every cached event, otherwise in the in-memory log.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PruneReport {
+    /// Removed for being older than `max_age_secs`.
+    pub expired: usize,
+    /// Removed to get down to `max_events`.
+    pub trimmed: usize,
+}
+
+/// Reaper counters since startup, reported on `GET /runner/events/retention`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RetentionStats {
+    pub passes: u64,
+    pub expired_total: u64,
+    pub trimmed_total: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_pass_at_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_pass: Option<PruneReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+#[derive(Clone, Default)]
+pub struct EventRetention {
+    stats: Arc<Mutex<RetentionStats>>,
+}
+
+impl EventRetention {
+    pub fn stats(&self) -> RetentionStats {
+        self.stats.lock().clone()
+    }
+
+    /// Apply `limits` to `events` once and count the pass. Blocking.
+    pub fn prune(
+        &self,
+        events: &RunnerEventLog,
+        limits: &EventRetentionConfig,
+        now_ms: u64,
+    ) -> Result<PruneReport> {
+        let result = prune_events(events, limits, now_ms);
+        let mut stats = self.stats.lock();
+        stats.passes += 1;
+        stats.last_pass_at_ms = Some(now_ms);
+        match &result {
+            Ok(report) => {
+                stats.expired_total += report.expired as u64;
+                stats.trimmed_total += report.trimmed as u64;
+                stats.last_pass = Some(*report);
+                stats.last_error = None;
+            }
+            Err(err) => stats.last_error = Some(format!("{err:#}")),
+        }
+        result
+    }
+
+    /// Prune every `interval_secs` until the task is dropped. A failed pass is logged and
+    /// retried on the next tick.
+    pub async fn run(
+        self,
+        events: Arc<RunnerEventLog>,
+        config: EventRetentionConfig,
+    ) -> Result<()> {
+        let mut ticker = tokio::time::interval(Duration::from_secs(config.interval_secs.max(1)));
+        loop {
+            ticker.tick().await;
+            let (retention, events, limits) = (self.clone(), events.clone(), config.clone());
+            let result = tokio::task::spawn_blocking(move || {
+                retention.prune(&events, &limits, current_timestamp_ms())
+            })
+            .await?;
+            match result {
+                Ok(report) if report != PruneReport::default() => info!(
+                    audit = "event_retention",
+                    expired = report.expired,
+                    trimmed = report.trimmed,
+                    "pruned runner events"
+                ),
+                Ok(_) => {}
+                Err(err) => warn!(?err, "runner event retention pass failed"),
+            }
+        }
+    }
+}
+
+/// Events a pass with `limits` would remove, oldest first, without removing them: the
+/// durable store's when one is configured, otherwise the in-memory log's. Backs
+/// `runner prune --dry-run`.
+pub fn preview_prune(
+    events: &RunnerEventLog,
+    limits: &EventRetentionConfig,
+    now_ms: u64,
+) -> Result<Vec<RunnerEvent>> {
+    let candidates = match events.store() {
+        Some(store) => store.recent(i64::MAX as usize)?,
+        None => events.read().iter().cloned().collect(),
+    };
+    let cutoff = limits
+        .max_age_secs
+        .map(|secs| now_ms.saturating_sub(secs.saturating_mul(1000)));
+    let (mut doomed, kept): (Vec<_>, Vec<_>) = candidates
+        .into_iter()
+        .partition(|event| cutoff.is_some_and(|cutoff| event.timestamp_ms < cutoff));
+    if let Some(keep) = limits.max_events {
+        let excess = kept.len().saturating_sub(keep);
+        doomed.extend(kept.into_iter().take(excess));
+    }
+    Ok(doomed)
+}
+
+fn prune_events(
+    events: &RunnerEventLog,
+    limits: &EventRetentionConfig,
+    now_ms: u64,
+) -> Result<PruneReport> {
+    let mut report = PruneReport::default();
+    let cutoff = limits
+        .max_age_secs
+        .map(|secs| now_ms.saturating_sub(secs.saturating_mul(1000)));
+    {
+        let mut guard = events.write();
+        if let Some(cutoff) = cutoff {
+            let before = guard.len();
+            guard.retain(|event| event.timestamp_ms >= cutoff);
+            report.expired = before - guard.len();
+        }
+        if let Some(keep) = limits.max_events
+            && guard.len() > keep
+        {
+            let excess = guard.len() - keep;
+            guard.drain(0..excess);
+            report.trimmed = excess;
+        }
+    }
+    if let Some(store) = events.store() {
+        report.expired = match cutoff {
+            Some(cutoff) => store.prune_older_than(cutoff)?,
+            None => 0,
+        };
+        report.trimmed = match limits.max_events {
+            Some(keep) => store.trim_to(keep)?,
+            None => 0,
+        };
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RunnerEvent;
+    use crate::event_policy::EventPolicy;
+    use crate::event_store::{RunnerEventStore, SqliteRunnerEventStore};
+    use crate::sqlite::SqliteDb;
+    use camino::Utf8PathBuf;
+    use serde_json::Value;
+
+    fn event(timestamp_ms: u64) -> RunnerEvent {
+        RunnerEvent {
+            timestamp_ms,
+            flow: "chat".into(),
+            tenant: Some("acme".into()),
+            team: None,
+            user: None,
+            payload: Value::Null,
+            result: Value::Null,
+            sequence: None,
+            instance: None,
+        }
+    }
+
+    #[test]
+    fn prunes_by_age_then_count_and_keeps_totals() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Utf8PathBuf::from_path_buf(tmp.path().to_path_buf()).unwrap();
+        let store = SqliteRunnerEventStore::new(SqliteDb::open(root, "events.db".into()).unwrap());
+        for timestamp_ms in [1_000, 2_000] {
+            store.append(&event(timestamp_ms)).unwrap();
+        }
+        let events =
+            RunnerEventLog::with_store(EventPolicy::default(), Some(store.clone())).unwrap();
+        for timestamp_ms in [50_000, 60_000, 70_000] {
+            events.write().push(event(timestamp_ms));
+            store.append(&event(timestamp_ms)).unwrap();
+        }
+        let limits = EventRetentionConfig {
+            max_age_secs: Some(30),
+            max_events: Some(2),
+            ..EventRetentionConfig::default()
+        };
+        let retention = EventRetention::default();
+
+        let report = retention.prune(&events, &limits, 75_000).unwrap();
+        assert_eq!(
+            report,
+            PruneReport {
+                expired: 2,
+                trimmed: 1
+            }
+        );
+        let cached: Vec<_> = events.read().iter().map(|e| e.timestamp_ms).collect();
+        assert_eq!(cached, [60_000, 70_000]);
+        assert_eq!(store.recent(10).unwrap().len(), 2);
+
+        let memory_only = RunnerEventLog::default();
+        memory_only.write().push(event(1_000));
+        let report = retention.prune(&memory_only, &limits, 75_000).unwrap();
+        assert_eq!(report.expired, 1);
+        let stats = retention.stats();
+        assert_eq!(stats.passes, 2);
+        assert_eq!((stats.expired_total, stats.trimmed_total), (3, 1));
+        assert_eq!(stats.last_pass_at_ms, Some(75_000));
+    }
+
+    #[test]
+    fn preview_lists_what_a_pass_would_remove_without_removing_it() {
+        let events = RunnerEventLog::default();
+        for timestamp_ms in [1_000, 50_000, 60_000, 70_000] {
+            events.write().push(event(timestamp_ms));
+        }
+        let limits = EventRetentionConfig {
+            max_age_secs: Some(30),
+            max_events: Some(2),
+            ..EventRetentionConfig::default()
+        };
+
+        let doomed: Vec<_> = preview_prune(&events, &limits, 75_000)
+            .unwrap()
+            .iter()
+            .map(|e| e.timestamp_ms)
+            .collect();
+        assert_eq!(doomed, [1_000, 50_000]);
+        assert_eq!(events.read().len(), 4);
+        let report = EventRetention::default()
+            .prune(&events, &limits, 75_000)
+            .unwrap();
+        assert_eq!(report.expired + report.trimmed, doomed.len());
+    }
+}
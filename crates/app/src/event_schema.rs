@@ -0,0 +1,239 @@
+//! Registry of JSON Schemas for typed events (`com.greentic.repo.build.status.v1`), built from
+//! the schemas embedded in the app plus the `event_schemas` packs declare. Events carrying a
+//! registered `type` are validated when `POST /runner/emit` ingests them and before embedded
+//! `events.publish` nodes emit them; `GET /schemas/{type}` serves the contracts.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use anyhow::{Result, anyhow, bail};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Schemas shipped with the app, keyed by event type.
+const BUILTIN_SCHEMAS: &[(&str, &str)] = &[
+    (
+        "com.greentic.repo.build.request.v1",
+        include_str!("../schemas/events/com.greentic.repo.build.request.v1.json"),
+    ),
+    (
+        "com.greentic.repo.build.status.v1",
+        include_str!("../schemas/events/com.greentic.repo.build.status.v1.json"),
+    ),
+];
+
+static BUILTIN: Lazy<SchemaRegistry> = Lazy::new(|| {
+    let mut registry = SchemaRegistry::default();
+    for (event_type, raw) in BUILTIN_SCHEMAS {
+        let schema = serde_json::from_str(raw).expect("embedded event schema is valid JSON");
+        registry
+            .register(event_type, schema, None)
+            .expect("embedded event schema is valid");
+    }
+    registry
+});
+
+/// Registry shared by the HTTP handlers and the embedded runner; swapped on pack reload.
+pub type SharedSchemaRegistry = Arc<RwLock<SchemaRegistry>>;
+
+/// Split a versioned event type into its name and version: `a.b.c.v2` → (`a.b.c`, 2).
+pub fn parse_event_type(event_type: &str) -> Option<(&str, u32)> {
+    let (name, version) = event_type.rsplit_once('.')?;
+    let version = version.strip_prefix('v')?.parse().ok()?;
+    let valid_name = !name.is_empty()
+        && name.split('.').all(|part| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+        });
+    valid_name.then_some((name, version))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EventSchema {
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub name: String,
+    pub version: u32,
+    /// Pack that declared the schema; `None` for schemas embedded in the app.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pack: Option<String>,
+    pub schema: Value,
+}
+
+/// A single schema failure located by JSON pointer into the event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EventViolation {
+    pub pointer: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SchemaRegistry {
+    schemas: BTreeMap<String, EventSchema>,
+}
+
+impl SchemaRegistry {
+    /// Registry holding only the embedded schemas.
+    pub fn builtin() -> Self {
+        BUILTIN.clone()
+    }
+
+    /// Add the schema for `event_type`. Re-registering an identical schema is a no-op; a
+    /// different schema for a type that is already registered is rejected, so a pack cannot
+    /// silently change a published contract (bump the version instead).
+    pub fn register(&mut self, event_type: &str, schema: Value, pack: Option<&str>) -> Result<()> {
+        let (name, version) = parse_event_type(event_type).ok_or_else(|| {
+            anyhow!("event type {event_type} must look like <lowercase.dotted.name>.v<N>")
+        })?;
+        jsonschema::validator_for(&schema)
+            .map_err(|err| anyhow!("invalid schema for event type {event_type}: {err}"))?;
+        if let Some(existing) = self.schemas.get(event_type) {
+            if existing.schema == schema {
+                return Ok(());
+            }
+            bail!(
+                "event type {event_type} is already registered by {} with a different schema",
+                existing.pack.as_deref().unwrap_or("the app")
+            );
+        }
+        self.schemas.insert(
+            event_type.to_string(),
+            EventSchema {
+                event_type: event_type.to_string(),
+                name: name.to_string(),
+                version,
+                pack: pack.map(str::to_string),
+                schema,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn get(&self, event_type: &str) -> Option<&EventSchema> {
+        self.schemas.get(event_type)
+    }
+
+    /// Exact lookup, or the highest registered version when `event_type` has no `.vN` suffix.
+    pub fn resolve(&self, event_type: &str) -> Option<&EventSchema> {
+        self.get(event_type).or_else(|| {
+            self.schemas
+                .values()
+                .filter(|schema| schema.name == event_type)
+                .max_by_key(|schema| schema.version)
+        })
+    }
+
+    pub fn schemas(&self) -> impl Iterator<Item = &EventSchema> {
+        self.schemas.values()
+    }
+
+    /// Validate `event` against the schema of its `type`. Events without a `type`, or with a
+    /// type nobody registered, are not checked and yield no violations.
+    pub fn validate(&self, event: &Value) -> Result<Vec<EventViolation>> {
+        let Some(schema) = event
+            .get("type")
+            .and_then(Value::as_str)
+            .and_then(|event_type| self.get(event_type))
+        else {
+            return Ok(Vec::new());
+        };
+        let validator = jsonschema::validator_for(&schema.schema)
+            .map_err(|err| anyhow!("invalid schema for {}: {err}", schema.event_type))?;
+        Ok(validator
+            .iter_errors(event)
+            .map(|err| EventViolation {
+                pointer: err.instance_path().as_str().to_string(),
+                message: err.to_string(),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn build_status_event() -> Value {
+        json!({
+            "topic": "greentic.repo.build.status",
+            "type": "com.greentic.repo.build.status.v1",
+            "subject": "repo:my-service",
+            "tenant": {"id": "tenant-123"},
+            "payload": {"status": "success", "repo": "my-service", "duration_ms": 10},
+        })
+    }
+
+    #[test]
+    fn parses_versioned_types() {
+        assert_eq!(
+            parse_event_type("com.greentic.repo.build.status.v1"),
+            Some(("com.greentic.repo.build.status", 1))
+        );
+        assert_eq!(parse_event_type("com.greentic.repo.build.status"), None);
+        assert_eq!(parse_event_type("Com.Greentic.v1"), None);
+        assert_eq!(parse_event_type(".v1"), None);
+    }
+
+    #[test]
+    fn validates_builtin_types_and_skips_unknown_ones() {
+        let registry = SchemaRegistry::builtin();
+        assert!(registry.validate(&build_status_event()).unwrap().is_empty());
+
+        let mut broken = build_status_event();
+        broken["payload"]["duration_ms"] = json!("slow");
+        let violations = registry.validate(&broken).unwrap();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].pointer, "/payload/duration_ms");
+
+        assert!(
+            registry
+                .validate(&json!({"type": "com.example.unknown.v1"}))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn resolves_latest_version_and_rejects_conflicts() {
+        let mut registry = SchemaRegistry::builtin();
+        registry
+            .register(
+                "com.example.ping.v1",
+                json!({"type": "object"}),
+                Some("demo"),
+            )
+            .unwrap();
+        registry
+            .register(
+                "com.example.ping.v2",
+                json!({"required": ["id"]}),
+                Some("demo"),
+            )
+            .unwrap();
+        assert_eq!(registry.resolve("com.example.ping").unwrap().version, 2);
+        assert_eq!(registry.resolve("com.example.ping.v1").unwrap().version, 1);
+
+        let err = registry
+            .register(
+                "com.example.ping.v1",
+                json!({"type": "string"}),
+                Some("other"),
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("already registered by demo"));
+        assert!(
+            registry
+                .register(
+                    "com.example.ping.v1",
+                    json!({"type": "object"}),
+                    Some("other")
+                )
+                .is_ok()
+        );
+        assert!(registry.register("ping", json!({}), None).is_err());
+    }
+}
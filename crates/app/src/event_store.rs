@@ -0,0 +1,226 @@
+//! Durable copy of the runner event log (`[stores.events]`). The in-memory log keeps serving
+//! `GET /runner/events`; a configured store also receives every recorded event and seeds the
+//! log on startup, so the recent history survives restarts.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use anyhow::{Context, Result};
+
+use crate::RunnerEvent;
+use crate::sqlite::SqliteDb;
+
+pub trait RunnerEventStore: Send + Sync {
+    fn append(&self, event: &RunnerEvent) -> Result<()>;
+    /// The latest `limit` events, oldest first.
+    fn recent(&self, limit: usize) -> Result<Vec<RunnerEvent>>;
+    fn clear(&self) -> Result<()>;
+    /// Stored events per flow.
+    fn count_by_flow(&self) -> Result<BTreeMap<String, usize>>;
+    /// Drop `tenant`'s events recorded before `cutoff_ms`; returns how many were removed.
+    fn prune(&self, tenant: &str, cutoff_ms: u64) -> Result<usize>;
+    /// Drop every event recorded before `cutoff_ms`, whatever its tenant.
+    fn prune_older_than(&self, cutoff_ms: u64) -> Result<usize>;
+    /// Keep only the latest `keep` events; returns how many were removed.
+    fn trim_to(&self, keep: usize) -> Result<usize>;
+    /// Highest `sequence` stored per key, so numbering continues across restarts.
+    fn last_sequences(&self) -> Result<HashMap<SequenceKey, u64>>;
+}
+
+/// What runner event `sequence` numbers count: one conversation (tenant/team/user) in one flow.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SequenceKey {
+    pub tenant: Option<String>,
+    pub team: Option<String>,
+    pub user: Option<String>,
+    pub flow: String,
+}
+
+impl SequenceKey {
+    pub fn of(event: &RunnerEvent) -> Self {
+        Self {
+            tenant: event.tenant.clone(),
+            team: event.team.clone(),
+            user: event.user.clone(),
+            flow: event.flow.clone(),
+        }
+    }
+}
+
+/// `runner_events` rows of the embedded SQLite database, one JSON event per row.
+pub struct SqliteRunnerEventStore {
+    db: Arc<SqliteDb>,
+}
+
+impl SqliteRunnerEventStore {
+    pub fn new(db: Arc<SqliteDb>) -> Arc<Self> {
+        Arc::new(Self { db })
+    }
+}
+
+impl RunnerEventStore for SqliteRunnerEventStore {
+    fn append(&self, event: &RunnerEvent) -> Result<()> {
+        self.db
+            .conn()
+            .prepare_cached(
+                "INSERT INTO runner_events (timestamp_ms, flow, tenant, event)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?
+            .execute(rusqlite::params![
+                event.timestamp_ms as i64,
+                event.flow,
+                event.tenant,
+                serde_json::to_string(event)?,
+            ])
+            .context("failed to append runner event")?;
+        Ok(())
+    }
+
+    fn recent(&self, limit: usize) -> Result<Vec<RunnerEvent>> {
+        let conn = self.db.conn();
+        let mut stmt = conn.prepare_cached(
+            "SELECT event FROM (SELECT id, event FROM runner_events ORDER BY id DESC LIMIT ?1)
+             ORDER BY id",
+        )?;
+        let rows = stmt.query_map([limit as i64], |row| row.get::<_, String>(0))?;
+        rows.map(|json| {
+            serde_json::from_str(&json?).context("invalid runner event JSON in sqlite store")
+        })
+        .collect()
+    }
+
+    fn clear(&self) -> Result<()> {
+        self.db
+            .conn()
+            .execute("DELETE FROM runner_events", [])
+            .context("failed to clear runner events")?;
+        Ok(())
+    }
+
+    fn count_by_flow(&self) -> Result<BTreeMap<String, usize>> {
+        let conn = self.db.conn();
+        let mut stmt =
+            conn.prepare_cached("SELECT flow, COUNT(*) FROM runner_events GROUP BY flow")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize))
+        })?;
+        rows.collect::<rusqlite::Result<_>>()
+            .context("failed to count runner events")
+    }
+
+    fn prune(&self, tenant: &str, cutoff_ms: u64) -> Result<usize> {
+        self.db
+            .conn()
+            .execute(
+                "DELETE FROM runner_events WHERE tenant = ?1 AND timestamp_ms < ?2",
+                rusqlite::params![tenant, cutoff_ms as i64],
+            )
+            .context("failed to prune runner events")
+    }
+
+    fn prune_older_than(&self, cutoff_ms: u64) -> Result<usize> {
+        self.db
+            .conn()
+            .execute(
+                "DELETE FROM runner_events WHERE timestamp_ms < ?1",
+                [cutoff_ms as i64],
+            )
+            .context("failed to prune runner events")
+    }
+
+    fn trim_to(&self, keep: usize) -> Result<usize> {
+        self.db
+            .conn()
+            .execute(
+                "DELETE FROM runner_events WHERE id <=
+                 (SELECT id FROM runner_events ORDER BY id DESC LIMIT 1 OFFSET ?1)",
+                [keep as i64],
+            )
+            .context("failed to trim runner events")
+    }
+
+    fn last_sequences(&self) -> Result<HashMap<SequenceKey, u64>> {
+        let conn = self.db.conn();
+        let mut stmt = conn.prepare_cached(
+            "SELECT json_extract(event, '$.tenant'), json_extract(event, '$.team'),
+                    json_extract(event, '$.user'), flow, MAX(json_extract(event, '$.sequence'))
+             FROM runner_events WHERE json_extract(event, '$.sequence') IS NOT NULL
+             GROUP BY 1, 2, 3, 4",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let key = SequenceKey {
+                tenant: row.get(0)?,
+                team: row.get(1)?,
+                user: row.get(2)?,
+                flow: row.get(3)?,
+            };
+            Ok((key, row.get::<_, i64>(4)? as u64))
+        })?;
+        rows.collect::<rusqlite::Result<_>>()
+            .context("failed to read runner event sequences")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use camino::Utf8PathBuf;
+    use serde_json::Value;
+
+    fn event(timestamp_ms: u64, tenant: &str) -> RunnerEvent {
+        RunnerEvent {
+            timestamp_ms,
+            flow: "chat".into(),
+            tenant: Some(tenant.into()),
+            team: None,
+            user: None,
+            payload: Value::Null,
+            result: Value::Null,
+            sequence: None,
+            instance: None,
+        }
+    }
+
+    #[test]
+    fn sqlite_store_keeps_order_and_prunes_by_tenant() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Utf8PathBuf::from_path_buf(tmp.path().to_path_buf()).unwrap();
+        let store = SqliteRunnerEventStore::new(SqliteDb::open(root, "events.db".into()).unwrap());
+        for (timestamp_ms, tenant) in [(1, "acme"), (2, "globex"), (3, "acme"), (4, "acme")] {
+            store.append(&event(timestamp_ms, tenant)).unwrap();
+        }
+        let latest: Vec<_> = store
+            .recent(2)
+            .unwrap()
+            .iter()
+            .map(|event| event.timestamp_ms)
+            .collect();
+        assert_eq!(latest, vec![3, 4]);
+
+        let mut sequenced = event(5, "acme");
+        for sequence in [3, 7] {
+            sequenced.sequence = Some(sequence);
+            store.append(&sequenced).unwrap();
+        }
+        let last = store.last_sequences().unwrap();
+        assert_eq!(last.len(), 1);
+        assert_eq!(last[&SequenceKey::of(&sequenced)], 7);
+
+        assert_eq!(store.prune("acme", 4).unwrap(), 2);
+        assert_eq!(store.recent(10).unwrap().len(), 4);
+        assert_eq!(store.prune_older_than(3).unwrap(), 1);
+        assert_eq!(store.trim_to(2).unwrap(), 1);
+        assert_eq!(store.trim_to(2).unwrap(), 0);
+        let kept: Vec<_> = store
+            .recent(10)
+            .unwrap()
+            .iter()
+            .map(|event| event.sequence)
+            .collect();
+        assert_eq!(kept, vec![Some(3), Some(7)]);
+        store.clear().unwrap();
+        assert!(store.recent(10).unwrap().is_empty());
+    }
+}
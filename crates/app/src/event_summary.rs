@@ -0,0 +1,279 @@
+//! Time-window aggregation of cached runner events for `GET /runner/events/summary`, so
+//! dashboards get counts, error rates and latency percentiles without pulling raw events.
+
+use std::{collections::BTreeMap, time::Duration};
+
+use anyhow::{Result, anyhow, bail};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::RunnerEvent;
+
+/// Event fields a summary can be grouped by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupKey {
+    Flow,
+    Tenant,
+    Team,
+    User,
+    /// `result.status`.
+    Status,
+}
+
+impl GroupKey {
+    fn parse(raw: &str) -> Result<Self> {
+        Ok(match raw {
+            "flow" => GroupKey::Flow,
+            "tenant" => GroupKey::Tenant,
+            "team" => GroupKey::Team,
+            "user" => GroupKey::User,
+            "status" => GroupKey::Status,
+            other => bail!("cannot group by {other} (expected flow, tenant, team, user or status)"),
+        })
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            GroupKey::Flow => "flow",
+            GroupKey::Tenant => "tenant",
+            GroupKey::Team => "team",
+            GroupKey::User => "user",
+            GroupKey::Status => "status",
+        }
+    }
+
+    fn value_of(self, event: &RunnerEvent) -> Option<String> {
+        match self {
+            GroupKey::Flow => Some(event.flow.clone()),
+            GroupKey::Tenant => event.tenant.clone(),
+            GroupKey::Team => event.team.clone(),
+            GroupKey::User => event.user.clone(),
+            GroupKey::Status => result_status(event).map(str::to_string),
+        }
+    }
+}
+
+/// Comma-separated group keys (`flow,tenant`); duplicates are dropped, order is kept.
+pub fn parse_group_by(raw: &str) -> Result<Vec<GroupKey>> {
+    let mut keys = Vec::new();
+    for part in raw
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+    {
+        let key = GroupKey::parse(part)?;
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    Ok(keys)
+}
+
+/// `30s`, `15m`, `1h`, `7d`; a bare number counts as seconds.
+pub fn parse_window(raw: &str) -> Result<Duration> {
+    let raw = raw.trim();
+    let split = raw.find(|c: char| !c.is_ascii_digit()).unwrap_or(raw.len());
+    let (amount, unit) = raw.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| anyhow!("invalid window {raw:?} (expected e.g. 15m, 1h, 7d)"))?;
+    let unit_secs = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        other => bail!("invalid window unit {other:?} in {raw:?} (expected s, m, h or d)"),
+    };
+    if amount == 0 {
+        bail!("window must be positive");
+    }
+    Ok(Duration::from_secs(amount.saturating_mul(unit_secs)))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EventSummary {
+    /// Start of the window (inclusive); `None` when every cached event was considered.
+    pub from_ms: Option<u64>,
+    pub to_ms: u64,
+    pub group_by: Vec<GroupKey>,
+    pub total: usize,
+    pub groups: Vec<GroupSummary>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GroupSummary {
+    /// Value of each `group_by` field for this group (`null` when the event has none).
+    pub key: BTreeMap<&'static str, Option<String>>,
+    pub count: usize,
+    /// Events whose `result.status` is present and not `ok`.
+    pub errors: usize,
+    pub error_rate: f64,
+    /// Present when at least one event in the group carries `result.duration_ms`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<Latency>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Latency {
+    pub samples: usize,
+    pub p50: u64,
+    pub p95: u64,
+    pub max: u64,
+}
+
+/// Aggregate the events that fall in `window` before `now_ms` (all of them without a
+/// window), one group per distinct combination of `group_by` values.
+pub fn summarize(
+    events: &[RunnerEvent],
+    now_ms: u64,
+    window: Option<Duration>,
+    group_by: &[GroupKey],
+) -> EventSummary {
+    let from_ms = window.map(|window| now_ms.saturating_sub(window.as_millis() as u64));
+    let mut groups: BTreeMap<Vec<Option<String>>, (usize, usize, Vec<u64>)> = BTreeMap::new();
+    let mut total = 0;
+    for event in events
+        .iter()
+        .filter(|event| from_ms.is_none_or(|from| event.timestamp_ms >= from))
+    {
+        total += 1;
+        let key = group_by.iter().map(|key| key.value_of(event)).collect();
+        let (count, errors, durations) = groups.entry(key).or_default();
+        *count += 1;
+        if result_status(event).is_some_and(|status| status != "ok") {
+            *errors += 1;
+        }
+        if let Some(duration) = event.result.get("duration_ms").and_then(Value::as_u64) {
+            durations.push(duration);
+        }
+    }
+
+    let groups = groups
+        .into_iter()
+        .map(|(values, (count, errors, mut durations))| GroupSummary {
+            key: group_by
+                .iter()
+                .map(|key| key.as_str())
+                .zip(values)
+                .collect(),
+            count,
+            errors,
+            error_rate: errors as f64 / count as f64,
+            latency_ms: latency(&mut durations),
+        })
+        .collect();
+    EventSummary {
+        from_ms,
+        to_ms: now_ms,
+        group_by: group_by.to_vec(),
+        total,
+        groups,
+    }
+}
+
+fn result_status(event: &RunnerEvent) -> Option<&str> {
+    event.result.get("status").and_then(Value::as_str)
+}
+
+/// Nearest-rank percentiles.
+fn latency(durations: &mut [u64]) -> Option<Latency> {
+    if durations.is_empty() {
+        return None;
+    }
+    durations.sort_unstable();
+    let rank = |pct: usize| durations[(durations.len() * pct).div_ceil(100).max(1) - 1];
+    Some(Latency {
+        samples: durations.len(),
+        p50: rank(50),
+        p95: rank(95),
+        max: durations[durations.len() - 1],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn event(timestamp_ms: u64, flow: &str, tenant: &str, result: Value) -> RunnerEvent {
+        RunnerEvent {
+            timestamp_ms,
+            flow: flow.into(),
+            tenant: Some(tenant.into()),
+            team: None,
+            user: None,
+            payload: Value::Null,
+            result,
+            sequence: None,
+            instance: None,
+        }
+    }
+
+    #[test]
+    fn parses_windows_and_group_keys() {
+        assert_eq!(parse_window("1h").unwrap(), Duration::from_secs(3600));
+        assert_eq!(parse_window("90").unwrap(), Duration::from_secs(90));
+        assert!(parse_window("1w").is_err());
+        assert!(parse_window("0m").is_err());
+        assert_eq!(
+            parse_group_by("flow, tenant,flow").unwrap(),
+            vec![GroupKey::Flow, GroupKey::Tenant]
+        );
+        assert!(parse_group_by("flow,node").is_err());
+    }
+
+    #[test]
+    fn groups_counts_errors_and_latency_inside_the_window() {
+        let now = 10_000_000;
+        let mut events: Vec<RunnerEvent> = (1..=20)
+            .map(|ms| {
+                event(
+                    now - 1_000,
+                    "build",
+                    "acme",
+                    json!({"status": "ok", "duration_ms": ms * 10}),
+                )
+            })
+            .collect();
+        events.push(event(
+            now - 500,
+            "build",
+            "acme",
+            json!({"status": "error"}),
+        ));
+        events.push(event(now - 200, "chat", "globex", json!({"echo": {}})));
+        events.push(event(
+            now - 7_200_000,
+            "build",
+            "acme",
+            json!({"status": "error"}),
+        ));
+
+        let summary = summarize(
+            &events,
+            now,
+            Some(Duration::from_secs(3600)),
+            &[GroupKey::Flow, GroupKey::Tenant],
+        );
+        assert_eq!(summary.total, 22);
+        assert_eq!(summary.from_ms, Some(now - 3_600_000));
+        let build = &summary.groups[0];
+        assert_eq!(build.key["flow"].as_deref(), Some("build"));
+        assert_eq!(build.count, 21);
+        assert_eq!(build.errors, 1);
+        let latency = build.latency_ms.as_ref().unwrap();
+        assert_eq!((latency.samples, latency.p50, latency.p95), (20, 100, 190));
+        assert_eq!(latency.max, 200);
+
+        let chat = &summary.groups[1];
+        assert_eq!(chat.key["tenant"].as_deref(), Some("globex"));
+        assert_eq!(chat.error_rate, 0.0);
+        assert!(chat.latency_ms.is_none());
+
+        let all = summarize(&events, now, None, &[]);
+        assert_eq!(all.total, 23);
+        assert_eq!(all.groups.len(), 1);
+        assert_eq!(all.groups[0].errors, 2);
+    }
+}
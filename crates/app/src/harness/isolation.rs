@@ -0,0 +1,163 @@
+use std::{fs, path::Path, process::Command};
+
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+
+use super::manifest::ComposeService;
+
+/// Name of the internal network in the generated compose override. Compose prefixes it with
+/// the project name, so every `TestEnv` gets its own.
+const NETWORK: &str = "hermetic";
+
+/// Lines in a service's logs that mean it tried to reach something outside the hermetic
+/// network. On an internal network there is no default route and Docker's DNS only knows the
+/// project's services, so an unexpected outbound call fails with one of these.
+const EGRESS_SIGNATURES: &[&str] = &[
+    "network is unreachable",
+    "enetunreach",
+    "no route to host",
+    "ehostunreach",
+    "temporary failure in name resolution",
+    "eai_again",
+    "name or service not known",
+    "no such host",
+    "could not resolve host",
+];
+
+/// Compose override that moves `services` onto a dedicated `internal: true` network and drops
+/// their published ports (Docker cannot publish ports from an internal network). The harness
+/// reaches the containers by their bridge IPs instead, which only works on Linux.
+pub fn compose_override(services: &[ComposeService]) -> String {
+    let mut yaml = format!("networks:\n  {NETWORK}:\n    internal: true\n\nservices:\n");
+    for service in services {
+        yaml.push_str(&format!(
+            "  {}:\n    networks: [{NETWORK}]\n    ports: !reset []\n",
+            service.compose_name()
+        ));
+    }
+    yaml
+}
+
+/// Full Docker name of the hermetic network for a compose project.
+pub fn network_name(project: &str) -> String {
+    format!("{project}_{NETWORK}")
+}
+
+/// Port a service listens on inside its container.
+pub fn container_port(service: ComposeService) -> u16 {
+    match service {
+        ComposeService::Nats => 4222,
+        ComposeService::Postgres => 5432,
+    }
+}
+
+/// IP of `service`'s container on the hermetic network. `compose` is the env's
+/// `docker compose -f ...` command, without a subcommand.
+pub fn container_ip(mut compose: Command, project: &str, service: &str) -> Result<String> {
+    let id = docker_stdout(compose.args(["ps", "-q", service]))
+        .with_context(|| format!("failed to find the {service} container"))?;
+    let id = id.trim();
+    if id.is_empty() {
+        bail!("no running container for {service}");
+    }
+    let format = format!(
+        "{{{{with index .NetworkSettings.Networks \"{}\"}}}}{{{{.IPAddress}}}}{{{{end}}}}",
+        network_name(project)
+    );
+    let ip = docker_stdout(Command::new("docker").args(["inspect", "-f", &format, id]))
+        .with_context(|| format!("failed to inspect the {service} container"))?;
+    let ip = ip.trim();
+    if ip.is_empty() {
+        bail!("{service} is not attached to {}", network_name(project));
+    }
+    Ok(ip.to_string())
+}
+
+/// An outbound call a component made (and had refused) while running on the hermetic network.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EgressViolation {
+    pub service: String,
+    pub line: String,
+}
+
+/// Pick the egress failures out of `docker compose logs` output, whose lines are prefixed
+/// with `<service>-<n>  | `.
+pub fn scan_logs(logs: &str) -> Vec<EgressViolation> {
+    logs.lines()
+        .filter(|line| {
+            let lower = line.to_ascii_lowercase();
+            EGRESS_SIGNATURES.iter().any(|sig| lower.contains(sig))
+        })
+        .map(|line| {
+            let (prefix, message) = line.split_once('|').unwrap_or(("", line));
+            let service = prefix.trim();
+            let service = service
+                .rsplit_once('-')
+                .filter(|(_, n)| n.chars().all(|c| c.is_ascii_digit()))
+                .map_or(service, |(name, _)| name);
+            EgressViolation {
+                service: service.to_string(),
+                line: message.trim().to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Write `artifacts/egress_violations.json`; an empty list is written too, so a clean
+/// hermetic run is distinguishable from one that never checked.
+pub fn write_report(artifacts_dir: &Path, violations: &[EgressViolation]) -> Result<()> {
+    let path = artifacts_dir.join("egress_violations.json");
+    let data = serde_json::to_vec_pretty(violations).context("failed to serialize JSON")?;
+    fs::write(&path, data).with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(())
+}
+
+fn docker_stdout(command: &mut Command) -> Result<String> {
+    let output = command.output().context("failed to execute docker")?;
+    if !output.status.success() {
+        bail!(
+            "docker failed (code {:?}): {}",
+            output.status.code(),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn override_moves_services_onto_the_internal_network() {
+        let yaml = compose_override(&[ComposeService::Nats, ComposeService::Postgres]);
+        assert!(yaml.starts_with("networks:\n  hermetic:\n    internal: true\n"));
+        assert!(yaml.contains("  nats:\n    networks: [hermetic]\n    ports: !reset []\n"));
+        assert!(yaml.contains("  postgres:\n    networks: [hermetic]\n    ports: !reset []\n"));
+    }
+
+    #[test]
+    fn scan_attributes_egress_failures_to_their_service() {
+        let logs = "\
+nats-1      | [1] Server is ready
+postgres-1  | LOG:  database system is ready to accept connections
+nats-1      | [1] Error trying to connect to route: dial tcp 203.0.113.7:6222: connect: network is unreachable
+postgres-1  | could not resolve host \"telemetry.example.com\"
+";
+        assert_eq!(
+            scan_logs(logs),
+            [
+                EgressViolation {
+                    service: "nats".into(),
+                    line: "[1] Error trying to connect to route: dial tcp 203.0.113.7:6222: \
+                           connect: network is unreachable"
+                        .into(),
+                },
+                EgressViolation {
+                    service: "postgres".into(),
+                    line: "could not resolve host \"telemetry.example.com\"".into(),
+                },
+            ]
+        );
+    }
+}
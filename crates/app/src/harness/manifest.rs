@@ -0,0 +1,151 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+
+/// Declarative `env.e2e.toml`: what a test or suite needs provisioned before it runs.
+/// Consumed by [`TestEnv::from_manifest`](super::TestEnv::from_manifest).
+///
+/// ```toml
+/// name = "e2e_pack_lifecycle"
+/// services = ["nats"]          # compose services to start (default: all)
+/// stack = ["runner"]           # Greentic stack components to boot
+/// hermetic = true              # isolated network, no egress (Linux only)
+///
+/// [[packs]]
+/// fixture = "fixtures/packs/hello"   # relative to the workspace root
+/// target = "dev"
+///
+/// [[tenants]]
+/// id = "tenant-a"
+/// secrets = { API_TOKEN = "secret-A" }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EnvManifest {
+    /// Directory name under `target/e2e/`; defaults to `E2E_TEST_NAME` or the test thread name.
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default = "ComposeService::all")]
+    pub services: Vec<ComposeService>,
+    #[serde(default)]
+    pub stack: Vec<StackComponent>,
+    /// Run the services on an internal network with no egress; see
+    /// [`TestEnv::up_hermetic`](super::TestEnv::up_hermetic).
+    #[serde(default)]
+    pub hermetic: bool,
+    #[serde(default)]
+    pub packs: Vec<PackPreload>,
+    #[serde(default)]
+    pub tenants: Vec<TenantSeed>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ComposeService {
+    Nats,
+    Postgres,
+}
+
+impl ComposeService {
+    pub fn all() -> Vec<Self> {
+        vec![Self::Nats, Self::Postgres]
+    }
+
+    /// Service name in `tests/compose/compose.e2e.yml`.
+    pub fn compose_name(self) -> &'static str {
+        match self {
+            Self::Nats => "nats",
+            Self::Postgres => "postgres",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StackComponent {
+    Runner,
+}
+
+/// Pack fixture built, verified and installed before the test body runs.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PackPreload {
+    pub fixture: PathBuf,
+    #[serde(default = "default_pack_target")]
+    pub target: String,
+    #[serde(default = "default_true")]
+    pub verify: bool,
+}
+
+impl PackPreload {
+    /// Fixture directory name, used to keep per-pack artifacts apart.
+    pub fn id(&self) -> String {
+        self.fixture
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "pack".into())
+    }
+}
+
+/// Tenant whose secrets are written to `artifacts/tenants/<id>/secrets.json`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TenantSeed {
+    pub id: String,
+    #[serde(default)]
+    pub secrets: BTreeMap<String, String>,
+}
+
+impl EnvManifest {
+    pub fn load(path: &Path) -> Result<Self> {
+        let raw = fs::read_to_string(path)
+            .with_context(|| format!("failed to read env manifest {}", path.display()))?;
+        let manifest: Self = toml::from_str(&raw)
+            .with_context(|| format!("invalid env manifest {}", path.display()))?;
+        manifest
+            .validate()
+            .with_context(|| format!("invalid env manifest {}", path.display()))?;
+        Ok(manifest)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.services.is_empty() {
+            bail!("services must list at least one compose service");
+        }
+        let mut tenants = BTreeSet::new();
+        for tenant in &self.tenants {
+            if tenant.id.trim().is_empty() || tenant.id.contains(['/', '\\', '.']) {
+                bail!("invalid tenant id {:?}", tenant.id);
+            }
+            if !tenants.insert(tenant.id.as_str()) {
+                bail!("tenant {} declared twice", tenant.id);
+            }
+        }
+        let mut packs = BTreeSet::new();
+        for pack in &self.packs {
+            if pack.fixture.is_absolute() {
+                bail!(
+                    "pack fixture {} must be relative to the workspace root",
+                    pack.fixture.display()
+                );
+            }
+            if !packs.insert(pack.id()) {
+                bail!("pack fixture {} declared twice", pack.fixture.display());
+            }
+        }
+        Ok(())
+    }
+}
+
+fn default_pack_target() -> String {
+    "dev".into()
+}
+
+fn default_true() -> bool {
+    true
+}
@@ -0,0 +1,562 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tokio::{sync::oneshot, task::JoinHandle, time::timeout};
+
+use providers_sim::phone::{PhoneChannel, Segment, SmsEncoding, render_outbound};
+
+use super::TestEnv;
+use crate::testkit::{ProviderSink, SinkResponse};
+use crate::trace_context::{TRACEPARENT, TraceContext};
+
+/// One messaging/provider E2E case: a flow behaviour, the inbound messages published to it over
+/// NATS, how the stub provider sink answers, and what the sink must capture.
+///
+/// ```yaml
+/// name: reply_thread
+/// behavior: { kind: thread_continuity, provider: stub-provider }
+/// inbound:
+///   - { text: ping, thread_id: thread-123, reply_to: msg-999 }
+/// sink: ok                     # or { delayed_ms: 1500 } / { status: 500 }
+/// expect:
+///   outbound:
+///     - thread_id: thread-123
+///       card.body: { contains: { id: preference } }
+///       text: { repeat: x, times: 8192 }
+/// ```
+///
+/// Each `outbound` entry matches the captured payload at the same position; keys are
+/// dot-separated paths (array indices as numbers). A plain value must be equal, `contains`
+/// accepts an array with a matching element or a string with that substring, `present` checks
+/// the path exists (or not) and `repeat` expects `times` copies of a string. `expect.error: true`
+/// requires the case to fail instead, e.g. against an erroring sink.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MessagingCase {
+    pub name: String,
+    pub behavior: FlowBehavior,
+    pub inbound: Vec<CaseInbound>,
+    #[serde(default)]
+    pub sink: SinkMode,
+    #[serde(default)]
+    pub expect: CaseExpectation,
+    #[serde(skip)]
+    pub source: PathBuf,
+}
+
+/// Inbound message as written in a case; `text` may be generated.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CaseInbound {
+    #[serde(default)]
+    pub text: Option<TextSpec>,
+    #[serde(default)]
+    pub thread_id: Option<String>,
+    #[serde(default)]
+    pub reply_to: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum TextSpec {
+    Literal(String),
+    Repeat { repeat: String, times: usize },
+}
+
+impl TextSpec {
+    pub fn render(&self) -> String {
+        match self {
+            TextSpec::Literal(text) => text.clone(),
+            TextSpec::Repeat { repeat, times } => repeat.repeat(*times),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SinkMode {
+    #[default]
+    Ok,
+    DelayedMs(u64),
+    Status(u16),
+}
+
+impl From<SinkMode> for SinkResponse {
+    fn from(mode: SinkMode) -> Self {
+        match mode {
+            SinkMode::Ok => SinkResponse::Ok,
+            SinkMode::DelayedMs(ms) => SinkResponse::Delayed(Duration::from_millis(ms)),
+            SinkMode::Status(status) => SinkResponse::Status(status),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CaseExpectation {
+    #[serde(default)]
+    pub error: bool,
+    #[serde(default)]
+    pub outbound: Vec<BTreeMap<String, Matcher>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Matcher {
+    Repeat { repeat: String, times: usize },
+    Contains { contains: Value },
+    Present { present: bool },
+    Equals(Value),
+}
+
+impl Matcher {
+    fn check(&self, actual: Option<&Value>) -> Result<()> {
+        match (self, actual) {
+            (Matcher::Present { present }, actual) => {
+                if actual.is_some() != *present {
+                    bail!("expected present: {present}, got {actual:?}");
+                }
+            }
+            (Matcher::Repeat { repeat, times }, Some(Value::String(text)))
+                if *text == repeat.repeat(*times) => {}
+            (Matcher::Repeat { repeat, times }, actual) => {
+                let len = actual.and_then(Value::as_str).map(str::len);
+                bail!("expected {times} x {repeat:?}, got a value of length {len:?}");
+            }
+            (Matcher::Contains { contains }, Some(Value::Array(items)))
+                if items.iter().any(|item| is_subset(contains, item)) => {}
+            (
+                Matcher::Contains {
+                    contains: Value::String(needle),
+                },
+                Some(Value::String(text)),
+            ) if text.contains(needle.as_str()) => {}
+            (Matcher::Contains { contains }, actual) => {
+                bail!("expected a value containing {contains}, got {actual:?}")
+            }
+            (Matcher::Equals(expected), Some(actual)) if expected == actual => {}
+            (Matcher::Equals(expected), actual) => bail!("expected {expected}, got {actual:?}"),
+        }
+        Ok(())
+    }
+}
+
+/// Every key of `expected` is in `actual` with a matching value; other values must be equal.
+fn is_subset(expected: &Value, actual: &Value) -> bool {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => expected
+            .iter()
+            .all(|(key, value)| actual.get(key).is_some_and(|found| is_subset(value, found))),
+        _ => expected == actual,
+    }
+}
+
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(value, |node, segment| match node {
+        Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+        _ => node.get(segment),
+    })
+}
+
+/// Load every `*.yaml` case under `dir`, sorted by file name.
+pub fn load_messaging_cases(dir: impl AsRef<Path>) -> Result<Vec<MessagingCase>> {
+    let dir = dir.as_ref();
+    let mut paths = fs::read_dir(dir)
+        .with_context(|| format!("failed to read messaging cases in {}", dir.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.retain(|path| path.extension().is_some_and(|ext| ext == "yaml"));
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let raw = fs::read_to_string(&path)
+                .with_context(|| format!("failed to read messaging case {}", path.display()))?;
+            let mut case: MessagingCase = serde_yaml_bw::from_str(&raw)
+                .with_context(|| format!("invalid messaging case {}", path.display()))?;
+            if case.inbound.is_empty() {
+                bail!("messaging case {} has no inbound messages", path.display());
+            }
+            if !case.expect.error && case.expect.outbound.len() != case.outbound_count() {
+                bail!(
+                    "messaging case {} expects {} outbound payloads, but its behavior sends {} for {} inbound messages",
+                    path.display(),
+                    case.expect.outbound.len(),
+                    case.outbound_count(),
+                    case.inbound.len()
+                );
+            }
+            case.source = path;
+            Ok(case)
+        })
+        .collect()
+}
+
+impl MessagingCase {
+    pub fn inbound_messages(&self) -> Vec<InboundMessage> {
+        self.inbound
+            .iter()
+            .map(|inbound| InboundMessage {
+                text: inbound.text.as_ref().map(TextSpec::render),
+                thread_id: inbound.thread_id.clone(),
+                reply_to: inbound.reply_to.clone(),
+            })
+            .collect()
+    }
+
+    /// What the flow worker would send for this case, without NATS or a sink.
+    pub fn simulate(&self) -> Result<Vec<Value>> {
+        self.inbound_messages()
+            .into_iter()
+            .flat_map(|inbound| self.behavior.apply(inbound))
+            .map(|outbound| Ok(serde_json::to_value(outbound)?))
+            .collect()
+    }
+
+    /// How many payloads the sink receives for the whole case.
+    pub fn outbound_count(&self) -> usize {
+        self.inbound_messages()
+            .into_iter()
+            .map(|inbound| self.behavior.apply(inbound).len())
+            .sum()
+    }
+
+    /// Check captured payloads against `expect.outbound`. Every payload must also name its
+    /// provider and carry either a card or text.
+    pub fn check(&self, captured: &[Value]) -> Result<()> {
+        if captured.len() != self.expect.outbound.len() {
+            bail!(
+                "{}: expected {} outbound payloads, captured {}",
+                self.name,
+                self.expect.outbound.len(),
+                captured.len()
+            );
+        }
+        for (idx, (payload, matchers)) in captured.iter().zip(&self.expect.outbound).enumerate() {
+            if payload.get("provider").is_none_or(Value::is_null) {
+                bail!("{} outbound {idx}: missing provider field", self.name);
+            }
+            if payload.get("card").is_none_or(Value::is_null)
+                && payload.get("text").is_none_or(Value::is_null)
+            {
+                bail!("{} outbound {idx}: neither card nor text", self.name);
+            }
+            for (path, matcher) in matchers {
+                matcher
+                    .check(lookup(payload, path))
+                    .with_context(|| format!("{} outbound {idx} at {path}", self.name))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Run the case against `env`'s NATS: spawn a flow worker on `e2e.messaging.<name>`, point
+    /// it at a provider sink answering per `sink`, publish the inbound messages and check what
+    /// the sink captured. Artifacts go to `provider-e2e/<name>/outbound.json`.
+    pub async fn run(&self, env: &TestEnv) -> Result<Vec<Value>> {
+        let result = self.deliver(env).await;
+        match (result, self.expect.error) {
+            (Ok(captured), false) => {
+                self.check(&captured)?;
+                Ok(captured)
+            }
+            (Ok(captured), true) => bail!(
+                "{}: expected the case to fail, but the sink captured {captured:?}",
+                self.name
+            ),
+            (Err(_), true) => Ok(Vec::new()),
+            (Err(err), false) => Err(err.context(format!("messaging case {}", self.name))),
+        }
+    }
+
+    async fn deliver(&self, env: &TestEnv) -> Result<Vec<Value>> {
+        let artifacts = env.artifacts_dir().join("provider-e2e").join(&self.name);
+        let sink =
+            ProviderSink::start_with(artifacts.join("outbound.json"), self.sink.into()).await?;
+
+        let inbound = self.inbound_messages();
+        let subject = format!("e2e.messaging.{}", self.name);
+        let mut worker = FlowWorker::spawn(
+            env.nats_url(),
+            subject.clone(),
+            sink.send_url(),
+            self.behavior.clone(),
+            inbound.len(),
+        );
+        worker.wait_ready(Duration::from_secs(5)).await?;
+        for msg in &inbound {
+            publish(env.nats_url(), &subject, msg).await?;
+        }
+        worker.wait().await?;
+
+        let captured = sink
+            .wait_for(self.outbound_count(), Duration::from_secs(8))
+            .await?;
+        sink.shutdown().await?;
+        Ok(captured)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct InboundMessage {
+    pub text: Option<String>,
+    pub thread_id: Option<String>,
+    pub reply_to: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OutboundPayload {
+    pub provider: String,
+    pub text: Option<String>,
+    pub thread_id: Option<String>,
+    pub reply_to: Option<String>,
+    pub card: Option<Value>,
+    /// Part of a send split up by a phone channel.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub segment: Option<Segment>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<SmsEncoding>,
+    /// What a phone channel could not carry as sent (`card`, `media`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub downgraded: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CardKind {
+    Basic,
+    Inputs,
+}
+
+/// What the stand-in flow worker does with each inbound message before posting it to the sink.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum FlowBehavior {
+    /// Uppercase the text.
+    Uppercase { provider: String },
+    /// Pass text, thread and reply ids through unchanged.
+    ThreadContinuity { provider: String },
+    /// Attach an adaptive card.
+    Card { provider: String, card: CardKind },
+    /// Attach a card with an `Action.OpenUrl`, for provider translation checks.
+    ProviderSmoke { provider: String },
+    /// Send the text, plus a card when set, through the simulated SMS or WhatsApp channel:
+    /// one payload per segment, with the card downgraded to text.
+    PhoneSend {
+        channel: PhoneChannel,
+        #[serde(default)]
+        card: Option<CardKind>,
+    },
+}
+
+impl FlowBehavior {
+    /// The payloads posted to the sink for `inbound`; only phone channels send more than one.
+    pub fn apply(&self, inbound: InboundMessage) -> Vec<OutboundPayload> {
+        let (provider, card) = match self {
+            FlowBehavior::Uppercase { provider } => {
+                return vec![OutboundPayload {
+                    provider: provider.clone(),
+                    text: inbound.text.map(|t| t.to_ascii_uppercase()),
+                    thread_id: inbound.thread_id,
+                    reply_to: inbound.reply_to,
+                    card: None,
+                    segment: None,
+                    encoding: None,
+                    downgraded: Vec::new(),
+                }];
+            }
+            FlowBehavior::PhoneSend { channel, card } => {
+                let card = card.map(CardKind::card);
+                return render_outbound(*channel, inbound.text.as_deref(), card.as_ref(), &[])
+                    .into_iter()
+                    .map(|message| OutboundPayload {
+                        provider: channel.provider().to_string(),
+                        text: message.text,
+                        thread_id: inbound.thread_id.clone(),
+                        reply_to: inbound.reply_to.clone(),
+                        card: None,
+                        segment: message.segment,
+                        encoding: message.encoding,
+                        downgraded: message.downgraded,
+                    })
+                    .collect();
+            }
+            FlowBehavior::ThreadContinuity { provider } => (provider, None),
+            FlowBehavior::Card { provider, card } => (provider, Some(card.card())),
+            FlowBehavior::ProviderSmoke { provider } => (provider, Some(provider_smoke_card())),
+        };
+        vec![OutboundPayload {
+            provider: provider.clone(),
+            text: inbound.text,
+            thread_id: inbound.thread_id,
+            reply_to: inbound.reply_to,
+            card,
+            segment: None,
+            encoding: None,
+            downgraded: Vec::new(),
+        }]
+    }
+}
+
+impl CardKind {
+    fn card(self) -> Value {
+        match self {
+            CardKind::Basic => basic_card(),
+            CardKind::Inputs => inputs_card(),
+        }
+    }
+}
+
+fn basic_card() -> Value {
+    json!({
+        "type": "AdaptiveCard",
+        "version": "1.5",
+        "body": [
+            { "type": "TextBlock", "size": "Medium", "weight": "Bolder", "text": "Here is a basic card" },
+            { "type": "TextBlock", "wrap": true, "text": "Static content to validate payload preservation." }
+        ]
+    })
+}
+
+fn inputs_card() -> Value {
+    json!({
+        "type": "AdaptiveCard",
+        "version": "1.5",
+        "body": [
+            { "type": "TextBlock", "text": "Pick a preference", "wrap": true },
+            {
+                "type": "Input.ChoiceSet",
+                "id": "preference",
+                "style": "expanded",
+                "choices": [
+                    { "title": "Email", "value": "email" },
+                    { "title": "SMS", "value": "sms" }
+                ]
+            },
+            { "type": "Input.Text", "id": "notes", "placeholder": "Optional notes" }
+        ],
+        "actions": [
+            { "type": "Action.Submit", "title": "Submit preferences", "data": { "action": "save_preferences" } }
+        ]
+    })
+}
+
+fn provider_smoke_card() -> Value {
+    json!({
+        "type": "AdaptiveCard",
+        "version": "1.5",
+        "body": [
+            { "type": "TextBlock", "text": "Provider smoke test", "wrap": true },
+            { "type": "TextBlock", "text": "Ensure URL + inputs survive translation." }
+        ],
+        "actions": [
+            { "type": "Action.OpenUrl", "title": "Open docs", "url": "https://example.com/docs" }
+        ]
+    })
+}
+
+/// Publish `inbound` with a fresh `traceparent`; returns the context that was sent.
+pub async fn publish(
+    nats_url: String,
+    subject: &str,
+    inbound: &InboundMessage,
+) -> Result<TraceContext> {
+    let client = async_nats::connect(nats_url)
+        .await
+        .with_context(|| "connect to NATS")?;
+    let trace = TraceContext::new_root();
+    client
+        .publish_with_headers(
+            subject.to_string(),
+            trace.headers(),
+            serde_json::to_vec(inbound)?.into(),
+        )
+        .await?;
+    client.flush().await?;
+    Ok(trace)
+}
+
+/// Subscribes to a subject, applies a [`FlowBehavior`] to the next `expected` messages and
+/// posts each result to the provider sink, continuing the inbound `traceparent`.
+pub struct FlowWorker {
+    handle: JoinHandle<Result<()>>,
+    ready: oneshot::Receiver<()>,
+}
+
+impl FlowWorker {
+    pub fn spawn(
+        nats_url: String,
+        subject: String,
+        sink_url: String,
+        behavior: FlowBehavior,
+        expected: usize,
+    ) -> Self {
+        let (ready_tx, ready_rx) = oneshot::channel();
+        let handle = tokio::spawn(async move {
+            let client = async_nats::connect(&nats_url)
+                .await
+                .with_context(|| format!("connect to NATS at {}", nats_url))?;
+            let mut sub = client.subscribe(subject.clone()).await?;
+            let _ = ready_tx.send(());
+            for idx in 0..expected {
+                let msg = timeout(Duration::from_secs(20), sub.next())
+                    .await
+                    .with_context(|| {
+                        format!("timed out awaiting inbound message {idx} (subscribe->next)")
+                    })?
+                    .ok_or_else(|| anyhow!("subscription ended before message"))?;
+                let inbound: InboundMessage = serde_json::from_slice(&msg.payload)?;
+                let trace = TraceContext::continue_from(msg.headers.as_ref());
+                for outbound in behavior.apply(inbound) {
+                    send_to_sink(&sink_url, &outbound, &trace).await?;
+                }
+            }
+            Ok(())
+        });
+        Self {
+            handle,
+            ready: ready_rx,
+        }
+    }
+
+    pub async fn wait_ready(&mut self, timeout_dur: Duration) -> Result<()> {
+        timeout(timeout_dur, &mut self.ready)
+            .await
+            .context("timed out waiting for worker subscribe ready")?
+            .map_err(|_| anyhow!("worker subscribe channel closed"))
+    }
+
+    /// Wait for the worker to handle every expected message.
+    pub async fn wait(self) -> Result<()> {
+        match self.handle.await {
+            Ok(res) => res,
+            Err(err) => Err(anyhow!("worker task join error: {err}")),
+        }
+    }
+}
+
+async fn send_to_sink(url: &str, outbound: &OutboundPayload, trace: &TraceContext) -> Result<()> {
+    let url = url.to_string();
+    let body = serde_json::to_value(outbound)?;
+    let traceparent = trace.to_string();
+    tokio::task::spawn_blocking(move || -> Result<()> {
+        let resp = ureq::post(&url)
+            .header(TRACEPARENT, &traceparent)
+            .send_json(body);
+        match resp {
+            Ok(r) if r.status() == 200 => Ok(()),
+            Ok(r) => bail!("sink responded with {}", r.status()),
+            Err(err) => bail!("failed to POST to sink: {err}"),
+        }
+    })
+    .await?
+}
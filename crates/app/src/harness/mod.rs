@@ -1,4 +1,5 @@
 use std::{
+    collections::BTreeMap,
     fs,
     io::Write,
     path::{Path, PathBuf},
@@ -20,6 +21,19 @@ pub mod pack;
 pub use pack::{BuildMode, PackBuildResult, PackInstallResult, PackVerifyResult, VerifyMode};
 pub mod config_layers;
 pub use config_layers::{ConfigLayers, SecretCheck, apply_secrets, load_toml, merge_json};
+pub mod manifest;
+pub use manifest::{ComposeService, EnvManifest, PackPreload, StackComponent, TenantSeed};
+pub mod runner_contract;
+pub use runner_contract::{
+    ContractCase, check_bridge_shapes, load_contract_cases, replay_against_runner,
+};
+pub mod messaging_cases;
+pub use messaging_cases::{MessagingCase, load_messaging_cases};
+pub mod teardown;
+use teardown::TeardownHooks;
+pub use teardown::{TeardownContext, TeardownReason};
+pub mod isolation;
+pub use isolation::EgressViolation;
 
 const NATS_PORT: u16 = 4223;
 const POSTGRES_PORT: u16 = 55432;
@@ -33,15 +47,93 @@ pub struct TestEnv {
     artifacts_dir: PathBuf,
     compose_file: PathBuf,
     project_name: String,
+    /// Set when the services run on a dedicated internal network with no egress; holds each
+    /// container's IP on that network.
+    hermetic: Option<BTreeMap<ComposeService, String>>,
     nats_url: String,
     db_url: String,
+    services: Vec<ComposeService>,
+    stack: Option<TestStack>,
+    packs: Vec<PreloadedPack>,
+    teardown: TeardownHooks,
     shutdown: bool,
 }
 
+/// Pack provisioned from an env manifest `[[packs]]` entry.
+#[derive(Debug)]
+pub struct PreloadedPack {
+    pub id: String,
+    pub gtpack: PathBuf,
+    pub mode: BuildMode,
+    pub target: String,
+}
+
 impl TestEnv {
     /// Bring up the harness: prepare directories, start Compose services, and wait for health.
+    /// Set `E2E_HERMETIC=1` to run the services on an isolated network (see
+    /// [`up_hermetic`](Self::up_hermetic)).
     pub async fn up() -> Result<Self> {
-        let name = resolve_test_name();
+        Self::start(
+            resolve_test_name(),
+            ComposeService::all(),
+            hermetic_requested(),
+        )
+        .await
+    }
+
+    /// Like [`up`](Self::up), but the compose project runs on its own `internal: true` Docker
+    /// network with no external egress, reached through the containers' bridge IPs (Linux
+    /// only). Outbound calls the services attempt fail; `down()` scans the compose logs for
+    /// them, writes `artifacts/egress_violations.json` and fails the test if any were found.
+    pub async fn up_hermetic() -> Result<Self> {
+        Self::start(resolve_test_name(), ComposeService::all(), true).await
+    }
+
+    /// Provision everything an `env.e2e.toml` declares: compose services, seeded tenant
+    /// secrets, preloaded packs (build, verify, install) and stack components. Relative paths
+    /// in the manifest resolve against `CARGO_MANIFEST_DIR` for the manifest itself and the
+    /// workspace root for pack fixtures.
+    pub async fn from_manifest(path: impl AsRef<Path>) -> Result<Self> {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(path.as_ref());
+        let manifest = EnvManifest::load(&path)?;
+        let name = manifest
+            .name
+            .as_deref()
+            .map(sanitize)
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(resolve_test_name);
+        let hermetic = manifest.hermetic || hermetic_requested();
+        let mut env = Self::start(name, manifest.services.clone(), hermetic).await?;
+        fs::copy(&path, env.root.join("env.e2e.toml"))
+            .with_context(|| format!("failed to copy {}", path.display()))?;
+
+        for tenant in &manifest.tenants {
+            for (key, value) in &tenant.secrets {
+                env.write_tenant_secret(&tenant.id, key, value)?;
+            }
+            env.append_log(&format!(
+                "seeded tenant {} ({} secret(s))",
+                tenant.id,
+                tenant.secrets.len()
+            ))?;
+        }
+        for pack in &manifest.packs {
+            let preloaded = env.preload_pack(pack)?;
+            env.packs.push(preloaded);
+        }
+        if manifest.stack.contains(&StackComponent::Runner) {
+            let mut stack = env.up_stack().await.map_err(anyhow::Error::new)?;
+            stack.healthcheck(&env.logs_dir).await?;
+            env.append_log("stack runner ready")?;
+            env.stack = Some(stack);
+        }
+        Ok(env)
+    }
+
+    async fn start(name: String, services: Vec<ComposeService>, hermetic: bool) -> Result<Self> {
+        if hermetic && !cfg!(target_os = "linux") {
+            bail!("hermetic networking needs Linux: the harness reaches containers by bridge IP");
+        }
         let root = workspace_root().join("target").join("e2e").join(&name);
         let logs_dir = root.join("logs");
         let artifacts_dir = root.join("artifacts");
@@ -70,23 +162,39 @@ impl TestEnv {
         write_json(&root.join("env.json"), &snapshot)?;
         write_text(&logs_dir.join("READY"), "ok\n")?;
 
-        let env = Self {
+        let mut env = Self {
             name,
             root,
             logs_dir,
             artifacts_dir,
             compose_file,
             project_name,
+            hermetic: None,
             nats_url,
             db_url,
+            services,
+            stack: None,
+            packs: Vec::new(),
+            teardown: TeardownHooks::default(),
             shutdown: false,
         };
 
         // Best effort cleanup in case a previous run crashed and left containers behind.
         let _ = env.compose_down();
 
+        if hermetic {
+            write_text(
+                &env.hermetic_override(),
+                isolation::compose_override(&env.services),
+            )?;
+            env.hermetic = Some(BTreeMap::new());
+        }
+
         env.append_log("starting compose stack")?;
         env.compose_up()?;
+        if hermetic {
+            env.resolve_hermetic_endpoints()?;
+        }
         env.wait_for_ports().await?;
         env.ensure_services_ready().await?;
         env.append_log("compose stack ready")?;
@@ -94,15 +202,82 @@ impl TestEnv {
         Ok(env)
     }
 
+    /// Register a collector to run when the env is torn down, by [`down`](Self::down) or by
+    /// `Drop` when the test returns early or panics. Hooks run in registration order while
+    /// the services are still up; a failing hook is logged to `logs/teardown.log` and does
+    /// not stop the teardown.
+    pub fn on_teardown(
+        &mut self,
+        hook: impl FnOnce(&TeardownContext<'_>) -> Result<()> + Send + 'static,
+    ) {
+        self.teardown.push(hook);
+    }
+
+    fn run_teardown_hooks(&mut self, reason: TeardownReason) {
+        let ctx = TeardownContext {
+            name: &self.name,
+            reason,
+            panicking: std::thread::panicking(),
+            logs_dir: &self.logs_dir,
+            artifacts_dir: &self.artifacts_dir,
+            nats_url: &self.nats_url,
+            db_url: &self.db_url,
+        };
+        let failed = self.teardown.run(&ctx);
+        if failed > 0 {
+            let _ = self.append_log(&format!("{failed} teardown hook(s) failed"));
+        }
+    }
+
     pub async fn down(mut self) -> Result<()> {
+        self.run_teardown_hooks(TeardownReason::Down);
+        if let Some(stack) = self.stack.take() {
+            self.append_log("stopping stack")?;
+            stack.down().await?;
+        }
         self.append_log("capturing compose logs before teardown")?;
         let _ = self.capture_compose_logs();
+        let violations = self.check_egress();
         self.append_log("stopping compose stack")?;
         self.compose_down()?;
         self.shutdown = true;
+        let violations = violations?;
+        if !violations.is_empty() {
+            bail!(
+                "{} unexpected outbound call(s) on the hermetic network; see {}",
+                violations.len(),
+                self.artifacts_dir.join("egress_violations.json").display()
+            );
+        }
         Ok(())
     }
 
+    /// Whether the services run on an isolated network (see [`up_hermetic`](Self::up_hermetic)).
+    pub fn is_hermetic(&self) -> bool {
+        self.hermetic.is_some()
+    }
+
+    /// Scan `logs/compose.log` for outbound calls refused by the hermetic network and write
+    /// them to `artifacts/egress_violations.json`. Empty (and nothing written) when the env is
+    /// not hermetic.
+    pub fn check_egress(&self) -> Result<Vec<EgressViolation>> {
+        if !self.is_hermetic() {
+            return Ok(Vec::new());
+        }
+        let log_path = self.logs_dir.join("compose.log");
+        let logs = fs::read_to_string(&log_path)
+            .with_context(|| format!("failed to read {}", log_path.display()))?;
+        let violations = isolation::scan_logs(&logs);
+        isolation::write_report(&self.artifacts_dir, &violations)?;
+        for violation in &violations {
+            self.append_log(&format!(
+                "egress violation from {}: {}",
+                violation.service, violation.line
+            ))?;
+        }
+        Ok(violations)
+    }
+
     pub fn artifacts_dir(&self) -> &Path {
         &self.artifacts_dir
     }
@@ -119,6 +294,16 @@ impl TestEnv {
         &self.root
     }
 
+    /// Packs provisioned by [`TestEnv::from_manifest`], in manifest order.
+    pub fn packs(&self) -> &[PreloadedPack] {
+        &self.packs
+    }
+
+    /// Stack booted by [`TestEnv::from_manifest`] when the manifest lists `stack` components.
+    pub fn stack_mut(&mut self) -> Option<&mut TestStack> {
+        self.stack.as_mut()
+    }
+
     /// Boot the Greentic stack (runner/deployer/store) if binaries are available locally.
     pub async fn up_stack(&self) -> Result<TestStack, StackError> {
         services::boot_stack(self).await
@@ -153,23 +338,101 @@ impl TestEnv {
     }
 
     fn compose_up(&self) -> Result<()> {
-        self.run_compose(&["up", "-d", "--remove-orphans"])?;
+        let mut args = vec!["up", "-d", "--remove-orphans"];
+        args.extend(self.services.iter().map(|service| service.compose_name()));
+        self.run_compose(&args)?;
         Ok(())
     }
 
+    fn preload_pack(&self, pack: &PackPreload) -> Result<PreloadedPack> {
+        let id = pack.id();
+        let artifacts = self.artifacts_dir.join("packs").join(&id);
+        let logs = self.logs_dir.join("packs").join(&id);
+        fs::create_dir_all(&logs)
+            .with_context(|| format!("failed to create {}", logs.display()))?;
+        let fixture = workspace_root().join(&pack.fixture);
+        let PackBuildResult { gtpack, mode } = pack::pack_build(&fixture, &artifacts, &logs)?;
+        if pack.verify && !pack::pack_verify(&gtpack, &logs)?.ok {
+            bail!("pack {id} failed verification");
+        }
+        let PackInstallResult { ok, target } =
+            pack::pack_install(&pack.target, &gtpack, &artifacts, &logs)?;
+        if !ok {
+            bail!("pack {id} failed to install into {}", pack.target);
+        }
+        self.append_log(&format!("preloaded pack {id} into {target}"))?;
+        Ok(PreloadedPack {
+            id,
+            gtpack,
+            mode,
+            target,
+        })
+    }
+
     fn compose_down(&self) -> Result<()> {
         self.run_compose(&["down", "-v"])?;
         Ok(())
     }
 
+    fn hermetic_override(&self) -> PathBuf {
+        self.root.join("compose.hermetic.yml")
+    }
+
+    /// `docker compose -f ...` for this env's project, without a subcommand.
+    fn compose_command(&self) -> Command {
+        let mut command = Command::new("docker");
+        command.arg("compose").arg("-f").arg(&self.compose_file);
+        if self.is_hermetic() {
+            command.arg("-f").arg(self.hermetic_override());
+        }
+        command
+            .env("COMPOSE_PROJECT_NAME", &self.project_name)
+            .current_dir(workspace_root());
+        command
+    }
+
+    /// Look up each container's IP on the hermetic network and point the URLs (and the
+    /// `env.json` snapshot) at it, since no ports are published to the host.
+    fn resolve_hermetic_endpoints(&mut self) -> Result<()> {
+        let mut ips = BTreeMap::new();
+        for service in &self.services {
+            let ip = isolation::container_ip(
+                self.compose_command(),
+                &self.project_name,
+                service.compose_name(),
+            )?;
+            self.append_log(&format!(
+                "{} reachable at {ip} on {}",
+                service.compose_name(),
+                isolation::network_name(&self.project_name)
+            ))?;
+            ips.insert(*service, ip);
+        }
+        self.hermetic = Some(ips);
+        self.nats_url = format!("nats://{}", self.service_addr(ComposeService::Nats));
+        self.db_url = format!(
+            "postgres://postgres:postgres@{}/postgres",
+            self.service_addr(ComposeService::Postgres)
+        );
+        let snapshot = EnvSnapshot::capture(&self.name, &self.root, &self.nats_url, &self.db_url)?;
+        write_json(&self.root.join("env.json"), &snapshot)
+    }
+
+    /// `host:port` the harness uses to reach `service`.
+    fn service_addr(&self, service: ComposeService) -> String {
+        match self.hermetic.as_ref().and_then(|ips| ips.get(&service)) {
+            Some(ip) => format!("{ip}:{}", isolation::container_port(service)),
+            None => match service {
+                ComposeService::Nats => format!("127.0.0.1:{NATS_PORT}"),
+                ComposeService::Postgres => format!("127.0.0.1:{POSTGRES_PORT}"),
+            },
+        }
+    }
+
     fn run_compose(&self, args: &[&str]) -> Result<()> {
-        let output = Command::new("docker")
-            .arg("compose")
-            .arg("-f")
-            .arg(&self.compose_file)
+        let output = self
+            .compose_command()
             .args(args)
-            .env("COMPOSE_PROJECT_NAME", &self.project_name)
-            .current_dir(workspace_root())
             .output()
             .context("failed to execute docker compose")?;
 
@@ -187,33 +450,49 @@ impl TestEnv {
     }
 
     async fn wait_for_ports(&self) -> Result<()> {
-        wait_for_port("nats", NATS_PORT, &self.logs_dir, Duration::from_secs(30)).await?;
-        wait_for_port(
-            "postgres",
-            POSTGRES_PORT,
-            &self.logs_dir,
-            Duration::from_secs(40),
-        )
-        .await?;
+        for service in &self.services {
+            match service {
+                ComposeService::Nats => {
+                    wait_for_port(
+                        "nats",
+                        &self.service_addr(*service),
+                        &self.logs_dir,
+                        Duration::from_secs(30),
+                    )
+                    .await?
+                }
+                ComposeService::Postgres => {
+                    wait_for_port(
+                        "postgres",
+                        &self.service_addr(*service),
+                        &self.logs_dir,
+                        Duration::from_secs(40),
+                    )
+                    .await?
+                }
+            }
+        }
         Ok(())
     }
 
     async fn ensure_services_ready(&self) -> Result<()> {
-        ensure_nats_ready(&self.nats_url, &self.logs_dir).await?;
-        ensure_postgres_ready(&self.db_url, &self.logs_dir).await?;
+        for service in &self.services {
+            match service {
+                ComposeService::Nats => ensure_nats_ready(&self.nats_url, &self.logs_dir).await?,
+                ComposeService::Postgres => {
+                    ensure_postgres_ready(&self.db_url, &self.logs_dir).await?
+                }
+            }
+        }
         Ok(())
     }
 
     fn capture_compose_logs(&self) -> Result<()> {
         let log_path = self.logs_dir.join("compose.log");
-        let output = Command::new("docker")
-            .arg("compose")
-            .arg("-f")
-            .arg(&self.compose_file)
+        let output = self
+            .compose_command()
             .arg("logs")
             .arg("--no-color")
-            .env("COMPOSE_PROJECT_NAME", &self.project_name)
-            .current_dir(workspace_root())
             .output()
             .context("failed to run docker compose logs")?;
 
@@ -250,7 +529,12 @@ impl Drop for TestEnv {
             return;
         }
         let _ = self.append_log("drop without down(); capturing logs and tearing down");
+        self.run_teardown_hooks(TeardownReason::Drop);
+        if let Some(stack) = self.stack.as_mut() {
+            let _ = stack.stop();
+        }
         let _ = self.capture_compose_logs();
+        let _ = self.check_egress();
         let _ = self.compose_down();
         let marker = self.logs_dir.join("dropped_without_down");
         let _ = fs::write(
@@ -332,6 +616,11 @@ impl EnvSnapshot {
     }
 }
 
+/// `E2E_HERMETIC=1` turns on hermetic networking for every env, manifest or not.
+fn hermetic_requested() -> bool {
+    std::env::var("E2E_HERMETIC").is_ok_and(|value| matches!(value.as_str(), "1" | "true"))
+}
+
 fn resolve_test_name() -> String {
     if let Ok(name) = std::env::var("E2E_TEST_NAME") {
         let cleaned = sanitize(&name);
@@ -388,11 +677,15 @@ fn write_text(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
     Ok(())
 }
 
-async fn wait_for_port(name: &str, port: u16, logs_dir: &Path, timeout_at: Duration) -> Result<()> {
+async fn wait_for_port(
+    name: &str,
+    addr: &str,
+    logs_dir: &Path,
+    timeout_at: Duration,
+) -> Result<()> {
     let start = Instant::now();
-    let addr = format!("127.0.0.1:{port}");
     loop {
-        match TcpStream::connect(&addr).await {
+        match TcpStream::connect(addr).await {
             Ok(_) => {
                 write_probe(logs_dir, name, "port open")?;
                 return Ok(());
@@ -0,0 +1,140 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::runner_protocol::{PROTOCOL_VERSION, RunnerAck, RunnerRequest};
+
+/// Recorded bridge ↔ runner exchange, replayed by contract tests.
+///
+/// ```json
+/// {
+///   "name": "emit_and_reload",
+///   "interactions": [
+///     {
+///       "path": "runner/emit",
+///       "request": {"protocol": 1, "message": "hello"},
+///       "response": {"status": 200, "body": {"protocol": 1, "status": "accepted"}}
+///     }
+///   ]
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ContractCase {
+    pub name: String,
+    pub interactions: Vec<RecordedInteraction>,
+    #[serde(skip)]
+    pub source: PathBuf,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RecordedInteraction {
+    pub path: String,
+    pub request: Value,
+    pub response: RecordedResponse,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub body: Value,
+}
+
+/// Load every `*.json` case under `dir`, sorted by file name.
+pub fn load_contract_cases(dir: impl AsRef<Path>) -> Result<Vec<ContractCase>> {
+    let dir = dir.as_ref();
+    let mut paths = fs::read_dir(dir)
+        .with_context(|| format!("failed to read contract cases in {}", dir.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.retain(|path| path.extension().is_some_and(|ext| ext == "json"));
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let raw = fs::read(&path)
+                .with_context(|| format!("failed to read contract case {}", path.display()))?;
+            let mut case: ContractCase = serde_json::from_slice(&raw)
+                .with_context(|| format!("invalid contract case {}", path.display()))?;
+            case.source = path;
+            Ok(case)
+        })
+        .collect()
+}
+
+/// Bridge side: every recorded request and ack must decode into the current protocol types
+/// and encode back to exactly the recorded JSON. A field the bridge renamed, dropped or added
+/// since the recording fails here.
+pub fn check_bridge_shapes(case: &ContractCase) -> Result<()> {
+    for (idx, interaction) in case.interactions.iter().enumerate() {
+        let context = || format!("{} interaction {idx} ({})", case.name, interaction.path);
+        let request = RunnerRequest::decode(&interaction.path, interaction.request.clone())
+            .with_context(context)?;
+        let accepted = (200..300).contains(&interaction.response.status);
+        if accepted && request.protocol() != PROTOCOL_VERSION {
+            bail!(
+                "{}: recorded protocol {} but the bridge speaks {PROTOCOL_VERSION}",
+                context(),
+                request.protocol()
+            );
+        }
+        ensure_same(&request.body(), &interaction.request).with_context(context)?;
+
+        let ack: RunnerAck = serde_json::from_value(interaction.response.body.clone())
+            .with_context(|| format!("{}: invalid ack", context()))?;
+        ensure_same(&serde_json::to_value(&ack)?, &interaction.response.body)
+            .with_context(|| format!("{}: ack", context()))?;
+    }
+    Ok(())
+}
+
+/// Runner side: POST each recorded request to the runner at `base_url` and require the
+/// recorded status and ack. Blocking; run it off the async runtime.
+pub fn replay_against_runner(base_url: &str, case: &ContractCase) -> Result<()> {
+    let config = ureq::Agent::config_builder()
+        .http_status_as_error(false)
+        .build();
+    let agent = ureq::Agent::new_with_config(config);
+    for (idx, interaction) in case.interactions.iter().enumerate() {
+        let url = format!(
+            "{}/{}",
+            base_url.trim_end_matches('/'),
+            interaction.path.trim_start_matches('/')
+        );
+        let context = || format!("{} interaction {idx} ({url})", case.name);
+        let response = agent
+            .post(&url)
+            .send_json(&interaction.request)
+            .with_context(context)?;
+        let status = response.status().as_u16();
+        let body: Value = response
+            .into_body()
+            .read_json()
+            .with_context(|| format!("{}: ack is not JSON", context()))?;
+        if status != interaction.response.status {
+            bail!(
+                "{}: expected status {}, runner answered {status} ({body})",
+                context(),
+                interaction.response.status
+            );
+        }
+        ensure_same(&body, &interaction.response.body)
+            .with_context(|| format!("{}: runner ack", context()))?;
+    }
+    Ok(())
+}
+
+fn ensure_same(actual: &Value, recorded: &Value) -> Result<()> {
+    if actual != recorded {
+        bail!("shape drifted from the recording:\n  recorded: {recorded}\n  actual:   {actual}");
+    }
+    Ok(())
+}
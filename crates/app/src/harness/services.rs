@@ -118,9 +118,18 @@ impl TestStack {
         Ok(())
     }
 
+    /// The runner's stdout/stderr log, which `runner import --watch --file` can follow.
+    pub fn runner_log_path(&self) -> &Path {
+        self.runner.log_path()
+    }
+
     pub async fn down(mut self) -> Result<()> {
-        self.runner.stop()?;
-        Ok(())
+        self.stop()
+    }
+
+    /// Synchronous teardown for `Drop` paths that cannot await.
+    pub(crate) fn stop(&mut self) -> Result<()> {
+        self.runner.stop()
     }
 }
 
@@ -358,25 +367,6 @@ fn binary_candidates(name: &str) -> Vec<PathBuf> {
     paths
 }
 
-async fn wait_for_port(name: &str, port: u16, logs_dir: &Path, timeout_at: Duration) -> Result<()> {
-    let start = Instant::now();
-    let addr = format!("127.0.0.1:{port}");
-    loop {
-        match TcpStream::connect(&addr).await {
-            Ok(_) => {
-                write_probe(logs_dir, name, "port open")?;
-                return Ok(());
-            }
-            Err(err) => {
-                if start.elapsed() > timeout_at {
-                    bail!("{name} did not open port {addr} in time: {err}");
-                }
-                sleep(Duration::from_millis(250)).await;
-            }
-        }
-    }
-}
-
 fn write_probe(logs_dir: &Path, service: &str, message: &str) -> Result<()> {
     let probe = logs_dir.join(format!("probe-{service}.log"));
     let mut file = fs::OpenOptions::new()
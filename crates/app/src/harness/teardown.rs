@@ -0,0 +1,154 @@
+use std::{
+    fs,
+    io::Write,
+    panic::{AssertUnwindSafe, catch_unwind},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+
+/// Why the environment is being torn down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TeardownReason {
+    /// [`TestEnv::down`](super::TestEnv::down) was called.
+    Down,
+    /// The env was dropped without `down()`: the test returned early, failed with `?` or
+    /// panicked.
+    Drop,
+}
+
+/// What a teardown hook gets to work with. Hooks run before the stack and the compose
+/// services stop, so NATS and Postgres are still reachable.
+pub struct TeardownContext<'a> {
+    pub name: &'a str,
+    pub reason: TeardownReason,
+    /// True when the test thread is unwinding from a panic.
+    pub panicking: bool,
+    pub logs_dir: &'a Path,
+    pub artifacts_dir: &'a Path,
+    pub nats_url: &'a str,
+    pub db_url: &'a str,
+}
+
+impl TeardownContext<'_> {
+    /// Write `contents` to `artifacts/<relative>`, creating parent directories.
+    pub fn write_artifact(&self, relative: &str, contents: impl AsRef<[u8]>) -> Result<PathBuf> {
+        let path = self.artifacts_dir.join(relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        fs::write(&path, contents)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+type Hook = Box<dyn FnOnce(&TeardownContext<'_>) -> Result<()> + Send>;
+
+/// Hooks registered with [`TestEnv::on_teardown`](super::TestEnv::on_teardown), run once.
+#[derive(Default)]
+pub(super) struct TeardownHooks {
+    hooks: Vec<Hook>,
+}
+
+impl TeardownHooks {
+    pub(super) fn push(
+        &mut self,
+        hook: impl FnOnce(&TeardownContext<'_>) -> Result<()> + Send + 'static,
+    ) {
+        self.hooks.push(Box::new(hook));
+    }
+
+    /// Run every hook in registration order. A hook that fails or panics is recorded in
+    /// `logs/teardown.log` and does not stop the others (or the teardown); returns how many
+    /// failed.
+    pub(super) fn run(&mut self, ctx: &TeardownContext<'_>) -> usize {
+        let mut failed = 0;
+        for (index, hook) in std::mem::take(&mut self.hooks).into_iter().enumerate() {
+            let outcome = match catch_unwind(AssertUnwindSafe(|| hook(ctx))) {
+                Ok(Ok(())) => "ok".to_string(),
+                Ok(Err(err)) => format!("failed: {err:#}"),
+                Err(panic) => format!("panicked: {}", panic_message(&*panic)),
+            };
+            if outcome != "ok" {
+                failed += 1;
+            }
+            let _ = append_line(
+                &ctx.logs_dir.join("teardown.log"),
+                &format!("hook #{index} ({:?}) {outcome}", ctx.reason),
+            );
+        }
+        failed
+    }
+}
+
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("non-string panic payload")
+}
+
+fn append_line(path: &Path, line: &str) -> Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("failed to open {}", path.display()))?;
+    writeln!(file, "{line}").with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use super::*;
+
+    #[test]
+    fn runs_every_hook_once_even_when_one_fails_or_panics() {
+        let tmp = tempfile::tempdir().unwrap();
+        let ctx = TeardownContext {
+            name: "teardown",
+            reason: TeardownReason::Drop,
+            panicking: false,
+            logs_dir: tmp.path(),
+            artifacts_dir: &tmp.path().join("artifacts"),
+            nats_url: "nats://127.0.0.1:4223",
+            db_url: "postgres://localhost/postgres",
+        };
+        let ran = Arc::new(AtomicUsize::new(0));
+        let mut hooks = TeardownHooks::default();
+        hooks.push(|_| anyhow::bail!("table missing"));
+        hooks.push(|_| panic!("collector bug"));
+        let counter = ran.clone();
+        hooks.push(move |ctx| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            ctx.write_artifact("collectors/db.txt", ctx.db_url)?;
+            Ok(())
+        });
+
+        assert_eq!(hooks.run(&ctx), 2);
+        assert_eq!(hooks.run(&ctx), 0);
+        assert_eq!(ran.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("artifacts/collectors/db.txt")).unwrap(),
+            "postgres://localhost/postgres"
+        );
+        let log = fs::read_to_string(tmp.path().join("teardown.log")).unwrap();
+        let lines: Vec<_> = log.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "hook #0 (Drop) failed: table missing",
+                "hook #1 (Drop) panicked: collector bug",
+                "hook #2 (Drop) ok",
+            ]
+        );
+    }
+}
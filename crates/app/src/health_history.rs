@@ -0,0 +1,344 @@
+//! Periodic internal health checks (session store, packs root, NATS when configured) kept in a
+//! ring buffer for `GET /healthz/history`, so intermittent infrastructure failures in
+//! long-lived environments show up as uptime figures and failure reasons.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    net::{TcpStream, ToSocketAddrs},
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, anyhow};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::session::SessionStore;
+use crate::session_stats::StoreHealth;
+
+/// Failures listed in the `last_failures` of a history report.
+const LAST_FAILURES: usize = 10;
+
+const NATS_DEFAULT_PORT: u16 = 4222;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthConfig {
+    /// Seconds between check rounds.
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    /// Check rounds kept in the history.
+    #[serde(default = "default_history")]
+    pub history: usize,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: default_interval_secs(),
+            history: default_history(),
+        }
+    }
+}
+
+fn default_interval_secs() -> u64 {
+    30
+}
+
+fn default_history() -> usize {
+    120
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CheckOutcome {
+    pub healthy: bool,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl CheckOutcome {
+    fn timed(check: impl FnOnce() -> Result<()>) -> Self {
+        let started = Instant::now();
+        let result = check();
+        Self {
+            healthy: result.is_ok(),
+            latency_ms: started.elapsed().as_millis() as u64,
+            error: result.err().map(|err| format!("{err:#}")),
+        }
+    }
+}
+
+/// One round of checks, keyed by check name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HealthSample {
+    pub timestamp_ms: u64,
+    pub checks: BTreeMap<String, CheckOutcome>,
+}
+
+impl HealthSample {
+    pub fn healthy(&self) -> bool {
+        self.checks.values().all(|check| check.healthy)
+    }
+}
+
+/// What a check round probes.
+pub struct HealthTargets<'a> {
+    pub session_backend: &'a str,
+    pub sessions: &'a dyn SessionStore,
+    pub packs_root: &'a Path,
+    pub nats_url: Option<&'a str>,
+    /// Connect timeout for network checks.
+    pub timeout: Duration,
+}
+
+/// Run every check once. Blocking; call from a blocking task.
+pub fn run_checks(targets: &HealthTargets<'_>, timestamp_ms: u64) -> HealthSample {
+    let mut checks = BTreeMap::new();
+    let store = StoreHealth::probe(targets.session_backend, targets.sessions);
+    checks.insert(
+        "session_store".to_string(),
+        CheckOutcome {
+            healthy: store.healthy,
+            latency_ms: store.latency_ms,
+            error: store.error,
+        },
+    );
+    checks.insert(
+        "packs_root".to_string(),
+        CheckOutcome::timed(|| {
+            std::fs::read_dir(targets.packs_root)
+                .map(drop)
+                .with_context(|| format!("cannot read {}", targets.packs_root.display()))
+        }),
+    );
+    if let Some(url) = targets.nats_url {
+        checks.insert(
+            "nats".to_string(),
+            CheckOutcome::timed(|| tcp_reachable(url, targets.timeout)),
+        );
+    }
+    HealthSample {
+        timestamp_ms,
+        checks,
+    }
+}
+
+/// Connect to the `host:port` of `url` (`nats://host[:port]`).
+fn tcp_reachable(url: &str, timeout: Duration) -> Result<()> {
+    let authority = url
+        .split_once("://")
+        .map_or(url, |(_, rest)| rest)
+        .split(['/', '?'])
+        .next()
+        .unwrap_or_default();
+    let authority = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let target = if authority
+        .rsplit_once(':')
+        .is_some_and(|(_, port)| !port.is_empty() && port.chars().all(|c| c.is_ascii_digit()))
+    {
+        authority.to_string()
+    } else {
+        format!("{authority}:{NATS_DEFAULT_PORT}")
+    };
+    let addr = target
+        .to_socket_addrs()
+        .with_context(|| format!("cannot resolve {target}"))?
+        .next()
+        .ok_or_else(|| anyhow!("{target} resolved to no address"))?;
+    TcpStream::connect_timeout(&addr, timeout)
+        .map(drop)
+        .with_context(|| format!("cannot connect to {target}"))
+}
+
+/// The most recent check rounds, oldest first.
+#[derive(Debug, Clone)]
+pub struct HealthHistory {
+    capacity: usize,
+    samples: Arc<Mutex<VecDeque<HealthSample>>>,
+}
+
+impl Default for HealthHistory {
+    fn default() -> Self {
+        Self::new(default_history())
+    }
+}
+
+impl HealthHistory {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity,
+            samples: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+        }
+    }
+
+    pub fn record(&self, sample: HealthSample) {
+        let mut samples = self.samples.lock();
+        if samples.len() == self.capacity {
+            samples.pop_front();
+        }
+        samples.push_back(sample);
+    }
+
+    pub fn report(&self) -> HealthReport {
+        let samples = self.samples.lock();
+        let mut checks: BTreeMap<String, CheckSummary> = BTreeMap::new();
+        let mut failures = Vec::new();
+        for sample in samples.iter() {
+            for (name, outcome) in &sample.checks {
+                let summary = checks.entry(name.clone()).or_default();
+                summary.samples += 1;
+                if !outcome.healthy {
+                    summary.failures += 1;
+                    let failure = FailureRecord {
+                        timestamp_ms: sample.timestamp_ms,
+                        check: name.clone(),
+                        error: outcome.error.clone(),
+                    };
+                    summary.last_failure = Some(failure.clone());
+                    failures.push(failure);
+                }
+            }
+        }
+        for summary in checks.values_mut() {
+            summary.uptime_percent = uptime(summary.samples - summary.failures, summary.samples);
+        }
+        let healthy = samples.iter().filter(|sample| sample.healthy()).count();
+        failures.reverse();
+        failures.truncate(LAST_FAILURES);
+        HealthReport {
+            capacity: self.capacity,
+            samples: samples.len(),
+            since_ms: samples.front().map(|sample| sample.timestamp_ms),
+            uptime_percent: uptime(healthy, samples.len()),
+            latest: samples.back().cloned(),
+            checks,
+            last_failures: failures,
+        }
+    }
+}
+
+fn uptime(healthy: usize, total: usize) -> Option<f64> {
+    (total > 0).then(|| healthy as f64 * 100.0 / total as f64)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HealthReport {
+    /// Check rounds the history holds at most.
+    pub capacity: usize,
+    pub samples: usize,
+    /// Timestamp of the oldest round still held.
+    pub since_ms: Option<u64>,
+    /// Share of rounds in which every check passed; `None` before the first round.
+    pub uptime_percent: Option<f64>,
+    pub latest: Option<HealthSample>,
+    pub checks: BTreeMap<String, CheckSummary>,
+    /// Most recent failures first.
+    pub last_failures: Vec<FailureRecord>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CheckSummary {
+    pub samples: usize,
+    pub failures: usize,
+    pub uptime_percent: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_failure: Option<FailureRecord>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FailureRecord {
+    pub timestamp_ms: u64,
+    pub check: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::InMemorySessionStore;
+
+    fn sample(timestamp_ms: u64, nats_error: Option<&str>) -> HealthSample {
+        let ok = CheckOutcome {
+            healthy: true,
+            latency_ms: 1,
+            error: None,
+        };
+        HealthSample {
+            timestamp_ms,
+            checks: BTreeMap::from([
+                ("session_store".to_string(), ok.clone()),
+                (
+                    "nats".to_string(),
+                    CheckOutcome {
+                        healthy: nats_error.is_none(),
+                        error: nats_error.map(str::to_string),
+                        ..ok
+                    },
+                ),
+            ]),
+        }
+    }
+
+    #[test]
+    fn reports_uptime_and_latest_failures_within_capacity() {
+        let history = HealthHistory::new(4);
+        assert_eq!(history.report().uptime_percent, None);
+        history.record(sample(1, Some("dropped before report")));
+        history.record(sample(2, None));
+        history.record(sample(3, Some("connection refused")));
+        history.record(sample(4, None));
+        history.record(sample(5, Some("timed out")));
+
+        let report = history.report();
+        assert_eq!(report.samples, 4);
+        assert_eq!(report.since_ms, Some(2));
+        assert_eq!(report.uptime_percent, Some(50.0));
+        assert_eq!(report.checks["session_store"].uptime_percent, Some(100.0));
+        let nats = &report.checks["nats"];
+        assert_eq!((nats.samples, nats.failures), (4, 2));
+        assert_eq!(nats.last_failure.as_ref().unwrap().timestamp_ms, 5);
+        let errors: Vec<_> = report
+            .last_failures
+            .iter()
+            .map(|failure| failure.error.as_deref().unwrap())
+            .collect();
+        assert_eq!(errors, vec!["timed out", "connection refused"]);
+        assert_eq!(report.latest.unwrap().timestamp_ms, 5);
+    }
+
+    #[test]
+    fn checks_store_packs_root_and_nats() {
+        let tmp = tempfile::tempdir().unwrap();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let nats = format!("nats://{}", listener.local_addr().unwrap());
+        let sessions = InMemorySessionStore::new();
+        let targets = HealthTargets {
+            session_backend: "memory",
+            sessions: sessions.as_ref(),
+            packs_root: tmp.path(),
+            nats_url: Some(&nats),
+            timeout: Duration::from_secs(1),
+        };
+        let sample = run_checks(&targets, 7);
+        assert!(sample.healthy(), "{sample:?}");
+        assert_eq!(sample.checks.len(), 3);
+
+        drop(listener);
+        let missing = tmp.path().join("missing");
+        let sample = run_checks(
+            &HealthTargets {
+                packs_root: &missing,
+                nats_url: None,
+                ..targets
+            },
+            8,
+        );
+        assert!(!sample.checks["packs_root"].healthy);
+        assert!(!sample.checks.contains_key("nats"));
+    }
+}
@@ -0,0 +1,219 @@
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use anyhow::{Context, Result, bail};
+use serde_json::Value;
+
+/// Secret lookups for `${secret:KEY}` placeholders; `put` is used by provisioning commands.
+pub trait SecretStore: Send + Sync {
+    fn get(&self, key: &str) -> Result<Option<String>>;
+    fn put(&self, key: &str, value: &str) -> Result<()>;
+}
+
+/// One file per secret (`<root>/<KEY>`), trailing newline stripped.
+pub struct DirSecretStore {
+    root: PathBuf,
+}
+
+impl DirSecretStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path_for(&self, key: &str) -> Result<PathBuf> {
+        if key.is_empty() || key.starts_with('.') || key.contains(['/', '\\']) {
+            bail!("invalid secret key {key:?}");
+        }
+        Ok(self.root.join(key))
+    }
+}
+
+impl SecretStore for DirSecretStore {
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        let path = self.path_for(key)?;
+        match fs::read_to_string(&path) {
+            Ok(value) => Ok(Some(value.trim_end_matches(['\r', '\n']).to_string())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => {
+                Err(err).with_context(|| format!("failed to read secret {}", path.display()))
+            }
+        }
+    }
+
+    fn put(&self, key: &str, value: &str) -> Result<()> {
+        let path = self.path_for(key)?;
+        fs::create_dir_all(&self.root)
+            .with_context(|| format!("failed to create {}", self.root.display()))?;
+        fs::write(&path, format!("{value}\n"))
+            .with_context(|| format!("failed to write secret {}", path.display()))
+    }
+}
+
+/// Resolves `${env:VAR}` and `${secret:KEY}` placeholders in manifest and channel config
+/// strings. `$${` escapes a literal `${`.
+#[derive(Default)]
+pub struct Interpolator {
+    /// Fallbacks for variables missing from the process environment (`[packs.env]`).
+    env: BTreeMap<String, String>,
+    secrets: Option<Box<dyn SecretStore>>,
+    verbatim: bool,
+}
+
+impl Interpolator {
+    pub fn new(env: BTreeMap<String, String>, secrets: Option<Box<dyn SecretStore>>) -> Self {
+        Self {
+            env,
+            secrets,
+            verbatim: false,
+        }
+    }
+
+    /// Leaves placeholders untouched, e.g. for environment-independent plan snapshots.
+    pub fn verbatim() -> Self {
+        Self {
+            verbatim: true,
+            ..Self::default()
+        }
+    }
+
+    /// Resolve every string in `value`, reporting all unresolved references at once with the
+    /// JSON path where each one appears.
+    pub fn resolve(&self, value: Value) -> Result<Value> {
+        if self.verbatim {
+            return Ok(value);
+        }
+        let mut problems = Vec::new();
+        let resolved = self.walk(value, "$", &mut problems);
+        if problems.is_empty() {
+            Ok(resolved)
+        } else {
+            bail!("unresolved placeholders:\n  {}", problems.join("\n  "))
+        }
+    }
+
+    fn walk(&self, value: Value, path: &str, problems: &mut Vec<String>) -> Value {
+        match value {
+            Value::String(text) => match self.resolve_str(&text) {
+                Ok(resolved) => Value::String(resolved),
+                Err(err) => {
+                    problems.push(format!("{path}: {err:#}"));
+                    Value::String(text)
+                }
+            },
+            Value::Array(items) => Value::Array(
+                items
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, item)| self.walk(item, &format!("{path}[{idx}]"), problems))
+                    .collect(),
+            ),
+            Value::Object(map) => Value::Object(
+                map.into_iter()
+                    .map(|(key, item)| {
+                        let child = format!("{path}.{key}");
+                        (key, self.walk(item, &child, problems))
+                    })
+                    .collect(),
+            ),
+            other => other,
+        }
+    }
+
+    fn resolve_str(&self, text: &str) -> Result<String> {
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find('$') {
+            out.push_str(&rest[..start]);
+            let tail = &rest[start..];
+            if let Some(after) = tail.strip_prefix("$${") {
+                out.push_str("${");
+                rest = after;
+            } else if let Some(after) = tail.strip_prefix("${") {
+                let end = after
+                    .find('}')
+                    .with_context(|| format!("unterminated placeholder in {text:?}"))?;
+                out.push_str(&self.lookup(&after[..end])?);
+                rest = &after[end + 1..];
+            } else {
+                out.push('$');
+                rest = &tail[1..];
+            }
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    fn lookup(&self, reference: &str) -> Result<String> {
+        match reference.split_once(':') {
+            Some(("env", name)) => std::env::var(name)
+                .ok()
+                .or_else(|| self.env.get(name).cloned())
+                .with_context(|| {
+                    format!("${{env:{name}}} is not set in the environment or [packs.env]")
+                }),
+            Some(("secret", key)) => {
+                let store = self.secrets.as_ref().with_context(|| {
+                    format!("${{secret:{key}}} needs a secret store ([packs].secrets_dir)")
+                })?;
+                store
+                    .get(key)?
+                    .with_context(|| format!("${{secret:{key}}} is not in the secret store"))
+            }
+            _ => bail!("unknown placeholder ${{{reference}}} (expected env:VAR or secret:KEY)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::path::Path;
+
+    fn interpolator(secrets_dir: &Path) -> Interpolator {
+        let env = BTreeMap::from([("GREENTIC_TEST_API_BASE".to_string(), "http://api".into())]);
+        Interpolator::new(env, Some(Box::new(DirSecretStore::new(secrets_dir))))
+    }
+
+    #[test]
+    fn resolves_env_fallbacks_secrets_and_escapes() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(tmp.path().join("SLACK_TOKEN"), "xoxb-123\n").unwrap();
+        let resolved = interpolator(tmp.path())
+            .resolve(json!({
+                "url": "${env:GREENTIC_TEST_API_BASE}/hooks",
+                "headers": ["Bearer ${secret:SLACK_TOKEN}"],
+                "literal": "$${env:NOT_RESOLVED} costs $5",
+                "retries": 3
+            }))
+            .unwrap();
+        assert_eq!(
+            resolved,
+            json!({
+                "url": "http://api/hooks",
+                "headers": ["Bearer xoxb-123"],
+                "literal": "${env:NOT_RESOLVED} costs $5",
+                "retries": 3
+            })
+        );
+    }
+
+    #[test]
+    fn reports_every_unresolved_reference_with_its_path() {
+        let tmp = tempfile::tempdir().unwrap();
+        let err = interpolator(tmp.path())
+            .resolve(json!({
+                "scenarios": [{"config": {"token": "${secret:MISSING}"}}],
+                "endpoint": "${env:GREENTIC_TEST_UNSET_VAR}",
+                "odd": "${vault:x}"
+            }))
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains(
+                "$.scenarios[0].config.token: ${secret:MISSING} is not in the secret store"
+            )
+        );
+        assert!(err.contains("$.endpoint: ${env:GREENTIC_TEST_UNSET_VAR} is not set"));
+        assert!(err.contains("$.odd: unknown placeholder ${vault:x}"));
+    }
+}
@@ -1,3 +1,79 @@
+pub mod annotations;
+mod anomaly;
+mod api_error;
+mod chat_token;
+mod cluster;
+#[cfg(feature = "components")]
+mod components;
+mod context_schema;
+mod csv_export;
+mod debugger;
+mod deployment;
+mod email_ingress;
+mod event_export;
+mod event_policy;
+mod event_retention;
+mod event_schema;
+mod event_store;
+mod event_summary;
 pub mod fixtures;
 pub mod harness;
+mod health_history;
+mod interpolate;
+mod listeners;
+#[cfg(feature = "mini-runner")]
+mod mini_runner;
+mod network;
+mod outbox;
+mod pack_assets;
+mod pack_generations;
+mod pack_history;
+mod pack_routes;
+mod pack_sandbox;
+mod pack_validate;
+mod panic_guard;
+pub mod partitioning;
+mod path_safety;
+mod phone_channel;
+mod plan_bundle;
+pub mod plan_cost;
+pub mod plan_policy;
+mod provider_sandbox;
+mod provider_smoke;
+mod providers;
+mod repl;
+mod runner_compare;
+mod runner_import;
+pub mod runner_protocol;
+mod runner_queue;
+mod runner_replay;
+mod runner_targets;
 pub mod scenario;
+mod server;
+pub mod session;
+mod session_audit;
+mod session_fsck;
+mod session_metrics;
+mod session_nudge;
+mod session_quota;
+mod session_snapshot;
+mod session_stats;
+mod session_stress;
+mod session_transfer;
+mod session_upgrade;
+mod single_flight;
+mod soak;
+mod sqlite;
+mod state_store;
+mod supervisor;
+mod tenant_bootstrap;
+mod tenant_policy;
+pub mod testkit;
+pub mod trace_context;
+mod trace_sampling;
+mod traffic;
+mod transcript_store;
+mod watch;
+
+pub(crate) use server::*;
+pub use server::{AppConfig, Server, ServerHandle, run};
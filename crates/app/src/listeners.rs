@@ -0,0 +1,284 @@
+//! Sockets `serve` accepts HTTP on (`[server.listeners]`): any number of TCP addresses plus an
+//! optional Unix domain socket for co-located runners and CLIs. Requests that arrive over the
+//! socket carry [`LocalPeer`] and are trusted like admin-token requests; who may connect is
+//! decided by the socket file's mode instead of a bearer token.
+
+use std::{future::Future, net::SocketAddr};
+
+use anyhow::{Context, Result, bail};
+use axum::{Extension, Router};
+use camino::Utf8PathBuf;
+use serde::{Deserialize, Serialize};
+use tokio::{net::TcpListener, sync::watch, task::JoinHandle};
+use tracing::{info, warn};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListenersConfig {
+    /// TCP addresses to bind; empty means `[server].listen_addr` alone.
+    #[serde(default)]
+    pub tcp: Vec<String>,
+    /// Unix domain socket path (relative to the working directory); a stale socket left by a
+    /// previous run is replaced.
+    #[serde(default)]
+    pub unix: Option<Utf8PathBuf>,
+    /// Permission bits of the socket file, e.g. `0o660` to admit the owning group.
+    #[serde(default = "default_unix_mode")]
+    pub unix_mode: u32,
+}
+
+impl Default for ListenersConfig {
+    fn default() -> Self {
+        Self {
+            tcp: Vec::new(),
+            unix: None,
+            unix_mode: default_unix_mode(),
+        }
+    }
+}
+
+fn default_unix_mode() -> u32 {
+    0o600
+}
+
+/// Request extension marking a request that came in over the Unix domain socket.
+#[derive(Debug, Clone, Copy)]
+pub struct LocalPeer;
+
+/// Bound listeners, ready to [`serve`](Self::serve).
+pub struct Listeners {
+    tcp: Vec<TcpListener>,
+    #[cfg(unix)]
+    unix: Option<(Utf8PathBuf, tokio::net::UnixListener)>,
+}
+
+impl Listeners {
+    pub async fn bind(config: &ListenersConfig, listen_addr: &str) -> Result<Self> {
+        let addrs = if config.tcp.is_empty() {
+            vec![listen_addr.to_string()]
+        } else {
+            config.tcp.clone()
+        };
+        let mut tcp = Vec::with_capacity(addrs.len());
+        for addr in &addrs {
+            let addr: SocketAddr = addr
+                .parse()
+                .with_context(|| format!("invalid listen address {addr}"))?;
+            let listener = TcpListener::bind(addr)
+                .await
+                .with_context(|| format!("failed to bind {addr}"))?;
+            info!(addr = %listener.local_addr()?, "listening for HTTP traffic");
+            tcp.push(listener);
+        }
+        #[cfg(unix)]
+        let unix = match &config.unix {
+            Some(path) => Some((path.clone(), bind_unix(path, config.unix_mode)?)),
+            None => None,
+        };
+        #[cfg(not(unix))]
+        if let Some(path) = &config.unix {
+            bail!(
+                "cannot listen on {path}: Unix domain sockets are not supported on this platform"
+            );
+        }
+        Ok(Self {
+            tcp,
+            #[cfg(unix)]
+            unix,
+        })
+    }
+
+    /// A single TCP listener the caller already bound, as an embedded server uses.
+    pub fn from_tcp(listener: TcpListener) -> Self {
+        Self {
+            tcp: vec![listener],
+            #[cfg(unix)]
+            unix: None,
+        }
+    }
+
+    /// Addresses the TCP listeners ended up on (useful with port `0`).
+    #[cfg(test)]
+    pub fn tcp_addrs(&self) -> Result<Vec<SocketAddr>> {
+        self.tcp
+            .iter()
+            .map(|listener| Ok(listener.local_addr()?))
+            .collect()
+    }
+
+    /// Serve `router` on every listener until `shutdown` resolves or one of them fails, then
+    /// drain all of them and remove the socket file.
+    pub async fn serve(
+        self,
+        router: Router,
+        shutdown: impl Future<Output = ()> + Send + 'static,
+    ) -> Result<()> {
+        let (stop, stopped) = watch::channel(false);
+        let trigger = stop.clone();
+        tokio::spawn(async move {
+            shutdown.await;
+            trigger.send_replace(true);
+        });
+
+        let mut servers: Vec<JoinHandle<Result<()>>> = Vec::new();
+        for listener in self.tcp {
+            let app = router.clone().into_make_service();
+            let (stop, stopped) = (stop.clone(), stopped.clone());
+            servers.push(tokio::spawn(async move {
+                let result = axum::serve(listener, app)
+                    .with_graceful_shutdown(wait_for_stop(stopped))
+                    .await;
+                stop.send_replace(true);
+                result.context("TCP listener exited with an error")
+            }));
+        }
+        #[cfg(unix)]
+        let socket = match self.unix {
+            Some((path, listener)) => {
+                let app = router.layer(Extension(LocalPeer)).into_make_service();
+                let (stop, stopped) = (stop.clone(), stopped.clone());
+                servers.push(tokio::spawn(async move {
+                    let result = axum::serve(listener, app)
+                        .with_graceful_shutdown(wait_for_stop(stopped))
+                        .await;
+                    stop.send_replace(true);
+                    result.context("Unix socket listener exited with an error")
+                }));
+                Some(path)
+            }
+            None => None,
+        };
+
+        let mut outcome = Ok(());
+        for server in servers {
+            let result = server
+                .await
+                .context("listener task panicked")
+                .and_then(|r| r);
+            if outcome.is_ok() {
+                outcome = result;
+            }
+        }
+        #[cfg(unix)]
+        if let Some(path) = socket
+            && let Err(err) = std::fs::remove_file(&path)
+        {
+            warn!(?err, %path, "failed to remove Unix socket");
+        }
+        outcome
+    }
+}
+
+async fn wait_for_stop(mut stopped: watch::Receiver<bool>) {
+    let _ = stopped.wait_for(|stop| *stop).await;
+}
+
+#[cfg(unix)]
+fn bind_unix(path: &Utf8PathBuf, mode: u32) -> Result<tokio::net::UnixListener> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_socket() => std::fs::remove_file(path)
+            .with_context(|| format!("failed to remove stale socket {path}"))?,
+        Ok(_) => bail!("{path} exists and is not a socket"),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err).with_context(|| format!("failed to inspect {path}")),
+    }
+    if let Some(parent) = path.parent().filter(|dir| !dir.as_str().is_empty()) {
+        std::fs::create_dir_all(parent).with_context(|| format!("failed to create {parent}"))?;
+    }
+    let listener = tokio::net::UnixListener::bind(path)
+        .with_context(|| format!("failed to bind Unix socket {path}"))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+        .with_context(|| format!("failed to set permissions on {path}"))?;
+    info!(%path, mode = format!("{mode:o}"), "listening for HTTP traffic on Unix socket");
+    Ok(listener)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use axum::routing::get;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::UnixStream,
+        sync::oneshot,
+    };
+
+    use super::*;
+
+    async fn peer(local: Option<Extension<LocalPeer>>) -> &'static str {
+        if local.is_some() { "local" } else { "remote" }
+    }
+
+    #[tokio::test]
+    async fn serves_tcp_and_unix_socket_and_marks_local_peers() {
+        let tmp = tempfile::tempdir().unwrap();
+        let socket = Utf8PathBuf::from_path_buf(tmp.path().join("run/greentic.sock")).unwrap();
+        std::fs::create_dir_all(socket.parent().unwrap()).unwrap();
+        // A socket left behind by a crashed server is replaced.
+        drop(std::os::unix::net::UnixListener::bind(&socket).unwrap());
+
+        let config = ListenersConfig {
+            tcp: vec!["127.0.0.1:0".into(), "127.0.0.1:0".into()],
+            unix: Some(socket.clone()),
+            unix_mode: 0o660,
+        };
+        let listeners = Listeners::bind(&config, "unused").await.unwrap();
+        let addrs = listeners.tcp_addrs().unwrap();
+        assert_eq!(addrs.len(), 2);
+        let mode = std::fs::metadata(&socket).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o660);
+
+        let (shutdown, stopped) = oneshot::channel::<()>();
+        let server = tokio::spawn(listeners.serve(
+            Router::new().route("/peer", get(peer)),
+            async move {
+                let _ = stopped.await;
+            },
+        ));
+
+        for addr in addrs {
+            let body = tokio::task::spawn_blocking(move || {
+                ureq::get(&format!("http://{addr}/peer"))
+                    .call()
+                    .unwrap()
+                    .body_mut()
+                    .read_to_string()
+                    .unwrap()
+            })
+            .await
+            .unwrap();
+            assert_eq!(body, "remote");
+        }
+
+        let mut stream = UnixStream::connect(&socket).await.unwrap();
+        stream
+            .write_all(b"GET /peer HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+        assert!(response.ends_with("local"), "{response}");
+
+        shutdown.send(()).unwrap();
+        server.await.unwrap().unwrap();
+        assert!(!socket.exists());
+    }
+
+    #[tokio::test]
+    async fn refuses_to_replace_a_file_that_is_not_a_socket() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = Utf8PathBuf::from_path_buf(tmp.path().join("greentic.sock")).unwrap();
+        std::fs::write(&path, "keep me").unwrap();
+        let config = ListenersConfig {
+            tcp: vec!["127.0.0.1:0".into()],
+            unix: Some(path.clone()),
+            ..ListenersConfig::default()
+        };
+        let err = Listeners::bind(&config, "unused").await.err().unwrap();
+        assert!(err.to_string().contains("is not a socket"), "{err}");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "keep me");
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Operator settings shared by every node kind; each operator reads the fields it needs.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct NodeSpec {
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub channel: Option<String>,
+    #[serde(default)]
+    pub topic: Option<String>,
+    #[serde(default)]
+    pub component: Option<String>,
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub config: Value,
+    /// Route label -> next node id; `default` is taken when the node picks no route.
+    #[serde(default)]
+    pub routing: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FlowNode {
+    /// Operator key, e.g. `messaging.send` or `worker.request`.
+    pub operator: String,
+    pub spec: NodeSpec,
+}
+
+#[derive(Debug, Clone)]
+pub struct Flow {
+    pub id: String,
+    pub entry: String,
+    pub nodes: BTreeMap<String, FlowNode>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FlowDoc {
+    id: String,
+    nodes: BTreeMap<String, BTreeMap<String, NodeSpec>>,
+}
+
+impl Flow {
+    /// Parse a `.ygtc` flow document. Every node holds exactly one operator, routing targets
+    /// must exist, and the entry node is the one no other node routes to.
+    pub fn parse(source: &str) -> Result<Self> {
+        let doc: FlowDoc = serde_yaml_bw::from_str(source).context("invalid flow document")?;
+        let mut nodes = BTreeMap::new();
+        for (node_id, operators) in doc.nodes {
+            let mut operators = operators.into_iter();
+            let (operator, spec) = operators
+                .next()
+                .ok_or_else(|| anyhow!("node {node_id} declares no operator"))?;
+            if operators.next().is_some() {
+                bail!("node {node_id} declares more than one operator");
+            }
+            nodes.insert(node_id, FlowNode { operator, spec });
+        }
+
+        let mut targets = BTreeSet::new();
+        for (node_id, node) in &nodes {
+            for (route, target) in &node.spec.routing {
+                if !nodes.contains_key(target) {
+                    bail!("node {node_id} routes {route} to unknown node {target}");
+                }
+                targets.insert(target.as_str());
+            }
+        }
+        let roots: Vec<&String> = nodes
+            .keys()
+            .filter(|id| !targets.contains(id.as_str()))
+            .collect();
+        let entry = match roots.as_slice() {
+            [only] => (*only).clone(),
+            [] => bail!(
+                "flow {} has no entry node (every node is routed to)",
+                doc.id
+            ),
+            many => many
+                .iter()
+                .find(|id| {
+                    let operator = &nodes[id.as_str()].operator;
+                    operator.ends_with(".ingress") || operator.ends_with(".source")
+                })
+                .map(|id| (*id).clone())
+                .ok_or_else(|| anyhow!("flow {} has several entry candidates: {many:?}", doc.id))?,
+        };
+
+        Ok(Self {
+            id: doc.id,
+            entry,
+            nodes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_repo_flows() {
+        let root = concat!(env!("CARGO_MANIFEST_DIR"), "/../../flows");
+        let source =
+            std::fs::read_to_string(format!("{root}/chat_driven/repo_assistant.ygtc")).unwrap();
+        let flow = Flow::parse(&source).unwrap();
+        assert_eq!(flow.id, "repo_assistant_chat");
+        assert_eq!(flow.entry, "ingress_message");
+        assert_eq!(flow.nodes["to_worker"].operator, "worker.request");
+        assert_eq!(
+            flow.nodes["to_worker"].spec.routing["rebuild_requested"],
+            "emit_rebuild_event"
+        );
+    }
+
+    #[test]
+    fn rejects_dangling_routes() {
+        let source = r#"
+id: broken
+nodes:
+  start:
+    noop:
+      routing:
+        default: missing
+"#;
+        let err = Flow::parse(source).unwrap_err();
+        assert!(err.to_string().contains("unknown node missing"));
+    }
+}
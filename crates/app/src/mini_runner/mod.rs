@@ -0,0 +1,889 @@
+//! Embedded flow execution for offline development (feature `mini-runner`).
+//!
+//! Loads the `.ygtc` flows a pack manifest declares and walks them node by node with a small
+//! set of built-in operators, so scenarios and the dev endpoints work without an external
+//! greentic-runner.
+
+mod flow;
+mod templates;
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tracing::{debug, info, warn};
+
+use crate::components::{ComponentHost, InvokeContext, pack_components};
+use crate::debugger::{Debugger, Stop};
+use crate::event_schema::SharedSchemaRegistry;
+use crate::network::NetworkPolicy;
+use crate::pack_sandbox::{PackSandbox, Sandboxes};
+use crate::trace_context::TraceContext;
+
+pub use flow::Flow;
+pub use templates::TemplateCatalog;
+use templates::{payload_value, render_template};
+
+/// Upper bound on nodes visited per run so routing cycles cannot spin forever.
+const MAX_STEPS: usize = 256;
+
+/// Flows and component artifacts declared by one pack manifest.
+#[derive(Debug, Clone)]
+pub struct PackFlows {
+    pub pack_id: String,
+    pub flows: BTreeMap<String, Flow>,
+    /// Locale-aware templates for `messaging.send` nodes with `config.template`.
+    pub templates: TemplateCatalog,
+    components: BTreeMap<String, PathBuf>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ManifestRefs {
+    id: String,
+    #[serde(default)]
+    flows: Vec<FlowRef>,
+    /// Locale of the pack's primary templates (defaults to `en`).
+    #[serde(default)]
+    default_locale: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FlowRef {
+    id: String,
+    file: PathBuf,
+}
+
+impl PackFlows {
+    /// Load the flows and components referenced by `pack_dir/pack.json`. Flow files are often
+    /// shared between packs, so they may live anywhere under `root` rather than the pack dir.
+    pub fn load(pack_dir: &Path, root: &Path) -> Result<Self> {
+        let manifest_path = pack_dir.join("pack.json");
+        let raw = fs::read(&manifest_path)
+            .with_context(|| format!("failed to read {}", manifest_path.display()))?;
+        let manifest: ManifestRefs = serde_json::from_slice(&raw)
+            .with_context(|| format!("invalid pack manifest {}", manifest_path.display()))?;
+
+        let root = root
+            .canonicalize()
+            .with_context(|| format!("failed to canonicalize {}", root.display()))?;
+        let mut flows = BTreeMap::new();
+        for flow_ref in manifest.flows {
+            let path = pack_dir
+                .join(&flow_ref.file)
+                .canonicalize()
+                .with_context(|| format!("flow {} not found", flow_ref.file.display()))?;
+            if !path.starts_with(&root) {
+                bail!("flow {} escapes root ({})", path.display(), root.display());
+            }
+            let source = fs::read_to_string(&path)
+                .with_context(|| format!("failed to read flow {}", path.display()))?;
+            let flow =
+                Flow::parse(&source).with_context(|| format!("invalid flow {}", path.display()))?;
+            if flow.id != flow_ref.id {
+                warn!(manifest_id = %flow_ref.id, flow_id = %flow.id, "flow id differs from manifest entry");
+            }
+            flows.insert(flow_ref.id, flow);
+        }
+
+        let templates = TemplateCatalog::load(pack_dir, manifest.default_locale.as_deref())
+            .with_context(|| format!("invalid templates in pack {}", manifest.id))?;
+        for (template, locale) in templates.missing_translations() {
+            warn!(pack = %manifest.id, %template, %locale, "template missing translation");
+        }
+
+        Ok(Self {
+            pack_id: manifest.id,
+            flows,
+            templates,
+            components: pack_components(pack_dir)?,
+        })
+    }
+}
+
+/// Runs a component operation for `worker.request` (and other component-backed) nodes.
+pub trait ComponentInvoker: Send + Sync {
+    fn invoke(&self, wasm: &Path, ctx: &InvokeContext, op: &str, input: &Value) -> Result<Value>;
+}
+
+impl ComponentInvoker for ComponentHost {
+    fn invoke(&self, wasm: &Path, ctx: &InvokeContext, op: &str, input: &Value) -> Result<Value> {
+        let invocation = ComponentHost::invoke(self, wasm, ctx, op, input)?;
+        for status in &invocation.statuses {
+            debug!(flow_id = %ctx.flow_id, node = %ctx.node_id, %status, "component status");
+        }
+        Ok(invocation.output)
+    }
+}
+
+/// Caller identity and inbound payload for one flow run.
+#[derive(Debug, Clone, Default)]
+pub struct RunInput {
+    pub session_id: String,
+    pub tenant: String,
+    pub team: Option<String>,
+    pub user: Option<String>,
+    pub payload: Value,
+    /// Inbound trace context (e.g. the caller's `traceparent`); runs without one start a trace.
+    pub trace: Option<TraceContext>,
+    /// Preferred locale (session or inbound message) for message templates.
+    pub locale: Option<String>,
+    /// Node to start at instead of the flow's entry; it must exist in the flow.
+    pub start_node: Option<String>,
+    /// Isolation for the run; `None` runs against the shared state store and subjects.
+    pub sandbox: Option<Arc<PackSandbox>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OutboundMessage {
+    pub node: String,
+    pub provider: Option<String>,
+    pub channel: Option<String>,
+    pub text: String,
+    pub payload: Value,
+    /// Locale of the template the text was rendered from, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PublishedEvent {
+    pub node: String,
+    pub provider: Option<String>,
+    pub topic: String,
+    pub payload: Value,
+    /// True when the event went out over NATS rather than only being recorded.
+    pub delivered: bool,
+    /// Context sent in the NATS `traceparent` header (a child of the run's trace).
+    pub traceparent: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RunOutcome {
+    pub flow_id: String,
+    /// Node ids in visiting order.
+    pub trace: Vec<String>,
+    /// Messages captured by `messaging.send` nodes.
+    pub messages: Vec<OutboundMessage>,
+    pub events: Vec<PublishedEvent>,
+    /// Payload leaving the last node.
+    pub output: Value,
+    /// Missing-translation fallbacks taken while rendering templates.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+pub struct MiniRunner {
+    invoker: Arc<dyn ComponentInvoker>,
+    nats_url: Option<String>,
+    nats: Mutex<Option<async_nats::Client>>,
+    network: NetworkPolicy,
+    event_schemas: Option<SharedSchemaRegistry>,
+    sandboxes: Option<Sandboxes>,
+    debugger: Option<Debugger>,
+}
+
+impl MiniRunner {
+    /// `events.publish` nodes go to NATS when `nats_url` is set and are only recorded otherwise.
+    pub fn new(invoker: Arc<dyn ComponentInvoker>, nats_url: Option<String>) -> Self {
+        Self {
+            invoker,
+            nats_url,
+            nats: Mutex::new(None),
+            network: NetworkPolicy::default(),
+            event_schemas: None,
+            sandboxes: None,
+            debugger: None,
+        }
+    }
+
+    /// Gate the NATS connection behind `network` (e.g. localhost-only when offline).
+    pub fn with_network_policy(mut self, network: NetworkPolicy) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Validate typed `events.publish` payloads against `registry` before they are sent.
+    pub fn with_event_schemas(mut self, registry: SharedSchemaRegistry) -> Self {
+        self.event_schemas = Some(registry);
+        self
+    }
+
+    /// Isolate runs in sandboxes from `sandboxes` when it is enabled.
+    pub fn with_sandboxes(mut self, sandboxes: Sandboxes) -> Self {
+        self.sandboxes = Some(sandboxes);
+        self
+    }
+
+    /// Pause runs at the breakpoints set on `debugger`.
+    pub fn with_debugger(mut self, debugger: Debugger) -> Self {
+        self.debugger = Some(debugger);
+        self
+    }
+
+    /// A fresh sandbox for a run of `pack_id`, or `None` when sandboxing is off. The caller
+    /// passes it in [`RunInput::sandbox`] and tears it down once the run is over.
+    pub fn open_sandbox(&self, pack_id: &str) -> Result<Option<Arc<PackSandbox>>> {
+        self.sandboxes
+            .as_ref()
+            .filter(|sandboxes| sandboxes.enabled())
+            .map(|sandboxes| sandboxes.open(pack_id))
+            .transpose()
+    }
+
+    pub async fn run(
+        &self,
+        pack: &PackFlows,
+        flow_id: &str,
+        input: RunInput,
+    ) -> Result<RunOutcome> {
+        let flow = pack
+            .flows
+            .get(flow_id)
+            .ok_or_else(|| anyhow!("pack {} declares no flow {flow_id}", pack.pack_id))?;
+        let mut outcome = RunOutcome {
+            flow_id: flow_id.to_string(),
+            trace: Vec::new(),
+            messages: Vec::new(),
+            events: Vec::new(),
+            output: Value::Null,
+            warnings: Vec::new(),
+        };
+        let trace = input.trace.clone().unwrap_or_else(TraceContext::new_root);
+        let mut payload = input.payload.clone();
+        let mut current = match &input.start_node {
+            Some(node) if !flow.nodes.contains_key(node) => {
+                bail!("flow {flow_id} has no node {node} to start at")
+            }
+            Some(node) => Some(node.clone()),
+            None => Some(flow.entry.clone()),
+        };
+        let mut stepping = false;
+
+        while let Some(node_id) = current.take() {
+            if outcome.trace.len() >= MAX_STEPS {
+                bail!("flow {flow_id} exceeded {MAX_STEPS} steps; routing cycle?");
+            }
+            if let Some(debugger) = &self.debugger {
+                let stop = Stop {
+                    flow: flow_id,
+                    node: &node_id,
+                    session_id: &input.session_id,
+                    tenant: &input.tenant,
+                    user: input.user.as_deref(),
+                };
+                (payload, stepping) = debugger.checkpoint(stop, payload, stepping).await;
+            }
+            if let Some(sandbox) = &input.sandbox {
+                sandbox
+                    .check(
+                        outcome.trace.len() + 1,
+                        outcome.messages.len(),
+                        outcome.events.len(),
+                        serde_json::to_vec(&payload)?.len(),
+                    )
+                    .with_context(|| format!("flow {flow_id} at node {node_id}"))?;
+            }
+            let node = &flow.nodes[&node_id];
+            debug!(%flow_id, trace_id = %trace.trace_id, node = %node_id, operator = %node.operator, "mini-runner step");
+            outcome.trace.push(node_id.clone());
+            let spec = &node.spec;
+            let mut route = None;
+
+            if let Some(component_id) = &spec.component {
+                let wasm = pack.components.get(component_id).ok_or_else(|| {
+                    anyhow!(
+                        "node {node_id} uses component {component_id} not declared by pack {}",
+                        pack.pack_id
+                    )
+                })?;
+                let ctx = InvokeContext {
+                    pack_id: Some(pack.pack_id.clone()),
+                    tenant: input.tenant.clone(),
+                    team: input.team.clone(),
+                    user: input.user.clone(),
+                    flow_id: flow_id.to_string(),
+                    node_id: node_id.clone(),
+                    sandbox: input.sandbox.clone(),
+                };
+                let op = spec
+                    .config
+                    .get("op")
+                    .and_then(Value::as_str)
+                    .unwrap_or(&node.operator);
+                let mut request = json!({
+                    "payload": payload,
+                    "config": spec.config,
+                    "profile": spec.profile,
+                });
+                if let Some(sandbox) = &input.sandbox {
+                    request["sandbox"] = json!({
+                        "run_id": sandbox.run_id(),
+                        "dir": sandbox.dir(),
+                    });
+                }
+                let response = self
+                    .invoker
+                    .invoke(wasm, &ctx, op, &request)
+                    .with_context(|| format!("node {node_id} ({component_id}) failed"))?;
+                route = response
+                    .get("route")
+                    .and_then(Value::as_str)
+                    .map(str::to_string);
+                payload = response.get("payload").cloned().unwrap_or(response);
+            } else {
+                match node.operator.as_str() {
+                    "messaging.ingress" | "events.source" | "noop" => {}
+                    "messaging.send" => {
+                        let (text, locale) = match spec
+                            .config
+                            .get("template")
+                            .and_then(Value::as_str)
+                        {
+                            Some(name) => {
+                                let template = pack
+                                    .templates
+                                    .resolve(name, input.locale.as_deref())
+                                    .with_context(|| format!("node {node_id} template"))?;
+                                if let Some(warning) = template.warning {
+                                    warn!(%flow_id, node = %node_id, %warning, "template locale fallback");
+                                    outcome.warnings.push(warning);
+                                }
+                                let text = render_template(template.source, |key| {
+                                    identity_value(&input, key)
+                                        .or_else(|| payload_value(&payload, key))
+                                });
+                                (text, template.locale.map(str::to_string))
+                            }
+                            None => (message_text(&payload), None),
+                        };
+                        outcome.messages.push(OutboundMessage {
+                            node: node_id.clone(),
+                            provider: spec.provider.clone(),
+                            channel: spec.channel.as_deref().map(|c| render(c, &input)),
+                            text,
+                            payload: payload.clone(),
+                            locale,
+                        });
+                    }
+                    "events.publish" => {
+                        let mut topic = spec
+                            .topic
+                            .as_deref()
+                            .map(|t| render(t, &input))
+                            .ok_or_else(|| anyhow!("node {node_id} has no topic"))?;
+                        if let Some(sandbox) = &input.sandbox {
+                            topic = sandbox.subject(&topic);
+                        }
+                        self.check_event_schema(&payload)
+                            .with_context(|| format!("node {node_id} publish to {topic}"))?;
+                        let hop = trace.child();
+                        let delivered = self.publish(&topic, &payload, &hop).await?;
+                        outcome.events.push(PublishedEvent {
+                            node: node_id.clone(),
+                            provider: spec.provider.clone(),
+                            topic,
+                            payload: payload.clone(),
+                            delivered,
+                            traceparent: hop.to_string(),
+                        });
+                    }
+                    other => bail!("node {node_id} uses unsupported operator {other}"),
+                }
+            }
+
+            current = route
+                .as_deref()
+                .and_then(|label| spec.routing.get(label))
+                .or_else(|| spec.routing.get("default"))
+                .cloned();
+        }
+
+        if let Some(sandbox) = &input.sandbox {
+            sandbox
+                .check(
+                    outcome.trace.len(),
+                    outcome.messages.len(),
+                    outcome.events.len(),
+                    serde_json::to_vec(&payload)?.len(),
+                )
+                .with_context(|| format!("flow {flow_id}"))?;
+        }
+        outcome.output = payload;
+        info!(
+            %flow_id,
+            trace_id = %trace.trace_id,
+            steps = outcome.trace.len(),
+            messages = outcome.messages.len(),
+            events = outcome.events.len(),
+            "mini-runner flow finished"
+        );
+        Ok(outcome)
+    }
+
+    /// Feed each user turn of a scenario through `flow_id` and return the resulting
+    /// transcript in golden-file form (`USER: ...` / `BOT: ...`).
+    pub async fn run_scenario(
+        &self,
+        pack: &PackFlows,
+        flow_id: &str,
+        scenario: &Value,
+        base: RunInput,
+    ) -> Result<Vec<String>> {
+        let steps = scenario
+            .get("steps")
+            .and_then(Value::as_array)
+            .ok_or_else(|| anyhow!("scenario has no steps"))?;
+        // One sandbox for the whole scenario so state carries over between turns.
+        let opened = match base.sandbox {
+            Some(_) => None,
+            None => self.open_sandbox(&pack.pack_id)?,
+        };
+        let base = RunInput {
+            sandbox: base.sandbox.clone().or_else(|| opened.clone()),
+            ..base
+        };
+        let transcript = self.run_turns(pack, flow_id, steps, &base).await;
+        if let Some(sandbox) = opened {
+            sandbox.teardown()?;
+        }
+        transcript
+    }
+
+    async fn run_turns(
+        &self,
+        pack: &PackFlows,
+        flow_id: &str,
+        steps: &[Value],
+        base: &RunInput,
+    ) -> Result<Vec<String>> {
+        let mut transcript = Vec::new();
+        for step in steps {
+            if step.get("actor").and_then(Value::as_str) != Some("user") {
+                continue;
+            }
+            let text = step
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            transcript.push(format!("USER: {text}"));
+            let input = RunInput {
+                payload: json!({ "text": text }),
+                ..base.clone()
+            };
+            let outcome = self.run(pack, flow_id, input).await?;
+            transcript.extend(
+                outcome
+                    .messages
+                    .into_iter()
+                    .map(|message| format!("BOT: {}", message.text)),
+            );
+        }
+        Ok(transcript)
+    }
+
+    fn check_event_schema(&self, payload: &Value) -> Result<()> {
+        let Some(registry) = &self.event_schemas else {
+            return Ok(());
+        };
+        let violations = registry.read().validate(payload)?;
+        if let Some(first) = violations.first() {
+            bail!(
+                "event violates schema of {} ({} violation(s); first at {:?}: {})",
+                payload["type"],
+                violations.len(),
+                first.pointer,
+                first.message
+            );
+        }
+        Ok(())
+    }
+
+    async fn publish(&self, topic: &str, payload: &Value, trace: &TraceContext) -> Result<bool> {
+        let Some(url) = &self.nats_url else {
+            return Ok(false);
+        };
+        let cached = self.nats.lock().clone();
+        let client = match cached {
+            Some(client) => client,
+            None => {
+                self.network.check("NATS", url)?;
+                let client = async_nats::connect(url.as_str())
+                    .await
+                    .with_context(|| format!("failed to connect to NATS at {url}"))?;
+                *self.nats.lock() = Some(client.clone());
+                client
+            }
+        };
+        client
+            .publish_with_headers(
+                topic.to_string(),
+                trace.headers(),
+                serde_json::to_vec(payload)?.into(),
+            )
+            .await
+            .with_context(|| format!("failed to publish to {topic}"))?;
+        client.flush().await?;
+        Ok(true)
+    }
+}
+
+fn render(template: &str, input: &RunInput) -> String {
+    template
+        .replace("{{session_id}}", &input.session_id)
+        .replace("{{tenant}}", &input.tenant)
+        .replace("{{team}}", input.team.as_deref().unwrap_or_default())
+        .replace("{{user}}", input.user.as_deref().unwrap_or_default())
+}
+
+/// Caller fields available to templates alongside the payload.
+fn identity_value(input: &RunInput, key: &str) -> Option<String> {
+    match key {
+        "session_id" => Some(input.session_id.clone()),
+        "tenant" => Some(input.tenant.clone()),
+        "team" => input.team.clone(),
+        "user" => input.user.clone(),
+        "locale" => input.locale.clone(),
+        _ => None,
+    }
+}
+
+fn message_text(payload: &Value) -> String {
+    ["text", "message"]
+        .iter()
+        .find_map(|key| payload.get(*key).and_then(Value::as_str))
+        .map(str::to_string)
+        .unwrap_or_else(|| match payload {
+            Value::String(text) => text.clone(),
+            other => other.to_string(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answers like the repo assistant worker, requesting a rebuild when asked to.
+    struct FakeWorker;
+
+    impl ComponentInvoker for FakeWorker {
+        fn invoke(
+            &self,
+            _wasm: &Path,
+            ctx: &InvokeContext,
+            _op: &str,
+            input: &Value,
+        ) -> Result<Value> {
+            assert_eq!(ctx.node_id, "to_worker");
+            let text = input["payload"]["text"].as_str().unwrap_or_default();
+            if text.contains("rebuild") {
+                Ok(json!({"route": "rebuild_requested", "payload": {"repo": "my-service"}}))
+            } else {
+                Ok(json!({"payload": {"text": format!("echo: {text}")}}))
+            }
+        }
+    }
+
+    fn demo_pack() -> PackFlows {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../..");
+        PackFlows::load(&root.join("packs/integration-demos"), &root).unwrap()
+    }
+
+    #[tokio::test]
+    async fn routes_through_worker_to_send_or_publish() {
+        let pack = demo_pack();
+        let runner = MiniRunner::new(Arc::new(FakeWorker), None);
+        let root = TraceContext::new_root();
+        let input = RunInput {
+            session_id: "sess-1".into(),
+            tenant: "dev".into(),
+            trace: Some(root.clone()),
+            ..RunInput::default()
+        };
+
+        let outcome = runner
+            .run(
+                &pack,
+                "repo_assistant_chat",
+                RunInput {
+                    payload: json!({"text": "status"}),
+                    ..input.clone()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            outcome.trace,
+            vec!["ingress_message", "to_worker", "respond", "done"]
+        );
+        assert_eq!(outcome.messages[0].text, "echo: status");
+        assert_eq!(outcome.messages[0].channel.as_deref(), Some("sess-1"));
+
+        let outcome = runner
+            .run(
+                &pack,
+                "repo_assistant_chat",
+                RunInput {
+                    payload: json!({"text": "please rebuild"}),
+                    ..input
+                },
+            )
+            .await
+            .unwrap();
+        assert!(outcome.messages.is_empty());
+        assert_eq!(outcome.events[0].topic, "greentic.repo.build.request");
+        assert!(!outcome.events[0].delivered);
+        let sent = TraceContext::parse(&outcome.events[0].traceparent).unwrap();
+        assert_eq!(sent.trace_id, root.trace_id);
+        assert_ne!(sent.span_id, root.span_id);
+    }
+
+    async fn next_pause(debugger: &Debugger) -> crate::debugger::PausedRun {
+        for _ in 0..500 {
+            if let Some(run) = debugger.paused().pop() {
+                return run;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("run never paused");
+    }
+
+    #[tokio::test]
+    async fn breakpoints_pause_before_a_node_and_step_to_the_next() {
+        use crate::debugger::{Breakpoint, Resume};
+
+        let pack = demo_pack();
+        let debugger = Debugger::default();
+        debugger.add(Breakpoint {
+            flow: "repo_assistant_chat".into(),
+            node: "respond".into(),
+        });
+        let runner =
+            Arc::new(MiniRunner::new(Arc::new(FakeWorker), None).with_debugger(debugger.clone()));
+        let run = tokio::spawn({
+            let runner = runner.clone();
+            async move {
+                let input = RunInput {
+                    session_id: "sess-1".into(),
+                    tenant: "dev".into(),
+                    payload: json!({"text": "status"}),
+                    ..RunInput::default()
+                };
+                runner.run(&pack, "repo_assistant_chat", input).await
+            }
+        });
+
+        let at_respond = next_pause(&debugger).await;
+        assert_eq!(at_respond.node, "respond");
+        assert!(!at_respond.stepped);
+        assert_eq!(at_respond.payload["text"], "echo: status");
+        assert!(debugger.resume(
+            &at_respond.id,
+            Resume {
+                payload: Some(json!({"text": "edited"})),
+                step: true,
+            },
+        ));
+
+        let at_done = next_pause(&debugger).await;
+        assert_eq!(at_done.node, "done");
+        assert!(at_done.stepped);
+        assert!(debugger.resume(&at_done.id, Resume::default()));
+
+        let outcome = run.await.unwrap().unwrap();
+        assert_eq!(
+            outcome.trace,
+            vec!["ingress_message", "to_worker", "respond", "done"]
+        );
+        assert_eq!(outcome.messages[0].text, "edited");
+    }
+
+    #[tokio::test]
+    async fn rejects_published_events_that_break_their_schema() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(
+            tmp.path().join("pack.json"),
+            r#"{"id": "emit", "flows": [{"id": "emit", "file": "emit.ygtc"}]}"#,
+        )
+        .unwrap();
+        fs::write(
+            tmp.path().join("emit.ygtc"),
+            "id: emit\nnodes:\n  publish:\n    events.publish:\n      topic: greentic.repo.build.request\n",
+        )
+        .unwrap();
+        let pack = PackFlows::load(tmp.path(), tmp.path()).unwrap();
+        let registry = Arc::new(parking_lot::RwLock::new(
+            crate::event_schema::SchemaRegistry::builtin(),
+        ));
+        let runner = MiniRunner::new(Arc::new(FakeWorker), None).with_event_schemas(registry);
+        let event = json!({
+            "topic": "greentic.repo.build.request",
+            "type": "com.greentic.repo.build.request.v1",
+            "subject": "repo:my-service",
+            "tenant": {"id": "tenant-123"},
+            "payload": {"reason": "manual"},
+        });
+        let run = |payload: Value| RunInput {
+            tenant: "dev".into(),
+            payload,
+            ..RunInput::default()
+        };
+
+        let outcome = runner.run(&pack, "emit", run(event.clone())).await.unwrap();
+        assert_eq!(outcome.events.len(), 1);
+
+        let mut broken = event;
+        broken["payload"] = json!({});
+        let err = runner.run(&pack, "emit", run(broken)).await.unwrap_err();
+        assert!(format!("{err:#}").contains("violates schema"));
+    }
+
+    #[tokio::test]
+    async fn sandboxed_runs_publish_under_their_own_subjects_within_budget() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(
+            tmp.path().join("pack.json"),
+            r#"{"id": "emit", "flows": [{"id": "emit", "file": "emit.ygtc"}]}"#,
+        )
+        .unwrap();
+        fs::write(
+            tmp.path().join("emit.ygtc"),
+            "id: emit\nnodes:\n  first:\n    events.publish:\n      topic: greentic.demo\n      routing:\n        default: second\n  second:\n    events.publish:\n      topic: greentic.demo\n",
+        )
+        .unwrap();
+        let pack = PackFlows::load(tmp.path(), tmp.path()).unwrap();
+        let config = |max_events| crate::pack_sandbox::SandboxConfig {
+            enabled: true,
+            root: Some(camino::Utf8PathBuf::from_path_buf(tmp.path().join("sandboxes")).unwrap()),
+            max_events,
+            ..Default::default()
+        };
+        let state = crate::state_store::InMemoryStateStore::new();
+        let runner = MiniRunner::new(Arc::new(FakeWorker), None)
+            .with_sandboxes(Sandboxes::new(config(2), state.clone()));
+        let run = |sandbox| RunInput {
+            tenant: "dev".into(),
+            sandbox,
+            ..RunInput::default()
+        };
+
+        let (first, second) = (
+            runner.open_sandbox("emit").unwrap().unwrap(),
+            runner.open_sandbox("emit").unwrap().unwrap(),
+        );
+        let (a, b) = tokio::join!(
+            runner.run(&pack, "emit", run(Some(first.clone()))),
+            runner.run(&pack, "emit", run(Some(second.clone()))),
+        );
+        let (a, b) = (a.unwrap(), b.unwrap());
+        assert_eq!(a.events[0].topic, first.subject("greentic.demo"));
+        assert_eq!(b.events[1].topic, second.subject("greentic.demo"));
+        assert_ne!(a.events[0].topic, b.events[0].topic);
+        first.teardown().unwrap();
+        assert!(!first.dir().exists());
+
+        let unsandboxed = runner.run(&pack, "emit", run(None)).await.unwrap();
+        assert_eq!(unsandboxed.events[0].topic, "greentic.demo");
+
+        let tight = MiniRunner::new(Arc::new(FakeWorker), None)
+            .with_sandboxes(Sandboxes::new(config(1), state));
+        let sandbox = tight.open_sandbox("emit").unwrap();
+        let err = tight.run(&pack, "emit", run(sandbox)).await.unwrap_err();
+        assert!(format!("{err:#}").contains("more than 1 events"));
+    }
+
+    #[tokio::test]
+    async fn starts_at_the_requested_node() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(
+            tmp.path().join("pack.json"),
+            r#"{"id": "emit", "flows": [{"id": "emit", "file": "emit.ygtc"}]}"#,
+        )
+        .unwrap();
+        fs::write(
+            tmp.path().join("emit.ygtc"),
+            "id: emit\nnodes:\n  first:\n    events.publish:\n      topic: greentic.first\n      routing:\n        default: second\n  second:\n    events.publish:\n      topic: greentic.second\n",
+        )
+        .unwrap();
+        let pack = PackFlows::load(tmp.path(), tmp.path()).unwrap();
+        let runner = MiniRunner::new(Arc::new(FakeWorker), None);
+        let run = |start_node: Option<&str>| RunInput {
+            tenant: "dev".into(),
+            start_node: start_node.map(str::to_string),
+            ..RunInput::default()
+        };
+
+        let outcome = runner
+            .run(&pack, "emit", run(Some("second")))
+            .await
+            .unwrap();
+        assert_eq!(outcome.trace, vec!["second"]);
+        assert_eq!(outcome.events[0].topic, "greentic.second");
+
+        let err = runner
+            .run(&pack, "emit", run(Some("missing")))
+            .await
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("has no node missing"));
+    }
+
+    #[tokio::test]
+    async fn renders_send_templates_in_the_caller_locale() {
+        let tmp = tempfile::tempdir().unwrap();
+        let pack_dir = tmp.path().join("notify");
+        fs::create_dir_all(pack_dir.join("templates")).unwrap();
+        fs::write(
+            pack_dir.join("pack.json"),
+            r#"{"id": "notify", "flows": [{"id": "notify", "file": "notify.ygtc"}]}"#,
+        )
+        .unwrap();
+        fs::write(
+            pack_dir.join("notify.ygtc"),
+            "id: notify\nnodes:\n  send:\n    messaging.send:\n      config:\n        template: build_status\n",
+        )
+        .unwrap();
+        fs::write(
+            pack_dir.join("templates/build_status.en.hbs"),
+            "Build {{status}} for {{repo}}",
+        )
+        .unwrap();
+        fs::write(
+            pack_dir.join("templates/build_status.de.hbs"),
+            "Build für {{repo}}: {{status}} ({{tenant}})",
+        )
+        .unwrap();
+        let pack = PackFlows::load(&pack_dir, tmp.path()).unwrap();
+        let runner = MiniRunner::new(Arc::new(FakeWorker), None);
+        let run = |locale: Option<&str>| RunInput {
+            tenant: "acme".into(),
+            payload: json!({"repo": "my-service", "status": "success"}),
+            locale: locale.map(str::to_string),
+            ..RunInput::default()
+        };
+
+        let outcome = runner.run(&pack, "notify", run(Some("de"))).await.unwrap();
+        assert_eq!(
+            outcome.messages[0].text,
+            "Build für my-service: success (acme)"
+        );
+        assert_eq!(outcome.messages[0].locale.as_deref(), Some("de"));
+        assert!(outcome.warnings.is_empty());
+
+        let outcome = runner
+            .run(&pack, "notify", run(Some("fr-CA")))
+            .await
+            .unwrap();
+        assert_eq!(outcome.messages[0].text, "Build success for my-service");
+        assert_eq!(outcome.messages[0].locale.as_deref(), Some("en"));
+        assert_eq!(
+            outcome.warnings,
+            vec!["template build_status has no fr-ca translation; fell back to en"]
+        );
+    }
+}
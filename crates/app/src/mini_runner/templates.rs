@@ -0,0 +1,247 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+};
+
+use anyhow::{Context, Result, anyhow};
+use serde_json::Value;
+
+/// Directory inside a pack holding outbound message templates.
+pub const TEMPLATES_DIR: &str = "templates";
+
+/// Locale assumed for unsuffixed templates when the manifest sets no `default_locale`.
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// Message templates named `<name>.<locale>.hbs` (or `<name>.hbs`, the locale-neutral
+/// fallback) under a pack's `templates/` directory.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateCatalog {
+    default_locale: String,
+    /// Template name -> normalized locale (`""` for the unsuffixed file) -> source.
+    templates: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// Template source chosen for a requested locale.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedTemplate<'a> {
+    pub source: &'a str,
+    /// Locale of the file used; `None` for the unsuffixed fallback.
+    pub locale: Option<&'a str>,
+    /// Set when the requested locale had no translation and a fallback was used.
+    pub warning: Option<String>,
+}
+
+impl TemplateCatalog {
+    pub fn load(pack_dir: &Path, default_locale: Option<&str>) -> Result<Self> {
+        let mut catalog = Self {
+            default_locale: normalize_locale(default_locale.unwrap_or(DEFAULT_LOCALE)),
+            templates: BTreeMap::new(),
+        };
+        let dir = pack_dir.join(TEMPLATES_DIR);
+        if !dir.is_dir() {
+            return Ok(catalog);
+        }
+        for entry in
+            fs::read_dir(&dir).with_context(|| format!("failed to read {}", dir.display()))?
+        {
+            let path = entry?.path();
+            let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let Some(stem) = file_name.strip_suffix(".hbs") else {
+                continue;
+            };
+            let (name, locale) = match stem.split_once('.') {
+                Some((name, locale)) => (name, normalize_locale(locale)),
+                None => (stem, String::new()),
+            };
+            let source = fs::read_to_string(&path)
+                .with_context(|| format!("failed to read template {}", path.display()))?;
+            catalog
+                .templates
+                .entry(name.to_string())
+                .or_default()
+                .insert(locale, source);
+        }
+        Ok(catalog)
+    }
+
+    /// Pick `name` for `locale` along the chain `de-ch` -> `de` -> default locale ->
+    /// unsuffixed file. Errors only when no variant of the template exists.
+    pub fn resolve(&self, name: &str, locale: Option<&str>) -> Result<ResolvedTemplate<'_>> {
+        let variants = self
+            .templates
+            .get(name)
+            .ok_or_else(|| anyhow!("no template named {name}"))?;
+        let requested = locale.map(normalize_locale);
+        let mut chain = Vec::new();
+        if let Some(requested) = &requested {
+            chain.push(requested.clone());
+            if let Some((language, _)) = requested.split_once('-') {
+                chain.push(language.to_string());
+            }
+        }
+        chain.push(self.default_locale.clone());
+        chain.push(String::new());
+
+        let (found, source) = chain
+            .iter()
+            .find_map(|candidate| variants.get_key_value(candidate))
+            .ok_or_else(|| anyhow!("template {name} has no {} or fallback variant", chain[0]))?;
+        let warning = requested
+            .filter(|requested| requested != found)
+            .map(|requested| {
+                let used = if found.is_empty() {
+                    "the unsuffixed template"
+                } else {
+                    found.as_str()
+                };
+                format!("template {name} has no {requested} translation; fell back to {used}")
+            });
+        Ok(ResolvedTemplate {
+            source,
+            locale: (!found.is_empty()).then_some(found.as_str()),
+            warning,
+        })
+    }
+
+    /// `(template, locale)` pairs where some other template of the pack is translated into
+    /// `locale` but this one is not: the translation coverage gaps of the pack. Templates that
+    /// only exist unsuffixed are locale-neutral and never reported.
+    pub fn missing_translations(&self) -> Vec<(String, String)> {
+        let locales: BTreeSet<&str> = self
+            .templates
+            .values()
+            .flat_map(|variants| variants.keys())
+            .filter(|locale| !locale.is_empty())
+            .map(String::as_str)
+            .collect();
+        let mut missing = Vec::new();
+        for (name, variants) in &self.templates {
+            if variants.keys().all(String::is_empty) {
+                continue;
+            }
+            for locale in &locales {
+                if !variants.contains_key(*locale) {
+                    missing.push((name.clone(), locale.to_string()));
+                }
+            }
+        }
+        missing
+    }
+}
+
+/// Lower-case BCP 47 tag with `-` separators (`de_CH` -> `de-ch`).
+fn normalize_locale(locale: &str) -> String {
+    locale.trim().replace('_', "-").to_ascii_lowercase()
+}
+
+/// Replace `{{key}}` / `{{ a.b }}` with values looked up by `lookup`; unknown keys render empty.
+pub fn render_template(source: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut rest = source;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            out.push_str(&rest[start..]);
+            return out;
+        };
+        out.push_str(&lookup(after[..end].trim()).unwrap_or_default());
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Dotted-path lookup into a JSON payload, rendering scalars without quotes.
+pub fn payload_value(payload: &Value, path: &str) -> Option<String> {
+    let value = path
+        .split('.')
+        .try_fold(payload, |value, key| value.get(key))?;
+    match value {
+        Value::Null => None,
+        Value::String(text) => Some(text.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn catalog(files: &[(&str, &str)], default_locale: Option<&str>) -> TemplateCatalog {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join(TEMPLATES_DIR);
+        fs::create_dir_all(&dir).unwrap();
+        for (name, body) in files {
+            fs::write(dir.join(name), body).unwrap();
+        }
+        TemplateCatalog::load(tmp.path(), default_locale).unwrap()
+    }
+
+    #[test]
+    fn resolves_locale_fallback_chain_with_warnings() {
+        let catalog = catalog(
+            &[
+                ("build_status.en.hbs", "Build {{status}}"),
+                ("build_status.de.hbs", "Build {{status}} (de)"),
+                ("build_status.de_CH.hbs", "Build {{status}} (ch)"),
+                ("greeting.hbs", "Hi"),
+            ],
+            None,
+        );
+
+        let ch = catalog.resolve("build_status", Some("de-CH")).unwrap();
+        assert_eq!((ch.locale, ch.warning.as_deref()), (Some("de-ch"), None));
+
+        let at = catalog.resolve("build_status", Some("de-AT")).unwrap();
+        assert_eq!(at.locale, Some("de"));
+        assert!(
+            at.warning
+                .unwrap()
+                .contains("no de-at translation; fell back to de")
+        );
+
+        let fr = catalog.resolve("build_status", Some("fr")).unwrap();
+        assert_eq!(fr.source, "Build {{status}}");
+        assert!(fr.warning.is_some());
+
+        let neutral = catalog.resolve("build_status", None).unwrap();
+        assert_eq!((neutral.locale, neutral.warning), (Some("en"), None));
+
+        let plain = catalog.resolve("greeting", Some("de")).unwrap();
+        assert_eq!(plain.locale, None);
+        assert!(plain.warning.unwrap().contains("unsuffixed"));
+
+        assert!(catalog.resolve("missing", Some("en")).is_err());
+    }
+
+    #[test]
+    fn reports_translation_coverage_gaps() {
+        let catalog = catalog(
+            &[
+                ("build_status.en.hbs", ""),
+                ("build_status.de.hbs", ""),
+                ("deploy_done.en.hbs", ""),
+                ("footer.hbs", ""),
+            ],
+            Some("en"),
+        );
+        assert_eq!(
+            catalog.missing_translations(),
+            vec![("deploy_done".to_string(), "de".to_string())]
+        );
+    }
+
+    #[test]
+    fn renders_payload_placeholders() {
+        let payload = json!({"repo": {"name": "my-service"}, "attempt": 2});
+        let text = render_template("{{ repo.name }} #{{attempt}}{{missing}} {{", |key| {
+            payload_value(&payload, key)
+        });
+        assert_eq!(text, "my-service #2 {{");
+    }
+}
@@ -0,0 +1,248 @@
+//! Outbound network policy for `serve --offline`: only loopback targets may be contacted, so
+//! a hermetic run fails loudly instead of silently reaching the internet.
+
+use std::{net::IpAddr, time::Duration};
+
+use anyhow::{Result, bail};
+use serde_json::Value;
+
+/// ureq's own limit, kept for online requests.
+const DEFAULT_MAX_REDIRECTS: u32 = 10;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NetworkPolicy {
+    offline: bool,
+}
+
+impl NetworkPolicy {
+    pub fn offline() -> Self {
+        Self { offline: true }
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Reject `url` unless the policy is online or it points at a loopback host.
+    /// `purpose` names the feature in the error (e.g. "runner proxy", "NATS").
+    pub fn check(&self, purpose: &str, url: &str) -> Result<()> {
+        if !self.offline {
+            return Ok(());
+        }
+        match host_of(url) {
+            Some(host) if is_loopback_host(host) => Ok(()),
+            Some(host) => bail!(
+                "offline mode: {purpose} target {url} needs network access to {host}; \
+                 point it at localhost or run without --offline"
+            ),
+            None => bail!("offline mode: cannot determine the host of {purpose} target {url}"),
+        }
+    }
+}
+
+/// HTTP client wrapper that consults the [`NetworkPolicy`] before every request.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OutboundHttp {
+    policy: NetworkPolicy,
+    /// Bound on each whole request; none waits as long as the server keeps the connection.
+    timeout: Option<Duration>,
+}
+
+impl OutboundHttp {
+    pub fn new(policy: NetworkPolicy) -> Self {
+        Self {
+            policy,
+            timeout: None,
+        }
+    }
+
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Offline, redirects are returned instead of followed: a loopback target could otherwise
+    /// send the request on to any host.
+    fn max_redirects(&self) -> u32 {
+        if self.policy.offline {
+            0
+        } else {
+            DEFAULT_MAX_REDIRECTS
+        }
+    }
+
+    /// GET `url` and return the response status and body (error statuses included).
+    pub fn get_as(&self, purpose: &str, url: &str, bearer: Option<&str>) -> Result<(u16, String)> {
+        self.policy.check(purpose, url)?;
+        let mut request = ureq::get(url);
+        if let Some(token) = bearer {
+            request = request.header("Authorization", &format!("Bearer {token}"));
+        }
+        let response = request
+            .config()
+            .http_status_as_error(false)
+            .timeout_global(self.timeout)
+            .max_redirects(self.max_redirects())
+            .build()
+            .call();
+        read_response(purpose, url, response)
+    }
+
+    /// POST `payload` and return the response status and body (error statuses included).
+    pub fn post_json(&self, purpose: &str, url: &str, payload: Value) -> Result<(u16, String)> {
+        self.post_json_as(purpose, url, None, payload)
+    }
+
+    /// [`Self::post_json`] with an optional `Authorization: Bearer` token.
+    pub fn post_json_as(
+        &self,
+        purpose: &str,
+        url: &str,
+        bearer: Option<&str>,
+        payload: Value,
+    ) -> Result<(u16, String)> {
+        self.policy.check(purpose, url)?;
+        let mut request = ureq::post(url);
+        if let Some(token) = bearer {
+            request = request.header("Authorization", &format!("Bearer {token}"));
+        }
+        let response = request
+            .config()
+            .http_status_as_error(false)
+            .timeout_global(self.timeout)
+            .max_redirects(self.max_redirects())
+            .build()
+            .send_json(payload);
+        read_response(purpose, url, response)
+    }
+
+    /// POST an `application/x-www-form-urlencoded` body (e.g. an OAuth token request).
+    pub fn post_form(
+        &self,
+        purpose: &str,
+        url: &str,
+        form: &[(&str, &str)],
+    ) -> Result<(u16, String)> {
+        self.policy.check(purpose, url)?;
+        let response = ureq::post(url)
+            .config()
+            .http_status_as_error(false)
+            .timeout_global(self.timeout)
+            .max_redirects(self.max_redirects())
+            .build()
+            .send_form(form.iter().copied());
+        read_response(purpose, url, response)
+    }
+}
+
+fn read_response(
+    purpose: &str,
+    url: &str,
+    response: Result<ureq::http::Response<ureq::Body>, ureq::Error>,
+) -> Result<(u16, String)> {
+    match response {
+        Ok(resp) => {
+            let status = resp.status().as_u16();
+            let body = resp.into_body().read_to_string().unwrap_or_default();
+            Ok((status, body))
+        }
+        Err(err) => bail!("{purpose} request to {url} failed: {err}"),
+    }
+}
+
+/// Host part of `scheme://[user@]host[:port]/...`, also accepting bare `host:port`
+/// (the NATS client allows both). IPv6 literals are returned without brackets.
+fn host_of(url: &str) -> Option<&str> {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let authority = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = if let Some(v6) = authority.strip_prefix('[') {
+        v6.split_once(']')?.0
+    } else {
+        authority.split(':').next()?
+    };
+    (!host.is_empty()).then_some(host)
+}
+
+fn is_loopback_host(host: &str) -> bool {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    if host == "localhost" || host.ends_with(".localhost") {
+        return true;
+    }
+    host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offline_policy_allows_only_loopback_targets() {
+        let offline = NetworkPolicy::offline();
+        for url in [
+            "http://localhost:8081",
+            "http://127.0.0.1/runner",
+            "nats://user:pw@127.0.0.2:4222",
+            "localhost:4222",
+            "redis://[::1]:6379/0",
+            "http://api.localhost",
+        ] {
+            offline
+                .check("test", url)
+                .unwrap_or_else(|err| panic!("{url}: {err}"));
+        }
+        for url in [
+            "http://runner.example.com:8081",
+            "nats://10.0.0.5:4222",
+            "demo.nats.io:4222",
+            "redis://[2001:db8::1]:6379",
+        ] {
+            let err = offline.check("NATS", url).unwrap_err().to_string();
+            assert!(err.contains("offline mode: NATS"), "{url}: {err}");
+        }
+        NetworkPolicy::default()
+            .check("test", "https://example.com")
+            .unwrap();
+    }
+
+    #[test]
+    fn outbound_http_refuses_remote_targets_without_connecting() {
+        let err = OutboundHttp::new(NetworkPolicy::offline())
+            .post_json("runner proxy", "http://192.0.2.1/runner/emit", Value::Null)
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("needs network access to 192.0.2.1")
+        );
+    }
+
+    #[test]
+    fn offline_requests_do_not_follow_redirects_off_host() {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 4096];
+            let _ = stream.read(&mut request).unwrap();
+            stream
+                .write_all(
+                    b"HTTP/1.1 302 Found\r\nLocation: http://192.0.2.1/exfil\r\n\
+                      Content-Length: 0\r\nConnection: close\r\n\r\n",
+                )
+                .unwrap();
+        });
+
+        let (status, _) = OutboundHttp::new(NetworkPolicy::offline())
+            .with_timeout(Duration::from_secs(5))
+            .get_as("webhook", &url, None)
+            .unwrap();
+        assert_eq!(status, 302);
+        server.join().unwrap();
+    }
+}
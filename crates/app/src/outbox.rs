@@ -0,0 +1,532 @@
+//! Outbox for provider sends (`[server.outbox]`, `GET /outbox`). Messages captured by
+//! `messaging.send` nodes in embedded runs are persisted in `[stores.state]` before anything
+//! is sent; a dispatcher loop posts them to the provider's sink, retrying with backoff until
+//! the sink accepts or `max_attempts` is spent, so a sink that is down mid-scenario delays
+//! messages instead of losing them.
+#![cfg_attr(not(feature = "mini-runner"), allow(dead_code))]
+
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::network::OutboundHttp;
+use crate::providers::Providers;
+use crate::session::current_timestamp_ms;
+use crate::state_store::StateStore;
+
+const NAMESPACE: &str = "outbox";
+const KEY: &str = "entries";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxConfig {
+    /// Queue and dispatch provider sends; off keeps messages in the run outcome only.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Send URL per provider id; `*` catches providers without their own entry. Providers
+    /// configured under `[providers.<name>]` are sent to their endpoint unless listed here.
+    #[serde(default)]
+    pub sinks: BTreeMap<String, String>,
+    /// Attempts before an entry is marked `failed` and left for inspection.
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled after every failed attempt.
+    #[serde(default = "default_retry_initial_ms")]
+    pub retry_initial_ms: u64,
+    #[serde(default = "default_retry_max_ms")]
+    pub retry_max_ms: u64,
+    /// How often the dispatcher looks for due entries.
+    #[serde(default = "default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+    /// An entry still unsent this long after it was queued counts as stuck.
+    #[serde(default = "default_stuck_after_secs")]
+    pub stuck_after_secs: u64,
+    /// Sent entries kept for inspection; older ones are dropped.
+    #[serde(default = "default_keep_sent")]
+    pub keep_sent: usize,
+}
+
+impl Default for OutboxConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sinks: BTreeMap::new(),
+            max_attempts: default_max_attempts(),
+            retry_initial_ms: default_retry_initial_ms(),
+            retry_max_ms: default_retry_max_ms(),
+            poll_interval_ms: default_poll_interval_ms(),
+            stuck_after_secs: default_stuck_after_secs(),
+            keep_sent: default_keep_sent(),
+        }
+    }
+}
+
+fn default_max_attempts() -> u32 {
+    8
+}
+
+fn default_retry_initial_ms() -> u64 {
+    500
+}
+
+fn default_retry_max_ms() -> u64 {
+    30_000
+}
+
+fn default_poll_interval_ms() -> u64 {
+    250
+}
+
+fn default_stuck_after_secs() -> u64 {
+    60
+}
+
+fn default_keep_sent() -> usize {
+    200
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutboxStatus {
+    Pending,
+    Sent,
+    /// Gave up after `max_attempts`.
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxEntry {
+    pub id: String,
+    pub provider: Option<String>,
+    /// Sink of the latest attempt (or the one configured when queued).
+    pub url: String,
+    /// Body posted to the sink; carries `outbox_id` so a sink can drop redelivered copies.
+    pub body: Value,
+    pub status: OutboxStatus,
+    pub attempts: u32,
+    pub created_at_ms: u64,
+    pub next_attempt_at_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sent_at_ms: Option<u64>,
+}
+
+impl OutboxEntry {
+    /// Failed, or queued longer than `stuck_after_ms` without being sent.
+    pub fn is_stuck(&self, now_ms: u64, stuck_after_ms: u64) -> bool {
+        match self.status {
+            OutboxStatus::Sent => false,
+            OutboxStatus::Failed => true,
+            OutboxStatus::Pending => now_ms.saturating_sub(self.created_at_ms) >= stuck_after_ms,
+        }
+    }
+}
+
+/// Entry counts by status, as reported on `GET /outbox`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct OutboxCounts {
+    pub pending: usize,
+    pub sent: usize,
+    pub failed: usize,
+}
+
+/// What one dispatcher pass did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DispatchRound {
+    pub sent: usize,
+    pub retried: usize,
+    pub failed: usize,
+}
+
+/// Where one send goes.
+struct Sink<'a> {
+    url: &'a str,
+    bearer: Option<&'a str>,
+    timeout: Option<Duration>,
+}
+
+#[derive(Clone)]
+pub struct Outbox {
+    store: Arc<dyn StateStore>,
+    config: OutboxConfig,
+    providers: Providers,
+    /// Serializes read-modify-write cycles on the stored list within this process.
+    lock: Arc<Mutex<()>>,
+}
+
+impl Outbox {
+    pub fn new(store: Arc<dyn StateStore>, config: OutboxConfig) -> Self {
+        Self {
+            store,
+            config,
+            providers: Providers::default(),
+            lock: Arc::default(),
+        }
+    }
+
+    /// Send to the `[providers.<name>]` endpoints, with their credentials and timeout.
+    pub fn with_providers(self, providers: Providers) -> Self {
+        Self { providers, ..self }
+    }
+
+    /// The provider's own sink, then its `[providers]` endpoint, then the `*` sink.
+    fn sink_for(&self, provider: Option<&str>) -> Option<Sink<'_>> {
+        fn plain(url: &String) -> Sink<'_> {
+            Sink {
+                url,
+                bearer: None,
+                timeout: None,
+            }
+        }
+        let Some(provider) = provider else {
+            return self.config.sinks.get("*").map(plain);
+        };
+        if let Some(url) = self.config.sinks.get(provider) {
+            return Some(plain(url));
+        }
+        if let Some(endpoint) = self.providers.get(provider) {
+            return Some(Sink {
+                url: &endpoint.endpoint,
+                bearer: endpoint.bearer.as_deref(),
+                timeout: Some(endpoint.timeout()),
+            });
+        }
+        self.config.sinks.get("*").map(plain)
+    }
+
+    /// Persist a send for `provider`. Returns `None` when the outbox is off or no sink is
+    /// configured for the provider.
+    pub fn enqueue(&self, provider: Option<&str>, message: Value) -> Result<Option<OutboxEntry>> {
+        if !self.config.enabled {
+            return Ok(None);
+        }
+        let Some(sink) = self.sink_for(provider) else {
+            return Ok(None);
+        };
+        let id = Uuid::new_v4().simple().to_string();
+        let mut body = message;
+        if let Value::Object(map) = &mut body {
+            map.insert("outbox_id".into(), json!(id));
+        }
+        let now = current_timestamp_ms();
+        let entry = OutboxEntry {
+            id,
+            provider: provider.map(str::to_string),
+            url: sink.url.to_string(),
+            body,
+            status: OutboxStatus::Pending,
+            attempts: 0,
+            created_at_ms: now,
+            next_attempt_at_ms: now,
+            last_error: None,
+            sent_at_ms: None,
+        };
+        let _guard = self.lock.lock();
+        let mut entries = self.load()?;
+        entries.push(entry.clone());
+        self.save(&entries)?;
+        Ok(Some(entry))
+    }
+
+    /// Every kept entry, oldest first.
+    pub fn entries(&self) -> Result<Vec<OutboxEntry>> {
+        let _guard = self.lock.lock();
+        self.load()
+    }
+
+    pub fn counts(entries: &[OutboxEntry]) -> OutboxCounts {
+        let mut counts = OutboxCounts::default();
+        for entry in entries {
+            match entry.status {
+                OutboxStatus::Pending => counts.pending += 1,
+                OutboxStatus::Sent => counts.sent += 1,
+                OutboxStatus::Failed => counts.failed += 1,
+            }
+        }
+        counts
+    }
+
+    /// Entries that failed or have waited past `stuck_after_secs`, oldest first.
+    pub fn stuck(&self, entries: &[OutboxEntry], now_ms: u64) -> Vec<OutboxEntry> {
+        let stuck_after_ms = self.config.stuck_after_secs.saturating_mul(1000);
+        entries
+            .iter()
+            .filter(|entry| entry.is_stuck(now_ms, stuck_after_ms))
+            .cloned()
+            .collect()
+    }
+
+    /// Send every pending entry that is due at `now_ms` to its provider's configured sink,
+    /// or the sink it was queued with when the provider has none any more. The list is not
+    /// locked while the sinks are contacted, so new entries can be queued meanwhile.
+    pub fn dispatch_due(&self, http: &OutboundHttp, now_ms: u64) -> Result<DispatchRound> {
+        let due: Vec<OutboxEntry> = {
+            let _guard = self.lock.lock();
+            self.load()?
+                .into_iter()
+                .filter(|entry| {
+                    entry.status == OutboxStatus::Pending && entry.next_attempt_at_ms <= now_ms
+                })
+                .collect()
+        };
+        if due.is_empty() {
+            return Ok(DispatchRound::default());
+        }
+        let results: BTreeMap<String, (String, Result<(), String>)> = due
+            .iter()
+            .map(|entry| {
+                let sink = self.sink_for(entry.provider.as_deref()).unwrap_or(Sink {
+                    url: &entry.url,
+                    bearer: None,
+                    timeout: None,
+                });
+                let url = sink.url.to_string();
+                let http = match sink.timeout {
+                    Some(timeout) => http.with_timeout(timeout),
+                    None => *http,
+                };
+                let result = match http.post_json_as(
+                    "provider outbox",
+                    &url,
+                    sink.bearer,
+                    entry.body.clone(),
+                ) {
+                    Ok((status, _)) if (200..300).contains(&status) => Ok(()),
+                    Ok((status, body)) => Err(format!("sink answered {status}: {body}")),
+                    Err(err) => Err(format!("{err:#}")),
+                };
+                (entry.id.clone(), (url, result))
+            })
+            .collect();
+
+        let mut round = DispatchRound::default();
+        let _guard = self.lock.lock();
+        let mut entries = self.load()?;
+        for entry in &mut entries {
+            let Some((url, result)) = results.get(&entry.id) else {
+                continue;
+            };
+            entry.url = url.clone();
+            entry.attempts += 1;
+            match result {
+                Ok(()) => {
+                    entry.status = OutboxStatus::Sent;
+                    entry.sent_at_ms = Some(now_ms);
+                    entry.last_error = None;
+                    round.sent += 1;
+                }
+                Err(err) if entry.attempts >= self.config.max_attempts => {
+                    warn!(id = %entry.id, url = %entry.url, attempts = entry.attempts, %err, "giving up on outbox entry");
+                    entry.status = OutboxStatus::Failed;
+                    entry.last_error = Some(err.clone());
+                    round.failed += 1;
+                }
+                Err(err) => {
+                    entry.next_attempt_at_ms = now_ms + self.backoff_ms(entry.attempts);
+                    entry.last_error = Some(err.clone());
+                    round.retried += 1;
+                }
+            }
+        }
+        prune_sent(&mut entries, self.config.keep_sent);
+        self.save(&entries)?;
+        Ok(round)
+    }
+
+    /// Delay after the `attempts`-th failure.
+    fn backoff_ms(&self, attempts: u32) -> u64 {
+        let doublings = attempts.saturating_sub(1).min(32);
+        self.config
+            .retry_initial_ms
+            .saturating_mul(1u64 << doublings)
+            .min(self.config.retry_max_ms)
+    }
+
+    /// Dispatch due entries every `poll_interval_ms` until the task is dropped.
+    pub async fn run(self, http: OutboundHttp) -> Result<()> {
+        let mut ticker =
+            tokio::time::interval(Duration::from_millis(self.config.poll_interval_ms.max(10)));
+        loop {
+            ticker.tick().await;
+            let outbox = self.clone();
+            let round = tokio::task::spawn_blocking(move || {
+                outbox.dispatch_due(&http, current_timestamp_ms())
+            })
+            .await
+            .context("outbox dispatcher panicked")??;
+            if round != DispatchRound::default() {
+                info!(
+                    sent = round.sent,
+                    retried = round.retried,
+                    failed = round.failed,
+                    "dispatched outbox entries"
+                );
+            }
+        }
+    }
+
+    fn load(&self) -> Result<Vec<OutboxEntry>> {
+        match self.store.get(NAMESPACE, KEY)? {
+            Some(raw) => serde_json::from_slice(&raw).context("corrupt outbox"),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn save(&self, entries: &[OutboxEntry]) -> Result<()> {
+        self.store
+            .set(NAMESPACE, KEY, serde_json::to_vec(entries)?)
+            .context("failed to store the outbox")
+    }
+}
+
+/// Drop the oldest sent entries beyond `keep`.
+fn prune_sent(entries: &mut Vec<OutboxEntry>, keep: usize) {
+    let mut excess = entries
+        .iter()
+        .filter(|entry| entry.status == OutboxStatus::Sent)
+        .count()
+        .saturating_sub(keep);
+    entries.retain(|entry| {
+        if excess > 0 && entry.status == OutboxStatus::Sent {
+            excess -= 1;
+            return false;
+        }
+        true
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state_store::InMemoryStateStore;
+    use crate::testkit::{ProviderSink, SinkResponse};
+
+    fn outbox(url: &str) -> Outbox {
+        Outbox::new(
+            InMemoryStateStore::new(),
+            OutboxConfig {
+                enabled: true,
+                sinks: BTreeMap::from([("webchat".to_string(), url.to_string())]),
+                max_attempts: 2,
+                retry_initial_ms: 100,
+                keep_sent: 1,
+                ..OutboxConfig::default()
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn delivers_queued_sends_and_keeps_them_while_the_sink_is_down() {
+        let tmp = tempfile::tempdir().unwrap();
+        let down =
+            ProviderSink::start_with(tmp.path().join("down.json"), SinkResponse::Status(503))
+                .await
+                .unwrap();
+        let outbox = outbox(&down.send_url());
+        assert!(
+            outbox
+                .enqueue(Some("slack"), json!({"text": "no sink"}))
+                .unwrap()
+                .is_none()
+        );
+        let entry = outbox
+            .enqueue(Some("webchat"), json!({"text": "hello"}))
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.body["outbox_id"], json!(entry.id));
+
+        let now = entry.created_at_ms;
+        let first = {
+            let outbox = outbox.clone();
+            tokio::task::spawn_blocking(move || outbox.dispatch_due(&OutboundHttp::default(), now))
+                .await
+                .unwrap()
+                .unwrap()
+        };
+        assert_eq!(first.retried, 1);
+        let entries = outbox.entries().unwrap();
+        assert_eq!(entries[0].status, OutboxStatus::Pending);
+        assert_eq!(entries[0].next_attempt_at_ms, now + 100);
+        assert!(entries[0].last_error.as_deref().unwrap().contains("503"));
+        // Not due yet, so nothing is attempted.
+        let idle = {
+            let outbox = outbox.clone();
+            tokio::task::spawn_blocking(move || outbox.dispatch_due(&OutboundHttp::default(), now))
+                .await
+                .unwrap()
+                .unwrap()
+        };
+        assert_eq!(idle, DispatchRound::default());
+        assert!(outbox.stuck(&entries, now).is_empty());
+        assert_eq!(outbox.stuck(&entries, now + 60_000).len(), 1);
+        down.shutdown().await.unwrap();
+
+        // Restarted against a working sink, the queued entry goes out there.
+        let up = ProviderSink::start(tmp.path().join("up.json"))
+            .await
+            .unwrap();
+        let outbox = Outbox::new(outbox.store.clone(), {
+            let mut config = outbox.config.clone();
+            config.sinks.insert("webchat".into(), up.send_url());
+            config
+        });
+        let second = {
+            let outbox = outbox.clone();
+            tokio::task::spawn_blocking(move || {
+                outbox.dispatch_due(&OutboundHttp::default(), now + 100)
+            })
+            .await
+            .unwrap()
+            .unwrap()
+        };
+        assert_eq!(second.sent, 1);
+        let received = up.wait_for(1, Duration::from_secs(2)).await.unwrap();
+        assert_eq!(received[0]["text"], "hello");
+        assert_eq!(received[0]["outbox_id"], json!(entry.id));
+        let entries = outbox.entries().unwrap();
+        assert_eq!(entries[0].status, OutboxStatus::Sent);
+        assert_eq!(entries[0].attempts, 2);
+        assert_eq!(entries[0].url, up.send_url());
+        assert_eq!(
+            Outbox::counts(&entries),
+            OutboxCounts {
+                pending: 0,
+                sent: 1,
+                failed: 0
+            }
+        );
+        up.shutdown().await.unwrap();
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts_and_prunes_old_sent_entries() {
+        // Nothing listens on port 9 of the loopback interface.
+        let outbox = outbox("http://127.0.0.1:9/send");
+        let entry = outbox
+            .enqueue(Some("webchat"), json!({"text": "lost?"}))
+            .unwrap()
+            .unwrap();
+        let http = OutboundHttp::default();
+        let now = entry.created_at_ms;
+        assert_eq!(outbox.dispatch_due(&http, now).unwrap().retried, 1);
+        assert_eq!(outbox.dispatch_due(&http, now + 100).unwrap().failed, 1);
+        let entries = outbox.entries().unwrap();
+        assert_eq!(entries[0].status, OutboxStatus::Failed);
+        assert_eq!(outbox.stuck(&entries, now + 100).len(), 1);
+
+        let mut entries = vec![entries[0].clone(); 3];
+        for (n, entry) in entries.iter_mut().enumerate() {
+            entry.id = n.to_string();
+            entry.status = OutboxStatus::Sent;
+        }
+        entries[1].status = OutboxStatus::Pending;
+        prune_sent(&mut entries, 1);
+        let ids: Vec<_> = entries.iter().map(|entry| entry.id.as_str()).collect();
+        assert_eq!(ids, ["1", "2"]);
+    }
+}
@@ -0,0 +1,148 @@
+//! Static assets shipped under `<pack>/assets/` (card images, templates), indexed at pack load
+//! and served by `GET /packs/{id}/assets/{*path}`.
+
+use std::path::{Component, Path};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+/// Directory inside a pack that holds servable assets.
+pub const ASSETS_DIR: &str = "assets";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PackAsset {
+    /// Path relative to `assets/`, `/`-separated; the URL suffix after `/assets/`.
+    pub path: String,
+    pub size: u64,
+    pub content_type: String,
+}
+
+/// Regular files under `<pack_dir>/assets`, sorted by path. Hidden files and symlinks are
+/// skipped so nothing outside the pack can be published by accident.
+pub fn discover_assets(pack_dir: &Path) -> Result<Vec<PackAsset>> {
+    let root = pack_dir.join(ASSETS_DIR);
+    if !root.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut assets = Vec::new();
+    let walker = WalkDir::new(&root)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|entry| !entry.file_name().to_string_lossy().starts_with('.'));
+    for entry in walker {
+        let entry = entry.with_context(|| format!("failed to scan {}", root.display()))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative = entry
+            .path()
+            .strip_prefix(&root)
+            .expect("walkdir yields paths under its root");
+        let path = relative
+            .components()
+            .map(|part| part.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        assets.push(PackAsset {
+            content_type: content_type_for(&path).to_string(),
+            size: entry.metadata()?.len(),
+            path,
+        });
+    }
+    assets.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(assets)
+}
+
+/// Whether a requested asset path is a plain relative path (no `..`, root or prefix parts).
+pub fn is_safe_asset_path(path: &str) -> bool {
+    !path.is_empty()
+        && !path.contains('\\')
+        && Path::new(path)
+            .components()
+            .all(|part| matches!(part, Component::Normal(_)))
+}
+
+/// Content type from the file extension; unknown extensions are served as octet-stream.
+pub fn content_type_for(path: &str) -> &'static str {
+    let ext = Path::new(path)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        "json" => "application/json",
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" => "text/javascript; charset=utf-8",
+        "md" => "text/markdown; charset=utf-8",
+        "txt" | "hbs" | "handlebars" | "tmpl" => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Strong validator for `ETag`/`If-None-Match`, derived from the file contents.
+pub fn etag_for(bytes: &[u8]) -> String {
+    let digest = Sha256::digest(bytes);
+    format!("\"{}\"", hex::encode(&digest[..16]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn discovers_nested_assets_and_skips_hidden_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let assets = tmp.path().join(ASSETS_DIR);
+        fs::create_dir_all(assets.join("cards/.cache")).unwrap();
+        fs::write(assets.join("cards/hero.PNG"), b"png").unwrap();
+        fs::write(assets.join("cards/.cache/stale.png"), b"x").unwrap();
+        fs::write(assets.join(".DS_Store"), b"x").unwrap();
+        fs::write(assets.join("welcome.hbs"), b"Hello {{name}}").unwrap();
+
+        let found = discover_assets(tmp.path()).unwrap();
+        assert_eq!(
+            found,
+            vec![
+                PackAsset {
+                    path: "cards/hero.PNG".into(),
+                    size: 3,
+                    content_type: "image/png".into(),
+                },
+                PackAsset {
+                    path: "welcome.hbs".into(),
+                    size: 14,
+                    content_type: "text/plain; charset=utf-8".into(),
+                },
+            ]
+        );
+        assert!(
+            discover_assets(&tmp.path().join("missing"))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn rejects_traversal_and_absolute_asset_paths() {
+        assert!(is_safe_asset_path("cards/hero.svg"));
+        for bad in [
+            "",
+            "../pack.json",
+            "cards/../../x",
+            "/etc/passwd",
+            "a\\..\\b",
+            "./x",
+        ] {
+            assert!(!is_safe_asset_path(bad), "{bad:?} should be rejected");
+        }
+    }
+}
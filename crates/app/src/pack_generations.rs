@@ -0,0 +1,323 @@
+//! Pack index generations kept alive after a reload. `[packs].retain_generations` bounds how
+//! many stay resolvable, the current one included: sessions parked under a superseded
+//! generation, and flow runs started on it, keep resolving against its index while new
+//! traffic uses the current one. A superseded generation with no live sessions and no runs
+//! left is released; `GET /packs/generations` reports where each one stands.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Arc;
+
+use anyhow::Result;
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use crate::PackIndex;
+use crate::session::{SessionFilter, SessionStore};
+
+/// Released generations remembered for the status report.
+const RELEASED_HISTORY: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GenerationState {
+    Current,
+    Draining,
+    Released,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GenerationStatus {
+    pub generation: u64,
+    pub state: GenerationState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub superseded_at_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub released_at_ms: Option<u64>,
+    /// Live sessions pinned to the generation; for released ones, those left at release.
+    pub sessions: usize,
+    /// Flow runs executing against the generation.
+    pub in_flight: usize,
+    /// Released before it drained because more than `retain` generations were alive; its
+    /// sessions fall back to the current index (and its flow-version upgrades).
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub evicted: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GenerationsReport {
+    pub current: u64,
+    pub retain: usize,
+    /// The current generation, the draining ones newest first, then recent releases.
+    pub generations: Vec<GenerationStatus>,
+}
+
+struct Draining {
+    index: Arc<PackIndex>,
+    superseded_at_ms: u64,
+}
+
+#[derive(Default)]
+struct Inner {
+    current: u64,
+    draining: BTreeMap<u64, Draining>,
+    in_flight: HashMap<u64, usize>,
+    released: VecDeque<GenerationStatus>,
+}
+
+impl Inner {
+    fn in_flight(&self, generation: u64) -> usize {
+        self.in_flight.get(&generation).copied().unwrap_or(0)
+    }
+
+    fn release(
+        &mut self,
+        generation: u64,
+        sessions: usize,
+        evicted: bool,
+        now_ms: u64,
+    ) -> Option<GenerationStatus> {
+        let draining = self.draining.remove(&generation)?;
+        let status = GenerationStatus {
+            generation,
+            state: GenerationState::Released,
+            superseded_at_ms: Some(draining.superseded_at_ms),
+            released_at_ms: Some(now_ms),
+            sessions,
+            in_flight: self.in_flight(generation),
+            evicted,
+        };
+        if self.released.len() == RELEASED_HISTORY {
+            self.released.pop_front();
+        }
+        self.released.push_back(status.clone());
+        Some(status)
+    }
+}
+
+/// Superseded pack indexes still draining, and the flow runs pinned to each generation.
+#[derive(Clone)]
+pub struct PackGenerations {
+    inner: Arc<Mutex<Inner>>,
+    retain: usize,
+}
+
+impl PackGenerations {
+    /// Keep up to `retain` generations resolvable, the current one included; `1` releases a
+    /// generation at the first sweep after it is superseded.
+    pub fn new(retain: usize) -> Self {
+        Self {
+            inner: Arc::default(),
+            retain: retain.max(1),
+        }
+    }
+
+    /// Record that `previous` was replaced by generation `current`. Called while the index
+    /// lock is held, so the current generation here never lags the installed index.
+    pub fn supersede(&self, previous: PackIndex, current: u64, now_ms: u64) {
+        let mut inner = self.inner.lock();
+        inner.current = current;
+        inner.draining.insert(
+            previous.generation,
+            Draining {
+                index: Arc::new(previous),
+                superseded_at_ms: now_ms,
+            },
+        );
+    }
+
+    /// The superseded index of `generation` while it is draining.
+    pub fn draining_index(&self, generation: u64) -> Option<Arc<PackIndex>> {
+        let inner = self.inner.lock();
+        inner
+            .draining
+            .get(&generation)
+            .map(|draining| draining.index.clone())
+    }
+
+    pub fn is_draining(&self) -> bool {
+        !self.inner.lock().draining.is_empty()
+    }
+
+    /// Pin a flow run to `requested` while that generation drains, otherwise to the current
+    /// one. The generation counts the run as in flight until the guard is dropped.
+    pub fn pin(&self, requested: Option<u64>) -> GenerationGuard {
+        let mut inner = self.inner.lock();
+        let draining = requested.and_then(|generation| {
+            inner
+                .draining
+                .get(&generation)
+                .map(|draining| (generation, draining.index.clone()))
+        });
+        let (generation, index) = match draining {
+            Some((generation, index)) => (generation, Some(index)),
+            None => (inner.current, None),
+        };
+        *inner.in_flight.entry(generation).or_default() += 1;
+        GenerationGuard {
+            inner: self.inner.clone(),
+            generation,
+            index,
+        }
+    }
+
+    /// Release the draining generations with no `sessions` and nothing in flight, then evict
+    /// the oldest ones still beyond `retain`. Returns what was released.
+    pub fn sweep(&self, sessions: &BTreeMap<u64, usize>, now_ms: u64) -> Vec<GenerationStatus> {
+        let mut inner = self.inner.lock();
+        let pinned = |generation: &u64| sessions.get(generation).copied().unwrap_or(0);
+        let mut released = Vec::new();
+        let drained: Vec<u64> = inner
+            .draining
+            .keys()
+            .filter(|generation| pinned(generation) == 0 && inner.in_flight(**generation) == 0)
+            .copied()
+            .collect();
+        for generation in drained {
+            released.extend(inner.release(generation, 0, false, now_ms));
+        }
+        while inner.draining.len() >= self.retain {
+            let Some(&oldest) = inner.draining.keys().next() else {
+                break;
+            };
+            released.extend(inner.release(oldest, pinned(&oldest), true, now_ms));
+        }
+        released
+    }
+
+    pub fn report(&self, sessions: &BTreeMap<u64, usize>) -> GenerationsReport {
+        let inner = self.inner.lock();
+        let pinned = |generation: u64| sessions.get(&generation).copied().unwrap_or(0);
+        let mut generations = vec![GenerationStatus {
+            generation: inner.current,
+            state: GenerationState::Current,
+            superseded_at_ms: None,
+            released_at_ms: None,
+            sessions: pinned(inner.current),
+            in_flight: inner.in_flight(inner.current),
+            evicted: false,
+        }];
+        generations.extend(inner.draining.iter().rev().map(|(&generation, draining)| {
+            GenerationStatus {
+                generation,
+                state: GenerationState::Draining,
+                superseded_at_ms: Some(draining.superseded_at_ms),
+                released_at_ms: None,
+                sessions: pinned(generation),
+                in_flight: inner.in_flight(generation),
+                evicted: false,
+            }
+        }));
+        generations.extend(inner.released.iter().rev().cloned());
+        GenerationsReport {
+            current: inner.current,
+            retain: self.retain,
+            generations,
+        }
+    }
+}
+
+/// A flow run pinned to one generation; see [`PackGenerations::pin`].
+pub struct GenerationGuard {
+    inner: Arc<Mutex<Inner>>,
+    generation: u64,
+    #[cfg_attr(not(feature = "mini-runner"), allow(dead_code))]
+    index: Option<Arc<PackIndex>>,
+}
+
+impl GenerationGuard {
+    /// The superseded index the run resolves against; `None` on the current generation.
+    #[cfg_attr(not(feature = "mini-runner"), allow(dead_code))]
+    pub fn index(&self) -> Option<&PackIndex> {
+        self.index.as_deref()
+    }
+}
+
+impl Drop for GenerationGuard {
+    fn drop(&mut self) {
+        let mut inner = self.inner.lock();
+        if let Some(count) = inner.in_flight.get_mut(&self.generation) {
+            *count -= 1;
+            if *count == 0 {
+                inner.in_flight.remove(&self.generation);
+            }
+        }
+    }
+}
+
+/// Live sessions per pack generation they are pinned to; untagged sessions are not counted.
+pub fn pinned_sessions(store: &dyn SessionStore, now_ms: u64) -> Result<BTreeMap<u64, usize>> {
+    let mut counts = BTreeMap::new();
+    store.scan(&SessionFilter::default().live_at(now_ms), &mut |record| {
+        if let Some(generation) = record.pack_generation {
+            *counts.entry(generation).or_default() += 1;
+        }
+    })?;
+    Ok(counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(generation: u64) -> PackIndex {
+        PackIndex {
+            generation,
+            ..PackIndex::default()
+        }
+    }
+
+    #[test]
+    fn sessions_and_runs_hold_a_generation_until_it_drains() {
+        let generations = PackGenerations::new(3);
+        generations.supersede(index(0), 1, 1_000);
+
+        let run = generations.pin(Some(0));
+        assert_eq!(run.index().map(|index| index.generation), Some(0));
+        assert!(
+            generations
+                .sweep(&BTreeMap::from([(0, 1)]), 2_000)
+                .is_empty()
+        );
+        assert!(generations.sweep(&BTreeMap::new(), 2_000).is_empty());
+
+        let report = generations.report(&BTreeMap::new());
+        assert_eq!(report.current, 1);
+        assert_eq!(report.generations[1].state, GenerationState::Draining);
+        assert_eq!(report.generations[1].in_flight, 1);
+
+        drop(run);
+        let released = generations.sweep(&BTreeMap::new(), 3_000);
+        assert_eq!(released.len(), 1);
+        assert_eq!(released[0].released_at_ms, Some(3_000));
+        assert!(!released[0].evicted);
+        assert!(!generations.is_draining());
+
+        // Once released, runs pinned to it fall back to the current generation.
+        let run = generations.pin(Some(0));
+        assert!(run.index().is_none());
+        assert_eq!(
+            generations.report(&BTreeMap::new()).generations[0].in_flight,
+            1
+        );
+    }
+
+    #[test]
+    fn generations_beyond_retain_are_evicted_oldest_first() {
+        let generations = PackGenerations::new(2);
+        generations.supersede(index(0), 1, 1_000);
+        generations.supersede(index(1), 2, 2_000);
+
+        let busy = BTreeMap::from([(0, 4), (1, 2)]);
+        let released = generations.sweep(&busy, 3_000);
+        assert_eq!(released.len(), 1);
+        assert_eq!((released[0].generation, released[0].sessions), (0, 4));
+        assert!(released[0].evicted);
+        assert!(generations.draining_index(0).is_none());
+        assert!(generations.draining_index(1).is_some());
+
+        let single = PackGenerations::new(1);
+        single.supersede(index(0), 1, 1_000);
+        assert_eq!(single.sweep(&busy, 2_000).len(), 1);
+        assert!(!single.is_draining());
+    }
+}
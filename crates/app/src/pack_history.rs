@@ -0,0 +1,234 @@
+//! Per-pack changelog kept in `[stores.state]`: whenever an installed pack index shows a pack
+//! with a different version or different files than last recorded, a [`PackChange`] is
+//! appended, so `GET /packs/{id}/history` can answer when a pack changed and what changed.
+
+use std::{collections::BTreeMap, fs, path::Path, sync::Arc};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use crate::state_store::StateStore;
+
+const NAMESPACE: &str = "pack_history";
+
+/// Changes kept per pack; older ones are dropped.
+pub const HISTORY_LIMIT: usize = 50;
+
+/// Fingerprint of a pack directory's files.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PackContents {
+    /// Digest over every file's path and digest; empty for packs that were not scanned.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub content_hash: String,
+    /// Digest per `/`-separated path relative to the pack.
+    #[serde(skip)]
+    pub files: BTreeMap<String, String>,
+}
+
+impl PackContents {
+    /// Hash every regular file under `pack_dir`, skipping hidden files and directories.
+    pub fn scan(pack_dir: &Path) -> Result<Self> {
+        let mut files = BTreeMap::new();
+        let walker = WalkDir::new(pack_dir)
+            .min_depth(1)
+            .into_iter()
+            .filter_entry(|entry| !entry.file_name().to_string_lossy().starts_with('.'));
+        for entry in walker {
+            let entry = entry.with_context(|| format!("failed to scan {}", pack_dir.display()))?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let bytes = fs::read(entry.path())
+                .with_context(|| format!("failed to read {}", entry.path().display()))?;
+            let relative = entry
+                .path()
+                .strip_prefix(pack_dir)
+                .expect("walkdir yields paths under its root");
+            let path = relative
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.insert(path, short_digest(&bytes));
+        }
+        let mut hasher = Sha256::new();
+        for (path, digest) in &files {
+            hasher.update(path.as_bytes());
+            hasher.update([0]);
+            hasher.update(digest.as_bytes());
+            hasher.update([b'\n']);
+        }
+        Ok(Self {
+            content_hash: hex::encode(&hasher.finalize()[..16]),
+            files,
+        })
+    }
+}
+
+fn short_digest(bytes: &[u8]) -> String {
+    hex::encode(&Sha256::digest(bytes)[..16])
+}
+
+/// One recorded change; the first entry of a pack has no `from_*` values.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackChange {
+    pub pack_id: String,
+    pub from_version: Option<String>,
+    pub to_version: Option<String>,
+    pub from_hash: Option<String>,
+    pub to_hash: String,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub modified: Vec<String>,
+    /// Pack index generation that introduced the change (0 for the index loaded at startup).
+    pub index_generation: u64,
+    pub changed_at_epoch_ms: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoredHistory {
+    /// File digests as of the latest change, to diff the next one against.
+    files: BTreeMap<String, String>,
+    /// Oldest first.
+    changes: Vec<PackChange>,
+}
+
+#[derive(Clone)]
+pub struct PackHistory {
+    store: Arc<dyn StateStore>,
+}
+
+impl PackHistory {
+    pub fn new(store: Arc<dyn StateStore>) -> Self {
+        Self { store }
+    }
+
+    /// Record a change for `id` when its version or contents differ from the latest recorded
+    /// change (or nothing is recorded yet). Returns the recorded change.
+    pub fn observe(
+        &self,
+        id: &str,
+        version: Option<&str>,
+        contents: &PackContents,
+        index_generation: u64,
+        now_ms: u64,
+    ) -> Result<Option<PackChange>> {
+        let mut history = self.load(id)?;
+        let latest = history.changes.last();
+        if latest.is_some_and(|latest| {
+            latest.to_version.as_deref() == version && latest.to_hash == contents.content_hash
+        }) {
+            return Ok(None);
+        }
+        let change = PackChange {
+            pack_id: id.to_string(),
+            from_version: latest.and_then(|latest| latest.to_version.clone()),
+            to_version: version.map(str::to_string),
+            from_hash: latest.map(|latest| latest.to_hash.clone()),
+            to_hash: contents.content_hash.clone(),
+            added: keys_missing_from(&contents.files, &history.files),
+            removed: keys_missing_from(&history.files, &contents.files),
+            modified: contents
+                .files
+                .iter()
+                .filter(|(path, digest)| history.files.get(*path).is_some_and(|old| old != *digest))
+                .map(|(path, _)| path.clone())
+                .collect(),
+            index_generation,
+            changed_at_epoch_ms: now_ms,
+        };
+        history.files = contents.files.clone();
+        history.changes.push(change.clone());
+        let excess = history.changes.len().saturating_sub(HISTORY_LIMIT);
+        history.changes.drain(..excess);
+        self.store
+            .set(NAMESPACE, id, serde_json::to_vec(&history)?)
+            .with_context(|| format!("failed to store the history of pack {id}"))?;
+        Ok(Some(change))
+    }
+
+    /// Recorded changes of `id`, newest first.
+    pub fn changes(&self, id: &str) -> Result<Vec<PackChange>> {
+        let mut changes = self.load(id)?.changes;
+        changes.reverse();
+        Ok(changes)
+    }
+
+    fn load(&self, id: &str) -> Result<StoredHistory> {
+        match self.store.get(NAMESPACE, id)? {
+            Some(raw) => serde_json::from_slice(&raw)
+                .with_context(|| format!("corrupt history for pack {id}")),
+            None => Ok(StoredHistory::default()),
+        }
+    }
+}
+
+fn keys_missing_from(
+    keys: &BTreeMap<String, String>,
+    other: &BTreeMap<String, String>,
+) -> Vec<String> {
+    keys.keys()
+        .filter(|key| !other.contains_key(*key))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state_store::InMemoryStateStore;
+
+    #[test]
+    fn records_version_and_content_changes_with_file_deltas() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(tmp.path().join("pack.json"), br#"{"version":"1.0.0"}"#).unwrap();
+        fs::create_dir_all(tmp.path().join("flows")).unwrap();
+        fs::write(tmp.path().join("flows/menu.ygtc"), b"nodes: []").unwrap();
+        fs::write(tmp.path().join(".DS_Store"), b"x").unwrap();
+        let first = PackContents::scan(tmp.path()).unwrap();
+        assert_eq!(
+            first.files.keys().collect::<Vec<_>>(),
+            ["flows/menu.ygtc", "pack.json"]
+        );
+
+        let history = PackHistory::new(InMemoryStateStore::new());
+        let added = history
+            .observe("menu", Some("1.0.0"), &first, 0, 10)
+            .unwrap()
+            .unwrap();
+        assert_eq!((added.from_version, added.from_hash), (None, None));
+        assert_eq!(added.added.len(), 2);
+        // Unchanged packs record nothing, however often the index is rebuilt.
+        assert!(
+            history
+                .observe("menu", Some("1.0.0"), &first, 1, 20)
+                .unwrap()
+                .is_none()
+        );
+
+        fs::write(tmp.path().join("pack.json"), br#"{"version":"1.1.0"}"#).unwrap();
+        fs::remove_file(tmp.path().join("flows/menu.ygtc")).unwrap();
+        fs::write(tmp.path().join("flows/support.ygtc"), b"nodes: []").unwrap();
+        let second = PackContents::scan(tmp.path()).unwrap();
+        let bumped = history
+            .observe("menu", Some("1.1.0"), &second, 2, 30)
+            .unwrap()
+            .unwrap();
+        assert_eq!(bumped.from_version.as_deref(), Some("1.0.0"));
+        assert_eq!(
+            bumped.from_hash.as_deref(),
+            Some(first.content_hash.as_str())
+        );
+        assert_eq!(bumped.to_hash, second.content_hash);
+        assert_eq!(bumped.added, ["flows/support.ygtc"]);
+        assert_eq!(bumped.removed, ["flows/menu.ygtc"]);
+        assert_eq!(bumped.modified, ["pack.json"]);
+
+        let changes = history.changes("menu").unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].index_generation, 2);
+        assert!(history.changes("unknown").unwrap().is_empty());
+    }
+}
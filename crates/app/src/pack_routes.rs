@@ -0,0 +1,426 @@
+//! HTTP routes declared by packs of kind `extension` (`"routes"` in `pack.json`), served under
+//! `/ext/{pack}/...` from the current pack index, so a reload publishes, changes or retires
+//! them. A route answers with a templated response, or forwards the request to a NATS
+//! subject on `runner.nats_url` and answers once it is published. Packs use them to ship
+//! mock endpoints for integration tests without changes to the bridge.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{Context, Result, bail};
+use axum::http::HeaderName;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::network::NetworkPolicy;
+use crate::trace_context::TraceContext;
+
+/// Pack kind whose manifest may declare routes.
+pub const EXTENSION_KIND: &str = "extension";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExtensionRoute {
+    #[serde(default = "default_method")]
+    pub method: String,
+    /// Path under `/ext/{pack}`, starting with `/`; a `{name}` segment matches any one
+    /// segment and is available to templates as `{{path.name}}`.
+    pub path: String,
+    /// Response status; `200`, or `202` for forwarded requests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// Response body, JSON with `{{...}}` placeholders in its strings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forward: Option<Forward>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Forward {
+    /// NATS subject the request is published to; may contain placeholders.
+    pub subject: String,
+}
+
+fn default_method() -> String {
+    "GET".into()
+}
+
+impl ExtensionRoute {
+    pub fn status(&self) -> u16 {
+        self.status
+            .unwrap_or(if self.forward.is_some() { 202 } else { 200 })
+    }
+
+    fn validate(&mut self) -> Result<()> {
+        self.method = self.method.to_ascii_uppercase();
+        if !matches!(
+            self.method.as_str(),
+            "GET" | "POST" | "PUT" | "PATCH" | "DELETE" | "HEAD" | "OPTIONS"
+        ) {
+            bail!("unsupported method {}", self.method);
+        }
+        let Some(rest) = self.path.strip_prefix('/') else {
+            bail!("path {:?} must start with /", self.path);
+        };
+        let mut names = BTreeSet::new();
+        for segment in rest.split('/') {
+            if segment.is_empty() && !rest.is_empty() {
+                bail!("path {:?} has an empty segment", self.path);
+            }
+            if let Some(name) = capture_name(segment)
+                && (name.is_empty() || !names.insert(name))
+            {
+                bail!("path {:?} repeats or leaves out a capture name", self.path);
+            }
+        }
+        if let Some(name) = self
+            .headers
+            .keys()
+            .find(|name| HeaderName::from_bytes(name.as_bytes()).is_err())
+        {
+            bail!("invalid header name {name:?}");
+        }
+        if let Some(status) = self.status
+            && !(100..=599).contains(&status)
+        {
+            bail!("invalid status {status}");
+        }
+        if let Some(forward) = &self.forward
+            && forward.subject.trim().is_empty()
+        {
+            bail!("forward.subject must not be empty");
+        }
+        Ok(())
+    }
+}
+
+fn capture_name(segment: &str) -> Option<&str> {
+    segment.strip_prefix('{')?.strip_suffix('}')
+}
+
+/// The routes a manifest declares. Only `extension` packs may declare any; duplicate
+/// method/path pairs and malformed routes fail the pack.
+pub fn load_routes(kind: Option<&str>, manifest: &Value) -> Result<Vec<ExtensionRoute>> {
+    let Some(raw) = manifest.get("routes") else {
+        return Ok(Vec::new());
+    };
+    if kind != Some(EXTENSION_KIND) {
+        bail!("routes are only served for packs of kind {EXTENSION_KIND}");
+    }
+    let mut routes: Vec<ExtensionRoute> =
+        serde_json::from_value(raw.clone()).context("routes must be an array of routes")?;
+    let mut seen = BTreeSet::new();
+    for route in &mut routes {
+        route
+            .validate()
+            .with_context(|| format!("invalid route {} {}", route.method, route.path))?;
+        if !seen.insert((route.method.clone(), route.path.clone())) {
+            bail!("route {} {} is declared twice", route.method, route.path);
+        }
+    }
+    Ok(routes)
+}
+
+pub enum RouteMatch<'a> {
+    Found(&'a ExtensionRoute, BTreeMap<String, String>),
+    /// The path is declared, but not for this method.
+    MethodNotAllowed,
+    NotFound,
+}
+
+/// The route for `method` and `path`; literal segments win over captures when both match.
+pub fn match_route<'a>(routes: &'a [ExtensionRoute], method: &str, path: &str) -> RouteMatch<'a> {
+    let mut best: Option<(usize, &ExtensionRoute, BTreeMap<String, String>)> = None;
+    let mut path_matched = false;
+    for route in routes {
+        let Some((literals, params)) = match_path(&route.path, path) else {
+            continue;
+        };
+        path_matched = true;
+        if route.method != method || best.as_ref().is_some_and(|(most, ..)| *most >= literals) {
+            continue;
+        }
+        best = Some((literals, route, params));
+    }
+    match best {
+        Some((_, route, params)) => RouteMatch::Found(route, params),
+        None if path_matched => RouteMatch::MethodNotAllowed,
+        None => RouteMatch::NotFound,
+    }
+}
+
+/// Captured segments when `path` fits `pattern`, with the number of literal segments.
+fn match_path(pattern: &str, path: &str) -> Option<(usize, BTreeMap<String, String>)> {
+    let pattern: Vec<&str> = pattern.trim_start_matches('/').split('/').collect();
+    let path: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+    if pattern.len() != path.len() {
+        return None;
+    }
+    let mut literals = 0;
+    let mut params = BTreeMap::new();
+    for (expected, actual) in pattern.iter().zip(&path) {
+        match capture_name(expected) {
+            Some(name) if !actual.is_empty() => {
+                params.insert(name.to_string(), actual.to_string());
+            }
+            Some(_) => return None,
+            None if expected == actual => literals += 1,
+            None => return None,
+        }
+    }
+    Some((literals, params))
+}
+
+/// What templates can refer to: `{{method}}`, `{{pack}}`, `{{path.<name>}}`,
+/// `{{query.<name>}}`, `{{body}}` and `{{body.<field>.<field>}}`.
+pub struct RequestVars {
+    pub pack: String,
+    pub method: String,
+    pub params: BTreeMap<String, String>,
+    pub query: BTreeMap<String, String>,
+    pub body: Value,
+}
+
+impl RequestVars {
+    fn lookup(&self, name: &str) -> Option<Value> {
+        let text = |value: &String| Some(Value::String(value.clone()));
+        match name.split_once('.') {
+            None if name == "method" => text(&self.method),
+            None if name == "pack" => text(&self.pack),
+            None if name == "body" => Some(self.body.clone()),
+            Some(("path", param)) => self.params.get(param).and_then(text),
+            Some(("query", param)) => self.query.get(param).and_then(text),
+            Some(("body", fields)) => fields
+                .split('.')
+                .try_fold(&self.body, |value, field| match value {
+                    Value::Array(items) => items.get(field.parse::<usize>().ok()?),
+                    _ => value.get(field),
+                })
+                .cloned(),
+            _ => None,
+        }
+    }
+
+    /// The request as published by a forward route.
+    pub fn to_message(&self, path: &str) -> Value {
+        json!({
+            "pack": self.pack,
+            "method": self.method,
+            "path": path,
+            "params": self.params,
+            "query": self.query,
+            "body": self.body,
+        })
+    }
+}
+
+/// Fill the placeholders in the strings of `template`. A string that is exactly one
+/// placeholder takes the referenced JSON value as is; placeholders inside longer strings are
+/// replaced by their text. Unknown placeholders render empty (`null` when alone).
+pub fn render(template: &Value, vars: &RequestVars) -> Value {
+    match template {
+        Value::String(text) => match whole_placeholder(text) {
+            Some(name) => vars.lookup(name).unwrap_or(Value::Null),
+            None => Value::String(render_text(text, vars)),
+        },
+        Value::Array(items) => Value::Array(items.iter().map(|item| render(item, vars)).collect()),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), render(value, vars)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+fn whole_placeholder(text: &str) -> Option<&str> {
+    let name = text.strip_prefix("{{")?.strip_suffix("}}")?;
+    (!name.contains("{{")).then(|| name.trim())
+}
+
+pub fn render_text(template: &str, vars: &RequestVars) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let name = rest[start + 2..start + end].trim();
+        match vars.lookup(name) {
+            Some(Value::String(text)) => out.push_str(&text),
+            Some(Value::Null) | None => {}
+            Some(value) => out.push_str(&value.to_string()),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Publishes forwarded requests on `runner.nats_url`, connecting on first use.
+pub struct NatsForwarder {
+    url: Option<String>,
+    network: NetworkPolicy,
+    client: Mutex<Option<async_nats::Client>>,
+}
+
+impl NatsForwarder {
+    pub fn new(url: Option<String>, network: NetworkPolicy) -> Self {
+        Self {
+            url,
+            network,
+            client: Mutex::new(None),
+        }
+    }
+
+    pub fn is_configured(&self) -> bool {
+        self.url.is_some()
+    }
+
+    pub async fn publish(
+        &self,
+        subject: &str,
+        message: &Value,
+        trace: &TraceContext,
+    ) -> Result<()> {
+        let Some(url) = &self.url else {
+            bail!("forward routes need runner.nats_url");
+        };
+        let cached = self.client.lock().clone();
+        let client = match cached {
+            Some(client) => client,
+            None => {
+                self.network.check("extension route forwarding", url)?;
+                let client = async_nats::connect(url.as_str())
+                    .await
+                    .with_context(|| format!("failed to connect to NATS at {url}"))?;
+                *self.client.lock() = Some(client.clone());
+                client
+            }
+        };
+        client
+            .publish_with_headers(
+                subject.to_string(),
+                trace.headers(),
+                serde_json::to_vec(message)?.into(),
+            )
+            .await
+            .with_context(|| format!("failed to publish to {subject}"))?;
+        client.flush().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn routes(manifest: Value) -> Result<Vec<ExtensionRoute>> {
+        load_routes(Some(EXTENSION_KIND), &manifest)
+    }
+
+    #[test]
+    fn loads_and_validates_routes() {
+        let loaded = routes(json!({"routes": [
+            {"path": "/orders/{id}", "body": {"id": "{{path.id}}"}},
+            {"method": "post", "path": "/orders", "forward": {"subject": "ext.orders"}},
+        ]}))
+        .unwrap();
+        assert_eq!(loaded[1].method, "POST");
+        assert_eq!((loaded[0].status(), loaded[1].status()), (200, 202));
+
+        let plain = json!({"routes": [{"path": "/x"}]});
+        assert!(load_routes(Some("application"), &plain).is_err());
+        assert!(load_routes(None, &json!({})).unwrap().is_empty());
+        for invalid in [
+            json!([{"path": "orders"}]),
+            json!([{"path": "/a//b"}]),
+            json!([{"path": "/{id}/{id}"}]),
+            json!([{"path": "/x", "method": "BREW"}]),
+            json!([{"path": "/x", "status": 700}]),
+            json!([{"path": "/x", "forward": {"subject": " "}}]),
+            json!([{"path": "/x", "respond": {}}]),
+            json!([{"path": "/x", "headers": {"bad header": "1"}}]),
+            json!([{"path": "/x"}, {"path": "/x", "method": "get"}]),
+        ] {
+            assert!(routes(json!({"routes": invalid})).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn matches_literals_before_captures_and_reports_wrong_methods() {
+        let loaded = routes(json!({"routes": [
+            {"path": "/orders/{id}"},
+            {"path": "/orders/latest", "status": 204},
+            {"method": "DELETE", "path": "/orders/{id}"},
+        ]}))
+        .unwrap();
+        let RouteMatch::Found(route, params) = match_route(&loaded, "GET", "/orders/42") else {
+            panic!("expected a match");
+        };
+        assert_eq!(
+            (route.path.as_str(), params["id"].as_str()),
+            ("/orders/{id}", "42")
+        );
+        let RouteMatch::Found(route, params) = match_route(&loaded, "GET", "/orders/latest") else {
+            panic!("expected a match");
+        };
+        assert_eq!((route.status(), params.len()), (204, 0));
+        assert!(matches!(
+            match_route(&loaded, "PUT", "/orders/42"),
+            RouteMatch::MethodNotAllowed
+        ));
+        assert!(matches!(
+            match_route(&loaded, "GET", "/orders/42/items"),
+            RouteMatch::NotFound
+        ));
+        assert!(matches!(
+            match_route(&loaded, "GET", "/orders/"),
+            RouteMatch::NotFound
+        ));
+    }
+
+    #[test]
+    fn renders_values_and_text() {
+        let vars = RequestVars {
+            pack: "mock-crm".into(),
+            method: "POST".into(),
+            params: BTreeMap::from([("id".into(), "42".into())]),
+            query: BTreeMap::from([("verbose".into(), "1".into())]),
+            body: json!({"customer": {"tier": "gold"}, "items": [{"sku": "A-1"}], "qty": 3}),
+        };
+        let rendered = render(
+            &json!({
+                "id": "{{path.id}}",
+                "label": "order {{ path.id }} for {{body.customer.tier}} ({{body.qty}} items)",
+                "first": "{{body.items.0.sku}}",
+                "echo": "{{body}}",
+                "missing": "{{query.page}}",
+                "list": ["{{method}}", "{{pack}}/{{query.verbose}}{{nope}}"],
+            }),
+            &vars,
+        );
+        assert_eq!(
+            rendered,
+            json!({
+                "id": "42",
+                "label": "order 42 for gold (3 items)",
+                "first": "A-1",
+                "echo": vars.body,
+                "missing": null,
+                "list": ["POST", "mock-crm/1"],
+            })
+        );
+        assert_eq!(
+            render_text("unterminated {{path.id", &vars),
+            "unterminated {{path.id"
+        );
+        assert_eq!(vars.to_message("/orders/42")["params"], json!({"id": "42"}));
+    }
+}
@@ -0,0 +1,348 @@
+//! Per-run isolation for embedded flow runs (`[runner.sandbox]`). A sandbox gives one pack run
+//! its own scratch directory, a state-store namespace under `sandbox/<pack>/<run>/`, a NATS
+//! subject prefix and a resource budget; tearing it down removes the directory and every state
+//! key the run wrote, so packs exercised concurrently on one bridge cannot see each other.
+#![cfg_attr(not(feature = "mini-runner"), allow(dead_code))]
+
+use std::{
+    collections::BTreeSet,
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, bail};
+use camino::Utf8PathBuf;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::state_store::StateStore;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SandboxConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Parent of the per-run scratch directories (defaults to `<tmp>/greentic-sandboxes`).
+    #[serde(default)]
+    pub root: Option<Utf8PathBuf>,
+    /// First token of sandboxed NATS subjects: `<prefix>.<pack>.<run>.<topic>`.
+    #[serde(default = "default_subject_prefix")]
+    pub subject_prefix: String,
+    #[serde(default = "default_max_steps")]
+    pub max_steps: usize,
+    #[serde(default = "default_max_outputs")]
+    pub max_messages: usize,
+    #[serde(default = "default_max_outputs")]
+    pub max_events: usize,
+    /// Largest serialized payload a node may hand to the next one.
+    #[serde(default = "default_max_payload_bytes")]
+    pub max_payload_bytes: usize,
+    /// Wall-clock budget of one run (a whole scenario, for scenario runs).
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            root: None,
+            subject_prefix: default_subject_prefix(),
+            max_steps: default_max_steps(),
+            max_messages: default_max_outputs(),
+            max_events: default_max_outputs(),
+            max_payload_bytes: default_max_payload_bytes(),
+            timeout_ms: default_timeout_ms(),
+        }
+    }
+}
+
+fn default_subject_prefix() -> String {
+    "sandbox".into()
+}
+
+fn default_max_steps() -> usize {
+    256
+}
+
+fn default_max_outputs() -> usize {
+    64
+}
+
+fn default_max_payload_bytes() -> usize {
+    1024 * 1024
+}
+
+fn default_timeout_ms() -> u64 {
+    30_000
+}
+
+/// Opens sandboxes over the configured state store.
+#[derive(Clone)]
+pub struct Sandboxes {
+    config: SandboxConfig,
+    state: Arc<dyn StateStore>,
+}
+
+impl Sandboxes {
+    pub fn new(config: SandboxConfig, state: Arc<dyn StateStore>) -> Self {
+        Self { config, state }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    pub fn open(&self, pack_id: &str) -> Result<Arc<PackSandbox>> {
+        let run_id = Uuid::new_v4().simple().to_string();
+        let root = self
+            .config
+            .root
+            .as_ref()
+            .map(|root| root.as_std_path().to_path_buf())
+            .unwrap_or_else(|| std::env::temp_dir().join("greentic-sandboxes"));
+        let dir = root.join(format!("{}-{run_id}", subject_token(pack_id)));
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("failed to create sandbox dir {}", dir.display()))?;
+        debug!(pack = %pack_id, run = %run_id, dir = %dir.display(), "opened pack sandbox");
+        Ok(Arc::new(PackSandbox {
+            subject_prefix: format!(
+                "{}.{}.{run_id}",
+                self.config.subject_prefix,
+                subject_token(pack_id)
+            ),
+            namespace: format!("sandbox/{pack_id}/{run_id}"),
+            pack_id: pack_id.to_string(),
+            run_id,
+            dir,
+            budget: Budget {
+                max_steps: self.config.max_steps,
+                max_messages: self.config.max_messages,
+                max_events: self.config.max_events,
+                max_payload_bytes: self.config.max_payload_bytes,
+                deadline: Instant::now() + Duration::from_millis(self.config.timeout_ms),
+            },
+            state: self.state.clone(),
+            written: Mutex::default(),
+        }))
+    }
+}
+
+/// Limits one sandboxed run must stay within.
+#[derive(Debug, Clone)]
+struct Budget {
+    max_steps: usize,
+    max_messages: usize,
+    max_events: usize,
+    max_payload_bytes: usize,
+    deadline: Instant,
+}
+
+/// Isolation for one pack run. Also a [`StateStore`]: namespaces are rewritten under the
+/// sandbox's own and every written key is remembered for [`teardown`](Self::teardown).
+pub struct PackSandbox {
+    pack_id: String,
+    run_id: String,
+    dir: PathBuf,
+    subject_prefix: String,
+    namespace: String,
+    budget: Budget,
+    state: Arc<dyn StateStore>,
+    written: Mutex<BTreeSet<(String, String)>>,
+}
+
+impl fmt::Debug for PackSandbox {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PackSandbox")
+            .field("pack_id", &self.pack_id)
+            .field("run_id", &self.run_id)
+            .field("dir", &self.dir)
+            .field("subject_prefix", &self.subject_prefix)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PackSandbox {
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// Scratch directory private to this run.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// NATS subject a flow's `topic` is published on inside the sandbox.
+    pub fn subject(&self, topic: &str) -> String {
+        format!("{}.{topic}", self.subject_prefix)
+    }
+
+    fn scoped(&self, namespace: &str) -> String {
+        format!("{}/{namespace}", self.namespace)
+    }
+
+    /// Fail once the run used more than its budget; `steps`, `messages` and `events` are the
+    /// counts so far and `payload_bytes` the size of the payload about to move on.
+    pub fn check(
+        &self,
+        steps: usize,
+        messages: usize,
+        events: usize,
+        payload_bytes: usize,
+    ) -> Result<()> {
+        let budget = &self.budget;
+        if steps > budget.max_steps {
+            bail!(
+                "sandbox budget exceeded: more than {} steps",
+                budget.max_steps
+            );
+        }
+        if messages > budget.max_messages {
+            bail!(
+                "sandbox budget exceeded: more than {} messages",
+                budget.max_messages
+            );
+        }
+        if events > budget.max_events {
+            bail!(
+                "sandbox budget exceeded: more than {} events",
+                budget.max_events
+            );
+        }
+        if payload_bytes > budget.max_payload_bytes {
+            bail!(
+                "sandbox budget exceeded: payload of {payload_bytes} bytes is over {}",
+                budget.max_payload_bytes
+            );
+        }
+        if Instant::now() > budget.deadline {
+            bail!("sandbox budget exceeded: run timed out");
+        }
+        Ok(())
+    }
+
+    /// Remove the scratch directory and every state key the run wrote; returns how many keys
+    /// were removed.
+    pub fn teardown(&self) -> Result<usize> {
+        let written = std::mem::take(&mut *self.written.lock());
+        for (namespace, key) in &written {
+            self.state.delete(namespace, key)?;
+        }
+        match fs::remove_dir_all(&self.dir) {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("failed to remove {}", self.dir.display()));
+            }
+        }
+        debug!(pack = %self.pack_id, run = %self.run_id, keys = written.len(), "tore down pack sandbox");
+        Ok(written.len())
+    }
+}
+
+impl Drop for PackSandbox {
+    fn drop(&mut self) {
+        if (self.dir.exists() || !self.written.lock().is_empty())
+            && let Err(err) = self.teardown()
+        {
+            warn!(?err, pack = %self.pack_id, run = %self.run_id, "sandbox teardown failed");
+        }
+    }
+}
+
+impl StateStore for PackSandbox {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>> {
+        self.state.get(&self.scoped(namespace), key)
+    }
+
+    fn set(&self, namespace: &str, key: &str, value: Vec<u8>) -> Result<()> {
+        let namespace = self.scoped(namespace);
+        self.state.set(&namespace, key, value)?;
+        self.written.lock().insert((namespace, key.to_string()));
+        Ok(())
+    }
+
+    fn delete(&self, namespace: &str, key: &str) -> Result<()> {
+        let namespace = self.scoped(namespace);
+        self.state.delete(&namespace, key)?;
+        self.written.lock().remove(&(namespace, key.to_string()));
+        Ok(())
+    }
+}
+
+/// Pack ids may contain `.`, which would add NATS subject tokens.
+fn subject_token(pack_id: &str) -> String {
+    pack_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state_store::InMemoryStateStore;
+
+    #[test]
+    fn concurrent_sandboxes_share_nothing_and_clean_up() {
+        let tmp = tempfile::tempdir().unwrap();
+        let state = InMemoryStateStore::new();
+        let sandboxes = Sandboxes::new(
+            SandboxConfig {
+                enabled: true,
+                root: Some(Utf8PathBuf::from_path_buf(tmp.path().to_path_buf()).unwrap()),
+                ..SandboxConfig::default()
+            },
+            state.clone(),
+        );
+        let first = sandboxes.open("demo.pack").unwrap();
+        let second = sandboxes.open("demo.pack").unwrap();
+
+        assert_ne!(first.dir(), second.dir());
+        assert!(first.dir().starts_with(tmp.path()));
+        let subject = first.subject("greentic.repo.build.request");
+        assert!(subject.starts_with("sandbox.demo_pack."));
+        assert_ne!(subject, second.subject("greentic.repo.build.request"));
+
+        first.set("dev", "counter", b"1".to_vec()).unwrap();
+        assert_eq!(second.get("dev", "counter").unwrap(), None);
+        assert_eq!(state.get("dev", "counter").unwrap(), None);
+        assert_eq!(first.get("dev", "counter").unwrap(), Some(b"1".to_vec()));
+
+        assert_eq!(first.teardown().unwrap(), 1);
+        assert!(!first.dir().exists());
+        assert_eq!(first.get("dev", "counter").unwrap(), None);
+        assert!(second.dir().exists());
+    }
+
+    #[test]
+    fn budget_rejects_runs_that_outgrow_it() {
+        let tmp = tempfile::tempdir().unwrap();
+        let sandboxes = Sandboxes::new(
+            SandboxConfig {
+                enabled: true,
+                root: Some(Utf8PathBuf::from_path_buf(tmp.path().to_path_buf()).unwrap()),
+                max_steps: 2,
+                max_payload_bytes: 8,
+                ..SandboxConfig::default()
+            },
+            InMemoryStateStore::new(),
+        );
+        let sandbox = sandboxes.open("demo").unwrap();
+        sandbox.check(2, 0, 0, 8).unwrap();
+        let err = sandbox.check(3, 0, 0, 0).unwrap_err();
+        assert!(err.to_string().contains("more than 2 steps"));
+        let err = sandbox.check(1, 0, 0, 9).unwrap_err();
+        assert!(err.to_string().contains("payload of 9 bytes"));
+    }
+}
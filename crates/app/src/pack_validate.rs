@@ -0,0 +1,509 @@
+//! Checks for the pack fixtures under `[packs].root`, behind `packs validate` and
+//! `POST /packs/validate`: every `<root>/*/pack.json` must match the embedded manifest schema
+//! (`schemas/pack/pack.v1.json`, also enforced when the pack index is built), carry the
+//! required fixture fields and semver versions, and each scenario's entry and golden files
+//! must exist and agree with the scenario id.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{Context, Result};
+use jsonschema::Validator;
+use once_cell::sync::Lazy;
+use semver::Version;
+use serde::Serialize;
+use serde_json::Value;
+
+const REQUIRED_MANIFEST_FIELDS: &[&str] =
+    &["id", "name", "version", "description", "type", "scenarios"];
+const REQUIRED_SCENARIO_FIELDS: &[&str] = &["id", "entry", "golden"];
+
+static MANIFEST_SCHEMA: Lazy<Validator> = Lazy::new(|| {
+    let schema: Value = serde_json::from_str(include_str!("../schemas/pack/pack.v1.json"))
+        .expect("embedded pack schema is valid JSON");
+    jsonschema::validator_for(&schema).expect("embedded pack schema is valid")
+});
+
+/// A `pack.json` schema failure located by JSON pointer into the manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ManifestViolation {
+    pub pointer: String,
+    pub message: String,
+}
+
+/// A pack directory whose manifest could not be parsed or interpolated, or failed the schema;
+/// it is left out of the index.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InvalidPack {
+    pub path: String,
+    /// The manifest's `id`, when it has a string one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub reasons: Vec<ManifestViolation>,
+}
+
+/// Check `manifest` against the embedded pack schema, returning every violation.
+pub fn check_manifest_schema(manifest: &Value) -> Vec<ManifestViolation> {
+    MANIFEST_SCHEMA
+        .iter_errors(manifest)
+        .map(|err| ManifestViolation {
+            pointer: err.instance_path().as_str().to_string(),
+            message: err.to_string(),
+        })
+        .collect()
+}
+
+/// Outcome of validating every pack under a root.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PackValidation {
+    pub ok: bool,
+    pub packs: Vec<PackReport>,
+    /// Problems not tied to one pack, such as an empty root.
+    pub errors: Vec<String>,
+}
+
+/// Problems found in one pack directory, as `<file>: <problem>` lines relative to the root.
+/// Schema failures are also listed structurally in `schema`.
+#[derive(Debug, Clone, Serialize)]
+pub struct PackReport {
+    pub pack: String,
+    pub errors: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub schema: Vec<ManifestViolation>,
+}
+
+impl PackValidation {
+    /// Root-level errors first, then each pack's in order.
+    pub fn all_errors(&self) -> impl Iterator<Item = &String> {
+        self.errors
+            .iter()
+            .chain(self.packs.iter().flat_map(|pack| &pack.errors))
+    }
+}
+
+/// Pack directories (those holding a `pack.json`) under `root`, at most `max_depth` levels
+/// down (`1` is `<root>/<pack>/`), sorted by path. A pack's own subdirectories are not
+/// searched, nor are hidden directories or symlinks.
+pub fn find_pack_dirs(root: &Path, max_depth: usize) -> Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    if root.is_dir() {
+        collect_pack_dirs(root, max_depth, &mut found)?;
+    }
+    found.sort();
+    Ok(found)
+}
+
+fn collect_pack_dirs(dir: &Path, depth_left: usize, found: &mut Vec<PathBuf>) -> Result<()> {
+    if depth_left == 0 {
+        return Ok(());
+    }
+    for entry in fs::read_dir(dir).with_context(|| format!("failed to read {}", dir.display()))? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() || entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        if path.join("pack.json").is_file() {
+            found.push(path);
+        } else {
+            collect_pack_dirs(&path, depth_left - 1, found)?;
+        }
+    }
+    Ok(())
+}
+
+/// Validate each `pack.json` found by [`find_pack_dirs`], in path order.
+pub fn validate_root(root: &Path, max_depth: usize) -> Result<PackValidation> {
+    let manifests: Vec<PathBuf> = find_pack_dirs(root, max_depth)?
+        .into_iter()
+        .map(|dir| dir.join("pack.json"))
+        .collect();
+
+    let mut validation = PackValidation::default();
+    if manifests.is_empty() {
+        validation
+            .errors
+            .push(format!("no pack manifests found under {}", root.display()));
+    }
+    for manifest in manifests {
+        let dir = manifest
+            .parent()
+            .expect("manifest sits in a pack directory");
+        let (errors, schema) = validate_manifest(root, &manifest);
+        validation.packs.push(PackReport {
+            pack: relative(root, dir),
+            errors,
+            schema,
+        });
+    }
+    let ok = validation.all_errors().next().is_none();
+    validation.ok = ok;
+    Ok(validation)
+}
+
+fn validate_manifest(root: &Path, path: &Path) -> (Vec<String>, Vec<ManifestViolation>) {
+    let shown = relative(root, path);
+    let manifest = match load_json(root, path) {
+        Ok(manifest) => manifest,
+        Err(err) => return (vec![err], Vec::new()),
+    };
+    let schema = check_manifest_schema(&manifest);
+    let Some(fields) = manifest.as_object() else {
+        return (
+            vec![format!("{shown}: manifest must be a JSON object")],
+            schema,
+        );
+    };
+
+    let mut errors: Vec<String> = schema
+        .iter()
+        .map(|violation| {
+            let pointer = if violation.pointer.is_empty() {
+                "/"
+            } else {
+                &violation.pointer
+            };
+            format!("{shown}: schema: {pointer}: {}", violation.message)
+        })
+        .collect();
+    let missing: Vec<_> = REQUIRED_MANIFEST_FIELDS
+        .iter()
+        .filter(|field| !fields.contains_key(**field))
+        .collect();
+    if !missing.is_empty() {
+        errors.push(format!("{shown}: missing fields {missing:?}"));
+    }
+    for field in ["id", "name", "version", "description", "type"] {
+        if fields.get(field).is_some_and(|value| !value.is_string()) {
+            errors.push(format!("{shown}: '{field}' must be a string"));
+        }
+    }
+    if let Some(version) = fields.get("version").and_then(Value::as_str)
+        && let Err(err) = Version::parse(version)
+    {
+        errors.push(format!("{shown}: version {version:?} is not semver: {err}"));
+    }
+    for component in fields
+        .get("components")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let id = component.get("id").and_then(Value::as_str).unwrap_or("?");
+        match component.get("version").and_then(Value::as_str) {
+            Some(version) => {
+                if let Err(err) = Version::parse(version) {
+                    errors.push(format!(
+                        "{shown}: component {id} version {version:?} is not semver: {err}"
+                    ));
+                }
+            }
+            None => errors.push(format!("{shown}: component {id} has no version")),
+        }
+    }
+
+    let dir = path.parent().expect("manifest sits in a pack directory");
+    match fields.get("scenarios").and_then(Value::as_array) {
+        Some(scenarios) if !scenarios.is_empty() => {
+            for scenario in scenarios {
+                errors.extend(validate_scenario(root, dir, &shown, scenario));
+            }
+        }
+        _ => {
+            errors.push(format!("{shown}: 'scenarios' must be a non-empty list"));
+            return (errors, schema);
+        }
+    }
+
+    if !dir.join("README.md").exists() {
+        errors.push(format!(
+            "{}: README.md is required for contributor context",
+            relative(root, dir)
+        ));
+    }
+    (errors, schema)
+}
+
+fn validate_scenario(root: &Path, dir: &Path, shown: &str, scenario: &Value) -> Vec<String> {
+    let Some(fields) = scenario.as_object() else {
+        return vec![format!("{shown}: scenario entries must be objects")];
+    };
+    let id = fields.get("id").and_then(Value::as_str);
+    let missing: Vec<_> = REQUIRED_SCENARIO_FIELDS
+        .iter()
+        .filter(|field| !fields.get(**field).is_some_and(Value::is_string))
+        .collect();
+    if !missing.is_empty() {
+        return vec![format!(
+            "{shown}: scenario {} missing fields {missing:?}",
+            id.unwrap_or("?")
+        )];
+    }
+    let id = id.expect("checked above");
+    let file = |field: &str| dir.join(fields[field].as_str().expect("checked above"));
+
+    let mut errors = Vec::new();
+    errors.extend(check_file(root, &file("entry"), |path, data| {
+        let mut errors = Vec::new();
+        if data.get("scenario").and_then(Value::as_str) != Some(id) {
+            errors.push(format!("{path}: scenario id mismatch (expected {id})"));
+        }
+        if !non_empty_list(data.get("steps")) {
+            errors.push(format!("{path}: steps must be a non-empty list"));
+        }
+        errors
+    }));
+    errors.extend(check_file(root, &file("golden"), |path, data| {
+        let mut errors = Vec::new();
+        if data.get("scenario_id").and_then(Value::as_str) != Some(id) {
+            errors.push(format!("{path}: scenario_id mismatch (expected {id})"));
+        }
+        if !non_empty_list(data.get("transcript")) {
+            errors.push(format!("{path}: transcript must be a non-empty list"));
+        }
+        errors
+    }));
+    errors
+}
+
+fn check_file(
+    root: &Path,
+    path: &Path,
+    check: impl FnOnce(&str, &Value) -> Vec<String>,
+) -> Vec<String> {
+    match load_json(root, path) {
+        Ok(data) => check(&relative(root, path), &data),
+        Err(err) => vec![err],
+    }
+}
+
+fn load_json(root: &Path, path: &Path) -> std::result::Result<Value, String> {
+    let shown = relative(root, path);
+    let raw = fs::read(path).map_err(|_| format!("missing file: {shown}"))?;
+    serde_json::from_slice(&raw).map_err(|err| format!("invalid JSON in {shown}: {err}"))
+}
+
+fn non_empty_list(value: Option<&Value>) -> bool {
+    value
+        .and_then(Value::as_array)
+        .is_some_and(|items| !items.is_empty())
+}
+
+fn relative(root: &Path, path: &Path) -> String {
+    let path = path.strip_prefix(root).unwrap_or(path);
+    path.components()
+        .map(|part| part.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// With `GREENTIC_PACK_VALIDATE=1`, also run `greentic-dev pack validate` and
+/// `greentic-pack sim` on each manifest. Missing binaries and failures come back as warnings;
+/// the native checks stay authoritative.
+pub fn external_validator_warnings(root: &Path, workdir: &Path, max_depth: usize) -> Vec<String> {
+    if std::env::var("GREENTIC_PACK_VALIDATE").as_deref() != Ok("1") {
+        return Vec::new();
+    }
+    let manifests: Vec<PathBuf> = find_pack_dirs(root, max_depth)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|dir| dir.join("pack.json"))
+        .collect();
+
+    let mut warnings = Vec::new();
+    for manifest in manifests {
+        for (bin, args) in [
+            ("greentic-dev", ["pack", "validate"].as_slice()),
+            ("greentic-pack", ["sim"].as_slice()),
+        ] {
+            let command = format!("{bin} {} {}", args.join(" "), manifest.display());
+            match Command::new(bin)
+                .args(args)
+                .arg(&manifest)
+                .current_dir(workdir)
+                .status()
+            {
+                Ok(status) if status.success() => {}
+                Ok(status) => warnings.push(format!(
+                    "command {command} failed with exit {}",
+                    status.code().unwrap_or(-1)
+                )),
+                Err(_) => warnings.push(format!("skipping {command} (binary not found on PATH)")),
+            }
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn write(dir: &Path, relative: &str, value: &Value) {
+        let path = dir.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, serde_json::to_vec(value).unwrap()).unwrap();
+    }
+
+    fn valid_pack(root: &Path, id: &str) -> PathBuf {
+        let dir = root.join(id);
+        write(
+            &dir,
+            "pack.json",
+            &json!({
+                "id": id,
+                "name": "Demo",
+                "version": "0.1.0",
+                "description": "demo",
+                "type": "adaptive",
+                "scenarios": [
+                    {"id": "greet", "entry": "scenarios/greet.json", "golden": "golden/greet.json"}
+                ],
+                "components": [{"id": "demo.component", "version": "1.0.0"}]
+            }),
+        );
+        write(
+            &dir,
+            "scenarios/greet.json",
+            &json!({"scenario": "greet", "steps": [{"send": "hi"}]}),
+        );
+        write(
+            &dir,
+            "golden/greet.json",
+            &json!({"scenario_id": "greet", "transcript": ["BOT: hi"]}),
+        );
+        fs::write(dir.join("README.md"), "demo\n").unwrap();
+        dir
+    }
+
+    #[test]
+    fn accepts_well_formed_packs() {
+        let tmp = tempfile::tempdir().unwrap();
+        valid_pack(tmp.path(), "alpha");
+        valid_pack(tmp.path(), "beta");
+
+        let validation = validate_root(tmp.path(), 3).unwrap();
+        assert!(validation.ok, "{validation:?}");
+        let packs: Vec<_> = validation.packs.iter().map(|p| p.pack.as_str()).collect();
+        assert_eq!(packs, ["alpha", "beta"]);
+    }
+
+    #[test]
+    fn finds_nested_packs_down_to_the_depth_limit() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        valid_pack(root, "flat");
+        valid_pack(&root.join("acme"), "billing");
+        valid_pack(&root.join("acme/archive/2025"), "old");
+        // Neither a pack inside another pack nor one under a hidden directory counts.
+        valid_pack(&root.join("flat/assets"), "inner");
+        valid_pack(&root.join(".trash"), "gone");
+
+        let found = |depth| -> Vec<String> {
+            find_pack_dirs(root, depth)
+                .unwrap()
+                .iter()
+                .map(|dir| relative(root, dir))
+                .collect()
+        };
+        assert_eq!(found(1), ["flat"]);
+        assert_eq!(found(3), ["acme/billing", "flat"]);
+        assert_eq!(found(4), ["acme/archive/2025/old", "acme/billing", "flat"]);
+
+        let validation = validate_root(root, 2).unwrap();
+        assert!(validation.ok, "{validation:?}");
+        let packs: Vec<_> = validation.packs.iter().map(|p| p.pack.as_str()).collect();
+        assert_eq!(packs, ["acme/billing", "flat"]);
+    }
+
+    #[test]
+    fn reports_schema_semver_and_scenario_problems_per_pack() {
+        let tmp = tempfile::tempdir().unwrap();
+        valid_pack(tmp.path(), "good");
+        let dir = valid_pack(tmp.path(), "broken");
+        write(
+            &dir,
+            "pack.json",
+            &json!({
+                "id": "broken",
+                "version": "1.0",
+                "type": "adaptive",
+                "scenarios": [
+                    {"id": "greet", "entry": "scenarios/greet.json", "golden": "golden/missing.json"},
+                    {"id": "orphan", "entry": "scenarios/greet.json"}
+                ],
+                "components": [{"id": "demo.component", "version": "latest"}]
+            }),
+        );
+        write(
+            &dir,
+            "scenarios/greet.json",
+            &json!({"scenario": "hello", "steps": []}),
+        );
+        fs::remove_file(dir.join("README.md")).unwrap();
+
+        let validation = validate_root(tmp.path(), 3).unwrap();
+        assert!(!validation.ok);
+        assert_eq!(validation.packs[1].pack, "good");
+        assert!(validation.packs[1].errors.is_empty());
+        let broken = &validation.packs[0];
+        assert_eq!(broken.pack, "broken");
+        assert_eq!(broken.errors.len(), 8, "{:#?}", broken.errors);
+        assert_eq!(
+            broken.errors[0],
+            r#"broken/pack.json: missing fields ["name", "description"]"#
+        );
+        assert!(broken.errors[1].starts_with(r#"broken/pack.json: version "1.0" is not semver"#));
+        assert!(broken.errors[2].starts_with(
+            r#"broken/pack.json: component demo.component version "latest" is not semver"#
+        ));
+        assert_eq!(
+            &broken.errors[3..],
+            [
+                "broken/scenarios/greet.json: scenario id mismatch (expected greet)",
+                "broken/scenarios/greet.json: steps must be a non-empty list",
+                "missing file: broken/golden/missing.json",
+                r#"broken/pack.json: scenario orphan missing fields ["golden"]"#,
+                "broken: README.md is required for contributor context",
+            ]
+        );
+    }
+
+    #[test]
+    fn schema_violations_are_reported_with_pointers() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = valid_pack(tmp.path(), "typed");
+        let mut manifest: Value =
+            serde_json::from_slice(&fs::read(dir.join("pack.json")).unwrap()).unwrap();
+        manifest["status"] = json!("retired");
+        manifest["tags"] = json!(["smoke", 7]);
+        write(&dir, "pack.json", &manifest);
+
+        let validation = validate_root(tmp.path(), 3).unwrap();
+        assert!(!validation.ok);
+        let report = &validation.packs[0];
+        let pointers: Vec<_> = report.schema.iter().map(|v| v.pointer.as_str()).collect();
+        assert_eq!(pointers, ["/status", "/tags/1"]);
+        assert!(report.errors[0].starts_with("typed/pack.json: schema: /status: "));
+        assert_eq!(report.errors.len(), 2);
+
+        assert_eq!(check_manifest_schema(&json!({"id": "ok"})), []);
+        let missing_id = check_manifest_schema(&json!({"name": "anonymous"}));
+        assert_eq!(missing_id.len(), 1);
+        assert_eq!(missing_id[0].pointer, "");
+    }
+
+    #[test]
+    fn an_empty_root_is_an_error() {
+        let tmp = tempfile::tempdir().unwrap();
+        let validation = validate_root(tmp.path(), 3).unwrap();
+        assert!(!validation.ok);
+        assert!(validation.packs.is_empty());
+        assert!(validation.errors[0].starts_with("no pack manifests found under"));
+    }
+}
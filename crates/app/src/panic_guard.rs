@@ -0,0 +1,125 @@
+//! Middleware turning handler panics into structured `500` responses instead of a dropped
+//! connection, and recording each one (with its request context) for
+//! `GET /diagnostics/panics`.
+
+use std::{
+    any::Any,
+    collections::VecDeque,
+    panic::AssertUnwindSafe,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use axum::{
+    Json,
+    extract::{Request, State},
+    http::{HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use futures::FutureExt;
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::json;
+use tracing::error;
+use uuid::Uuid;
+
+/// Header carrying the request id echoed on panic responses (taken from the request if set).
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Panic reports kept for `GET /diagnostics/panics`.
+const RECENT_PANICS: usize = 50;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PanicReport {
+    pub timestamp_ms: u64,
+    pub request_id: String,
+    pub method: String,
+    pub path: String,
+    pub message: String,
+}
+
+/// Total panic count plus the most recent reports.
+#[derive(Debug, Clone, Default)]
+pub struct PanicLog {
+    total: Arc<AtomicU64>,
+    recent: Arc<Mutex<VecDeque<PanicReport>>>,
+}
+
+impl PanicLog {
+    pub fn total(&self) -> u64 {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    /// Recent reports, oldest first.
+    pub fn recent(&self) -> Vec<PanicReport> {
+        self.recent.lock().iter().cloned().collect()
+    }
+
+    fn record(&self, report: PanicReport) {
+        self.total.fetch_add(1, Ordering::Relaxed);
+        let mut recent = self.recent.lock();
+        if recent.len() == RECENT_PANICS {
+            recent.pop_front();
+        }
+        recent.push_back(report);
+    }
+}
+
+/// `axum::middleware::from_fn_with_state(panic_log, catch_panics)`.
+pub async fn catch_panics(State(log): State<PanicLog>, request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+
+    let payload = match AssertUnwindSafe(next.run(request)).catch_unwind().await {
+        Ok(response) => return response,
+        Err(payload) => payload,
+    };
+    let message = panic_message(payload.as_ref());
+    error!(
+        audit = "handler_panic",
+        %request_id,
+        %method,
+        %path,
+        %message,
+        "request handler panicked"
+    );
+    log.record(PanicReport {
+        timestamp_ms: crate::now_millis(),
+        request_id: request_id.clone(),
+        method,
+        path,
+        message: message.clone(),
+    });
+
+    let mut response = (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({
+            "error": "internal_panic",
+            "message": message,
+            "request_id": request_id,
+        })),
+    )
+        .into_response();
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "handler panicked".to_string())
+}
@@ -0,0 +1,162 @@
+//! Stable assignment of tenants and sessions to a fixed number of partitions
+//! (`[partitioning]`), reported on `GET /partitions`. The runner worker pool
+//! (`runner_queue::worker_for`) places ordering keys with it too, and anything else that
+//! shards by key should share it rather than hash on its own. Keys are hashed with SHA-256
+//! (not `DefaultHasher`, whose output may change between Rust releases) and placed with jump
+//! consistent hashing, so the same key lands on the same partition on every instance and
+//! build, and going from N to N+1 partitions only moves the ~1/(N+1) of keys that now belong
+//! to the new partition.
+
+use std::collections::BTreeMap;
+
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartitioningConfig {
+    /// Number of partitions keys are spread over.
+    #[serde(default = "default_partitions")]
+    pub partitions: u32,
+}
+
+impl Default for PartitioningConfig {
+    fn default() -> Self {
+        Self {
+            partitions: default_partitions(),
+        }
+    }
+}
+
+fn default_partitions() -> u32 {
+    16
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Partitioner {
+    partitions: u32,
+}
+
+impl Partitioner {
+    pub fn new(partitions: u32) -> Result<Self> {
+        if partitions == 0 {
+            bail!("partition count must be at least 1");
+        }
+        Ok(Self { partitions })
+    }
+
+    pub fn from_config(config: &PartitioningConfig) -> Result<Self> {
+        Self::new(config.partitions)
+    }
+
+    pub fn partitions(&self) -> u32 {
+        self.partitions
+    }
+
+    /// Partition of an arbitrary key, in `0..partitions`.
+    pub fn partition_of(&self, key: &str) -> u32 {
+        let digest = Sha256::digest(key.as_bytes());
+        let mut head = [0u8; 8];
+        head.copy_from_slice(&digest[..8]);
+        jump_hash(u64::from_be_bytes(head), self.partitions)
+    }
+
+    /// Assign every key, reporting how many landed on each partition.
+    pub fn assign<I>(&self, keys: I) -> Assignments
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut load = vec![0; self.partitions as usize];
+        let assignments: BTreeMap<String, u32> = keys
+            .into_iter()
+            .map(|key| {
+                let partition = self.partition_of(&key);
+                load[partition as usize] += 1;
+                (key, partition)
+            })
+            .collect();
+        Assignments {
+            partitions: self.partitions,
+            assignments,
+            load,
+        }
+    }
+}
+
+/// Keys with their partition, plus the number of keys per partition (index = partition).
+#[derive(Debug, Clone, Serialize)]
+pub struct Assignments {
+    pub partitions: u32,
+    pub assignments: BTreeMap<String, u32>,
+    pub load: Vec<usize>,
+}
+
+/// Lamping & Veach, "A Fast, Minimal Memory, Consistent Hash Algorithm" (2014).
+fn jump_hash(mut key: u64, buckets: u32) -> u32 {
+    let mut bucket: i64 = -1;
+    let mut next: i64 = 0;
+    while next < i64::from(buckets) {
+        bucket = next;
+        key = key.wrapping_mul(2_862_933_555_777_941_757).wrapping_add(1);
+        next = ((bucket + 1) as f64 * ((1u64 << 31) as f64 / ((key >> 33) + 1) as f64)) as i64;
+    }
+    bucket as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys() -> impl Iterator<Item = String> {
+        (0..10_000).map(|idx| format!("tenant-{idx}"))
+    }
+
+    #[test]
+    fn assignments_are_stable_and_evenly_spread() {
+        let partitioner = Partitioner::new(8).unwrap();
+        assert_eq!(
+            partitioner.partition_of("acme"),
+            Partitioner::new(8).unwrap().partition_of("acme")
+        );
+        let assigned = partitioner.assign(keys());
+        assert_eq!(assigned.load.iter().sum::<usize>(), 10_000);
+        for load in &assigned.load {
+            assert!(
+                (1_000..1_500).contains(load),
+                "uneven load {:?}",
+                assigned.load
+            );
+        }
+        assert_eq!(Partitioner::new(1).unwrap().partition_of("acme"), 0);
+        assert!(Partitioner::new(0).is_err());
+    }
+
+    #[test]
+    fn placements_are_pinned_across_builds() {
+        // Fixed values: a change here reshuffles every deployed shard.
+        let partitioner = Partitioner::new(16).unwrap();
+        let placed: Vec<u32> = ["acme", "dev", "tenant-42", ""]
+            .iter()
+            .map(|key| partitioner.partition_of(key))
+            .collect();
+        assert_eq!(placed, vec![10, 15, 10, 5]);
+    }
+
+    #[test]
+    fn growing_and_shrinking_only_moves_keys_of_the_changed_partition() {
+        let before = Partitioner::new(8).unwrap();
+        let after = Partitioner::new(9).unwrap();
+        let mut moved = 0;
+        for key in keys() {
+            let (old, new) = (before.partition_of(&key), after.partition_of(&key));
+            if old != new {
+                // Growing moves keys only onto the new partition; shrinking back moves
+                // exactly those keys and no others.
+                assert_eq!(new, 8, "{key} moved from {old} to {new}");
+                moved += 1;
+            }
+        }
+        // 1/9 of the keys is ~1111; a modulo scheme would move ~8/9 of them.
+        assert!((900..1_350).contains(&moved), "{moved} keys moved");
+    }
+}
@@ -0,0 +1,153 @@
+//! SMS and WhatsApp as messaging channels, against the providers-sim phone simulator.
+//! `POST /providers/{sms|whatsapp}/webhook` takes the simulator's inbound message, routes
+//! its business number to a tenant and flow by the `[[phone.routes]]` rules and runs the
+//! flow with a canonical inbound message. Sends to the `sms`/`whatsapp` providers are
+//! rendered the way the channel delivers them, so a long text is queued as one outbox entry
+//! per segment and cards arrive as text.
+#![cfg_attr(not(feature = "mini-runner"), allow(dead_code))]
+
+use providers_sim::phone::{PhoneChannel, PhoneInbound, PhoneMedia, render_outbound};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PhoneConfig {
+    /// Which tenant and flow each business number belongs to; the first matching rule wins.
+    #[serde(default)]
+    pub routes: Vec<PhoneRoute>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhoneRoute {
+    /// Business number messages are sent to, in E.164 form (`+15550100`).
+    pub number: String,
+    /// Channel the rule applies to; unset matches SMS and WhatsApp alike.
+    #[serde(default)]
+    pub channel: Option<PhoneChannel>,
+    pub tenant: String,
+    #[serde(default)]
+    pub team: Option<String>,
+    pub flow: String,
+}
+
+impl PhoneConfig {
+    pub fn route(&self, channel: PhoneChannel, to: &str) -> Option<&PhoneRoute> {
+        let to = normalize_number(to);
+        self.routes.iter().find(|route| {
+            route.channel.is_none_or(|only| only == channel)
+                && normalize_number(&route.number) == to
+        })
+    }
+}
+
+/// `+1 (555) 010-0` and `whatsapp:+15550100` style numbers reduced to `+15550100`.
+pub fn normalize_number(number: &str) -> String {
+    let number = number.strip_prefix("whatsapp:").unwrap_or(number);
+    number
+        .chars()
+        .filter(|ch| ch.is_ascii_digit() || *ch == '+')
+        .collect()
+}
+
+/// Canonical inbound message for a webhook delivery. Each sender/business number pair is
+/// one conversation, so it doubles as the `thread_id`.
+pub fn inbound_payload(channel: PhoneChannel, inbound: &PhoneInbound) -> Value {
+    let (from, to) = (
+        normalize_number(&inbound.from),
+        normalize_number(&inbound.to),
+    );
+    json!({
+        "channel": channel.provider(),
+        "provider": channel.provider(),
+        "text": inbound.text,
+        "from": from,
+        "to": to,
+        "message_id": inbound.message_id,
+        "media": inbound.media,
+        "thread_id": format!("{}:{to}:{from}", channel.provider()),
+    })
+}
+
+/// The outbox bodies for one send to a phone channel: `body` (as queued for any provider)
+/// once per message the channel delivers, with the text, media, segment and downgrades of
+/// that message. The card and media are read from `body.payload.card` and
+/// `body.payload.media`.
+pub fn outbound_bodies(channel: PhoneChannel, body: &Value) -> Vec<Value> {
+    let payload = body.get("payload");
+    let card = payload.and_then(|payload| payload.get("card"));
+    let media: Vec<PhoneMedia> = payload
+        .and_then(|payload| payload.get("media"))
+        .and_then(|media| serde_json::from_value(media.clone()).ok())
+        .unwrap_or_default();
+    let text = body.get("text").and_then(Value::as_str);
+    render_outbound(channel, text, card, &media)
+        .into_iter()
+        .map(|message| {
+            let mut body = body.clone();
+            body["text"] = json!(message.text);
+            body["media"] = json!(message.media);
+            body["segment"] = json!(message.segment);
+            body["encoding"] = json!(message.encoding);
+            body["downgraded"] = json!(message.downgraded);
+            body
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes_numbers_and_splits_sends_per_channel() {
+        let config = PhoneConfig {
+            routes: vec![
+                PhoneRoute {
+                    number: "+1 555 0100".into(),
+                    channel: Some(PhoneChannel::Whatsapp),
+                    tenant: "acme".into(),
+                    team: None,
+                    flow: "wa-support".into(),
+                },
+                PhoneRoute {
+                    number: "+15550100".into(),
+                    channel: None,
+                    tenant: "acme".into(),
+                    team: None,
+                    flow: "sms-support".into(),
+                },
+            ],
+        };
+        let route = |channel, to| config.route(channel, to).map(|route| route.flow.as_str());
+        assert_eq!(
+            route(PhoneChannel::Whatsapp, "whatsapp:+15550100"),
+            Some("wa-support")
+        );
+        assert_eq!(route(PhoneChannel::Sms, "+1-555-0100"), Some("sms-support"));
+        assert_eq!(route(PhoneChannel::Sms, "+15550199"), None);
+
+        let inbound = PhoneInbound {
+            message_id: "SM1".into(),
+            from: "+1 555 0142".into(),
+            to: "+15550100".into(),
+            text: Some("hi".into()),
+            media: Vec::new(),
+        };
+        let payload = inbound_payload(PhoneChannel::Sms, &inbound);
+        assert_eq!(payload["from"], "+15550142");
+        assert_eq!(payload["thread_id"], "sms:+15550100:+15550142");
+
+        let body = json!({
+            "flow": "sms-support",
+            "provider": "sms",
+            "text": "y".repeat(200),
+            "payload": { "card": { "type": "AdaptiveCard", "body": [{ "type": "TextBlock", "text": "Done" }] } },
+        });
+        let sends = outbound_bodies(PhoneChannel::Sms, &body);
+        assert_eq!(sends.len(), 2);
+        assert_eq!(sends[0]["segment"], json!({ "index": 1, "count": 2 }));
+        assert_eq!(sends[0]["flow"], "sms-support");
+        assert_eq!(sends[1]["text"], format!("{}\nDone", "y".repeat(47)));
+        assert_eq!(sends[1]["downgraded"], json!(["card"]));
+    }
+}
@@ -0,0 +1,266 @@
+//! Signed plan bundles (`packs plan --sign`, `deploy verify-plan-bundle`): a directory holding
+//! `plan.json`, `metadata.json` (provenance plus the plan's SHA-256) and `plan.sig`, the hex
+//! Ed25519 signature of the metadata bytes. Both JSON files are checked against the schemas
+//! under `schemas/plan-bundle/`, so deployers only act on plans this bridge produced.
+
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::deployment::DeploymentPlan;
+
+pub const BUNDLE_FORMAT: &str = "greentic.plan-bundle.v1";
+const PLAN_FILE: &str = "plan.json";
+const METADATA_FILE: &str = "metadata.json";
+const SIGNATURE_FILE: &str = "plan.sig";
+
+static PLAN_SCHEMA: Lazy<Value> = Lazy::new(|| {
+    serde_json::from_str(include_str!("../schemas/plan-bundle/plan.v1.json"))
+        .expect("embedded plan schema is valid JSON")
+});
+static METADATA_SCHEMA: Lazy<Value> = Lazy::new(|| {
+    serde_json::from_str(include_str!("../schemas/plan-bundle/metadata.v1.json"))
+        .expect("embedded metadata schema is valid JSON")
+});
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleMetadata {
+    pub format: String,
+    pub algorithm: String,
+    /// Hex Ed25519 public key of the signer.
+    pub public_key: String,
+    pub plan_sha256: String,
+    pub pack_id: String,
+    pub pack_version: String,
+    /// Content hash of the pack directory the plan was inferred from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pack_content_hash: Option<String>,
+    pub tenant: String,
+    pub environment: String,
+    pub generator: String,
+    pub signed_at_epoch_ms: u64,
+}
+
+/// Read an Ed25519 signing key stored as the hex of its 32-byte seed.
+pub fn load_signing_key(path: &Path) -> Result<SigningKey> {
+    Ok(SigningKey::from_bytes(&read_hex_key(path)?))
+}
+
+/// Read an Ed25519 public key stored as 32 hex-encoded bytes.
+pub fn load_verifying_key(path: &Path) -> Result<VerifyingKey> {
+    VerifyingKey::from_bytes(&read_hex_key(path)?)
+        .with_context(|| format!("{} is not a valid Ed25519 public key", path.display()))
+}
+
+fn read_hex_key(path: &Path) -> Result<[u8; 32]> {
+    let raw = fs::read_to_string(path)
+        .with_context(|| format!("failed to read key {}", path.display()))?;
+    let bytes = hex::decode(raw.trim())
+        .with_context(|| format!("key {} is not hex-encoded", path.display()))?;
+    bytes.try_into().map_err(|bytes: Vec<u8>| {
+        anyhow!(
+            "key {} has {} bytes, expected 32",
+            path.display(),
+            bytes.len()
+        )
+    })
+}
+
+/// Write `plan` as a signed bundle into `dir` (created if missing) and return its metadata.
+pub fn write_bundle(
+    dir: &Path,
+    plan: &DeploymentPlan,
+    pack_content_hash: Option<String>,
+    key: &SigningKey,
+    now_ms: u64,
+) -> Result<BundleMetadata> {
+    let plan_json = serde_json::to_vec_pretty(plan)?;
+    ensure_valid(
+        &PLAN_SCHEMA,
+        &serde_json::from_slice(&plan_json)?,
+        PLAN_FILE,
+    )?;
+    let metadata = BundleMetadata {
+        format: BUNDLE_FORMAT.into(),
+        algorithm: "ed25519".into(),
+        public_key: hex::encode(key.verifying_key().as_bytes()),
+        plan_sha256: hex::encode(Sha256::digest(&plan_json)),
+        pack_id: plan.pack_id.clone(),
+        pack_version: plan.pack_version.to_string(),
+        pack_content_hash,
+        tenant: plan.tenant.clone(),
+        environment: plan.environment.clone(),
+        generator: concat!("greentic-integration ", env!("CARGO_PKG_VERSION")).into(),
+        signed_at_epoch_ms: now_ms,
+    };
+    let metadata_json = serde_json::to_vec_pretty(&metadata)?;
+    let signature = key.sign(&metadata_json);
+
+    fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    for (name, bytes) in [
+        (PLAN_FILE, plan_json),
+        (METADATA_FILE, metadata_json),
+        (
+            SIGNATURE_FILE,
+            hex::encode(signature.to_bytes()).into_bytes(),
+        ),
+    ] {
+        let path = dir.join(name);
+        fs::write(&path, bytes).with_context(|| format!("failed to write {}", path.display()))?;
+    }
+    Ok(metadata)
+}
+
+/// Check a bundle's signature against `trusted`, the plan digest, both schemas and that the
+/// plan matches the metadata it was signed with. Returns the verified plan and metadata.
+pub fn verify_bundle(
+    dir: &Path,
+    trusted: &VerifyingKey,
+) -> Result<(DeploymentPlan, BundleMetadata)> {
+    let read = |name: &str| {
+        let path = dir.join(name);
+        fs::read(&path).with_context(|| format!("failed to read {}", path.display()))
+    };
+    let metadata_json = read(METADATA_FILE)?;
+    let plan_json = read(PLAN_FILE)?;
+    let signature = String::from_utf8(read(SIGNATURE_FILE)?)
+        .ok()
+        .and_then(|raw| hex::decode(raw.trim()).ok())
+        .and_then(|bytes| Signature::from_slice(&bytes).ok())
+        .ok_or_else(|| anyhow!("{SIGNATURE_FILE} is not a hex Ed25519 signature"))?;
+
+    let metadata_value: Value = serde_json::from_slice(&metadata_json)
+        .with_context(|| format!("invalid JSON in {METADATA_FILE}"))?;
+    ensure_valid(&METADATA_SCHEMA, &metadata_value, METADATA_FILE)?;
+    let metadata: BundleMetadata = serde_json::from_value(metadata_value)?;
+    ensure!(
+        metadata.public_key == hex::encode(trusted.as_bytes()),
+        "bundle was signed by key {}, not the trusted key",
+        metadata.public_key
+    );
+    trusted
+        .verify(&metadata_json, &signature)
+        .map_err(|_| anyhow!("signature does not match {METADATA_FILE}"))?;
+    ensure!(
+        hex::encode(Sha256::digest(&plan_json)) == metadata.plan_sha256,
+        "{PLAN_FILE} does not match the digest in {METADATA_FILE}"
+    );
+
+    let plan_value: Value = serde_json::from_slice(&plan_json)
+        .with_context(|| format!("invalid JSON in {PLAN_FILE}"))?;
+    ensure_valid(&PLAN_SCHEMA, &plan_value, PLAN_FILE)?;
+    let plan: DeploymentPlan = serde_json::from_value(plan_value)
+        .with_context(|| format!("{PLAN_FILE} is not a deployment plan"))?;
+    let signed = (
+        &metadata.pack_id,
+        &metadata.pack_version,
+        &metadata.tenant,
+        &metadata.environment,
+    );
+    let planned = (
+        &plan.pack_id,
+        &plan.pack_version.to_string(),
+        &plan.tenant,
+        &plan.environment,
+    );
+    if signed != planned {
+        bail!("{PLAN_FILE} describes {planned:?} but {METADATA_FILE} was signed for {signed:?}");
+    }
+    Ok((plan, metadata))
+}
+
+fn ensure_valid(schema: &Value, instance: &Value, file: &str) -> Result<()> {
+    let validator = jsonschema::validator_for(schema)
+        .map_err(|err| anyhow!("invalid embedded schema for {file}: {err}"))?;
+    let errors: Vec<String> = validator
+        .iter_errors(instance)
+        .map(|err| format!("{}: {err}", err.instance_path().as_str()))
+        .collect();
+    if !errors.is_empty() {
+        bail!("{file} violates its schema: {}", errors.join("; "));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use semver::Version;
+    use serde_json::json;
+
+    fn plan() -> DeploymentPlan {
+        DeploymentPlan {
+            pack_id: "menu".into(),
+            pack_version: Version::new(1, 2, 0),
+            tenant: "acme".into(),
+            environment: "prod".into(),
+            runners: Vec::new(),
+            messaging: None,
+            channels: Vec::new(),
+            secrets: Vec::new(),
+            oauth: Vec::new(),
+            telemetry: None,
+            extra: json!({}),
+        }
+    }
+
+    #[test]
+    fn verifies_signed_bundles_and_rejects_tampering() {
+        let tmp = tempfile::tempdir().unwrap();
+        let key_path = tmp.path().join("signing.key");
+        fs::write(&key_path, format!("{}\n", hex::encode([7u8; 32]))).unwrap();
+        let key = load_signing_key(&key_path).unwrap();
+        let public_path = tmp.path().join("signing.pub");
+        fs::write(&public_path, hex::encode(key.verifying_key().as_bytes())).unwrap();
+        let trusted = load_verifying_key(&public_path).unwrap();
+
+        let bundle = tmp.path().join("bundle");
+        let written = write_bundle(&bundle, &plan(), Some("abc".into()), &key, 42).unwrap();
+        let (verified, metadata) = verify_bundle(&bundle, &trusted).unwrap();
+        assert_eq!(verified, plan());
+        assert_eq!(metadata, written);
+
+        // A different signer is rejected even though its own signature is valid.
+        let other = SigningKey::from_bytes(&[9u8; 32]);
+        let err = verify_bundle(&bundle, &other.verifying_key()).unwrap_err();
+        assert!(err.to_string().contains("not the trusted key"), "{err}");
+
+        // Editing the plan breaks the digest; editing the metadata breaks the signature.
+        let plan_path = bundle.join(PLAN_FILE);
+        let original = fs::read_to_string(&plan_path).unwrap();
+        fs::write(&plan_path, original.replace("prod", "dev")).unwrap();
+        let err = verify_bundle(&bundle, &trusted).unwrap_err();
+        assert!(
+            err.to_string().contains("does not match the digest"),
+            "{err}"
+        );
+        fs::write(&plan_path, &original).unwrap();
+        let metadata_path = bundle.join(METADATA_FILE);
+        let signed = fs::read_to_string(&metadata_path).unwrap();
+        fs::write(&metadata_path, signed.replace("\"abc\"", "\"abd\"")).unwrap();
+        let err = verify_bundle(&bundle, &trusted).unwrap_err();
+        assert!(
+            err.to_string().contains("signature does not match"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn rejects_metadata_outside_the_schema() {
+        let tmp = tempfile::tempdir().unwrap();
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        write_bundle(tmp.path(), &plan(), None, &key, 42).unwrap();
+        fs::write(
+            tmp.path().join(METADATA_FILE),
+            json!({"format": "something-else"}).to_string(),
+        )
+        .unwrap();
+        let err = verify_bundle(tmp.path(), &key.verifying_key()).unwrap_err();
+        assert!(err.to_string().contains("violates its schema"), "{err}");
+    }
+}
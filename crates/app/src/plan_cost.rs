@@ -0,0 +1,386 @@
+//! Rough monthly resource and cost hints for inferred deployment plans, priced from
+//! `plans/pricing.yaml`. `packs plan --cost` adds the estimate as `extra.cost`, and
+//! `packs plan-diff` compares the estimates of two plans so reviewers see what a pack change
+//! does to the footprint. The numbers are for comparison, not billing.
+//!
+//! ```yaml
+//! currency: USD
+//! cpu_core_month: 30.0
+//! memory_gib_month: 4.0
+//! durable_subject_month: 1.5
+//! telemetry_month: { none: 0, low: 5, medium: 20, high: 60 }
+//! default_requests: { cpu: 250m, memory: 256Mi }
+//! ```
+
+use std::{collections::BTreeMap, fmt, path::Path};
+
+use anyhow::{Context, Result, bail};
+use greentic_types::deployment::{DeploymentPlan, RunnerPlan};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Prices per month; omitted keys keep their defaults.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PricingTable {
+    pub currency: String,
+    pub cpu_core_month: f64,
+    pub memory_gib_month: f64,
+    pub durable_subject_month: f64,
+    pub telemetry_month: BTreeMap<TelemetryVolume, f64>,
+    /// Requests assumed for runners whose capabilities declare none.
+    pub default_requests: ResourceRequests,
+}
+
+impl Default for PricingTable {
+    fn default() -> Self {
+        Self {
+            currency: "USD".into(),
+            cpu_core_month: 30.0,
+            memory_gib_month: 4.0,
+            durable_subject_month: 1.5,
+            telemetry_month: BTreeMap::from([
+                (TelemetryVolume::None, 0.0),
+                (TelemetryVolume::Low, 5.0),
+                (TelemetryVolume::Medium, 20.0),
+                (TelemetryVolume::High, 60.0),
+            ]),
+            default_requests: ResourceRequests {
+                cpu: "250m".into(),
+                memory: "256Mi".into(),
+            },
+        }
+    }
+}
+
+/// Kubernetes-style quantities: `cpu` in cores or millicores (`500m`), `memory` in bytes or
+/// with a binary/decimal suffix (`256Mi`, `1G`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ResourceRequests {
+    pub cpu: String,
+    pub memory: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TelemetryVolume {
+    None,
+    Low,
+    Medium,
+    High,
+}
+
+impl fmt::Display for TelemetryVolume {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::None => "none",
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Resource footprint and monthly cost of one plan.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlanCost {
+    pub currency: String,
+    pub replicas: u32,
+    pub cpu_cores: f64,
+    pub memory_gib: f64,
+    pub durable_subjects: usize,
+    pub telemetry_volume: TelemetryVolume,
+    pub monthly: MonthlyCost,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MonthlyCost {
+    pub compute: f64,
+    pub memory: f64,
+    pub messaging: f64,
+    pub telemetry: f64,
+    pub total: f64,
+}
+
+/// Head minus base for each figure of two estimates.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CostDelta {
+    pub replicas: i64,
+    pub cpu_cores: f64,
+    pub memory_gib: f64,
+    pub durable_subjects: i64,
+    pub telemetry_volume: Option<(TelemetryVolume, TelemetryVolume)>,
+    pub monthly_total: f64,
+}
+
+impl PricingTable {
+    pub fn from_yaml(raw: &str) -> Result<Self> {
+        let table: Self = serde_yaml_bw::from_str(raw).context("invalid pricing table")?;
+        parse_cpu(&table.default_requests.cpu)?;
+        parse_memory_gib(&table.default_requests.memory)?;
+        Ok(table)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read pricing table {}", path.display()))?;
+        Self::from_yaml(&raw).with_context(|| format!("in {}", path.display()))
+    }
+
+    /// Price `plan`: every runner's replicas times its requests (declared under
+    /// `capabilities.resources.requests`, else the table's defaults), its durable messaging
+    /// subjects, and its telemetry volume class.
+    pub fn estimate(&self, plan: &DeploymentPlan) -> Result<PlanCost> {
+        let mut replicas = 0;
+        let mut cpu_cores = 0.0;
+        let mut memory_gib = 0.0;
+        for runner in &plan.runners {
+            let (cpu, memory) = self.requests_of(runner)?;
+            replicas += runner.replicas;
+            cpu_cores += cpu * f64::from(runner.replicas);
+            memory_gib += memory * f64::from(runner.replicas);
+        }
+        let durable_subjects = plan
+            .messaging
+            .iter()
+            .flat_map(|messaging| &messaging.subjects)
+            .filter(|subject| subject.durable)
+            .count();
+        let telemetry_volume = telemetry_volume(plan)?;
+
+        let compute = cpu_cores * self.cpu_core_month;
+        let memory = memory_gib * self.memory_gib_month;
+        let messaging = durable_subjects as f64 * self.durable_subject_month;
+        let telemetry = self
+            .telemetry_month
+            .get(&telemetry_volume)
+            .copied()
+            .unwrap_or_default();
+        Ok(PlanCost {
+            currency: self.currency.clone(),
+            replicas,
+            cpu_cores: round(cpu_cores),
+            memory_gib: round(memory_gib),
+            durable_subjects,
+            telemetry_volume,
+            monthly: MonthlyCost {
+                compute: round(compute),
+                memory: round(memory),
+                messaging: round(messaging),
+                telemetry: round(telemetry),
+                total: round(compute + memory + messaging + telemetry),
+            },
+        })
+    }
+
+    /// Add the estimate to `plan.extra.cost`.
+    pub fn annotate(&self, plan: &mut DeploymentPlan) -> Result<PlanCost> {
+        let cost = self.estimate(plan)?;
+        if !plan.extra.is_object() {
+            plan.extra = Value::Object(Default::default());
+        }
+        plan.extra["cost"] = serde_json::to_value(&cost)?;
+        Ok(cost)
+    }
+
+    fn requests_of(&self, runner: &RunnerPlan) -> Result<(f64, f64)> {
+        let declared = runner.capabilities.pointer("/resources/requests");
+        let field = |name: &str, default: &str| -> Result<String> {
+            match declared.and_then(|requests| requests.get(name)) {
+                None => Ok(default.to_string()),
+                Some(Value::String(value)) => Ok(value.clone()),
+                Some(Value::Number(value)) => Ok(value.to_string()),
+                Some(other) => bail!("runner {} declares {name} request {other}", runner.name),
+            }
+        };
+        let cpu = field("cpu", &self.default_requests.cpu)?;
+        let memory = field("memory", &self.default_requests.memory)?;
+        let cpu = parse_cpu(&cpu).with_context(|| format!("runner {}", runner.name))?;
+        let memory =
+            parse_memory_gib(&memory).with_context(|| format!("runner {}", runner.name))?;
+        Ok((cpu, memory))
+    }
+}
+
+impl PlanCost {
+    pub fn delta(base: &Self, head: &Self) -> CostDelta {
+        CostDelta {
+            replicas: i64::from(head.replicas) - i64::from(base.replicas),
+            cpu_cores: round(head.cpu_cores - base.cpu_cores),
+            memory_gib: round(head.memory_gib - base.memory_gib),
+            durable_subjects: head.durable_subjects as i64 - base.durable_subjects as i64,
+            telemetry_volume: (base.telemetry_volume != head.telemetry_volume)
+                .then_some((base.telemetry_volume, head.telemetry_volume)),
+            monthly_total: round(head.monthly.total - base.monthly.total),
+        }
+    }
+}
+
+/// `telemetry.extra.volume` when the plan declares it; otherwise `none` without required
+/// telemetry and a class by channel count (up to 2 low, up to 5 medium, more high).
+fn telemetry_volume(plan: &DeploymentPlan) -> Result<TelemetryVolume> {
+    let Some(telemetry) = &plan.telemetry else {
+        return Ok(TelemetryVolume::None);
+    };
+    if let Some(declared) = telemetry.extra.get("volume") {
+        return serde_json::from_value(declared.clone()).with_context(|| {
+            format!("telemetry volume {declared} (expected none, low, medium or high)")
+        });
+    }
+    Ok(match plan.channels.len() {
+        _ if !telemetry.required => TelemetryVolume::None,
+        0..=2 => TelemetryVolume::Low,
+        3..=5 => TelemetryVolume::Medium,
+        _ => TelemetryVolume::High,
+    })
+}
+
+fn parse_cpu(raw: &str) -> Result<f64> {
+    let value = match raw.strip_suffix('m') {
+        Some(millis) => millis.parse::<f64>().map(|m| m / 1000.0),
+        None => raw.parse::<f64>(),
+    };
+    match value {
+        Ok(cores) if cores.is_finite() && cores >= 0.0 => Ok(cores),
+        _ => bail!("invalid cpu quantity {raw:?}"),
+    }
+}
+
+fn parse_memory_gib(raw: &str) -> Result<f64> {
+    const GIB: f64 = 1024.0 * 1024.0 * 1024.0;
+    let units: &[(&str, f64)] = &[
+        ("Ki", 1024.0),
+        ("Mi", 1024.0 * 1024.0),
+        ("Gi", GIB),
+        ("Ti", GIB * 1024.0),
+        ("k", 1e3),
+        ("M", 1e6),
+        ("G", 1e9),
+        ("T", 1e12),
+    ];
+    let (number, scale) = units
+        .iter()
+        .find_map(|(suffix, scale)| raw.strip_suffix(suffix).map(|n| (n, *scale)))
+        .unwrap_or((raw, 1.0));
+    match number.parse::<f64>() {
+        Ok(value) if value.is_finite() && value >= 0.0 => Ok(value * scale / GIB),
+        _ => bail!("invalid memory quantity {raw:?}"),
+    }
+}
+
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use greentic_types::deployment::{
+        ChannelPlan, MessagingPlan, MessagingSubjectPlan, TelemetryPlan,
+    };
+    use serde_json::json;
+
+    use super::*;
+
+    fn plan(replicas: u32, capabilities: Value, durable: &[bool]) -> DeploymentPlan {
+        DeploymentPlan {
+            pack_id: "menu".into(),
+            pack_version: semver::Version::new(1, 0, 0),
+            tenant: "dev".into(),
+            environment: "dev".into(),
+            runners: vec![RunnerPlan {
+                name: "menu-runner".into(),
+                replicas,
+                capabilities,
+            }],
+            messaging: Some(MessagingPlan {
+                logical_cluster: "default".into(),
+                subjects: durable
+                    .iter()
+                    .enumerate()
+                    .map(|(i, durable)| MessagingSubjectPlan {
+                        name: format!("s{i}"),
+                        purpose: "scenario".into(),
+                        durable: *durable,
+                        extra: Value::Null,
+                    })
+                    .collect(),
+                extra: Value::Null,
+            }),
+            channels: vec![ChannelPlan {
+                name: "main".into(),
+                flow_id: "main".into(),
+                kind: "scenario".into(),
+                config: Value::Null,
+            }],
+            secrets: Vec::new(),
+            oauth: Vec::new(),
+            telemetry: Some(TelemetryPlan {
+                required: true,
+                suggested_endpoint: None,
+                extra: Value::Null,
+            }),
+            extra: json!({"pack_kind": "application"}),
+        }
+    }
+
+    #[test]
+    fn prices_replicas_requests_subjects_and_telemetry() {
+        let pricing = PricingTable::default();
+        let declared = json!({"resources": {"requests": {"cpu": "500m", "memory": "1Gi"}}});
+        let cost = pricing
+            .estimate(&plan(3, declared, &[true, false, true]))
+            .unwrap();
+        assert_eq!(cost.replicas, 3);
+        assert_eq!(cost.cpu_cores, 1.5);
+        assert_eq!(cost.memory_gib, 3.0);
+        assert_eq!(cost.durable_subjects, 2);
+        assert_eq!(cost.telemetry_volume, TelemetryVolume::Low);
+        assert_eq!(
+            cost.monthly,
+            MonthlyCost {
+                compute: 45.0,
+                memory: 12.0,
+                messaging: 3.0,
+                telemetry: 5.0,
+                total: 65.0,
+            }
+        );
+
+        let defaults = pricing.estimate(&plan(1, Value::Null, &[])).unwrap();
+        assert_eq!(defaults.cpu_cores, 0.25);
+        assert_eq!(defaults.memory_gib, 0.25);
+
+        let bad = json!({"resources": {"requests": {"cpu": "lots"}}});
+        assert!(pricing.estimate(&plan(1, bad, &[])).is_err());
+    }
+
+    #[test]
+    fn pricing_overrides_and_declared_telemetry_volume() {
+        let pricing = PricingTable::from_yaml(
+            "currency: EUR\ncpu_core_month: 10\ndefault_requests: {cpu: '1', memory: 512Mi}\n",
+        )
+        .unwrap();
+        assert_eq!(pricing.memory_gib_month, 4.0, "unset keys keep defaults");
+        assert!(PricingTable::from_yaml("cpu_per_core: 1").is_err());
+        assert!(PricingTable::from_yaml("default_requests: {cpu: x, memory: 1Gi}").is_err());
+
+        let mut loud = plan(2, Value::Null, &[true]);
+        loud.telemetry.as_mut().unwrap().extra = json!({"volume": "high"});
+        let cost = pricing.annotate(&mut loud).unwrap();
+        assert_eq!(cost.currency, "EUR");
+        assert_eq!(cost.telemetry_volume, TelemetryVolume::High);
+        assert_eq!(loud.extra["cost"]["monthly"]["total"], json!(85.5));
+        assert_eq!(loud.extra["pack_kind"], "application");
+
+        let base = pricing.estimate(&plan(2, Value::Null, &[true])).unwrap();
+        let delta = PlanCost::delta(&base, &cost);
+        assert_eq!(delta.replicas, 0);
+        assert_eq!(
+            delta.telemetry_volume,
+            Some((TelemetryVolume::Low, TelemetryVolume::High))
+        );
+        assert_eq!(delta.monthly_total, 55.0);
+    }
+}
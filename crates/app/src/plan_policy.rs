@@ -0,0 +1,196 @@
+//! Org-wide invariants for inferred deployment plans (`plans/policy.yaml`), checked at plan
+//! time by `packs plan --enforce-policy` or through [`PlanPolicy::enforce`].
+//!
+//! ```yaml
+//! max_replicas_per_runner: 4
+//! require_telemetry_for_kinds: [deployment]
+//! forbidden_channel_kinds:
+//!   prod: [dev-console]
+//!   "*": [legacy-sms]
+//! ```
+
+use std::{collections::BTreeMap, fmt, path::Path};
+
+use anyhow::{Context, Result};
+use greentic_types::deployment::DeploymentPlan;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Key of `forbidden_channel_kinds` that applies to every environment.
+pub const ANY_ENVIRONMENT: &str = "*";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlanPolicy {
+    /// Upper bound on `runners[].replicas`.
+    #[serde(default)]
+    pub max_replicas_per_runner: Option<u32>,
+    /// Pack kinds (`extra.pack_kind`) whose plans must carry `telemetry.required = true`.
+    #[serde(default)]
+    pub require_telemetry_for_kinds: Vec<String>,
+    /// Channel kinds a plan may not declare, keyed by environment (`*` for all).
+    #[serde(default)]
+    pub forbidden_channel_kinds: BTreeMap<String, Vec<String>>,
+}
+
+/// One broken rule, naming the rule and the part of the plan that breaks it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PolicyViolation {
+    pub rule: &'static str,
+    pub subject: String,
+    pub message: String,
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}): {}", self.rule, self.subject, self.message)
+    }
+}
+
+/// Every violation of a plan, returned by [`PlanPolicy::enforce`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("plan for {pack_id} violates {} policy rule(s): {}", violations.len(), list(violations))]
+pub struct PolicyViolations {
+    pub pack_id: String,
+    pub violations: Vec<PolicyViolation>,
+}
+
+fn list(violations: &[PolicyViolation]) -> String {
+    violations
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+impl PlanPolicy {
+    pub fn from_yaml(raw: &str) -> Result<Self> {
+        serde_yaml_bw::from_str(raw).context("invalid plan policy")
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read plan policy {}", path.display()))?;
+        Self::from_yaml(&raw).with_context(|| format!("in {}", path.display()))
+    }
+
+    pub fn check(&self, plan: &DeploymentPlan) -> Vec<PolicyViolation> {
+        let mut violations = Vec::new();
+        if let Some(max) = self.max_replicas_per_runner {
+            for runner in plan.runners.iter().filter(|runner| runner.replicas > max) {
+                violations.push(PolicyViolation {
+                    rule: "max_replicas_per_runner",
+                    subject: format!("runner {}", runner.name),
+                    message: format!("{} replicas exceed the limit of {max}", runner.replicas),
+                });
+            }
+        }
+        if let Some(kind) = plan.extra.get("pack_kind").and_then(Value::as_str)
+            && self.require_telemetry_for_kinds.iter().any(|k| k == kind)
+            && !plan.telemetry.as_ref().is_some_and(|t| t.required)
+        {
+            violations.push(PolicyViolation {
+                rule: "require_telemetry_for_kinds",
+                subject: format!("pack {}", plan.pack_id),
+                message: format!("{kind} packs must declare required telemetry"),
+            });
+        }
+        let forbidden: Vec<&String> = [ANY_ENVIRONMENT, plan.environment.as_str()]
+            .iter()
+            .filter_map(|env| self.forbidden_channel_kinds.get(*env))
+            .flatten()
+            .collect();
+        for channel in &plan.channels {
+            if forbidden.contains(&&channel.kind) {
+                violations.push(PolicyViolation {
+                    rule: "forbidden_channel_kinds",
+                    subject: format!("channel {}", channel.name),
+                    message: format!(
+                        "channel kind {} is not allowed in environment {}",
+                        channel.kind, plan.environment
+                    ),
+                });
+            }
+        }
+        violations
+    }
+
+    pub fn enforce(&self, plan: &DeploymentPlan) -> Result<(), PolicyViolations> {
+        let violations = self.check(plan);
+        if violations.is_empty() {
+            return Ok(());
+        }
+        Err(PolicyViolations {
+            pack_id: plan.pack_id.clone(),
+            violations,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use greentic_types::deployment::{ChannelPlan, RunnerPlan};
+    use serde_json::json;
+
+    fn plan(environment: &str) -> DeploymentPlan {
+        DeploymentPlan {
+            pack_id: "deploy".into(),
+            pack_version: semver::Version::new(1, 0, 0),
+            tenant: "dev".into(),
+            environment: environment.into(),
+            runners: vec![RunnerPlan {
+                name: "deploy-runner".into(),
+                replicas: 6,
+                capabilities: Value::Null,
+            }],
+            messaging: None,
+            channels: vec![ChannelPlan {
+                name: "console".into(),
+                flow_id: "main".into(),
+                kind: "dev-console".into(),
+                config: Value::Null,
+            }],
+            secrets: Vec::new(),
+            oauth: Vec::new(),
+            telemetry: None,
+            extra: json!({"pack_kind": "deployment"}),
+        }
+    }
+
+    #[test]
+    fn reports_each_broken_rule() {
+        let policy = PlanPolicy::from_yaml(
+            "max_replicas_per_runner: 4\nrequire_telemetry_for_kinds: [deployment]\nforbidden_channel_kinds:\n  prod: [dev-console]\n",
+        )
+        .unwrap();
+        assert!(PlanPolicy::from_yaml("max_replica: 4").is_err());
+
+        let rules: Vec<_> = policy
+            .check(&plan("prod"))
+            .into_iter()
+            .map(|violation| violation.rule)
+            .collect();
+        assert_eq!(
+            rules,
+            vec![
+                "max_replicas_per_runner",
+                "require_telemetry_for_kinds",
+                "forbidden_channel_kinds"
+            ]
+        );
+        let err = policy.enforce(&plan("dev")).unwrap_err();
+        assert_eq!(err.violations.len(), 2);
+        assert!(err.to_string().contains("runner deploy-runner"), "{err}");
+
+        let mut compliant = plan("dev");
+        compliant.runners[0].replicas = 2;
+        compliant.telemetry = Some(greentic_types::deployment::TelemetryPlan {
+            required: true,
+            suggested_endpoint: None,
+            extra: Value::Null,
+        });
+        assert!(policy.enforce(&compliant).is_ok());
+        assert!(PlanPolicy::default().check(&plan("prod")).is_empty());
+    }
+}
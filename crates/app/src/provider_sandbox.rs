@@ -0,0 +1,518 @@
+//! Sandbox credentials for real messaging providers (a Slack test workspace, a Teams test
+//! tenant), kept in the secret store under `<secrets_dir>/providers/<name>/<FIELD>`, and the
+//! minimal authenticated round trip behind `providers verify`: post a message to the test
+//! channel and delete it again, so bad credentials fail fast instead of at an e2e send step.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Serialize;
+use serde_json::{Value, json};
+
+use crate::interpolate::{DirSecretStore, SecretStore};
+use crate::network::OutboundHttp;
+
+/// Directory of `[packs].secrets_dir` holding one sub-directory per provider.
+pub const PROVIDERS_DIR: &str = "providers";
+
+const SLACK_API_BASE: &str = "https://slack.com/api";
+const GRAPH_BASE: &str = "https://graph.microsoft.com/v1.0";
+const LOGIN_BASE: &str = "https://login.microsoftonline.com";
+const GRAPH_SCOPE: &str = "https://graph.microsoft.com/.default";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SandboxProvider {
+    Slack,
+    Teams,
+}
+
+impl SandboxProvider {
+    pub const ALL: [SandboxProvider; 2] = [SandboxProvider::Slack, SandboxProvider::Teams];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SandboxProvider::Slack => "slack",
+            SandboxProvider::Teams => "teams",
+        }
+    }
+
+    /// Fields `verify` needs.
+    pub fn required_fields(self) -> &'static [&'static str] {
+        match self {
+            SandboxProvider::Slack => &["BOT_TOKEN", "CHANNEL"],
+            SandboxProvider::Teams => &[
+                "TENANT_ID",
+                "CLIENT_ID",
+                "CLIENT_SECRET",
+                "TEAM_ID",
+                "CHANNEL_ID",
+            ],
+        }
+    }
+
+    /// Endpoint overrides, for proxies or local stubs.
+    pub fn optional_fields(self) -> &'static [&'static str] {
+        match self {
+            SandboxProvider::Slack => &["API_BASE"],
+            SandboxProvider::Teams => &["GRAPH_BASE", "LOGIN_BASE"],
+        }
+    }
+
+    fn knows(self, field: &str) -> bool {
+        self.required_fields().contains(&field) || self.optional_fields().contains(&field)
+    }
+}
+
+impl fmt::Display for SandboxProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl std::str::FromStr for SandboxProvider {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|provider| provider.name().eq_ignore_ascii_case(raw.trim()))
+            .ok_or_else(|| anyhow!("unknown provider {raw:?}; expected slack or teams"))
+    }
+}
+
+/// Per-provider credentials in `<secrets_dir>/providers/`.
+#[derive(Debug, Clone)]
+pub struct CredentialVault {
+    root: PathBuf,
+}
+
+impl CredentialVault {
+    pub fn new(secrets_dir: &Path) -> Self {
+        Self {
+            root: secrets_dir.join(PROVIDERS_DIR),
+        }
+    }
+
+    fn store(&self, provider: SandboxProvider) -> DirSecretStore {
+        DirSecretStore::new(self.root.join(provider.name()))
+    }
+
+    /// Write `values` (field names are case-insensitive); unknown fields are refused before
+    /// anything is written. Returns the stored field names.
+    pub fn register(
+        &self,
+        provider: SandboxProvider,
+        values: &BTreeMap<String, String>,
+    ) -> Result<Vec<String>> {
+        let values: BTreeMap<String, &str> = values
+            .iter()
+            .map(|(field, value)| (field.trim().to_ascii_uppercase(), value.as_str()))
+            .collect();
+        if let Some(field) = values.keys().find(|field| !provider.knows(field)) {
+            let mut known = provider.required_fields().to_vec();
+            known.extend(provider.optional_fields());
+            bail!(
+                "{provider} has no credential field {field}; expected one of {}",
+                known.join(", ")
+            );
+        }
+        let store = self.store(provider);
+        for (field, value) in &values {
+            store
+                .put(field, value)
+                .with_context(|| format!("{provider}: credential {field}"))?;
+        }
+        Ok(values.into_keys().collect())
+    }
+
+    /// Credentials for `provider`, failing with the missing field names when incomplete.
+    pub fn load(&self, provider: SandboxProvider) -> Result<SandboxCredentials> {
+        let store = self.store(provider);
+        let mut values = BTreeMap::new();
+        let mut missing = Vec::new();
+        for field in provider.required_fields() {
+            match store.get(field)?.filter(|value| !value.is_empty()) {
+                Some(value) => {
+                    values.insert(*field, value);
+                }
+                None => missing.push(*field),
+            }
+        }
+        if !missing.is_empty() {
+            bail!(
+                "{provider} sandbox credentials are incomplete; missing {} (register them with `providers register --name {provider}`)",
+                missing.join(", ")
+            );
+        }
+        for field in provider.optional_fields() {
+            if let Some(value) = store.get(field)?.filter(|value| !value.is_empty()) {
+                values.insert(*field, value);
+            }
+        }
+        Ok(SandboxCredentials { provider, values })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SandboxCredentials {
+    pub provider: SandboxProvider,
+    values: BTreeMap<&'static str, String>,
+}
+
+impl SandboxCredentials {
+    pub(crate) fn get(&self, field: &str) -> &str {
+        self.values.get(field).map_or("", String::as_str)
+    }
+
+    fn base(&self, field: &str, default: &str) -> String {
+        self.values
+            .get(field)
+            .map_or(default, String::as_str)
+            .trim_end_matches('/')
+            .to_string()
+    }
+
+    /// Slack Web API base, e.g. `https://slack.com/api`.
+    pub(crate) fn slack_api(&self) -> String {
+        self.base("API_BASE", SLACK_API_BASE)
+    }
+
+    /// Graph collection of the sandbox Teams channel's messages.
+    pub(crate) fn teams_messages_url(&self) -> String {
+        format!(
+            "{}/teams/{}/channels/{}/messages",
+            self.base("GRAPH_BASE", GRAPH_BASE),
+            self.get("TEAM_ID"),
+            self.get("CHANNEL_ID")
+        )
+    }
+
+    /// Client-credentials token request for Microsoft Graph.
+    pub(crate) fn request_graph_token(&self, http: &OutboundHttp) -> Result<(u16, String)> {
+        http.post_form(
+            "teams sandbox",
+            &format!(
+                "{}/{}/oauth2/v2.0/token",
+                self.base("LOGIN_BASE", LOGIN_BASE),
+                self.get("TENANT_ID")
+            ),
+            &[
+                ("grant_type", "client_credentials"),
+                ("client_id", self.get("CLIENT_ID")),
+                ("client_secret", self.get("CLIENT_SECRET")),
+                ("scope", GRAPH_SCOPE),
+            ],
+        )
+    }
+}
+
+/// One authenticated call made by `verify`.
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyStep {
+    pub step: &'static str,
+    pub ok: bool,
+    pub latency_ms: u64,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyReport {
+    pub provider: SandboxProvider,
+    pub ok: bool,
+    pub steps: Vec<VerifyStep>,
+}
+
+/// Post a message to the sandbox channel and delete it. Stops at the first failing step.
+pub fn verify(credentials: &SandboxCredentials, http: &OutboundHttp, text: &str) -> VerifyReport {
+    let mut steps = Vec::new();
+    let outcome = match credentials.provider {
+        SandboxProvider::Slack => verify_slack(credentials, http, text, &mut steps),
+        SandboxProvider::Teams => verify_teams(credentials, http, text, &mut steps),
+    };
+    if let Err(err) = outcome {
+        // A transport error leaves no step behind; record it against the step it broke.
+        if steps.last().is_none_or(|step| step.ok) {
+            steps.push(VerifyStep {
+                step: "request",
+                ok: false,
+                latency_ms: 0,
+                detail: format!("{err:#}"),
+            });
+        }
+    }
+    VerifyReport {
+        provider: credentials.provider,
+        ok: steps.iter().all(|step| step.ok),
+        steps,
+    }
+}
+
+/// Run one call, record it, and return its JSON body when it succeeded.
+fn step(
+    steps: &mut Vec<VerifyStep>,
+    name: &'static str,
+    call: impl FnOnce() -> Result<(u16, String)>,
+    accept: impl FnOnce(u16, &Value) -> Result<String, String>,
+) -> Result<Value> {
+    let started = Instant::now();
+    let (status, body) = call()?;
+    let latency_ms = u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX);
+    let parsed: Value = serde_json::from_str(&body).unwrap_or(Value::Null);
+    let verdict = accept(status, &parsed);
+    let ok = verdict.is_ok();
+    let detail = verdict.unwrap_or_else(|reason| reason);
+    steps.push(VerifyStep {
+        step: name,
+        ok,
+        latency_ms,
+        detail: detail.clone(),
+    });
+    if ok {
+        Ok(parsed)
+    } else {
+        Err(anyhow!("{name} failed: {detail}"))
+    }
+}
+
+/// Slack answers `200` with `{"ok": false, "error": ...}` for most failures.
+pub(crate) fn slack_ok(status: u16, body: &Value) -> Result<String, String> {
+    if body["ok"].as_bool() == Some(true) {
+        return Ok(format!("HTTP {status}"));
+    }
+    Err(format!(
+        "HTTP {status}: {}",
+        body["error"].as_str().unwrap_or("unexpected response")
+    ))
+}
+
+fn verify_slack(
+    credentials: &SandboxCredentials,
+    http: &OutboundHttp,
+    text: &str,
+    steps: &mut Vec<VerifyStep>,
+) -> Result<()> {
+    let base = credentials.slack_api();
+    let token = credentials.get("BOT_TOKEN");
+    let channel = credentials.get("CHANNEL");
+    let posted = step(
+        steps,
+        "post_message",
+        || {
+            http.post_json_as(
+                "slack sandbox",
+                &format!("{base}/chat.postMessage"),
+                Some(token),
+                json!({ "channel": channel, "text": text }),
+            )
+        },
+        slack_ok,
+    )?;
+    // Deleting needs the channel id Slack resolved, which may differ from a channel name.
+    let ts = posted["ts"].as_str().unwrap_or_default();
+    let channel = posted["channel"].as_str().unwrap_or(channel);
+    step(
+        steps,
+        "delete_message",
+        || {
+            http.post_json_as(
+                "slack sandbox",
+                &format!("{base}/chat.delete"),
+                Some(token),
+                json!({ "channel": channel, "ts": ts }),
+            )
+        },
+        slack_ok,
+    )?;
+    Ok(())
+}
+
+pub(crate) fn http_ok(status: u16, body: &Value) -> Result<String, String> {
+    if (200..300).contains(&status) {
+        return Ok(format!("HTTP {status}"));
+    }
+    let reason = body["error_description"]
+        .as_str()
+        .or_else(|| body["error"]["message"].as_str())
+        .unwrap_or("unexpected response");
+    Err(format!("HTTP {status}: {reason}"))
+}
+
+fn verify_teams(
+    credentials: &SandboxCredentials,
+    http: &OutboundHttp,
+    text: &str,
+    steps: &mut Vec<VerifyStep>,
+) -> Result<()> {
+    let token = step(
+        steps,
+        "acquire_token",
+        || credentials.request_graph_token(http),
+        http_ok,
+    )?;
+    let token = token["access_token"]
+        .as_str()
+        .context("token response carried no access_token")?;
+    let messages = credentials.teams_messages_url();
+    let posted = step(
+        steps,
+        "post_message",
+        || {
+            http.post_json_as(
+                "teams sandbox",
+                &messages,
+                Some(token),
+                json!({ "body": { "content": text } }),
+            )
+        },
+        http_ok,
+    )?;
+    let id = posted["id"]
+        .as_str()
+        .context("post response carried no message id")?;
+    step(
+        steps,
+        "delete_message",
+        || {
+            http.post_json_as(
+                "teams sandbox",
+                &format!("{messages}/{id}/softDelete"),
+                Some(token),
+                json!({}),
+            )
+        },
+        http_ok,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Json, Router, extract::State, routing::post};
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+
+    #[test]
+    fn register_refuses_unknown_fields_and_load_names_missing_ones() {
+        let tmp = tempfile::tempdir().unwrap();
+        let vault = CredentialVault::new(tmp.path());
+        let values = |pairs: &[(&str, &str)]| -> BTreeMap<String, String> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+
+        let err = vault
+            .register(SandboxProvider::Slack, &values(&[("password", "x")]))
+            .unwrap_err();
+        assert!(err.to_string().contains("no credential field PASSWORD"));
+        assert!(!tmp.path().join(PROVIDERS_DIR).exists());
+
+        let stored = vault
+            .register(SandboxProvider::Slack, &values(&[("bot_token", "xoxb-1")]))
+            .unwrap();
+        assert_eq!(stored, vec!["BOT_TOKEN"]);
+        assert!(tmp.path().join("providers/slack/BOT_TOKEN").is_file());
+        let err = vault.load(SandboxProvider::Slack).unwrap_err();
+        assert!(err.to_string().contains("missing CHANNEL"), "{err}");
+
+        vault
+            .register(SandboxProvider::Slack, &values(&[("CHANNEL", "C1")]))
+            .unwrap();
+        let credentials = vault.load(SandboxProvider::Slack).unwrap();
+        assert_eq!(credentials.get("BOT_TOKEN"), "xoxb-1");
+        assert_eq!(credentials.slack_api(), SLACK_API_BASE);
+        assert_eq!(
+            "Teams".parse::<SandboxProvider>().unwrap(),
+            SandboxProvider::Teams
+        );
+        assert!("discord".parse::<SandboxProvider>().is_err());
+    }
+
+    #[derive(Clone, Default)]
+    struct SlackStub {
+        calls: Arc<Mutex<Vec<(String, Value)>>>,
+    }
+
+    async fn slack_call(
+        State(stub): State<SlackStub>,
+        uri: axum::http::Uri,
+        headers: axum::http::HeaderMap,
+        Json(body): Json<Value>,
+    ) -> Json<Value> {
+        let authorized = headers
+            .get("authorization")
+            .is_some_and(|value| value == "Bearer xoxb-good");
+        stub.calls.lock().push((uri.path().to_string(), body));
+        if !authorized {
+            return Json(json!({"ok": false, "error": "invalid_auth"}));
+        }
+        Json(json!({"ok": true, "channel": "C123", "ts": "1700000000.000100"}))
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn slack_verify_posts_then_deletes_and_reports_bad_tokens() {
+        let stub = SlackStub::default();
+        let app = Router::new()
+            .route("/api/chat.postMessage", post(slack_call))
+            .route("/api/chat.delete", post(slack_call))
+            .with_state(stub.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}/api", listener.local_addr().unwrap());
+        tokio::spawn(axum::serve(listener, app).into_future());
+
+        let tmp = tempfile::tempdir().unwrap();
+        let vault = CredentialVault::new(tmp.path());
+        let register = |token: &str| {
+            vault
+                .register(
+                    SandboxProvider::Slack,
+                    &BTreeMap::from([
+                        ("BOT_TOKEN".to_string(), token.to_string()),
+                        ("CHANNEL".to_string(), "#e2e".to_string()),
+                        ("API_BASE".to_string(), base.clone()),
+                    ]),
+                )
+                .unwrap();
+            vault.load(SandboxProvider::Slack).unwrap()
+        };
+
+        let credentials = register("xoxb-good");
+        let report = tokio::task::spawn_blocking(move || {
+            verify(&credentials, &OutboundHttp::default(), "verify")
+        })
+        .await
+        .unwrap();
+        assert!(report.ok, "{report:?}");
+        let steps: Vec<_> = report.steps.iter().map(|step| step.step).collect();
+        assert_eq!(steps, vec!["post_message", "delete_message"]);
+        {
+            let calls = stub.calls.lock();
+            assert_eq!(calls[0].1, json!({"channel": "#e2e", "text": "verify"}));
+            assert_eq!(
+                calls[1],
+                (
+                    "/api/chat.delete".to_string(),
+                    json!({"channel": "C123", "ts": "1700000000.000100"})
+                )
+            );
+        }
+
+        let credentials = register("xoxb-revoked");
+        let report = tokio::task::spawn_blocking(move || {
+            verify(&credentials, &OutboundHttp::default(), "verify")
+        })
+        .await
+        .unwrap();
+        assert!(!report.ok);
+        assert_eq!(report.steps.len(), 1);
+        assert_eq!(report.steps[0].detail, "HTTP 200: invalid_auth");
+    }
+}
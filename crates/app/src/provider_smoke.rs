@@ -0,0 +1,589 @@
+//! `providers smoke`: send the canonical text, card and threaded reply through every provider
+//! with sandbox credentials, keep each provider API response as an artifact, and compare what
+//! worked with the capabilities `providers.yaml` claims for the reference provider.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+    time::Instant,
+};
+
+use anyhow::{Context, Result, anyhow};
+use providers_sim::capabilities::CapabilityDoc;
+use serde::Serialize;
+use serde_json::{Value, json};
+use tracing::warn;
+
+use crate::network::OutboundHttp;
+use crate::provider_sandbox::{SandboxCredentials, SandboxProvider, http_ok, slack_ok};
+
+/// The cases every provider runs, in order; the reply threads under the text message.
+pub const SMOKE_CASES: [SmokeCase; 3] =
+    [SmokeCase::Text, SmokeCase::Card, SmokeCase::ThreadedReply];
+
+const CARD_TITLE: &str = "Greentic smoke card";
+const CARD_BODY: &str = "Canonical card sent by providers smoke.";
+const CARD_ACTION: &str = "Acknowledge";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmokeCase {
+    Text,
+    Card,
+    ThreadedReply,
+}
+
+impl SmokeCase {
+    pub fn name(self) -> &'static str {
+        match self {
+            SmokeCase::Text => "text",
+            SmokeCase::Card => "card",
+            SmokeCase::ThreadedReply => "threaded_reply",
+        }
+    }
+
+    /// `providers.yaml` capabilities a successful run of this case demonstrates.
+    pub fn capabilities(self, provider: SandboxProvider) -> &'static [&'static str] {
+        match (self, provider) {
+            (SmokeCase::Text, _) => &["send_message"],
+            // Slack renders the card as Block Kit: buttons, but no Adaptive Card.
+            (SmokeCase::Card, SandboxProvider::Slack) => &["buttons"],
+            (SmokeCase::Card, SandboxProvider::Teams) => &["adaptive_cards", "buttons"],
+            (SmokeCase::ThreadedReply, _) => &["threads"],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SmokeCaseResult {
+    pub case: SmokeCase,
+    pub capabilities: Vec<&'static str>,
+    pub ok: bool,
+    pub status: Option<u16>,
+    pub latency_ms: u64,
+    pub detail: String,
+    /// Artifact file holding the request and the provider's response.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artifact: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderSmoke {
+    pub provider: SandboxProvider,
+    pub ok: bool,
+    pub cases: Vec<SmokeCaseResult>,
+}
+
+/// One capability as claimed by the reference provider and as observed live.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ParityRow {
+    pub capability: String,
+    pub claimed: bool,
+    pub verified_by: Vec<String>,
+    pub failed_by: Vec<String>,
+}
+
+impl ParityRow {
+    /// Claimed, exercised, and failing on every provider that tried it.
+    pub fn contradicted(&self) -> bool {
+        self.claimed && self.verified_by.is_empty() && !self.failed_by.is_empty()
+    }
+}
+
+/// A posted message, removed again once the run is over.
+enum Posted {
+    Slack {
+        channel: String,
+        ts: String,
+    },
+    /// Graph URL of the message or reply.
+    Teams {
+        url: String,
+    },
+}
+
+/// Run every case against `credentials`, writing artifacts to `<artifacts>/<provider>/`.
+/// Posted messages are deleted afterwards unless `keep` is set.
+pub fn run(
+    credentials: &SandboxCredentials,
+    http: &OutboundHttp,
+    artifacts: &Path,
+    keep: bool,
+) -> ProviderSmoke {
+    let provider = credentials.provider;
+    let dir = artifacts.join(provider.name());
+    let mut posted = Vec::new();
+    let cases = match provider {
+        SandboxProvider::Slack => smoke_slack(credentials, http, &dir, &mut posted),
+        SandboxProvider::Teams => smoke_teams(credentials, http, &dir, &mut posted),
+    };
+    if !keep {
+        clean_up(credentials, http, posted);
+    }
+    ProviderSmoke {
+        provider,
+        ok: cases.iter().all(|case| case.ok),
+        cases,
+    }
+}
+
+/// Capabilities claimed by the reference provider or exercised by the runs, with the
+/// providers that demonstrated or failed each one.
+pub fn parity(doc: &CapabilityDoc, runs: &[ProviderSmoke]) -> Vec<ParityRow> {
+    let claimed = doc.reference_capabilities().unwrap_or_default();
+    let mut rows: BTreeMap<String, ParityRow> = claimed
+        .iter()
+        .map(|capability| {
+            (
+                capability.clone(),
+                ParityRow {
+                    capability: capability.clone(),
+                    claimed: true,
+                    verified_by: Vec::new(),
+                    failed_by: Vec::new(),
+                },
+            )
+        })
+        .collect();
+    for run in runs {
+        let mut verified = BTreeSet::new();
+        let mut failed = BTreeSet::new();
+        for case in &run.cases {
+            let target = if case.ok { &mut verified } else { &mut failed };
+            target.extend(case.capabilities.iter().copied());
+        }
+        // A capability shown by one case is not undone by another case that also uses it.
+        for capability in &verified {
+            failed.remove(capability);
+        }
+        for (capability, ok) in verified
+            .iter()
+            .map(|c| (c, true))
+            .chain(failed.iter().map(|c| (c, false)))
+        {
+            let row = rows
+                .entry(capability.to_string())
+                .or_insert_with(|| ParityRow {
+                    capability: capability.to_string(),
+                    claimed: false,
+                    verified_by: Vec::new(),
+                    failed_by: Vec::new(),
+                });
+            let list = if ok {
+                &mut row.verified_by
+            } else {
+                &mut row.failed_by
+            };
+            list.push(run.provider.to_string());
+        }
+    }
+    rows.into_values().collect()
+}
+
+/// Send one request, keep request and response as `<dir>/<case>.json`, and judge it.
+fn send(
+    dir: &Path,
+    case: SmokeCase,
+    provider: SandboxProvider,
+    url: &str,
+    request: &Value,
+    call: impl FnOnce() -> Result<(u16, String)>,
+    accept: fn(u16, &Value) -> Result<String, String>,
+) -> (SmokeCaseResult, Value) {
+    let started = Instant::now();
+    let outcome = call();
+    let latency_ms = u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX);
+    let (status, response, verdict) = match outcome {
+        Ok((status, body)) => {
+            let response = serde_json::from_str(&body).unwrap_or(Value::String(body));
+            let verdict = accept(status, &response);
+            (Some(status), response, verdict)
+        }
+        Err(err) => (None, Value::Null, Err(format!("{err:#}"))),
+    };
+    let artifact = write_artifact(
+        dir,
+        case,
+        &json!({
+            "provider": provider,
+            "case": case,
+            "url": url,
+            "request": request,
+            "status": status,
+            "response": response,
+        }),
+    );
+    let ok = verdict.is_ok();
+    let result = SmokeCaseResult {
+        case,
+        capabilities: case.capabilities(provider).to_vec(),
+        ok,
+        status,
+        latency_ms,
+        detail: verdict.unwrap_or_else(|reason| reason),
+        artifact,
+    };
+    (result, if ok { response } else { Value::Null })
+}
+
+fn write_artifact(dir: &Path, case: SmokeCase, body: &Value) -> Option<String> {
+    let path = dir.join(format!("{}.json", case.name()));
+    let written = fs::create_dir_all(dir)
+        .and_then(|()| fs::write(&path, serde_json::to_vec_pretty(body).unwrap_or_default()));
+    match written {
+        Ok(()) => Some(path.display().to_string()),
+        Err(err) => {
+            warn!(?err, path = %path.display(), "failed to write smoke artifact");
+            None
+        }
+    }
+}
+
+/// A case that could not be attempted because an earlier one failed.
+fn not_attempted(provider: SandboxProvider, case: SmokeCase, reason: &str) -> SmokeCaseResult {
+    SmokeCaseResult {
+        case,
+        capabilities: case.capabilities(provider).to_vec(),
+        ok: false,
+        status: None,
+        latency_ms: 0,
+        detail: reason.to_string(),
+        artifact: None,
+    }
+}
+
+fn smoke_slack(
+    credentials: &SandboxCredentials,
+    http: &OutboundHttp,
+    dir: &Path,
+    posted: &mut Vec<Posted>,
+) -> Vec<SmokeCaseResult> {
+    let provider = SandboxProvider::Slack;
+    let url = format!("{}/chat.postMessage", credentials.slack_api());
+    let token = credentials.get("BOT_TOKEN");
+    let channel = credentials.get("CHANNEL");
+    let post = |case: SmokeCase, request: Value| {
+        send(
+            dir,
+            case,
+            provider,
+            &url,
+            &request,
+            || http.post_json_as("slack sandbox", &url, Some(token), request.clone()),
+            slack_ok,
+        )
+    };
+    let mut track = |response: &Value| {
+        if let (Some(channel), Some(ts)) = (response["channel"].as_str(), response["ts"].as_str()) {
+            posted.push(Posted::Slack {
+                channel: channel.to_string(),
+                ts: ts.to_string(),
+            });
+        }
+    };
+
+    let mut results = Vec::new();
+    let (text, response) = post(
+        SmokeCase::Text,
+        json!({ "channel": channel, "text": smoke_text("text") }),
+    );
+    track(&response);
+    let parent = response["ts"].as_str().map(str::to_string);
+    results.push(text);
+
+    let (card, response) = post(
+        SmokeCase::Card,
+        json!({
+            "channel": channel,
+            "text": CARD_TITLE,
+            "blocks": [
+                { "type": "header", "text": { "type": "plain_text", "text": CARD_TITLE } },
+                { "type": "section", "text": { "type": "mrkdwn", "text": CARD_BODY } },
+                {
+                    "type": "actions",
+                    "elements": [{
+                        "type": "button",
+                        "action_id": "smoke_ack",
+                        "text": { "type": "plain_text", "text": CARD_ACTION },
+                        "value": "ack",
+                    }],
+                },
+            ],
+        }),
+    );
+    track(&response);
+    results.push(card);
+
+    match parent {
+        Some(thread_ts) => {
+            let (reply, response) = post(
+                SmokeCase::ThreadedReply,
+                json!({
+                    "channel": channel,
+                    "text": smoke_text("threaded reply"),
+                    "thread_ts": thread_ts,
+                }),
+            );
+            track(&response);
+            results.push(reply);
+        }
+        None => results.push(not_attempted(
+            provider,
+            SmokeCase::ThreadedReply,
+            "no parent message: the text case failed",
+        )),
+    }
+    results
+}
+
+fn smoke_teams(
+    credentials: &SandboxCredentials,
+    http: &OutboundHttp,
+    dir: &Path,
+    posted: &mut Vec<Posted>,
+) -> Vec<SmokeCaseResult> {
+    let provider = SandboxProvider::Teams;
+    let token = match graph_token(credentials, http) {
+        Ok(token) => token,
+        Err(err) => {
+            let reason = format!("{err:#}");
+            return SMOKE_CASES
+                .iter()
+                .map(|case| not_attempted(provider, *case, &reason))
+                .collect();
+        }
+    };
+    let messages = credentials.teams_messages_url();
+    let post = |case: SmokeCase, url: &str, request: Value| {
+        send(
+            dir,
+            case,
+            provider,
+            url,
+            &request,
+            || http.post_json_as("teams sandbox", url, Some(&token), request.clone()),
+            http_ok,
+        )
+    };
+
+    let mut results = Vec::new();
+    let (text, response) = post(
+        SmokeCase::Text,
+        &messages,
+        json!({ "body": { "content": smoke_text("text") } }),
+    );
+    let parent = response["id"].as_str().map(|id| format!("{messages}/{id}"));
+    results.push(text);
+
+    let card = json!({
+        "type": "AdaptiveCard",
+        "version": "1.4",
+        "body": [
+            { "type": "TextBlock", "text": CARD_TITLE, "weight": "Bolder", "size": "Medium" },
+            { "type": "TextBlock", "text": CARD_BODY, "wrap": true },
+        ],
+        "actions": [{ "type": "Action.Submit", "title": CARD_ACTION, "data": { "ack": true } }],
+    });
+    let (card, response) = post(
+        SmokeCase::Card,
+        &messages,
+        json!({
+            "body": { "contentType": "html", "content": "<attachment id=\"smoke-card\"></attachment>" },
+            "attachments": [{
+                "id": "smoke-card",
+                "contentType": "application/vnd.microsoft.card.adaptive",
+                "content": card.to_string(),
+            }],
+        }),
+    );
+    if let Some(id) = response["id"].as_str() {
+        posted.push(Posted::Teams {
+            url: format!("{messages}/{id}"),
+        });
+    }
+    results.push(card);
+
+    match &parent {
+        Some(parent) => {
+            let url = format!("{parent}/replies");
+            let (reply, response) = post(
+                SmokeCase::ThreadedReply,
+                &url,
+                json!({ "body": { "content": smoke_text("threaded reply") } }),
+            );
+            if let Some(id) = response["id"].as_str() {
+                posted.push(Posted::Teams {
+                    url: format!("{url}/{id}"),
+                });
+            }
+            results.push(reply);
+        }
+        None => results.push(not_attempted(
+            provider,
+            SmokeCase::ThreadedReply,
+            "no parent message: the text case failed",
+        )),
+    }
+    // The parent goes last so its reply is removed first.
+    if let Some(parent) = parent {
+        posted.push(Posted::Teams { url: parent });
+    }
+    results
+}
+
+fn graph_token(credentials: &SandboxCredentials, http: &OutboundHttp) -> Result<String> {
+    let (status, body) = credentials.request_graph_token(http)?;
+    let body: Value = serde_json::from_str(&body).unwrap_or(Value::Null);
+    http_ok(status, &body).map_err(|reason| anyhow!("token request failed: {reason}"))?;
+    body["access_token"]
+        .as_str()
+        .map(str::to_string)
+        .context("token response carried no access_token")
+}
+
+fn smoke_text(case: &str) -> String {
+    format!("greentic-integration providers smoke: {case}")
+}
+
+/// Delete what the run posted, replies before their parent; failures are logged, not
+/// reported.
+fn clean_up(credentials: &SandboxCredentials, http: &OutboundHttp, mut posted: Vec<Posted>) {
+    if posted.is_empty() {
+        return;
+    }
+    let token = match credentials.provider {
+        SandboxProvider::Slack => Ok(credentials.get("BOT_TOKEN").to_string()),
+        SandboxProvider::Teams => graph_token(credentials, http),
+    };
+    if credentials.provider == SandboxProvider::Slack {
+        // Slack messages were tracked in posting order, so the thread parent comes first.
+        posted.reverse();
+    }
+    let outcome = token.and_then(|token| {
+        posted.into_iter().try_for_each(|message| {
+            let (purpose, url, body) = match message {
+                Posted::Slack { channel, ts } => (
+                    "slack sandbox",
+                    format!("{}/chat.delete", credentials.slack_api()),
+                    json!({ "channel": channel, "ts": ts }),
+                ),
+                Posted::Teams { url } => ("teams sandbox", format!("{url}/softDelete"), json!({})),
+            };
+            http.post_json_as(purpose, &url, Some(&token), body)
+                .map(drop)
+        })
+    });
+    if let Err(err) = outcome {
+        warn!(?err, provider = %credentials.provider, "failed to delete smoke messages");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider_sandbox::CredentialVault;
+    use axum::{Json, Router, extract::State, routing::post};
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+
+    #[derive(Clone, Default)]
+    struct SlackStub {
+        calls: Arc<Mutex<Vec<(String, Value)>>>,
+    }
+
+    /// Accepts everything except cards, the way a workspace without Block Kit access would.
+    async fn slack_call(
+        State(stub): State<SlackStub>,
+        uri: axum::http::Uri,
+        Json(body): Json<Value>,
+    ) -> Json<Value> {
+        let mut calls = stub.calls.lock();
+        calls.push((uri.path().to_string(), body.clone()));
+        if body.get("blocks").is_some() {
+            return Json(json!({"ok": false, "error": "invalid_blocks"}));
+        }
+        Json(json!({"ok": true, "channel": "C123", "ts": format!("1700000000.{:06}", calls.len())}))
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn slack_smoke_threads_the_reply_records_artifacts_and_cleans_up() {
+        let stub = SlackStub::default();
+        let app = Router::new()
+            .route("/api/chat.postMessage", post(slack_call))
+            .route("/api/chat.delete", post(slack_call))
+            .with_state(stub.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}/api", listener.local_addr().unwrap());
+        tokio::spawn(axum::serve(listener, app).into_future());
+
+        let tmp = tempfile::tempdir().unwrap();
+        let vault = CredentialVault::new(&tmp.path().join("secrets"));
+        vault
+            .register(
+                SandboxProvider::Slack,
+                &BTreeMap::from([
+                    ("BOT_TOKEN".to_string(), "xoxb-1".to_string()),
+                    ("CHANNEL".to_string(), "#e2e".to_string()),
+                    ("API_BASE".to_string(), base),
+                ]),
+            )
+            .unwrap();
+        let credentials = vault.load(SandboxProvider::Slack).unwrap();
+        let artifacts = tmp.path().join("artifacts");
+        let artifacts_dir = artifacts.clone();
+        let run = tokio::task::spawn_blocking(move || {
+            run(
+                &credentials,
+                &OutboundHttp::default(),
+                &artifacts_dir,
+                false,
+            )
+        })
+        .await
+        .unwrap();
+
+        let outcomes: Vec<_> = run.cases.iter().map(|case| (case.case, case.ok)).collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                (SmokeCase::Text, true),
+                (SmokeCase::Card, false),
+                (SmokeCase::ThreadedReply, true)
+            ]
+        );
+        assert!(!run.ok);
+        assert_eq!(run.cases[1].detail, "HTTP 200: invalid_blocks");
+        let artifact: Value =
+            serde_json::from_slice(&fs::read(artifacts.join("slack/card.json")).unwrap()).unwrap();
+        assert_eq!(artifact["response"]["error"], "invalid_blocks");
+        assert_eq!(artifact["request"]["blocks"][2]["type"], "actions");
+
+        let calls = stub.calls.lock().clone();
+        assert_eq!(calls[2].1["thread_ts"], "1700000000.000001");
+        // Reply first, then its parent.
+        let deleted: Vec<_> = calls[3..]
+            .iter()
+            .map(|(path, body)| (path.as_str(), body["ts"].as_str().unwrap()))
+            .collect();
+        assert_eq!(
+            deleted,
+            vec![
+                ("/api/chat.delete", "1700000000.000003"),
+                ("/api/chat.delete", "1700000000.000001")
+            ]
+        );
+
+        let doc: CapabilityDoc = serde_yaml_bw::from_str(
+            "reference_provider: real\nsimulator_provider: sim\nproviders:\n  real: { capabilities: [send_message, buttons, streaming] }\n  sim: { capabilities: [send_message] }\ndowngrades: []\n",
+        )
+        .unwrap();
+        let rows = parity(&doc, &[run]);
+        let row = |name: &str| rows.iter().find(|row| row.capability == name).unwrap();
+        assert_eq!(row("send_message").verified_by, vec!["slack"]);
+        assert!(row("buttons").contradicted());
+        assert!(!row("streaming").contradicted());
+        assert!(!row("threads").claimed);
+        assert_eq!(row("threads").verified_by, vec!["slack"]);
+    }
+}
@@ -0,0 +1,283 @@
+//! Provider endpoints configured under `[providers.<name>]`: where sends for a provider go,
+//! the secret holding its credentials, capability overrides and a request timeout. Every
+//! endpoint is probed at startup and then periodically, and the latest result is served on
+//! `GET /providers/{name}/health`, so a wrong URL or a missing token shows up when the
+//! server boots rather than as a failed POST in the middle of a scenario.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Result, bail};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::interpolate::SecretStore;
+use crate::network::OutboundHttp;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderConfig {
+    /// URL sends for this provider are posted to (`http://` or `https://`).
+    pub endpoint: String,
+    /// Key in the `[packs].secrets_dir` store whose value is sent as a bearer token.
+    #[serde(default)]
+    pub auth_secret: Option<String>,
+    /// Capabilities switched on (`true`) or off (`false`) on top of the provider's entry in
+    /// the simulator capability map.
+    #[serde(default)]
+    pub capabilities: BTreeMap<String, bool>,
+    /// Bound on every request to the provider, probes included.
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// URL probed instead of `endpoint`, for providers with a dedicated health route.
+    #[serde(default)]
+    pub health_url: Option<String>,
+}
+
+fn default_timeout_ms() -> u64 {
+    5_000
+}
+
+/// A configured provider with its secret resolved.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderEndpoint {
+    pub name: String,
+    pub endpoint: String,
+    #[serde(skip)]
+    pub bearer: Option<String>,
+    /// Why the auth secret could not be used; the provider probes unhealthy while set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth_error: Option<String>,
+    /// Effective capabilities after the overrides.
+    pub capabilities: BTreeSet<String>,
+    pub timeout_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub health_url: Option<String>,
+}
+
+impl ProviderEndpoint {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms.max(1))
+    }
+
+    fn probe_url(&self) -> &str {
+        self.health_url.as_deref().unwrap_or(&self.endpoint)
+    }
+
+    /// Contact the endpoint once. Any answer proves it reachable; `401`/`403` mean the
+    /// credentials are wrong and `5xx` that the provider is failing.
+    pub fn probe(&self, http: &OutboundHttp, checked_at_ms: u64) -> ProviderHealth {
+        let started = Instant::now();
+        let (status, error) = match &self.auth_error {
+            Some(err) => (None, Some(err.clone())),
+            None => match http.with_timeout(self.timeout()).get_as(
+                "provider health probe",
+                self.probe_url(),
+                self.bearer.as_deref(),
+            ) {
+                Ok((status @ (401 | 403), _)) => (
+                    Some(status),
+                    Some(format!("endpoint rejected the credentials ({status})")),
+                ),
+                Ok((status, body)) if status >= 500 => (
+                    Some(status),
+                    Some(format!("endpoint answered {status}: {body}")),
+                ),
+                Ok((status, _)) => (Some(status), None),
+                Err(err) => (None, Some(format!("{err:#}"))),
+            },
+        };
+        ProviderHealth {
+            healthy: error.is_none(),
+            checked_at_ms,
+            latency_ms: started.elapsed().as_millis() as u64,
+            status,
+            error,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProviderHealth {
+    pub healthy: bool,
+    pub checked_at_ms: u64,
+    pub latency_ms: u64,
+    /// HTTP status of the probe, when the endpoint answered.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Every configured provider and the result of its latest probe.
+#[derive(Clone, Default)]
+pub struct Providers {
+    endpoints: Arc<BTreeMap<String, ProviderEndpoint>>,
+    health: Arc<Mutex<BTreeMap<String, ProviderHealth>>>,
+}
+
+impl Providers {
+    /// Resolve `configs`. `base_capabilities` maps provider names to the capabilities the
+    /// simulator capability map lists for them. A malformed endpoint fails the load; a
+    /// secret that cannot be read only marks the provider unhealthy.
+    pub fn load(
+        configs: &BTreeMap<String, ProviderConfig>,
+        secrets: Option<&dyn SecretStore>,
+        base_capabilities: &BTreeMap<String, Vec<String>>,
+    ) -> Result<Self> {
+        let mut endpoints = BTreeMap::new();
+        for (name, config) in configs {
+            for url in std::iter::once(&config.endpoint).chain(&config.health_url) {
+                if !(url.starts_with("http://") || url.starts_with("https://")) {
+                    bail!("[providers.{name}]: {url:?} is not an http(s) URL");
+                }
+            }
+            let (bearer, auth_error) = match &config.auth_secret {
+                None => (None, None),
+                Some(key) => match secrets.map(|store| store.get(key)) {
+                    None => (
+                        None,
+                        Some(format!(
+                            "auth secret {key} needs [packs].secrets_dir to be configured"
+                        )),
+                    ),
+                    Some(Ok(Some(token))) => (Some(token), None),
+                    Some(Ok(None)) => (None, Some(format!("auth secret {key} is not set"))),
+                    Some(Err(err)) => (None, Some(format!("{err:#}"))),
+                },
+            };
+            let mut capabilities: BTreeSet<String> = base_capabilities
+                .get(name)
+                .into_iter()
+                .flatten()
+                .cloned()
+                .collect();
+            for (capability, enabled) in &config.capabilities {
+                if *enabled {
+                    capabilities.insert(capability.clone());
+                } else {
+                    capabilities.remove(capability);
+                }
+            }
+            endpoints.insert(
+                name.clone(),
+                ProviderEndpoint {
+                    name: name.clone(),
+                    endpoint: config.endpoint.clone(),
+                    bearer,
+                    auth_error,
+                    capabilities,
+                    timeout_ms: config.timeout_ms,
+                    health_url: config.health_url.clone(),
+                },
+            );
+        }
+        Ok(Self {
+            endpoints: Arc::new(endpoints),
+            health: Arc::default(),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.endpoints.is_empty()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ProviderEndpoint> {
+        self.endpoints.get(name)
+    }
+
+    /// Latest probe of `name`, if it has been probed.
+    pub fn health(&self, name: &str) -> Option<ProviderHealth> {
+        self.health.lock().get(name).cloned()
+    }
+
+    /// Probe every provider and keep the results; returns the unhealthy ones. Blocking.
+    pub fn probe_all(&self, http: &OutboundHttp, now_ms: u64) -> Vec<(String, ProviderHealth)> {
+        let mut unhealthy = Vec::new();
+        for (name, endpoint) in self.endpoints.iter() {
+            let health = endpoint.probe(http, now_ms);
+            if !health.healthy {
+                unhealthy.push((name.clone(), health.clone()));
+            }
+            self.health.lock().insert(name.clone(), health);
+        }
+        unhealthy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpolate::DirSecretStore;
+    use crate::testkit::ProviderSink;
+
+    fn config(endpoint: &str) -> ProviderConfig {
+        ProviderConfig {
+            endpoint: endpoint.into(),
+            auth_secret: None,
+            capabilities: BTreeMap::new(),
+            timeout_ms: 1_000,
+            health_url: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn probes_flag_unreachable_endpoints_and_missing_secrets() {
+        let tmp = tempfile::tempdir().unwrap();
+        let sink = ProviderSink::start(tmp.path().join("sink.json"))
+            .await
+            .unwrap();
+        let secrets = DirSecretStore::new(tmp.path().join("secrets"));
+        secrets.put("SLACK_TOKEN", "xoxb-test").unwrap();
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let closed_url = format!("http://{}/send", closed.local_addr().unwrap());
+        drop(closed);
+
+        let mut slack = config(&sink.send_url());
+        slack.auth_secret = Some("SLACK_TOKEN".into());
+        slack.capabilities = BTreeMap::from([("cards".into(), false), ("files".into(), true)]);
+        let mut teams = config(&sink.send_url());
+        teams.auth_secret = Some("TEAMS_TOKEN".into());
+        let configs = BTreeMap::from([
+            ("slack".to_string(), slack),
+            ("teams".to_string(), teams),
+            ("webex".to_string(), config(&closed_url)),
+        ]);
+        let base = BTreeMap::from([("slack".to_string(), vec!["text".into(), "cards".into()])]);
+        let providers = Providers::load(&configs, Some(&secrets), &base).unwrap();
+        let slack = providers.get("slack").unwrap();
+        assert_eq!(slack.bearer.as_deref(), Some("xoxb-test"));
+        assert_eq!(
+            slack.capabilities,
+            BTreeSet::from(["files".to_string(), "text".to_string()])
+        );
+        assert!(providers.health("slack").is_none());
+
+        let probed = providers.clone();
+        let unhealthy =
+            tokio::task::spawn_blocking(move || probed.probe_all(&OutboundHttp::default(), 42))
+                .await
+                .unwrap();
+        let names: Vec<_> = unhealthy.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["teams", "webex"]);
+        let slack = providers.health("slack").unwrap();
+        assert!(slack.healthy, "{slack:?}");
+        assert_eq!(slack.checked_at_ms, 42);
+        assert_eq!(
+            providers.health("teams").unwrap().error.as_deref(),
+            Some("auth secret TEAMS_TOKEN is not set")
+        );
+        assert!(providers.health("webex").unwrap().status.is_none());
+
+        let err = Providers::load(
+            &BTreeMap::from([("bad".to_string(), config("slack.example"))]),
+            None,
+            &BTreeMap::new(),
+        )
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("[providers.bad]"), "{err}");
+    }
+}
@@ -0,0 +1,562 @@
+//! `greentic-integration repl`: an interactive prompt against a running bridge, with short
+//! commands for the usual emit/resume/inspect loop, tab completion over the server's packs,
+//! session users and flows, and a live tail of new runner events printed above the prompt.
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{Result, anyhow, bail};
+use futures::StreamExt;
+use greentic_integration_client::{
+    BridgeClient, EmitRequest, PackQuery, ResumeRequest, RunnerEvent, SessionQuery,
+};
+use parking_lot::RwLock;
+use rustyline::{
+    Context, Editor, ExternalPrinter, Helper,
+    completion::{Completer, Pair},
+    error::ReadlineError,
+    highlight::Highlighter,
+    hint::Hinter,
+    history::DefaultHistory,
+    validate::Validator,
+};
+use serde_json::{Value, json};
+use tokio::task::JoinHandle;
+
+/// How often the event tail polls `GET /runner/events`.
+const TAIL_POLL: Duration = Duration::from_millis(500);
+
+const COMMANDS: &[&str] = &[
+    "clear", "emit", "events", "exit", "help", "packs", "quit", "resume", "sessions", "tail", "use",
+];
+
+const HELP: &str = "\
+commands:
+  emit <flow> [json]          run a flow (payload defaults to {})
+  resume <user> [json|text]   resume the user's waiting session; text becomes {\"text\": ...}
+  sessions [user]             list sessions of the current tenant/team
+  packs [id]                  list the packs resolved for the current tenant/team
+  events [n]                  show the last n runner events (default 10)
+  clear                       clear the server's runner events
+  use tenant|team|user <id>   set who commands run for; `-` unsets
+  tail on|off                 print new runner events as they arrive (on by default)
+  help, quit";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplCommand {
+    Emit {
+        flow: String,
+        payload: Value,
+    },
+    Resume {
+        user: String,
+        payload: Value,
+    },
+    Sessions {
+        user: Option<String>,
+    },
+    Packs {
+        id: Option<String>,
+    },
+    Events {
+        limit: usize,
+    },
+    Clear,
+    Use {
+        field: CallerField,
+        value: Option<String>,
+    },
+    Tail(bool),
+    Help,
+    Quit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallerField {
+    Tenant,
+    Team,
+    User,
+}
+
+/// Parse one prompt line; `Ok(None)` for a blank line.
+pub fn parse_command(line: &str) -> Result<Option<ReplCommand>> {
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(None);
+    }
+    let (verb, rest) = split_word(line);
+    let command = match verb {
+        "emit" => {
+            let (flow, payload) = split_word(rest);
+            if flow.is_empty() {
+                bail!("usage: emit <flow> [json]");
+            }
+            let payload = match unquote(payload) {
+                "" => json!({}),
+                raw => serde_json::from_str(raw)
+                    .map_err(|err| anyhow!("payload is not valid JSON: {err}"))?,
+            };
+            ReplCommand::Emit {
+                flow: flow.to_string(),
+                payload,
+            }
+        }
+        "resume" => {
+            let (user, payload) = split_word(rest);
+            if user.is_empty() {
+                bail!("usage: resume <user> [json|text]");
+            }
+            let payload = unquote(payload);
+            let payload = serde_json::from_str(payload)
+                .ok()
+                .filter(Value::is_object)
+                .unwrap_or_else(|| json!({ "text": payload }));
+            ReplCommand::Resume {
+                user: user.to_string(),
+                payload,
+            }
+        }
+        "sessions" => ReplCommand::Sessions {
+            user: Some(rest.to_string()).filter(|user| !user.is_empty()),
+        },
+        "packs" => ReplCommand::Packs {
+            id: Some(rest.to_string()).filter(|id| !id.is_empty()),
+        },
+        "events" => ReplCommand::Events {
+            limit: match rest {
+                "" => 10,
+                raw => raw
+                    .parse()
+                    .map_err(|_| anyhow!("usage: events [n], got {raw:?}"))?,
+            },
+        },
+        "clear" => ReplCommand::Clear,
+        "use" => {
+            let (field, value) = split_word(rest);
+            let field = match field {
+                "tenant" => CallerField::Tenant,
+                "team" => CallerField::Team,
+                "user" => CallerField::User,
+                _ => bail!("usage: use tenant|team|user <id>"),
+            };
+            let value = match value {
+                "" => bail!("usage: use tenant|team|user <id> (`-` unsets)"),
+                "-" => None,
+                value => Some(value.to_string()),
+            };
+            ReplCommand::Use { field, value }
+        }
+        "tail" => match rest {
+            "on" => ReplCommand::Tail(true),
+            "off" => ReplCommand::Tail(false),
+            _ => bail!("usage: tail on|off"),
+        },
+        "help" | "?" => ReplCommand::Help,
+        "quit" | "exit" => ReplCommand::Quit,
+        other => bail!("unknown command {other:?}; type `help`"),
+    };
+    Ok(Some(command))
+}
+
+fn split_word(text: &str) -> (&str, &str) {
+    let text = text.trim_start();
+    match text.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim()),
+        None => (text, ""),
+    }
+}
+
+/// Strip one pair of matching outer quotes, as a shell would for `'{"text":"hi"}'`.
+fn unquote(text: &str) -> &str {
+    ['\'', '"']
+        .iter()
+        .find_map(|quote| {
+            text.strip_prefix(*quote)
+                .and_then(|inner| inner.strip_suffix(*quote))
+        })
+        .unwrap_or(text)
+}
+
+/// Names offered by tab completion, refreshed from the server after each command.
+#[derive(Debug, Default)]
+pub struct Completions {
+    pub packs: Vec<String>,
+    pub users: Vec<String>,
+    pub flows: Vec<String>,
+}
+
+impl Completions {
+    /// Candidates for the word being typed at `pos`, and where that word starts.
+    pub fn complete(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        let before = &line[..pos];
+        let start = before.rfind(char::is_whitespace).map_or(0, |idx| idx + 1);
+        let word = &before[start..];
+        let previous: Vec<&str> = before[..start].split_whitespace().collect();
+        let pool: Vec<&str> = match previous.as_slice() {
+            [] => COMMANDS.to_vec(),
+            ["emit"] => self.flows.iter().map(String::as_str).collect(),
+            ["resume"] | ["sessions"] | ["use", "user"] => {
+                self.users.iter().map(String::as_str).collect()
+            }
+            ["use"] => vec!["team", "tenant", "user"],
+            ["tail"] => vec!["off", "on"],
+            ["packs"] => self.packs.iter().map(String::as_str).collect(),
+            _ => Vec::new(),
+        };
+        let matches = pool
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(word))
+            .map(str::to_string)
+            .collect();
+        (start, matches)
+    }
+}
+
+struct ReplHelper {
+    completions: Arc<RwLock<Completions>>,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let (start, matches) = self.completions.read().complete(line, pos);
+        let pairs = matches
+            .into_iter()
+            .map(|candidate| Pair {
+                display: candidate.clone(),
+                replacement: candidate,
+            })
+            .collect();
+        Ok((start, pairs))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+/// Who the prompt's commands run for.
+#[derive(Debug, Default)]
+struct Caller {
+    tenant: Option<String>,
+    team: Option<String>,
+    user: Option<String>,
+}
+
+impl Caller {
+    fn prompt(&self) -> String {
+        let who = [&self.tenant, &self.team, &self.user]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join("/");
+        if who.is_empty() {
+            "greentic> ".to_string()
+        } else {
+            format!("greentic [{who}]> ")
+        }
+    }
+}
+
+pub async fn run(client: BridgeClient, tenant: Option<String>, team: Option<String>) -> Result<()> {
+    let completions = Arc::new(RwLock::new(Completions::default()));
+    let mut editor: Editor<ReplHelper, DefaultHistory> = Editor::new()?;
+    editor.set_helper(Some(ReplHelper {
+        completions: completions.clone(),
+    }));
+    let mut caller = Caller {
+        tenant,
+        team,
+        user: None,
+    };
+    println!(
+        "Connected to {} (type `help` for commands)",
+        client.base_url()
+    );
+    refresh_completions(&client, &caller, &completions).await;
+    let mut tail = Some(start_tail(&client, &mut editor));
+
+    loop {
+        let prompt = caller.prompt();
+        let line = match tokio::task::block_in_place(|| editor.readline(&prompt)) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(err) => return Err(err.into()),
+        };
+        let command = match parse_command(&line) {
+            Ok(Some(command)) => command,
+            Ok(None) => continue,
+            Err(err) => {
+                eprintln!("{err}");
+                continue;
+            }
+        };
+        let _ = editor.add_history_entry(line.as_str());
+        match command {
+            ReplCommand::Quit => break,
+            ReplCommand::Help => println!("{HELP}"),
+            ReplCommand::Use { field, value } => match field {
+                CallerField::Tenant => caller.tenant = value,
+                CallerField::Team => caller.team = value,
+                CallerField::User => caller.user = value,
+            },
+            ReplCommand::Tail(on) => match (on, tail.take()) {
+                (true, None) => tail = Some(start_tail(&client, &mut editor)),
+                (true, running) => tail = running,
+                (false, running) => {
+                    if let Some(task) = running {
+                        task.abort();
+                    }
+                }
+            },
+            command => {
+                if let Err(err) = execute(&client, &caller, command).await {
+                    eprintln!("error: {err:#}");
+                }
+            }
+        }
+        refresh_completions(&client, &caller, &completions).await;
+    }
+    if let Some(task) = tail {
+        task.abort();
+    }
+    Ok(())
+}
+
+async fn execute(client: &BridgeClient, caller: &Caller, command: ReplCommand) -> Result<()> {
+    match command {
+        ReplCommand::Emit { flow, payload } => {
+            let request = EmitRequest {
+                tenant: caller.tenant.clone(),
+                team: caller.team.clone(),
+                user: caller.user.clone(),
+                ..EmitRequest::new(flow, payload)
+            };
+            let event = client.emit(&request).await?;
+            println!("{}", serde_json::to_string_pretty(&event.result)?);
+        }
+        ReplCommand::Resume { user, payload } => {
+            let request = ResumeRequest {
+                tenant: caller.tenant.clone(),
+                team: caller.team.clone(),
+                ..ResumeRequest::new(user, payload)
+            };
+            let event = client.resume(&request).await?;
+            println!("{}", serde_json::to_string_pretty(&event.result)?);
+        }
+        ReplCommand::Sessions { user } => {
+            let list = client.list_sessions(&session_query(caller, user)).await?;
+            if list.sessions.is_empty() {
+                println!("No sessions.");
+            }
+            for session in list.sessions {
+                println!(
+                    "{} user={} flow={} node={}",
+                    session.key,
+                    session.user.as_deref().unwrap_or("-"),
+                    session.cursor.flow_id.as_deref().unwrap_or("-"),
+                    session.cursor.node_id.as_deref().unwrap_or("-"),
+                );
+            }
+        }
+        ReplCommand::Packs { id } => {
+            let list = client.list_packs(&pack_query(caller)).await?;
+            let packs = list
+                .packs
+                .into_iter()
+                .filter(|pack| id.as_ref().is_none_or(|id| &pack.id == id));
+            for pack in packs {
+                println!(
+                    "{} {} [{}]",
+                    pack.id,
+                    pack.kind.as_deref().unwrap_or("-"),
+                    pack.status
+                );
+            }
+        }
+        ReplCommand::Events { limit } => {
+            let events = client.runner_events().await?;
+            let skip = events.len().saturating_sub(limit);
+            for event in &events[skip..] {
+                println!("{}", format_event(event));
+            }
+        }
+        ReplCommand::Clear => {
+            client.clear_runner_events().await?;
+            println!("Cleared runner events.");
+        }
+        ReplCommand::Use { .. } | ReplCommand::Tail(_) | ReplCommand::Help | ReplCommand::Quit => {}
+    }
+    Ok(())
+}
+
+fn session_query(caller: &Caller, user: Option<String>) -> SessionQuery {
+    SessionQuery {
+        tenant: caller.tenant.clone(),
+        team: caller.team.clone(),
+        user,
+        ..SessionQuery::default()
+    }
+}
+
+fn pack_query(caller: &Caller) -> PackQuery {
+    PackQuery {
+        tenant: caller.tenant.clone(),
+        team: caller.team.clone(),
+        user: caller.user.clone(),
+        ..PackQuery::default()
+    }
+}
+
+/// Reload completion candidates; a server that is briefly unreachable keeps the old ones.
+async fn refresh_completions(
+    client: &BridgeClient,
+    caller: &Caller,
+    completions: &RwLock<Completions>,
+) {
+    let (pack_query, session_query) = (pack_query(caller), session_query(caller, None));
+    let (packs, sessions, events) = tokio::join!(
+        client.list_packs(&pack_query),
+        client.list_sessions(&session_query),
+        client.runner_events(),
+    );
+    let mut completions = completions.write();
+    if let Ok(packs) = packs {
+        completions.packs = packs.packs.into_iter().map(|pack| pack.id).collect();
+    }
+    let mut flows = Vec::new();
+    if let Ok(sessions) = sessions {
+        let mut users = Vec::new();
+        for session in sessions.sessions {
+            users.extend(session.user);
+            flows.extend(session.cursor.flow_id);
+        }
+        users.sort();
+        users.dedup();
+        completions.users = users;
+    }
+    if let Ok(events) = events {
+        flows.extend(events.into_iter().map(|event| event.flow));
+    }
+    flows.sort();
+    flows.dedup();
+    if !flows.is_empty() {
+        completions.flows = flows;
+    }
+}
+
+/// Print events recorded from now on above the prompt until the task is aborted.
+fn start_tail(
+    client: &BridgeClient,
+    editor: &mut Editor<ReplHelper, DefaultHistory>,
+) -> JoinHandle<()> {
+    // Without a terminal (piped input) there is no prompt to print above.
+    let mut printer: Box<dyn ExternalPrinter + Send> = match editor.create_external_printer() {
+        Ok(printer) => Box::new(printer),
+        Err(_) => Box::new(StdoutPrinter),
+    };
+    let since = crate::now_millis();
+    let mut events = Box::pin(client.stream_events(TAIL_POLL));
+    tokio::spawn(async move {
+        while let Some(event) = events.next().await {
+            let Ok(event) = event else { continue };
+            if event.timestamp_ms < since {
+                continue;
+            }
+            if printer
+                .print(format!("  ~ {}", format_event(&event)))
+                .is_err()
+            {
+                break;
+            }
+        }
+    })
+}
+
+struct StdoutPrinter;
+
+impl ExternalPrinter for StdoutPrinter {
+    fn print(&mut self, msg: String) -> rustyline::Result<()> {
+        println!("{msg}");
+        Ok(())
+    }
+}
+
+fn format_event(event: &RunnerEvent) -> String {
+    let status = event
+        .result
+        .get("status")
+        .and_then(Value::as_str)
+        .unwrap_or("-");
+    format!(
+        "[{}] {} tenant={} user={} status={status}",
+        event.timestamp_ms,
+        event.flow,
+        event.tenant.as_deref().unwrap_or("-"),
+        event.user.as_deref().unwrap_or("-"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_short_commands() {
+        assert_eq!(
+            parse_command(r#"emit flow-x '{"text":"hi"}'"#).unwrap(),
+            Some(ReplCommand::Emit {
+                flow: "flow-x".into(),
+                payload: json!({"text": "hi"}),
+            })
+        );
+        assert_eq!(
+            parse_command("resume user-1 hello there").unwrap(),
+            Some(ReplCommand::Resume {
+                user: "user-1".into(),
+                payload: json!({"text": "hello there"}),
+            })
+        );
+        assert_eq!(
+            parse_command("use tenant -").unwrap(),
+            Some(ReplCommand::Use {
+                field: CallerField::Tenant,
+                value: None,
+            })
+        );
+        assert_eq!(parse_command("  ").unwrap(), None);
+        assert!(parse_command("emit flow-x {not json").is_err());
+        assert!(parse_command("events many").is_err());
+        assert!(parse_command("deploy").is_err());
+    }
+
+    #[test]
+    fn completes_commands_flows_and_users() {
+        let completions = Completions {
+            packs: vec!["demo-menu".into()],
+            users: vec!["user-1".into(), "user-2".into()],
+            flows: vec!["chat".into(), "checkout".into(), "menu".into()],
+        };
+        assert_eq!(completions.complete("em", 2), (0, vec!["emit".to_string()]));
+        assert_eq!(
+            completions.complete("emit ch", 7),
+            (5, vec!["chat".to_string(), "checkout".to_string()])
+        );
+        assert_eq!(completions.complete("resume user-", 12).1.len(), 2);
+        assert_eq!(completions.complete("use te", 6).1, vec!["team", "tenant"]);
+        assert!(completions.complete("emit chat x", 11).1.is_empty());
+    }
+}
@@ -0,0 +1,440 @@
+//! Differential replay for `runner compare`: the same recorded traffic is emitted against a
+//! baseline and a candidate bridge, and the resulting runner events, outbound payloads and
+//! sessions are normalized (volatile fields dropped, generated ids masked) and diffed into a
+//! regression report.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+
+use crate::RunnerEvent;
+
+/// Keys whose values differ between any two runs: clocks, counters, trace and session ids.
+pub const VOLATILE_KEYS: &[&str] = &[
+    "timestamp_ms",
+    "sequence",
+    "traceparent",
+    "trace_id",
+    "span_id",
+    "session_id",
+    "updated_at_epoch_ms",
+];
+
+/// Placeholder for generated UUIDs (e.g. the embedded runner's session ids in `channel`).
+const UUID_MASK: &str = "<uuid>";
+
+/// Drops volatile keys plus `--ignore` entries: bare key names match anywhere, entries
+/// starting with `/` are JSON pointers into the compared document.
+#[derive(Debug, Clone)]
+pub struct Normalizer {
+    keys: BTreeSet<String>,
+    pointers: BTreeSet<String>,
+}
+
+impl Normalizer {
+    pub fn new(ignore: &[String]) -> Self {
+        let mut keys: BTreeSet<String> = VOLATILE_KEYS.iter().map(|key| key.to_string()).collect();
+        let mut pointers = BTreeSet::new();
+        for entry in ignore {
+            if entry.starts_with('/') {
+                pointers.insert(entry.clone());
+            } else {
+                keys.insert(entry.clone());
+            }
+        }
+        Self { keys, pointers }
+    }
+
+    pub fn normalize(&self, value: &Value) -> Value {
+        self.normalize_at(value, "")
+    }
+
+    fn normalize_at(&self, value: &Value, path: &str) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .filter_map(|(key, value)| {
+                        let child = format!("{path}/{}", escape(key));
+                        (!self.keys.contains(key) && !self.pointers.contains(&child))
+                            .then(|| (key.clone(), self.normalize_at(value, &child)))
+                    })
+                    .collect(),
+            ),
+            Value::Array(items) => Value::Array(
+                items
+                    .iter()
+                    .enumerate()
+                    .filter(|(idx, _)| !self.pointers.contains(&format!("{path}/{idx}")))
+                    .map(|(idx, item)| self.normalize_at(item, &format!("{path}/{idx}")))
+                    .collect(),
+            ),
+            Value::String(text) if is_uuid(text) => Value::String(UUID_MASK.into()),
+            other => other.clone(),
+        }
+    }
+}
+
+/// One value that differs; a side is absent when only the other has the path.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Difference {
+    /// JSON pointer into the compared document.
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub baseline: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub candidate: Option<Value>,
+}
+
+/// Structural diff of two JSON documents, in path order.
+pub fn diff(baseline: &Value, candidate: &Value) -> Vec<Difference> {
+    let mut out = Vec::new();
+    diff_at(Some(baseline), Some(candidate), String::new(), &mut out);
+    out
+}
+
+fn diff_at(
+    baseline: Option<&Value>,
+    candidate: Option<&Value>,
+    path: String,
+    out: &mut Vec<Difference>,
+) {
+    match (baseline, candidate) {
+        (Some(Value::Object(left)), Some(Value::Object(right))) => {
+            let keys: BTreeSet<&String> = left.keys().chain(right.keys()).collect();
+            for key in keys {
+                diff_at(
+                    left.get(key),
+                    right.get(key),
+                    format!("{path}/{}", escape(key)),
+                    out,
+                );
+            }
+        }
+        (Some(Value::Array(left)), Some(Value::Array(right))) => {
+            for idx in 0..left.len().max(right.len()) {
+                diff_at(left.get(idx), right.get(idx), format!("{path}/{idx}"), out);
+            }
+        }
+        (left, right) if left != right => out.push(Difference {
+            path: if path.is_empty() { "/".into() } else { path },
+            baseline: left.cloned(),
+            candidate: right.cloned(),
+        }),
+        _ => {}
+    }
+}
+
+/// What one side answered for a replayed event: the runner event, or why the emit failed.
+pub type EmitResult = std::result::Result<RunnerEvent, String>;
+
+/// Differences for one replayed event, split into what the flow sent out
+/// (`result.outcome.messages`/`events`) and everything else about the run.
+#[derive(Debug, Clone, Serialize)]
+pub struct EventComparison {
+    /// Position in the replay.
+    pub index: usize,
+    pub flow: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    pub recorded_at_ms: u64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub outbound: Vec<Difference>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub result: Vec<Difference>,
+}
+
+impl EventComparison {
+    pub fn new(
+        normalizer: &Normalizer,
+        index: usize,
+        recorded: &RunnerEvent,
+        baseline: &EmitResult,
+        candidate: &EmitResult,
+    ) -> Self {
+        let (baseline_out, baseline_rest) = split_outbound(normalizer, baseline);
+        let (candidate_out, candidate_rest) = split_outbound(normalizer, candidate);
+        Self {
+            index,
+            flow: recorded.flow.clone(),
+            tenant: recorded.tenant.clone(),
+            user: recorded.user.clone(),
+            recorded_at_ms: recorded.timestamp_ms,
+            outbound: diff(&baseline_out, &candidate_out),
+            result: diff(&baseline_rest, &candidate_rest),
+        }
+    }
+
+    pub fn is_identical(&self) -> bool {
+        self.outbound.is_empty() && self.result.is_empty()
+    }
+}
+
+/// Normalized `(outbound, rest)` of one side's answer.
+fn split_outbound(normalizer: &Normalizer, answer: &EmitResult) -> (Value, Value) {
+    let mut value = match answer {
+        Ok(event) => normalizer.normalize(&serde_json::to_value(event).unwrap_or_default()),
+        Err(error) => return (Value::Null, json!({ "error": error })),
+    };
+    let mut outbound = Map::new();
+    if let Some(outcome) = value
+        .pointer_mut("/result/outcome")
+        .and_then(Value::as_object_mut)
+    {
+        for key in ["messages", "events"] {
+            if let Some(sent) = outcome.remove(key) {
+                outbound.insert(key.to_string(), sent);
+            }
+        }
+    }
+    (Value::Object(outbound), value)
+}
+
+/// Diff two session listings. Keys are usually generated, so sessions are matched by
+/// `tenant:team:user` (`-` when unset) and their `key` is not compared.
+pub fn compare_sessions(
+    normalizer: &Normalizer,
+    baseline: &[Value],
+    candidate: &[Value],
+) -> Vec<Difference> {
+    let by_identity = |sessions: &[Value]| {
+        let mut seen = BTreeMap::<String, usize>::new();
+        let mut map = Map::new();
+        for session in sessions {
+            let field = |name: &str| session[name].as_str().unwrap_or("-").to_string();
+            let identity = format!("{}:{}:{}", field("tenant"), field("team"), field("user"));
+            let count = seen.entry(identity.clone()).or_default();
+            let identity = match *count {
+                0 => identity,
+                n => format!("{identity}#{n}"),
+            };
+            *count += 1;
+            let mut normalized = normalizer.normalize(session);
+            if let Some(object) = normalized.as_object_mut() {
+                object.remove("key");
+            }
+            map.insert(identity, normalized);
+        }
+        Value::Object(map)
+    };
+    diff(&by_identity(baseline), &by_identity(candidate))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CompareReport {
+    pub baseline: String,
+    pub candidate: String,
+    pub replayed: usize,
+    /// Replayed events both sides answered the same way.
+    pub identical: usize,
+    /// Only the events that differ.
+    pub events: Vec<EventComparison>,
+    pub sessions: Vec<Difference>,
+}
+
+impl CompareReport {
+    pub fn new(baseline: &str, candidate: &str) -> Self {
+        Self {
+            baseline: baseline.to_string(),
+            candidate: candidate.to_string(),
+            replayed: 0,
+            identical: 0,
+            events: Vec::new(),
+            sessions: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, comparison: EventComparison) {
+        self.replayed += 1;
+        if comparison.is_identical() {
+            self.identical += 1;
+        } else {
+            self.events.push(comparison);
+        }
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.events.is_empty() && self.sessions.is_empty()
+    }
+
+    /// Human-readable summary: one block per differing event, then session differences.
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "baseline  {}\ncandidate {}\n{} of {} replayed event(s) identical, {} differ; {} session difference(s)\n",
+            self.baseline,
+            self.candidate,
+            self.identical,
+            self.replayed,
+            self.events.len(),
+            self.sessions.len()
+        );
+        for event in &self.events {
+            out.push_str(&format!(
+                "\n#{} flow={} tenant={} user={} (recorded at {})\n",
+                event.index,
+                event.flow,
+                event.tenant.as_deref().unwrap_or("-"),
+                event.user.as_deref().unwrap_or("-"),
+                event.recorded_at_ms
+            ));
+            for (section, differences) in [("outbound", &event.outbound), ("result", &event.result)]
+            {
+                for difference in differences {
+                    out.push_str(&render_difference(section, difference));
+                }
+            }
+        }
+        if !self.sessions.is_empty() {
+            out.push_str("\nsessions\n");
+            for difference in &self.sessions {
+                out.push_str(&render_difference("session", difference));
+            }
+        }
+        out
+    }
+}
+
+fn render_difference(section: &str, difference: &Difference) -> String {
+    let show = |value: &Option<Value>| match value {
+        Some(value) => value.to_string(),
+        None => "(absent)".into(),
+    };
+    format!(
+        "  {section} {}: {} -> {}\n",
+        difference.path,
+        show(&difference.baseline),
+        show(&difference.candidate)
+    )
+}
+
+/// JSON pointer escaping (RFC 6901).
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+fn is_uuid(text: &str) -> bool {
+    let groups: Vec<&str> = text.split('-').collect();
+    groups.len() == 5
+        && groups
+            .iter()
+            .zip([8, 4, 4, 4, 12])
+            .all(|(group, len)| group.len() == len && group.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(text: &str, channel: &str) -> RunnerEvent {
+        RunnerEvent {
+            timestamp_ms: 1,
+            flow: "chat".into(),
+            tenant: Some("dev".into()),
+            team: None,
+            user: Some("alice".into()),
+            payload: json!({"text": "hi"}),
+            result: json!({
+                "status": "ok",
+                "outcome": {
+                    "trace": ["ingress", "send"],
+                    "messages": [{"text": text, "channel": channel}],
+                    "events": [],
+                },
+            }),
+            sequence: Some(1),
+            instance: None,
+        }
+    }
+
+    #[test]
+    fn normalizes_volatile_fields_and_ignored_paths() {
+        let normalizer = Normalizer::new(&["debug".into(), "/result/latency_ms".into()]);
+        let value = json!({
+            "timestamp_ms": 5,
+            "result": {
+                "latency_ms": 12,
+                "debug": {"host": "a"},
+                "session_id": "abc",
+                "channel": "67e55044-10b1-426f-9247-bb680e5fe0c8",
+                "kept": "67e55044",
+            },
+        });
+        assert_eq!(
+            normalizer.normalize(&value),
+            json!({"result": {"channel": "<uuid>", "kept": "67e55044"}})
+        );
+    }
+
+    #[test]
+    fn separates_outbound_differences_from_the_rest_of_the_result() {
+        let normalizer = Normalizer::new(&[]);
+        let recorded = event("hello", "x");
+        let baseline = Ok(event("hello", "67e55044-10b1-426f-9247-bb680e5fe0c8"));
+        let mut changed = event("hello!", "0f8fad5b-d9cb-469f-a165-70867728950e");
+        changed.timestamp_ms = 99;
+        changed.result["status"] = json!("error");
+        let comparison = EventComparison::new(&normalizer, 0, &recorded, &baseline, &Ok(changed));
+        assert_eq!(
+            comparison.outbound,
+            vec![Difference {
+                path: "/messages/0/text".into(),
+                baseline: Some(json!("hello")),
+                candidate: Some(json!("hello!")),
+            }]
+        );
+        assert_eq!(comparison.result.len(), 1);
+        assert_eq!(comparison.result[0].path, "/result/status");
+
+        let failed = EventComparison::new(
+            &normalizer,
+            1,
+            &recorded,
+            &baseline,
+            &Err("connection refused".into()),
+        );
+        assert!(failed.result.iter().any(|d| d.path == "/error"));
+
+        let mut report = CompareReport::new("http://a", "http://b");
+        report.push(EventComparison::new(
+            &normalizer,
+            2,
+            &recorded,
+            &baseline,
+            &baseline,
+        ));
+        report.push(comparison);
+        assert_eq!((report.replayed, report.identical), (2, 1));
+        assert!(!report.is_clean());
+        assert!(
+            report
+                .render()
+                .contains("outbound /messages/0/text: \"hello\" -> \"hello!\"")
+        );
+    }
+
+    #[test]
+    fn matches_sessions_by_identity_not_key() {
+        let normalizer = Normalizer::new(&[]);
+        let session = |key: &str, node: &str| json!({"key": key, "tenant": "dev", "user": "alice", "cursor": {"node_id": node}, "updated_at_epoch_ms": 1});
+        assert!(
+            compare_sessions(
+                &normalizer,
+                &[session("a", "wait")],
+                &[session("b", "wait")]
+            )
+            .is_empty()
+        );
+        let differences = compare_sessions(
+            &normalizer,
+            &[session("a", "wait")],
+            &[
+                session("b", "done"),
+                json!({"tenant": "dev", "user": "bob"}),
+            ],
+        );
+        let paths: Vec<_> = differences.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(paths, ["/dev:-:alice/cursor/node_id", "/dev:-:bob"]);
+    }
+}
@@ -0,0 +1,221 @@
+//! Turn the external runner's structured log into [`RunnerEvent`]s, so `GET /runner/events`
+//! shows real runner activity next to the events the bridge synthesizes.
+//!
+//! Each line is either a saved `RunnerEvent` (kept as is) or a `tracing` JSON record
+//! (`{"timestamp", "level", "target", "fields": {...}, "span": {...}, "spans": [...]}`). A
+//! record is attributed to the `flow`/`flow_id`, `tenant`/`tenant_id`, `team`/`team_id` and
+//! `user`/`user_id` found in its fields, then its current span, then its enclosing spans.
+//! Lines that are not JSON or name no flow are skipped.
+
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use chrono::DateTime;
+use serde_json::{Map, Value, json};
+
+use crate::RunnerEvent;
+
+/// `result.source` of imported log records.
+pub const RUNNER_SOURCE: &str = "greentic-runner";
+
+#[derive(Debug, Default)]
+pub struct ImportBatch {
+    pub events: Vec<RunnerEvent>,
+    /// Non-empty lines that were not JSON or carried no flow.
+    pub skipped: usize,
+}
+
+/// Parse every line of `text`. Records without a timestamp are stamped `now_ms`.
+pub fn parse_lines(text: &str, now_ms: u64) -> ImportBatch {
+    let mut batch = ImportBatch::default();
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        match parse_line(line, now_ms) {
+            Some(event) => batch.events.push(event),
+            None => batch.skipped += 1,
+        }
+    }
+    batch
+}
+
+pub fn parse_line(line: &str, now_ms: u64) -> Option<RunnerEvent> {
+    let value: Value = serde_json::from_str(line.trim()).ok()?;
+    if let Ok(event) = serde_json::from_value::<RunnerEvent>(value.clone()) {
+        return Some(event);
+    }
+    let record = value.as_object()?;
+    let fields = record.get("fields").and_then(Value::as_object);
+    let spans: Vec<&Map<String, Value>> = record
+        .get("span")
+        .into_iter()
+        .chain(
+            record
+                .get("spans")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flat_map(|spans| spans.iter().rev()),
+        )
+        .filter_map(Value::as_object)
+        .collect();
+    let lookup = |keys: [&str; 2]| -> Option<String> {
+        fields
+            .into_iter()
+            .chain(std::iter::once(record))
+            .chain(spans.iter().copied())
+            .find_map(|scope| keys.iter().find_map(|key| scope.get(*key)))
+            .and_then(|value| match value {
+                Value::String(text) => Some(text.clone()),
+                Value::Number(number) => Some(number.to_string()),
+                _ => None,
+            })
+    };
+
+    let flow = lookup(["flow", "flow_id"])?;
+    let level = record
+        .get("level")
+        .and_then(Value::as_str)
+        .unwrap_or("INFO")
+        .to_ascii_uppercase();
+    let message = fields
+        .and_then(|fields| fields.get("message"))
+        .or_else(|| record.get("message"))
+        .cloned()
+        .unwrap_or(Value::Null);
+    let status = if level == "ERROR" { "error" } else { "ok" };
+    Some(RunnerEvent {
+        timestamp_ms: timestamp_ms(record).unwrap_or(now_ms),
+        tenant: lookup(["tenant", "tenant_id"]),
+        team: lookup(["team", "team_id"]),
+        user: lookup(["user", "user_id"]),
+        payload: json!({
+            "message": message,
+            "level": level,
+            "target": record.get("target").cloned().unwrap_or(Value::Null),
+            "fields": fields.cloned().unwrap_or_default(),
+        }),
+        result: json!({
+            "flow": flow,
+            "status": status,
+            "source": RUNNER_SOURCE,
+        }),
+        flow,
+        sequence: None,
+        instance: None,
+    })
+}
+
+/// `timestamp_ms` (epoch millis) or an RFC 3339 `timestamp`.
+fn timestamp_ms(record: &Map<String, Value>) -> Option<u64> {
+    if let Some(ms) = record.get("timestamp_ms").and_then(Value::as_u64) {
+        return Some(ms);
+    }
+    let parsed = DateTime::parse_from_rfc3339(record.get("timestamp")?.as_str()?).ok()?;
+    u64::try_from(parsed.timestamp_millis()).ok()
+}
+
+/// Follows a growing log file, yielding only lines completed since the last poll. A file
+/// that shrank (rotated or truncated) is read again from the start.
+#[derive(Debug, Default)]
+pub struct LogTail {
+    offset: u64,
+    partial: String,
+}
+
+impl LogTail {
+    pub fn poll(&mut self, path: &Path, now_ms: u64) -> Result<ImportBatch> {
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            // The runner may not have created its log yet.
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(ImportBatch::default());
+            }
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed to open {}", path.display()));
+            }
+        };
+        let len = file.metadata()?.len();
+        if len < self.offset {
+            self.offset = 0;
+            self.partial.clear();
+        }
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut chunk = Vec::new();
+        file.read_to_end(&mut chunk)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        self.offset += chunk.len() as u64;
+        self.partial.push_str(&String::from_utf8_lossy(&chunk));
+        let Some(end) = self.partial.rfind('\n') else {
+            return Ok(ImportBatch::default());
+        };
+        let complete: String = self.partial.drain(..=end).collect();
+        Ok(parse_lines(&complete, now_ms))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attributes_tracing_records_from_fields_and_spans() {
+        let log = r#"
+{"timestamp":"2025-01-02T03:04:05.250Z","level":"INFO","target":"greentic_runner::flow","fields":{"message":"node completed","node":"reply"},"span":{"name":"node","flow_id":"chat"},"spans":[{"name":"session","tenant_id":"acme","team":"support"},{"name":"flow","tenant":"globex","user_id":"u-1"}]}
+runner listening on 0.0.0.0:3333
+{"timestamp":"2025-01-02T03:04:06Z","level":"ERROR","fields":{"message":"provider failed","flow":"chat","tenant":"acme"}}
+{"timestamp":"2025-01-02T03:04:07Z","level":"INFO","fields":{"message":"warming cache"}}
+{"timestamp_ms":42,"flow":"emit","tenant":null,"team":null,"user":null,"payload":{},"result":{"status":"ok"}}
+"#;
+        let batch = parse_lines(log, 7);
+        assert_eq!(batch.skipped, 2);
+        assert_eq!(batch.events.len(), 3);
+
+        let node = &batch.events[0];
+        assert_eq!(node.timestamp_ms, 1_735_787_045_250);
+        assert_eq!(node.flow, "chat");
+        // The innermost span wins over the enclosing session span.
+        assert_eq!(node.tenant.as_deref(), Some("globex"));
+        assert_eq!(node.team.as_deref(), Some("support"));
+        assert_eq!(node.user.as_deref(), Some("u-1"));
+        assert_eq!(node.payload["message"], "node completed");
+        assert_eq!(node.payload["fields"]["node"], "reply");
+        assert_eq!(node.result["source"], RUNNER_SOURCE);
+        assert_eq!(node.result["status"], "ok");
+
+        let failure = &batch.events[1];
+        assert_eq!(failure.tenant.as_deref(), Some("acme"));
+        assert_eq!(failure.result["status"], "error");
+
+        let saved = &batch.events[2];
+        assert_eq!((saved.timestamp_ms, saved.flow.as_str()), (42, "emit"));
+        assert!(saved.result.get("source").is_none());
+    }
+
+    #[test]
+    fn tail_yields_completed_lines_and_restarts_after_truncation() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("runner.log");
+        let mut tail = LogTail::default();
+        assert!(tail.poll(&path, 0).unwrap().events.is_empty());
+
+        let line = |flow: &str| format!(r#"{{"level":"INFO","fields":{{"flow":"{flow}"}}}}"#);
+        std::fs::write(&path, format!("{}\n{}", line("a"), line("b"))).unwrap();
+        let flows = |batch: ImportBatch| -> Vec<String> {
+            batch.events.into_iter().map(|event| event.flow).collect()
+        };
+        assert_eq!(flows(tail.poll(&path, 0).unwrap()), vec!["a"]);
+
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        std::io::Write::write_all(&mut file, b"\n").unwrap();
+        assert_eq!(flows(tail.poll(&path, 0).unwrap()), vec!["b"]);
+        assert!(tail.poll(&path, 0).unwrap().events.is_empty());
+
+        std::fs::write(&path, format!("{}\n", line("c"))).unwrap();
+        assert_eq!(flows(tail.poll(&path, 0).unwrap()), vec!["c"]);
+    }
+}
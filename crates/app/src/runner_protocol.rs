@@ -0,0 +1,216 @@
+//! Wire protocol between the bridge's runner proxy and a greentic-runner (`RUNNER_PROXY_URL`).
+//!
+//! The bridge POSTs one JSON command per endpoint (`runner/emit`, `runner/reload`,
+//! `runner/activity`) and the runner answers with a [`RunnerAck`]. Every body carries the
+//! `protocol` version it was written for. Types reject unknown fields so contract tests
+//! (`harness::runner_contract`) notice when either side's shape drifts.
+
+use std::sync::Arc;
+
+use anyhow::{Result, anyhow};
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    routing::post,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Version written into every command and ack; bump on incompatible shape changes.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// `runner/emit`: free-form message forwarded to the runner log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EmitCommand {
+    pub protocol: u32,
+    pub message: String,
+}
+
+/// `runner/reload`: the bridge's pack index after a (re)build, plus seed defaults.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReloadCommand {
+    pub protocol: u32,
+    pub packs: Vec<PackDescriptor>,
+    pub defaults: RunnerDefaults,
+}
+
+/// What the runner needs to know about an indexed pack.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PackDescriptor {
+    pub id: String,
+    pub name: Option<String>,
+    pub kind: Option<String>,
+    pub version: Option<String>,
+    /// Lifecycle status: `active`, `deprecated` or `disabled`.
+    pub status: String,
+    pub tags: Vec<String>,
+    pub path: String,
+    pub flows: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RunnerDefaults {
+    pub tenant: Option<String>,
+    pub team: Option<String>,
+}
+
+/// `runner/activity`: a flow activity the bridge handled, with the result it recorded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ActivityCommand {
+    pub protocol: u32,
+    pub flow: String,
+    pub tenant: Option<String>,
+    pub team: Option<String>,
+    pub user: Option<String>,
+    pub payload: Value,
+    /// Run result; always carries a `status` (`ok`, `error`).
+    pub result: Value,
+}
+
+/// One bridge → runner command, decoded by endpoint.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RunnerRequest {
+    Emit(EmitCommand),
+    Reload(ReloadCommand),
+    Activity(ActivityCommand),
+}
+
+impl RunnerRequest {
+    /// Endpoint path relative to the runner base URL.
+    pub fn path(&self) -> &'static str {
+        match self {
+            RunnerRequest::Emit(_) => "runner/emit",
+            RunnerRequest::Reload(_) => "runner/reload",
+            RunnerRequest::Activity(_) => "runner/activity",
+        }
+    }
+
+    pub fn protocol(&self) -> u32 {
+        match self {
+            RunnerRequest::Emit(cmd) => cmd.protocol,
+            RunnerRequest::Reload(cmd) => cmd.protocol,
+            RunnerRequest::Activity(cmd) => cmd.protocol,
+        }
+    }
+
+    pub fn body(&self) -> Value {
+        let body = match self {
+            RunnerRequest::Emit(cmd) => serde_json::to_value(cmd),
+            RunnerRequest::Reload(cmd) => serde_json::to_value(cmd),
+            RunnerRequest::Activity(cmd) => serde_json::to_value(cmd),
+        };
+        body.expect("runner commands serialize to JSON")
+    }
+
+    /// Decode `body` as the command served at `path` (leading `/` optional).
+    pub fn decode(path: &str, body: Value) -> Result<Self> {
+        let path = path.trim_start_matches('/');
+        let decoded = match path {
+            "runner/emit" => serde_json::from_value(body).map(RunnerRequest::Emit),
+            "runner/reload" => serde_json::from_value(body).map(RunnerRequest::Reload),
+            "runner/activity" => serde_json::from_value(body).map(RunnerRequest::Activity),
+            other => return Err(anyhow!("unknown runner endpoint {other}")),
+        };
+        decoded.map_err(|err| anyhow!("invalid {path} command: {err}"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AckStatus {
+    Accepted,
+    Rejected,
+}
+
+/// Runner reply to a command. A `rejected` ack explains why in `message`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RunnerAck {
+    pub protocol: u32,
+    pub status: AckStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl RunnerAck {
+    pub fn accepted() -> Self {
+        Self {
+            protocol: PROTOCOL_VERSION,
+            status: AckStatus::Accepted,
+            message: None,
+        }
+    }
+
+    pub fn rejected(message: impl Into<String>) -> Self {
+        Self {
+            protocol: PROTOCOL_VERSION,
+            status: AckStatus::Rejected,
+            message: Some(message.into()),
+        }
+    }
+}
+
+/// Commands a [`stub_router`] accepted, in arrival order.
+pub type StubLog = Arc<Mutex<Vec<RunnerRequest>>>;
+
+/// Minimal runner implementing this protocol: decodes commands strictly, records the accepted
+/// ones in `log` and acks them. Malformed commands and other protocol versions get `422` with
+/// a rejected ack.
+pub fn stub_router(log: StubLog) -> Router {
+    Router::new()
+        .route("/runner/{endpoint}", post(stub_command))
+        .with_state(log)
+}
+
+async fn stub_command(
+    State(log): State<StubLog>,
+    Path(endpoint): Path<String>,
+    Json(body): Json<Value>,
+) -> (StatusCode, Json<RunnerAck>) {
+    let request = match RunnerRequest::decode(&format!("runner/{endpoint}"), body) {
+        Ok(request) if request.protocol() == PROTOCOL_VERSION => request,
+        Ok(request) => {
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(RunnerAck::rejected(format!(
+                    "unsupported protocol {} (runner speaks {PROTOCOL_VERSION})",
+                    request.protocol()
+                ))),
+            );
+        }
+        Err(err) => {
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(RunnerAck::rejected(err.to_string())),
+            );
+        }
+    };
+    log.lock().push(request);
+    (StatusCode::OK, Json(RunnerAck::accepted()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn decodes_by_endpoint_and_rejects_unknown_fields() {
+        let body = json!({"protocol": 1, "message": "hello"});
+        let request = RunnerRequest::decode("/runner/emit", body.clone()).unwrap();
+        assert_eq!(request.path(), "runner/emit");
+        assert_eq!(request.body(), body);
+
+        let err = RunnerRequest::decode("runner/emit", json!({"protocol": 1, "msg": "hello"}))
+            .unwrap_err();
+        assert!(err.to_string().contains("invalid runner/emit command"));
+        assert!(RunnerRequest::decode("runner/other", json!({})).is_err());
+    }
+}
@@ -0,0 +1,421 @@
+//! Bounded command queue feeding the runner proxy loop. When the queue is full, `[runner.queue]`
+//! picks the overflow policy: wait for space up to a timeout, or shed the command. Either way a
+//! command that cannot be queued is recorded as a dead letter. [`run_workers`] drains the queue
+//! on a pool of worker threads keyed by each command's ordering key.
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use anyhow::{Result, anyhow, bail};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::mpsc::{self, error::SendTimeoutError, error::TrySendError};
+
+use crate::partitioning::Partitioner;
+
+/// Commands buffered per worker before the dispatcher waits (and the main queue fills up).
+const WORKER_BUFFER: usize = 32;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Wait up to `block_timeout_ms` for space, then dead-letter the command.
+    #[default]
+    Block,
+    /// Dead-letter the command immediately.
+    Shed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunnerQueueConfig {
+    #[serde(default = "default_capacity")]
+    pub capacity: usize,
+    #[serde(default)]
+    pub overflow: OverflowPolicy,
+    #[serde(default = "default_block_timeout_ms")]
+    pub block_timeout_ms: u64,
+    /// Most recent dead letters kept for `GET /runner/queue`.
+    #[serde(default = "default_dead_letter_limit")]
+    pub dead_letter_limit: usize,
+}
+
+impl Default for RunnerQueueConfig {
+    fn default() -> Self {
+        Self {
+            capacity: default_capacity(),
+            overflow: OverflowPolicy::default(),
+            block_timeout_ms: default_block_timeout_ms(),
+            dead_letter_limit: default_dead_letter_limit(),
+        }
+    }
+}
+
+fn default_capacity() -> usize {
+    1024
+}
+
+fn default_block_timeout_ms() -> u64 {
+    1000
+}
+
+fn default_dead_letter_limit() -> usize {
+    100
+}
+
+pub trait QueuedCommand: Send + 'static {
+    /// Commands with the same key run on the same worker, in submission order. Commands
+    /// without a key share one lane.
+    fn ordering_key(&self) -> Option<&str>;
+    /// What the command looks like in the dead-letter list.
+    fn kind(&self) -> &'static str;
+    /// Small JSON description; must not carry whole pack indexes or payload blobs.
+    fn summary(&self) -> Value;
+}
+
+/// A command that could not be queued.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeadLetter {
+    pub timestamp_ms: u64,
+    pub kind: String,
+    pub reason: String,
+    pub summary: Value,
+}
+
+/// Point-in-time queue metrics.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QueueStats {
+    pub capacity: usize,
+    pub depth: usize,
+    /// Deepest the queue has been since startup.
+    pub max_depth: usize,
+    pub overflow: OverflowPolicy,
+    pub enqueued: u64,
+    pub shed: u64,
+    pub timed_out: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    max_depth: AtomicUsize,
+    enqueued: AtomicU64,
+    shed: AtomicU64,
+    timed_out: AtomicU64,
+}
+
+/// Sending half of the runner queue; cheap to clone.
+pub struct RunnerQueue<T> {
+    tx: mpsc::Sender<T>,
+    overflow: OverflowPolicy,
+    block_timeout: Duration,
+    dead_letter_limit: usize,
+    counters: Arc<Counters>,
+    dead_letters: Arc<Mutex<VecDeque<DeadLetter>>>,
+}
+
+impl<T> Clone for RunnerQueue<T> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+            overflow: self.overflow,
+            block_timeout: self.block_timeout,
+            dead_letter_limit: self.dead_letter_limit,
+            counters: self.counters.clone(),
+            dead_letters: self.dead_letters.clone(),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for RunnerQueue<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RunnerQueue")
+            .field("capacity", &self.tx.max_capacity())
+            .field("overflow", &self.overflow)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: QueuedCommand> RunnerQueue<T> {
+    pub fn new(config: &RunnerQueueConfig) -> (Self, mpsc::Receiver<T>) {
+        let (tx, rx) = mpsc::channel(config.capacity.max(1));
+        let queue = Self {
+            tx,
+            overflow: config.overflow,
+            block_timeout: Duration::from_millis(config.block_timeout_ms),
+            dead_letter_limit: config.dead_letter_limit,
+            counters: Arc::default(),
+            dead_letters: Arc::default(),
+        };
+        (queue, rx)
+    }
+
+    /// Queue `command`, applying the overflow policy when the queue is full. Errors when the
+    /// command was dead-lettered or the proxy loop is gone.
+    pub async fn submit(&self, command: T) -> Result<()> {
+        let command = match self.tx.try_send(command) {
+            Ok(()) => {
+                self.record_enqueued();
+                return Ok(());
+            }
+            Err(TrySendError::Closed(_)) => bail!("runner proxy loop is not running"),
+            Err(TrySendError::Full(command)) => command,
+        };
+        match self.overflow {
+            OverflowPolicy::Shed => {
+                self.counters.shed.fetch_add(1, Ordering::Relaxed);
+                self.dead_letter(&command, "shed: runner queue full".into());
+                bail!("runner queue full; shed {} command", command.kind())
+            }
+            OverflowPolicy::Block => {
+                match self.tx.send_timeout(command, self.block_timeout).await {
+                    Ok(()) => {
+                        self.record_enqueued();
+                        Ok(())
+                    }
+                    Err(SendTimeoutError::Closed(_)) => bail!("runner proxy loop is not running"),
+                    Err(SendTimeoutError::Timeout(command)) => {
+                        self.counters.timed_out.fetch_add(1, Ordering::Relaxed);
+                        let reason = format!(
+                            "timed out after {}ms waiting for runner queue space",
+                            self.block_timeout.as_millis()
+                        );
+                        self.dead_letter(&command, reason.clone());
+                        bail!("{} command {reason}", command.kind())
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        QueueStats {
+            capacity: self.tx.max_capacity(),
+            depth: self.depth(),
+            max_depth: self.counters.max_depth.load(Ordering::Relaxed),
+            overflow: self.overflow,
+            enqueued: self.counters.enqueued.load(Ordering::Relaxed),
+            shed: self.counters.shed.load(Ordering::Relaxed),
+            timed_out: self.counters.timed_out.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Dead letters, oldest first.
+    pub fn dead_letters(&self) -> Vec<DeadLetter> {
+        self.dead_letters.lock().iter().cloned().collect()
+    }
+
+    fn depth(&self) -> usize {
+        self.tx.max_capacity() - self.tx.capacity()
+    }
+
+    fn record_enqueued(&self) {
+        self.counters.enqueued.fetch_add(1, Ordering::Relaxed);
+        self.counters
+            .max_depth
+            .fetch_max(self.depth(), Ordering::Relaxed);
+    }
+
+    fn dead_letter(&self, command: &T, reason: String) {
+        if self.dead_letter_limit == 0 {
+            return;
+        }
+        let mut letters = self.dead_letters.lock();
+        if letters.len() == self.dead_letter_limit {
+            letters.pop_front();
+        }
+        letters.push_back(DeadLetter {
+            timestamp_ms: crate::now_millis(),
+            kind: command.kind().to_string(),
+            reason,
+            summary: command.summary(),
+        });
+    }
+}
+
+/// Drain `rx` on `workers` blocking threads, calling `handle(worker, command)`. Each command goes
+/// to the worker picked by [`worker_for`], so ordering holds per key while different keys run in
+/// parallel. Returns once `rx` is closed and every worker has finished, or with an error as soon
+/// as a command is routed to a worker that died (the command is lost); `rx` stays usable so a
+/// supervisor can start a fresh pool on it.
+pub async fn run_workers<T, F>(rx: &mut mpsc::Receiver<T>, workers: usize, handle: F) -> Result<()>
+where
+    T: QueuedCommand,
+    F: Fn(usize, T) + Send + Sync + 'static,
+{
+    let workers = workers.max(1);
+    let handle = Arc::new(handle);
+    let mut lanes = Vec::with_capacity(workers);
+    let mut threads = Vec::with_capacity(workers);
+    for worker in 0..workers {
+        let (tx, mut lane) = mpsc::channel::<T>(WORKER_BUFFER);
+        let handle = handle.clone();
+        threads.push(tokio::task::spawn_blocking(move || {
+            while let Some(command) = lane.blocking_recv() {
+                handle(worker, command);
+            }
+        }));
+        lanes.push(tx);
+    }
+    let mut result = Ok(());
+    while let Some(command) = rx.recv().await {
+        let worker = worker_for(command.ordering_key(), workers);
+        if let Err(err) = lanes[worker].send(command).await {
+            result = Err(anyhow!(
+                "runner worker {worker} stopped; dropped a {} command",
+                err.0.kind()
+            ));
+            break;
+        }
+    }
+    drop(lanes);
+    for (worker, thread) in threads.into_iter().enumerate() {
+        if let Err(err) = thread.await
+            && result.is_ok()
+        {
+            result = Err(anyhow!("runner worker {worker} failed: {err}"));
+        }
+    }
+    result
+}
+
+/// Stable worker index for an ordering key, placed by the shared [`Partitioner`] so a key keeps
+/// its worker across builds and resizing the pool moves as few keys as possible.
+pub fn worker_for(key: Option<&str>, workers: usize) -> usize {
+    let workers = u32::try_from(workers.max(1)).unwrap_or(u32::MAX);
+    let partitioner = Partitioner::new(workers).expect("at least one worker");
+    partitioner.partition_of(key.unwrap_or_default()) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    struct Ping(u32);
+
+    impl QueuedCommand for Ping {
+        fn ordering_key(&self) -> Option<&str> {
+            None
+        }
+
+        fn kind(&self) -> &'static str {
+            "ping"
+        }
+
+        fn summary(&self) -> Value {
+            json!({ "n": self.0 })
+        }
+    }
+
+    fn config(overflow: OverflowPolicy) -> RunnerQueueConfig {
+        RunnerQueueConfig {
+            capacity: 2,
+            overflow,
+            block_timeout_ms: 20,
+            dead_letter_limit: 2,
+        }
+    }
+
+    #[tokio::test]
+    async fn shed_policy_dead_letters_overflow_and_tracks_depth() {
+        let (queue, mut rx) = RunnerQueue::new(&config(OverflowPolicy::Shed));
+        for n in 0..5 {
+            let result = queue.submit(Ping(n)).await;
+            assert_eq!(result.is_ok(), n < 2, "submit {n}");
+        }
+        let stats = queue.stats();
+        assert_eq!((stats.depth, stats.max_depth), (2, 2));
+        assert_eq!((stats.enqueued, stats.shed, stats.timed_out), (2, 3, 0));
+
+        let letters = queue.dead_letters();
+        let summaries: Vec<&Value> = letters.iter().map(|letter| &letter.summary).collect();
+        assert_eq!(summaries, vec![&json!({"n": 3}), &json!({"n": 4})]);
+        assert!(letters[0].reason.starts_with("shed"));
+
+        assert_eq!(rx.recv().await.map(|ping| ping.0), Some(0));
+        assert_eq!(queue.stats().depth, 1);
+        queue.submit(Ping(5)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn block_policy_waits_for_space_then_times_out() {
+        let (queue, mut rx) = RunnerQueue::new(&config(OverflowPolicy::Block));
+        queue.submit(Ping(0)).await.unwrap();
+        queue.submit(Ping(1)).await.unwrap();
+
+        let consumer = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            let first = rx.recv().await.map(|ping| ping.0);
+            (first, rx)
+        });
+        queue.submit(Ping(2)).await.unwrap();
+        let (first, _rx) = consumer.await.unwrap();
+        assert_eq!(first, Some(0));
+
+        let err = queue.submit(Ping(3)).await.unwrap_err();
+        assert!(err.to_string().contains("timed out after 20ms"), "{err}");
+        let stats = queue.stats();
+        assert_eq!((stats.enqueued, stats.timed_out), (3, 1));
+        assert_eq!(queue.dead_letters()[0].kind, "ping");
+    }
+
+    struct Job {
+        tenant: String,
+        seq: u32,
+    }
+
+    impl QueuedCommand for Job {
+        fn ordering_key(&self) -> Option<&str> {
+            Some(&self.tenant)
+        }
+
+        fn kind(&self) -> &'static str {
+            "job"
+        }
+
+        fn summary(&self) -> Value {
+            Value::Null
+        }
+    }
+
+    #[tokio::test]
+    async fn workers_keep_per_tenant_order_and_spread_tenants() {
+        let tenants = ["acme", "globex", "initech", "umbrella", "hooli", "dev"];
+        let (tx, mut rx) = mpsc::channel(8);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let recorder = seen.clone();
+        let pool = tokio::spawn(async move {
+            run_workers(&mut rx, 4, move |worker, job: Job| {
+                recorder.lock().push((worker, job.tenant, job.seq));
+            })
+            .await
+        });
+        for seq in 0..50 {
+            for tenant in tenants {
+                let tenant = tenant.to_string();
+                tx.send(Job { tenant, seq }).await.unwrap();
+            }
+        }
+        drop(tx);
+        pool.await.unwrap().unwrap();
+
+        let seen = seen.lock();
+        assert_eq!(seen.len(), 50 * tenants.len());
+        for tenant in tenants {
+            let runs: Vec<_> = seen.iter().filter(|(_, t, _)| t == tenant).collect();
+            let order: Vec<u32> = runs.iter().map(|(_, _, seq)| *seq).collect();
+            assert_eq!(order, (0..50).collect::<Vec<_>>(), "{tenant} out of order");
+            let worker = worker_for(Some(tenant), 4);
+            assert!(runs.iter().all(|(w, _, _)| *w == worker), "{tenant} moved");
+        }
+        let workers: std::collections::BTreeSet<usize> =
+            seen.iter().map(|(worker, _, _)| *worker).collect();
+        assert!(workers.len() > 1, "all tenants hashed to one worker");
+    }
+}
@@ -0,0 +1,204 @@
+//! Schedule recorded runner events for `runner replay`: events are ordered by their original
+//! timestamp, rebased so the first one fires at the start of the replay, compressed by the
+//! replay speed and moved to other tenants by `--remap-tenant` rules.
+
+use std::{collections::BTreeMap, time::Duration};
+
+use anyhow::{Result, anyhow, bail};
+
+use crate::RunnerEvent;
+use crate::runner_import::RUNNER_SOURCE;
+
+/// How fast recorded gaps are replayed: `10x` runs ten times faster, `max` sends back to back.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplaySpeed {
+    Factor(f64),
+    Max,
+}
+
+impl std::str::FromStr for ReplaySpeed {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        let raw = raw.trim();
+        if raw.eq_ignore_ascii_case("max") {
+            return Ok(Self::Max);
+        }
+        let factor: f64 = raw
+            .strip_suffix(['x', 'X'])
+            .unwrap_or(raw)
+            .parse()
+            .map_err(|_| anyhow!("invalid speed {raw:?}; use e.g. 1x, 10x, 0.5x or max"))?;
+        if !factor.is_finite() || factor <= 0.0 {
+            bail!("speed must be greater than zero, got {raw:?}");
+        }
+        Ok(Self::Factor(factor))
+    }
+}
+
+/// `--remap-tenant from=to` rules; unmatched tenants are replayed unchanged.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TenantRemap(BTreeMap<String, String>);
+
+impl TenantRemap {
+    pub fn parse(rules: &[String]) -> Result<Self> {
+        let mut map = BTreeMap::new();
+        for rule in rules {
+            let (from, to) = rule
+                .split_once('=')
+                .map(|(from, to)| (from.trim(), to.trim()))
+                .filter(|(from, to)| !from.is_empty() && !to.is_empty())
+                .ok_or_else(|| anyhow!("invalid tenant remap {rule:?}; expected from=to"))?;
+            if map.insert(from.to_string(), to.to_string()).is_some() {
+                bail!("tenant {from} is remapped more than once");
+            }
+        }
+        Ok(Self(map))
+    }
+
+    pub fn apply(&self, tenant: Option<String>) -> Option<String> {
+        tenant.map(|tenant| self.0.get(&tenant).cloned().unwrap_or(tenant))
+    }
+}
+
+/// One event of the replay, due `offset` after the replay starts.
+#[derive(Debug, Clone)]
+pub struct ScheduledEvent {
+    pub offset: Duration,
+    pub original_timestamp_ms: u64,
+    pub event: RunnerEvent,
+}
+
+#[derive(Debug, Default)]
+pub struct ReplayPlan {
+    pub events: Vec<ScheduledEvent>,
+    /// Events recorded from the runner's own log (`runner import`). They describe runner
+    /// output rather than traffic into the bridge, so they are not re-emitted.
+    pub skipped: usize,
+}
+
+impl ReplayPlan {
+    pub fn new(mut events: Vec<RunnerEvent>, speed: ReplaySpeed, remap: &TenantRemap) -> Self {
+        let before = events.len();
+        events.retain(|event| {
+            event
+                .result
+                .get("source")
+                .and_then(serde_json::Value::as_str)
+                != Some(RUNNER_SOURCE)
+        });
+        let skipped = before - events.len();
+        events.sort_by_key(|event| event.timestamp_ms);
+        let first = events.first().map_or(0, |event| event.timestamp_ms);
+        let events = events
+            .into_iter()
+            .map(|mut event| {
+                let gap = Duration::from_millis(event.timestamp_ms - first);
+                let offset = match speed {
+                    ReplaySpeed::Factor(factor) => gap.div_f64(factor),
+                    ReplaySpeed::Max => Duration::ZERO,
+                };
+                event.tenant = remap.apply(event.tenant.take());
+                ScheduledEvent {
+                    offset,
+                    original_timestamp_ms: event.timestamp_ms,
+                    event,
+                }
+            })
+            .collect();
+        Self { events, skipped }
+    }
+
+    /// Time from the first to the last emit.
+    pub fn duration(&self) -> Duration {
+        self.events
+            .last()
+            .map_or(Duration::ZERO, |last| last.offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+
+    fn event(timestamp_ms: u64, tenant: &str, result: Value) -> RunnerEvent {
+        RunnerEvent {
+            timestamp_ms,
+            flow: "chat".into(),
+            tenant: Some(tenant.into()),
+            team: None,
+            user: None,
+            payload: json!({"text": "hi"}),
+            result,
+            sequence: None,
+            instance: None,
+        }
+    }
+
+    #[test]
+    fn parses_speed_and_remap_rules() {
+        assert_eq!(
+            "10x".parse::<ReplaySpeed>().unwrap(),
+            ReplaySpeed::Factor(10.0)
+        );
+        assert_eq!(
+            "0.5".parse::<ReplaySpeed>().unwrap(),
+            ReplaySpeed::Factor(0.5)
+        );
+        assert_eq!("MAX".parse::<ReplaySpeed>().unwrap(), ReplaySpeed::Max);
+        assert!("0x".parse::<ReplaySpeed>().is_err());
+        assert!("fast".parse::<ReplaySpeed>().is_err());
+
+        let remap = TenantRemap::parse(&["dev=staging".into()]).unwrap();
+        assert_eq!(remap.apply(Some("dev".into())).as_deref(), Some("staging"));
+        assert_eq!(remap.apply(Some("prod".into())).as_deref(), Some("prod"));
+        assert_eq!(remap.apply(None), None);
+        assert!(TenantRemap::parse(&["dev".into()]).is_err());
+        assert!(TenantRemap::parse(&["dev=a".into(), "dev=b".into()]).is_err());
+    }
+
+    #[test]
+    fn rebases_sorts_and_compresses_recorded_gaps() {
+        let remap = TenantRemap::parse(&["dev=staging".into()]).unwrap();
+        let recorded = vec![
+            event(21_000, "dev", json!({"status": "ok"})),
+            event(1_000, "dev", json!({"status": "ok"})),
+            event(
+                5_000,
+                "dev",
+                json!({"status": "ok", "source": RUNNER_SOURCE}),
+            ),
+            event(11_000, "prod", json!({"status": "error"})),
+        ];
+        let plan = ReplayPlan::new(recorded, ReplaySpeed::Factor(10.0), &remap);
+        assert_eq!(plan.skipped, 1);
+        let schedule: Vec<_> = plan
+            .events
+            .iter()
+            .map(|scheduled| {
+                (
+                    scheduled.offset.as_millis(),
+                    scheduled.original_timestamp_ms,
+                    scheduled.event.tenant.clone().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            schedule,
+            vec![
+                (0, 1_000, "staging".to_string()),
+                (1_000, 11_000, "prod".to_string()),
+                (2_000, 21_000, "staging".to_string()),
+            ]
+        );
+        assert_eq!(plan.duration(), Duration::from_secs(2));
+
+        let plan = ReplayPlan::new(
+            vec![event(1, "dev", Value::Null), event(9, "dev", Value::Null)],
+            ReplaySpeed::Max,
+            &TenantRemap::default(),
+        );
+        assert_eq!(plan.duration(), Duration::ZERO);
+    }
+}
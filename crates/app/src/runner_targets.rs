@@ -0,0 +1,216 @@
+//! `[[runner.targets]]`: named runners the proxy forwards session turns to. A session is pinned
+//! to one target when it is parked (`SessionRecord.runner_target`) and its resume is forwarded
+//! there, so every turn of a conversation reaches the same runner. A target whose forward fails
+//! is marked unhealthy until a forward to it succeeds again; a session pinned to an unhealthy or
+//! removed target is re-pinned on its next write or resume, and a [`REPIN_FLOW`] event records
+//! the move. Without targets the single `RUNNER_PROXY_URL` runner is used and nothing is pinned.
+
+use std::{collections::BTreeSet, sync::Arc};
+
+use anyhow::{Result, bail};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::RunnerEvent;
+use crate::partitioning::Partitioner;
+
+/// Flow name of the runner event recorded when a session moves to another target.
+pub const REPIN_FLOW: &str = "runner.repin";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunnerTargetConfig {
+    /// Stable name recorded on pinned sessions; renaming a target re-pins its sessions.
+    pub name: String,
+    /// Base URL of the runner's protocol endpoints.
+    pub url: String,
+}
+
+/// Where a session's turns go: `target`, plus the target it was moved away from, if any.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pin {
+    pub target: String,
+    pub url: String,
+    pub repinned_from: Option<String>,
+}
+
+impl Pin {
+    /// The [`REPIN_FLOW`] event for a moved session; `None` when it kept its target.
+    pub fn repin_event(&self, key: &str, tenant: &str, now_ms: u64) -> Option<RunnerEvent> {
+        let from = self.repinned_from.as_ref()?;
+        Some(RunnerEvent {
+            timestamp_ms: now_ms,
+            flow: REPIN_FLOW.to_string(),
+            tenant: Some(tenant.to_string()),
+            team: None,
+            user: None,
+            payload: json!({ "session": key, "from": from, "to": self.target }),
+            result: json!({ "status": "repinned" }),
+            sequence: None,
+            instance: None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetStatus {
+    pub name: String,
+    pub url: String,
+    pub healthy: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RunnerTargets {
+    targets: Arc<Vec<RunnerTargetConfig>>,
+    unhealthy: Arc<Mutex<BTreeSet<String>>>,
+}
+
+impl RunnerTargets {
+    pub fn new(targets: &[RunnerTargetConfig]) -> Result<Self> {
+        let mut names = BTreeSet::new();
+        for target in targets {
+            if target.name.trim().is_empty() {
+                bail!("runner target names must not be empty");
+            }
+            if !names.insert(target.name.as_str()) {
+                bail!("runner target {:?} is configured twice", target.name);
+            }
+        }
+        Ok(Self {
+            targets: Arc::new(targets.to_vec()),
+            unhealthy: Arc::default(),
+        })
+    }
+
+    pub fn url(&self, name: &str) -> Option<&str> {
+        self.targets
+            .iter()
+            .find(|target| target.name == name)
+            .map(|target| target.url.as_str())
+    }
+
+    pub fn is_healthy(&self, name: &str) -> bool {
+        !self.unhealthy.lock().contains(name)
+    }
+
+    /// Record the outcome of a forward to `name`. Returns true when its health changed.
+    pub fn mark(&self, name: &str, healthy: bool) -> bool {
+        let mut unhealthy = self.unhealthy.lock();
+        if healthy {
+            unhealthy.remove(name)
+        } else {
+            unhealthy.insert(name.to_string())
+        }
+    }
+
+    pub fn statuses(&self) -> Vec<TargetStatus> {
+        self.targets
+            .iter()
+            .map(|target| TargetStatus {
+                name: target.name.clone(),
+                url: target.url.clone(),
+                healthy: self.is_healthy(&target.name),
+            })
+            .collect()
+    }
+
+    /// Keep `current` while it is configured and healthy, otherwise place `key` on a healthy
+    /// target (any target when none is healthy). `None` when no targets are configured.
+    pub fn pin(&self, current: Option<&str>, key: &str) -> Option<Pin> {
+        if let Some(name) = current
+            && let Some(url) = self.url(name)
+            && self.is_healthy(name)
+        {
+            return Some(Pin {
+                target: name.to_string(),
+                url: url.to_string(),
+                repinned_from: None,
+            });
+        }
+        let healthy: Vec<_> = self
+            .targets
+            .iter()
+            .filter(|target| self.is_healthy(&target.name))
+            .collect();
+        let candidates = if healthy.is_empty() {
+            self.targets.iter().collect()
+        } else {
+            healthy
+        };
+        let partitioner = Partitioner::new(u32::try_from(candidates.len()).ok()?).ok()?;
+        let chosen = candidates[partitioner.partition_of(key) as usize];
+        Some(Pin {
+            target: chosen.name.clone(),
+            url: chosen.url.clone(),
+            repinned_from: current
+                .filter(|name| *name != chosen.name)
+                .map(str::to_string),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn targets() -> RunnerTargets {
+        RunnerTargets::new(&["a", "b", "c"].map(|name| RunnerTargetConfig {
+            name: name.into(),
+            url: format!("http://runner-{name}"),
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn sessions_keep_a_healthy_target_and_move_off_an_unhealthy_one() {
+        let targets = targets();
+        let first = targets.pin(None, "session-1").unwrap();
+        assert_eq!(first.repinned_from, None);
+        assert_eq!(targets.pin(None, "session-1"), Some(first.clone()));
+        assert_eq!(
+            targets.pin(Some(&first.target), "session-1"),
+            Some(first.clone())
+        );
+
+        assert!(targets.mark(&first.target, false));
+        assert!(!targets.mark(&first.target, false));
+        let moved = targets.pin(Some(&first.target), "session-1").unwrap();
+        assert_ne!(moved.target, first.target);
+        assert_eq!(moved.repinned_from.as_deref(), Some(first.target.as_str()));
+        let event = moved.repin_event("session-1", "acme", 7).unwrap();
+        assert_eq!(event.flow, REPIN_FLOW);
+        assert_eq!(event.payload["from"], first.target.as_str());
+        assert_eq!(event.payload["to"], moved.target.as_str());
+
+        // Recovery does not move the session back; it stays where it was re-pinned.
+        assert!(targets.mark(&first.target, true));
+        let kept = targets.pin(Some(&moved.target), "session-1").unwrap();
+        assert_eq!(kept.target, moved.target);
+        assert!(kept.repin_event("session-1", "acme", 7).is_none());
+    }
+
+    #[test]
+    fn removed_targets_repin_and_no_targets_means_no_pin() {
+        let targets = targets();
+        let pin = targets.pin(Some("retired"), "session-1").unwrap();
+        assert_eq!(pin.repinned_from.as_deref(), Some("retired"));
+        for name in ["a", "b", "c"] {
+            targets.mark(name, false);
+        }
+        assert!(
+            targets.pin(None, "session-1").is_some(),
+            "all down still pins"
+        );
+
+        assert!(
+            RunnerTargets::default()
+                .pin(Some("a"), "session-1")
+                .is_none()
+        );
+        let twice = RunnerTargetConfig {
+            name: "a".into(),
+            url: "http://runner-a".into(),
+        };
+        assert!(RunnerTargets::new(&[twice.clone(), twice]).is_err());
+    }
+}
@@ -8,6 +8,7 @@ use serde_json::{Value, json};
 use std::collections::HashMap;
 
 use crate::harness::TestEnv;
+use crate::trace_context::TraceContext;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Scenario {
@@ -40,12 +41,19 @@ pub enum Step {
         actual: Value,
         expected: Value,
     },
+    /// Fails unless the last message awaited on `subject` carried this scenario's trace id.
+    AssertTraceContinuity {
+        subject: String,
+    },
 }
 
 pub struct ScenarioRunner {
     nats_url: String,
     observations: PathBuf,
     subscribers: HashMap<String, async_nats::Subscriber>,
+    /// Root context every `NatsPublish` continues with a child span.
+    trace: TraceContext,
+    received_traces: HashMap<String, Option<TraceContext>>,
 }
 
 impl ScenarioRunner {
@@ -55,9 +63,15 @@ impl ScenarioRunner {
             nats_url: env.nats_url(),
             observations,
             subscribers: HashMap::new(),
+            trace: TraceContext::new_root(),
+            received_traces: HashMap::new(),
         })
     }
 
+    pub fn trace(&self) -> &TraceContext {
+        &self.trace
+    }
+
     pub async fn run(&mut self, scenario: &Scenario) -> Result<()> {
         let mut nats: Option<Client> = None;
         for step in &scenario.steps {
@@ -69,11 +83,14 @@ impl ScenarioRunner {
                         self.subscribers.insert(subject.clone(), sub);
                     }
                     let bytes = serde_json::to_vec(payload)?;
-                    client.publish(subject.clone(), bytes.into()).await?;
+                    let trace = self.trace.child();
+                    client
+                        .publish_with_headers(subject.clone(), trace.headers(), bytes.into())
+                        .await?;
                     client.flush().await?;
                     self.record(
                         "nats_publish",
-                        json!({"subject": subject, "payload": payload}),
+                        json!({"subject": subject, "payload": payload, "traceparent": trace.to_string()}),
                     )?;
                 }
                 Step::AwaitNats {
@@ -104,10 +121,16 @@ impl ScenarioRunner {
                     {
                         bail!("awaited NATS payload did not match expected");
                     }
+                    let trace = TraceContext::extract(msg.headers.as_ref());
                     self.record(
                         "await_nats",
-                        json!({"subject": subject, "payload": payload_val}),
+                        json!({
+                            "subject": subject,
+                            "payload": payload_val,
+                            "traceparent": trace.as_ref().map(ToString::to_string),
+                        }),
                     )?;
+                    self.received_traces.insert(subject.clone(), trace);
                 }
                 Step::AssertJson { actual, expected } => {
                     if actual != expected {
@@ -118,6 +141,26 @@ impl ScenarioRunner {
                         json!({"actual": actual, "expected": expected}),
                     )?;
                 }
+                Step::AssertTraceContinuity { subject } => {
+                    let received = self
+                        .received_traces
+                        .get(subject)
+                        .ok_or_else(|| anyhow::anyhow!("no message awaited on {subject}"))?;
+                    let Some(received) = received else {
+                        bail!("message on {subject} carried no traceparent");
+                    };
+                    if received.trace_id != self.trace.trace_id {
+                        bail!(
+                            "trace id changed on {subject}: expected {} got {}",
+                            self.trace.trace_id,
+                            received.trace_id
+                        );
+                    }
+                    self.record(
+                        "assert_trace_continuity",
+                        json!({"subject": subject, "trace_id": received.trace_id}),
+                    )?;
+                }
                 Step::InstallPack { pack_id } => {
                     self.record(
                         "install_pack",
@@ -128,11 +171,7 @@ impl ScenarioRunner {
                     self.record("start_service", json!({"name": name, "status": "recorded"}))?;
                 }
                 Step::HttpPost { url, body } => {
-                    let resp = ureq::post(url).send_json(body.clone());
-                    let (status, text) = match resp {
-                        Ok(r) => (r.status().as_u16() as i32, String::new()),
-                        Err(err) => (0, err.to_string()),
-                    };
+                    let (status, text) = http_post(url.clone(), body.clone()).await;
                     self.record(
                         "http_post",
                         json!({"url": url, "body": body, "status": status, "response": text}),
@@ -169,3 +208,25 @@ impl ScenarioRunner {
         Ok(())
     }
 }
+
+/// Timeout for `http_post` steps.
+const HTTP_POST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Runs a scenario `http_post` on the blocking pool so a slow endpoint cannot stall the
+/// runtime driving NATS subscriptions. Returns `(status, error)`; status is `0` on failure.
+async fn http_post(url: String, body: Value) -> (i32, String) {
+    let outcome = tokio::task::spawn_blocking(move || {
+        let config = ureq::Agent::config_builder()
+            .timeout_global(Some(HTTP_POST_TIMEOUT))
+            .build();
+        ureq::Agent::new_with_config(config)
+            .post(&url)
+            .send_json(body)
+    })
+    .await;
+    match outcome {
+        Ok(Ok(response)) => (response.status().as_u16() as i32, String::new()),
+        Ok(Err(err)) => (0, err.to_string()),
+        Err(err) => (0, format!("http_post task failed: {err}")),
+    }
+}
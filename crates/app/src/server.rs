@@ -0,0 +1,13652 @@
+//! The `greentic-integration` CLI and HTTP server: configuration, app state, routes and
+//! handlers, and the commands `main.rs` dispatches through [`run`].
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    convert::Infallible,
+    fs,
+    net::SocketAddr,
+    sync::{Arc, OnceLock},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use axum::{
+    Extension, Json, Router,
+    body::Bytes,
+    extract::{Path, Query},
+    http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode, header, header::AUTHORIZATION},
+    middleware,
+    response::{
+        IntoResponse, Response,
+        sse::{Event as SseEvent, KeepAlive, Sse},
+    },
+    routing::{any, get, post},
+};
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use directories::ProjectDirs;
+use figment::{
+    Figment,
+    providers::{Env, Format, Serialized, Toml},
+};
+use futures::StreamExt;
+use greentic_integration_client::{
+    BridgeClient, ClientOptions, DryRunPreview, EmitRequest, ResumeRequest, SessionQuery,
+};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher, recommended_watcher};
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+use tokio::{
+    net::TcpListener,
+    signal,
+    sync::{broadcast, mpsc},
+    task::JoinHandle,
+};
+use tracing::{Instrument, debug, error, info, trace, warn};
+use uuid::Uuid;
+
+use crate::annotations::{Annotation, AnnotationReport, line_of};
+use crate::anomaly::{AnomalyConfig, AnomalyDetector};
+use crate::api_error::ApiError;
+use crate::chat_token::{CHAT_TOKEN_PREFIX, ChatGrant, ChatTokens, DevChatConfig};
+use crate::cluster::{Cluster, ClusterConfig, ClusterStatus};
+#[cfg(feature = "components")]
+use crate::components;
+use crate::context_schema::{load_context_schemas, load_schema_map, validate_context};
+use crate::debugger::{Breakpoint, DebugConfig, Debugger, Resume};
+use crate::deployment::{
+    ChannelPlan, DeploymentPlan, MessagingPlan, MessagingSubjectPlan, RunnerPlan, TelemetryPlan,
+};
+use crate::email_ingress::{EmailConfig, EmailIngress, EmailRoute, InboundEmail};
+use crate::event_policy::{EventPolicy, EventPolicyConfig};
+use crate::event_retention::{EventRetention, EventRetentionConfig, preview_prune};
+use crate::event_schema::{SchemaRegistry, SharedSchemaRegistry};
+use crate::event_store::{RunnerEventStore, SequenceKey, SqliteRunnerEventStore};
+use crate::health_history::{HealthConfig, HealthHistory, HealthReport, HealthTargets, run_checks};
+use crate::interpolate::{DirSecretStore, Interpolator, SecretStore};
+use crate::listeners::{Listeners, ListenersConfig, LocalPeer};
+use crate::network::{NetworkPolicy, OutboundHttp};
+use crate::outbox::{Outbox, OutboxConfig, OutboxStatus};
+use crate::pack_assets::{
+    ASSETS_DIR, PackAsset, content_type_for, discover_assets, etag_for, is_safe_asset_path,
+};
+use crate::pack_generations::{GenerationsReport, PackGenerations, pinned_sessions};
+use crate::pack_history::{PackChange, PackContents, PackHistory};
+use crate::pack_routes::{ExtensionRoute, NatsForwarder, RequestVars, RouteMatch};
+use crate::pack_sandbox::SandboxConfig;
+use crate::pack_validate::{InvalidPack, ManifestViolation};
+use crate::panic_guard::{PanicLog, catch_panics};
+use crate::partitioning::{Assignments, Partitioner, PartitioningConfig};
+use crate::path_safety::normalize_under_root;
+use crate::phone_channel::PhoneConfig;
+use crate::plan_cost::{PlanCost, PricingTable};
+use crate::plan_policy::PlanPolicy;
+use crate::provider_sandbox::{CredentialVault, SandboxProvider, VerifyReport};
+use crate::provider_smoke::{ParityRow, ProviderSmoke};
+use crate::providers::{ProviderConfig, Providers};
+use crate::runner_protocol::{
+    AckStatus, ActivityCommand, EmitCommand, PROTOCOL_VERSION, PackDescriptor, ReloadCommand,
+    RunnerAck, RunnerDefaults, RunnerRequest,
+};
+use crate::runner_queue::{
+    DeadLetter, QueueStats, QueuedCommand, RunnerQueue, RunnerQueueConfig, run_workers,
+};
+use crate::runner_targets::{Pin, RunnerTargetConfig, RunnerTargets, TargetStatus};
+use crate::session::{
+    CompactionThresholds, FileSessionStore, InMemorySessionStore, NatsKvSessionStore,
+    RawSessionAccess, RedisSessionStore, SessionFilter, SessionLease, SessionNudge,
+    SessionPageRequest, SessionRecord, SessionStore, SessionUpsert, SoftDeleteSessionStore,
+    SqliteSessionStore, purge_expired,
+};
+use crate::session_audit::{
+    ACTOR_HEADER, AuditedSessionStore, FileSessionAuditLog, SessionAuditLog, SqliteSessionAuditLog,
+    as_actor,
+};
+use crate::session_fsck::{FsckOptions, run_fsck};
+use crate::session_metrics::{MeteredSessionStore, SessionMetrics, SessionMetricsReport};
+use crate::session_nudge::NudgeConfig;
+use crate::session_quota::{SessionQuotas, TenantUsage};
+use crate::session_snapshot::{RestoreReport, SnapshotDir, SnapshotInfo};
+use crate::session_stats::{SessionStats, StoreHealth, collect_stats};
+use crate::session_stress::{StressOptions, run_stress};
+use crate::session_upgrade::{
+    ContextMigrations, PassthroughMigrator, SessionUpgrade, mark_outdated_sessions, upgrade_session,
+};
+use crate::single_flight::SingleFlight;
+use crate::sqlite::SqliteDb;
+use crate::supervisor::{Supervisor, SupervisorConfig, TaskRegistry};
+use crate::tenant_bootstrap::{BootstrapFile, BootstrapTargets, bootstrap as bootstrap_tenants};
+use crate::tenant_policy::{TenantConfig, reap_expired};
+use crate::trace_context::{TRACEPARENT, TraceContext};
+use crate::trace_sampling::{TailSampler, TelemetryConfig, trace_requests};
+use crate::traffic::{
+    TrafficAction, TrafficConfig, TrafficControl, TrafficPlanner, TrafficProfile, TrafficStats,
+    TrafficStatus,
+};
+use crate::transcript_store::{
+    Direction, FileTranscriptStore, InMemoryTranscriptStore, RedisTranscriptStore, TranscriptEntry,
+    TranscriptStore,
+};
+use crate::{
+    csv_export, event_export, event_schema, event_summary, pack_routes, pack_validate,
+    phone_channel, plan_bundle, plan_cost, provider_sandbox, provider_smoke, repl, runner_compare,
+    runner_import, runner_replay, session_nudge, session_transfer, soak, state_store,
+    trace_sampling, watch,
+};
+#[cfg(feature = "mini-runner")]
+use crate::{mini_runner, pack_generations, pack_sandbox};
+
+static APP_NAME: &str = "greentic-integration";
+static DEFAULT_CONFIG: Lazy<AppConfig> = Lazy::new(AppConfig::default);
+static TENANTS_FILE: &str = "tenants.toml";
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "greentic-integration",
+    version,
+    about = "Greentic integration harness CLI"
+)]
+struct Cli {
+    #[command(flatten)]
+    http: HttpArgs,
+    /// Print what a mutating command would change instead of changing it
+    #[arg(long, global = true)]
+    dry_run: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// Timeout and retries for CLI commands that call a running bridge (`--server`).
+#[derive(Args, Debug)]
+struct HttpArgs {
+    /// Per-request timeout in seconds
+    #[arg(long, global = true, default_value_t = 30)]
+    http_timeout: u64,
+    /// Retries for requests that are safe to repeat (see the client's retry rules)
+    #[arg(long, global = true, default_value_t = 2)]
+    http_retries: u32,
+}
+
+impl HttpArgs {
+    fn client_options(&self) -> ClientOptions {
+        ClientOptions {
+            timeout: Duration::from_secs(self.http_timeout),
+            retries: self.http_retries,
+            ..ClientOptions::default()
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run the integration HTTP/WebSocket bridge
+    Serve(ServeArgs),
+    /// Helper utilities for pack management
+    Packs {
+        #[command(subcommand)]
+        command: PacksCommand,
+    },
+    /// Session-store maintenance commands
+    Sessions {
+        #[command(subcommand)]
+        command: SessionCommand,
+    },
+    /// Runner proxy utilities
+    Runner {
+        #[command(subcommand)]
+        command: RunnerCommandCli,
+    },
+    /// Tenant provisioning
+    Tenants {
+        #[command(subcommand)]
+        command: TenantsCommand,
+    },
+    /// Sandbox credentials for real messaging providers
+    Providers {
+        #[command(subcommand)]
+        command: ProvidersCommand,
+    },
+    /// Checks for deployers consuming plans produced by this bridge
+    Deploy {
+        #[command(subcommand)]
+        command: DeployCommand,
+    },
+    /// Long-running load against a server
+    Loadtest {
+        #[command(subcommand)]
+        command: LoadtestCommand,
+    },
+    /// Interactive prompt for emitting, resuming and inspecting against a server
+    Repl(ReplArgs),
+    /// Print a shell completion script for every subcommand of this build
+    Completions(CompletionsArgs),
+    /// Generate man pages for the CLI and its subcommands
+    Mangen(MangenArgs),
+    /// Smoke-test component artifacts shipped inside packs
+    #[cfg(feature = "components")]
+    Components {
+        #[command(subcommand)]
+        command: ComponentsCommand,
+    },
+}
+
+#[derive(Args, Debug)]
+struct CompletionsArgs {
+    #[arg(value_enum)]
+    shell: clap_complete::Shell,
+}
+
+#[derive(Args, Debug)]
+struct MangenArgs {
+    /// Write one page per (sub)command into this directory instead of printing the top-level page
+    #[arg(long)]
+    out: Option<Utf8PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+enum DeployCommand {
+    /// Verify a bundle written by `packs plan --sign` before deploying its plan
+    VerifyPlanBundle(VerifyPlanBundleArgs),
+}
+
+#[derive(Args, Debug)]
+struct VerifyPlanBundleArgs {
+    /// Bundle directory holding plan.json, metadata.json and plan.sig
+    bundle: Utf8PathBuf,
+    /// Trusted Ed25519 public key (hex) the bundle must be signed with
+    #[arg(long)]
+    key: Utf8PathBuf,
+}
+
+#[derive(Subcommand, Debug)]
+enum LoadtestCommand {
+    /// Drive a steady workload for hours and check the server's invariants as it runs
+    Soak(SoakArgs),
+}
+
+#[derive(Args, Debug)]
+struct SoakArgs {
+    /// How long to run (`30m`, `8h`)
+    #[arg(long, default_value = "1h", value_parser = event_summary::parse_window)]
+    duration: Duration,
+    #[arg(long, value_enum, default_value = "chat")]
+    profile: soak::SoakProfile,
+    /// Operations per second
+    #[arg(long, default_value_t = 20)]
+    rate: u32,
+    /// Simulated users; the workload never holds more sessions open than this
+    #[arg(long, default_value_t = 50)]
+    users: usize,
+    #[arg(long, default_value = "soak")]
+    tenant: String,
+    /// How often the invariants are checked
+    #[arg(long, default_value = "1m", value_parser = event_summary::parse_window)]
+    check_every: Duration,
+    /// How often the checks so far are written to a checkpoint file
+    #[arg(long, default_value = "1h", value_parser = event_summary::parse_window)]
+    checkpoint_every: Duration,
+    /// Memory budget for the server process
+    #[arg(long, default_value_t = 512)]
+    max_rss_mb: u64,
+    /// p99 latency limit per check interval
+    #[arg(long, default_value_t = 500)]
+    max_p99_ms: u64,
+    /// Share of failed operations tolerated per check interval
+    #[arg(long, default_value_t = 1.0)]
+    max_error_percent: f64,
+    /// Directory for checkpoints and summary.json
+    #[arg(long, default_value = ".data/soak")]
+    out: Utf8PathBuf,
+    /// Stop at the first check that finds a violation
+    #[arg(long)]
+    fail_fast: bool,
+    /// Seed for the operation mix
+    #[arg(long, default_value_t = 42)]
+    seed: u64,
+    #[arg(long, default_value = "http://localhost:8080")]
+    server: String,
+}
+
+#[cfg(feature = "components")]
+#[derive(Subcommand, Debug)]
+enum ComponentsCommand {
+    /// Invoke a pack component once and print its output and emitted statuses
+    Invoke(ComponentInvokeArgs),
+}
+
+#[cfg(feature = "components")]
+#[derive(Args, Debug)]
+struct ComponentInvokeArgs {
+    /// Component id as declared in a pack manifest
+    #[arg(long)]
+    id: String,
+    /// JSON input passed to the component
+    #[arg(long, default_value = "{}")]
+    input: String,
+    /// Operation name passed to `invoke`
+    #[arg(long, default_value = "invoke")]
+    op: String,
+    /// Only look for the component in this pack
+    #[arg(long)]
+    pack_id: Option<String>,
+    /// Tenant to invoke as (defaults to config defaults)
+    #[arg(long)]
+    tenant: Option<String>,
+    /// Flow id reported in the execution context
+    #[arg(long, default_value = "components-invoke")]
+    flow_id: String,
+    /// Environment stamped on the plan served to deploy-plan components
+    #[arg(long, default_value = "dev")]
+    environment: String,
+}
+
+#[derive(Args, Debug)]
+struct ServeArgs {
+    /// Path to the configuration file (defaults to config/dev.toml)
+    #[arg(long, value_name = "PATH")]
+    config: Option<Utf8PathBuf>,
+    /// Enable pack hot-reload (dev only)
+    #[arg(long)]
+    watch: bool,
+    /// Refuse all outbound network access except localhost targets (hermetic runs)
+    #[arg(long)]
+    offline: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum PacksCommand {
+    /// Validate pack manifests under the configured packs root
+    Validate,
+    /// List available packs discovered under the configured root
+    List(PackListArgs),
+    /// Select the scenario suite for a targeted pass (filter by pack kind and tags)
+    Scenarios(ScenarioSuiteArgs),
+    /// Rebuild the pack index locally or via HTTP
+    Reload(ReloadArgs),
+    /// Infer a base deployment plan for a pack and print it
+    Plan(PlanArgs),
+    /// Write canonical plans for every discovered pack to fixtures/plans/<id>.json
+    PlanSnapshot(PlanSnapshotArgs),
+    /// Compare two plan files: which sections changed and what it does to the cost estimate
+    PlanDiff(PlanDiffArgs),
+    /// Play a pack scenario through the embedded runner and compare it with its golden file
+    #[cfg(feature = "mini-runner")]
+    RunScenario(RunScenarioArgs),
+}
+
+#[cfg(feature = "mini-runner")]
+#[derive(Args, Debug)]
+struct RunScenarioArgs {
+    /// Pack id to resolve from the pack index
+    #[arg(long)]
+    pack_id: String,
+    /// Scenario id from the pack manifest
+    #[arg(long)]
+    scenario: String,
+    /// Flow to drive (defaults to the flow named like the scenario, or the pack's only flow)
+    #[arg(long)]
+    flow: Option<String>,
+    /// Tenant to run as (defaults to config defaults)
+    #[arg(long)]
+    tenant: Option<String>,
+    /// Print the transcript without comparing it to the golden file
+    #[arg(long, default_value_t = false)]
+    no_golden: bool,
+    /// Locale for message templates (e.g. `de-CH`); falls back along `de` -> pack default
+    #[arg(long)]
+    locale: Option<String>,
+    /// Write the outcome as GitHub Checks annotations (JSON) to this file
+    #[arg(long)]
+    annotations: Option<Utf8PathBuf>,
+}
+
+#[derive(Args, Debug, Default)]
+struct PackListArgs {
+    #[arg(long)]
+    tenant: Option<String>,
+    #[arg(long)]
+    team: Option<String>,
+    #[arg(long)]
+    user: Option<String>,
+    /// Only packs of this kind (application, deployment, ...)
+    #[arg(long)]
+    kind: Option<String>,
+    /// Only packs carrying this tag (repeatable; all must match)
+    #[arg(long = "tag")]
+    tags: Vec<String>,
+}
+
+#[derive(Args, Debug, Default)]
+struct ScenarioSuiteArgs {
+    /// Only scenarios from packs of this kind
+    #[arg(long)]
+    kind: Option<String>,
+    /// Only scenarios tagged (on the scenario or its pack) with this tag; repeatable
+    #[arg(long = "tag")]
+    tags: Vec<String>,
+    /// Print the suite as JSON
+    #[arg(long, default_value_t = false)]
+    json: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum SessionCommand {
+    /// Remove matching sessions from the backing store
+    Purge(SessionPurgeArgs),
+    /// Resume a stored session (sends payload and clears session)
+    Resume(SessionResumeArgs),
+    /// List resumable sessions
+    List(SessionListArgs),
+    /// Show one session by key
+    Get(SessionGetArgs),
+    /// Check the backing store for inconsistencies (and repair them with --fix)
+    Fsck(SessionFsckArgs),
+    /// Fold the file backend's journal into its snapshot
+    Compact,
+    /// Show who wrote or removed a session, from the `[stores.audit]` log
+    History(SessionHistoryArgs),
+    /// Write the configured backend's sessions to a JSON lines file
+    Export(SessionExportArgs),
+    /// Load a `sessions export` file into the configured backend
+    Import(SessionImportArgs),
+    /// Copy every session from one backend to another and verify the copies
+    Migrate(SessionMigrateArgs),
+    /// Hammer the configured backend with a concurrent mixed workload
+    Stress(SessionStressArgs),
+    /// Show a live table of sessions as a server creates, updates and removes them
+    Watch(SessionWatchArgs),
+}
+
+#[derive(Args, Debug)]
+struct SessionGetArgs {
+    key: String,
+    #[arg(long, default_value = "http://localhost:8080")]
+    server: String,
+}
+
+#[derive(Args, Debug)]
+struct SessionWatchArgs {
+    #[arg(long)]
+    tenant: Option<String>,
+    #[arg(long)]
+    team: Option<String>,
+    #[arg(long)]
+    user: Option<String>,
+    /// Only show sessions currently in this flow
+    #[arg(long)]
+    flow: Option<String>,
+    #[arg(long, default_value = "http://localhost:8080")]
+    server: String,
+}
+
+#[derive(Args, Debug)]
+struct SessionStressArgs {
+    /// Number of worker threads
+    #[arg(long, default_value_t = 4)]
+    threads: usize,
+    /// Total operations across all threads
+    #[arg(long, default_value_t = 10_000)]
+    ops: usize,
+    /// Distinct session keys per thread
+    #[arg(long, default_value_t = 64)]
+    key_space: usize,
+    /// Seed for the deterministic operation mix
+    #[arg(long, default_value_t = 42)]
+    seed: u64,
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args, Debug)]
+struct SessionHistoryArgs {
+    /// Session key to show the history of
+    #[arg(long)]
+    key: String,
+    /// Print the entries as JSON
+    #[arg(long, default_value_t = false)]
+    json: bool,
+}
+
+#[derive(Args, Debug)]
+struct SessionExportArgs {
+    /// File to write, one session record per line
+    #[arg(long)]
+    out: Utf8PathBuf,
+    /// Only export sessions of this tenant
+    #[arg(long)]
+    tenant: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct SessionImportArgs {
+    /// File written by `sessions export`
+    #[arg(long = "in", value_name = "FILE")]
+    input: Utf8PathBuf,
+    /// Replace sessions the backend already holds a newer copy of
+    #[arg(long, default_value_t = false)]
+    overwrite: bool,
+}
+
+#[derive(Args, Debug)]
+struct SessionMigrateArgs {
+    /// Backend to copy sessions from
+    #[arg(long)]
+    from: StoreBackend,
+    /// Backend to copy sessions into
+    #[arg(long)]
+    to: StoreBackend,
+    /// Config whose `[stores.session]` describes the source (default: the usual config)
+    #[arg(long, value_name = "PATH")]
+    from_config: Option<Utf8PathBuf>,
+    /// Config whose `[stores.session]` describes the target (default: the usual config)
+    #[arg(long, value_name = "PATH")]
+    to_config: Option<Utf8PathBuf>,
+    /// Replace sessions the target already holds a newer copy of
+    #[arg(long, default_value_t = false)]
+    overwrite: bool,
+    /// Sessions copied between progress reports
+    #[arg(long, default_value_t = 500)]
+    batch: usize,
+}
+
+#[derive(Args, Debug)]
+struct SessionFsckArgs {
+    /// Rewrite the store with all detected issues repaired
+    #[arg(long)]
+    fix: bool,
+    /// Allowed clock skew for future `updated_at` timestamps, in seconds
+    #[arg(long, default_value_t = 300)]
+    max_skew_secs: u64,
+}
+
+#[derive(Subcommand, Debug)]
+enum TenantsCommand {
+    /// Create tenants, teams, secrets, pack overrides and demo sessions from a YAML file
+    Bootstrap(TenantBootstrapArgs),
+}
+
+#[derive(Args, Debug)]
+struct TenantBootstrapArgs {
+    /// YAML file listing the tenants to provision
+    #[arg(long, value_name = "PATH")]
+    file: Utf8PathBuf,
+    /// Configuration file whose stores, packs root and tenants file are used
+    #[arg(long, value_name = "PATH")]
+    config: Option<Utf8PathBuf>,
+    /// Also seed the `demo_sessions` of each tenant into the session store
+    #[arg(long)]
+    demo_sessions: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum ProvidersCommand {
+    /// Store sandbox credentials for a provider in the secret store
+    Register(ProviderRegisterArgs),
+    /// Post and delete a message in the sandbox channel to check the stored credentials
+    Verify(ProviderVerifyArgs),
+    /// Send a text, a card and a threaded reply through each provider with credentials
+    Smoke(ProviderSmokeArgs),
+}
+
+#[derive(Args, Debug)]
+struct ProviderRegisterArgs {
+    /// Provider to register (`slack` or `teams`)
+    #[arg(long)]
+    name: SandboxProvider,
+    /// Credential field as FIELD=VALUE, e.g. `--set BOT_TOKEN=xoxb-...` (repeatable)
+    #[arg(long = "set", value_name = "FIELD=VALUE")]
+    set: Vec<String>,
+    /// Also read every field from `SANDBOX_<PROVIDER>_<FIELD>` environment variables
+    #[arg(long)]
+    from_env: bool,
+    /// Configuration file whose `[packs].secrets_dir` holds the credentials
+    #[arg(long, value_name = "PATH")]
+    config: Option<Utf8PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct ProviderVerifyArgs {
+    /// Provider to verify (`slack` or `teams`)
+    #[arg(long)]
+    name: SandboxProvider,
+    /// Configuration file whose `[packs].secrets_dir` holds the credentials
+    #[arg(long, value_name = "PATH")]
+    config: Option<Utf8PathBuf>,
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args, Debug)]
+struct ProviderSmokeArgs {
+    /// Only these providers (repeatable); defaults to every provider with credentials
+    #[arg(long)]
+    name: Vec<SandboxProvider>,
+    /// Where request/response artifacts go (default `target/e2e/providers-smoke`)
+    #[arg(long, value_name = "DIR")]
+    artifacts: Option<Utf8PathBuf>,
+    /// Capability map whose reference provider claims are checked
+    #[arg(long, value_name = "PATH")]
+    capabilities: Option<Utf8PathBuf>,
+    /// Leave the posted messages in the sandbox channels
+    #[arg(long)]
+    keep_messages: bool,
+    /// Configuration file whose `[packs].secrets_dir` holds the credentials
+    #[arg(long, value_name = "PATH")]
+    config: Option<Utf8PathBuf>,
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args, Debug)]
+struct SessionPurgeArgs {
+    #[arg(long)]
+    tenant: Option<String>,
+    #[arg(long)]
+    team: Option<String>,
+    #[arg(long)]
+    user: Option<String>,
+    /// Only sessions not written for at least this long (`12h`, `7d`)
+    #[arg(
+        long,
+        value_name = "AGE",
+        value_parser = event_summary::parse_window,
+        conflicts_with = "updated_before"
+    )]
+    older_than: Option<Duration>,
+    /// Only sessions last written before this time (RFC 3339 or epoch milliseconds)
+    #[arg(long, value_name = "TIMESTAMP", value_parser = parse_timestamp_ms)]
+    updated_before: Option<u64>,
+}
+
+impl SessionPurgeArgs {
+    /// Update cutoff from `--older-than` (relative to `now_ms`) or `--updated-before`.
+    fn cutoff_ms(&self, now_ms: u64) -> Option<u64> {
+        self.older_than
+            .map(|age| now_ms.saturating_sub(age.as_millis() as u64))
+            .or(self.updated_before)
+    }
+}
+
+#[derive(Args, Debug)]
+struct PlanSnapshotArgs {
+    /// Compare with the existing snapshots instead of rewriting them; fails on drift
+    #[arg(long, default_value_t = false)]
+    check: bool,
+}
+
+#[derive(Args, Debug)]
+struct PlanArgs {
+    /// Environment name to stamp on the plan
+    #[arg(long, default_value = "dev")]
+    environment: String,
+    /// Tenant to use (defaults to config defaults)
+    #[arg(long)]
+    tenant: Option<String>,
+    /// Pack id to resolve from the pack index
+    #[arg(long)]
+    pack_id: Option<String>,
+    /// Pretty-print JSON output
+    #[arg(long, default_value_t = false)]
+    pretty: bool,
+    /// Fail with the violated rules when the plan breaks the plan policy
+    #[arg(long, default_value_t = false)]
+    enforce_policy: bool,
+    /// Plan policy file (defaults to plans/policy.yaml in the workspace)
+    #[arg(long, requires = "enforce_policy")]
+    policy: Option<Utf8PathBuf>,
+    /// Write a signed plan bundle instead of printing the plan
+    #[arg(long, default_value_t = false, requires = "key")]
+    sign: bool,
+    /// Ed25519 signing key (hex of the 32-byte seed)
+    #[arg(long, requires = "sign")]
+    key: Option<Utf8PathBuf>,
+    /// Directory the signed bundle is written to
+    #[arg(long, default_value = "plan-bundle", requires = "sign")]
+    out: Utf8PathBuf,
+    /// Annotate the plan with a resource/cost estimate under `extra.cost`
+    #[arg(long, default_value_t = false)]
+    cost: bool,
+    /// Pricing table (defaults to plans/pricing.yaml in the workspace, else built-in prices)
+    #[arg(long)]
+    pricing: Option<Utf8PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct PlanDiffArgs {
+    /// Plan before the change (e.g. the committed fixtures/plans/<id>.json)
+    base: Utf8PathBuf,
+    /// Plan after the change
+    head: Utf8PathBuf,
+    /// Pricing table (defaults to plans/pricing.yaml in the workspace, else built-in prices)
+    #[arg(long)]
+    pricing: Option<Utf8PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct SessionResumeArgs {
+    /// Resume this session instead of looking one up by --user.
+    #[arg(long, required_unless_present = "user")]
+    key: Option<String>,
+    /// Restart the flow at this node instead of its entry.
+    #[arg(long)]
+    node_id: Option<String>,
+    #[arg(long)]
+    tenant: Option<String>,
+    #[arg(long)]
+    team: Option<String>,
+    #[arg(long)]
+    user: Option<String>,
+    #[arg(long, value_name = "JSON")]
+    payload: Option<String>,
+    #[arg(long, default_value = "http://localhost:8080")]
+    server: String,
+}
+
+/// The bridge configuration `serve` reads (`config/dev.toml`); see `docs/APP_DESIGN.md`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppConfig {
+    #[serde(default)]
+    server: ServerConfig,
+    #[serde(default)]
+    packs: PackConfig,
+    #[serde(default)]
+    runner: RunnerConfig,
+    #[serde(default)]
+    stores: StoresConfig,
+    #[serde(default)]
+    sessions: SessionsConfig,
+    #[serde(default)]
+    defaults: SeedDefaults,
+    /// Tenant metadata and data policies (`[tenants.<id>]`), keyed by tenant id.
+    #[serde(default)]
+    tenants: BTreeMap<String, TenantConfig>,
+    /// Provider endpoints (`[providers.<name>]`), keyed by provider id.
+    #[serde(default)]
+    providers: BTreeMap<String, ProviderConfig>,
+    /// Inbound email channel (`[email]`): SMTP receiver and recipient routing rules.
+    #[serde(default)]
+    email: EmailConfig,
+    /// SMS/WhatsApp webhook routing rules (`[[phone.routes]]`).
+    #[serde(default)]
+    phone: PhoneConfig,
+    /// Instance identity and coordination between bridges sharing stores (`[cluster]`).
+    #[serde(default)]
+    cluster: ClusterConfig,
+    /// Tail-based sampling of request traces (`[telemetry.sampling]`).
+    #[serde(default)]
+    telemetry: TelemetryConfig,
+    /// Partition count for tenant/session sharding (`[partitioning]`).
+    #[serde(default)]
+    partitioning: PartitioningConfig,
+}
+
+impl AppConfig {
+    /// Load the configuration the way the CLI does: defaults, then `path` (or the default
+    /// config file), the tenants file next to it and `GREENTIC_*` environment overrides.
+    pub fn load(path: Option<&Utf8Path>) -> Result<Self> {
+        load_config(path.map(Utf8Path::to_path_buf).as_ref())
+    }
+
+    /// Parse `toml` over the defaults, without reading any file or the environment.
+    pub fn from_toml(toml: &str) -> Result<Self> {
+        Figment::from(Serialized::defaults(DEFAULT_CONFIG.clone()))
+            .merge(Toml::string(toml))
+            .extract()
+            .context("failed to parse greentic-integration configuration")
+    }
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            server: ServerConfig {
+                listen_addr: "0.0.0.0:8080".into(),
+                listeners: ListenersConfig::default(),
+                admin_token: None,
+                supervisor: SupervisorConfig::default(),
+                residency: None,
+                retention_interval_secs: default_retention_interval_secs(),
+                health: HealthConfig::default(),
+                dev_chat: DevChatConfig::default(),
+                traffic: TrafficConfig::default(),
+                debug: DebugConfig::default(),
+                outbox: OutboxConfig::default(),
+            },
+            packs: PackConfig {
+                root: Utf8PathBuf::from("packs"),
+                default_tenant: "dev".into(),
+                env: BTreeMap::new(),
+                secrets_dir: None,
+                retain_generations: default_retain_generations(),
+                drain_interval_secs: default_drain_interval_secs(),
+                watch_debounce_ms: default_watch_debounce_ms(),
+                watch_max_wait_ms: default_watch_max_wait_ms(),
+                max_depth: default_pack_max_depth(),
+            },
+            runner: RunnerConfig {
+                wasm_cache: Utf8PathBuf::from(".cache/wasm"),
+                nats_url: None,
+                environment: default_plan_environment(),
+                workers: default_runner_workers(),
+                queue: RunnerQueueConfig::default(),
+                event_policy: EventPolicyConfig::default(),
+                anomaly: AnomalyConfig::default(),
+                event_retention: EventRetentionConfig::default(),
+                sandbox: SandboxConfig::default(),
+                targets: Vec::new(),
+            },
+            stores: StoresConfig {
+                session: StoreConfig::file(default_session_store_path()),
+                state: StoreConfig::memory(),
+                transcript: StoreConfig::memory(),
+                events: StoreConfig::memory(),
+                audit: StoreConfig::memory(),
+            },
+            sessions: SessionsConfig::default(),
+            defaults: SeedDefaults::default(),
+            tenants: BTreeMap::new(),
+            providers: BTreeMap::new(),
+            email: EmailConfig::default(),
+            phone: PhoneConfig::default(),
+            cluster: ClusterConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            partitioning: PartitioningConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ServerConfig {
+    #[serde(default = "default_listen_addr")]
+    listen_addr: String,
+    /// Extra TCP addresses and a Unix domain socket to serve on (`[server.listeners]`).
+    #[serde(default)]
+    listeners: ListenersConfig,
+    /// Bearer token granting admin privileges (e.g. bypassing purge confirmation).
+    #[serde(default)]
+    admin_token: Option<String>,
+    /// Restart policy for background tasks (`[server.supervisor]`).
+    #[serde(default)]
+    supervisor: SupervisorConfig,
+    /// Data residency tag of this deployment, matched against `[tenants.<id>].residency`.
+    #[serde(default)]
+    residency: Option<String>,
+    /// How often tenant retention policies are enforced.
+    #[serde(default = "default_retention_interval_secs")]
+    retention_interval_secs: u64,
+    /// Periodic internal health checks reported on `/healthz/history` (`[server.health]`).
+    #[serde(default)]
+    health: HealthConfig,
+    /// Scoped token issuance for the dev chat UI (`[server.dev_chat]`).
+    #[serde(default)]
+    dev_chat: DevChatConfig,
+    /// Synthetic demo traffic behind `/dev/traffic` (`[server.traffic]`).
+    #[serde(default)]
+    traffic: TrafficConfig,
+    /// Flow breakpoints behind `/debug` (`[server.debug]`).
+    #[serde(default)]
+    debug: DebugConfig,
+    /// Durable provider sends behind `GET /outbox` (`[server.outbox]`).
+    #[serde(default)]
+    outbox: OutboxConfig,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            listen_addr: default_listen_addr(),
+            listeners: ListenersConfig::default(),
+            admin_token: None,
+            supervisor: SupervisorConfig::default(),
+            residency: None,
+            retention_interval_secs: default_retention_interval_secs(),
+            health: HealthConfig::default(),
+            dev_chat: DevChatConfig::default(),
+            traffic: TrafficConfig::default(),
+            debug: DebugConfig::default(),
+            outbox: OutboxConfig::default(),
+        }
+    }
+}
+
+fn default_retention_interval_secs() -> u64 {
+    3600
+}
+
+fn default_listen_addr() -> String {
+    "0.0.0.0:8080".into()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct PackConfig {
+    #[serde(default = "default_packs_root")]
+    root: Utf8PathBuf,
+    #[serde(default = "default_tenant")]
+    default_tenant: String,
+    /// Values for `${env:VAR}` placeholders when VAR is not set in the process environment.
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+    /// Directory of `${secret:KEY}` values, one file per key (relative to the workspace).
+    #[serde(default)]
+    secrets_dir: Option<Utf8PathBuf>,
+    /// Pack index generations kept resolvable after a reload, the current one included, so
+    /// sessions and flow runs started on an older one can finish against it.
+    #[serde(default = "default_retain_generations")]
+    retain_generations: usize,
+    /// How often superseded generations are checked for remaining activity.
+    #[serde(default = "default_drain_interval_secs")]
+    drain_interval_secs: u64,
+    /// `serve --watch` rebuilds the index once the pack root has been quiet this long.
+    #[serde(default = "default_watch_debounce_ms")]
+    watch_debounce_ms: u64,
+    /// ...or once changes have kept arriving for this long, so a steady stream of writes
+    /// cannot hold the reload off forever.
+    #[serde(default = "default_watch_max_wait_ms")]
+    watch_max_wait_ms: u64,
+    /// How many directory levels under `root` are searched for `pack.json`; `1` only finds
+    /// `<root>/<pack>/`, `2` also `<root>/<tenant>/<pack>/`.
+    #[serde(default = "default_pack_max_depth")]
+    max_depth: usize,
+}
+
+impl Default for PackConfig {
+    fn default() -> Self {
+        Self {
+            root: default_packs_root(),
+            default_tenant: default_tenant(),
+            env: BTreeMap::new(),
+            secrets_dir: None,
+            retain_generations: default_retain_generations(),
+            drain_interval_secs: default_drain_interval_secs(),
+            watch_debounce_ms: default_watch_debounce_ms(),
+            watch_max_wait_ms: default_watch_max_wait_ms(),
+            max_depth: default_pack_max_depth(),
+        }
+    }
+}
+
+fn default_pack_max_depth() -> usize {
+    3
+}
+
+fn default_retain_generations() -> usize {
+    2
+}
+
+fn default_drain_interval_secs() -> u64 {
+    10
+}
+
+fn default_watch_debounce_ms() -> u64 {
+    300
+}
+
+fn default_watch_max_wait_ms() -> u64 {
+    5_000
+}
+
+fn default_packs_root() -> Utf8PathBuf {
+    Utf8PathBuf::from("packs")
+}
+
+fn default_tenant() -> String {
+    "dev".into()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RunnerConfig {
+    #[serde(default = "default_wasm_cache")]
+    wasm_cache: Utf8PathBuf,
+    /// NATS server used by the embedded runner for `events.publish` (recorded only when unset).
+    #[serde(default)]
+    nats_url: Option<String>,
+    /// Environment stamped on plans served to deploy-plan components.
+    #[serde(default = "default_plan_environment")]
+    environment: String,
+    /// Threads processing runner commands; a tenant's commands always share one thread.
+    #[serde(default = "default_runner_workers")]
+    workers: usize,
+    /// Bounded queue in front of the runner proxy (`[runner.queue]`).
+    #[serde(default)]
+    queue: RunnerQueueConfig,
+    /// Sampling and scrubbing applied to recorded runner events (`[runner.event_policy]`).
+    #[serde(default)]
+    event_policy: EventPolicyConfig,
+    /// Analyzers over the recorded event stream (`[runner.anomaly]`).
+    #[serde(default)]
+    anomaly: AnomalyConfig,
+    /// Age and count limits on recorded events (`[runner.event_retention]`).
+    #[serde(default)]
+    event_retention: EventRetentionConfig,
+    /// Per-run isolation of embedded flow and scenario runs (`[runner.sandbox]`).
+    #[serde(default)]
+    sandbox: SandboxConfig,
+    /// Runners session turns are pinned to and forwarded to (`[[runner.targets]]`).
+    #[serde(default)]
+    targets: Vec<RunnerTargetConfig>,
+}
+
+impl Default for RunnerConfig {
+    fn default() -> Self {
+        Self {
+            wasm_cache: default_wasm_cache(),
+            nats_url: None,
+            environment: default_plan_environment(),
+            workers: default_runner_workers(),
+            queue: RunnerQueueConfig::default(),
+            event_policy: EventPolicyConfig::default(),
+            anomaly: AnomalyConfig::default(),
+            event_retention: EventRetentionConfig::default(),
+            sandbox: SandboxConfig::default(),
+            targets: Vec::new(),
+        }
+    }
+}
+
+fn default_runner_workers() -> usize {
+    4
+}
+
+fn default_plan_environment() -> String {
+    "dev".into()
+}
+
+fn default_wasm_cache() -> Utf8PathBuf {
+    Utf8PathBuf::from(".cache/wasm")
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoresConfig {
+    #[serde(default = "StoreConfig::memory")]
+    session: StoreConfig,
+    #[serde(default = "StoreConfig::memory")]
+    state: StoreConfig,
+    /// Per-session message transcripts (`file_path` is a directory, one JSONL file per session).
+    #[serde(default = "StoreConfig::memory")]
+    transcript: StoreConfig,
+    /// Durable copy of the runner event log (`memory` keeps none, or `sqlite`).
+    #[serde(default = "StoreConfig::memory")]
+    events: StoreConfig,
+    /// Append-only history of session writes (`memory` keeps none, `file` for JSONL or `sqlite`).
+    #[serde(default = "StoreConfig::memory")]
+    audit: StoreConfig,
+}
+
+impl Default for StoresConfig {
+    fn default() -> Self {
+        Self {
+            session: StoreConfig::file(default_session_store_path()),
+            state: StoreConfig::memory(),
+            transcript: StoreConfig::memory(),
+            events: StoreConfig::memory(),
+            audit: StoreConfig::memory(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SessionsConfig {
+    /// Purges matching more sessions than this require `confirm=true` or an admin token.
+    #[serde(default = "default_purge_confirm_threshold")]
+    purge_confirm_threshold: usize,
+    /// When set, removed sessions are tombstoned and restorable for this many seconds.
+    #[serde(default)]
+    soft_delete_window_secs: Option<u64>,
+    /// How often the background compactor finalizes expired tombstones.
+    #[serde(default = "default_compaction_interval_secs")]
+    compaction_interval_secs: u64,
+    /// Flows whose context is carried unchanged when a session is resumed on a newer version.
+    #[serde(default)]
+    passthrough_upgrade_flows: Vec<String>,
+    /// Upper bound on how long a resume holds its session lock on shared (redis) stores.
+    #[serde(default = "default_resume_lock_ttl_secs")]
+    resume_lock_ttl_secs: u64,
+    /// How often sessions whose `ttl_ms` has run out are removed from the store.
+    #[serde(default = "default_expiry_sweep_interval_secs")]
+    expiry_sweep_interval_secs: u64,
+    /// Live sessions a tenant may hold unless `[tenants.<id>].max_sessions` says otherwise.
+    #[serde(default)]
+    max_per_tenant: Option<usize>,
+    /// Where `POST /sessions/snapshot` writes snapshots (relative to the workspace).
+    #[serde(default = "default_snapshot_dir")]
+    snapshot_dir: Utf8PathBuf,
+    /// Nudge events for sessions left idle (`[sessions.nudge]`).
+    #[serde(default)]
+    nudge: NudgeConfig,
+}
+
+impl Default for SessionsConfig {
+    fn default() -> Self {
+        Self {
+            purge_confirm_threshold: default_purge_confirm_threshold(),
+            soft_delete_window_secs: None,
+            compaction_interval_secs: default_compaction_interval_secs(),
+            passthrough_upgrade_flows: Vec::new(),
+            resume_lock_ttl_secs: default_resume_lock_ttl_secs(),
+            expiry_sweep_interval_secs: default_expiry_sweep_interval_secs(),
+            max_per_tenant: None,
+            snapshot_dir: default_snapshot_dir(),
+            nudge: NudgeConfig::default(),
+        }
+    }
+}
+
+fn default_snapshot_dir() -> Utf8PathBuf {
+    Utf8PathBuf::from(".data/snapshots")
+}
+
+fn default_compaction_interval_secs() -> u64 {
+    60
+}
+
+fn default_resume_lock_ttl_secs() -> u64 {
+    30
+}
+
+fn default_expiry_sweep_interval_secs() -> u64 {
+    30
+}
+
+fn default_purge_confirm_threshold() -> usize {
+    25
+}
+
+fn default_session_store_path() -> Utf8PathBuf {
+    Utf8PathBuf::from(".data/sessions.json")
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+struct SeedDefaults {
+    #[serde(default)]
+    tenant: Option<String>,
+    #[serde(default)]
+    team: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub(crate) struct PackIndex {
+    pub(crate) entries: Vec<PackEntry>,
+    /// Pack directories whose manifest failed the pack schema; never resolved.
+    pub(crate) invalid: Vec<InvalidPack>,
+    /// Bumped every time a rebuilt index is installed (0 for the index loaded at startup).
+    pub(crate) generation: u64,
+}
+
+/// Manifest `status`: disabled packs stay indexed but never resolve; deprecated packs
+/// resolve with warnings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum PackStatus {
+    #[default]
+    Active,
+    Deprecated,
+    Disabled,
+}
+
+impl PackStatus {
+    fn is_active(&self) -> bool {
+        *self == PackStatus::Active
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            PackStatus::Active => "active",
+            PackStatus::Deprecated => "deprecated",
+            PackStatus::Disabled => "disabled",
+        }
+    }
+}
+
+/// Kind/tag selection shared by `packs list`, `packs scenarios` and `GET /packs`.
+/// Kinds compare case-insensitively; every requested tag must be present.
+#[derive(Debug, Clone, Default)]
+struct PackFilter {
+    kind: Option<String>,
+    tags: Vec<String>,
+}
+
+impl PackFilter {
+    /// Tags may also arrive comma-separated (`?tag=smoke,events`).
+    fn new(kind: Option<String>, tags: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            kind: sanitize_optional(kind),
+            tags: tags
+                .into_iter()
+                .flat_map(|tag| {
+                    tag.split(',')
+                        .map(|t| t.trim().to_string())
+                        .collect::<Vec<_>>()
+                })
+                .filter(|tag| !tag.is_empty())
+                .collect(),
+        }
+    }
+
+    fn matches_kind(&self, entry: &PackEntry) -> bool {
+        self.kind.as_ref().is_none_or(|kind| {
+            entry
+                .kind
+                .as_deref()
+                .is_some_and(|k| k.eq_ignore_ascii_case(kind))
+        })
+    }
+
+    fn matches(&self, entry: &PackEntry) -> bool {
+        self.matches_with(entry, &[])
+    }
+
+    /// Like `matches`, with `extra_tags` (e.g. a scenario's own tags) counting too.
+    fn matches_with(&self, entry: &PackEntry, extra_tags: &[String]) -> bool {
+        self.matches_kind(entry)
+            && self
+                .tags
+                .iter()
+                .all(|tag| entry.tags.contains(tag) || extra_tags.contains(tag))
+    }
+}
+
+/// A pack whose lifecycle status changed between two index builds.
+#[derive(Debug, Clone, Serialize)]
+struct PackTransition {
+    id: String,
+    from: PackStatus,
+    to: PackStatus,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct PackEntry {
+    pub(crate) id: String,
+    name: Option<String>,
+    kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    #[serde(default, skip_serializing_if = "PackStatus::is_active")]
+    status: PackStatus,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    /// Static files under `<pack>/assets/`, served by `GET /packs/{id}/assets/{*path}`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    assets: Vec<PackAsset>,
+    path: Utf8PathBuf,
+    /// `path` relative to the pack root (`acme/billing` for `packs/acme/billing/`).
+    #[serde(default)]
+    relative_path: Utf8PathBuf,
+    /// Flow ids the pack provides (scenario ids plus flows with a declared context schema).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    flows: Vec<String>,
+    /// JSON Schemas for session `context`, keyed by flow id.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    context_schemas: BTreeMap<String, Value>,
+    /// JSON Schemas for typed events, keyed by versioned event type.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    event_schemas: BTreeMap<String, Value>,
+    /// HTTP routes of an `extension` pack, served under `/ext/{id}/`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    routes: Vec<ExtensionRoute>,
+    #[serde(flatten)]
+    contents: PackContents,
+}
+
+#[derive(Debug, Deserialize)]
+struct PackManifestStub {
+    id: String,
+    version: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    kind: Option<String>,
+    #[serde(default)]
+    #[allow(dead_code)]
+    description: Option<String>,
+    #[serde(default)]
+    r#type: Option<String>,
+    #[serde(default)]
+    scenarios: Vec<ScenarioStub>,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+struct ScenarioStub {
+    id: String,
+    #[serde(default)]
+    entry: Option<String>,
+    #[serde(default)]
+    golden: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Channel config carried into the deployment plan (placeholders resolved).
+    #[serde(default)]
+    config: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoreConfig {
+    #[serde(default)]
+    backend: StoreBackend,
+    redis_url: Option<String>,
+    #[serde(default)]
+    redis_prefix: Option<String>,
+    #[serde(default)]
+    file_path: Option<Utf8PathBuf>,
+    /// File session backend: fold the journal into the snapshot after this many entries.
+    #[serde(default)]
+    journal_max_entries: Option<usize>,
+    /// File session backend: fold the journal into the snapshot once it reaches this size.
+    #[serde(default)]
+    journal_max_bytes: Option<u64>,
+    /// NATS KV session backend: server to connect to, e.g. `nats://127.0.0.1:4222`.
+    #[serde(default)]
+    nats_url: Option<String>,
+    /// NATS KV session backend: bucket holding the sessions (default `greentic-sessions`).
+    #[serde(default)]
+    nats_bucket: Option<String>,
+}
+
+impl StoreConfig {
+    fn memory() -> Self {
+        Self {
+            backend: StoreBackend::Memory,
+            redis_url: None,
+            redis_prefix: None,
+            file_path: None,
+            journal_max_entries: None,
+            journal_max_bytes: None,
+            nats_url: None,
+            nats_bucket: None,
+        }
+    }
+
+    fn file(path: Utf8PathBuf) -> Self {
+        Self {
+            backend: StoreBackend::File,
+            redis_url: None,
+            redis_prefix: None,
+            file_path: Some(path),
+            journal_max_entries: None,
+            journal_max_bytes: None,
+            nats_url: None,
+            nats_bucket: None,
+        }
+    }
+}
+
+impl Default for StoreConfig {
+    fn default() -> Self {
+        StoreConfig::memory()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+enum StoreBackend {
+    #[default]
+    Memory,
+    File,
+    Redis,
+    /// Embedded SQLite database at `file_path` (default `.data/greentic.db`).
+    Sqlite,
+    /// JetStream key-value bucket on `nats_url`; sessions only.
+    #[serde(rename = "nats_kv")]
+    #[value(name = "nats_kv")]
+    NatsKv,
+}
+
+impl StoreBackend {
+    fn as_str(&self) -> &'static str {
+        match self {
+            StoreBackend::Memory => "memory",
+            StoreBackend::File => "file",
+            StoreBackend::Redis => "redis",
+            StoreBackend::Sqlite => "sqlite",
+            StoreBackend::NatsKv => "nats_kv",
+        }
+    }
+}
+
+type SharedSessionStore = Arc<dyn SessionStore>;
+type SharedTranscriptStore = Arc<dyn TranscriptStore>;
+type SharedPackIndex = Arc<RwLock<PackIndex>>;
+type SharedRunnerEvents = Arc<RunnerEventLog>;
+/// Runner command receiver, shared so a restarted proxy loop picks up where the last one died.
+type SharedRunnerReceiver = Arc<tokio::sync::Mutex<mpsc::Receiver<RunnerCommand>>>;
+
+#[derive(Clone)]
+#[allow(dead_code)]
+struct AppState {
+    config: AppConfig,
+    session_store: SharedSessionStore,
+    transcripts: SharedTranscriptStore,
+    runner_proxy: RunnerHostProxy,
+    pack_index: SharedPackIndex,
+    runner_events: SharedRunnerEvents,
+    context_migrations: Arc<ContextMigrations>,
+    /// Coalesces concurrent `/packs/reload` calls and watcher-triggered rebuilds.
+    pack_reload: Arc<SingleFlight<PackReload>>,
+    /// Superseded pack indexes still draining, reported on `/packs/generations`.
+    pack_generations: PackGenerations,
+    /// Outbound network policy (`serve --offline`), reported on `/healthz`.
+    network: NetworkPolicy,
+    /// Health of the supervised background tasks, reported on `/readyz`.
+    tasks: TaskRegistry,
+    /// Results of the periodic health checks, reported on `/healthz/history`.
+    health: HealthHistory,
+    /// Handler panics caught by the panic guard, reported on `/diagnostics/panics`.
+    panics: PanicLog,
+    /// Embedded plus pack-declared event schemas, rebuilt with the pack index.
+    event_schemas: SharedSchemaRegistry,
+    /// Tokens issued by `POST /dev/chat/session`.
+    chat_tokens: ChatTokens,
+    /// The synthetic traffic run started from `/dev/traffic`.
+    traffic: TrafficControl,
+    /// Version and content changes of indexed packs, kept in `[stores.state]`.
+    pack_history: PackHistory,
+    /// Breakpoints and paused embedded runs behind `/debug`.
+    debugger: Debugger,
+    /// Provider sends of embedded runs awaiting delivery, kept in `[stores.state]`.
+    outbox: Outbox,
+    /// `[providers]` endpoints and their latest probes, reported on `/providers/{name}/health`.
+    providers: Providers,
+    /// Counters of the runner event retention passes, reported on `/runner/events/retention`.
+    event_retention: EventRetention,
+    /// Whole-store session snapshots written and restored over `/sessions/snapshot`.
+    session_snapshots: Arc<SnapshotDir>,
+    /// Per-tenant session limits enforced on `POST /sessions`.
+    session_quotas: SessionQuotas,
+    /// This instance's identity and task leases, reported on `/cluster`.
+    cluster: Cluster,
+    /// Tenant/session sharding (`[partitioning]`), reported on `/partitions`.
+    partitioner: Partitioner,
+    /// Runners sessions are pinned to (`[[runner.targets]]`), reported on `/runner/targets`.
+    runner_targets: RunnerTargets,
+    /// Per-tenant session counters, reported on `/metrics` and `/metrics/sessions`.
+    session_metrics: SessionMetrics,
+    /// Failed and slow request traces, reported on `/diagnostics/traces`.
+    traces: TailSampler,
+    /// Publishes requests to forwarding extension routes on `runner.nats_url`.
+    route_forwarder: Arc<NatsForwarder>,
+    #[cfg(feature = "mini-runner")]
+    mini_runner: Arc<mini_runner::MiniRunner>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct SessionFilterInput {
+    tenant: Option<String>,
+    team: Option<String>,
+    user: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    needs_upgrade: Option<bool>,
+}
+
+impl SessionFilterInput {
+    fn merge_with(self, other: Option<SessionFilterInput>) -> Self {
+        let mut merged = self;
+        if let Some(override_input) = other {
+            if override_input.tenant.is_some() {
+                merged.tenant = override_input.tenant;
+            }
+            if override_input.team.is_some() {
+                merged.team = override_input.team;
+            }
+            if override_input.user.is_some() {
+                merged.user = override_input.user;
+            }
+            if override_input.needs_upgrade.is_some() {
+                merged.needs_upgrade = override_input.needs_upgrade;
+            }
+        }
+        merged
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SessionPurgeOptions {
+    #[serde(default)]
+    dry_run: bool,
+    #[serde(default)]
+    confirm: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SessionPurgeResponse {
+    removed: usize,
+    matched: usize,
+    #[serde(default)]
+    dry_run: bool,
+    /// Dry runs only: the matching keys, sorted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    keys: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sessions: Option<Vec<SessionView>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SessionUpsertRequest {
+    #[serde(default)]
+    key: Option<String>,
+    #[serde(default)]
+    tenant: Option<String>,
+    #[serde(default)]
+    team: Option<String>,
+    #[serde(default)]
+    user: Option<String>,
+    #[serde(default)]
+    flow_id: Option<String>,
+    #[serde(default)]
+    node_id: Option<String>,
+    #[serde(default)]
+    context: Option<Value>,
+    /// Preferred locale for outbound message templates (e.g. `de-CH`).
+    #[serde(default)]
+    locale: Option<String>,
+    /// Expire the session this many milliseconds after this write.
+    #[serde(default)]
+    ttl_ms: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SessionListResponse {
+    count: usize,
+    sessions: Vec<SessionView>,
+    /// Present when more sessions follow; pass it back as `cursor`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    next_cursor: Option<String>,
+}
+
+/// Paging half of the `GET /sessions` query; unset `limit` returns every match.
+#[derive(Debug, Default, Deserialize)]
+struct SessionPageQuery {
+    #[serde(default)]
+    limit: Option<usize>,
+    #[serde(default)]
+    offset: usize,
+    #[serde(default)]
+    cursor: Option<String>,
+    /// `key` (default), `updated_at` or `-updated_at`.
+    #[serde(default)]
+    sort: Option<String>,
+}
+
+impl SessionPageQuery {
+    fn into_request(self) -> Result<SessionPageRequest> {
+        Ok(SessionPageRequest {
+            sort: self
+                .sort
+                .as_deref()
+                .map(str::parse)
+                .transpose()?
+                .unwrap_or_default(),
+            limit: self.limit,
+            offset: self.offset,
+            cursor: self.cursor.filter(|cursor| !cursor.is_empty()),
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct PackListResponse {
+    count: usize,
+    packs: Vec<PackInfo>,
+    resolved_keys: Vec<String>,
+    missing_keys: Vec<String>,
+    index_generation: u64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
+    /// Lifecycle changes applied by the reload that produced this listing.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    transitions: Vec<PackTransition>,
+    /// Packs left out of the index because their manifest failed the pack schema.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    invalid: Vec<InvalidPack>,
+}
+
+#[derive(Debug, Serialize)]
+struct PackInfo {
+    id: String,
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kind: Option<String>,
+    #[serde(skip_serializing_if = "PackStatus::is_active")]
+    status: PackStatus,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    assets: Vec<PackAsset>,
+    path: String,
+    relative_path: String,
+}
+
+/// Runner events kept in memory (and in `[stores.events]`, when durable).
+const RECENT_RUNNER_EVENTS: usize = 100;
+
+/// Recorded events buffered per `GET /runner/events/stream` subscriber before it lags.
+const RUNNER_EVENT_STREAM_BUFFER: usize = 256;
+
+/// Most recent runner events (oldest first), recorded through the `[runner.event_policy]`.
+pub(crate) struct RunnerEventLog {
+    events: RwLock<Vec<RunnerEvent>>,
+    policy: EventPolicy,
+    store: Option<Arc<dyn RunnerEventStore>>,
+    live: broadcast::Sender<RunnerEvent>,
+    /// Last `sequence` handed out per [`SequenceKey`].
+    sequences: Mutex<HashMap<SequenceKey, u64>>,
+    /// Set when the instance is part of a cluster; see [`RunnerEventLog::join_cluster`].
+    cluster: OnceLock<ClusterLink>,
+}
+
+/// Where a clustered instance stamps and sends the events it records itself.
+struct ClusterLink {
+    instance: String,
+    outbound: mpsc::UnboundedSender<RunnerEvent>,
+}
+
+impl Default for RunnerEventLog {
+    fn default() -> Self {
+        Self {
+            events: RwLock::default(),
+            policy: EventPolicy::default(),
+            store: None,
+            live: broadcast::channel(RUNNER_EVENT_STREAM_BUFFER).0,
+            sequences: Mutex::default(),
+            cluster: OnceLock::new(),
+        }
+    }
+}
+
+impl RunnerEventLog {
+    fn new(policy: EventPolicy) -> SharedRunnerEvents {
+        Arc::new(Self {
+            policy,
+            ..Self::default()
+        })
+    }
+
+    /// A log mirrored into `store` and seeded with its latest events.
+    pub(crate) fn with_store(
+        policy: EventPolicy,
+        store: Option<Arc<dyn RunnerEventStore>>,
+    ) -> Result<SharedRunnerEvents> {
+        let (events, sequences) = match &store {
+            Some(store) => (store.recent(RECENT_RUNNER_EVENTS)?, store.last_sequences()?),
+            None => Default::default(),
+        };
+        Ok(Arc::new(Self {
+            events: RwLock::new(events),
+            policy,
+            store,
+            sequences: Mutex::new(sequences),
+            ..Self::default()
+        }))
+    }
+
+    /// Next `sequence` for `event`'s tenant/team/user and flow.
+    fn next_sequence(&self, event: &RunnerEvent) -> u64 {
+        let mut sequences = self.sequences.lock();
+        let last = sequences.entry(SequenceKey::of(event)).or_default();
+        *last += 1;
+        *last
+    }
+
+    pub(crate) fn store(&self) -> Option<&dyn RunnerEventStore> {
+        self.store.as_deref()
+    }
+
+    /// Stamp events recorded from now on with `instance` and hand each to the returned
+    /// receiver for publishing to the other instances.
+    fn join_cluster(&self, instance: &str) -> mpsc::UnboundedReceiver<RunnerEvent> {
+        let (outbound, rx) = mpsc::unbounded_channel();
+        let link = ClusterLink {
+            instance: instance.to_string(),
+            outbound,
+        };
+        if self.cluster.set(link).is_err() {
+            warn!("runner event log already joined a cluster");
+        }
+        rx
+    }
+
+    /// Events recorded from now on.
+    fn subscribe(&self) -> broadcast::Receiver<RunnerEvent> {
+        self.live.subscribe()
+    }
+
+    pub(crate) fn read(&self) -> RwLockReadGuard<'_, Vec<RunnerEvent>> {
+        self.events.read()
+    }
+
+    pub(crate) fn write(&self) -> RwLockWriteGuard<'_, Vec<RunnerEvent>> {
+        self.events.write()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct RunnerEvent {
+    pub(crate) timestamp_ms: u64,
+    pub(crate) flow: String,
+    pub(crate) tenant: Option<String>,
+    pub(crate) team: Option<String>,
+    pub(crate) user: Option<String>,
+    pub(crate) payload: Value,
+    pub(crate) result: Value,
+    /// Allocated by [`record_runner_event`] per [`SequenceKey`], starting at 1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) sequence: Option<u64>,
+    /// Cluster instance that recorded the event; unset when running standalone.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) instance: Option<String>,
+}
+
+impl From<RunnerEvent> for greentic_integration_client::RunnerEvent {
+    fn from(event: RunnerEvent) -> Self {
+        Self {
+            timestamp_ms: event.timestamp_ms,
+            flow: event.flow,
+            tenant: event.tenant,
+            team: event.team,
+            user: event.user,
+            payload: event.payload,
+            result: event.result,
+            sequence: event.sequence,
+            instance: event.instance,
+        }
+    }
+}
+
+impl From<greentic_integration_client::RunnerEvent> for RunnerEvent {
+    fn from(event: greentic_integration_client::RunnerEvent) -> Self {
+        Self {
+            timestamp_ms: event.timestamp_ms,
+            flow: event.flow,
+            tenant: event.tenant,
+            team: event.team,
+            user: event.user,
+            payload: event.payload,
+            result: event.result,
+            sequence: event.sequence,
+            instance: event.instance,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct SessionView {
+    pub(crate) key: String,
+    pub(crate) tenant: String,
+    pub(crate) team: Option<String>,
+    pub(crate) user: Option<String>,
+    pub(crate) cursor: SessionCursorView,
+    pub(crate) context: Value,
+    pub(crate) updated_at_epoch_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) pack_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) flow_version: Option<String>,
+    #[serde(default)]
+    pub(crate) needs_upgrade: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) locale: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) ttl_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) pack_generation: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) runner_target: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) nudges: Vec<SessionNudge>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct SessionCursorView {
+    pub(crate) flow_id: Option<String>,
+    pub(crate) node_id: Option<String>,
+}
+
+impl From<SessionRecord> for SessionView {
+    fn from(record: SessionRecord) -> Self {
+        Self {
+            key: record.key,
+            tenant: record.tenant,
+            team: record.team,
+            user: record.user,
+            cursor: SessionCursorView {
+                flow_id: record.flow_id,
+                node_id: record.node_id,
+            },
+            context: record.context,
+            updated_at_epoch_ms: record.updated_at_epoch_ms,
+            pack_id: record.pack_id,
+            flow_version: record.flow_version,
+            needs_upgrade: record.needs_upgrade,
+            locale: record.locale,
+            ttl_ms: record.ttl_ms,
+            pack_generation: record.pack_generation,
+            runner_target: record.runner_target,
+            nudges: record.nudges,
+        }
+    }
+}
+
+/// Entry point of the `greentic-integration` binary: parse the command line and run it.
+pub async fn run() -> Result<()> {
+    init_tracing();
+
+    let cli = Cli::parse();
+    let http = cli.http.client_options();
+    let dry_run = cli.dry_run;
+    if dry_run && !honours_dry_run(&cli.command) {
+        bail!(
+            "--dry-run is only supported by sessions purge, sessions import, sessions migrate, \
+             packs reload, runner clear, runner prune, runner replay and tenants bootstrap"
+        );
+    }
+    match cli.command {
+        Command::Serve(args) => serve(args).await?,
+        Command::Packs { command } => handle_packs(command, &http, dry_run).await?,
+        Command::Sessions { command } => handle_sessions(command, &http, dry_run).await?,
+        Command::Runner { command } => handle_runner(command, &http, dry_run).await?,
+        Command::Tenants { command } => match command {
+            TenantsCommand::Bootstrap(args) => bootstrap_tenants_cli(args, dry_run)?,
+        },
+        Command::Providers { command } => match command {
+            ProvidersCommand::Register(args) => register_provider_cli(args)?,
+            ProvidersCommand::Verify(args) => verify_provider_cli(args).await?,
+            ProvidersCommand::Smoke(args) => smoke_providers_cli(args).await?,
+        },
+        Command::Deploy { command } => match command {
+            DeployCommand::VerifyPlanBundle(args) => verify_plan_bundle_cli(args)?,
+        },
+        Command::Loadtest { command } => match command {
+            LoadtestCommand::Soak(args) => soak_cli(args, &http).await?,
+        },
+        Command::Repl(args) => {
+            repl::run(bridge_client(&args.server, &http), args.tenant, args.team).await?
+        }
+        Command::Completions(args) => write_completions(args.shell, &mut std::io::stdout()),
+        Command::Mangen(args) => mangen_cli(args)?,
+        #[cfg(feature = "components")]
+        Command::Components { command } => match command {
+            ComponentsCommand::Invoke(args) => invoke_component_cli(args)?,
+        },
+    }
+
+    Ok(())
+}
+
+/// Commands that honour the global `--dry-run`. Every other command refuses the flag rather
+/// than risk changing something the caller only meant to preview.
+fn honours_dry_run(command: &Command) -> bool {
+    matches!(
+        command,
+        Command::Sessions {
+            command: SessionCommand::Purge(_)
+                | SessionCommand::Import(_)
+                | SessionCommand::Migrate(_)
+        } | Command::Packs {
+            command: PacksCommand::Reload(_)
+        } | Command::Runner {
+            command: RunnerCommandCli::Clear(_)
+                | RunnerCommandCli::Prune(_)
+                | RunnerCommandCli::Replay(_)
+        } | Command::Tenants {
+            command: TenantsCommand::Bootstrap(_)
+        }
+    )
+}
+
+fn print_dry_run(preview: &DryRunPreview) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(preview)?);
+    Ok(())
+}
+
+/// Completion scripts are generated from the clap definition of this binary, so they follow
+/// feature-gated subcommands (`components`) and new flags without a checked-in copy.
+fn write_completions(shell: clap_complete::Shell, out: &mut dyn std::io::Write) {
+    let mut command = Cli::command();
+    let name = command.get_name().to_string();
+    clap_complete::generate(shell, &mut command, name, out);
+}
+
+fn mangen_cli(args: MangenArgs) -> Result<()> {
+    match args.out {
+        Some(dir) => {
+            fs::create_dir_all(&dir).with_context(|| format!("failed to create {dir}"))?;
+            clap_mangen::generate_to(Cli::command(), &dir)
+                .with_context(|| format!("failed to write man pages to {dir}"))?;
+            println!("man pages written to {dir}");
+        }
+        None => clap_mangen::Man::new(Cli::command()).render(&mut std::io::stdout())?,
+    }
+    Ok(())
+}
+
+/// Fail fast when a configured outbound target is not allowed by `network`
+/// (with `--offline`, anything off localhost).
+fn check_network_targets(
+    network: &NetworkPolicy,
+    config: &AppConfig,
+    runner_base: Option<&str>,
+) -> Result<()> {
+    if let Some(base) = runner_base {
+        network.check("runner proxy (RUNNER_PROXY_URL)", base)?;
+    }
+    if let Some(url) = &config.runner.nats_url {
+        network.check("runner NATS (runner.nats_url)", url)?;
+    }
+    if let Some(url) = &config.cluster.redis_url {
+        network.check("cluster coordination (cluster.redis_url)", url)?;
+    }
+    for (name, provider) in &config.providers {
+        let purpose = format!("provider endpoint (providers.{name})");
+        network.check(&purpose, &provider.endpoint)?;
+        if let Some(url) = &provider.health_url {
+            network.check(&purpose, url)?;
+        }
+    }
+    if let Some(url) = &config.telemetry.sampling.export_url {
+        network.check("trace collector (telemetry.sampling.export_url)", url)?;
+    }
+    if let Some(url) = &config.runner.anomaly.webhook_url {
+        network.check("anomaly webhook (runner.anomaly.webhook_url)", url)?;
+    }
+    if let Some(url) = &config.sessions.nudge.webhook {
+        network.check("nudge webhook (sessions.nudge.webhook)", url)?;
+    }
+    for (provider, url) in &config.server.outbox.sinks {
+        network.check(
+            &format!("outbox sink (server.outbox.sinks.{provider})"),
+            url,
+        )?;
+    }
+    for (id, tenant) in &config.tenants {
+        if let Some(url) = &tenant.resume_webhook {
+            network.check(&format!("resume webhook (tenants.{id})"), url)?;
+        }
+    }
+    for (name, key, store) in [
+        ("session store", "session", &config.stores.session),
+        ("state store", "state", &config.stores.state),
+        ("transcript store", "transcript", &config.stores.transcript),
+    ] {
+        let (field, url) = match store.backend {
+            StoreBackend::Redis => ("redis_url", &store.redis_url),
+            StoreBackend::NatsKv => ("nats_url", &store.nats_url),
+            _ => continue,
+        };
+        if let Some(url) = url {
+            network.check(&format!("{name} (stores.{key}.{field})"), url)?;
+        }
+    }
+    Ok(())
+}
+
+async fn serve(args: ServeArgs) -> Result<()> {
+    let config = load_config(args.config.as_ref())?;
+    let launched = Server::from_config(config)
+        .offline(args.offline)
+        .watch(args.watch)
+        .launch()
+        .await?;
+    let server = &launched.state.config.server;
+    let listeners = Listeners::bind(&server.listeners, &server.listen_addr).await?;
+    launched.serve(listeners, shutdown_signal()).await
+}
+
+/// The bridge `serve` runs, for embedding in another program or a test: it serves on a
+/// listener the caller bound (port `0` picks a free one) until its handle is shut down.
+///
+/// ```no_run
+/// # async fn embed() -> anyhow::Result<()> {
+/// use greentic_integration::{AppConfig, Server, session::InMemorySessionStore};
+///
+/// let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+/// let server = Server::from_config(AppConfig::load(None)?)
+///     .with_session_store(InMemorySessionStore::new())
+///     .serve(listener)
+///     .await?;
+/// server.client().healthz().await?;
+/// server.shutdown().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Server {
+    config: AppConfig,
+    session_store: Option<Arc<dyn SessionStore>>,
+    offline: bool,
+    watch: bool,
+}
+
+impl Server {
+    pub fn from_config(config: AppConfig) -> Self {
+        Self {
+            config,
+            session_store: None,
+            offline: false,
+            watch: false,
+        }
+    }
+
+    /// Keep sessions in `store` instead of the `[stores.session]` backend. Soft deletes,
+    /// auditing and session metrics still wrap it as configured.
+    pub fn with_session_store(mut self, store: Arc<dyn SessionStore>) -> Self {
+        self.session_store = Some(store);
+        self
+    }
+
+    /// Refuse outbound traffic to anything but localhost, like `serve --offline`.
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Reload packs when files under the packs root change, like `serve --watch`.
+    pub fn watch(mut self, watch: bool) -> Self {
+        self.watch = watch;
+        self
+    }
+
+    /// Start the background tasks and serve on `listener`. Returns once the server is up;
+    /// it runs until the handle is shut down or dropped.
+    pub async fn serve(self, listener: TcpListener) -> Result<ServerHandle> {
+        let addr = listener.local_addr()?;
+        let launched = self.launch().await?;
+        let session_store = launched.state.session_store.clone();
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        info!(%addr, "embedded bridge listening");
+        let task = tokio::spawn(launched.serve(Listeners::from_tcp(listener), async move {
+            // A dropped handle stops the server too.
+            let _ = stopped.await;
+        }));
+        Ok(ServerHandle {
+            addr,
+            session_store,
+            stop,
+            task,
+        })
+    }
+
+    fn network(&self) -> NetworkPolicy {
+        if self.offline {
+            NetworkPolicy::offline()
+        } else {
+            NetworkPolicy::default()
+        }
+    }
+
+    /// The state behind the router, with the runner proxy loop running on `supervisor`. The
+    /// other background tasks are left to [`Server::launch`].
+    fn build_state(&self, supervisor: &Supervisor, pack_index: PackIndex) -> Result<AppState> {
+        let config = &self.config;
+        let network = self.network();
+        let runner_base = runner_proxy_base_from_env();
+        let session_metrics = SessionMetrics::default();
+        let session_store = match &self.session_store {
+            Some(store) => store.clone(),
+            None => build_session_store(&config.stores.session)?,
+        };
+        let session_store = MeteredSessionStore::new(
+            audit_session_store(
+                wrap_session_store(session_store, &config.sessions),
+                &config.stores.audit,
+                "server",
+            )?,
+            session_metrics.clone(),
+        );
+        let event_schemas = Arc::new(RwLock::new(build_schema_registry(&pack_index)));
+        let pack_index = Arc::new(RwLock::new(pack_index));
+        let state_store = build_state_store(&config.stores.state)?;
+        let runner_events = RunnerEventLog::with_store(
+            EventPolicy::from_config(&config.runner.event_policy)?,
+            build_event_store(&config.stores.events)?,
+        )?;
+        let (runner_queue, runner_rx) = RunnerQueue::new(&config.runner.queue);
+        let runner_proxy = RunnerHostProxy::new(runner_queue, runner_base.clone());
+        let runner_targets = RunnerTargets::new(&config.runner.targets)?;
+        {
+            let runner_rx: SharedRunnerReceiver = Arc::new(tokio::sync::Mutex::new(runner_rx));
+            let events = runner_events.clone();
+            let targets = runner_targets.clone();
+            let workers = config.runner.workers;
+            supervisor.spawn("runner_proxy", true, move || {
+                proxy_runner_loop(
+                    runner_rx.clone(),
+                    events.clone(),
+                    runner_base.clone(),
+                    targets.clone(),
+                    OutboundHttp::new(network),
+                    workers,
+                )
+            });
+        }
+        let debugger = Debugger::new(Duration::from_secs(config.server.debug.pause_timeout_secs));
+        let providers = load_providers(config)?;
+        let traces = TailSampler::global();
+        traces.configure(&config.telemetry.sampling)?;
+        Ok(AppState {
+            config: config.clone(),
+            session_store,
+            transcripts: build_transcript_store(&config.stores.transcript)?,
+            runner_proxy,
+            pack_index: pack_index.clone(),
+            runner_events: runner_events.clone(),
+            context_migrations: Arc::new(build_context_migrations(&config.sessions)),
+            pack_reload: Arc::new(SingleFlight::default()),
+            pack_generations: PackGenerations::new(config.packs.retain_generations),
+            network,
+            tasks: supervisor.registry(),
+            health: HealthHistory::new(config.server.health.history),
+            panics: PanicLog::default(),
+            event_schemas: event_schemas.clone(),
+            chat_tokens: ChatTokens::default(),
+            traffic: TrafficControl::default(),
+            pack_history: PackHistory::new(state_store.clone()),
+            debugger: debugger.clone(),
+            outbox: Outbox::new(state_store.clone(), config.server.outbox.clone())
+                .with_providers(providers.clone()),
+            providers,
+            event_retention: EventRetention::default(),
+            session_snapshots: Arc::new(SnapshotDir::new(
+                workspace_root().to_path_buf(),
+                config.sessions.snapshot_dir.clone(),
+            )?),
+            session_quotas: SessionQuotas::default(),
+            cluster: Cluster::from_config(&config.cluster)?,
+            partitioner: Partitioner::from_config(&config.partitioning)?,
+            runner_targets,
+            session_metrics,
+            traces,
+            route_forwarder: Arc::new(NatsForwarder::new(config.runner.nats_url.clone(), network)),
+            #[cfg(feature = "mini-runner")]
+            mini_runner: embedded_runner(
+                config,
+                AppPlanHost::new(
+                    pack_index,
+                    runner_events,
+                    config.runner.environment.clone(),
+                    &config.packs,
+                ),
+                network,
+                event_schemas,
+                state_store,
+                config.server.debug.enabled.then_some(debugger),
+            )?,
+        })
+    }
+
+    /// Build the state and start every background task around it: everything `serve` does
+    /// before it accepts requests.
+    async fn launch(self) -> Result<Launched> {
+        let config = self.config.clone();
+        let network = self.network();
+        check_network_targets(&network, &config, runner_proxy_base_from_env().as_deref())?;
+        let packs_root = resolve_packs_root(&config.packs)?;
+        let supervisor = Supervisor::new(config.server.supervisor.clone());
+        let state = self.build_state(&supervisor, build_pack_index(&config.packs)?)?;
+        let cluster = state.cluster.clone();
+        if cluster.is_clustered() {
+            join_cluster(&supervisor, &cluster, &config, &state.runner_events);
+        }
+
+        info!(
+            backend = ?config.stores.session.backend,
+            file_path = ?config.stores.session.file_path,
+            redis_url = ?config.stores.session.redis_url,
+            custom = self.session_store.is_some(),
+            "session store configured"
+        );
+        info!(
+            packs = state.pack_index.read().entries.len(),
+            root = %packs_root,
+            "pack index loaded"
+        );
+        info!(
+            ?config,
+            watch = self.watch,
+            offline = network.is_offline(),
+            "starting integration server"
+        );
+
+        let packs = state.pack_index.read().clone();
+        record_pack_history(&state, &packs);
+        state
+            .runner_proxy
+            .submit(RunnerCommand::ReloadPacks {
+                packs,
+                defaults: config.defaults.clone(),
+            })
+            .await;
+
+        // Bound up front so a bad `[email].smtp_listen` fails startup instead of the task.
+        let smtp_listener = match &config.email.smtp_listen {
+            Some(addr) => Some(Arc::new(
+                tokio::net::TcpListener::bind(addr)
+                    .await
+                    .with_context(|| format!("failed to bind [email].smtp_listen {addr}"))?,
+            )),
+            None => None,
+        };
+
+        if config.sessions.soft_delete_window_secs.is_some() {
+            let store = state.session_store.clone();
+            let every = Duration::from_secs(config.sessions.compaction_interval_secs.max(1));
+            spawn_singleton(
+                &supervisor,
+                &cluster,
+                "session_compaction",
+                true,
+                move || compact_session_tombstones(store.clone(), every),
+            );
+        }
+        {
+            let store = state.session_store.clone();
+            let every = Duration::from_secs(config.sessions.expiry_sweep_interval_secs.max(1));
+            spawn_singleton(&supervisor, &cluster, "session_expiry", false, move || {
+                as_actor(
+                    "system:expiry",
+                    sweep_expired_sessions(store.clone(), every),
+                )
+            });
+        }
+        if config.sessions.nudge.enabled {
+            config.sessions.nudge.validate()?;
+            let nudge_state = state.clone();
+            let every = Duration::from_secs(config.sessions.nudge.interval_secs.max(1));
+            spawn_singleton(&supervisor, &cluster, "session_nudge", false, move || {
+                as_actor(
+                    "system:nudge",
+                    nudge_stale_sessions(nudge_state.clone(), every),
+                )
+            });
+        }
+        if config
+            .tenants
+            .values()
+            .any(|tenant| !tenant.retention.is_empty())
+        {
+            let reap_state = state.clone();
+            let every = Duration::from_secs(config.server.retention_interval_secs.max(1));
+            spawn_singleton(&supervisor, &cluster, "retention_reaper", true, move || {
+                as_actor(
+                    "system:retention",
+                    reap_tenant_retention(reap_state.clone(), every),
+                )
+            });
+        }
+        {
+            let health_state = state.clone();
+            let root = packs_root.clone();
+            let every = Duration::from_secs(config.server.health.interval_secs.max(1));
+            supervisor.spawn("health_checks", false, move || {
+                record_health_checks(health_state.clone(), root.clone(), every)
+            });
+        }
+        if config.runner.event_retention.is_enabled() {
+            let retention = state.event_retention.clone();
+            let events = state.runner_events.clone();
+            let limits = config.runner.event_retention.clone();
+            supervisor.spawn("event_retention", false, move || {
+                retention.clone().run(events.clone(), limits.clone())
+            });
+        }
+        if config.runner.anomaly.enabled {
+            let events = state.runner_events.clone();
+            let anomaly = config.runner.anomaly.clone();
+            spawn_singleton(
+                &supervisor,
+                &cluster,
+                "anomaly_detector",
+                false,
+                move || {
+                    detect_anomalies(events.clone(), anomaly.clone(), OutboundHttp::new(network))
+                },
+            );
+        }
+        if !state.providers.is_empty() {
+            let probed = state.providers.clone();
+            let unhealthy = tokio::task::spawn_blocking(move || {
+                probed.probe_all(&OutboundHttp::new(network), now_millis())
+            })
+            .await?;
+            for (provider, health) in unhealthy {
+                warn!(%provider, error = ?health.error, "provider endpoint failed its startup probe");
+            }
+            let providers = state.providers.clone();
+            let every = Duration::from_secs(config.server.health.interval_secs.max(1));
+            supervisor.spawn("provider_probes", false, move || {
+                probe_providers(providers.clone(), OutboundHttp::new(network), every)
+            });
+        }
+        if config.server.outbox.enabled {
+            let outbox = state.outbox.clone();
+            spawn_singleton(
+                &supervisor,
+                &cluster,
+                "outbox_dispatcher",
+                false,
+                move || outbox.clone().run(OutboundHttp::new(network)),
+            );
+        }
+        if let Some(listener) = smtp_listener {
+            info!(addr = ?listener.local_addr().ok(), "email ingress listening for SMTP");
+            let (ingress, email_state) = (EmailIngress::new(config.email.clone()), state.clone());
+            supervisor.spawn("email_ingress", false, move || {
+                let state = email_state.clone();
+                ingress
+                    .clone()
+                    .serve_smtp(listener.clone(), move |route, email| {
+                        deliver_email(state.clone(), route, email)
+                    })
+            });
+        }
+        let sampling = &config.telemetry.sampling;
+        if sampling.enabled
+            && let Some(url) = sampling.export_url.clone()
+        {
+            let traces = state.traces.clone();
+            supervisor.spawn("trace_export", false, move || {
+                traces
+                    .clone()
+                    .export(OutboundHttp::new(network), url.clone())
+            });
+        }
+        if config.packs.retain_generations > 1 {
+            let drain_state = state.clone();
+            let every = Duration::from_secs(config.packs.drain_interval_secs.max(1));
+            supervisor.spawn("pack_generation_drain", false, move || {
+                drain_pack_generations_loop(drain_state.clone(), every)
+            });
+        }
+        if self.watch {
+            // A broken watcher only costs hot reload, so it never takes the server down.
+            let watch_state = state.clone();
+            supervisor.spawn("pack_watcher", false, move || {
+                watch_packs(packs_root.clone(), watch_state.clone())
+            });
+        }
+
+        let traffic = &config.server.traffic;
+        if traffic.enabled && traffic.autostart {
+            start_traffic(&state, traffic.profile.clone())
+                .context("invalid [server.traffic.profile] for autostart")?;
+        }
+
+        Ok(Launched { state, supervisor })
+    }
+}
+
+/// A bridge whose state and background tasks are up, waiting for listeners.
+struct Launched {
+    state: AppState,
+    supervisor: Supervisor,
+}
+
+impl Launched {
+    /// Serve on `listeners` until `shutdown` resolves or a critical task gives up, then stop
+    /// the background tasks.
+    async fn serve(
+        self,
+        listeners: Listeners,
+        shutdown: impl Future<Output = ()> + Send + 'static,
+    ) -> Result<()> {
+        let escalated = self.supervisor.escalated();
+        let server_task = tokio::spawn(listeners.serve(build_router(self.state), async move {
+            tokio::select! {
+                _ = shutdown => {}
+                reason = escalated => error!(%reason, "shutting down after task failure"),
+            }
+        }));
+
+        if let Err(err) = server_task.await.expect("server task panicked") {
+            error!(?err, "server task failed");
+        }
+        self.supervisor.shutdown();
+        if let Some(reason) = self.supervisor.escalation() {
+            bail!("server stopped: {reason}");
+        }
+
+        info!("server shut down cleanly");
+
+        Ok(())
+    }
+}
+
+/// A bridge started by [`Server::serve`]. Dropping it stops the server without waiting;
+/// [`ServerHandle::shutdown`] also waits for in-flight requests and background tasks.
+pub struct ServerHandle {
+    addr: SocketAddr,
+    session_store: Arc<dyn SessionStore>,
+    stop: tokio::sync::oneshot::Sender<()>,
+    task: JoinHandle<Result<()>>,
+}
+
+impl ServerHandle {
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// A typed client pointed at this server.
+    pub fn client(&self) -> BridgeClient {
+        BridgeClient::new(self.base_url())
+    }
+
+    /// The store the server keeps sessions in, wrappers included, for seeding or inspecting
+    /// sessions without going through HTTP.
+    pub fn session_store(&self) -> Arc<dyn SessionStore> {
+        self.session_store.clone()
+    }
+
+    /// Stop accepting requests, drain the in-flight ones and stop the background tasks.
+    pub async fn shutdown(self) -> Result<()> {
+        let _ = self.stop.send(());
+        self.task.await.context("embedded server task panicked")?
+    }
+}
+
+/// Start the tasks that make this instance part of `[cluster]`: membership heartbeats and
+/// runner event fan-out and fan-in.
+fn join_cluster(
+    supervisor: &Supervisor,
+    cluster: &Cluster,
+    config: &AppConfig,
+    runner_events: &SharedRunnerEvents,
+) {
+    for (store, backend) in [
+        ("stores.session", config.stores.session.backend),
+        ("stores.state", config.stores.state.backend),
+    ] {
+        if !matches!(backend, StoreBackend::Redis | StoreBackend::NatsKv) {
+            warn!(
+                store,
+                backend = backend.as_str(),
+                "clustered instances do not share this store; use redis"
+            );
+        }
+    }
+    info!(
+        instance = cluster.instance_id(),
+        "joining the bridge cluster"
+    );
+    {
+        let cluster = cluster.clone();
+        supervisor.spawn("cluster_heartbeat", false, move || {
+            cluster.clone().heartbeat()
+        });
+    }
+    {
+        let outbound = Arc::new(tokio::sync::Mutex::new(
+            runner_events.join_cluster(cluster.instance_id()),
+        ));
+        let cluster = cluster.clone();
+        supervisor.spawn("cluster_events_out", false, move || {
+            publish_cluster_events(cluster.clone(), outbound.clone())
+        });
+    }
+    {
+        let (cluster, events) = (cluster.clone(), runner_events.clone());
+        supervisor.spawn("cluster_events_in", false, move || {
+            ingest_cluster_events(cluster.clone(), events.clone())
+        });
+    }
+}
+
+/// Supervise a task that runs on one instance of the cluster at a time.
+fn spawn_singleton<F, Fut>(
+    supervisor: &Supervisor,
+    cluster: &Cluster,
+    name: &'static str,
+    critical: bool,
+    start: F,
+) where
+    F: Fn() -> Fut + Clone + Send + Sync + 'static,
+    Fut: std::future::Future<Output = Result<()>> + Send + 'static,
+{
+    let cluster = cluster.clone();
+    supervisor.spawn(name, critical, move || {
+        cluster.clone().leader_only(name, start.clone())
+    });
+}
+
+async fn publish_cluster_events(
+    cluster: Cluster,
+    outbound: Arc<tokio::sync::Mutex<mpsc::UnboundedReceiver<RunnerEvent>>>,
+) -> Result<()> {
+    let mut outbound = outbound.lock().await;
+    while let Some(event) = outbound.recv().await {
+        let (cluster, message) = (cluster.clone(), serde_json::to_string(&event)?);
+        // A lost publish costs the other instances one event; retrying would reorder them.
+        if let Err(err) = tokio::task::spawn_blocking(move || cluster.publish(&message)).await? {
+            warn!(?err, flow = %event.flow, "failed to publish runner event to the cluster");
+        }
+    }
+    Ok(())
+}
+
+async fn ingest_cluster_events(cluster: Cluster, events: SharedRunnerEvents) -> Result<()> {
+    tokio::task::spawn_blocking(move || {
+        let instance = cluster.instance_id().to_string();
+        cluster.subscribe(&mut |message| {
+            match serde_json::from_str::<RunnerEvent>(&message) {
+                Ok(event) if event.instance.as_deref() == Some(instance.as_str()) => {}
+                Ok(event) => ingest_remote_event(&events, event),
+                Err(err) => warn!(?err, "ignoring a malformed cluster runner event"),
+            }
+            true
+        })
+    })
+    .await??;
+    bail!("the cluster event subscription ended")
+}
+
+async fn handle_packs(cmd: PacksCommand, http: &ClientOptions, dry_run: bool) -> Result<()> {
+    match cmd {
+        PacksCommand::Validate => run_pack_validator()?,
+        PacksCommand::List(args) => list_packs(args)?,
+        PacksCommand::Scenarios(args) => list_scenario_suite(args)?,
+        PacksCommand::Reload(args) => reload_packs_cli(args, http, dry_run).await?,
+        PacksCommand::Plan(args) => plan_pack(args)?,
+        PacksCommand::PlanSnapshot(args) => plan_snapshot_cli(args)?,
+        PacksCommand::PlanDiff(args) => plan_diff_cli(args)?,
+        #[cfg(feature = "mini-runner")]
+        PacksCommand::RunScenario(args) => run_scenario_cli(args).await?,
+    }
+
+    Ok(())
+}
+
+async fn handle_sessions(cmd: SessionCommand, http: &ClientOptions, dry_run: bool) -> Result<()> {
+    match cmd {
+        SessionCommand::Purge(args) => purge_sessions(args, dry_run)?,
+        SessionCommand::Resume(args) => resume_session_cli(args, http).await?,
+        SessionCommand::List(args) => list_sessions_cli(args, http).await?,
+        SessionCommand::Get(args) => get_session_cli(args, http).await?,
+        SessionCommand::Fsck(args) => fsck_sessions(args)?,
+        SessionCommand::Compact => compact_sessions()?,
+        SessionCommand::History(args) => session_history_cli(args)?,
+        SessionCommand::Export(args) => export_sessions_cli(args)?,
+        SessionCommand::Import(args) => import_sessions_cli(args, dry_run)?,
+        SessionCommand::Migrate(args) => migrate_sessions_cli(args, dry_run)?,
+        SessionCommand::Stress(args) => stress_sessions(args)?,
+        SessionCommand::Watch(args) => watch_sessions_cli(args, http).await?,
+    }
+
+    Ok(())
+}
+
+fn bootstrap_tenants_cli(args: TenantBootstrapArgs, dry_run: bool) -> Result<()> {
+    let config = load_config(args.config.as_ref())?;
+    let raw =
+        fs::read_to_string(&args.file).with_context(|| format!("failed to read {}", args.file))?;
+    let file = BootstrapFile::from_yaml(&raw)?;
+    let packs_root = resolve_packs_root(&config.packs)?;
+    let secrets_dir = config
+        .packs
+        .secrets_dir
+        .as_ref()
+        .map(|dir| workspace_root().join(dir));
+    let sessions = if args.demo_sessions {
+        Some(audit_session_store(
+            wrap_session_store(
+                build_session_store(&config.stores.session)?,
+                &config.sessions,
+            ),
+            &config.stores.audit,
+            &cli_actor(),
+        )?)
+    } else {
+        None
+    };
+    let tenants_file = tenants_file_path(args.config.as_ref());
+    let targets = BootstrapTargets {
+        tenants_file: tenants_file.as_std_path(),
+        packs_root: packs_root.as_std_path(),
+        secrets_dir: secrets_dir.as_ref().map(|dir| dir.as_std_path()),
+        sessions: sessions.as_deref(),
+    };
+    let reports = bootstrap_tenants(&file, &targets, dry_run)?;
+
+    let verb = if dry_run {
+        "Would provision"
+    } else {
+        "Provisioned"
+    };
+    println!("{verb} {} tenant(s) into {tenants_file}", reports.len());
+    for report in &reports {
+        println!(
+            "- {}: {} team(s), {} user(s), secrets [{}], packs [{}], sessions [{}]",
+            report.tenant,
+            report.teams.len(),
+            report.users,
+            report.secrets.join(", "),
+            report.packs.join(", "),
+            report.sessions.join(", ")
+        );
+    }
+    Ok(())
+}
+
+fn credential_vault(config: Option<&Utf8PathBuf>) -> Result<CredentialVault> {
+    let config = load_config(config)?;
+    let secrets_dir = config
+        .packs
+        .secrets_dir
+        .as_ref()
+        .context("provider sandbox credentials need [packs].secrets_dir to be configured")?;
+    Ok(CredentialVault::new(
+        workspace_root().join(secrets_dir).as_std_path(),
+    ))
+}
+
+fn register_provider_cli(args: ProviderRegisterArgs) -> Result<()> {
+    let provider = args.name;
+    let mut values = BTreeMap::new();
+    if args.from_env {
+        for field in provider
+            .required_fields()
+            .iter()
+            .chain(provider.optional_fields())
+        {
+            let var = format!("SANDBOX_{}_{field}", provider.name().to_ascii_uppercase());
+            if let Ok(value) = std::env::var(&var) {
+                values.insert(field.to_string(), value);
+            }
+        }
+    }
+    for pair in &args.set {
+        let (field, value) = pair
+            .split_once('=')
+            .with_context(|| format!("invalid --set {pair:?}; expected FIELD=VALUE"))?;
+        values.insert(field.to_string(), value.to_string());
+    }
+    if values.is_empty() {
+        bail!("nothing to register; pass --set FIELD=VALUE or --from-env");
+    }
+    let stored = credential_vault(args.config.as_ref())?.register(provider, &values)?;
+    println!(
+        "Stored {provider} sandbox credential(s): {}",
+        stored.join(", ")
+    );
+    Ok(())
+}
+
+async fn verify_provider_cli(args: ProviderVerifyArgs) -> Result<()> {
+    let credentials = credential_vault(args.config.as_ref())?.load(args.name)?;
+    let text = format!("greentic-integration providers verify ({})", now_millis());
+    let report = tokio::task::spawn_blocking(move || {
+        provider_sandbox::verify(&credentials, &OutboundHttp::default(), &text)
+    })
+    .await?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_verify_report(&report);
+    }
+    if !report.ok {
+        bail!(
+            "{} sandbox credentials failed verification",
+            report.provider
+        );
+    }
+    Ok(())
+}
+
+fn print_verify_report(report: &VerifyReport) {
+    for step in &report.steps {
+        println!(
+            "{} {:<15} {:>5}ms  {}",
+            if step.ok { "ok  " } else { "FAIL" },
+            step.step,
+            step.latency_ms,
+            step.detail
+        );
+    }
+    if report.ok {
+        println!("{} sandbox credentials verified", report.provider);
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct SmokeReport {
+    providers: Vec<ProviderSmoke>,
+    /// Providers left out because their credentials are missing or incomplete.
+    skipped: BTreeMap<String, String>,
+    parity: Vec<ParityRow>,
+}
+
+async fn smoke_providers_cli(args: ProviderSmokeArgs) -> Result<()> {
+    let vault = credential_vault(args.config.as_ref())?;
+    let requested = !args.name.is_empty();
+    let names = if requested {
+        args.name
+    } else {
+        SandboxProvider::ALL.to_vec()
+    };
+    let mut configured = Vec::new();
+    let mut skipped = BTreeMap::new();
+    for provider in names {
+        match vault.load(provider) {
+            Ok(credentials) => configured.push(credentials),
+            Err(err) if requested => return Err(err),
+            Err(err) => {
+                skipped.insert(provider.to_string(), err.to_string());
+            }
+        }
+    }
+    if configured.is_empty() {
+        bail!(
+            "no provider has sandbox credentials; register them with `providers register --name <provider>`"
+        );
+    }
+    let capabilities = args
+        .capabilities
+        .map(Utf8PathBuf::into_std_path_buf)
+        .unwrap_or_else(providers_sim::capabilities::capabilities_path);
+    if !capabilities.is_file() {
+        bail!("capability map {} does not exist", capabilities.display());
+    }
+    let doc = providers_sim::capabilities::load_capabilities(&capabilities)
+        .with_context(|| format!("failed to parse {}", capabilities.display()))?;
+    let artifacts = args
+        .artifacts
+        .unwrap_or_else(|| workspace_root().join("target/e2e/providers-smoke"));
+
+    let mut providers = Vec::new();
+    for credentials in configured {
+        let dir = artifacts.clone().into_std_path_buf();
+        let keep = args.keep_messages;
+        providers.push(
+            tokio::task::spawn_blocking(move || {
+                provider_smoke::run(&credentials, &OutboundHttp::default(), &dir, keep)
+            })
+            .await?,
+        );
+    }
+    let report = SmokeReport {
+        parity: provider_smoke::parity(&doc, &providers),
+        providers,
+        skipped,
+    };
+    fs::create_dir_all(&artifacts)?;
+    let report_path = artifacts.join("report.json");
+    fs::write(&report_path, serde_json::to_vec_pretty(&report)?)
+        .with_context(|| format!("failed to write {report_path}"))?;
+    smoke_annotations(&report, &report_path, &capabilities)
+        .write(artifacts.join("annotations.json"), workspace_root())?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_smoke_report(&report);
+        println!("Artifacts: {artifacts}");
+    }
+    let failed: Vec<String> = report
+        .providers
+        .iter()
+        .filter(|run| !run.ok)
+        .map(|run| run.provider.to_string())
+        .collect();
+    if !failed.is_empty() {
+        bail!("provider smoke failed for {}", failed.join(", "));
+    }
+    Ok(())
+}
+
+/// Failed cases as failures on their artifact, and capabilities the reference provider claims
+/// but no provider could verify as warnings on the capability map.
+fn smoke_annotations(
+    report: &SmokeReport,
+    report_path: &Utf8Path,
+    capabilities: &std::path::Path,
+) -> AnnotationReport {
+    let mut annotations = AnnotationReport::default();
+    for run in &report.providers {
+        for case in run.cases.iter().filter(|case| !case.ok) {
+            let path = case.artifact.as_deref().unwrap_or(report_path.as_str());
+            annotations.push(Annotation::failure(path, &case.detail).title(format!(
+                "{} {} smoke failed",
+                run.provider,
+                case.case.name()
+            )));
+        }
+    }
+    let map = fs::read_to_string(capabilities).unwrap_or_default();
+    for row in report.parity.iter().filter(|row| row.contradicted()) {
+        annotations.push(
+            Annotation::warning(
+                capabilities,
+                format!(
+                    "claimed capability {} failed on {}",
+                    row.capability,
+                    row.failed_by.join(", ")
+                ),
+            )
+            .line(line_of(&map, &row.capability, 0).unwrap_or(1))
+            .title("capability claim not met"),
+        );
+    }
+    annotations
+}
+
+fn print_smoke_report(report: &SmokeReport) {
+    for run in &report.providers {
+        println!("{}:", run.provider);
+        for case in &run.cases {
+            println!(
+                "  {} {:<15} {:>5}ms  {}",
+                if case.ok { "ok  " } else { "FAIL" },
+                case.case.name(),
+                case.latency_ms,
+                case.detail
+            );
+        }
+    }
+    for (provider, reason) in &report.skipped {
+        println!("{provider}: skipped ({reason})");
+    }
+    println!("Capability parity against providers.yaml:");
+    for row in &report.parity {
+        let observed = match (row.verified_by.is_empty(), row.failed_by.is_empty()) {
+            (true, true) => "not exercised".to_string(),
+            _ => format!(
+                "verified by [{}], failed by [{}]",
+                row.verified_by.join(", "),
+                row.failed_by.join(", ")
+            ),
+        };
+        let marker = if row.contradicted() {
+            "  <- claim not met"
+        } else {
+            ""
+        };
+        println!(
+            "  {:<16} {:<9} {observed}{marker}",
+            row.capability,
+            if row.claimed { "claimed" } else { "unclaimed" }
+        );
+    }
+}
+
+fn purge_sessions(args: SessionPurgeArgs, dry_run: bool) -> Result<()> {
+    let config = load_config(None)?;
+    let store = audit_session_store(
+        wrap_session_store(
+            build_session_store(&config.stores.session)?,
+            &config.sessions,
+        ),
+        &config.stores.audit,
+        &cli_actor(),
+    )?;
+    let filter_input = SessionFilterInput {
+        tenant: args.tenant.clone(),
+        team: args.team.clone(),
+        user: args.user.clone(),
+        needs_upgrade: None,
+    };
+    let mut filter = build_session_filter(filter_input, &config.defaults);
+    let cutoff = args.cutoff_ms(now_millis());
+    if let Some(cutoff) = cutoff {
+        filter = filter.updated_before(cutoff);
+    }
+    if dry_run {
+        return print_dry_run(&preview_session_purge(store.as_ref(), &filter)?);
+    }
+    let removed = store.purge(&filter)?;
+    info!(
+        removed,
+        tenant = ?args.tenant,
+        team = ?args.team,
+        user = ?args.user,
+        updated_before_ms = ?cutoff,
+        "purged matching sessions"
+    );
+    Ok(())
+}
+
+/// `2024-05-01T00:00:00Z` (any RFC 3339 offset) or milliseconds since the epoch.
+fn parse_timestamp_ms(raw: &str) -> Result<u64> {
+    let raw = raw.trim();
+    if let Ok(ms) = raw.parse::<u64>() {
+        return Ok(ms);
+    }
+    let at = chrono::DateTime::parse_from_rfc3339(raw).map_err(|err| {
+        anyhow!("invalid timestamp {raw:?} (expected RFC 3339 or epoch milliseconds): {err}")
+    })?;
+    u64::try_from(at.timestamp_millis()).map_err(|_| anyhow!("timestamp {raw:?} is before 1970"))
+}
+
+/// Sessions `purge` would remove for `filter`: their keys and the flows they were parked in.
+fn preview_session_purge(
+    store: &dyn SessionStore,
+    filter: &SessionFilter,
+) -> Result<DryRunPreview> {
+    let mut keys = Vec::new();
+    let mut flows = BTreeSet::new();
+    store.scan(filter, &mut |record| {
+        keys.push(record.key.clone());
+        flows.extend(record.flow_id.clone());
+    })?;
+    keys.sort();
+    Ok(DryRunPreview {
+        action: "sessions purge".into(),
+        count: keys.len(),
+        keys,
+        subjects: flows.into_iter().collect(),
+    })
+}
+
+fn session_history_cli(args: SessionHistoryArgs) -> Result<()> {
+    let config = load_config(None)?;
+    let Some(log) = build_session_audit_log(&config.stores.audit)? else {
+        println!(
+            "Session auditing is off ([stores.audit] backend = \"memory\"); nothing recorded."
+        );
+        return Ok(());
+    };
+    let entries = log.history(&args.key)?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(());
+    }
+    println!(
+        "{} audit entr{} for {}:",
+        entries.len(),
+        if entries.len() == 1 { "y" } else { "ies" },
+        args.key
+    );
+    for entry in entries {
+        let at = chrono::DateTime::from_timestamp_millis(entry.timestamp_ms as i64)
+            .map(|at| at.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+            .unwrap_or_else(|| entry.timestamp_ms.to_string());
+        println!(
+            "- {at} {} by {} tenant={:?} team={:?} user={:?}",
+            entry.op.as_str(),
+            entry.actor,
+            entry.tenant,
+            entry.team,
+            entry.user
+        );
+    }
+    Ok(())
+}
+
+/// Who a CLI process writes sessions as in the audit log.
+fn cli_actor() -> String {
+    match std::env::var("USER") {
+        Ok(user) if !user.is_empty() => format!("cli:{user}"),
+        _ => "cli".into(),
+    }
+}
+
+fn export_sessions_cli(args: SessionExportArgs) -> Result<()> {
+    let config = load_config(None)?;
+    let store = wrap_session_store(
+        build_session_store(&config.stores.session)?,
+        &config.sessions,
+    );
+    if let Some(parent) = args.out.parent().filter(|dir| !dir.as_str().is_empty()) {
+        fs::create_dir_all(parent).with_context(|| format!("failed to create {parent}"))?;
+    }
+    let file =
+        fs::File::create(&args.out).with_context(|| format!("failed to create {}", args.out))?;
+    let filter = SessionFilter::new(args.tenant, None, None);
+    let written = session_transfer::export_sessions(
+        store.as_ref(),
+        &filter,
+        &mut std::io::BufWriter::new(file),
+    )?;
+    println!(
+        "Exported {written} session(s) from the {} backend to {}",
+        config.stores.session.backend.as_str(),
+        args.out
+    );
+    Ok(())
+}
+
+fn import_sessions_cli(args: SessionImportArgs, dry_run: bool) -> Result<()> {
+    let config = load_config(None)?;
+    let store = audit_session_store(
+        wrap_session_store(
+            build_session_store(&config.stores.session)?,
+            &config.sessions,
+        ),
+        &config.stores.audit,
+        &cli_actor(),
+    )?;
+    let file =
+        fs::File::open(&args.input).with_context(|| format!("failed to open {}", args.input))?;
+    let records = session_transfer::read_export(std::io::BufReader::new(file))?;
+    let tenants: BTreeSet<String> = records.iter().map(|record| record.tenant.clone()).collect();
+    let report =
+        session_transfer::import_sessions(store.as_ref(), records, args.overwrite, !dry_run)?;
+    if dry_run {
+        return print_dry_run(&DryRunPreview {
+            action: "sessions import".into(),
+            count: report.imported.len(),
+            keys: report.imported,
+            subjects: tenants.into_iter().collect(),
+        });
+    }
+    println!(
+        "Imported {} session(s) into the {} backend; skipped {} it already held newer copies of.",
+        report.imported.len(),
+        config.stores.session.backend.as_str(),
+        report.skipped.len()
+    );
+    for key in &report.skipped {
+        println!("- skipped {key}");
+    }
+    Ok(())
+}
+
+/// The `[stores.session]` settings of `config` pointed at `backend`. Connection settings
+/// carry over as they are; when the config names another backend, `file_path` belongs to
+/// that backend and is dropped so `backend` uses its default location.
+fn migration_store_config(config: &StoreConfig, backend: StoreBackend) -> StoreConfig {
+    let mut store = config.clone();
+    if store.backend != backend {
+        store.backend = backend;
+        store.file_path = None;
+    }
+    store
+}
+
+fn migrate_sessions_cli(args: SessionMigrateArgs, dry_run: bool) -> Result<()> {
+    if args.from == StoreBackend::Memory || args.to == StoreBackend::Memory {
+        bail!("the memory backend does not outlive this process; migrate between durable backends");
+    }
+    let source_config = load_config(args.from_config.as_ref())?;
+    let target_config = load_config(args.to_config.as_ref())?;
+    let source_store = migration_store_config(&source_config.stores.session, args.from);
+    let target_store = migration_store_config(&target_config.stores.session, args.to);
+    if source_store.backend == target_store.backend
+        && serde_json::to_value(&source_store)? == serde_json::to_value(&target_store)?
+    {
+        bail!(
+            "--from and --to resolve to the same {} store; point --to-config at the new one",
+            args.from.as_str()
+        );
+    }
+    let source = build_session_store(&source_store)?;
+    let target = audit_session_store(
+        build_session_store(&target_store)?,
+        &target_config.stores.audit,
+        &cli_actor(),
+    )?;
+    let report = session_transfer::migrate_sessions(
+        source.as_ref(),
+        target.as_ref(),
+        args.overwrite,
+        !dry_run,
+        args.batch,
+        &mut |done, total| eprintln!("{done}/{total} session(s) processed"),
+    )?;
+    if dry_run {
+        return print_dry_run(&DryRunPreview {
+            action: "sessions migrate".into(),
+            count: report.copied.len(),
+            keys: report.copied,
+            subjects: vec![args.from.as_str().into(), args.to.as_str().into()],
+        });
+    }
+    println!(
+        "Copied {} of {} session(s) from the {} backend to the {} backend; skipped {} it already held newer copies of.",
+        report.copied.len(),
+        report.total,
+        args.from.as_str(),
+        args.to.as_str(),
+        report.skipped.len()
+    );
+    for key in &report.skipped {
+        println!("- skipped {key}");
+    }
+    if !report.mismatched.is_empty() {
+        for key in &report.mismatched {
+            println!("- mismatch {key}");
+        }
+        bail!(
+            "{} copied session(s) did not read back identical from the {} backend",
+            report.mismatched.len(),
+            args.to.as_str()
+        );
+    }
+    println!("Verified all {} copied session(s).", report.copied.len());
+    Ok(())
+}
+
+fn compact_sessions() -> Result<()> {
+    let config = load_config(None)?;
+    let store_config = &config.stores.session;
+    if !matches!(store_config.backend, StoreBackend::File) {
+        println!(
+            "The {} session backend keeps no journal; nothing to compact.",
+            store_config.backend.as_str()
+        );
+        return Ok(());
+    }
+    let compaction = file_session_store(store_config)?.compact()?;
+    println!(
+        "Folded {} journal entr{} ({} bytes) into a snapshot of {} session(s).",
+        compaction.journal_entries,
+        if compaction.journal_entries == 1 {
+            "y"
+        } else {
+            "ies"
+        },
+        compaction.journal_bytes,
+        compaction.sessions
+    );
+    Ok(())
+}
+
+fn fsck_sessions(args: SessionFsckArgs) -> Result<()> {
+    let config = load_config(None)?;
+    let store_config = &config.stores.session;
+    let raw: Arc<dyn RawSessionAccess> = match store_config.backend {
+        StoreBackend::Memory => {
+            println!("Memory session backend keeps no persistent data; nothing to check.");
+            return Ok(());
+        }
+        StoreBackend::File => file_session_store(store_config)?,
+        StoreBackend::Redis => {
+            let url = store_config
+                .redis_url
+                .as_deref()
+                .ok_or_else(|| anyhow!("redis backend requires redis_url"))?;
+            RedisSessionStore::new(url, store_config.redis_prefix.clone())?
+        }
+        StoreBackend::Sqlite => SqliteSessionStore::new(open_sqlite(store_config)?),
+        StoreBackend::NatsKv => nats_kv_session_store(store_config)?,
+    };
+    let options = FsckOptions {
+        max_skew_ms: args.max_skew_secs.saturating_mul(1000),
+        tombstone_window_ms: config
+            .sessions
+            .soft_delete_window_secs
+            .map(|secs| secs.saturating_mul(1000)),
+        default_tenant: config.defaults.tenant.clone(),
+    };
+    let report = run_fsck(raw.as_ref(), &options, args.fix)?;
+
+    println!(
+        "Scanned {} entr(y/ies), found {} issue(s)",
+        report.scanned,
+        report.issues.len()
+    );
+    for issue in &report.issues {
+        println!(
+            "- [{}] key={:?}: {}",
+            serde_json::to_value(issue.kind)?
+                .as_str()
+                .unwrap_or_default(),
+            issue.key,
+            issue.detail
+        );
+    }
+    match report.repaired_records {
+        Some(count) => println!("Repaired store; {count} record(s) written back."),
+        None if !report.issues.is_empty() => {
+            bail!(
+                "session store has {} issue(s); rerun with --fix to repair",
+                report.issues.len()
+            )
+        }
+        None => {}
+    }
+    Ok(())
+}
+
+fn stress_sessions(args: SessionStressArgs) -> Result<()> {
+    let config = load_config(None)?;
+    let store = wrap_session_store(
+        build_session_store(&config.stores.session)?,
+        &config.sessions,
+    );
+    info!(
+        backend = ?config.stores.session.backend,
+        threads = args.threads,
+        ops = args.ops,
+        "starting session store stress run"
+    );
+    let report = run_stress(
+        store,
+        &StressOptions {
+            threads: args.threads,
+            ops: args.ops,
+            key_space: args.key_space,
+            seed: args.seed,
+        },
+    )?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!(
+            "{} op(s) on {} thread(s) in {} ms",
+            report.ops, report.threads, report.elapsed_ms
+        );
+        for summary in &report.latencies {
+            let buckets: Vec<String> = summary
+                .histogram
+                .iter()
+                .map(|(label, count)| format!("{label}={count}"))
+                .collect();
+            println!(
+                "- {:?}: n={} p50={}us p95={}us p99={}us max={}us [{}]",
+                summary.op,
+                summary.count,
+                summary.p50_us,
+                summary.p95_us,
+                summary.p99_us,
+                summary.max_us,
+                buckets.join(" ")
+            );
+        }
+    }
+
+    if !report.violations.is_empty() {
+        for violation in report.violations.iter().take(20) {
+            eprintln!("violation: {violation}");
+        }
+        bail!(
+            "stress run found {} consistency violation(s)",
+            report.violations.len()
+        );
+    }
+    Ok(())
+}
+
+async fn resume_session_cli(args: SessionResumeArgs, http: &ClientOptions) -> Result<()> {
+    let payload = args
+        .payload
+        .as_deref()
+        .map(serde_json::from_str::<Value>)
+        .transpose()
+        .context("invalid JSON payload for session resume")?
+        .unwrap_or(Value::Null);
+    let request = ResumeRequest {
+        key: args.key,
+        node_id: args.node_id,
+        tenant: args.tenant,
+        team: args.team,
+        user: args.user,
+        payload,
+        locale: None,
+    };
+    let event = bridge_client(&args.server, http).resume(&request).await?;
+    println!(
+        "Resumed flow {} for tenant={:?} user={:?}; result={}",
+        event.flow, event.tenant, event.user, event.result
+    );
+    Ok(())
+}
+
+async fn list_sessions_cli(args: SessionListArgs, http: &ClientOptions) -> Result<()> {
+    let query = SessionQuery {
+        tenant: args.tenant,
+        team: args.team,
+        user: args.user,
+        needs_upgrade: args.needs_upgrade,
+        limit: args.limit,
+        offset: args.offset,
+        cursor: args.cursor,
+        sort: args.sort,
+    };
+    let client = bridge_client(&args.server, http);
+    if args.format == ListOutput::Csv {
+        print!("{}", client.list_sessions_csv(&query).await?);
+        return Ok(());
+    }
+    let data = client.list_sessions(&query).await?;
+    println!("{} session(s):", data.count);
+    for session in data.sessions {
+        println!(
+            "- key={} tenant={} team={:?} user={:?} flow={:?} node={:?} version={:?}{}",
+            session.key,
+            session.tenant,
+            session.team,
+            session.user,
+            session.cursor.flow_id,
+            session.cursor.node_id,
+            session.flow_version,
+            if session.needs_upgrade {
+                " (needs upgrade)"
+            } else {
+                ""
+            }
+        );
+    }
+    if let Some(cursor) = data.next_cursor {
+        println!("more sessions follow: --cursor {cursor}");
+    }
+    Ok(())
+}
+
+async fn get_session_cli(args: SessionGetArgs, http: &ClientOptions) -> Result<()> {
+    let session = match bridge_client(&args.server, http)
+        .get_session(&args.key)
+        .await
+    {
+        Err(err) if err.status() == Some(404) => bail!("session {} not found", args.key),
+        other => other?,
+    };
+    println!("key: {}", session.key);
+    println!("tenant: {}", session.tenant);
+    println!("team: {:?}", session.team);
+    println!("user: {:?}", session.user);
+    println!(
+        "cursor: flow={:?} node={:?}",
+        session.cursor.flow_id, session.cursor.node_id
+    );
+    println!(
+        "pack: {:?} version={:?}{}",
+        session.pack_id,
+        session.flow_version,
+        if session.needs_upgrade {
+            " (needs upgrade)"
+        } else {
+            ""
+        }
+    );
+    println!("locale: {:?}", session.locale);
+    println!("updated_at_epoch_ms: {}", session.updated_at_epoch_ms);
+    if let Some(ttl_ms) = session.ttl_ms {
+        println!("ttl_ms: {ttl_ms}");
+    }
+    println!(
+        "context: {}",
+        serde_json::to_string_pretty(&session.context)?
+    );
+    Ok(())
+}
+
+async fn watch_sessions_cli(args: SessionWatchArgs, http: &ClientOptions) -> Result<()> {
+    let query = SessionQuery {
+        tenant: args.tenant,
+        team: args.team,
+        user: args.user,
+        ..SessionQuery::default()
+    };
+    let changes = bridge_client(&args.server, http).watch_sessions(&query);
+    let mut table = watch::SessionTable::new(args.flow);
+    follow_stream(changes, |change| {
+        let line = table.apply(change)?;
+        Some((line, table.render()))
+    })
+    .await
+}
+
+/// Feed `stream` into `apply` until Ctrl-C. On a terminal every accepted item redraws the
+/// table `apply` renders; otherwise its one-line description is printed.
+async fn follow_stream<T>(
+    stream: impl futures::Stream<Item = greentic_integration_client::Result<T>>,
+    mut apply: impl FnMut(T) -> Option<(String, String)>,
+) -> Result<()> {
+    use std::io::IsTerminal;
+    let redraw = std::io::stdout().is_terminal();
+    let mut stream = std::pin::pin!(stream);
+    loop {
+        let item = tokio::select! {
+            _ = signal::ctrl_c() => return Ok(()),
+            item = stream.next() => item,
+        };
+        let Some(item) = item else {
+            return Ok(());
+        };
+        if let Some((line, table)) = apply(item?) {
+            if redraw {
+                print!("{}{table}", watch::CLEAR_SCREEN);
+            } else {
+                println!("{line}");
+            }
+        }
+    }
+}
+
+fn build_session_store(config: &StoreConfig) -> Result<SharedSessionStore> {
+    match config.backend {
+        StoreBackend::Memory => Ok(InMemorySessionStore::new()),
+        StoreBackend::File => Ok(file_session_store(config)? as SharedSessionStore),
+        StoreBackend::Redis => {
+            let url = config
+                .redis_url
+                .as_deref()
+                .ok_or_else(|| anyhow!("redis backend requires redis_url"))?;
+            let store = RedisSessionStore::new(url, config.redis_prefix.clone())?;
+            Ok(store as SharedSessionStore)
+        }
+        StoreBackend::Sqlite => Ok(SqliteSessionStore::new(open_sqlite(config)?)),
+        StoreBackend::NatsKv => Ok(nats_kv_session_store(config)? as SharedSessionStore),
+    }
+}
+
+fn nats_kv_session_store(config: &StoreConfig) -> Result<Arc<NatsKvSessionStore>> {
+    let url = config
+        .nats_url
+        .as_deref()
+        .ok_or_else(|| anyhow!("nats_kv backend requires nats_url"))?;
+    NatsKvSessionStore::new(url, config.nats_bucket.clone())
+}
+
+fn file_session_store(config: &StoreConfig) -> Result<Arc<FileSessionStore>> {
+    let defaults = CompactionThresholds::default();
+    let thresholds = CompactionThresholds {
+        max_entries: config.journal_max_entries.unwrap_or(defaults.max_entries),
+        max_bytes: config.journal_max_bytes.unwrap_or(defaults.max_bytes),
+    };
+    let path = config
+        .file_path
+        .clone()
+        .unwrap_or_else(default_session_store_path);
+    FileSessionStore::new(workspace_root().to_path_buf(), path, thresholds)
+}
+
+fn default_sqlite_path() -> Utf8PathBuf {
+    Utf8PathBuf::from(".data/greentic.db")
+}
+
+/// The SQLite database behind a `backend = "sqlite"` store; stores may share one file.
+fn open_sqlite(config: &StoreConfig) -> Result<Arc<SqliteDb>> {
+    SqliteDb::open(
+        workspace_root().to_path_buf(),
+        config.file_path.clone().unwrap_or_else(default_sqlite_path),
+    )
+}
+
+fn build_event_store(config: &StoreConfig) -> Result<Option<Arc<dyn RunnerEventStore>>> {
+    match config.backend {
+        StoreBackend::Memory => Ok(None),
+        StoreBackend::Sqlite => Ok(Some(SqliteRunnerEventStore::new(open_sqlite(config)?))),
+        StoreBackend::File | StoreBackend::Redis | StoreBackend::NatsKv => bail!(
+            "stores.events supports the memory or sqlite backend, not {}",
+            config.backend.as_str()
+        ),
+    }
+}
+
+fn build_transcript_store(config: &StoreConfig) -> Result<SharedTranscriptStore> {
+    match config.backend {
+        StoreBackend::Memory => Ok(InMemoryTranscriptStore::new()),
+        StoreBackend::File => {
+            let dir = config
+                .file_path
+                .clone()
+                .unwrap_or_else(|| Utf8PathBuf::from(".data/transcripts"));
+            Ok(FileTranscriptStore::new(
+                workspace_root().to_path_buf(),
+                dir,
+            )?)
+        }
+        StoreBackend::Redis => {
+            let url = config
+                .redis_url
+                .as_deref()
+                .ok_or_else(|| anyhow!("redis backend requires redis_url"))?;
+            Ok(RedisTranscriptStore::new(url, config.redis_prefix.clone())?)
+        }
+        StoreBackend::Sqlite | StoreBackend::NatsKv => bail!(
+            "stores.transcript does not support the {} backend",
+            config.backend.as_str()
+        ),
+    }
+}
+
+fn build_state_store(config: &StoreConfig) -> Result<Arc<dyn state_store::StateStore>> {
+    match config.backend {
+        StoreBackend::Memory => Ok(state_store::InMemoryStateStore::new()),
+        StoreBackend::File => {
+            let path = config
+                .file_path
+                .clone()
+                .unwrap_or_else(|| Utf8PathBuf::from(".data/state.json"));
+            Ok(state_store::FileStateStore::new(
+                workspace_root().to_path_buf(),
+                path,
+            )?)
+        }
+        StoreBackend::Redis => {
+            let url = config
+                .redis_url
+                .as_deref()
+                .ok_or_else(|| anyhow!("redis backend requires redis_url"))?;
+            Ok(state_store::RedisStateStore::new(
+                url,
+                config.redis_prefix.clone(),
+            )?)
+        }
+        StoreBackend::Sqlite => Ok(state_store::SqliteStateStore::new(open_sqlite(config)?)),
+        StoreBackend::NatsKv => bail!("stores.state does not support the nats_kv backend"),
+    }
+}
+
+/// Layer session policies (soft delete) from `[sessions]` over the raw backend.
+fn build_session_audit_log(config: &StoreConfig) -> Result<Option<Arc<dyn SessionAuditLog>>> {
+    match config.backend {
+        StoreBackend::Memory => Ok(None),
+        StoreBackend::File => Ok(Some(FileSessionAuditLog::new(
+            workspace_root().to_path_buf(),
+            config
+                .file_path
+                .clone()
+                .unwrap_or_else(|| Utf8PathBuf::from(".data/session-audit.jsonl")),
+        )?)),
+        StoreBackend::Sqlite => Ok(Some(SqliteSessionAuditLog::new(open_sqlite(config)?))),
+        StoreBackend::Redis | StoreBackend::NatsKv => bail!(
+            "stores.audit does not support the {} backend",
+            config.backend.as_str()
+        ),
+    }
+}
+
+/// Record writes to `store` in the `[stores.audit]` log, attributed to `actor` unless the
+/// task says otherwise.
+fn audit_session_store(
+    store: SharedSessionStore,
+    config: &StoreConfig,
+    actor: &str,
+) -> Result<SharedSessionStore> {
+    Ok(match build_session_audit_log(config)? {
+        Some(log) => AuditedSessionStore::new(store, log, actor),
+        None => store,
+    })
+}
+
+fn wrap_session_store(store: SharedSessionStore, config: &SessionsConfig) -> SharedSessionStore {
+    match config.soft_delete_window_secs {
+        Some(window) => SoftDeleteSessionStore::new(store, window.saturating_mul(1000)),
+        None => store,
+    }
+}
+
+async fn compact_session_tombstones(store: SharedSessionStore, every: Duration) -> Result<()> {
+    let mut ticker = tokio::time::interval(every);
+    loop {
+        ticker.tick().await;
+        match store.finalize_deletions() {
+            Ok(0) => {}
+            Ok(finalized) => info!(finalized, "finalized expired session tombstones"),
+            Err(err) => warn!(?err, "session tombstone compaction failed"),
+        }
+    }
+}
+
+async fn sweep_expired_sessions(store: SharedSessionStore, every: Duration) -> Result<()> {
+    let mut ticker = tokio::time::interval(every);
+    loop {
+        ticker.tick().await;
+        match purge_expired(store.as_ref(), now_millis()) {
+            Ok(0) => {}
+            Ok(expired) => info!(expired, "removed expired sessions"),
+            Err(err) => warn!(?err, "session expiry sweep failed"),
+        }
+    }
+}
+
+async fn nudge_stale_sessions(state: AppState, every: Duration) -> Result<()> {
+    let mut ticker = tokio::time::interval(every);
+    loop {
+        ticker.tick().await;
+        match nudge_due_sessions(&state, now_millis()).await {
+            Ok(0) => {}
+            Ok(nudged) => info!(nudged, "nudged idle sessions"),
+            Err(err) => warn!(?err, "session nudge pass failed"),
+        }
+    }
+}
+
+/// Nudge every session due at `now_ms`. Each is re-read under its resume lock and skipped
+/// when it changed since the scan or is being resumed; a failed webhook leaves it due for
+/// the next pass.
+async fn nudge_due_sessions(state: &AppState, now_ms: u64) -> Result<usize> {
+    let config = &state.config.sessions.nudge;
+    let store = state.session_store.clone();
+    let ttl = Duration::from_secs(state.config.sessions.resume_lock_ttl_secs.max(1));
+    let mut nudged = 0;
+    for due in session_nudge::due(store.as_ref(), config, now_ms)? {
+        let Some(_lease) = store.try_lock(&due.session.key, ttl)? else {
+            continue;
+        };
+        match store.get(&due.session.key)? {
+            Some(current) if session_nudge::unchanged(&due, &current) => {}
+            _ => continue,
+        }
+        let payload = due.payload(config);
+        let session = &due.session;
+        if let Some(url) = config.webhook.clone() {
+            let http = OutboundHttp::new(state.network);
+            let sent =
+                tokio::task::spawn_blocking(move || http.post_json("nudge webhook", &url, payload))
+                    .await?;
+            match sent {
+                Ok((status, _)) if (200..300).contains(&status) => {}
+                Ok((status, body)) => {
+                    warn!(status, %body, key = %session.key, "nudge webhook rejected");
+                    continue;
+                }
+                Err(err) => {
+                    warn!(?err, key = %session.key, "nudge webhook failed");
+                    continue;
+                }
+            }
+        } else {
+            state
+                .runner_proxy
+                .submit(RunnerCommand::EmitActivity {
+                    flow: session.flow_id.clone().unwrap_or_default(),
+                    tenant: Some(session.tenant.clone()),
+                    team: session.team.clone(),
+                    user: session.user.clone(),
+                    payload,
+                })
+                .await;
+        }
+        info!(key = %session.key, flow = ?session.flow_id, idle_ms = due.idle_ms, "nudged idle session");
+        session_nudge::record(store.as_ref(), due.session, config, due.idle_ms, now_ms)?;
+        nudged += 1;
+    }
+    Ok(nudged)
+}
+
+async fn reap_tenant_retention(state: AppState, every: Duration) -> Result<()> {
+    let mut ticker = tokio::time::interval(every);
+    loop {
+        ticker.tick().await;
+        for (tenant, policy) in &state.config.tenants {
+            if policy.retention.is_empty() {
+                continue;
+            }
+            match reap_expired(
+                tenant,
+                &policy.retention,
+                now_millis(),
+                state.session_store.as_ref(),
+                &state.runner_events,
+                state.transcripts.as_ref(),
+            ) {
+                Ok(report) => info!(
+                    audit = "tenant_retention",
+                    %tenant,
+                    sessions = report.sessions,
+                    events = report.events,
+                    transcripts = report.transcripts,
+                    "enforced tenant retention"
+                ),
+                Err(err) => warn!(?err, %tenant, "tenant retention pass failed"),
+            }
+        }
+    }
+}
+
+/// Probe the `[providers]` endpoints every `every`; the startup probe has already run.
+async fn probe_providers(providers: Providers, http: OutboundHttp, every: Duration) -> Result<()> {
+    let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + every, every);
+    loop {
+        ticker.tick().await;
+        let probed = providers.clone();
+        let unhealthy =
+            tokio::task::spawn_blocking(move || probed.probe_all(&http, now_millis())).await?;
+        for (provider, health) in unhealthy {
+            warn!(%provider, error = ?health.error, "provider health probe failed");
+        }
+    }
+}
+
+async fn record_health_checks(
+    state: AppState,
+    packs_root: Utf8PathBuf,
+    every: Duration,
+) -> Result<()> {
+    let mut ticker = tokio::time::interval(every);
+    loop {
+        ticker.tick().await;
+        let probe_state = state.clone();
+        let root = packs_root.clone();
+        let sample = tokio::task::spawn_blocking(move || {
+            let targets = HealthTargets {
+                session_backend: probe_state.config.stores.session.backend.as_str(),
+                sessions: probe_state.session_store.as_ref(),
+                packs_root: root.as_std_path(),
+                nats_url: probe_state.config.runner.nats_url.as_deref(),
+                timeout: every.min(Duration::from_secs(5)),
+            };
+            run_checks(&targets, now_millis())
+        })
+        .await?;
+        for (check, outcome) in sample.checks.iter().filter(|(_, outcome)| !outcome.healthy) {
+            warn!(%check, error = ?outcome.error, "health check failed");
+        }
+        state.health.record(sample);
+    }
+}
+
+/// Check a request against the tenant's `[tenants.<id>]` policy and audit the decision.
+/// `payload` is the inbound traffic, if any; tenants without a policy are not checked.
+fn enforce_tenant_policy(
+    state: &AppState,
+    tenant: Option<&str>,
+    action: &str,
+    payload: Option<&Value>,
+) -> Result<(), ApiError> {
+    let Some((tenant, policy)) =
+        tenant.and_then(|tenant| state.config.tenants.get_key_value(tenant))
+    else {
+        return Ok(());
+    };
+    let decision = policy.evaluate(state.config.server.residency.as_deref(), payload);
+    info!(
+        audit = "tenant_policy",
+        %tenant,
+        action,
+        allowed = decision.allowed,
+        provider = ?decision.provider,
+        reason = ?decision.reason,
+        "evaluated tenant policy"
+    );
+    if decision.allowed {
+        return Ok(());
+    }
+    Err(ApiError::Json(
+        StatusCode::FORBIDDEN,
+        json!({
+            "error": "tenant_policy_denied",
+            "tenant": tenant,
+            "reason": decision.reason,
+        }),
+    ))
+}
+
+fn build_session_filter(input: SessionFilterInput, defaults: &SeedDefaults) -> SessionFilter {
+    let tenant =
+        sanitize_optional(input.tenant).or_else(|| sanitize_optional(defaults.tenant.clone()));
+    let team = sanitize_optional(input.team).or_else(|| sanitize_optional(defaults.team.clone()));
+    let user = sanitize_optional(input.user);
+    SessionFilter::new(tenant, team, user).with_needs_upgrade(input.needs_upgrade)
+}
+
+fn build_context_migrations(config: &SessionsConfig) -> ContextMigrations {
+    let mut migrations = ContextMigrations::default();
+    for flow_id in &config.passthrough_upgrade_flows {
+        migrations.register(flow_id.clone(), Arc::new(PassthroughMigrator));
+    }
+    migrations
+}
+
+fn normalize_upsert_payload(
+    payload: SessionUpsertRequest,
+    defaults: &SeedDefaults,
+) -> Result<SessionUpsert, StatusCode> {
+    let tenant = sanitize_optional(payload.tenant)
+        .or_else(|| sanitize_optional(defaults.tenant.clone()))
+        .ok_or_else(|| {
+            warn!("session upsert missing tenant");
+            StatusCode::BAD_REQUEST
+        })?;
+
+    let user = sanitize_optional(payload.user);
+    if user.is_none() {
+        warn!("session upsert missing user");
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let key = sanitize_optional(payload.key).unwrap_or_else(|| Uuid::new_v4().to_string());
+    let team = sanitize_optional(payload.team).or_else(|| sanitize_optional(defaults.team.clone()));
+    let flow_id = sanitize_optional(payload.flow_id);
+    let node_id = sanitize_optional(payload.node_id);
+    let locale = sanitize_optional(payload.locale);
+
+    Ok(SessionUpsert {
+        key,
+        tenant,
+        team,
+        user,
+        flow_id,
+        node_id,
+        context: payload.context.unwrap_or_default(),
+        pack_id: None,
+        flow_version: None,
+        locale,
+        ttl_ms: payload.ttl_ms.filter(|ttl| *ttl > 0),
+        pack_generation: None,
+        runner_target: None,
+    })
+}
+
+fn sanitize_optional(value: Option<String>) -> Option<String> {
+    value.map(|v| v.trim().to_owned()).filter(|v| !v.is_empty())
+}
+
+/// Feed every recorded runner event through the `[runner.anomaly]` analyzers, recording what
+/// they raise on the `anomaly` flow and posting it to the webhook, if one is configured.
+async fn detect_anomalies(
+    events: SharedRunnerEvents,
+    config: AnomalyConfig,
+    http: OutboundHttp,
+) -> Result<()> {
+    let Some(mut detector) = AnomalyDetector::from_config(&config) else {
+        return Ok(());
+    };
+    let mut live = events.subscribe();
+    loop {
+        let event = match live.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!(
+                    skipped,
+                    "anomaly detector fell behind the runner event stream"
+                );
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => return Ok(()),
+        };
+        for anomaly in detector.observe(&event, now_millis()) {
+            warn!(kind = ?anomaly.kind, flow = %anomaly.flow, "{}", anomaly.message);
+            record_runner_event(&events, anomaly.to_event());
+            if let Some(url) = config.webhook_url.clone() {
+                let payload = serde_json::to_value(&anomaly)?;
+                tokio::task::spawn_blocking(move || {
+                    match http.post_json("anomaly webhook", &url, payload) {
+                        Ok((status, _)) if (200..300).contains(&status) => {}
+                        Ok((status, body)) => warn!(status, %body, "anomaly webhook rejected"),
+                        Err(err) => warn!(?err, "anomaly webhook failed"),
+                    }
+                });
+            }
+        }
+    }
+}
+
+async fn watch_packs(pack_root: Utf8PathBuf, state: AppState) -> Result<()> {
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let root = pack_root.clone().into_std_path_buf();
+    let mut watcher: RecommendedWatcher =
+        recommended_watcher(move |res: Result<Event, notify::Error>| match res {
+            Ok(event) => {
+                trace!(?event, "pack watcher event");
+                if is_pack_change(&root, &event) {
+                    let _ = tx.send(event);
+                }
+            }
+            Err(err) => warn!(?err, "pack watcher error"),
+        })
+        .context("failed to initialize pack watcher")?;
+
+    watcher
+        .watch(pack_root.as_std_path(), RecursiveMode::Recursive)
+        .with_context(|| format!("failed to watch {pack_root}"))?;
+
+    let debounce = Duration::from_millis(state.config.packs.watch_debounce_ms);
+    let max_wait = Duration::from_millis(state.config.packs.watch_max_wait_ms);
+    info!(root = %pack_root, ?debounce, ?max_wait, "pack watcher started");
+    while let Some(changes) = next_change_burst(&mut rx, debounce, max_wait).await {
+        match reload_packs_after_change(&state).await {
+            Ok(reload) => info!(
+                changes,
+                generation = reload.index.generation,
+                "pack index reloaded after change"
+            ),
+            Err(err) => warn!(?err, "pack reload failed"),
+        }
+    }
+
+    Ok(())
+}
+
+/// Events that can change the index: anything but reads, on at least one path that is not
+/// hidden below `root` (editor swap files, `.git`), which pack hashing skips as well.
+fn is_pack_change(root: &std::path::Path, event: &Event) -> bool {
+    !matches!(event.kind, notify::EventKind::Access(_))
+        && event.paths.iter().any(|path| {
+            let relative = path.strip_prefix(root).unwrap_or_else(|_| {
+                path.file_name()
+                    .map_or(path.as_path(), std::path::Path::new)
+            });
+            !relative.iter().any(|part| {
+                part.to_str()
+                    .is_some_and(|name| name.starts_with('.') && name != "." && name != "..")
+            })
+        })
+}
+
+/// Wait for the next change, then for `quiet` without further changes, so a burst of writes
+/// (an editor save, a `git checkout`) triggers one reload that sees all of it. A burst is cut
+/// off `max_wait` after its first change; later changes start the next burst. Returns the
+/// number of events coalesced, or `None` once the watcher is gone.
+async fn next_change_burst<T>(
+    rx: &mut tokio::sync::mpsc::UnboundedReceiver<T>,
+    quiet: Duration,
+    max_wait: Duration,
+) -> Option<usize> {
+    rx.recv().await?;
+    let deadline = tokio::time::Instant::now() + max_wait;
+    let mut changes = 1;
+    loop {
+        let wait = quiet.min(deadline.saturating_duration_since(tokio::time::Instant::now()));
+        match tokio::time::timeout(wait, rx.recv()).await {
+            Ok(Some(_)) => changes += 1,
+            Ok(None) | Err(_) => return Some(changes),
+        }
+    }
+}
+
+fn resolve_packs_root(config: &PackConfig) -> Result<Utf8PathBuf> {
+    let workspace = workspace_root();
+    let workspace_root = workspace
+        .as_std_path()
+        .canonicalize()
+        .with_context(|| format!("failed to canonicalize workspace root {workspace}"))?;
+    let safe_root = normalize_under_root(&workspace_root, config.root.as_std_path())?;
+    Utf8PathBuf::from_path_buf(safe_root).map_err(|_| anyhow!("packs root is not valid UTF-8"))
+}
+
+/// Placeholder resolution for manifests: process env, then `[packs.env]`, plus the
+/// `[packs].secrets_dir` secret store when configured.
+fn pack_interpolator(config: &PackConfig) -> Interpolator {
+    let secrets = pack_secret_store(config).map(|store| Box::new(store) as Box<dyn SecretStore>);
+    Interpolator::new(config.env.clone(), secrets)
+}
+
+fn pack_secret_store(config: &PackConfig) -> Option<DirSecretStore> {
+    config
+        .secrets_dir
+        .as_ref()
+        .map(|dir| DirSecretStore::new(workspace_root().join(dir).into_std_path_buf()))
+}
+
+/// Resolve `[providers]`, with capabilities based on the simulator capability map.
+fn load_providers(config: &AppConfig) -> Result<Providers> {
+    if config.providers.is_empty() {
+        return Ok(Providers::default());
+    }
+    let path = providers_sim::capabilities::capabilities_path();
+    let base = if path.is_file() {
+        providers_sim::capabilities::load_capabilities(&path)
+            .with_context(|| format!("failed to parse {}", path.display()))?
+            .providers
+            .into_iter()
+            .map(|(name, entry)| (name, entry.capabilities))
+            .collect()
+    } else {
+        BTreeMap::new()
+    };
+    let secrets = pack_secret_store(&config.packs);
+    Providers::load(
+        &config.providers,
+        secrets.as_ref().map(|store| store as &dyn SecretStore),
+        &base,
+    )
+}
+
+fn build_pack_index(config: &PackConfig) -> Result<PackIndex> {
+    index_packs_under(&resolve_packs_root(config)?, config)
+}
+
+/// [`build_pack_index`] over an already resolved `root`, which may lie outside the workspace.
+pub(crate) fn index_packs_under(root: &Utf8Path, config: &PackConfig) -> Result<PackIndex> {
+    let interpolator = pack_interpolator(config);
+    if !root.exists() {
+        warn!(root = %root, "pack root does not exist");
+        return Ok(PackIndex::default());
+    }
+
+    let mut entries = Vec::new();
+    let mut invalid = Vec::new();
+    for path in pack_validate::find_pack_dirs(root.as_std_path(), config.max_depth)
+        .with_context(|| format!("failed to read pack root {root}"))?
+    {
+        let manifest_path = path.join("pack.json");
+        let manifest_display = manifest_path.display().to_string();
+        let raw = fs::read(&manifest_path)
+            .with_context(|| format!("failed to read {manifest_display}"))?;
+        let parsed = serde_json::from_slice(&raw)
+            .map_err(|err| format!("invalid JSON: {err}"))
+            .and_then(|manifest| {
+                interpolator
+                    .resolve(manifest)
+                    .map_err(|err| format!("failed to interpolate: {err:#}"))
+            });
+        let manifest = match parsed {
+            Ok(manifest) => manifest,
+            Err(message) => {
+                warn!(manifest = %manifest_display, %message, "skipping pack with an unreadable manifest");
+                invalid.push(InvalidPack {
+                    path: path.to_string_lossy().into_owned(),
+                    id: None,
+                    reasons: vec![ManifestViolation {
+                        pointer: String::new(),
+                        message,
+                    }],
+                });
+                continue;
+            }
+        };
+        let reasons = pack_validate::check_manifest_schema(&manifest);
+        if !reasons.is_empty() {
+            warn!(manifest = %manifest_display, violations = reasons.len(), "skipping pack with an invalid manifest");
+            invalid.push(InvalidPack {
+                path: path.to_string_lossy().into_owned(),
+                id: manifest
+                    .get("id")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                reasons,
+            });
+            continue;
+        }
+        let id = manifest
+            .get("id")
+            .and_then(|v| v.as_str())
+            .unwrap_or("unknown")
+            .to_string();
+        let name = manifest
+            .get("name")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+        let kind = manifest
+            .get("kind")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+        let tags = manifest
+            .get("tags")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|tag| tag.as_str().map(str::to_string))
+            .collect();
+        let version = manifest
+            .get("version")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+        let status = match manifest.get("status") {
+            Some(value) => PackStatus::deserialize(value).with_context(|| {
+                format!(
+                    "invalid status {value} in {manifest_display} (expected active, deprecated or disabled)"
+                )
+            })?,
+            None => PackStatus::Active,
+        };
+        let context_schemas = load_context_schemas(&path, &manifest)
+            .with_context(|| format!("invalid context_schemas in {manifest_display}"))?;
+        let event_schemas = load_schema_map(&path, &manifest, "event_schemas")
+            .with_context(|| format!("invalid event_schemas in {manifest_display}"))?;
+        let routes = pack_routes::load_routes(kind.as_deref(), &manifest)
+            .with_context(|| format!("invalid routes in {manifest_display}"))?;
+        let assets = discover_assets(&path)
+            .with_context(|| format!("failed to index assets of pack {id}"))?;
+        let contents = PackContents::scan(&path)
+            .with_context(|| format!("failed to hash the contents of pack {id}"))?;
+        let mut flows: Vec<String> = manifest
+            .get("scenarios")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .chain(
+                manifest
+                    .get("flows")
+                    .and_then(|v| v.as_array())
+                    .into_iter()
+                    .flatten(),
+            )
+            .filter_map(|item| item.get("id").and_then(|v| v.as_str()))
+            .map(|s| s.to_string())
+            .chain(context_schemas.keys().cloned())
+            .collect();
+        flows.sort();
+        flows.dedup();
+        let pack_path = match Utf8PathBuf::from_path_buf(path.clone()) {
+            Ok(p) => p,
+            Err(_) => Utf8PathBuf::from(path.to_string_lossy().to_string()),
+        };
+        let relative_path = pack_path
+            .strip_prefix(root)
+            .map(Utf8Path::to_path_buf)
+            .unwrap_or_else(|_| pack_path.clone());
+        entries.push(PackEntry {
+            id,
+            name,
+            kind,
+            version,
+            status,
+            tags,
+            assets,
+            path: pack_path,
+            relative_path,
+            flows,
+            context_schemas,
+            event_schemas,
+            routes,
+            contents,
+        });
+    }
+
+    Ok(PackIndex {
+        entries,
+        invalid,
+        generation: 0,
+    })
+}
+
+/// Embedded event schemas plus those declared by enabled packs. A pack redefining a known
+/// type with a different schema is skipped with a warning rather than failing the reload.
+fn build_schema_registry(index: &PackIndex) -> SchemaRegistry {
+    let mut registry = SchemaRegistry::builtin();
+    for entry in index.enabled() {
+        for (event_type, schema) in &entry.event_schemas {
+            if let Err(err) = registry.register(event_type, schema.clone(), Some(&entry.id)) {
+                warn!(?err, pack = %entry.id, %event_type, "ignoring pack event schema");
+            }
+        }
+    }
+    registry
+}
+
+impl PackEntry {
+    /// How the pack is announced to the runner on `runner/reload`.
+    fn descriptor(&self) -> PackDescriptor {
+        PackDescriptor {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            kind: self.kind.clone(),
+            version: self.version.clone(),
+            status: self.status.as_str().to_string(),
+            tags: self.tags.clone(),
+            path: self.path.to_string(),
+            flows: self.flows.clone(),
+        }
+    }
+}
+
+fn deprecation_warning(entry: &PackEntry) -> Option<String> {
+    (entry.status == PackStatus::Deprecated).then(|| {
+        format!(
+            "pack {} is deprecated; plan a migration before it is disabled",
+            entry.id
+        )
+    })
+}
+
+fn infer_base_deployment_plan(
+    entry: &PackEntry,
+    tenant: String,
+    environment: String,
+    placeholders: &Interpolator,
+) -> Result<DeploymentPlan> {
+    let manifest_path = entry.path.join("pack.json");
+    let raw = fs::read_to_string(&manifest_path)
+        .with_context(|| format!("failed to read {manifest_path}"))?;
+    let manifest: Value = serde_json::from_str(&raw)
+        .with_context(|| format!("invalid pack manifest {manifest_path}"))?;
+    let manifest = placeholders
+        .resolve(manifest)
+        .with_context(|| format!("failed to interpolate {manifest_path}"))?;
+    let manifest: PackManifestStub = serde_json::from_value(manifest)
+        .with_context(|| format!("invalid pack manifest {manifest_path}"))?;
+
+    let pack_version = Version::parse(&manifest.version)
+        .with_context(|| format!("invalid semver version in {manifest_path}"))?;
+
+    let channels = manifest
+        .scenarios
+        .iter()
+        .map(|scenario| ChannelPlan {
+            name: scenario.id.clone(),
+            flow_id: scenario.id.clone(),
+            kind: manifest.r#type.as_deref().unwrap_or("scenario").to_string(),
+            config: scenario.config.clone(),
+        })
+        .collect();
+
+    let mut extra = json!({
+        "pack_name": manifest.name,
+        "pack_kind": manifest.kind,
+    });
+    if let Some(warning) = deprecation_warning(entry) {
+        warn!(pack = %entry.id, "{warning}");
+        extra["warnings"] = json!([warning]);
+    }
+
+    let subjects: Vec<MessagingSubjectPlan> = manifest
+        .scenarios
+        .iter()
+        .map(|scenario| MessagingSubjectPlan {
+            name: scenario.id.clone(),
+            purpose: manifest.r#type.as_deref().unwrap_or("scenario").to_string(),
+            durable: true,
+            extra: Value::Null,
+        })
+        .collect();
+
+    let messaging = (!subjects.is_empty()).then(|| MessagingPlan {
+        logical_cluster: "default".into(),
+        subjects,
+        extra: Value::Null,
+    });
+
+    let telemetry_required = manifest
+        .kind
+        .as_deref()
+        .map(|k| k.eq_ignore_ascii_case("application") || k.eq_ignore_ascii_case("deployment"))
+        .unwrap_or(false);
+
+    Ok(DeploymentPlan {
+        pack_id: manifest.id,
+        pack_version,
+        tenant,
+        environment,
+        runners: vec![RunnerPlan {
+            name: format!("{}-runner", entry.id),
+            replicas: 1,
+            capabilities: Value::Null,
+        }],
+        messaging,
+        channels,
+        secrets: Vec::new(),
+        oauth: Vec::new(),
+        telemetry: Some(TelemetryPlan {
+            required: telemetry_required,
+            suggested_endpoint: None,
+            extra: Value::Null,
+        }),
+        extra,
+    })
+}
+
+/// Deploy-plan host bindings for components: plans come from the pack (a stored
+/// `plans/<environment>.json`, else inferred from the manifest) and statuses land in the
+/// runner event log next to the flows that emitted them.
+#[cfg(feature = "components")]
+struct AppPlanHost {
+    pack_index: SharedPackIndex,
+    runner_events: SharedRunnerEvents,
+    environment: String,
+    placeholders: Interpolator,
+}
+
+#[cfg(feature = "components")]
+impl AppPlanHost {
+    fn new(
+        pack_index: SharedPackIndex,
+        runner_events: SharedRunnerEvents,
+        environment: String,
+        packs: &PackConfig,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            pack_index,
+            runner_events,
+            environment,
+            placeholders: pack_interpolator(packs),
+        })
+    }
+}
+
+#[cfg(feature = "components")]
+impl components::PlanHost for AppPlanHost {
+    fn deployment_plan(&self, ctx: &components::InvokeContext) -> Result<String> {
+        let index = self.pack_index.read();
+        let entry = match &ctx.pack_id {
+            Some(pack_id) => index
+                .entries
+                .iter()
+                .find(|entry| entry.id == *pack_id)
+                .cloned(),
+            None => index.pack_for_flow(
+                &ctx.flow_id,
+                Some(&ctx.tenant),
+                ctx.team.as_deref(),
+                ctx.user.as_deref(),
+            ),
+        }
+        .ok_or_else(|| anyhow!("no pack found to plan flow {}", ctx.flow_id))?;
+        drop(index);
+
+        let stored = entry
+            .path
+            .join("plans")
+            .join(format!("{}.json", self.environment));
+        if stored.is_file() {
+            let raw = fs::read_to_string(&stored)
+                .with_context(|| format!("failed to read stored plan {stored}"))?;
+            let plan: DeploymentPlan = serde_json::from_str(&raw)
+                .with_context(|| format!("invalid stored plan {stored}"))?;
+            return Ok(serde_json::to_string(&plan)?);
+        }
+        let plan = infer_base_deployment_plan(
+            &entry,
+            ctx.tenant.clone(),
+            self.environment.clone(),
+            &self.placeholders,
+        )?;
+        Ok(serde_json::to_string(&plan)?)
+    }
+
+    fn emit_status(&self, ctx: &components::InvokeContext, message: &str) {
+        record_runner_event(
+            &self.runner_events,
+            RunnerEvent {
+                timestamp_ms: now_millis(),
+                flow: ctx.flow_id.clone(),
+                tenant: Some(ctx.tenant.clone()),
+                team: ctx.team.clone(),
+                user: ctx.user.clone(),
+                payload: json!({ "component": ctx.node_id, "pack_id": ctx.pack_id }),
+                result: json!({ "status": "component_status", "message": message }),
+                sequence: None,
+                instance: None,
+            },
+        );
+    }
+}
+
+fn run_pack_validator() -> Result<()> {
+    let config = load_config(None)?;
+    let packs_root = resolve_packs_root(&config.packs)?;
+    info!(root = %packs_root, "validating packs");
+    let validation =
+        pack_validate::validate_root(packs_root.as_std_path(), config.packs.max_depth)?;
+    for warning in pack_validate::external_validator_warnings(
+        packs_root.as_std_path(),
+        workspace_root().as_std_path(),
+        config.packs.max_depth,
+    ) {
+        println!("[warn] {warning}");
+    }
+    if !validation.ok {
+        let errors: Vec<_> = validation.all_errors().collect();
+        for error in &errors {
+            eprintln!("[error] {error}");
+        }
+        bail!("pack validation failed with {} error(s)", errors.len());
+    }
+    println!("Validated {} pack(s) successfully.", validation.packs.len());
+    Ok(())
+}
+
+fn list_packs(args: PackListArgs) -> Result<()> {
+    let config = load_config(None)?;
+    let packs_root = resolve_packs_root(&config.packs)?;
+    let index = build_pack_index(&config.packs)?;
+    let tenant = args.tenant.as_deref().or(config.defaults.tenant.as_deref());
+    let team = args.team.as_deref().or(config.defaults.team.as_deref());
+    let user = args.user.as_deref();
+    let (resolved, resolved_keys, missing_keys) = index.resolve_for(tenant, team, user);
+    let filter = PackFilter::new(args.kind, args.tags);
+    let resolved: Vec<PackEntry> = resolved
+        .into_iter()
+        .filter(|entry| filter.matches(entry))
+        .collect();
+
+    if resolved.is_empty() {
+        println!("No packs found under {packs_root}");
+        return Ok(());
+    }
+
+    if !resolved_keys.is_empty() {
+        println!("Resolved keys: {}", resolved_keys.join(", "));
+    } else if tenant.is_some() {
+        println!("No overrides matched; showing all packs.");
+    }
+
+    if !missing_keys.is_empty() {
+        println!("Missing overrides: {}", missing_keys.join(", "));
+    }
+
+    println!("Discovered {} pack(s):", resolved.len());
+    for entry in resolved {
+        let kind = entry.kind.as_deref().unwrap_or("unknown");
+        let status = if entry.status.is_active() {
+            String::new()
+        } else {
+            format!(" ({})", entry.status.as_str())
+        };
+        println!(
+            "- {} ({}) [{kind}]{status} @ {}",
+            entry.id,
+            entry.name.as_deref().unwrap_or("unnamed"),
+            entry.path
+        );
+    }
+    let disabled: Vec<&str> = index
+        .entries
+        .iter()
+        .filter(|entry| entry.status == PackStatus::Disabled)
+        .map(|entry| entry.id.as_str())
+        .collect();
+    if !disabled.is_empty() {
+        println!("Disabled (not resolved): {}", disabled.join(", "));
+    }
+    for pack in &index.invalid {
+        println!("Invalid manifest (skipped): {}", pack.path);
+        for reason in &pack.reasons {
+            println!("    {}: {}", reason.pointer, reason.message);
+        }
+    }
+    Ok(())
+}
+
+/// One scenario selected for a targeted integration pass.
+#[derive(Debug, Serialize)]
+struct SuiteScenario {
+    pack_id: String,
+    scenario_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entry: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    golden: Option<String>,
+    tags: Vec<String>,
+}
+
+/// Scenarios of every enabled pack accepted by `filter`, ordered by pack then scenario id.
+fn scenario_suite(index: &PackIndex, filter: &PackFilter) -> Result<Vec<SuiteScenario>> {
+    let mut suite = Vec::new();
+    for entry in index.enabled() {
+        if !filter.matches_kind(entry) {
+            continue;
+        }
+        let manifest_path = entry.path.join("pack.json");
+        let manifest: PackManifestStub = serde_json::from_str(
+            &fs::read_to_string(&manifest_path)
+                .with_context(|| format!("failed to read {manifest_path}"))?,
+        )
+        .with_context(|| format!("invalid pack manifest {manifest_path}"))?;
+        for scenario in manifest.scenarios {
+            if !filter.matches_with(entry, &scenario.tags) {
+                continue;
+            }
+            let mut tags = entry.tags.clone();
+            tags.extend(scenario.tags.iter().cloned());
+            tags.sort();
+            tags.dedup();
+            suite.push(SuiteScenario {
+                pack_id: entry.id.clone(),
+                scenario_id: scenario.id,
+                entry: scenario.entry,
+                golden: scenario.golden,
+                tags,
+            });
+        }
+    }
+    suite.sort_by(|a, b| (&a.pack_id, &a.scenario_id).cmp(&(&b.pack_id, &b.scenario_id)));
+    Ok(suite)
+}
+
+fn list_scenario_suite(args: ScenarioSuiteArgs) -> Result<()> {
+    let config = load_config(None)?;
+    let index = build_pack_index(&config.packs)?;
+    let suite = scenario_suite(&index, &PackFilter::new(args.kind, args.tags))?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&suite)?);
+        return Ok(());
+    }
+    println!("Selected {} scenario(s):", suite.len());
+    for scenario in &suite {
+        println!(
+            "- {}/{} [{}]",
+            scenario.pack_id,
+            scenario.scenario_id,
+            scenario.tags.join(", ")
+        );
+    }
+    Ok(())
+}
+
+fn plan_pack(args: PlanArgs) -> Result<()> {
+    let config = load_config(None)?;
+    let packs_root = resolve_packs_root(&config.packs)?;
+    let index = build_pack_index(&config.packs)?;
+    let tenant = args
+        .tenant
+        .or_else(|| config.defaults.tenant.clone())
+        .unwrap_or_else(|| "dev".to_string());
+    let team = config.defaults.team.as_deref();
+    let (resolved, _, _) = index.resolve_for(Some(&tenant), team, None);
+    if resolved.is_empty() {
+        bail!(
+            "no packs found under {packs_root} (consider adjusting [packs].root or tenant defaults)"
+        );
+    }
+    let entry = if let Some(pack_id) = args.pack_id.as_deref() {
+        match resolved.into_iter().find(|p| p.id == pack_id) {
+            Some(entry) => entry,
+            None if index
+                .entries
+                .iter()
+                .any(|p| p.id == pack_id && p.status == PackStatus::Disabled) =>
+            {
+                bail!("pack id {pack_id} is disabled (manifest status)")
+            }
+            None => bail!("pack id {pack_id} not found in index"),
+        }
+    } else {
+        resolved
+            .first()
+            .cloned()
+            .ok_or_else(|| anyhow!("no pack resolved"))?
+    };
+
+    let mut plan = infer_base_deployment_plan(
+        &entry,
+        tenant,
+        args.environment,
+        &pack_interpolator(&config.packs),
+    )?;
+    if args.cost {
+        load_pricing(args.pricing.as_deref())?.annotate(&mut plan)?;
+    }
+    if args.enforce_policy {
+        let path = args
+            .policy
+            .unwrap_or_else(|| workspace_root().join(PLAN_POLICY_FILE));
+        PlanPolicy::load(path.as_std_path())?.enforce(&plan)?;
+    }
+    if let Some(key) = args.key.filter(|_| args.sign) {
+        let key = plan_bundle::load_signing_key(key.as_std_path())?;
+        let content_hash = Some(entry.contents.content_hash).filter(|hash| !hash.is_empty());
+        let metadata = plan_bundle::write_bundle(
+            args.out.as_std_path(),
+            &plan,
+            content_hash,
+            &key,
+            now_millis(),
+        )?;
+        println!(
+            "signed plan bundle for {}@{} written to {} (public key {})",
+            metadata.pack_id, metadata.pack_version, args.out, metadata.public_key
+        );
+        return Ok(());
+    }
+    let json = if args.pretty {
+        serde_json::to_string_pretty(&plan)?
+    } else {
+        serde_json::to_string(&plan)?
+    };
+    println!("{json}");
+    Ok(())
+}
+
+fn verify_plan_bundle_cli(args: VerifyPlanBundleArgs) -> Result<()> {
+    let trusted = plan_bundle::load_verifying_key(args.key.as_std_path())?;
+    let (plan, metadata) = plan_bundle::verify_bundle(args.bundle.as_std_path(), &trusted)
+        .with_context(|| format!("plan bundle {} failed verification", args.bundle))?;
+    println!(
+        "plan bundle {} verified: {}@{} for tenant {} in {} (signed at {} ms by {}, {})",
+        args.bundle,
+        plan.pack_id,
+        plan.pack_version,
+        plan.tenant,
+        plan.environment,
+        metadata.signed_at_epoch_ms,
+        metadata.public_key,
+        metadata.generator
+    );
+    Ok(())
+}
+
+/// Org-wide plan rules checked by `packs plan --enforce-policy`, relative to the workspace.
+const PLAN_POLICY_FILE: &str = "plans/policy.yaml";
+
+/// Prices used by `packs plan --cost` and `packs plan-diff`, relative to the workspace.
+const PLAN_PRICING_FILE: &str = "plans/pricing.yaml";
+
+/// `path`, else the workspace pricing table when present, else the built-in prices.
+fn load_pricing(path: Option<&Utf8Path>) -> Result<PricingTable> {
+    match path {
+        Some(path) => PricingTable::load(path.as_std_path()),
+        None => {
+            let default = workspace_root().join(PLAN_PRICING_FILE);
+            if default.exists() {
+                PricingTable::load(default.as_std_path())
+            } else {
+                Ok(PricingTable::default())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct PlanDiffReport {
+    pack_id: String,
+    /// Top-level plan sections that differ (`extra.cost` annotations are ignored).
+    changed: Vec<String>,
+    cost: PlanCostComparison,
+}
+
+#[derive(Debug, Serialize)]
+struct PlanCostComparison {
+    base: PlanCost,
+    head: PlanCost,
+    delta: plan_cost::CostDelta,
+}
+
+fn plan_diff_cli(args: PlanDiffArgs) -> Result<()> {
+    let pricing = load_pricing(args.pricing.as_deref())?;
+    let read = |path: &Utf8Path| -> Result<DeploymentPlan> {
+        let raw = fs::read_to_string(path).with_context(|| format!("failed to read {path}"))?;
+        serde_json::from_str(&raw).with_context(|| format!("{path} is not a deployment plan"))
+    };
+    let report = diff_plans(&read(&args.base)?, &read(&args.head)?, &pricing)?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
+
+fn diff_plans(
+    base: &DeploymentPlan,
+    head: &DeploymentPlan,
+    pricing: &PricingTable,
+) -> Result<PlanDiffReport> {
+    let sections = |plan: &DeploymentPlan| -> Result<serde_json::Map<String, Value>> {
+        let mut value = serde_json::to_value(plan)?;
+        if let Some(extra) = value.get_mut("extra").and_then(Value::as_object_mut) {
+            extra.remove("cost");
+        }
+        match value {
+            Value::Object(map) => Ok(map),
+            _ => bail!("deployment plan did not serialize to an object"),
+        }
+    };
+    let (old, new) = (sections(base)?, sections(head)?);
+    let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    let changed = keys
+        .into_iter()
+        .filter(|key| old.get(*key) != new.get(*key))
+        .cloned()
+        .collect();
+    let (base_cost, head_cost) = (pricing.estimate(base)?, pricing.estimate(head)?);
+    Ok(PlanDiffReport {
+        pack_id: head.pack_id.clone(),
+        changed,
+        cost: PlanCostComparison {
+            delta: PlanCost::delta(&base_cost, &head_cost),
+            base: base_cost,
+            head: head_cost,
+        },
+    })
+}
+
+/// Plan snapshots are stamped with a fixed identity so they don't follow local defaults.
+const PLAN_SNAPSHOT_TENANT: &str = "dev";
+const PLAN_SNAPSHOT_ENVIRONMENT: &str = "dev";
+
+fn plan_snapshot_path(pack_id: &str) -> Result<Utf8PathBuf> {
+    plan_snapshot_path_in(&workspace_root().join("fixtures/plans"), pack_id)
+}
+
+/// `<dir>/<pack_id>.json`, refusing ids that would name a file outside `dir`.
+pub(crate) fn plan_snapshot_path_in(dir: &Utf8Path, pack_id: &str) -> Result<Utf8PathBuf> {
+    if pack_id.is_empty() || pack_id.starts_with('.') || pack_id.contains(['/', '\\']) {
+        bail!("pack id {pack_id:?} cannot be used as a snapshot file name");
+    }
+    Ok(dir.join(format!("{pack_id}.json")))
+}
+
+/// Golden form of a pack's inferred plan: sorted keys, pretty-printed, trailing newline.
+pub(crate) fn canonical_plan_snapshot(entry: &PackEntry) -> Result<String> {
+    let plan = infer_base_deployment_plan(
+        entry,
+        PLAN_SNAPSHOT_TENANT.into(),
+        PLAN_SNAPSHOT_ENVIRONMENT.into(),
+        &Interpolator::verbatim(),
+    )
+    .with_context(|| format!("failed to plan pack {}", entry.id))?;
+    let value = serde_json::to_value(&plan)?;
+    Ok(format!("{}\n", serde_json::to_string_pretty(&value)?))
+}
+
+fn plan_snapshot_cli(args: PlanSnapshotArgs) -> Result<()> {
+    let config = load_config(None)?;
+    let mut index = build_pack_index(&config.packs)?;
+    index.entries.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let mut drifted = Vec::new();
+    for entry in &index.entries {
+        let path = plan_snapshot_path(&entry.id)?;
+        let snapshot = canonical_plan_snapshot(entry)?;
+        let existing = fs::read_to_string(&path).ok();
+        if existing.as_deref() == Some(snapshot.as_str()) {
+            println!("unchanged {path}");
+        } else if args.check {
+            println!("drifted   {path}");
+            drifted.push(entry.id.clone());
+        } else {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, snapshot).with_context(|| format!("failed to write {path}"))?;
+            let verb = if existing.is_some() {
+                "updated"
+            } else {
+                "created"
+            };
+            println!("{verb}   {path}");
+        }
+    }
+    if !drifted.is_empty() {
+        bail!(
+            "plan snapshots out of date for {}; review and rerun `greentic-integration packs plan-snapshot`",
+            drifted.join(", ")
+        );
+    }
+    Ok(())
+}
+
+#[cfg(feature = "mini-runner")]
+async fn run_scenario_cli(args: RunScenarioArgs) -> Result<()> {
+    let config = load_config(None)?;
+    let index = build_pack_index(&config.packs)?;
+    let entry = index
+        .entries
+        .iter()
+        .find(|entry| entry.id == args.pack_id)
+        .ok_or_else(|| anyhow!("pack id {} not found in index", args.pack_id))?;
+    let manifest_path = entry.path.join("pack.json");
+    let manifest: PackManifestStub = serde_json::from_str(
+        &fs::read_to_string(&manifest_path)
+            .with_context(|| format!("failed to read {manifest_path}"))?,
+    )
+    .with_context(|| format!("invalid pack manifest {manifest_path}"))?;
+    let scenario = manifest
+        .scenarios
+        .iter()
+        .find(|scenario| scenario.id == args.scenario)
+        .ok_or_else(|| anyhow!("pack {} has no scenario {}", entry.id, args.scenario))?;
+    let entry_file = scenario
+        .entry
+        .as_deref()
+        .ok_or_else(|| anyhow!("scenario {} has no entry file", scenario.id))?;
+    let scenario_doc = read_pack_json(entry, entry_file)?;
+
+    let pack =
+        mini_runner::PackFlows::load(entry.path.as_std_path(), workspace_root().as_std_path())?;
+    let flow_id = match args.flow {
+        Some(flow) => flow,
+        None if pack.flows.contains_key(&scenario.id) => scenario.id.clone(),
+        None if pack.flows.len() == 1 => pack.flows.keys().next().cloned().unwrap_or_default(),
+        None => bail!(
+            "pack {} declares {} flows; pick one with --flow",
+            entry.id,
+            pack.flows.len()
+        ),
+    };
+    let tenant = args
+        .tenant
+        .or_else(|| config.defaults.tenant.clone())
+        .unwrap_or_else(|| config.packs.default_tenant.clone());
+    let runner = embedded_runner(
+        &config,
+        AppPlanHost::new(
+            Arc::new(RwLock::new(index.clone())),
+            SharedRunnerEvents::default(),
+            config.runner.environment.clone(),
+            &config.packs,
+        ),
+        NetworkPolicy::default(),
+        Arc::new(RwLock::new(build_schema_registry(&index))),
+        build_state_store(&config.stores.state)?,
+        None,
+    )?;
+    let title = format!("{}/{}", entry.id, scenario.id);
+    let annotate = |annotation: Option<Annotation>| -> Result<()> {
+        let Some(out) = &args.annotations else {
+            return Ok(());
+        };
+        let mut report = AnnotationReport::default();
+        report.extend(annotation.map(|annotation| annotation.title(title.clone())));
+        report.write(out, workspace_root())
+    };
+    let transcript = match runner
+        .run_scenario(
+            &pack,
+            &flow_id,
+            &scenario_doc,
+            mini_runner::RunInput {
+                session_id: format!("scenario-{}", scenario.id),
+                tenant,
+                team: config.defaults.team.clone(),
+                user: Some("scenario-user".into()),
+                payload: Value::Null,
+                trace: Some(TraceContext::new_root()),
+                locale: args.locale,
+                start_node: None,
+                sandbox: None,
+            },
+        )
+        .await
+    {
+        Ok(transcript) => transcript,
+        Err(err) => {
+            let entry_path =
+                normalize_under_root(entry.path.as_std_path(), std::path::Path::new(entry_file))?;
+            annotate(Some(Annotation::failure(entry_path, format!("{err:#}"))))?;
+            return Err(err);
+        }
+    };
+    for line in &transcript {
+        println!("{line}");
+    }
+
+    let Some(golden_file) = scenario.golden.as_deref().filter(|_| !args.no_golden) else {
+        return annotate(None);
+    };
+    let golden: Vec<String> = serde_json::from_value(
+        read_pack_json(entry, golden_file)?
+            .get("transcript")
+            .cloned()
+            .unwrap_or_default(),
+    )
+    .with_context(|| format!("golden {golden_file} has no transcript"))?;
+    if let Some(line) =
+        (0..transcript.len().max(golden.len())).find(|idx| transcript.get(*idx) != golden.get(*idx))
+    {
+        let message = format!(
+            "transcript diverges from {golden_file} at line {}: expected {:?}, got {:?}",
+            line + 1,
+            golden.get(line),
+            transcript.get(line)
+        );
+        let golden_path =
+            normalize_under_root(entry.path.as_std_path(), std::path::Path::new(golden_file))?;
+        let raw = fs::read_to_string(&golden_path).unwrap_or_default();
+        // Point at the diverging entry; a golden that is too short points at its last one.
+        let anchor = golden.get(line).or(golden.last());
+        let file_line = anchor
+            .and_then(|expected| {
+                let nth = golden[..line.min(golden.len() - 1)]
+                    .iter()
+                    .filter(|earlier| *earlier == expected)
+                    .count();
+                line_of(&raw, &serde_json::to_string(expected).ok()?, nth)
+            })
+            .unwrap_or(1);
+        annotate(Some(
+            Annotation::failure(golden_path, &message).line(file_line),
+        ))?;
+        bail!("{message}");
+    }
+    println!("transcript matches {golden_file}");
+    annotate(None)
+}
+
+#[cfg(feature = "mini-runner")]
+fn read_pack_json(entry: &PackEntry, relative: &str) -> Result<Value> {
+    let path = normalize_under_root(entry.path.as_std_path(), std::path::Path::new(relative))?;
+    let raw = fs::read(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    serde_json::from_slice(&raw).with_context(|| format!("invalid JSON in {}", path.display()))
+}
+
+#[cfg(feature = "mini-runner")]
+fn embedded_runner(
+    config: &AppConfig,
+    plans: Arc<dyn components::PlanHost>,
+    network: NetworkPolicy,
+    event_schemas: SharedSchemaRegistry,
+    state_store: Arc<dyn state_store::StateStore>,
+    debugger: Option<Debugger>,
+) -> Result<Arc<mini_runner::MiniRunner>> {
+    let sandboxes =
+        pack_sandbox::Sandboxes::new(config.runner.sandbox.clone(), state_store.clone());
+    let host = components::ComponentHost::new(state_store).with_plan_host(plans);
+    let mut runner = mini_runner::MiniRunner::new(Arc::new(host), config.runner.nats_url.clone())
+        .with_network_policy(network)
+        .with_event_schemas(event_schemas)
+        .with_sandboxes(sandboxes);
+    if let Some(debugger) = debugger {
+        runner = runner.with_debugger(debugger);
+    }
+    Ok(Arc::new(runner))
+}
+
+#[cfg(feature = "components")]
+fn invoke_component_cli(args: ComponentInvokeArgs) -> Result<()> {
+    let config = load_config(None)?;
+    let index = build_pack_index(&config.packs)?;
+    let mut found = None;
+    for entry in &index.entries {
+        if args.pack_id.as_ref().is_some_and(|id| *id != entry.id) {
+            continue;
+        }
+        let components = components::pack_components(entry.path.as_std_path())
+            .with_context(|| format!("failed to read components of pack {}", entry.id))?;
+        if let Some(path) = components.get(&args.id) {
+            found = Some((entry.id.clone(), path.clone()));
+            break;
+        }
+    }
+    let (pack_id, wasm) = found.ok_or_else(|| match &args.pack_id {
+        Some(pack_id) => anyhow!("pack {pack_id} declares no component {}", args.id),
+        None => anyhow!("no indexed pack declares component {}", args.id),
+    })?;
+    let input: Value = serde_json::from_str(&args.input).context("--input is not valid JSON")?;
+    let tenant = args
+        .tenant
+        .or_else(|| config.defaults.tenant.clone())
+        .unwrap_or_else(|| config.packs.default_tenant.clone());
+    info!(component = %args.id, pack = %pack_id, wasm = %wasm.display(), "invoking component");
+
+    let plans = AppPlanHost::new(
+        Arc::new(RwLock::new(index)),
+        SharedRunnerEvents::default(),
+        args.environment,
+        &config.packs,
+    );
+    let host = components::ComponentHost::new(build_state_store(&config.stores.state)?)
+        .with_plan_host(plans);
+    let invocation = host.invoke(
+        &wasm,
+        &components::InvokeContext {
+            pack_id: Some(pack_id),
+            tenant,
+            team: config.defaults.team.clone(),
+            user: None,
+            flow_id: args.flow_id,
+            node_id: args.id,
+            sandbox: None,
+        },
+        &args.op,
+        &input,
+    )?;
+    println!("{}", serde_json::to_string_pretty(&invocation)?);
+    Ok(())
+}
+
+async fn reload_packs_cli(args: ReloadArgs, http: &ClientOptions, dry_run: bool) -> Result<()> {
+    if let Some(server) = args.server {
+        if dry_run {
+            let preview = bridge_client(&server, http).preview_reload_packs().await?;
+            return print_dry_run(&preview);
+        }
+        let listing = bridge_client(&server, http).reload_packs().await?;
+        println!(
+            "Server reload succeeded: {} pack(s) at index generation {}",
+            listing.count, listing.index_generation
+        );
+        for transition in &listing.transitions {
+            println!(
+                "- {}: {} -> {}",
+                transition.id, transition.from, transition.to
+            );
+        }
+        for warning in &listing.warnings {
+            println!("warning: {warning}");
+        }
+        return Ok(());
+    }
+
+    let config = load_config(None)?;
+    let packs_root = resolve_packs_root(&config.packs)?;
+    let index = build_pack_index(&config.packs)?;
+    println!(
+        "Rebuilt pack index with {} entries under {}",
+        index.entries.len(),
+        packs_root
+    );
+    for entry in &index.entries {
+        println!(
+            "- {} ({}) @ {}",
+            entry.id,
+            entry.name.as_deref().unwrap_or("unnamed"),
+            entry.path
+        );
+    }
+    println!(
+        "Note: running servers must call /packs/reload or run with --watch to pick up changes."
+    );
+    Ok(())
+}
+
+fn build_router(state: AppState) -> Router {
+    let routes = Router::new()
+        .route("/healthz", get(healthz))
+        .route("/diagnostics/panics", get(panic_diagnostics_http))
+        .route("/diagnostics/process", get(process_diagnostics_http))
+        .route("/diagnostics/traces", get(trace_diagnostics_http))
+        .route("/readyz", get(readyz))
+        .route("/healthz/history", get(healthz_history))
+        .route("/cluster", get(cluster_status_http))
+        .route("/dev/chat/session", post(dev_chat_session_http))
+        .route("/dev/traffic", get(traffic_status_http))
+        .route("/dev/traffic/start", post(start_traffic_http))
+        .route("/dev/traffic/stop", post(stop_traffic_http))
+        .route(
+            "/debug/breakpoints",
+            get(list_breakpoints_http)
+                .post(add_breakpoint_http)
+                .delete(remove_breakpoints_http),
+        )
+        .route("/debug/paused", get(paused_runs_http))
+        .route("/debug/continue", post(continue_run_http))
+        .route("/outbox", get(outbox_http))
+        .route("/partitions", get(partitions_http))
+        .route("/providers/{name}/health", get(provider_health_http))
+        .route("/providers/{name}/webhook", post(provider_webhook_http))
+        .route("/packs", get(list_packs_http))
+        .route("/packs/reload", post(reload_packs_http))
+        .route("/packs/validate", post(validate_packs_http))
+        .route("/packs/{id}/plan", post(plan_pack_http))
+        .route("/packs/{id}/assets/{*path}", get(pack_asset_http))
+        .route("/packs/{id}/history", get(pack_history_http))
+        .route("/packs/generations", get(pack_generations_http))
+        .route("/ext/{pack}", any(pack_extension_http))
+        .route("/ext/{pack}/{*path}", any(pack_extension_http))
+        .route(
+            "/runner/events",
+            get(list_runner_events).delete(clear_runner_events_http),
+        )
+        .route("/runner/events/import", post(import_runner_events_http))
+        .route("/runner/events/stream", get(runner_events_stream_http))
+        .route("/runner/events/summary", get(runner_events_summary_http))
+        .route("/runner/events/retention", get(runner_event_retention_http))
+        .route("/runner/events/prune", post(prune_runner_events_http))
+        .route("/runner/emit", post(runner_emit_http))
+        .route("/runner/queue", get(runner_queue_http))
+        .route("/runner/targets", get(runner_targets_http))
+        .route("/schemas", get(list_schemas_http))
+        .route("/tenants/{tenant}/usage", get(tenant_usage_http))
+        .route("/metrics", get(metrics_http))
+        .route("/metrics/sessions", get(session_metrics_http))
+        .route("/schemas/{type}", get(get_schema_http))
+        .route(
+            "/sessions",
+            get(list_sessions)
+                .delete(delete_sessions)
+                .post(upsert_session),
+        )
+        .route("/sessions/resume", post(resume_session_http))
+        .route("/sessions/snapshot", post(create_session_snapshot_http))
+        .route("/sessions/snapshots", get(list_session_snapshots_http))
+        .route("/sessions/restore", post(restore_session_snapshot_http))
+        .route("/sessions/stats", get(session_stats_http))
+        .route("/sessions/stream", get(sessions_stream_http))
+        .route("/sessions/{key}", get(get_session_http))
+        .route("/sessions/{key}/restore", post(restore_session_http))
+        .route("/sessions/{key}/transcript", get(session_transcript_http));
+    with_app_layers(routes, state)
+}
+
+/// Shared layers for every route: handler panics become structured `500`s, session writes are
+/// attributed to the caller, then `AppState`; outermost, the request span the tail sampler
+/// buffers, so it sees the final status.
+fn with_app_layers(router: Router, state: AppState) -> Router {
+    let traces = state.traces.clone();
+    router
+        .layer(middleware::from_fn_with_state(
+            state.panics.clone(),
+            catch_panics,
+        ))
+        .layer(middleware::from_fn(attribute_session_writes))
+        .layer(Extension(state))
+        .layer(middleware::from_fn_with_state(traces, trace_requests))
+}
+
+/// Audit actor of an HTTP request: `local` over the Unix socket, `admin` with the admin token,
+/// `http` otherwise, plus `:<name>` when the caller names itself in `X-Greentic-Actor`.
+async fn attribute_session_writes(
+    Extension(state): Extension<AppState>,
+    local: Option<Extension<LocalPeer>>,
+    request: axum::extract::Request,
+    next: middleware::Next,
+) -> Response {
+    let headers = request.headers();
+    let base = if local.is_some() {
+        "local"
+    } else if is_admin_request(headers, &state.config.server) {
+        "admin"
+    } else {
+        "http"
+    };
+    let actor = match headers
+        .get(ACTOR_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+    {
+        Some(name) => format!("{base}:{name}"),
+        None => base.to_string(),
+    };
+    as_actor(actor, next.run(request)).await
+}
+
+async fn panic_diagnostics_http(Extension(state): Extension<AppState>) -> Json<Value> {
+    Json(json!({
+        "total": state.panics.total(),
+        "recent": state.panics.recent(),
+    }))
+}
+
+/// Requests the tail sampler kept (`[telemetry.sampling]`), newest first.
+async fn trace_diagnostics_http(
+    Extension(state): Extension<AppState>,
+) -> Json<trace_sampling::SamplingReport> {
+    Json(state.traces.report())
+}
+
+/// Resource figures a long-running load test watches for leaks.
+async fn process_diagnostics_http(Extension(state): Extension<AppState>) -> Json<Value> {
+    let queue = state.runner_proxy.queue.stats();
+    Json(json!({
+        "pid": std::process::id(),
+        "rss_bytes": process_rss_bytes(),
+        "runner_events": {
+            "len": state.runner_events.read().len(),
+            "capacity": RECENT_RUNNER_EVENTS,
+        },
+        "runner_queue": {
+            "len": queue.depth,
+            "capacity": queue.capacity,
+        },
+    }))
+}
+
+/// Resident set size of this process; `None` where `/proc` is unavailable.
+fn process_rss_bytes() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let kib = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kib * 1024)
+}
+
+async fn healthz(Extension(state): Extension<AppState>) -> Json<Value> {
+    Json(json!({
+        "status": "ok",
+        "offline": state.network.is_offline(),
+    }))
+}
+
+#[derive(Debug, Serialize)]
+struct HealthHistoryResponse {
+    interval_secs: u64,
+    #[serde(flatten)]
+    report: HealthReport,
+}
+
+/// This instance's id, the live cluster members and who holds each singleton task.
+async fn cluster_status_http(
+    Extension(state): Extension<AppState>,
+) -> Result<Json<ClusterStatus>, StatusCode> {
+    let cluster = state.cluster.clone();
+    tokio::task::spawn_blocking(move || cluster.status())
+        .await
+        .map_err(|err| {
+            error!(?err, "cluster status task failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .map(Json)
+        .map_err(|err| {
+            error!(?err, "failed to read the cluster state");
+            StatusCode::SERVICE_UNAVAILABLE
+        })
+}
+
+async fn healthz_history(Extension(state): Extension<AppState>) -> Json<HealthHistoryResponse> {
+    Json(HealthHistoryResponse {
+        interval_secs: state.config.server.health.interval_secs,
+        report: state.health.report(),
+    })
+}
+
+/// `200` while every critical background task runs, `503` while one is restarting or has
+/// failed for good; the per-task health is included either way.
+async fn readyz(Extension(state): Extension<AppState>) -> (StatusCode, Json<Value>) {
+    let ready = state.tasks.is_ready();
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (
+        status,
+        Json(json!({
+            "status": if ready { "ready" } else { "degraded" },
+            "tasks": state.tasks.snapshot(),
+        })),
+    )
+}
+
+/// `?format=` of listings that can also be downloaded as a spreadsheet.
+#[derive(Debug, Default, Deserialize)]
+struct ListFormatQuery {
+    #[serde(default)]
+    format: ListFormat,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ListFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+/// A `text/csv` download named `filename`.
+fn csv_response(filename: &str, body: String) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, csv_export::CONTENT_TYPE.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{filename}\""),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+/// Header carrying `next_cursor` for CSV pages, which have no envelope to put it in.
+const NEXT_CURSOR_HEADER: &str = "x-next-cursor";
+
+async fn list_sessions(
+    Extension(state): Extension<AppState>,
+    Query(query): Query<SessionFilterInput>,
+    Query(page): Query<SessionPageQuery>,
+    Query(output): Query<ListFormatQuery>,
+) -> Result<Response, ApiError> {
+    let filter_input = query.merge_with(None);
+    let filter = build_session_filter(filter_input, &state.config.defaults).live_at(now_millis());
+    let page = page.into_request().map_err(|err| {
+        ApiError::Json(
+            StatusCode::BAD_REQUEST,
+            json!({ "error": "invalid_page", "reason": err.to_string() }),
+        )
+    })?;
+    let page = state
+        .session_store
+        .list_page(&filter, &page)
+        .map_err(|err| {
+            error!(?err, "failed to list sessions");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    let sessions: Vec<SessionView> = page.sessions.into_iter().map(SessionView::from).collect();
+    if output.format == ListFormat::Csv {
+        let mut response = csv_response("sessions.csv", csv_export::sessions(&sessions));
+        if let Some(cursor) = page
+            .next_cursor
+            .and_then(|cursor| HeaderValue::from_str(&cursor).ok())
+        {
+            response.headers_mut().insert(NEXT_CURSOR_HEADER, cursor);
+        }
+        return Ok(response);
+    }
+    Ok(Json(SessionListResponse {
+        count: sessions.len(),
+        sessions,
+        next_cursor: page.next_cursor,
+    })
+    .into_response())
+}
+
+/// How often `GET /sessions/stream` re-reads the store for changes.
+const SESSION_STREAM_POLL: Duration = Duration::from_secs(1);
+
+/// One change seen by `GET /sessions/stream`; `removed` carries no session.
+#[derive(Debug, Serialize)]
+struct SessionChange {
+    change: &'static str,
+    key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    session: Option<SessionView>,
+}
+
+/// Server-sent `session` events for the sessions matching the filter: every current session
+/// as `created` on connect, then `created`/`updated`/`removed` as the store changes.
+async fn sessions_stream_http(
+    Extension(state): Extension<AppState>,
+    Query(query): Query<SessionFilterInput>,
+) -> Sse<impl futures::Stream<Item = Result<SseEvent, Infallible>>> {
+    let filter = build_session_filter(query.merge_with(None), &state.config.defaults);
+    let seen: HashMap<String, Value> = HashMap::new();
+    let stream = futures::stream::unfold((seen, true), move |(mut seen, first)| {
+        let state = state.clone();
+        let filter = filter.clone();
+        async move {
+            if !first {
+                tokio::time::sleep(SESSION_STREAM_POLL).await;
+            }
+            let records = match state.session_store.list(&filter) {
+                Ok(records) => records,
+                Err(err) => {
+                    warn!(?err, "session stream failed to list sessions");
+                    return Some((Vec::new(), (seen, false)));
+                }
+            };
+            let changes = diff_sessions(&mut seen, records);
+            Some((changes, (seen, false)))
+        }
+    })
+    .flat_map(|changes| {
+        futures::stream::iter(changes.into_iter().map(|change| {
+            Ok(SseEvent::default()
+                .event("session")
+                .json_data(change)
+                .unwrap_or_default())
+        }))
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// Changes between the sessions last streamed (`seen`, updated in place) and `records`.
+fn diff_sessions(
+    seen: &mut HashMap<String, Value>,
+    records: Vec<SessionRecord>,
+) -> Vec<SessionChange> {
+    let mut changes = Vec::new();
+    let mut current = HashMap::with_capacity(records.len());
+    for record in records {
+        let view = SessionView::from(record);
+        let value = serde_json::to_value(&view).unwrap_or_default();
+        let change = match seen.get(&view.key) {
+            None => Some("created"),
+            Some(previous) if *previous != value => Some("updated"),
+            Some(_) => None,
+        };
+        current.insert(view.key.clone(), value);
+        if let Some(change) = change {
+            changes.push(SessionChange {
+                change,
+                key: view.key.clone(),
+                session: Some(view),
+            });
+        }
+    }
+    let mut removed: Vec<_> = seen
+        .keys()
+        .filter(|key| !current.contains_key(*key))
+        .cloned()
+        .collect();
+    removed.sort();
+    changes.extend(removed.into_iter().map(|key| SessionChange {
+        change: "removed",
+        key,
+        session: None,
+    }));
+    *seen = current;
+    changes
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SessionStatsQuery {
+    tenant: Option<String>,
+    team: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct SessionStatsResponse {
+    #[serde(flatten)]
+    stats: SessionStats,
+    store: StoreHealth,
+}
+
+/// Counts across every tenant unless `tenant`/`team` narrow them; seed defaults do not apply.
+async fn session_stats_http(
+    Extension(state): Extension<AppState>,
+    Query(query): Query<SessionStatsQuery>,
+) -> Result<Json<SessionStatsResponse>, StatusCode> {
+    let store = StoreHealth::probe(
+        state.config.stores.session.backend.as_str(),
+        state.session_store.as_ref(),
+    );
+    let filter = SessionFilter::new(
+        sanitize_optional(query.tenant),
+        sanitize_optional(query.team),
+        None,
+    );
+    let stats =
+        collect_stats(state.session_store.as_ref(), &filter, now_millis()).map_err(|err| {
+            error!(?err, "failed to compute session stats");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    Ok(Json(SessionStatsResponse { stats, store }))
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PartitionsQuery {
+    /// `tenant` (default) or `session`.
+    by: Option<String>,
+    /// Only this tenant (and its sessions).
+    tenant: Option<String>,
+    /// Preview another partition count instead of `[partitioning].partitions`.
+    partitions: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+struct PartitionsResponse {
+    by: &'static str,
+    #[serde(flatten)]
+    assigned: Assignments,
+}
+
+/// Current tenant (configured or with sessions) or session assignments and per-partition load.
+async fn partitions_http(
+    Extension(state): Extension<AppState>,
+    Query(query): Query<PartitionsQuery>,
+) -> Result<Json<PartitionsResponse>, ApiError> {
+    let bad_request =
+        |message: String| ApiError::Json(StatusCode::BAD_REQUEST, json!({ "error": message }));
+    let partitioner = match query.partitions {
+        Some(partitions) => {
+            Partitioner::new(partitions).map_err(|err| bad_request(err.to_string()))?
+        }
+        None => state.partitioner,
+    };
+    let filter = SessionFilter::new(sanitize_optional(query.tenant), None, None);
+    let sessions = state.session_store.list(&filter).map_err(|err| {
+        error!(?err, "failed to list sessions for partition assignments");
+        ApiError::Status(StatusCode::INTERNAL_SERVER_ERROR)
+    })?;
+    let (by, keys): (_, Vec<String>) = match query.by.as_deref().unwrap_or("tenant") {
+        "tenant" => {
+            let mut tenants: BTreeSet<String> = state
+                .config
+                .tenants
+                .keys()
+                .filter(|tenant| filter.tenant.as_ref().is_none_or(|only| only == *tenant))
+                .cloned()
+                .collect();
+            tenants.extend(sessions.into_iter().map(|session| session.tenant));
+            ("tenant", tenants.into_iter().collect())
+        }
+        "session" => (
+            "session",
+            sessions.into_iter().map(|session| session.key).collect(),
+        ),
+        other => {
+            return Err(bad_request(format!(
+                "unknown partition key {other:?}; expected tenant or session"
+            )));
+        }
+    };
+    Ok(Json(PartitionsResponse {
+        by,
+        assigned: partitioner.assign(keys),
+    }))
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PackQuery {
+    tenant: Option<String>,
+    team: Option<String>,
+    user: Option<String>,
+    kind: Option<String>,
+    /// Comma-separated tags; all must match.
+    tag: Option<String>,
+}
+
+async fn list_packs_http(
+    Extension(state): Extension<AppState>,
+    Query(query): Query<PackQuery>,
+) -> Json<PackListResponse> {
+    let index = state.pack_index.read().clone();
+    list_packs_filtered(
+        &index,
+        query
+            .tenant
+            .as_deref()
+            .or(state.config.defaults.tenant.as_deref()),
+        query
+            .team
+            .as_deref()
+            .or(state.config.defaults.team.as_deref()),
+        query.user.as_deref(),
+        &PackFilter::new(query.kind.clone(), query.tag.clone()),
+    )
+}
+
+fn list_packs_filtered(
+    index: &PackIndex,
+    tenant: Option<&str>,
+    team: Option<&str>,
+    user: Option<&str>,
+    filter: &PackFilter,
+) -> Json<PackListResponse> {
+    let (resolved, resolved_keys, missing_keys) = index.resolve_for(tenant, team, user);
+    let resolved: Vec<PackEntry> = resolved
+        .into_iter()
+        .filter(|entry| filter.matches(entry))
+        .collect();
+    let packs = resolved
+        .iter()
+        .map(|entry| PackInfo {
+            id: entry.id.clone(),
+            name: entry.name.clone(),
+            kind: entry.kind.clone(),
+            status: entry.status,
+            tags: entry.tags.clone(),
+            assets: entry.assets.clone(),
+            path: entry.path.to_string(),
+            relative_path: entry.relative_path.to_string(),
+        })
+        .collect::<Vec<_>>();
+    let warnings = resolved.iter().filter_map(deprecation_warning).collect();
+    Json(PackListResponse {
+        count: packs.len(),
+        packs,
+        resolved_keys,
+        missing_keys,
+        index_generation: index.generation,
+        warnings,
+        transitions: Vec::new(),
+        invalid: index.invalid.clone(),
+    })
+}
+
+async fn list_runner_events(
+    Extension(state): Extension<AppState>,
+    Query(output): Query<ListFormatQuery>,
+) -> Response {
+    let events = state.runner_events.read().clone();
+    match output.format {
+        ListFormat::Json => Json(events).into_response(),
+        ListFormat::Csv => csv_response("runner-events.csv", csv_export::runner_events(&events)),
+    }
+}
+
+/// Server-sent `runner_event` events for every event recorded after connecting. A client
+/// that falls more than the stream buffer behind gets a `lagged` event with the skip count.
+async fn runner_events_stream_http(
+    Extension(state): Extension<AppState>,
+) -> Sse<impl futures::Stream<Item = Result<SseEvent, Infallible>>> {
+    let stream = futures::stream::unfold(state.runner_events.subscribe(), |mut live| async move {
+        let event = match live.recv().await {
+            Ok(event) => SseEvent::default()
+                .event("runner_event")
+                .json_data(event)
+                .unwrap_or_default(),
+            Err(broadcast::error::RecvError::Lagged(skipped)) => SseEvent::default()
+                .event("lagged")
+                .data(skipped.to_string()),
+            Err(broadcast::error::RecvError::Closed) => return None,
+        };
+        Some((Ok(event), live))
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct EventSummaryQuery {
+    /// Look-back window such as `15m`, `1h` or `7d`; all cached events when omitted.
+    window: Option<String>,
+    /// Comma-separated keys: `flow`, `tenant`, `team`, `user`, `status`.
+    group_by: Option<String>,
+}
+
+async fn runner_events_summary_http(
+    Extension(state): Extension<AppState>,
+    Query(query): Query<EventSummaryQuery>,
+) -> Result<Json<event_summary::EventSummary>, ApiError> {
+    let bad_request = |err: anyhow::Error| {
+        ApiError::Json(StatusCode::BAD_REQUEST, json!({ "error": err.to_string() }))
+    };
+    let window = query
+        .window
+        .as_deref()
+        .map(event_summary::parse_window)
+        .transpose()
+        .map_err(bad_request)?;
+    let group_by = event_summary::parse_group_by(query.group_by.as_deref().unwrap_or_default())
+        .map_err(bad_request)?;
+    let events = state.runner_events.read();
+    Ok(Json(event_summary::summarize(
+        &events,
+        now_millis(),
+        window,
+        &group_by,
+    )))
+}
+
+/// `POST /runner/events/prune` limits; each replaces its `[runner.event_retention]` value.
+/// `dry_run=true` answers with a [`DryRunPreview`] of the events the pass would remove.
+#[derive(Debug, Default, Deserialize)]
+struct PruneQuery {
+    max_age_secs: Option<u64>,
+    max_events: Option<usize>,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+async fn prune_runner_events_http(
+    Extension(state): Extension<AppState>,
+    Query(query): Query<PruneQuery>,
+) -> Result<Response, ApiError> {
+    let configured = &state.config.runner.event_retention;
+    let limits = EventRetentionConfig {
+        max_age_secs: query.max_age_secs.or(configured.max_age_secs),
+        max_events: query.max_events.or(configured.max_events),
+        ..configured.clone()
+    };
+    if !limits.is_enabled() {
+        return Err(ApiError::Json(
+            StatusCode::BAD_REQUEST,
+            json!({
+                "error": "no_retention_limits",
+                "reason": "set [runner.event_retention] or pass max_age_secs / max_events",
+            }),
+        ));
+    }
+    if query.dry_run {
+        let preview = tokio::task::spawn_blocking(move || {
+            preview_prune(&state.runner_events, &limits, now_millis())
+        })
+        .await
+        .map_err(|err| {
+            error!(?err, "runner event prune preview task failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .map_err(|err| {
+            error!(?err, "failed to preview runner event prune");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+        return Ok(Json(prune_preview(&preview)).into_response());
+    }
+    let report = tokio::task::spawn_blocking(move || {
+        state
+            .event_retention
+            .prune(&state.runner_events, &limits, now_millis())
+    })
+    .await
+    .map_err(|err| {
+        error!(?err, "runner event prune task failed");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .map_err(|err| {
+        error!(?err, "failed to prune runner events");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    info!(
+        audit = "event_retention",
+        expired = report.expired,
+        trimmed = report.trimmed,
+        manual = true,
+        "pruned runner events"
+    );
+    Ok(Json(report).into_response())
+}
+
+/// The events a prune would drop, counted by flow like a clear preview.
+fn prune_preview(doomed: &[RunnerEvent]) -> DryRunPreview {
+    let mut by_flow = BTreeMap::new();
+    for event in doomed {
+        *by_flow.entry(event.flow.as_str()).or_insert(0) += 1;
+    }
+    DryRunPreview {
+        action: "runner prune".into(),
+        count: doomed.len(),
+        keys: Vec::new(),
+        subjects: by_flow
+            .iter()
+            .map(|(flow, count)| format!("{flow} ({count})"))
+            .collect(),
+    }
+}
+
+async fn runner_event_retention_http(Extension(state): Extension<AppState>) -> Json<Value> {
+    Json(json!({
+        "config": state.config.runner.event_retention,
+        "stats": state.event_retention.stats(),
+    }))
+}
+
+/// `?dry_run=true` on a mutating endpoint: answer with a [`DryRunPreview`] instead.
+#[derive(Debug, Default, Deserialize)]
+struct DryRunQuery {
+    #[serde(default)]
+    dry_run: bool,
+}
+
+async fn clear_runner_events_http(
+    Extension(state): Extension<AppState>,
+    Query(query): Query<DryRunQuery>,
+) -> Result<Response, StatusCode> {
+    if query.dry_run {
+        return Ok(Json(preview_clear_runner_events(&state).map_err(|err| {
+            error!(?err, "failed to count persisted runner events");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?)
+        .into_response());
+    }
+    state.runner_events.write().clear();
+    if let Some(store) = state.runner_events.store()
+        && let Err(err) = store.clear()
+    {
+        error!(?err, "failed to clear persisted runner events");
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Events a clear would drop, by flow: the durable store's when one is configured (it holds
+/// every cached event), otherwise the in-memory cache's.
+fn preview_clear_runner_events(state: &AppState) -> Result<DryRunPreview> {
+    let by_flow = match state.runner_events.store() {
+        Some(store) => store.count_by_flow()?,
+        None => {
+            let mut by_flow = BTreeMap::new();
+            for event in state.runner_events.read().iter() {
+                *by_flow.entry(event.flow.clone()).or_insert(0) += 1;
+            }
+            by_flow
+        }
+    };
+    Ok(DryRunPreview {
+        action: "runner clear".into(),
+        count: by_flow.values().sum(),
+        keys: Vec::new(),
+        subjects: by_flow
+            .iter()
+            .map(|(flow, count)| format!("{flow} ({count})"))
+            .collect(),
+    })
+}
+
+#[derive(Debug, Serialize)]
+struct RunnerEventImportResponse {
+    imported: usize,
+}
+
+/// Record events produced outside the bridge (`runner import`) through the event policy and
+/// keep the cached log in timestamp order, so they interleave with synthesized events.
+async fn import_runner_events_http(
+    Extension(state): Extension<AppState>,
+    Json(events): Json<Vec<RunnerEvent>>,
+) -> Json<RunnerEventImportResponse> {
+    let imported = events.len();
+    for event in events {
+        record_runner_event(&state.runner_events, event);
+    }
+    state
+        .runner_events
+        .write()
+        .sort_by_key(|event| event.timestamp_ms);
+    Json(RunnerEventImportResponse { imported })
+}
+
+#[derive(Debug, Serialize)]
+struct RunnerQueueResponse {
+    #[serde(flatten)]
+    stats: QueueStats,
+    dead_letters: Vec<DeadLetter>,
+}
+
+async fn runner_queue_http(Extension(state): Extension<AppState>) -> Json<RunnerQueueResponse> {
+    let queue = &state.runner_proxy.queue;
+    Json(RunnerQueueResponse {
+        stats: queue.stats(),
+        dead_letters: queue.dead_letters(),
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RunnerEmitRequest {
+    flow: String,
+    tenant: Option<String>,
+    team: Option<String>,
+    user: Option<String>,
+    payload: Option<Value>,
+    /// Pack index generation the caller resolved the flow against; checked when present.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    index_generation: Option<u64>,
+    /// Locale for outbound message templates; defaults to `payload.locale`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    locale: Option<String>,
+}
+
+async fn runner_emit_http(
+    Extension(state): Extension<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<RunnerEmitRequest>,
+) -> Result<Json<RunnerEvent>, ApiError> {
+    if let Some(observed) = req.index_generation {
+        check_index_generation(state.pack_index.read().generation, Some(observed))?;
+    }
+    let payload = req.payload.unwrap_or(Value::Null);
+    check_event_schema(&state, &payload)?;
+    let caller = FlowCaller {
+        tenant: req.tenant.or_else(|| state.config.defaults.tenant.clone()),
+        team: req.team.or_else(|| state.config.defaults.team.clone()),
+        user: req.user,
+        locale: inbound_locale(req.locale, &payload),
+        start_node: None,
+        generation: None,
+    };
+    enforce_tenant_policy(
+        &state,
+        caller.tenant.as_deref(),
+        "runner_emit",
+        Some(&payload),
+    )?;
+    let event = run_flow_event(
+        &state,
+        req.flow,
+        caller,
+        payload,
+        http_trace_context(&headers),
+    )
+    .await;
+    record_runner_event(&state.runner_events, event.clone());
+    Ok(Json(event))
+}
+
+/// Run the routed flow for one inbound email, as `POST /runner/emit` would for a message.
+async fn deliver_email(state: AppState, route: EmailRoute, email: InboundEmail) -> Result<()> {
+    let payload = email.payload();
+    let caller = FlowCaller {
+        tenant: Some(route.tenant),
+        team: route.team.or_else(|| state.config.defaults.team.clone()),
+        user: Some(email.from),
+        locale: None,
+        start_node: None,
+        generation: None,
+    };
+    enforce_tenant_policy(
+        &state,
+        caller.tenant.as_deref(),
+        "email_ingress",
+        Some(&payload),
+    )
+    .map_err(|err| anyhow!("tenant policy refused the message ({})", err.status()))?;
+    let event = run_flow_event(&state, route.flow, caller, payload, None).await;
+    record_runner_event(&state.runner_events, event);
+    Ok(())
+}
+
+/// Reject an ingested event whose `type` is registered but whose body breaks its schema.
+fn check_event_schema(state: &AppState, event: &Value) -> Result<(), ApiError> {
+    let violations = state.event_schemas.read().validate(event).map_err(|err| {
+        error!(?err, "failed to evaluate event schema");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if violations.is_empty() {
+        return Ok(());
+    }
+    let event_type = event.get("type").cloned().unwrap_or(Value::Null);
+    warn!(%event_type, ?violations, "runner event rejected by event schema");
+    Err(ApiError::Json(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        json!({
+            "error": "event_schema_violation",
+            "type": event_type,
+            "violations": violations,
+        }),
+    ))
+}
+
+async fn list_schemas_http(Extension(state): Extension<AppState>) -> Json<Value> {
+    let registry = state.event_schemas.read();
+    let schemas: Vec<Value> = registry
+        .schemas()
+        .map(|schema| {
+            json!({
+                "type": schema.event_type,
+                "name": schema.name,
+                "version": schema.version,
+                "pack": schema.pack,
+            })
+        })
+        .collect();
+    Json(json!({ "count": schemas.len(), "schemas": schemas }))
+}
+
+/// Schema for an exact versioned type, or the latest version when the `.vN` suffix is omitted.
+async fn get_schema_http(
+    Extension(state): Extension<AppState>,
+    Path(event_type): Path<String>,
+) -> Result<Json<event_schema::EventSchema>, StatusCode> {
+    state
+        .event_schemas
+        .read()
+        .resolve(&event_type)
+        .cloned()
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Explicit request locale, else a `locale` carried by the inbound message payload.
+fn inbound_locale(explicit: Option<String>, payload: &Value) -> Option<String> {
+    sanitize_optional(explicit).or_else(|| {
+        payload
+            .get("locale")
+            .and_then(Value::as_str)
+            .and_then(|locale| sanitize_optional(Some(locale.to_string())))
+    })
+}
+
+/// Caller's W3C `traceparent`, continued by flow runs so their NATS publishes join its trace.
+fn http_trace_context(headers: &HeaderMap) -> Option<TraceContext> {
+    headers
+        .get(TRACEPARENT)
+        .and_then(|value| value.to_str().ok())
+        .and_then(TraceContext::parse)
+}
+
+/// Stale-read guard for pack-dependent mutations: the caller must echo the
+/// `index_generation` it observed on `/packs`, or get 428/409 instead of acting on an index
+/// that was swapped by a reload in the meantime.
+fn check_index_generation(current: u64, observed: Option<u64>) -> Result<(), ApiError> {
+    match observed {
+        None => Err(ApiError::Json(
+            StatusCode::PRECONDITION_REQUIRED,
+            json!({ "error": "index_generation is required", "index_generation": current }),
+        )),
+        Some(observed) if observed != current => Err(ApiError::Json(
+            StatusCode::CONFLICT,
+            json!({
+                "error": "pack index changed since it was observed",
+                "observed": observed,
+                "index_generation": current,
+            }),
+        )),
+        Some(_) => Ok(()),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PackPlanRequest {
+    index_generation: Option<u64>,
+    tenant: Option<String>,
+    #[serde(default = "default_plan_environment")]
+    environment: String,
+}
+
+async fn plan_pack_http(
+    Extension(state): Extension<AppState>,
+    Path(pack_id): Path<String>,
+    Json(req): Json<PackPlanRequest>,
+) -> Result<Json<DeploymentPlan>, ApiError> {
+    let tenant = sanitize_optional(req.tenant)
+        .or_else(|| state.config.defaults.tenant.clone())
+        .unwrap_or_else(|| state.config.packs.default_tenant.clone());
+    let entry = {
+        let index = state.pack_index.read();
+        check_index_generation(index.generation, req.index_generation)?;
+        let (resolved, _, _) =
+            index.resolve_for(Some(&tenant), state.config.defaults.team.as_deref(), None);
+        resolved
+            .into_iter()
+            .find(|entry| entry.id == pack_id)
+            .ok_or(StatusCode::NOT_FOUND)?
+    };
+    let plan = infer_base_deployment_plan(
+        &entry,
+        tenant,
+        req.environment,
+        &pack_interpolator(&state.config.packs),
+    )
+    .map_err(|err| {
+        error!(?err, pack = %pack_id, "failed to infer deployment plan");
+        StatusCode::UNPROCESSABLE_ENTITY
+    })?;
+    Ok(Json(plan))
+}
+
+#[derive(Debug, Serialize)]
+struct PackHistoryResponse {
+    pack_id: String,
+    /// Version and content hash in the current index; absent once the pack was removed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    current: Option<Value>,
+    /// Newest first.
+    changes: Vec<PackChange>,
+}
+
+/// The current pack index generation, the superseded ones still draining with their remaining
+/// sessions and flow runs, and recent releases. Releases the generations that have drained.
+async fn pack_generations_http(
+    Extension(state): Extension<AppState>,
+) -> Result<Json<GenerationsReport>, StatusCode> {
+    let sessions = drain_pack_generations(&state).map_err(|err| {
+        error!(?err, "failed to count sessions per pack generation");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(state.pack_generations.report(&sessions)))
+}
+
+/// Recorded version and content changes of a pack, including packs no longer indexed.
+async fn pack_history_http(
+    Extension(state): Extension<AppState>,
+    Path(pack_id): Path<String>,
+) -> Result<Json<PackHistoryResponse>, ApiError> {
+    let current = state
+        .pack_index
+        .read()
+        .entries
+        .iter()
+        .find(|entry| entry.id == pack_id)
+        .map(
+            |entry| json!({"version": entry.version, "content_hash": entry.contents.content_hash}),
+        );
+    let history = state.pack_history.clone();
+    let id = pack_id.clone();
+    let changes = tokio::task::spawn_blocking(move || history.changes(&id))
+        .await
+        .map_err(|err| {
+            error!(?err, "pack history task failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .map_err(|err| {
+            error!(?err, pack = %pack_id, "failed to read pack history");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if current.is_none() && changes.is_empty() {
+        return Err(StatusCode::NOT_FOUND.into());
+    }
+    Ok(Json(PackHistoryResponse {
+        pack_id,
+        current,
+        changes,
+    }))
+}
+
+/// Serve a file indexed under `<pack>/assets/`. Only paths discovered at index time are
+/// served (reload to publish new files); responses carry an `ETag` and honour `If-None-Match`.
+async fn pack_asset_http(
+    Extension(state): Extension<AppState>,
+    Path((pack_id, asset_path)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    if !is_safe_asset_path(&asset_path) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let pack_dir = {
+        let index = state.pack_index.read();
+        let entry = index
+            .enabled()
+            .find(|entry| entry.id == pack_id)
+            .ok_or(StatusCode::NOT_FOUND)?;
+        if !entry.assets.iter().any(|asset| asset.path == asset_path) {
+            return Err(StatusCode::NOT_FOUND);
+        }
+        entry.path.join(ASSETS_DIR)
+    };
+    let file = normalize_under_root(pack_dir.as_std_path(), std::path::Path::new(&asset_path))
+        .map_err(|err| {
+            warn!(?err, pack = %pack_id, asset = %asset_path, "refusing pack asset");
+            StatusCode::NOT_FOUND
+        })?;
+    let bytes = fs::read(&file).map_err(|_| StatusCode::NOT_FOUND)?;
+    let etag = etag_for(&bytes);
+    let cache_headers = [
+        (header::ETAG, etag.clone()),
+        (
+            header::CACHE_CONTROL,
+            "public, max-age=300, must-revalidate".to_string(),
+        ),
+    ];
+    let fresh = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value.split(',').any(|tag| {
+                let tag = tag.trim();
+                tag == "*" || tag == etag
+            })
+        });
+    if fresh {
+        return Ok((StatusCode::NOT_MODIFIED, cache_headers).into_response());
+    }
+    Ok((
+        cache_headers,
+        [(header::CONTENT_TYPE, content_type_for(&asset_path))],
+        bytes,
+    )
+        .into_response())
+}
+
+/// A route declared by an enabled `extension` pack, looked up in the current index so a
+/// reload takes effect on the next request. Responses render the route's `body` (strings as
+/// `text/plain`, anything else as JSON); forward routes publish the request and answer `202`.
+async fn pack_extension_http(
+    Extension(state): Extension<AppState>,
+    Path(params): Path<BTreeMap<String, String>>,
+    Query(query): Query<BTreeMap<String, String>>,
+    method: Method,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, ApiError> {
+    let pack_id = params.get("pack").cloned().unwrap_or_default();
+    let path = format!(
+        "/{}",
+        params.get("path").map(String::as_str).unwrap_or_default()
+    );
+    let (route, captures) = {
+        let index = state.pack_index.read();
+        let entry = index
+            .enabled()
+            .find(|entry| entry.id == pack_id && !entry.routes.is_empty())
+            .ok_or(StatusCode::NOT_FOUND)?;
+        match pack_routes::match_route(&entry.routes, method.as_str(), &path) {
+            RouteMatch::Found(route, captures) => (route.clone(), captures),
+            RouteMatch::MethodNotAllowed => return Err(StatusCode::METHOD_NOT_ALLOWED.into()),
+            RouteMatch::NotFound => return Err(StatusCode::NOT_FOUND.into()),
+        }
+    };
+    let body = if body.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_slice(&body)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&body).into_owned()))
+    };
+    let vars = RequestVars {
+        pack: pack_id,
+        method: route.method.clone(),
+        params: captures,
+        query,
+        body,
+    };
+    if let Some(forward) = &route.forward {
+        if !state.route_forwarder.is_configured() {
+            return Err(ApiError::Json(
+                StatusCode::SERVICE_UNAVAILABLE,
+                json!({"error": "forward routes need runner.nats_url"}),
+            ));
+        }
+        let subject = pack_routes::render_text(&forward.subject, &vars);
+        let trace = http_trace_context(&headers)
+            .map(|parent| parent.child())
+            .unwrap_or_else(TraceContext::new_root);
+        if let Err(err) = state
+            .route_forwarder
+            .publish(&subject, &vars.to_message(&path), &trace)
+            .await
+        {
+            error!(?err, pack = %vars.pack, %subject, "failed to forward extension route");
+            return Err(ApiError::Json(
+                StatusCode::BAD_GATEWAY,
+                json!({"error": format!("{err:#}")}),
+            ));
+        }
+    }
+    let status =
+        StatusCode::from_u16(route.status()).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let mut response = match route
+        .body
+        .as_ref()
+        .map(|body| pack_routes::render(body, &vars))
+    {
+        None => status.into_response(),
+        Some(Value::String(text)) => (status, text).into_response(),
+        Some(value) => (status, Json(value)).into_response(),
+    };
+    for (name, value) in &route.headers {
+        let value = HeaderValue::from_str(&pack_routes::render_text(value, &vars))
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        response.headers_mut().insert(name, value);
+    }
+    Ok(response)
+}
+
+#[derive(Debug, Deserialize)]
+struct SessionResumeRequest {
+    /// Resume this session directly instead of looking one up by tenant/team/user; any of
+    /// those that are also given must match it.
+    #[serde(default)]
+    key: Option<String>,
+    /// Run the flow from this node instead of its entry, e.g. to restart a stuck flow at a
+    /// specific step. Not available to dev chat tokens.
+    #[serde(default)]
+    node_id: Option<String>,
+    tenant: Option<String>,
+    team: Option<String>,
+    user: Option<String>,
+    payload: Option<Value>,
+    /// Overrides the session's stored locale for this turn.
+    #[serde(default)]
+    locale: Option<String>,
+}
+
+async fn resume_session_http(
+    Extension(state): Extension<AppState>,
+    headers: HeaderMap,
+    Json(mut req): Json<SessionResumeRequest>,
+) -> Result<Json<RunnerEvent>, ApiError> {
+    let started = std::time::Instant::now();
+    let grant = chat_grant(&state, &headers)?;
+    if let Some(grant) = &grant {
+        if !grant.covers(
+            req.tenant.as_deref(),
+            req.team.as_deref(),
+            req.user.as_deref(),
+        ) {
+            return Err(chat_scope_violation(grant));
+        }
+        if req.node_id.is_some() {
+            return Err(chat_scope_violation(grant));
+        }
+        req.tenant = Some(grant.tenant.clone());
+        req.team = grant.team.clone();
+        req.user = Some(grant.user.clone());
+    }
+    let payload = req.payload.unwrap_or(Value::Null);
+    let (session, tenant, user) = match req.key {
+        Some(key) => {
+            let filter = SessionFilter::new(req.tenant, req.team, req.user).live_at(now_millis());
+            let session = state
+                .session_store
+                .get(&key)
+                .map_err(|err| {
+                    error!(?err, %key, "session lookup failed");
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?
+                .filter(|session| !session.is_deleted() && filter.matches(session))
+                .ok_or(StatusCode::NOT_FOUND)?;
+            let tenant = Some(session.tenant.clone());
+            enforce_tenant_policy(&state, tenant.as_deref(), "session_resume", Some(&payload))?;
+            let user = session.user.clone();
+            (session, tenant, user)
+        }
+        None => {
+            let tenant = req.tenant.or_else(|| state.config.defaults.tenant.clone());
+            let user = req.user.clone();
+            if user.is_none() {
+                return Err(StatusCode::BAD_REQUEST.into());
+            }
+            enforce_tenant_policy(&state, tenant.as_deref(), "session_resume", Some(&payload))?;
+            let team = match &grant {
+                Some(grant) => grant.team.clone(),
+                None => req.team.or_else(|| state.config.defaults.team.clone()),
+            };
+            let filter =
+                SessionFilter::new(tenant.clone(), team, user.clone()).live_at(now_millis());
+            let session = state
+                .session_store
+                .find(&filter)
+                .map_err(|err| {
+                    error!(?err, "session lookup failed");
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?
+                .ok_or(StatusCode::NOT_FOUND)?;
+            (session, tenant, user)
+        }
+    };
+    if let Some(grant) = grant.filter(|grant| grant.session_key != session.key) {
+        return Err(chat_scope_violation(&grant));
+    }
+    let (_lease, session) = lease_session_for_resume(&state, &session.key)?;
+    let flow = session.flow_id.clone().ok_or(StatusCode::BAD_REQUEST)?;
+    let session = upgrade_session_for_resume(&state, session, &flow)?;
+    let pin = pin_runner_target(
+        &state,
+        &session.key,
+        &session.tenant,
+        session.runner_target.as_deref(),
+    );
+    check_session_context(
+        &state,
+        &session.tenant,
+        session.team.as_deref(),
+        session.user.as_deref(),
+        &flow,
+        &session.context,
+    )?;
+    let caller = FlowCaller {
+        tenant,
+        team: session.team.clone(),
+        user,
+        locale: inbound_locale(req.locale, &payload).or_else(|| session.locale.clone()),
+        start_node: sanitize_optional(req.node_id),
+        generation: session.pack_generation,
+    };
+    if let Some(node) = &caller.start_node {
+        info!(key = %session.key, %flow, %node, stored_node = ?session.node_id, "resuming session at an operator-chosen node");
+    }
+    let event = run_flow_event(&state, flow, caller, payload, http_trace_context(&headers)).await;
+    let entries = transcript_entries(&event);
+    if let Err(err) = state.transcripts.append(&session.key, &entries) {
+        error!(?err, key = %session.key, "failed to persist session transcript");
+    }
+    if let Err(err) = state.session_store.remove(&session.key) {
+        error!(?err, key = %session.key, "failed to clear resumed session");
+        return Err(StatusCode::INTERNAL_SERVER_ERROR.into());
+    }
+    record_runner_event(&state.runner_events, event.clone());
+    if let Some(pin) = pin {
+        state
+            .runner_proxy
+            .submit(RunnerCommand::ForwardActivity {
+                target: pin.target,
+                url: pin.url,
+                event: event.clone(),
+            })
+            .await;
+    }
+    notify_resume_webhook(&state, &session, &event);
+    state
+        .session_metrics
+        .observe_resume(&session.tenant, started.elapsed());
+    Ok(Json(event))
+}
+
+/// POST a resume's event to its tenant's `resume_webhook`, if one is configured. Delivery is
+/// best effort and does not hold up the resume.
+fn notify_resume_webhook(state: &AppState, session: &SessionRecord, event: &RunnerEvent) {
+    let Some(url) = state
+        .config
+        .tenants
+        .get(&session.tenant)
+        .and_then(|tenant| tenant.resume_webhook.clone())
+    else {
+        return;
+    };
+    let payload = match serde_json::to_value(event) {
+        Ok(payload) => payload,
+        Err(err) => {
+            warn!(?err, key = %session.key, "failed to encode resume webhook payload");
+            return;
+        }
+    };
+    let (http, key) = (OutboundHttp::new(state.network), session.key.clone());
+    tokio::task::spawn_blocking(
+        move || match http.post_json("resume webhook", &url, payload) {
+            Ok((status, _)) if (200..300).contains(&status) => {}
+            Ok((status, body)) => warn!(status, %body, %key, "resume webhook rejected"),
+            Err(err) => warn!(?err, %key, "resume webhook failed"),
+        },
+    );
+}
+
+/// Lock `key` for the rest of a resume and re-read the session under the lock, so exactly one
+/// of several concurrent resumes runs the flow; the others get `409`.
+fn lease_session_for_resume(
+    state: &AppState,
+    key: &str,
+) -> Result<(SessionLease, SessionRecord), ApiError> {
+    let conflict = |reason: &str| {
+        warn!(%key, reason, "concurrent session resume rejected");
+        ApiError::Json(StatusCode::CONFLICT, json!({ "error": reason, "key": key }))
+    };
+    let ttl = Duration::from_secs(state.config.sessions.resume_lock_ttl_secs.max(1));
+    let lease = state
+        .session_store
+        .try_lock(key, ttl)
+        .map_err(|err| {
+            error!(?err, %key, "failed to lock session for resume");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or_else(|| conflict("session_resume_in_progress"))?;
+    let session = state
+        .session_store
+        .get(key)
+        .map_err(|err| {
+            error!(?err, %key, "session lookup failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or_else(|| conflict("session_already_resumed"))?;
+    Ok((lease, session))
+}
+
+/// Migrate a session pinned to an older flow version, refusing to resume it when no
+/// migrator is registered for the flow.
+fn upgrade_session_for_resume(
+    state: &AppState,
+    session: SessionRecord,
+    flow_id: &str,
+) -> Result<SessionRecord, ApiError> {
+    // A session on a draining generation finishes against the flow version it started on.
+    if session
+        .pack_generation
+        .is_some_and(|generation| state.pack_generations.draining_index(generation).is_some())
+    {
+        return Ok(session);
+    }
+    let current_version = state
+        .pack_index
+        .read()
+        .pack_for_flow(
+            flow_id,
+            Some(&session.tenant),
+            session.team.as_deref(),
+            session.user.as_deref(),
+        )
+        .and_then(|pack| pack.version);
+    let key = session.key.clone();
+    let upgrade = upgrade_session(
+        &state.context_migrations,
+        session.clone(),
+        current_version.as_deref(),
+    )
+    .map_err(|err| {
+        error!(?err, %key, %flow_id, "session context migration failed");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    match upgrade {
+        SessionUpgrade::Current => Ok(session),
+        SessionUpgrade::Migrated(record) => {
+            info!(%key, %flow_id, from = ?session.flow_version, to = ?record.flow_version, "migrated session context");
+            Ok(*record)
+        }
+        SessionUpgrade::Blocked {
+            from_version,
+            to_version,
+        } => {
+            warn!(%key, %flow_id, ?from_version, %to_version, "session needs upgrade but no migrator is registered");
+            Err(ApiError::Json(
+                StatusCode::CONFLICT,
+                json!({
+                    "error": "session_needs_upgrade",
+                    "flow_id": flow_id,
+                    "from_version": from_version,
+                    "to_version": to_version,
+                }),
+            ))
+        }
+    }
+}
+
+async fn delete_sessions(
+    Extension(state): Extension<AppState>,
+    local: Option<Extension<LocalPeer>>,
+    headers: HeaderMap,
+    Query(query): Query<SessionFilterInput>,
+    Query(options): Query<SessionPurgeOptions>,
+    body: Option<Json<SessionFilterInput>>,
+) -> Result<Json<SessionPurgeResponse>, StatusCode> {
+    let body_filter = body.map(|Json(inner)| inner);
+    let merged = query.merge_with(body_filter);
+    let filter = build_session_filter(merged, &state.config.defaults);
+    let matching = state.session_store.list(&filter).map_err(|err| {
+        error!(?err, "failed to list sessions for purge");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let matched = matching.len();
+
+    if options.dry_run {
+        info!(
+            audit = "session_purge",
+            matched,
+            dry_run = true,
+            tenant = ?filter.tenant,
+            team = ?filter.team,
+            user = ?filter.user,
+            "session purge preview"
+        );
+        let mut keys: Vec<String> = matching.iter().map(|record| record.key.clone()).collect();
+        keys.sort();
+        return Ok(Json(SessionPurgeResponse {
+            removed: 0,
+            matched,
+            dry_run: true,
+            keys: Some(keys),
+            sessions: Some(matching.into_iter().map(SessionView::from).collect()),
+        }));
+    }
+
+    let admin = local.is_some() || is_admin_request(&headers, &state.config.server);
+    let threshold = state.config.sessions.purge_confirm_threshold;
+    if matched > threshold && !options.confirm && !admin {
+        warn!(
+            matched,
+            threshold, "session purge exceeds threshold without confirm=true; refusing"
+        );
+        return Err(StatusCode::PRECONDITION_REQUIRED);
+    }
+
+    let removed = state.session_store.purge(&filter).map_err(|err| {
+        error!(?err, "failed to purge sessions via HTTP");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    info!(
+        audit = "session_purge",
+        removed,
+        matched,
+        confirm = options.confirm,
+        admin,
+        tenant = ?filter.tenant,
+        team = ?filter.team,
+        user = ?filter.user,
+        "purged sessions via HTTP"
+    );
+    Ok(Json(SessionPurgeResponse {
+        removed,
+        matched,
+        dry_run: false,
+        keys: None,
+        sessions: None,
+    }))
+}
+
+async fn create_session_snapshot_http(
+    Extension(state): Extension<AppState>,
+) -> Result<(StatusCode, Json<SnapshotInfo>), StatusCode> {
+    let snapshot = state
+        .session_snapshots
+        .create(state.session_store.as_ref(), now_millis())
+        .map_err(|err| {
+            error!(?err, "failed to snapshot sessions");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    info!(
+        audit = "session_snapshot",
+        snapshot = %snapshot.name,
+        sessions = snapshot.sessions,
+        "snapshotted sessions via HTTP"
+    );
+    Ok((StatusCode::CREATED, Json(snapshot)))
+}
+
+async fn list_session_snapshots_http(
+    Extension(state): Extension<AppState>,
+) -> Result<Json<Vec<SnapshotInfo>>, StatusCode> {
+    state.session_snapshots.list().map(Json).map_err(|err| {
+        error!(?err, "failed to list session snapshots");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SessionRestoreRequest {
+    /// Snapshot name as returned by `POST /sessions/snapshot`; the newest when omitted.
+    #[serde(default)]
+    snapshot: Option<String>,
+    #[serde(default)]
+    confirm: bool,
+}
+
+/// Return the store to a snapshot. Like purges, a restore that would remove more sessions
+/// than `purge_confirm_threshold` needs `confirm` or an admin token.
+async fn restore_session_snapshot_http(
+    Extension(state): Extension<AppState>,
+    local: Option<Extension<LocalPeer>>,
+    headers: HeaderMap,
+    body: Option<Json<SessionRestoreRequest>>,
+) -> Result<Json<RestoreReport>, ApiError> {
+    let Json(req) = body.unwrap_or_default();
+    let known = state.session_snapshots.list().map_err(|err| {
+        error!(?err, "failed to list session snapshots");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let exists = match &req.snapshot {
+        Some(name) => known.iter().any(|snapshot| &snapshot.name == name),
+        None => !known.is_empty(),
+    };
+    if !exists {
+        return Err(ApiError::Json(
+            StatusCode::NOT_FOUND,
+            json!({"error": "snapshot not found", "snapshot": req.snapshot}),
+        ));
+    }
+    let admin = local.is_some() || is_admin_request(&headers, &state.config.server);
+    let threshold = state.config.sessions.purge_confirm_threshold;
+    let mut refused = 0;
+    let report = state
+        .session_snapshots
+        .restore(
+            state.session_store.as_ref(),
+            req.snapshot.as_deref(),
+            |removed| {
+                refused = removed;
+                removed <= threshold || req.confirm || admin
+            },
+        )
+        .map_err(|err| {
+            error!(?err, "failed to restore a session snapshot");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    let Some(report) = report else {
+        warn!(
+            removed = refused,
+            threshold, "session restore exceeds threshold without confirm; refusing"
+        );
+        return Err(ApiError::Json(
+            StatusCode::PRECONDITION_REQUIRED,
+            json!({"error": "restore would remove sessions; pass confirm", "removed": refused}),
+        ));
+    };
+    info!(
+        audit = "session_restore",
+        snapshot = %report.snapshot,
+        restored = report.restored,
+        removed = report.removed.len(),
+        confirm = req.confirm,
+        admin,
+        "restored sessions via HTTP"
+    );
+    Ok(Json(report))
+}
+
+fn is_admin_request(headers: &HeaderMap, server: &ServerConfig) -> bool {
+    let Some(expected) = server.admin_token.as_deref().filter(|t| !t.is_empty()) else {
+        return false;
+    };
+    bearer_token(headers).is_some_and(|token| tokens_match(token, expected))
+}
+
+/// Compare tokens through their SHA-256 digests without an early exit, so the time taken does
+/// not reveal how much of a guess was right.
+fn tokens_match(given: &str, expected: &str) -> bool {
+    let (given, expected) = (Sha256::digest(given), Sha256::digest(expected));
+    given
+        .iter()
+        .zip(expected.iter())
+        .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+/// The dev chat grant behind a `gtc_` bearer token. Requests without one are not scoped;
+/// an unknown or expired chat token is rejected with `401`.
+fn chat_grant(state: &AppState, headers: &HeaderMap) -> Result<Option<ChatGrant>, ApiError> {
+    let Some(token) = bearer_token(headers).filter(|token| token.starts_with(CHAT_TOKEN_PREFIX))
+    else {
+        return Ok(None);
+    };
+    match state.chat_tokens.verify(token, now_millis()) {
+        Some(grant) => Ok(Some(grant)),
+        None => Err(ApiError::Json(
+            StatusCode::UNAUTHORIZED,
+            json!({ "error": "chat_token_invalid" }),
+        )),
+    }
+}
+
+fn chat_scope_violation(grant: &ChatGrant) -> ApiError {
+    warn!(key = %grant.session_key, tenant = %grant.tenant, "dev chat token used outside its session");
+    ApiError::Json(
+        StatusCode::FORBIDDEN,
+        json!({ "error": "chat_token_scope", "session_key": grant.session_key }),
+    )
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct DevChatSessionRequest {
+    #[serde(default)]
+    tenant: Option<String>,
+    #[serde(default)]
+    team: Option<String>,
+    /// Defaults to a generated `webchat-<id>` user.
+    #[serde(default)]
+    user: Option<String>,
+    #[serde(default)]
+    flow_id: Option<String>,
+    #[serde(default)]
+    node_id: Option<String>,
+    #[serde(default)]
+    locale: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct DevChatSessionResponse {
+    token: String,
+    token_type: &'static str,
+    expires_at_epoch_ms: u64,
+    session: SessionView,
+}
+
+/// Seed a session under a generated key and mint a token scoped to it, so the dev chat page
+/// can resume it and read its transcript without holding the admin token.
+async fn dev_chat_session_http(
+    Extension(state): Extension<AppState>,
+    payload: Option<Json<DevChatSessionRequest>>,
+) -> Result<Json<DevChatSessionResponse>, ApiError> {
+    let dev_chat = &state.config.server.dev_chat;
+    if !dev_chat.enabled {
+        return Err(StatusCode::NOT_FOUND.into());
+    }
+    let req = payload.map(|Json(req)| req).unwrap_or_default();
+    let key = Uuid::new_v4().to_string();
+    let user = req.user.unwrap_or_else(|| format!("webchat-{}", &key[..8]));
+    let Json(session) = upsert_session(
+        Extension(state.clone()),
+        HeaderMap::new(),
+        Json(SessionUpsertRequest {
+            key: Some(key),
+            tenant: req.tenant,
+            team: req.team,
+            user: Some(user),
+            flow_id: req.flow_id,
+            node_id: req.node_id,
+            context: None,
+            locale: req.locale,
+            ttl_ms: None,
+        }),
+    )
+    .await?;
+    let grant = ChatGrant {
+        session_key: session.key.clone(),
+        tenant: session.tenant.clone(),
+        team: session.team.clone(),
+        user: session.user.clone().unwrap_or_default(),
+        expires_at_epoch_ms: 0,
+    };
+    let ttl = Duration::from_secs(dev_chat.token_ttl_secs.max(1));
+    let (token, grant) = state.chat_tokens.issue(grant, ttl, now_millis());
+    info!(
+        audit = "dev_chat_token",
+        key = %grant.session_key,
+        tenant = %grant.tenant,
+        expires_at_epoch_ms = grant.expires_at_epoch_ms,
+        "issued dev chat token"
+    );
+    Ok(Json(DevChatSessionResponse {
+        token,
+        token_type: "Bearer",
+        expires_at_epoch_ms: grant.expires_at_epoch_ms,
+        session,
+    }))
+}
+
+async fn traffic_status_http(
+    Extension(state): Extension<AppState>,
+) -> Result<Json<TrafficStatus>, ApiError> {
+    if !state.config.server.traffic.enabled {
+        return Err(StatusCode::NOT_FOUND.into());
+    }
+    Ok(Json(state.traffic.status()))
+}
+
+/// Start generating the posted profile, or `[server.traffic.profile]` without a body.
+async fn start_traffic_http(
+    Extension(state): Extension<AppState>,
+    profile: Option<Json<TrafficProfile>>,
+) -> Result<Json<TrafficStatus>, ApiError> {
+    if !state.config.server.traffic.enabled {
+        return Err(StatusCode::NOT_FOUND.into());
+    }
+    let profile = profile
+        .map(|Json(profile)| profile)
+        .unwrap_or_else(|| state.config.server.traffic.profile.clone());
+    match start_traffic(&state, profile) {
+        Ok(Some(status)) => Ok(Json(status)),
+        Ok(None) => Err(ApiError::Json(
+            StatusCode::CONFLICT,
+            json!({
+                "error": "traffic_running",
+                "message": "synthetic traffic is already running; stop it first",
+            }),
+        )),
+        Err(err) => Err(ApiError::Json(
+            StatusCode::BAD_REQUEST,
+            json!({ "error": "invalid_traffic_profile", "message": err.to_string() }),
+        )),
+    }
+}
+
+async fn stop_traffic_http(
+    Extension(state): Extension<AppState>,
+) -> Result<Json<TrafficStatus>, ApiError> {
+    if !state.config.server.traffic.enabled {
+        return Err(StatusCode::NOT_FOUND.into());
+    }
+    let status = state.traffic.stop();
+    info!(sent = ?status.sent, "stopped synthetic traffic");
+    Ok(Json(status))
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct OutboxQuery {
+    /// List every kept entry with this status as well.
+    #[serde(default)]
+    status: Option<OutboxStatus>,
+}
+
+async fn outbox_http(
+    Extension(state): Extension<AppState>,
+    Query(query): Query<OutboxQuery>,
+) -> Result<Json<Value>, ApiError> {
+    if !state.config.server.outbox.enabled {
+        return Err(StatusCode::NOT_FOUND.into());
+    }
+    let outbox = state.outbox.clone();
+    let entries = tokio::task::spawn_blocking(move || outbox.entries())
+        .await
+        .map_err(|err| {
+            error!(?err, "outbox task failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .map_err(|err| {
+            error!(?err, "failed to read the outbox");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    let mut body = json!({
+        "counts": Outbox::counts(&entries),
+        "stuck": state.outbox.stuck(&entries, now_millis()),
+    });
+    if let Some(status) = query.status {
+        body["entries"] = json!(
+            entries
+                .iter()
+                .filter(|entry| entry.status == status)
+                .collect::<Vec<_>>()
+        );
+    }
+    Ok(Json(body))
+}
+
+/// `200` while the provider's latest probe passed, `503` otherwise (or before the first).
+/// Inbound message from the simulated SMS/WhatsApp provider: run the flow its business
+/// number routes to, as `POST /runner/emit` would.
+async fn provider_webhook_http(
+    Extension(state): Extension<AppState>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    Json(inbound): Json<providers_sim::phone::PhoneInbound>,
+) -> Result<Json<RunnerEvent>, ApiError> {
+    let Some(channel) = providers_sim::phone::PhoneChannel::from_provider(&name) else {
+        return Err(ApiError::Json(
+            StatusCode::NOT_FOUND,
+            json!({ "error": "unknown_provider", "provider": name }),
+        ));
+    };
+    let Some(route) = state.config.phone.route(channel, &inbound.to).cloned() else {
+        warn!(provider = %name, to = %inbound.to, "no phone route for inbound message");
+        return Err(ApiError::Json(
+            StatusCode::NOT_FOUND,
+            json!({ "error": "no_phone_route", "provider": name, "to": inbound.to }),
+        ));
+    };
+    let payload = phone_channel::inbound_payload(channel, &inbound);
+    let caller = FlowCaller {
+        tenant: Some(route.tenant),
+        team: route.team.or_else(|| state.config.defaults.team.clone()),
+        user: Some(phone_channel::normalize_number(&inbound.from)),
+        locale: None,
+        start_node: None,
+        generation: None,
+    };
+    enforce_tenant_policy(
+        &state,
+        caller.tenant.as_deref(),
+        "provider_webhook",
+        Some(&payload),
+    )?;
+    let event = run_flow_event(
+        &state,
+        route.flow,
+        caller,
+        payload,
+        http_trace_context(&headers),
+    )
+    .await;
+    record_runner_event(&state.runner_events, event.clone());
+    Ok(Json(event))
+}
+
+async fn provider_health_http(
+    Extension(state): Extension<AppState>,
+    Path(name): Path<String>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let Some(endpoint) = state.providers.get(&name) else {
+        return Err(ApiError::Json(
+            StatusCode::NOT_FOUND,
+            json!({ "error": "unknown_provider", "provider": name }),
+        ));
+    };
+    let health = state.providers.health(&name);
+    let status = if health.as_ref().is_some_and(|health| health.healthy) {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    Ok((
+        status,
+        Json(json!({
+            "provider": endpoint,
+            "health": health,
+        })),
+    ))
+}
+
+fn require_debugger(state: &AppState) -> Result<&Debugger, ApiError> {
+    if !state.config.server.debug.enabled {
+        return Err(StatusCode::NOT_FOUND.into());
+    }
+    Ok(&state.debugger)
+}
+
+async fn list_breakpoints_http(
+    Extension(state): Extension<AppState>,
+) -> Result<Json<Value>, ApiError> {
+    let debugger = require_debugger(&state)?;
+    Ok(Json(json!({ "breakpoints": debugger.breakpoints() })))
+}
+
+async fn add_breakpoint_http(
+    Extension(state): Extension<AppState>,
+    Json(breakpoint): Json<Breakpoint>,
+) -> Result<Json<Value>, ApiError> {
+    let debugger = require_debugger(&state)?;
+    let added = debugger.add(breakpoint.clone());
+    info!(flow = %breakpoint.flow, node = %breakpoint.node, added, "set flow breakpoint");
+    Ok(Json(
+        json!({ "added": added, "breakpoints": debugger.breakpoints() }),
+    ))
+}
+
+/// Remove the breakpoint in the body, or every breakpoint without one.
+async fn remove_breakpoints_http(
+    Extension(state): Extension<AppState>,
+    body: Option<Json<Breakpoint>>,
+) -> Result<Json<Value>, ApiError> {
+    let debugger = require_debugger(&state)?;
+    let removed = match body {
+        Some(Json(breakpoint)) => usize::from(debugger.remove(&breakpoint)),
+        None => debugger.clear(),
+    };
+    Ok(Json(
+        json!({ "removed": removed, "breakpoints": debugger.breakpoints() }),
+    ))
+}
+
+async fn paused_runs_http(Extension(state): Extension<AppState>) -> Result<Json<Value>, ApiError> {
+    let debugger = require_debugger(&state)?;
+    Ok(Json(json!({ "paused": debugger.paused() })))
+}
+
+#[derive(Debug, Deserialize)]
+struct DebugContinueRequest {
+    id: String,
+    #[serde(flatten)]
+    resume: Resume,
+}
+
+async fn continue_run_http(
+    Extension(state): Extension<AppState>,
+    Json(req): Json<DebugContinueRequest>,
+) -> Result<Json<Value>, ApiError> {
+    let debugger = require_debugger(&state)?;
+    let step = req.resume.step;
+    if !debugger.resume(&req.id, req.resume) {
+        return Err(ApiError::Json(
+            StatusCode::NOT_FOUND,
+            json!({ "error": "not_paused", "id": req.id }),
+        ));
+    }
+    info!(id = %req.id, step, "continued paused flow run");
+    Ok(Json(json!({ "continued": req.id, "step": step })))
+}
+
+/// Start a traffic run of `profile`; `None` when one is already going.
+fn start_traffic(state: &AppState, profile: TrafficProfile) -> Result<Option<TrafficStatus>> {
+    let default_tenant = state
+        .config
+        .defaults
+        .tenant
+        .clone()
+        .unwrap_or_else(|| state.config.packs.default_tenant.clone());
+    let planner = TrafficPlanner::new(profile.clone(), &default_tenant, now_millis())?;
+    let task_state = state.clone();
+    let status = state.traffic.start(profile, now_millis(), move |stats| {
+        tokio::spawn(generate_traffic(task_state, planner, stats))
+    });
+    if status.is_some() {
+        info!("started synthetic traffic");
+    }
+    Ok(status)
+}
+
+/// Send `planner`'s turns through the emit, session and resume handlers until aborted.
+async fn generate_traffic(state: AppState, mut planner: TrafficPlanner, stats: Arc<TrafficStats>) {
+    let mut ticker = tokio::time::interval(planner.pause());
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        let action = planner.next();
+        let result = send_traffic(&state, &action).await;
+        if let Err(err) = &result {
+            debug!(?action, status = %err.status(), "synthetic traffic turn failed");
+        }
+        planner.record(&action, result.is_ok());
+        stats.record(&action, result.is_ok());
+    }
+}
+
+async fn send_traffic(state: &AppState, action: &TrafficAction) -> Result<(), ApiError> {
+    match action.clone() {
+        TrafficAction::Message {
+            tenant,
+            user,
+            flow,
+            payload,
+            ..
+        } => {
+            let Json(_event) = runner_emit_http(
+                Extension(state.clone()),
+                HeaderMap::new(),
+                Json(RunnerEmitRequest {
+                    flow: flow.clone(),
+                    tenant: Some(tenant.clone()),
+                    team: None,
+                    user: Some(user.clone()),
+                    payload: Some(payload),
+                    index_generation: None,
+                    locale: None,
+                }),
+            )
+            .await?;
+            // Park the conversation so a later turn can resume it.
+            let Json(_session) = upsert_session(
+                Extension(state.clone()),
+                HeaderMap::new(),
+                Json(SessionUpsertRequest {
+                    key: Some(format!("traffic-{tenant}-{user}")),
+                    tenant: Some(tenant),
+                    team: None,
+                    user: Some(user),
+                    flow_id: Some(flow),
+                    node_id: Some("await-reply".into()),
+                    context: None,
+                    locale: None,
+                    ttl_ms: None,
+                }),
+            )
+            .await?;
+        }
+        TrafficAction::Resume {
+            tenant,
+            user,
+            payload,
+        } => {
+            let Json(_event) = resume_session_http(
+                Extension(state.clone()),
+                HeaderMap::new(),
+                Json(SessionResumeRequest {
+                    key: None,
+                    node_id: None,
+                    tenant: Some(tenant),
+                    team: None,
+                    user: Some(user),
+                    payload: Some(payload),
+                    locale: None,
+                }),
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// One session by key; tombstoned and expired sessions are `404` like missing ones.
+async fn get_session_http(
+    Extension(state): Extension<AppState>,
+    Path(key): Path<String>,
+) -> Result<Json<SessionView>, StatusCode> {
+    let record = state.session_store.get(&key).map_err(|err| {
+        error!(?err, %key, "failed to read session");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    record
+        .filter(|record| !record.is_expired(now_millis()))
+        .map(|record| Json(SessionView::from(record)))
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn restore_session_http(
+    Extension(state): Extension<AppState>,
+    Path(key): Path<String>,
+) -> Result<Json<SessionView>, StatusCode> {
+    let restored = state.session_store.restore(&key).map_err(|err| {
+        error!(?err, %key, "failed to restore session");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let record = restored.ok_or(StatusCode::NOT_FOUND)?;
+    info!(audit = "session_restore", %key, "restored soft-deleted session");
+    Ok(Json(SessionView::from(record)))
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct TranscriptQuery {
+    /// Compare against this pack's scenario golden (requires `scenario`).
+    pack: Option<String>,
+    scenario: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct TranscriptResponse {
+    key: String,
+    count: usize,
+    entries: Vec<TranscriptEntry>,
+    /// `USER:`/`BOT:` lines, the golden-file form.
+    transcript: Vec<String>,
+    /// Same hash providers-sim reports for golden snapshots.
+    transcript_hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    golden: Option<GoldenComparison>,
+}
+
+#[derive(Debug, Serialize)]
+struct GoldenComparison {
+    pack_id: String,
+    scenario_id: String,
+    path: String,
+    transcript_hash: String,
+    matches: bool,
+    /// First line index where live traffic and the golden differ.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    first_difference: Option<usize>,
+}
+
+async fn session_transcript_http(
+    Extension(state): Extension<AppState>,
+    headers: HeaderMap,
+    Path(key): Path<String>,
+    Query(query): Query<TranscriptQuery>,
+) -> Result<Json<TranscriptResponse>, ApiError> {
+    if let Some(grant) = chat_grant(&state, &headers)?.filter(|grant| grant.session_key != key) {
+        return Err(chat_scope_violation(&grant));
+    }
+    let entries = state.transcripts.get(&key).map_err(|err| {
+        error!(?err, %key, "failed to read session transcript");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if entries.is_empty() {
+        return Err(StatusCode::NOT_FOUND.into());
+    }
+    let transcript: Vec<String> = entries.iter().map(TranscriptEntry::line).collect();
+    let golden = match (query.pack, query.scenario) {
+        (Some(pack_id), Some(scenario_id)) => Some(compare_with_golden(
+            &state.pack_index.read(),
+            &pack_id,
+            &scenario_id,
+            &transcript,
+        )?),
+        (None, None) => None,
+        _ => {
+            return Err(ApiError::Json(
+                StatusCode::BAD_REQUEST,
+                json!({"error": "pack and scenario must be given together"}),
+            ));
+        }
+    };
+    Ok(Json(TranscriptResponse {
+        key,
+        count: entries.len(),
+        transcript_hash: providers_sim::hash_transcript(&transcript),
+        entries,
+        transcript,
+        golden,
+    }))
+}
+
+fn compare_with_golden(
+    index: &PackIndex,
+    pack_id: &str,
+    scenario_id: &str,
+    transcript: &[String],
+) -> Result<GoldenComparison, ApiError> {
+    let not_found = |error: String| ApiError::Json(StatusCode::NOT_FOUND, json!({"error": error}));
+    let entry = index
+        .enabled()
+        .find(|entry| entry.id == pack_id)
+        .ok_or_else(|| not_found(format!("unknown pack {pack_id}")))?;
+    let golden = scenario_suite(index, &PackFilter::default())
+        .map_err(|err| {
+            error!(?err, %pack_id, "failed to read pack scenarios");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .into_iter()
+        .find(|scenario| scenario.pack_id == pack_id && scenario.scenario_id == scenario_id)
+        .and_then(|scenario| scenario.golden)
+        .ok_or_else(|| not_found(format!("pack {pack_id} has no golden for {scenario_id}")))?;
+    let path = normalize_under_root(entry.path.as_std_path(), std::path::Path::new(&golden))
+        .map_err(|err| {
+            warn!(?err, %pack_id, %golden, "refusing golden path");
+            not_found(format!("golden {golden} is outside pack {pack_id}"))
+        })?;
+    let expected = providers_sim::read_golden_transcript(&path).map_err(|err| {
+        error!(?err, path = %path.display(), "failed to read golden transcript");
+        StatusCode::UNPROCESSABLE_ENTITY
+    })?;
+    let first_difference = (0..transcript.len().max(expected.len()))
+        .find(|idx| transcript.get(*idx) != expected.get(*idx));
+    Ok(GoldenComparison {
+        pack_id: pack_id.to_string(),
+        scenario_id: scenario_id.to_string(),
+        path: golden,
+        transcript_hash: providers_sim::hash_transcript(&expected),
+        matches: first_difference.is_none(),
+        first_difference,
+    })
+}
+
+/// Inbound payload text plus the messages a run sent back (embedded runner outcome).
+fn transcript_entries(event: &RunnerEvent) -> Vec<TranscriptEntry> {
+    let flow_id = Some(event.flow.clone());
+    let mut entries = Vec::new();
+    if let Some(text) = payload_text(&event.payload) {
+        entries.push(TranscriptEntry {
+            timestamp_ms: event.timestamp_ms,
+            direction: Direction::Inbound,
+            text,
+            flow_id: flow_id.clone(),
+            node: None,
+            tenant: event.tenant.clone(),
+        });
+    }
+    let messages = event.result["outcome"]["messages"].as_array();
+    for message in messages.into_iter().flatten() {
+        let Some(text) = message.get("text").and_then(Value::as_str) else {
+            continue;
+        };
+        entries.push(TranscriptEntry {
+            timestamp_ms: event.timestamp_ms,
+            direction: Direction::Outbound,
+            text: text.to_string(),
+            flow_id: flow_id.clone(),
+            node: message
+                .get("node")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            tenant: event.tenant.clone(),
+        });
+    }
+    entries
+}
+
+/// `text`/`message` of a chat payload, or a bare string payload.
+fn payload_text(payload: &Value) -> Option<String> {
+    match payload {
+        Value::String(text) => Some(text.clone()),
+        Value::Object(_) => ["text", "message"]
+            .iter()
+            .find_map(|key| payload.get(*key).and_then(Value::as_str))
+            .map(str::to_string),
+        _ => None,
+    }
+}
+
+async fn reload_packs_http(
+    Extension(state): Extension<AppState>,
+    Query(query): Query<DryRunQuery>,
+) -> Result<Response, StatusCode> {
+    if query.dry_run {
+        let config = state.config.packs.clone();
+        let next = tokio::task::spawn_blocking(move || build_pack_index(&config))
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .map_err(|err| {
+                error!(?err, "failed to rebuild pack index for preview");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        let preview = preview_pack_reload(&state.pack_index.read(), &next);
+        return Ok(Json(preview).into_response());
+    }
+    let PackReload { index, transitions } = reload_packs(&state).await.map_err(|err| {
+        error!(?err, "failed to rebuild pack index");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let Json(mut listing) = list_packs_filtered(
+        &index,
+        state.config.defaults.tenant.as_deref(),
+        state.config.defaults.team.as_deref(),
+        None,
+        &PackFilter::default(),
+    );
+    listing.transitions = transitions;
+    Ok(Json(listing).into_response())
+}
+
+/// Validate the pack fixtures under `[packs].root` without touching the served index.
+async fn validate_packs_http(
+    Extension(state): Extension<AppState>,
+) -> Result<Json<pack_validate::PackValidation>, StatusCode> {
+    let config = state.config.packs.clone();
+    tokio::task::spawn_blocking(move || {
+        let root = resolve_packs_root(&config)?;
+        pack_validate::validate_root(root.as_std_path(), config.max_depth)
+    })
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .map(Json)
+    .map_err(|err| {
+        error!(?err, "failed to validate packs");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// Packs a reload from `current` to `next` would add, remove or change (version, files or
+/// lifecycle status), one subject line per change.
+fn preview_pack_reload(current: &PackIndex, next: &PackIndex) -> DryRunPreview {
+    let find =
+        |index: &PackIndex, id: &str| index.entries.iter().find(|entry| entry.id == id).cloned();
+    let ids: BTreeSet<&str> = current
+        .entries
+        .iter()
+        .chain(&next.entries)
+        .map(|entry| entry.id.as_str())
+        .collect();
+    let version = |entry: &PackEntry| entry.version.clone().unwrap_or_else(|| "-".into());
+    let mut keys = Vec::new();
+    let mut subjects = Vec::new();
+    for id in ids {
+        let changes = match (find(current, id), find(next, id)) {
+            (None, Some(new)) => vec![format!("{id}: added at {}", version(&new))],
+            (Some(_), None) => vec![format!("{id}: removed")],
+            (Some(old), Some(new)) => {
+                let mut changes = Vec::new();
+                if old.version != new.version {
+                    changes.push(format!("{id}: {} -> {}", version(&old), version(&new)));
+                } else if old.contents.content_hash != new.contents.content_hash {
+                    changes.push(format!("{id}: files changed"));
+                }
+                if old.status != new.status {
+                    changes.push(format!(
+                        "{id}: {} -> {}",
+                        old.status.as_str(),
+                        new.status.as_str()
+                    ));
+                }
+                changes
+            }
+            (None, None) => Vec::new(),
+        };
+        if !changes.is_empty() {
+            keys.push(id.to_string());
+            subjects.extend(changes);
+        }
+    }
+    DryRunPreview {
+        action: "packs reload".into(),
+        count: keys.len(),
+        keys,
+        subjects,
+    }
+}
+
+async fn upsert_session(
+    Extension(state): Extension<AppState>,
+    headers: HeaderMap,
+    Json(mut payload): Json<SessionUpsertRequest>,
+) -> Result<Json<SessionView>, ApiError> {
+    // A dev chat token may only re-seed its own session (e.g. to park the next turn).
+    if let Some(grant) = chat_grant(&state, &headers)? {
+        let key = payload.key.as_deref().unwrap_or(&grant.session_key);
+        if key != grant.session_key
+            || !grant.covers(
+                payload.tenant.as_deref(),
+                payload.team.as_deref(),
+                payload.user.as_deref(),
+            )
+        {
+            return Err(chat_scope_violation(&grant));
+        }
+        payload.key = Some(grant.session_key.clone());
+        payload.tenant = Some(grant.tenant.clone());
+        payload.team = grant.team.clone();
+        payload.user = Some(grant.user.clone());
+    }
+    let mut upsert = normalize_upsert_payload(payload, &state.config.defaults)?;
+    enforce_tenant_policy(&state, Some(&upsert.tenant), "session_upsert", None)?;
+    let existing = state.session_store.get(&upsert.key).map_err(|err| {
+        error!(?err, key = %upsert.key, "session lookup failed");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if let Some(flow_id) = upsert.flow_id.as_deref() {
+        // Re-parking a session keeps it on the generation it started under while that drains.
+        let pinned = existing
+            .as_ref()
+            .and_then(|session| session.pack_generation);
+        let (generation, pack) = with_pack_generation(&state, pinned, |index| {
+            index.pack_for_flow(
+                flow_id,
+                Some(&upsert.tenant),
+                upsert.team.as_deref(),
+                upsert.user.as_deref(),
+            )
+        });
+        upsert.pack_generation = Some(generation);
+        if let Some(pack) = pack {
+            upsert.pack_id = Some(pack.id);
+            upsert.flow_version = pack.version;
+        }
+        check_session_context(
+            &state,
+            &upsert.tenant,
+            upsert.team.as_deref(),
+            upsert.user.as_deref(),
+            flow_id,
+            &upsert.context,
+        )?;
+    }
+    // ...and on the runner target it was pinned to, unless that target went unhealthy.
+    let current_target = existing.and_then(|session| session.runner_target);
+    upsert.runner_target = pin_runner_target(
+        &state,
+        &upsert.key,
+        &upsert.tenant,
+        current_target.as_deref(),
+    )
+    .map(|pin| pin.target);
+    let (tenant, key) = (upsert.tenant.clone(), upsert.key.clone());
+    let admitted = state
+        .session_quotas
+        .admit(
+            state.session_store.as_ref(),
+            &tenant,
+            &key,
+            session_limit(&state.config, &tenant),
+            now_millis(),
+            || state.session_store.upsert(upsert),
+        )
+        .map_err(|err| {
+            error!(?err, %tenant, "failed to check session quota");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    match admitted {
+        Ok(written) => written.map(SessionView::from).map(Json).map_err(|err| {
+            error!(?err, "failed to upsert session");
+            StatusCode::INTERNAL_SERVER_ERROR.into()
+        }),
+        Err(usage) => {
+            warn!(%tenant, sessions = usage.sessions, limit = ?usage.limit, "session quota exceeded");
+            Err(ApiError::RetryAfter(
+                usage.retry_after_secs(now_millis()),
+                json!({
+                    "error": "session_quota_exceeded",
+                    "tenant": tenant,
+                    "sessions": usage.sessions,
+                    "limit": usage.limit,
+                    "retry_after_secs": usage.retry_after_secs(now_millis()),
+                }),
+            ))
+        }
+    }
+}
+
+/// Pin session `key` to a runner target, keeping `current` while it is healthy, and record a
+/// `runner.repin` event when the session moves. `None` without `[[runner.targets]]`.
+fn pin_runner_target(
+    state: &AppState,
+    key: &str,
+    tenant: &str,
+    current: Option<&str>,
+) -> Option<Pin> {
+    let pin = state.runner_targets.pin(current, key)?;
+    if let Some(event) = pin.repin_event(key, tenant, now_millis()) {
+        warn!(%key, from = ?pin.repinned_from, to = %pin.target, "session re-pinned to another runner target");
+        record_runner_event(&state.runner_events, event);
+    }
+    Some(pin)
+}
+
+async fn runner_targets_http(Extension(state): Extension<AppState>) -> Json<Vec<TargetStatus>> {
+    Json(state.runner_targets.statuses())
+}
+
+/// Live sessions `tenant` may hold: its own `max_sessions`, else `[sessions].max_per_tenant`.
+fn session_limit(config: &AppConfig, tenant: &str) -> Option<usize> {
+    config
+        .tenants
+        .get(tenant)
+        .and_then(|policy| policy.max_sessions)
+        .or(config.sessions.max_per_tenant)
+}
+
+/// Current session count of `tenant` against its quota; unconfigured tenants report no limit.
+async fn tenant_usage_http(
+    Extension(state): Extension<AppState>,
+    Path(tenant): Path<String>,
+) -> Result<Json<TenantUsage>, StatusCode> {
+    let limit = session_limit(&state.config, &tenant);
+    TenantUsage::measure(state.session_store.as_ref(), &tenant, limit, now_millis())
+        .map(Json)
+        .map_err(|err| {
+            error!(?err, %tenant, "failed to count tenant sessions");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+fn session_metrics_report(state: &AppState) -> Result<SessionMetricsReport, StatusCode> {
+    state
+        .session_metrics
+        .report(state.session_store.as_ref())
+        .map_err(|err| {
+            error!(?err, "failed to count sessions for metrics");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+/// Per-tenant session counters, active sessions and resume latency as JSON.
+async fn session_metrics_http(
+    Extension(state): Extension<AppState>,
+) -> Result<Json<SessionMetricsReport>, StatusCode> {
+    session_metrics_report(&state).map(Json)
+}
+
+/// The session metrics in the Prometheus text exposition format.
+async fn metrics_http(Extension(state): Extension<AppState>) -> Result<Response, StatusCode> {
+    let report = session_metrics_report(&state)?;
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        report.to_prometheus(),
+    )
+        .into_response())
+}
+
+/// Validate a session context against the schema the pack index declares for `flow_id`.
+fn check_session_context(
+    state: &AppState,
+    tenant: &str,
+    team: Option<&str>,
+    user: Option<&str>,
+    flow_id: &str,
+    context: &Value,
+) -> Result<(), ApiError> {
+    let schema = state
+        .pack_index
+        .read()
+        .context_schema_for(flow_id, Some(tenant), team, user);
+    let Some(schema) = schema else {
+        return Ok(());
+    };
+    let violations = validate_context(&schema, context).map_err(|err| {
+        error!(?err, %flow_id, "failed to evaluate context schema");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if violations.is_empty() {
+        return Ok(());
+    }
+    warn!(%flow_id, ?violations, "session context rejected by flow schema");
+    Err(ApiError::Json(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        json!({
+            "error": "context_schema_violation",
+            "flow_id": flow_id,
+            "violations": violations,
+        }),
+    ))
+}
+
+async fn shutdown_signal() {
+    if let Err(err) = signal::ctrl_c().await {
+        warn!(?err, "failed to listen for shutdown signal");
+        return;
+    }
+    info!("shutdown signal received");
+}
+
+fn load_config(explicit_path: Option<&Utf8PathBuf>) -> Result<AppConfig> {
+    let mut figment = Figment::from(Serialized::defaults(DEFAULT_CONFIG.clone()));
+
+    if let Some(path) = explicit_path {
+        figment = figment.merge(Toml::file(path));
+    } else if let Some(path) = resolve_default_config_path() {
+        figment = figment.merge(Toml::file(path));
+    } else {
+        warn!("no config file found; relying on defaults + env overrides");
+    }
+    figment = figment.merge(Toml::file(tenants_file_path(explicit_path)));
+
+    figment = figment.merge(Env::prefixed("GREENTIC_").split("__"));
+
+    figment
+        .extract()
+        .context("failed to load greentic-integration configuration")
+}
+
+pub(crate) fn workspace_root() -> &'static Utf8Path {
+    static ROOT: Lazy<Utf8PathBuf> = Lazy::new(|| {
+        let manifest_dir = Utf8PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        manifest_dir
+            .parent()
+            .and_then(|p| p.parent())
+            .map(Utf8PathBuf::from)
+            .unwrap_or(manifest_dir)
+    });
+    ROOT.as_path()
+}
+
+/// `tenants.toml` next to the config file (`config/tenants.toml` without one), written by
+/// `tenants bootstrap` and merged over the config's own `[tenants]`.
+fn tenants_file_path(explicit_path: Option<&Utf8PathBuf>) -> Utf8PathBuf {
+    explicit_path
+        .cloned()
+        .or_else(resolve_default_config_path)
+        .and_then(|path| path.parent().map(|dir| dir.join(TENANTS_FILE)))
+        .unwrap_or_else(|| workspace_root().join("config").join(TENANTS_FILE))
+}
+
+fn resolve_default_config_path() -> Option<Utf8PathBuf> {
+    let repo_relative = workspace_root().join("config/dev.toml");
+    if repo_relative.exists() {
+        return Some(repo_relative);
+    }
+
+    if let Some(dirs) = ProjectDirs::from("ai", "Greentic", APP_NAME)
+        && let Ok(path) = Utf8PathBuf::from_path_buf(dirs.config_dir().join("config.toml"))
+        && path.exists()
+    {
+        return Some(path);
+    }
+
+    None
+}
+
+fn init_tracing() {
+    use tracing_subscriber::{EnvFilter, Layer, layer::SubscriberExt, util::SubscriberInitExt};
+
+    let filter = std::env::var("RUST_LOG").unwrap_or_else(|_| "info".into());
+    let _ = tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_target(false)
+                .with_filter(EnvFilter::new(filter)),
+        )
+        .with(TailSampler::global().layer())
+        .try_init();
+}
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+struct RunnerHostProxy {
+    queue: RunnerQueue<RunnerCommand>,
+    runner_base: Option<String>,
+}
+
+fn runner_proxy_base_from_env() -> Option<String> {
+    std::env::var("RUNNER_PROXY_URL")
+        .ok()
+        .or_else(|| std::env::var("GREENTIC_RUNNER_URL").ok())
+}
+
+/// POST a protocol command to the runner. A JSON reply must be a [`RunnerAck`]; runners that
+/// answer `2xx` with an empty body are treated as accepting.
+fn send_runner_request(http: &OutboundHttp, base: &str, request: RunnerRequest) -> Result<()> {
+    let url = format!("{}/{}", base.trim_end_matches('/'), request.path());
+    let (status, body) = http.post_json("runner proxy", &url, request.body())?;
+    let ack = (!body.trim().is_empty())
+        .then(|| serde_json::from_str::<RunnerAck>(&body))
+        .transpose()
+        .with_context(|| format!("runner at {url} answered with an invalid ack: {body}"))?;
+    if let Some(ack) = &ack {
+        if ack.protocol != PROTOCOL_VERSION {
+            warn!(%url, runner = ack.protocol, bridge = PROTOCOL_VERSION, "runner protocol version differs");
+        }
+        if ack.status == AckStatus::Rejected {
+            bail!(
+                "runner rejected {} ({status}): {}",
+                request.path(),
+                ack.message.as_deref().unwrap_or("no reason given")
+            );
+        }
+    }
+    if status >= 400 {
+        bail!("runner request to {} failed with status {}", url, status);
+    }
+    Ok(())
+}
+
+impl RunnerHostProxy {
+    #[allow(dead_code)]
+    fn new(queue: RunnerQueue<RunnerCommand>, runner_base: Option<String>) -> Self {
+        Self { queue, runner_base }
+    }
+
+    /// Queue a command for the proxy loop. Overflow is handled by the `[runner.queue]` policy;
+    /// dead-lettered commands are logged and visible on `GET /runner/queue`.
+    async fn submit(&self, command: RunnerCommand) {
+        if let Err(err) = self.queue.submit(command).await {
+            error!(?err, "failed to submit command to runner proxy");
+        }
+    }
+}
+
+impl QueuedCommand for RunnerCommand {
+    fn ordering_key(&self) -> Option<&str> {
+        match self {
+            RunnerCommand::EmitActivity { tenant, .. } => tenant.as_deref(),
+            RunnerCommand::ForwardActivity { event, .. } => event.tenant.as_deref(),
+            RunnerCommand::Emit(_) | RunnerCommand::ReloadPacks { .. } => None,
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            RunnerCommand::Emit(_) => "emit",
+            RunnerCommand::ReloadPacks { .. } => "reload_packs",
+            RunnerCommand::EmitActivity { .. } => "emit_activity",
+            RunnerCommand::ForwardActivity { .. } => "forward_activity",
+        }
+    }
+
+    fn summary(&self) -> Value {
+        match self {
+            RunnerCommand::Emit(message) => json!({ "message": message }),
+            RunnerCommand::ReloadPacks { packs, .. } => json!({
+                "generation": packs.generation,
+                "packs": packs.entries.len(),
+            }),
+            RunnerCommand::EmitActivity {
+                flow,
+                tenant,
+                team,
+                user,
+                ..
+            } => json!({
+                "flow": flow,
+                "tenant": tenant,
+                "team": team,
+                "user": user,
+            }),
+            RunnerCommand::ForwardActivity { target, event, .. } => json!({
+                "target": target,
+                "flow": event.flow,
+                "tenant": event.tenant,
+            }),
+        }
+    }
+}
+
+#[derive(Debug)]
+#[allow(dead_code)]
+enum RunnerCommand {
+    Emit(String),
+    ReloadPacks {
+        packs: PackIndex,
+        defaults: SeedDefaults,
+    },
+    EmitActivity {
+        flow: String,
+        tenant: Option<String>,
+        team: Option<String>,
+        user: Option<String>,
+        payload: Value,
+    },
+    /// Send an already recorded session turn to the runner target the session is pinned to.
+    ForwardActivity {
+        target: String,
+        url: String,
+        event: RunnerEvent,
+    },
+}
+
+/// Process runner commands on `workers` threads (`[runner].workers`). Commands for the same
+/// tenant stay on one worker, so each tenant's commands run in submission order.
+async fn proxy_runner_loop(
+    rx: SharedRunnerReceiver,
+    events: SharedRunnerEvents,
+    runner_base: Option<String>,
+    targets: RunnerTargets,
+    http: OutboundHttp,
+    workers: usize,
+) -> Result<()> {
+    let mut rx = rx.lock().await;
+    run_workers(&mut rx, workers, move |worker, cmd| {
+        process_runner_command(
+            &events,
+            runner_base.as_deref(),
+            &targets,
+            &http,
+            worker,
+            cmd,
+        )
+    })
+    .await
+}
+
+fn process_runner_command(
+    events: &SharedRunnerEvents,
+    runner_base: Option<&str>,
+    targets: &RunnerTargets,
+    http: &OutboundHttp,
+    worker: usize,
+    cmd: RunnerCommand,
+) {
+    match cmd {
+        RunnerCommand::Emit(message) => {
+            info!(%message, worker, "runner proxy emit");
+            if let Some(base) = runner_base
+                && let Err(err) = send_runner_request(
+                    http,
+                    base,
+                    RunnerRequest::Emit(EmitCommand {
+                        protocol: PROTOCOL_VERSION,
+                        message,
+                    }),
+                )
+            {
+                warn!(?err, "runner proxy emit forward failed");
+            }
+        }
+        RunnerCommand::ReloadPacks { packs, defaults } => {
+            info!(
+                pack_count = packs.entries.len(),
+                default_tenant = ?defaults.tenant,
+                default_team = ?defaults.team,
+                worker,
+                "runner proxy reload packs"
+            );
+            for pack in &packs.entries {
+                info!(
+                    pack_id = %pack.id,
+                    pack_name = ?pack.name,
+                    path = %pack.path,
+                    "runner proxy indexed pack"
+                );
+            }
+            if let Some(base) = runner_base {
+                let request = RunnerRequest::Reload(ReloadCommand {
+                    protocol: PROTOCOL_VERSION,
+                    packs: packs.entries.iter().map(PackEntry::descriptor).collect(),
+                    defaults: RunnerDefaults {
+                        tenant: defaults.tenant,
+                        team: defaults.team,
+                    },
+                });
+                if let Err(err) = send_runner_request(http, base, request) {
+                    warn!(?err, "runner proxy reload forward failed");
+                }
+            }
+        }
+        RunnerCommand::EmitActivity {
+            flow,
+            tenant,
+            team,
+            user,
+            payload,
+        } => {
+            let event = record_runner_event(
+                events,
+                synthesize_runner_event(flow, tenant, team, user, payload),
+            );
+            info!(
+                flow = %event.flow,
+                tenant = ?event.tenant,
+                team = ?event.team,
+                user = ?event.user,
+                payload = %event.payload,
+                result = %event.result,
+                worker,
+                "runner proxy emit activity"
+            );
+            if let Some(base) = runner_base
+                && let Err(err) = send_runner_request(
+                    http,
+                    base,
+                    RunnerRequest::Activity(ActivityCommand {
+                        protocol: PROTOCOL_VERSION,
+                        flow: event.flow,
+                        tenant: event.tenant,
+                        team: event.team,
+                        user: event.user,
+                        payload: event.payload,
+                        result: event.result,
+                    }),
+                )
+            {
+                warn!(?err, "runner proxy activity forward failed");
+            }
+        }
+        RunnerCommand::ForwardActivity { target, url, event } => {
+            let request = RunnerRequest::Activity(ActivityCommand {
+                protocol: PROTOCOL_VERSION,
+                flow: event.flow,
+                tenant: event.tenant,
+                team: event.team,
+                user: event.user,
+                payload: event.payload,
+                result: event.result,
+            });
+            match send_runner_request(http, &url, request) {
+                Ok(()) => {
+                    if targets.mark(&target, true) {
+                        info!(%target, worker, "runner target recovered");
+                    }
+                }
+                Err(err) => {
+                    warn!(?err, %target, worker, "runner target forward failed");
+                    if targets.mark(&target, false) {
+                        warn!(%target, "runner target marked unhealthy; its sessions re-pin on their next turn");
+                    }
+                }
+            }
+        }
+    }
+}
+impl PackIndex {
+    /// Pack providing `flow_id`, preferring packs resolved for the caller.
+    fn pack_for_flow(
+        &self,
+        flow_id: &str,
+        tenant: Option<&str>,
+        team: Option<&str>,
+        user: Option<&str>,
+    ) -> Option<PackEntry> {
+        let (resolved, _, _) = self.resolve_for(tenant, team, user);
+        resolved
+            .iter()
+            .chain(self.enabled())
+            .find(|entry| entry.flows.iter().any(|flow| flow == flow_id))
+            .cloned()
+    }
+
+    /// Entries that take part in resolution (everything but disabled packs).
+    fn enabled(&self) -> impl Iterator<Item = &PackEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.status != PackStatus::Disabled)
+    }
+
+    /// Lifecycle changes from `previous` to this index; new packs count as coming from active.
+    fn transitions_from(&self, previous: &PackIndex) -> Vec<PackTransition> {
+        self.entries
+            .iter()
+            .filter_map(|entry| {
+                let from = previous
+                    .entries
+                    .iter()
+                    .find(|old| old.id == entry.id)
+                    .map_or(PackStatus::Active, |old| old.status);
+                (from != entry.status).then(|| PackTransition {
+                    id: entry.id.clone(),
+                    from,
+                    to: entry.status,
+                })
+            })
+            .collect()
+    }
+
+    /// Loaded version per pack id.
+    fn current_versions(&self) -> HashMap<String, String> {
+        self.entries
+            .iter()
+            .filter_map(|entry| Some((entry.id.clone(), entry.version.clone()?)))
+            .collect()
+    }
+
+    /// Schema for a flow's session context, preferring packs resolved for the caller.
+    fn context_schema_for(
+        &self,
+        flow_id: &str,
+        tenant: Option<&str>,
+        team: Option<&str>,
+        user: Option<&str>,
+    ) -> Option<Value> {
+        let (resolved, _, _) = self.resolve_for(tenant, team, user);
+        resolved
+            .iter()
+            .chain(self.enabled())
+            .find_map(|entry| entry.context_schemas.get(flow_id).cloned())
+    }
+
+    fn resolve_for(
+        &self,
+        tenant: Option<&str>,
+        team: Option<&str>,
+        user: Option<&str>,
+    ) -> (Vec<PackEntry>, Vec<String>, Vec<String>) {
+        let mut desired = Vec::new();
+        if let Some(t) = tenant {
+            if let Some(team) = team {
+                if let Some(user) = user {
+                    desired.push(format!("{t}:{team}:{user}"));
+                }
+                desired.push(format!("{t}:{team}"));
+            }
+            desired.push(t.to_string());
+        }
+
+        let enabled: Vec<PackEntry> = self.enabled().cloned().collect();
+        if desired.is_empty() {
+            return (enabled, desired, Vec::new());
+        }
+
+        let mut matched = Vec::new();
+        let mut matched_keys = Vec::new();
+        let mut missing_keys = Vec::new();
+        for key in desired {
+            if let Some(entry) = enabled.iter().find(|e| e.id == key) {
+                matched.push(entry.clone());
+                matched_keys.push(key);
+            } else {
+                missing_keys.push(key);
+            }
+        }
+
+        if matched.is_empty() {
+            (
+                enabled,
+                Vec::new(),
+                missing_keys, /* entire chain missing */
+            )
+        } else {
+            (matched, matched_keys, missing_keys)
+        }
+    }
+}
+/// Outcome of one pack index rebuild, shared by every caller coalesced onto it.
+#[derive(Debug, Clone)]
+struct PackReload {
+    index: PackIndex,
+    transitions: Vec<PackTransition>,
+}
+
+/// Rebuild and install the pack index. Concurrent callers (HTTP reloads, the watcher)
+/// join the rebuild already in progress instead of starting their own.
+async fn reload_packs(state: &AppState) -> Result<PackReload> {
+    let rebuild = rebuild_packs(state.clone());
+    state.pack_reload.run(move || rebuild).await
+}
+
+/// Reload for a change the watcher saw: a rebuild already in progress may have scanned the
+/// pack root before the change, so it is waited out and a newer one joined or started.
+async fn reload_packs_after_change(state: &AppState) -> Result<PackReload> {
+    let rebuild = rebuild_packs(state.clone());
+    state.pack_reload.run_fresh(move || rebuild).await
+}
+
+async fn rebuild_packs(task_state: AppState) -> Result<PackReload> {
+    let runner_proxy = task_state.runner_proxy.clone();
+    let defaults = task_state.config.defaults.clone();
+    let reload = tokio::task::spawn_blocking(move || {
+        let index = build_pack_index(&task_state.config.packs)?;
+        Ok::<_, anyhow::Error>(install_pack_index(&task_state, index))
+    })
+    .await
+    .context("pack reload task failed")??;
+    runner_proxy
+        .submit(RunnerCommand::ReloadPacks {
+            packs: reload.index.clone(),
+            defaults,
+        })
+        .await;
+    info!(
+        pack_count = reload.index.entries.len(),
+        generation = reload.index.generation,
+        transitions = reload.transitions.len(),
+        "pack index reloaded successfully"
+    );
+    Ok(reload)
+}
+
+/// Swap in a freshly built pack index under the next generation, keep the previous one
+/// draining, and flag sessions pinned to pack versions that are no longer loaded. Logs
+/// lifecycle transitions; the caller notifies the runner proxy.
+fn install_pack_index(state: &AppState, mut index: PackIndex) -> PackReload {
+    let transitions = {
+        let mut guard = state.pack_index.write();
+        index.generation = guard.generation + 1;
+        let transitions = index.transitions_from(&guard);
+        let previous = std::mem::replace(&mut *guard, index.clone());
+        state
+            .pack_generations
+            .supersede(previous, index.generation, now_millis());
+        transitions
+    };
+    *state.event_schemas.write() = build_schema_registry(&index);
+    for transition in &transitions {
+        match transition.to {
+            PackStatus::Active => {
+                info!(pack = %transition.id, from = transition.from.as_str(), "pack reactivated")
+            }
+            PackStatus::Deprecated => {
+                warn!(pack = %transition.id, from = transition.from.as_str(), "pack deprecated")
+            }
+            PackStatus::Disabled => {
+                warn!(pack = %transition.id, from = transition.from.as_str(), "pack disabled; excluded from resolution")
+            }
+        }
+    }
+    match mark_outdated_sessions(state.session_store.as_ref(), &index.current_versions()) {
+        Ok(0) => {}
+        Ok(flagged) => info!(
+            flagged,
+            "sessions flagged for flow upgrade after pack reload"
+        ),
+        Err(err) => warn!(?err, "failed to flag sessions for flow upgrade"),
+    }
+    if let Err(err) = drain_pack_generations(state) {
+        warn!(?err, "failed to check draining pack generations");
+    }
+    record_pack_history(state, &index);
+    PackReload { index, transitions }
+}
+
+/// Release superseded pack generations with no live sessions or flow runs left, evicting the
+/// oldest beyond `[packs].retain_generations`. Returns the live sessions per generation.
+fn drain_pack_generations(state: &AppState) -> Result<BTreeMap<u64, usize>> {
+    let now = now_millis();
+    let sessions = pinned_sessions(state.session_store.as_ref(), now)?;
+    for released in state.pack_generations.sweep(&sessions, now) {
+        if released.evicted {
+            warn!(
+                generation = released.generation,
+                sessions = released.sessions,
+                in_flight = released.in_flight,
+                "pack generation evicted before it drained"
+            );
+        } else {
+            info!(
+                generation = released.generation,
+                "pack generation drained and released"
+            );
+        }
+    }
+    Ok(sessions)
+}
+
+async fn drain_pack_generations_loop(state: AppState, every: Duration) -> Result<()> {
+    let mut ticker = tokio::time::interval(every);
+    loop {
+        ticker.tick().await;
+        if state.pack_generations.is_draining()
+            && let Err(err) = drain_pack_generations(&state)
+        {
+            warn!(?err, "pack generation drain check failed");
+        }
+    }
+}
+
+/// Run `resolve` against the draining index of the `pinned` generation, or against the
+/// current index once that generation is released. Returns the generation resolved against.
+fn with_pack_generation<R>(
+    state: &AppState,
+    pinned: Option<u64>,
+    resolve: impl FnOnce(&PackIndex) -> R,
+) -> (u64, R) {
+    if let Some(index) =
+        pinned.and_then(|generation| state.pack_generations.draining_index(generation))
+    {
+        return (index.generation, resolve(&index));
+    }
+    let index = state.pack_index.read();
+    (index.generation, resolve(&index))
+}
+
+/// Append a history entry for every pack whose version or contents changed since it was
+/// last recorded. Failures are logged; they never block an index swap.
+fn record_pack_history(state: &AppState, index: &PackIndex) {
+    let now = now_millis();
+    for entry in &index.entries {
+        match state.pack_history.observe(
+            &entry.id,
+            entry.version.as_deref(),
+            &entry.contents,
+            index.generation,
+            now,
+        ) {
+            Ok(Some(change)) => info!(
+                pack = %entry.id,
+                from = change.from_version.as_deref().unwrap_or("-"),
+                to = change.to_version.as_deref().unwrap_or("-"),
+                added = change.added.len(),
+                removed = change.removed.len(),
+                modified = change.modified.len(),
+                "pack changed"
+            ),
+            Ok(None) => {}
+            Err(err) => warn!(?err, pack = %entry.id, "failed to record pack history"),
+        }
+    }
+}
+
+async fn runner_emit_cli(args: RunnerEmitArgs, http: &ClientOptions) -> Result<()> {
+    let config = load_config(None)?;
+    let (queue, rx) = RunnerQueue::new(&config.runner.queue);
+    let runner_base = runner_proxy_base_from_env();
+    let proxy = RunnerHostProxy::new(queue, runner_base.clone());
+    let events = RunnerEventLog::new(EventPolicy::from_config(&config.runner.event_policy)?);
+    tokio::spawn(proxy_runner_loop(
+        Arc::new(tokio::sync::Mutex::new(rx)),
+        events.clone(),
+        runner_base,
+        RunnerTargets::default(),
+        OutboundHttp::default(),
+        config.runner.workers,
+    ));
+
+    let payload = args
+        .payload
+        .as_deref()
+        .map(serde_json::from_str::<Value>)
+        .transpose()
+        .context("invalid JSON payload for runner emit")?
+        .unwrap_or(Value::Null);
+
+    if let Some(server) = args.server {
+        let request = EmitRequest {
+            tenant: args.tenant.or_else(|| config.defaults.tenant.clone()),
+            team: args.team.or_else(|| config.defaults.team.clone()),
+            user: args.user,
+            ..EmitRequest::new(args.flow, payload)
+        };
+        let event = bridge_client(&server, http).emit(&request).await?;
+        println!(
+            "Server runner emit result -> tenant={:?} team={:?} user={:?} result={}",
+            event.tenant, event.team, event.user, event.result
+        );
+        return Ok(());
+    }
+
+    proxy
+        .submit(RunnerCommand::EmitActivity {
+            flow: args.flow,
+            tenant: args.tenant.or_else(|| config.defaults.tenant.clone()),
+            team: args.team.or_else(|| config.defaults.team.clone()),
+            user: args.user,
+            payload,
+        })
+        .await;
+    println!("Runner emit command submitted (check server logs if running).");
+    Ok(())
+}
+
+async fn fetch_runner_events(server: &str, http: &ClientOptions) -> Result<Vec<RunnerEvent>> {
+    let events = bridge_client(server, http).runner_events().await?;
+    Ok(events.into_iter().map(RunnerEvent::from).collect())
+}
+
+/// Saved events: a JSON array (as served by `/runner/events`) or one event per line.
+fn read_runner_events_file(path: &Utf8Path) -> Result<Vec<RunnerEvent>> {
+    let raw = fs::read_to_string(path).with_context(|| format!("failed to read {path}"))?;
+    if raw.trim_start().starts_with('[') {
+        return serde_json::from_str(&raw).with_context(|| format!("invalid events in {path}"));
+    }
+    raw.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("invalid event on line {} of {path}", idx + 1))
+        })
+        .collect()
+}
+
+async fn runner_export_cli(args: RunnerExportArgs, http: &ClientOptions) -> Result<()> {
+    let events = match &args.input {
+        Some(path) => read_runner_events_file(path)?,
+        None => fetch_runner_events(&args.server, http).await?,
+    };
+    match args.format {
+        ExportFormat::Parquet => event_export::write_parquet(&events, args.out.as_std_path())?,
+        ExportFormat::Jsonl => event_export::write_jsonl(&events, args.out.as_std_path())?,
+    }
+    println!("Exported {} runner event(s) to {}", events.len(), args.out);
+    Ok(())
+}
+
+async fn runner_replay_cli(
+    args: RunnerReplayArgs,
+    http: &ClientOptions,
+    dry_run: bool,
+) -> Result<()> {
+    let remap = runner_replay::TenantRemap::parse(&args.remap_tenant)?;
+    let plan =
+        runner_replay::ReplayPlan::new(read_runner_events_file(&args.from)?, args.speed, &remap);
+    if plan.skipped > 0 {
+        println!(
+            "Skipping {} event(s) imported from the runner log",
+            plan.skipped
+        );
+    }
+    if dry_run {
+        for scheduled in &plan.events {
+            let event = &scheduled.event;
+            println!(
+                "+{}ms flow={} tenant={:?} team={:?} user={:?} (recorded at {})",
+                scheduled.offset.as_millis(),
+                event.flow,
+                event.tenant,
+                event.team,
+                event.user,
+                scheduled.original_timestamp_ms
+            );
+        }
+        println!(
+            "Would replay {} event(s) over {:.1}s",
+            plan.events.len(),
+            plan.duration().as_secs_f64()
+        );
+        return Ok(());
+    }
+
+    let client = bridge_client(&args.server, http);
+    let start = tokio::time::Instant::now();
+    let mut failed = 0;
+    for scheduled in &plan.events {
+        tokio::time::sleep_until(start + scheduled.offset).await;
+        let event = &scheduled.event;
+        let request = EmitRequest {
+            tenant: event.tenant.clone(),
+            team: event.team.clone(),
+            user: event.user.clone(),
+            ..EmitRequest::new(event.flow.clone(), event.payload.clone())
+        };
+        if let Err(err) = client.emit(&request).await {
+            failed += 1;
+            eprintln!(
+                "replay of flow {} recorded at {} failed: {err}",
+                event.flow, scheduled.original_timestamp_ms
+            );
+        }
+    }
+    println!(
+        "Replayed {} event(s) against {} in {:.1}s",
+        plan.events.len() - failed,
+        args.server,
+        start.elapsed().as_secs_f64()
+    );
+    if failed > 0 {
+        bail!("{failed} replayed event(s) failed");
+    }
+    Ok(())
+}
+
+async fn runner_compare_cli(args: RunnerCompareArgs, http: &ClientOptions) -> Result<()> {
+    let remap = runner_replay::TenantRemap::parse(&args.remap_tenant)?;
+    let plan = runner_replay::ReplayPlan::new(
+        read_runner_events_file(&args.from)?,
+        runner_replay::ReplaySpeed::Max,
+        &remap,
+    );
+    let normalizer = runner_compare::Normalizer::new(&args.ignore);
+    let baseline = bridge_client(&args.baseline, http);
+    let candidate = bridge_client(&args.candidate, http);
+    let mut report = runner_compare::CompareReport::new(&args.baseline, &args.candidate);
+    let mut users = BTreeSet::new();
+    for (index, scheduled) in plan.events.iter().enumerate() {
+        let event = &scheduled.event;
+        let request = EmitRequest {
+            tenant: event.tenant.clone(),
+            team: event.team.clone(),
+            user: event.user.clone(),
+            ..EmitRequest::new(event.flow.clone(), event.payload.clone())
+        };
+        let emit = |client: &BridgeClient| {
+            let client = client.clone();
+            let request = request.clone();
+            async move {
+                client
+                    .emit(&request)
+                    .await
+                    .map(RunnerEvent::from)
+                    .map_err(|err| err.to_string())
+            }
+        };
+        let (left, right) = tokio::join!(emit(&baseline), emit(&candidate));
+        report.push(runner_compare::EventComparison::new(
+            &normalizer,
+            index,
+            event,
+            &left,
+            &right,
+        ));
+        if let Some(user) = &event.user {
+            users.insert((event.tenant.clone(), user.clone()));
+        }
+    }
+    for (tenant, user) in users {
+        let query = SessionQuery {
+            tenant,
+            user: Some(user),
+            ..SessionQuery::default()
+        };
+        let (left, right) = tokio::try_join!(
+            comparable_sessions(&baseline, &query),
+            comparable_sessions(&candidate, &query)
+        )?;
+        report
+            .sessions
+            .extend(runner_compare::compare_sessions(&normalizer, &left, &right));
+    }
+
+    print!("{}", report.render());
+    if let Some(out) = &args.out {
+        fs::write(out, serde_json::to_string_pretty(&report)?)
+            .with_context(|| format!("failed to write {out}"))?;
+        println!("report written to {out}");
+    }
+    if !report.is_clean() {
+        bail!(
+            "candidate differs from baseline in {} event(s) and {} session field(s)",
+            report.events.len(),
+            report.sessions.len()
+        );
+    }
+    Ok(())
+}
+
+/// Every session matching `query`, as JSON for `runner compare`.
+async fn comparable_sessions(client: &BridgeClient, query: &SessionQuery) -> Result<Vec<Value>> {
+    let mut sessions = Vec::new();
+    let mut query = query.clone();
+    loop {
+        let page = client.list_sessions(&query).await?;
+        sessions.extend(page.sessions.into_iter().map(|session| {
+            json!({
+                "key": session.key,
+                "tenant": session.tenant,
+                "team": session.team,
+                "user": session.user,
+                "cursor": {
+                    "flow_id": session.cursor.flow_id,
+                    "node_id": session.cursor.node_id,
+                },
+                "context": session.context,
+                "pack_id": session.pack_id,
+                "flow_version": session.flow_version,
+                "needs_upgrade": session.needs_upgrade,
+                "locale": session.locale,
+                "ttl_ms": session.ttl_ms,
+            })
+        }));
+        match page.next_cursor {
+            Some(cursor) => query.cursor = Some(cursor),
+            None => return Ok(sessions),
+        }
+    }
+}
+
+async fn runner_watch_cli(args: RunnerWatchArgs, http: &ClientOptions) -> Result<()> {
+    let filter = watch::EventFilter {
+        flow: args.flow,
+        tenant: args.tenant,
+        status: args.status,
+    };
+    let events = bridge_client(&args.server, http).watch_runner_events();
+    let mut table = watch::EventTable::new(filter, args.limit);
+    follow_stream(events, |event| {
+        let line = table.apply(event)?;
+        Some((line, table.render()))
+    })
+    .await
+}
+
+async fn soak_cli(args: SoakArgs, http: &ClientOptions) -> Result<()> {
+    let client = bridge_client(&args.server, http);
+    client
+        .healthz()
+        .await
+        .with_context(|| format!("server {} is not reachable", args.server))?;
+    let options = soak::SoakOptions {
+        profile: args.profile,
+        duration: args.duration,
+        rate: args.rate,
+        users: args.users,
+        tenant: args.tenant,
+        check_every: args.check_every,
+        checkpoint_every: args.checkpoint_every,
+        limits: soak::SoakLimits {
+            max_rss_mb: args.max_rss_mb,
+            max_p99_ms: args.max_p99_ms,
+            max_error_percent: args.max_error_percent,
+        },
+        out: args.out,
+        fail_fast: args.fail_fast,
+        seed: args.seed,
+    };
+    info!(
+        profile = ?options.profile,
+        duration_secs = options.duration.as_secs(),
+        rate = options.rate,
+        "starting soak run"
+    );
+    let report = soak::run(&client, &options).await?;
+    println!(
+        "Soak ({:?}) ran {}s: {} op(s), {} error(s), {} check(s); report in {}",
+        report.profile,
+        report.elapsed_secs,
+        report.ops,
+        report.errors,
+        report.checks,
+        options.out.join("summary.json")
+    );
+    if !report.violations.is_empty() {
+        for violation in &report.violations {
+            println!("- {violation}");
+        }
+        bail!(
+            "soak run found {} invariant violation(s)",
+            report.violations.len()
+        );
+    }
+    Ok(())
+}
+
+/// Events sent per `POST /runner/events/import`.
+const IMPORT_CHUNK: usize = 500;
+
+async fn runner_import_cli(args: RunnerImportArgs, http: &ClientOptions) -> Result<()> {
+    let client = bridge_client(&args.server, http);
+    if !args.watch {
+        let raw = fs::read_to_string(&args.file)
+            .with_context(|| format!("failed to read {}", args.file))?;
+        let batch = runner_import::parse_lines(&raw, now_millis());
+        let imported = send_imported_events(&client, batch.events).await?;
+        println!(
+            "Imported {imported} runner event(s) from {} ({} line(s) skipped)",
+            args.file, batch.skipped
+        );
+        return Ok(());
+    }
+
+    let mut tail = runner_import::LogTail::default();
+    let interval = Duration::from_millis(args.interval_ms.max(1));
+    println!("Watching {} (Ctrl-C to stop)", args.file);
+    loop {
+        let batch = tail.poll(args.file.as_std_path(), now_millis())?;
+        if !batch.events.is_empty() {
+            let imported = send_imported_events(&client, batch.events).await?;
+            println!("Imported {imported} runner event(s)");
+        }
+        tokio::select! {
+            _ = signal::ctrl_c() => return Ok(()),
+            _ = tokio::time::sleep(interval) => {}
+        }
+    }
+}
+
+async fn send_imported_events(client: &BridgeClient, events: Vec<RunnerEvent>) -> Result<usize> {
+    let events: Vec<greentic_integration_client::RunnerEvent> =
+        events.into_iter().map(Into::into).collect();
+    let mut imported = 0;
+    for chunk in events.chunks(IMPORT_CHUNK) {
+        imported += client.import_runner_events(chunk).await?.imported;
+    }
+    Ok(imported)
+}
+
+async fn runner_events_cli(args: RunnerEventsArgs, http: &ClientOptions) -> Result<()> {
+    if args.format == ListOutput::Csv {
+        let csv = bridge_client(&args.server, http)
+            .runner_events_csv()
+            .await?;
+        print!("{csv}");
+        return Ok(());
+    }
+    let events = fetch_runner_events(&args.server, http).await?;
+    if events.is_empty() {
+        println!("No runner events recorded.");
+        return Ok(());
+    }
+    for event in events {
+        println!(
+            "[{}] flow={} tenant={:?} team={:?} user={:?} payload={} result={}",
+            event.timestamp_ms,
+            event.flow,
+            event.tenant,
+            event.team,
+            event.user,
+            event.payload,
+            event.result
+        );
+    }
+    Ok(())
+}
+
+async fn runner_clear_cli(
+    args: RunnerClearArgs,
+    http: &ClientOptions,
+    dry_run: bool,
+) -> Result<()> {
+    if dry_run {
+        let preview = bridge_client(&args.server, http)
+            .preview_clear_runner_events()
+            .await?;
+        return print_dry_run(&preview);
+    }
+    bridge_client(&args.server, http)
+        .clear_runner_events()
+        .await?;
+    println!("Cleared runner events on {}", args.server);
+    Ok(())
+}
+
+async fn runner_prune_cli(
+    args: RunnerPruneArgs,
+    http: &ClientOptions,
+    dry_run: bool,
+) -> Result<()> {
+    let client = bridge_client(&args.server, http);
+    if dry_run {
+        let preview = client
+            .preview_prune_runner_events(args.max_age_secs, args.max_events)
+            .await?;
+        return print_dry_run(&preview);
+    }
+    let pruned = client
+        .prune_runner_events(args.max_age_secs, args.max_events)
+        .await?;
+    println!(
+        "Pruned {} expired and {} excess runner event(s) on {}",
+        pruned.expired, pruned.trimmed, args.server
+    );
+    Ok(())
+}
+
+/// Run `flow` through the embedded runner when it is built in and a loaded pack declares the
+/// flow; otherwise fall back to the echo stub.
+/// Who a flow runs for, plus the locale its outbound message templates are rendered in.
+#[derive(Debug, Clone, Default)]
+struct FlowCaller {
+    tenant: Option<String>,
+    team: Option<String>,
+    user: Option<String>,
+    locale: Option<String>,
+    /// Node to start at instead of the flow's entry (resume with a `node_id` override).
+    start_node: Option<String>,
+    /// Pack generation to run against while it drains; the current one otherwise.
+    generation: Option<u64>,
+}
+
+async fn run_flow_event(
+    state: &AppState,
+    flow: String,
+    caller: FlowCaller,
+    payload: Value,
+    trace: Option<TraceContext>,
+) -> RunnerEvent {
+    let span = tracing::info_span!("flow_run", %flow, tenant = ?caller.tenant);
+    async move { run_pinned_flow(state, flow, caller, payload, trace).await }
+        .instrument(span)
+        .await
+}
+
+async fn run_pinned_flow(
+    state: &AppState,
+    flow: String,
+    caller: FlowCaller,
+    payload: Value,
+    trace: Option<TraceContext>,
+) -> RunnerEvent {
+    let pinned = state.pack_generations.pin(caller.generation);
+    #[cfg(feature = "mini-runner")]
+    if let Some(result) = run_embedded_flow(state, &flow, &caller, &payload, trace, &pinned).await {
+        return RunnerEvent {
+            timestamp_ms: now_millis(),
+            flow,
+            tenant: caller.tenant,
+            team: caller.team,
+            user: caller.user,
+            payload,
+            result,
+            sequence: None,
+            instance: None,
+        };
+    }
+    #[cfg(not(feature = "mini-runner"))]
+    let _ = (trace, &caller.locale, &pinned);
+    let mut event = synthesize_runner_event(flow, caller.tenant, caller.team, caller.user, payload);
+    if let Some(node) = caller.start_node {
+        event.result["start_node"] = Value::String(node);
+    }
+    event
+}
+
+#[cfg(feature = "mini-runner")]
+async fn run_embedded_flow(
+    state: &AppState,
+    flow: &str,
+    caller: &FlowCaller,
+    payload: &Value,
+    trace: Option<TraceContext>,
+    pinned: &pack_generations::GenerationGuard,
+) -> Option<Value> {
+    let resolve = |index: &PackIndex| {
+        index.pack_for_flow(
+            flow,
+            caller.tenant.as_deref(),
+            caller.team.as_deref(),
+            caller.user.as_deref(),
+        )
+    };
+    let entry = match pinned.index() {
+        Some(index) => resolve(index),
+        None => resolve(&state.pack_index.read()),
+    }?;
+    let pack = match mini_runner::PackFlows::load(
+        entry.path.as_std_path(),
+        workspace_root().as_std_path(),
+    ) {
+        Ok(pack) if pack.flows.contains_key(flow) => pack,
+        Ok(_) => return None,
+        Err(err) => {
+            warn!(?err, pack = %entry.id, "failed to load pack flows for embedded runner");
+            return None;
+        }
+    };
+    let sandbox = match state.mini_runner.open_sandbox(&pack.pack_id) {
+        Ok(sandbox) => sandbox,
+        Err(err) => {
+            warn!(?err, pack = %entry.id, "failed to open pack sandbox");
+            return Some(json!({
+                "flow": flow,
+                "status": "error",
+                "runner": "embedded",
+                "error": format!("{err:#}"),
+            }));
+        }
+    };
+    let input = mini_runner::RunInput {
+        session_id: Uuid::new_v4().to_string(),
+        tenant: caller
+            .tenant
+            .clone()
+            .unwrap_or_else(|| state.config.packs.default_tenant.clone()),
+        team: caller.team.clone(),
+        user: caller.user.clone(),
+        payload: payload.clone(),
+        trace,
+        locale: caller.locale.clone(),
+        start_node: caller.start_node.clone(),
+        sandbox: sandbox.clone(),
+    };
+    let tenant = input.tenant.clone();
+    let outcome = state.mini_runner.run(&pack, flow, input).await;
+    if let Ok(outcome) = &outcome {
+        queue_provider_sends(state, flow, &tenant, caller, outcome);
+    }
+    if let Some(sandbox) = sandbox
+        && let Err(err) = sandbox.teardown()
+    {
+        warn!(?err, pack = %entry.id, "failed to tear down pack sandbox");
+    }
+    Some(match outcome {
+        Ok(outcome) => json!({
+            "flow": flow,
+            "status": "ok",
+            "runner": "embedded",
+            "outcome": outcome,
+        }),
+        Err(err) => {
+            warn!(?err, %flow, "embedded flow run failed");
+            json!({
+                "flow": flow,
+                "status": "error",
+                "runner": "embedded",
+                "error": format!("{err:#}"),
+            })
+        }
+    })
+}
+
+/// Hand the run's `messaging.send` output to the outbox; messages without a configured sink
+/// stay in the outcome only. Sends to a phone channel are queued once per message the channel
+/// delivers (see `phone_channel`).
+#[cfg(feature = "mini-runner")]
+fn queue_provider_sends(
+    state: &AppState,
+    flow: &str,
+    tenant: &str,
+    caller: &FlowCaller,
+    outcome: &mini_runner::RunOutcome,
+) {
+    for message in &outcome.messages {
+        let body = json!({
+            "flow": flow,
+            "node": message.node,
+            "tenant": tenant,
+            "team": caller.team,
+            "user": caller.user,
+            "provider": message.provider,
+            "channel": message.channel,
+            "text": message.text,
+            "payload": message.payload,
+            "locale": message.locale,
+        });
+        let bodies = match message
+            .provider
+            .as_deref()
+            .and_then(providers_sim::phone::PhoneChannel::from_provider)
+        {
+            Some(channel) => phone_channel::outbound_bodies(channel, &body),
+            None => vec![body],
+        };
+        for body in bodies {
+            match state.outbox.enqueue(message.provider.as_deref(), body) {
+                Ok(Some(entry)) => {
+                    debug!(id = %entry.id, %flow, node = %message.node, "queued provider send")
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    warn!(?err, %flow, node = %message.node, "failed to queue provider send")
+                }
+            }
+        }
+    }
+}
+
+fn synthesize_runner_event(
+    flow: String,
+    tenant: Option<String>,
+    team: Option<String>,
+    user: Option<String>,
+    payload: Value,
+) -> RunnerEvent {
+    let result = json!({
+        "flow": flow,
+        "echo": payload,
+        "status": "ok",
+    });
+    RunnerEvent {
+        timestamp_ms: now_millis(),
+        flow,
+        tenant,
+        team,
+        user,
+        payload,
+        result,
+        sequence: None,
+        instance: None,
+    }
+}
+
+/// Scrub `event` per the log's policy and keep it unless sampling drops it. Returns the
+/// scrubbed event for callers that log or forward it.
+fn record_runner_event(events: &SharedRunnerEvents, event: RunnerEvent) -> RunnerEvent {
+    let mut event = events.policy.scrub(event);
+    if !events.policy.keep(&event) {
+        return event;
+    }
+    let link = events.cluster.get();
+    if let Some(link) = link {
+        event.instance = Some(link.instance.clone());
+    }
+    // Allocated under the log's write lock so sequence order is log order.
+    let guard = events.write();
+    event.sequence = Some(events.next_sequence(&event));
+    append_runner_event(events, guard, &event);
+    if let Some(link) = link {
+        let _ = link.outbound.send(event.clone());
+    }
+    event
+}
+
+/// Add an event another cluster instance recorded, keeping its instance and sequence.
+fn ingest_remote_event(events: &SharedRunnerEvents, event: RunnerEvent) {
+    let guard = events.write();
+    append_runner_event(events, guard, &event);
+}
+
+fn append_runner_event(
+    events: &SharedRunnerEvents,
+    mut guard: RwLockWriteGuard<'_, Vec<RunnerEvent>>,
+    event: &RunnerEvent,
+) {
+    guard.push(event.clone());
+    let len = guard.len();
+    if len > RECENT_RUNNER_EVENTS {
+        let excess = len - RECENT_RUNNER_EVENTS;
+        guard.drain(0..excess);
+    }
+    if let Some(store) = events.store()
+        && let Err(err) = store.append(event)
+    {
+        warn!(?err, flow = %event.flow, "failed to persist runner event");
+    }
+    // No subscribers is the common case.
+    let _ = events.live.send(event.clone());
+}
+
+pub(crate) fn now_millis() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod app_tests {
+    use super::*;
+    use crate::deployment::PackKind;
+    use crate::fixtures::Fixture;
+    use crate::{anomaly, event_policy, panic_guard, runner_queue, runner_targets};
+    use axum::{
+        Extension,
+        body::{self, Body},
+        http::{Request, StatusCode},
+    };
+    use tokio::net::TcpListener;
+    use tower::ServiceExt;
+
+    fn state_with_session(flow_id: &str) -> AppState {
+        let state = test_state();
+        state
+            .session_store
+            .upsert(SessionUpsert {
+                key: "test-sess".into(),
+                tenant: "dev".into(),
+                team: None,
+                user: Some("user-test".into()),
+                flow_id: Some(flow_id.into()),
+                node_id: Some("node-wait".into()),
+                context: json!({"waiting": true}),
+                pack_id: None,
+                flow_version: None,
+                locale: None,
+                ttl_ms: None,
+                pack_generation: None,
+                runner_target: None,
+            })
+            .unwrap();
+        state
+    }
+
+    #[tokio::test]
+    async fn resume_session_endpoint_removes_entry() {
+        let state = state_with_session("flow-test");
+        let req = SessionResumeRequest {
+            key: None,
+            node_id: None,
+            tenant: Some("dev".into()),
+            team: None,
+            user: Some("user-test".into()),
+            payload: Some(json!({"reply": "hi"})),
+            locale: None,
+        };
+        let response = resume_session_http(Extension(state.clone()), HeaderMap::new(), Json(req))
+            .await
+            .expect("resume should succeed");
+        assert_eq!(response.flow, "flow-test");
+        assert!(!state.runner_events.read().is_empty());
+
+        let filter = SessionFilter::new(Some("dev".into()), None, Some("user-test".into()));
+        assert!(state.session_store.find(&filter).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn resume_posts_the_event_to_the_tenant_webhook() {
+        let (hook_tx, mut hook_rx) = tokio::sync::mpsc::unbounded_channel::<Value>();
+        let hook = Router::new().route(
+            "/resumed",
+            post(move |Json(body): Json<Value>| {
+                let _ = hook_tx.send(body);
+                async { StatusCode::NO_CONTENT }
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let hook_addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, hook).await });
+
+        let mut state = state_with_session("flow-crm");
+        state.config.tenants.insert(
+            "dev".into(),
+            TenantConfig {
+                resume_webhook: Some(format!("http://{hook_addr}/resumed")),
+                ..TenantConfig::default()
+            },
+        );
+        let req = SessionResumeRequest {
+            key: None,
+            node_id: None,
+            tenant: Some("dev".into()),
+            team: None,
+            user: Some("user-test".into()),
+            payload: Some(json!({"reply": "hi"})),
+            locale: None,
+        };
+        let Json(event) = resume_session_http(Extension(state), HeaderMap::new(), Json(req))
+            .await
+            .expect("resume should succeed");
+
+        let posted = tokio::time::timeout(Duration::from_secs(5), hook_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(posted, serde_json::to_value(&event).unwrap());
+        assert_eq!(posted["flow"], "flow-crm");
+        assert_eq!(posted["tenant"], "dev");
+    }
+
+    #[tokio::test]
+    async fn idle_sessions_are_nudged_through_the_runner_proxy_or_a_webhook() {
+        let mut state = test_state();
+        state.session_store = InMemorySessionStore::new();
+        state.config.sessions.nudge.flows = BTreeMap::from([("checkout".into(), 60)]);
+        for (key, flow) in [("cart", "checkout"), ("ticket", "support")] {
+            state
+                .session_store
+                .put(SessionRecord {
+                    key: key.into(),
+                    tenant: "dev".into(),
+                    user: Some(format!("user-{key}")),
+                    flow_id: Some(flow.into()),
+                    node_id: Some("ask".into()),
+                    updated_at_epoch_ms: 1_000,
+                    ..SessionRecord::default()
+                })
+                .unwrap();
+        }
+
+        assert_eq!(nudge_due_sessions(&state, 60_999).await.unwrap(), 0);
+        assert_eq!(nudge_due_sessions(&state, 61_000).await.unwrap(), 1);
+        assert_eq!(nudge_due_sessions(&state, 200_000).await.unwrap(), 0);
+        assert_eq!(state.runner_proxy.queue.stats().enqueued, 1);
+        let resp = build_router(state.clone())
+            .oneshot(
+                Request::builder()
+                    .uri("/sessions/cart")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let view: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(view["updated_at_epoch_ms"], 1_000);
+        assert_eq!(
+            view["nudges"],
+            json!([{"at_epoch_ms": 61_000, "idle_ms": 60_000, "event": "session.nudge"}])
+        );
+
+        let (hook_tx, mut hook_rx) = tokio::sync::mpsc::unbounded_channel::<Value>();
+        let hook = Router::new().route(
+            "/nudged",
+            post(move |Json(body): Json<Value>| {
+                let _ = hook_tx.send(body);
+                async { StatusCode::NO_CONTENT }
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let hook_addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, hook).await });
+        state.config.sessions.nudge.webhook = Some(format!("http://{hook_addr}/nudged"));
+        state.config.sessions.nudge.idle_secs = Some(300);
+        state.config.sessions.nudge.event = "conversation.abandoned".into();
+        assert_eq!(nudge_due_sessions(&state, 301_000).await.unwrap(), 1);
+        let posted = hook_rx.try_recv().unwrap();
+        assert_eq!(
+            posted,
+            json!({
+                "type": "conversation.abandoned",
+                "session": "ticket",
+                "flow": "support",
+                "node": "ask",
+                "idle_ms": 300_000,
+                "nudge": 1,
+            })
+        );
+        assert_eq!(state.runner_proxy.queue.stats().enqueued, 1);
+    }
+
+    #[tokio::test]
+    async fn session_metrics_count_writes_and_resumes_per_tenant() {
+        let mut state = test_state();
+        state.session_store =
+            MeteredSessionStore::new(InMemorySessionStore::new(), state.session_metrics.clone());
+        for user in ["user-1", "user-2"] {
+            state
+                .session_store
+                .upsert(SessionUpsert {
+                    key: format!("sess-{user}"),
+                    tenant: "dev".into(),
+                    team: None,
+                    user: Some(user.into()),
+                    flow_id: Some("flow-metrics".into()),
+                    node_id: Some("node-wait".into()),
+                    context: Value::Null,
+                    pack_id: None,
+                    flow_version: None,
+                    locale: None,
+                    ttl_ms: None,
+                    pack_generation: None,
+                    runner_target: None,
+                })
+                .unwrap();
+        }
+        let req = SessionResumeRequest {
+            key: None,
+            node_id: None,
+            tenant: Some("dev".into()),
+            team: None,
+            user: Some("user-1".into()),
+            payload: Some(json!({"reply": "hi"})),
+            locale: None,
+        };
+        let Json(resumed) =
+            resume_session_http(Extension(state.clone()), HeaderMap::new(), Json(req))
+                .await
+                .expect("resume should succeed");
+        assert_eq!(resumed.tenant.as_deref(), Some("dev"));
+
+        let router = build_router(state);
+        let get = |uri: &'static str| {
+            router
+                .clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        };
+        let response = get("/metrics/sessions").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let metrics: Value = serde_json::from_slice(&body).unwrap();
+        let dev = &metrics["tenants"]["dev"];
+        assert_eq!(dev["active"], 1);
+        assert_eq!(dev["upserts"], 2);
+        assert_eq!(dev["removes"], 1);
+        assert_eq!(dev["resume_latency"]["count"], 1);
+
+        let response = get("/metrics").await.unwrap();
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/plain; version=0.0.4"
+        );
+        let body = body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+        assert!(
+            text.contains("greentic_sessions_active{tenant=\"dev\"} 1\n"),
+            "{text}"
+        );
+        assert!(text.contains("greentic_session_resume_latency_ms_count{tenant=\"dev\"} 1\n"));
+    }
+
+    #[tokio::test]
+    async fn resume_by_key_can_restart_at_a_chosen_node() {
+        let mut state = test_state();
+        state.session_store = InMemorySessionStore::new();
+        state
+            .session_store
+            .upsert(SessionUpsert {
+                key: "retry-sess".into(),
+                tenant: "dev".into(),
+                team: None,
+                user: Some("user-retry".into()),
+                flow_id: Some("flow-retry".into()),
+                node_id: Some("node-stuck".into()),
+                context: Value::Null,
+                pack_id: None,
+                flow_version: None,
+                locale: None,
+                ttl_ms: None,
+                pack_generation: None,
+                runner_target: None,
+            })
+            .unwrap();
+        let req = |tenant: &str| SessionResumeRequest {
+            key: Some("retry-sess".into()),
+            node_id: Some("node-retry".into()),
+            tenant: Some(tenant.into()),
+            team: None,
+            user: None,
+            payload: Some(json!({"reply": "again"})),
+            locale: None,
+        };
+        let err = resume_session_http(
+            Extension(state.clone()),
+            HeaderMap::new(),
+            Json(req("acme")),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            err.status(),
+            StatusCode::NOT_FOUND,
+            "other tenants' keys stay hidden"
+        );
+
+        let Json(event) =
+            resume_session_http(Extension(state.clone()), HeaderMap::new(), Json(req("dev")))
+                .await
+                .expect("resume by key");
+        assert_eq!(event.flow, "flow-retry");
+        assert_eq!(event.user.as_deref(), Some("user-retry"));
+        assert_eq!(event.result["start_node"], "node-retry");
+        assert!(state.session_store.get("retry-sess").unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn concurrent_resume_of_a_locked_session_conflicts() {
+        let mut state = test_state();
+        state.session_store = InMemorySessionStore::new();
+        state
+            .session_store
+            .upsert(SessionUpsert {
+                key: "lock-sess".into(),
+                tenant: "dev".into(),
+                team: None,
+                user: Some("user-lock".into()),
+                flow_id: Some("flow-lock".into()),
+                node_id: None,
+                context: json!({}),
+                pack_id: None,
+                flow_version: None,
+                locale: None,
+                ttl_ms: None,
+                pack_generation: None,
+                runner_target: None,
+            })
+            .unwrap();
+        let resume = || {
+            resume_session_http(
+                Extension(state.clone()),
+                HeaderMap::new(),
+                Json(SessionResumeRequest {
+                    key: None,
+                    node_id: None,
+                    tenant: Some("dev".into()),
+                    team: None,
+                    user: Some("user-lock".into()),
+                    payload: None,
+                    locale: None,
+                }),
+            )
+        };
+
+        let lease = state
+            .session_store
+            .try_lock("lock-sess", Duration::from_secs(30))
+            .unwrap()
+            .expect("lock is free");
+        let err = resume().await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::CONFLICT);
+        assert!(state.runner_events.read().is_empty());
+
+        drop(lease);
+        assert_eq!(resume().await.expect("resume wins").flow, "flow-lock");
+        assert_eq!(resume().await.unwrap_err().status(), StatusCode::NOT_FOUND);
+        assert_eq!(state.runner_events.read().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn expired_sessions_are_hidden_from_list_and_resume() {
+        let mut state = test_state();
+        state.session_store = InMemorySessionStore::new();
+        let now = now_millis();
+        for (key, user, updated_at_epoch_ms) in [
+            ("ttl-live", "user-live", now),
+            ("ttl-gone", "user-gone", now - 5_000),
+        ] {
+            state
+                .session_store
+                .put(SessionRecord {
+                    key: key.into(),
+                    tenant: "dev".into(),
+                    user: Some(user.into()),
+                    flow_id: Some("flow-ttl".into()),
+                    updated_at_epoch_ms,
+                    ttl_ms: Some(1_000),
+                    ..SessionRecord::default()
+                })
+                .unwrap();
+        }
+
+        let listed = list_sessions(
+            Extension(state.clone()),
+            Query(SessionFilterInput::default()),
+            Query(SessionPageQuery::default()),
+            Query(ListFormatQuery::default()),
+        )
+        .await
+        .unwrap();
+        let body = body::to_bytes(listed.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let listed: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(listed["count"], 1);
+        assert_eq!(listed["sessions"][0]["key"], "ttl-live");
+        assert_eq!(listed["sessions"][0]["ttl_ms"], 1_000);
+
+        let err = resume_session_http(
+            Extension(state.clone()),
+            HeaderMap::new(),
+            Json(SessionResumeRequest {
+                key: None,
+                node_id: None,
+                tenant: Some("dev".into()),
+                team: None,
+                user: Some("user-gone".into()),
+                payload: None,
+                locale: None,
+            }),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+        assert!(state.runner_events.read().is_empty());
+    }
+
+    #[tokio::test]
+    async fn sessions_list_pages_with_cursor_and_sort() {
+        let mut state = test_state();
+        state.session_store = InMemorySessionStore::new();
+        for (key, updated_at_epoch_ms) in [("p-old", 1_000), ("p-new", 3_000), ("p-mid", 2_000)] {
+            state
+                .session_store
+                .put(SessionRecord {
+                    key: key.into(),
+                    tenant: "pages".into(),
+                    user: Some("user-pages".into()),
+                    updated_at_epoch_ms,
+                    ..SessionRecord::default()
+                })
+                .unwrap();
+        }
+        let app = build_router(state);
+        let get = |uri: String| {
+            let app = app.clone();
+            async move {
+                let resp = app
+                    .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                    .await
+                    .unwrap();
+                let status = resp.status();
+                let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+                (status, serde_json::from_slice::<Value>(&body).unwrap())
+            }
+        };
+
+        let (status, first) = get("/sessions?tenant=pages&sort=-updated_at&limit=2".into()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(first["count"], 2);
+        assert_eq!(first["sessions"][0]["key"], "p-new");
+        assert_eq!(first["sessions"][1]["key"], "p-mid");
+        let cursor = first["next_cursor"].as_str().expect("another page");
+        let (_, second) = get(format!(
+            "/sessions?tenant=pages&sort=-updated_at&limit=2&cursor={cursor}"
+        ))
+        .await;
+        assert_eq!(second["count"], 1);
+        assert_eq!(second["sessions"][0]["key"], "p-old");
+        assert!(second.get("next_cursor").is_none());
+
+        let (status, err) = get("/sessions?tenant=pages&sort=newest".into()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(err["error"], "invalid_page");
+    }
+
+    #[tokio::test]
+    async fn session_detail_returns_one_session_or_404() {
+        let mut state = test_state();
+        state.session_store = InMemorySessionStore::new();
+        for (key, updated_at_epoch_ms) in [("detail/1", now_millis()), ("detail-gone", 1)] {
+            state
+                .session_store
+                .put(SessionRecord {
+                    key: key.into(),
+                    tenant: "dev".into(),
+                    flow_id: Some("menu".into()),
+                    context: json!({"step": 2}),
+                    updated_at_epoch_ms,
+                    ttl_ms: Some(60_000),
+                    ..SessionRecord::default()
+                })
+                .unwrap();
+        }
+        let app = build_router(state);
+        let get = |uri: &str| {
+            app.clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        };
+
+        let resp = get("/sessions/detail%2F1").await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let view: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(view["key"], "detail/1");
+        assert_eq!(view["cursor"]["flow_id"], "menu");
+        assert_eq!(view["context"], json!({"step": 2}));
+
+        for missing in ["/sessions/detail-gone", "/sessions/unknown"] {
+            let resp = get(missing).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND, "{missing}");
+        }
+    }
+
+    #[tokio::test]
+    async fn resume_session_missing_user() {
+        let state = state_with_session("flow-test");
+        let req = SessionResumeRequest {
+            key: None,
+            node_id: None,
+            tenant: Some("dev".into()),
+            team: None,
+            user: Some("unknown".into()),
+            payload: None,
+            locale: None,
+        };
+        let err = resume_session_http(Extension(state), HeaderMap::new(), Json(req))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn sessions_list_endpoint_reports_entries() {
+        let state = state_with_session("flow-list");
+        let app = build_router(state.clone());
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/sessions")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let data: SessionListResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(data.count, 1);
+        assert_eq!(
+            data.sessions[0].cursor.flow_id.as_deref(),
+            Some("flow-list")
+        );
+    }
+
+    #[tokio::test]
+    async fn runner_emit_endpoint_records_event() {
+        let state = test_state();
+        let app = build_router(state.clone());
+        let req = RunnerEmitRequest {
+            flow: "flow-demo".into(),
+            tenant: Some("dev".into()),
+            team: None,
+            user: Some("user-emit".into()),
+            payload: Some(json!({"text": "hi"})),
+            index_generation: None,
+            locale: None,
+        };
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/runner/emit")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_vec(&req).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(state.runner_events.read().len(), 1);
+
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .uri("/runner/events")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn runner_emit_and_list_returns_echoed_result() {
+        let state = test_state();
+        let app = build_router(state.clone());
+        let payload = json!({"hello": "world", "count": 42});
+        let req = RunnerEmitRequest {
+            flow: "flow-integration".into(),
+            tenant: Some("dev".into()),
+            team: Some("team-a".into()),
+            user: Some("user-x".into()),
+            payload: Some(payload.clone()),
+            index_generation: None,
+            locale: None,
+        };
+
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/runner/emit")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_vec(&req).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .uri("/runner/events")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let events: Vec<RunnerEvent> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(events.len(), 1);
+        let ev = &events[0];
+        assert_eq!(ev.flow, "flow-integration");
+        assert_eq!(ev.tenant.as_deref(), Some("dev"));
+        assert_eq!(ev.team.as_deref(), Some("team-a"));
+        assert_eq!(ev.user.as_deref(), Some("user-x"));
+        assert_eq!(ev.payload, payload);
+        assert_eq!(ev.result["status"], "ok");
+        assert_eq!(ev.result["echo"], payload);
+    }
+
+    #[tokio::test]
+    async fn runner_events_can_be_cleared() {
+        let state = test_state();
+        let app = build_router(state.clone());
+        let req = RunnerEmitRequest {
+            flow: "flow-clear".into(),
+            tenant: None,
+            team: None,
+            user: None,
+            payload: Some(json!({"foo": "bar"})),
+            index_generation: None,
+            locale: None,
+        };
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/runner/emit")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_vec(&req).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(state.runner_events.read().len(), 1);
+
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("DELETE")
+                    .uri("/runner/events")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(state.runner_events.read().len(), 0);
+    }
+
+    #[tokio::test]
+    async fn imported_runner_log_joins_the_event_timeline() {
+        let state = test_state();
+        record_runner_event(
+            &state.runner_events,
+            synthesize_runner_event("flow-bridge".into(), None, None, None, json!({})),
+        );
+        let log = concat!(
+            r#"{"timestamp":"2020-01-01T00:00:00Z","level":"INFO","fields":{"message":"started","flow_id":"chat","tenant_id":"acme"}}"#,
+            "\n",
+            "plain text banner\n",
+        );
+        let batch = runner_import::parse_lines(log, now_millis());
+        assert_eq!(batch.skipped, 1);
+        let app = build_router(state.clone());
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/runner/events/import")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_vec(&batch.events).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let data: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(data["imported"], 1);
+
+        let events = state.runner_events.read();
+        let flows: Vec<_> = events.iter().map(|event| event.flow.as_str()).collect();
+        assert_eq!(flows, vec!["chat", "flow-bridge"]);
+        assert_eq!(events[0].tenant.as_deref(), Some("acme"));
+        assert_eq!(events[0].result["source"], runner_import::RUNNER_SOURCE);
+    }
+
+    #[tokio::test]
+    async fn sqlite_event_store_survives_restart_and_clear() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Utf8PathBuf::from_path_buf(tmp.path().to_path_buf()).unwrap();
+        let open = || -> Option<Arc<dyn RunnerEventStore>> {
+            Some(SqliteRunnerEventStore::new(
+                SqliteDb::open(root.clone(), "greentic.db".into()).unwrap(),
+            ))
+        };
+        let events = RunnerEventLog::with_store(EventPolicy::default(), open()).unwrap();
+        for flow in ["flow-a", "flow-b"] {
+            record_runner_event(
+                &events,
+                RunnerEvent {
+                    timestamp_ms: now_millis(),
+                    flow: flow.into(),
+                    tenant: Some("acme".into()),
+                    team: None,
+                    user: None,
+                    payload: json!({}),
+                    result: json!({"status": "ok"}),
+                    sequence: None,
+                    instance: None,
+                },
+            );
+        }
+        drop(events);
+
+        let mut state = test_state();
+        state.runner_events = RunnerEventLog::with_store(EventPolicy::default(), open()).unwrap();
+        let app = build_router(state.clone());
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/runner/events")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let listed: Vec<RunnerEvent> = serde_json::from_slice(&body).unwrap();
+        let flows: Vec<_> = listed.iter().map(|event| event.flow.as_str()).collect();
+        assert_eq!(flows, vec!["flow-a", "flow-b"]);
+
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .method("DELETE")
+                    .uri("/runner/events")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert!(open().unwrap().recent(10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn runner_event_sequences_count_per_conversation_and_survive_restart() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Utf8PathBuf::from_path_buf(tmp.path().to_path_buf()).unwrap();
+        let open = || -> Option<Arc<dyn RunnerEventStore>> {
+            Some(SqliteRunnerEventStore::new(
+                SqliteDb::open(root.clone(), "greentic.db".into()).unwrap(),
+            ))
+        };
+        let record = |events: &SharedRunnerEvents, flow: &str, user: &str| {
+            record_runner_event(
+                events,
+                RunnerEvent {
+                    timestamp_ms: now_millis(),
+                    flow: flow.into(),
+                    tenant: Some("acme".into()),
+                    team: None,
+                    user: Some(user.into()),
+                    payload: json!({}),
+                    result: json!({"status": "ok"}),
+                    sequence: None,
+                    instance: None,
+                },
+            )
+            .sequence
+        };
+
+        let events = RunnerEventLog::with_store(EventPolicy::default(), open()).unwrap();
+        let first: Vec<_> = [
+            ("chat", "ana"),
+            ("chat", "ana"),
+            ("chat", "ben"),
+            ("menu", "ana"),
+            ("chat", "ana"),
+        ]
+        .into_iter()
+        .map(|(flow, user)| record(&events, flow, user))
+        .collect();
+        assert_eq!(first, [Some(1), Some(2), Some(1), Some(1), Some(3)]);
+        drop(events);
+
+        // Numbering continues from the durable store rather than restarting at 1.
+        let events = RunnerEventLog::with_store(EventPolicy::default(), open()).unwrap();
+        assert_eq!(record(&events, "chat", "ana"), Some(4));
+        assert_eq!(record(&events, "chat", "cleo"), Some(1));
+        assert_eq!(events.read().last().unwrap().sequence, Some(1));
+    }
+
+    #[tokio::test]
+    async fn runner_event_policy_scrubs_and_samples_recorded_events() {
+        let mut state = test_state();
+        state.runner_events = RunnerEventLog::new(
+            EventPolicy::from_config(&EventPolicyConfig {
+                sample_percent: BTreeMap::from([("flow-noisy".to_string(), 0.0)]),
+                scrub: vec!["payload.text".into(), "user".into()],
+            })
+            .unwrap(),
+        );
+        let app = build_router(state.clone());
+        for flow in ["flow-private", "flow-noisy"] {
+            let req = RunnerEmitRequest {
+                flow: flow.into(),
+                tenant: None,
+                team: None,
+                user: Some("alice@example.com".into()),
+                payload: Some(json!({"text": "my address is 1 Main St", "channel": "web"})),
+                index_generation: None,
+                locale: None,
+            };
+            let resp = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri("/runner/emit")
+                        .header("content-type", "application/json")
+                        .body(Body::from(serde_json::to_vec(&req).unwrap()))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .uri("/runner/events")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let events: Vec<RunnerEvent> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].flow, "flow-private");
+        assert_eq!(events[0].payload["text"], event_policy::SCRUBBED);
+        assert_eq!(events[0].payload["channel"], "web");
+        assert_eq!(events[0].user.as_deref(), Some(event_policy::SCRUBBED));
+    }
+
+    #[tokio::test]
+    async fn anomaly_detector_records_and_posts_error_bursts() {
+        let (hook_tx, mut hook_rx) = tokio::sync::mpsc::unbounded_channel::<Value>();
+        let hook = Router::new().route(
+            "/hook",
+            post(move |Json(body): Json<Value>| {
+                let _ = hook_tx.send(body);
+                async { StatusCode::NO_CONTENT }
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let hook_addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, hook).await });
+
+        let events = RunnerEventLog::new(EventPolicy::default());
+        let config = AnomalyConfig {
+            enabled: true,
+            max_errors_per_flow: Some(1),
+            webhook_url: Some(format!("http://{hook_addr}/hook")),
+            ..AnomalyConfig::default()
+        };
+        let detector = tokio::spawn(detect_anomalies(
+            events.clone(),
+            config,
+            OutboundHttp::default(),
+        ));
+        while events.live.receiver_count() == 0 {
+            tokio::task::yield_now().await;
+        }
+        for _ in 0..2 {
+            record_runner_event(
+                &events,
+                RunnerEvent {
+                    timestamp_ms: now_millis(),
+                    flow: "flow-flaky".into(),
+                    tenant: Some("acme".into()),
+                    team: None,
+                    user: None,
+                    payload: Value::Null,
+                    result: json!({"status": "error"}),
+                    sequence: None,
+                    instance: None,
+                },
+            );
+        }
+
+        let posted = tokio::time::timeout(Duration::from_secs(5), hook_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(posted["kind"], "error_burst");
+        assert_eq!(posted["flow"], "flow-flaky");
+        let recorded = events.read().clone();
+        assert_eq!(recorded.len(), 3);
+        assert_eq!(recorded[2].flow, anomaly::ANOMALY_FLOW);
+        assert_eq!(recorded[2].tenant.as_deref(), Some("acme"));
+        assert_eq!(recorded[2].result["kind"], "error_burst");
+        detector.abort();
+    }
+
+    #[tokio::test]
+    async fn tenant_policy_rejects_disallowed_providers_and_residency() {
+        let mut state = test_state();
+        state.config.server.residency = Some("eu".into());
+        state.config.tenants.insert(
+            "acme".into(),
+            TenantConfig {
+                residency: Some("eu".into()),
+                allowed_providers: Some(vec!["webchat".into()]),
+                ..TenantConfig::default()
+            },
+        );
+        state.config.tenants.insert(
+            "globex".into(),
+            TenantConfig {
+                residency: Some("us".into()),
+                ..TenantConfig::default()
+            },
+        );
+        let app = build_router(state);
+        let emit = |tenant: &str, channel: &str| {
+            let req = RunnerEmitRequest {
+                flow: "flow-policy".into(),
+                tenant: Some(tenant.into()),
+                team: None,
+                user: Some("user-1".into()),
+                payload: Some(json!({"channel": channel, "text": "hi"})),
+                index_generation: None,
+                locale: None,
+            };
+            Request::builder()
+                .method("POST")
+                .uri("/runner/emit")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&req).unwrap()))
+                .unwrap()
+        };
+
+        let resp = app.clone().oneshot(emit("acme", "webchat")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = app.clone().oneshot(emit("acme", "slack")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let data: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(data["error"], "tenant_policy_denied");
+        assert_eq!(data["reason"], "provider slack is not allowed");
+
+        let resp = app.oneshot(emit("globex", "webchat")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn phone_webhook_routes_by_business_number() {
+        let mut state = test_state();
+        state.config.phone.routes.push(phone_channel::PhoneRoute {
+            number: "+15550100".into(),
+            channel: Some(providers_sim::phone::PhoneChannel::Sms),
+            tenant: "acme".into(),
+            team: None,
+            flow: "sms-support".into(),
+        });
+        let app = build_router(state.clone());
+        let webhook = |provider: &str, to: &str| {
+            Request::builder()
+                .method("POST")
+                .uri(format!("/providers/{provider}/webhook"))
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "message_id": "SM1",
+                        "from": "+1 555 0142",
+                        "to": to,
+                        "text": "where is my order?",
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap()
+        };
+
+        let resp = app
+            .clone()
+            .oneshot(webhook("sms", "+15550100"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let event: RunnerEvent = serde_json::from_slice(&body).unwrap();
+        assert_eq!(event.flow, "sms-support");
+        assert_eq!(event.tenant.as_deref(), Some("acme"));
+        assert_eq!(event.user.as_deref(), Some("+15550142"));
+        assert_eq!(event.payload["channel"], "sms");
+        assert_eq!(event.payload["thread_id"], "sms:+15550100:+15550142");
+        assert!(!state.runner_events.read().is_empty());
+
+        let resp = app
+            .clone()
+            .oneshot(webhook("whatsapp", "+15550100"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND, "route is SMS only");
+        let resp = app
+            .clone()
+            .oneshot(webhook("teams", "+15550100"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn session_quota_returns_429_and_usage_reports_counts() {
+        let mut state = test_state();
+        state.session_store = InMemorySessionStore::new();
+        state.config.sessions.max_per_tenant = Some(5);
+        state.config.tenants.insert(
+            "acme".into(),
+            TenantConfig {
+                max_sessions: Some(1),
+                ..TenantConfig::default()
+            },
+        );
+        let app = build_router(state);
+        let upsert = |key: &str| {
+            Request::builder()
+                .method("POST")
+                .uri("/sessions")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::to_vec(&json!({"key": key, "tenant": "acme", "user": "u1"}))
+                        .unwrap(),
+                ))
+                .unwrap()
+        };
+
+        let resp = app.clone().oneshot(upsert("quota-a")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = app.clone().oneshot(upsert("quota-a")).await.unwrap();
+        assert_eq!(
+            resp.status(),
+            StatusCode::OK,
+            "replacing is not a new session"
+        );
+
+        let resp = app.clone().oneshot(upsert("quota-b")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(resp.headers()["retry-after"], "60");
+        let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let data: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(data["error"], "session_quota_exceeded");
+        assert_eq!(
+            (data["sessions"].clone(), data["limit"].clone()),
+            (json!(1), json!(1))
+        );
+
+        let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let resp = app
+            .clone()
+            .oneshot(get("/tenants/acme/usage"))
+            .await
+            .unwrap();
+        let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let data: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            data,
+            json!({"tenant": "acme", "sessions": 1, "limit": 1, "remaining": 0})
+        );
+        let resp = app.oneshot(get("/tenants/globex/usage")).await.unwrap();
+        let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let data: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(data["limit"], 5, "falls back to [sessions].max_per_tenant");
+        assert_eq!(data["sessions"], 0);
+    }
+
+    #[tokio::test]
+    async fn runner_events_summary_groups_recent_events() {
+        let state = test_state();
+        for (flow, status, age_ms) in [
+            ("flow-a", "ok", 1_000),
+            ("flow-a", "error", 2_000),
+            ("flow-b", "ok", 3_000),
+            ("flow-a", "error", 2 * 3_600_000),
+        ] {
+            record_runner_event(
+                &state.runner_events,
+                RunnerEvent {
+                    timestamp_ms: now_millis() - age_ms,
+                    flow: flow.into(),
+                    tenant: Some("dev".into()),
+                    team: None,
+                    user: None,
+                    payload: Value::Null,
+                    result: json!({"status": status, "duration_ms": age_ms}),
+                    sequence: None,
+                    instance: None,
+                },
+            );
+        }
+        let app = build_router(state);
+        let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+        let resp = app
+            .clone()
+            .oneshot(get("/runner/events/summary?window=1h&group_by=flow,tenant"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let data: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(data["total"], 3);
+        assert_eq!(
+            data["groups"][0]["key"],
+            json!({"flow": "flow-a", "tenant": "dev"})
+        );
+        assert_eq!(data["groups"][0]["error_rate"], 0.5);
+        assert_eq!(data["groups"][0]["latency_ms"]["p95"], 2_000);
+        assert_eq!(data["groups"][1]["count"], 1);
+
+        let resp = app
+            .oneshot(get("/runner/events/summary?window=soon"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    fn seed_sessions(state: &AppState, count: usize) {
+        for idx in 0..count {
+            state
+                .session_store
+                .upsert(SessionUpsert {
+                    key: format!("purge-{idx}"),
+                    tenant: "dev".into(),
+                    team: None,
+                    user: Some(format!("user-{idx}")),
+                    flow_id: Some("flow-purge".into()),
+                    node_id: None,
+                    context: Value::Null,
+                    pack_id: None,
+                    flow_version: None,
+                    locale: None,
+                    ttl_ms: None,
+                    pack_generation: None,
+                    runner_target: None,
+                })
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn purge_dry_run_reports_without_removing() {
+        let mut state = test_state();
+        state.session_store = InMemorySessionStore::new();
+        seed_sessions(&state, 3);
+        let app = build_router(state.clone());
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .method("DELETE")
+                    .uri("/sessions?tenant=dev&dry_run=true")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let data: SessionPurgeResponse = serde_json::from_slice(&body).unwrap();
+        assert!(data.dry_run);
+        assert_eq!(data.matched, 3);
+        assert_eq!(data.removed, 0);
+        let keys = data.keys.unwrap();
+        assert_eq!(keys.len(), 3);
+        assert!(keys.is_sorted());
+        assert_eq!(data.sessions.unwrap().len(), 3);
+        assert_eq!(
+            state
+                .session_store
+                .list(&SessionFilter::default())
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+
+    #[tokio::test]
+    async fn traffic_endpoints_start_and_stop_synthetic_activity() {
+        let mut state = test_state();
+        state.session_store = InMemorySessionStore::new();
+        let request = |uri: &str, body: Option<Value>| {
+            let builder = Request::builder().method("POST").uri(uri);
+            match body {
+                Some(body) => builder
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string())),
+                None => builder.body(Body::empty()),
+            }
+            .unwrap()
+        };
+        let read = |resp: axum::response::Response| async move {
+            let status = resp.status();
+            let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+            (status, serde_json::from_slice::<Value>(&body).unwrap())
+        };
+
+        let resp = build_router(state.clone())
+            .oneshot(request("/dev/traffic/start", None))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        state.config.server.traffic.enabled = true;
+        let app = build_router(state.clone());
+        // The configured profile has no flows yet.
+        let (status, body) = read(
+            app.clone()
+                .oneshot(request("/dev/traffic/start", None))
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid_traffic_profile");
+
+        let profile = json!({
+            "messages_per_minute": 6000.0,
+            "tenants": ["acme"],
+            "users_per_tenant": 2,
+            "flows": {"flow-demo": 1},
+            "resume_ratio": 1.0,
+            "seed": 3,
+        });
+        let (status, started) = read(
+            app.clone()
+                .oneshot(request("/dev/traffic/start", Some(profile.clone())))
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(started["running"], true);
+        let resp = app
+            .clone()
+            .oneshot(request("/dev/traffic/start", Some(profile)))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while state.traffic.status().sent.unwrap().resumes == 0 {
+            assert!(std::time::Instant::now() < deadline, "no resumes generated");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let (status, stopped) = read(
+            app.clone()
+                .oneshot(request("/dev/traffic/stop", None))
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(stopped["running"], false);
+        assert_eq!(stopped["sent"]["errors"], 0);
+        let events = state.runner_events.read().clone();
+        assert!(events.iter().any(|event| {
+            event.flow == "flow-demo"
+                && event.tenant.as_deref() == Some("acme")
+                && event.payload["synthetic"] == true
+        }));
+    }
+
+    #[tokio::test]
+    async fn outbox_endpoint_reports_stuck_provider_sends() {
+        let mut state = test_state();
+        let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let resp = build_router(state.clone())
+            .oneshot(get("/outbox"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        state.config.server.outbox = OutboxConfig {
+            enabled: true,
+            sinks: BTreeMap::from([("*".to_string(), "http://127.0.0.1:9/send".to_string())]),
+            stuck_after_secs: 0,
+            ..OutboxConfig::default()
+        };
+        state.outbox = Outbox::new(
+            state_store::InMemoryStateStore::new(),
+            state.config.server.outbox.clone(),
+        );
+        let entry = state
+            .outbox
+            .enqueue(Some("webchat"), json!({"text": "hello"}))
+            .unwrap()
+            .unwrap();
+        let resp = build_router(state.clone())
+            .oneshot(get("/outbox?status=pending"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body["counts"],
+            json!({"pending": 1, "sent": 0, "failed": 0})
+        );
+        assert_eq!(body["stuck"][0]["id"], json!(entry.id));
+        assert_eq!(body["entries"][0]["body"]["text"], "hello");
+    }
+
+    #[tokio::test]
+    async fn provider_health_endpoint_reports_the_latest_probe() {
+        let mut state = test_state();
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/send", closed.local_addr().unwrap());
+        drop(closed);
+        state.config.providers = BTreeMap::from([(
+            "slack".to_string(),
+            ProviderConfig {
+                endpoint: endpoint.clone(),
+                auth_secret: None,
+                capabilities: BTreeMap::from([("threads".to_string(), true)]),
+                timeout_ms: 500,
+                health_url: None,
+            },
+        )]);
+        state.providers = load_providers(&state.config).unwrap();
+        let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let read = |resp: axum::response::Response| async move {
+            let status = resp.status();
+            let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+            (status, serde_json::from_slice::<Value>(&body).unwrap())
+        };
+
+        let (status, body) = read(
+            build_router(state.clone())
+                .oneshot(get("/providers/teams/health"))
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"], "unknown_provider");
+
+        let (status, body) = read(
+            build_router(state.clone())
+                .oneshot(get("/providers/slack/health"))
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["health"], Value::Null);
+        assert_eq!(body["provider"]["endpoint"], json!(endpoint));
+        assert!(
+            body["provider"]["capabilities"]
+                .as_array()
+                .unwrap()
+                .contains(&json!("threads"))
+        );
+
+        let providers = state.providers.clone();
+        tokio::task::spawn_blocking(move || providers.probe_all(&OutboundHttp::default(), 7))
+            .await
+            .unwrap();
+        let (status, body) = read(
+            build_router(state.clone())
+                .oneshot(get("/providers/slack/health"))
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["health"]["healthy"], false);
+        assert_eq!(body["health"]["checked_at_ms"], 7);
+        assert!(body["health"]["error"].as_str().unwrap().contains("failed"));
+    }
+
+    #[tokio::test]
+    async fn prune_endpoint_applies_retention_limits_and_counts_passes() {
+        let state = test_state();
+        let now = now_millis();
+        for (age_ms, flow) in [(600_000, "old"), (0, "a"), (0, "b")] {
+            state.runner_events.write().push(RunnerEvent {
+                timestamp_ms: now - age_ms,
+                flow: flow.into(),
+                tenant: None,
+                team: None,
+                user: None,
+                payload: Value::Null,
+                result: Value::Null,
+                sequence: None,
+                instance: None,
+            });
+        }
+        let send = |method: &str, uri: &str| {
+            build_router(state.clone()).oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+        let read = |resp: axum::response::Response| async move {
+            let status = resp.status();
+            let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+            (status, serde_json::from_slice::<Value>(&body).unwrap())
+        };
+
+        let (status, body) = read(send("POST", "/runner/events/prune").await.unwrap()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "no_retention_limits");
+
+        let (status, body) = read(
+            send("POST", "/runner/events/prune?max_age_secs=60&max_events=1")
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!({"expired": 1, "trimmed": 1}));
+        let flows: Vec<_> = state
+            .runner_events
+            .read()
+            .iter()
+            .map(|event| event.flow.clone())
+            .collect();
+        assert_eq!(flows, ["b"]);
+
+        let (status, body) = read(send("GET", "/runner/events/retention").await.unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["stats"]["passes"], 1);
+        assert_eq!(body["stats"]["expired_total"], 1);
+        assert_eq!(body["config"]["interval_secs"], 300);
+    }
+
+    #[tokio::test]
+    async fn runner_prune_dry_run_previews_without_removing() {
+        let state = test_state();
+        let now = now_millis();
+        for (age_ms, flow) in [(600_000, "old"), (0, "a"), (0, "b")] {
+            state.runner_events.write().push(RunnerEvent {
+                timestamp_ms: now - age_ms,
+                flow: flow.into(),
+                tenant: None,
+                team: None,
+                user: None,
+                payload: Value::Null,
+                result: Value::Null,
+                sequence: None,
+                instance: None,
+            });
+        }
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(axum::serve(listener, build_router(state.clone())).into_future());
+
+        let cli = Cli::try_parse_from([
+            "greentic-integration",
+            "--dry-run",
+            "runner",
+            "prune",
+            "--max-age-secs",
+            "60",
+            "--max-events",
+            "1",
+            "--server",
+            &base,
+        ])
+        .unwrap();
+        assert!(honours_dry_run(&cli.command));
+        let Command::Runner {
+            command: RunnerCommandCli::Prune(args),
+        } = cli.command
+        else {
+            panic!("unexpected command {:?}", cli.command);
+        };
+        runner_prune_cli(args, &ClientOptions::default(), true)
+            .await
+            .unwrap();
+        assert_eq!(state.runner_events.read().len(), 3);
+
+        let preview = bridge_client(&base, &ClientOptions::default())
+            .preview_prune_runner_events(Some(60), Some(1))
+            .await
+            .unwrap();
+        assert_eq!(preview.action, "runner prune");
+        assert_eq!(preview.count, 2);
+        assert_eq!(preview.subjects, ["a (1)", "old (1)"]);
+        assert_eq!(state.runner_events.read().len(), 3);
+        assert_eq!(state.event_retention.stats().passes, 0);
+    }
+
+    #[tokio::test]
+    async fn debug_endpoints_manage_breakpoints_when_enabled() {
+        let mut state = test_state();
+        let request = |method: &str, uri: &str, body: Option<Value>| {
+            let builder = Request::builder().method(method).uri(uri);
+            match body {
+                Some(body) => builder
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string())),
+                None => builder.body(Body::empty()),
+            }
+            .unwrap()
+        };
+        let read = |resp: axum::response::Response| async move {
+            let status = resp.status();
+            let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+            (status, serde_json::from_slice::<Value>(&body).unwrap())
+        };
+
+        let resp = build_router(state.clone())
+            .oneshot(request("GET", "/debug/breakpoints", None))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        state.config.server.debug.enabled = true;
+        let app = build_router(state.clone());
+        let breakpoint = json!({"flow": "repo_assistant_chat", "node": "respond"});
+        let (status, added) = read(
+            app.clone()
+                .oneshot(request(
+                    "POST",
+                    "/debug/breakpoints",
+                    Some(breakpoint.clone()),
+                ))
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(added["added"], true);
+        let (_, listed) = read(
+            app.clone()
+                .oneshot(request("GET", "/debug/breakpoints", None))
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(listed["breakpoints"], json!([breakpoint]));
+        let (_, paused) = read(
+            app.clone()
+                .oneshot(request("GET", "/debug/paused", None))
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(paused["paused"], json!([]));
+
+        let (status, body) = read(
+            app.clone()
+                .oneshot(request(
+                    "POST",
+                    "/debug/continue",
+                    Some(json!({"id": "nope", "step": true})),
+                ))
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"], "not_paused");
+
+        let (status, removed) = read(
+            app.clone()
+                .oneshot(request("DELETE", "/debug/breakpoints", None))
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(removed["removed"], 1);
+        assert!(state.debugger.breakpoints().is_empty());
+    }
+
+    #[tokio::test]
+    async fn dev_chat_token_is_scoped_to_its_session() {
+        let mut state = test_state();
+        state.session_store = InMemorySessionStore::new();
+        let request = |method: &str, uri: &str, token: Option<&str>, body: Value| {
+            let mut builder = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("content-type", "application/json");
+            if let Some(token) = token {
+                builder = builder.header("authorization", format!("Bearer {token}"));
+            }
+            builder.body(Body::from(body.to_string())).unwrap()
+        };
+        let read = |resp: axum::response::Response| async move {
+            let status = resp.status();
+            let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+            (
+                status,
+                serde_json::from_slice::<Value>(&body).unwrap_or_default(),
+            )
+        };
+
+        let app = build_router(state.clone());
+        let resp = app
+            .oneshot(request("POST", "/dev/chat/session", None, json!({})))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        state.config.server.dev_chat.enabled = true;
+        let app = build_router(state.clone());
+        let (status, issued) = read(
+            app.clone()
+                .oneshot(request(
+                    "POST",
+                    "/dev/chat/session",
+                    None,
+                    json!({"tenant": "dev", "flow_id": "flow-chat"}),
+                ))
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let token = issued["token"].as_str().unwrap().to_string();
+        let key = issued["session"]["key"].as_str().unwrap().to_string();
+        assert_eq!(issued["token_type"], "Bearer");
+        assert!(issued["expires_at_epoch_ms"].as_u64().unwrap() > now_millis());
+        assert!(
+            issued["session"]["user"]
+                .as_str()
+                .unwrap()
+                .starts_with("webchat-")
+        );
+
+        let (status, data) = read(
+            app.clone()
+                .oneshot(request(
+                    "POST",
+                    "/sessions/resume",
+                    Some(&token),
+                    json!({"user": "someone-else", "payload": {"text": "hi"}}),
+                ))
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(data["error"], "chat_token_scope");
+        let (status, _) = read(
+            app.clone()
+                .oneshot(request(
+                    "POST",
+                    "/sessions",
+                    Some(&token),
+                    json!({"key": "other", "user": "x"}),
+                ))
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, data) = read(
+            app.clone()
+                .oneshot(request(
+                    "POST",
+                    "/sessions/resume",
+                    Some("gtc_forged"),
+                    json!({"user": "user-1"}),
+                ))
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(data["error"], "chat_token_invalid");
+
+        let (status, event) = read(
+            app.clone()
+                .oneshot(request(
+                    "POST",
+                    "/sessions/resume",
+                    Some(&token),
+                    json!({"payload": {"text": "hi"}}),
+                ))
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(event["flow"], "flow-chat");
+
+        let transcript = |uri: String| {
+            app.clone()
+                .oneshot(request("GET", &uri, Some(&token), Value::Null))
+        };
+        let resp = transcript(format!("/sessions/{key}/transcript"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = transcript("/sessions/test-sess/transcript".into())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn admin_token_must_match_exactly() {
+        let server = ServerConfig {
+            admin_token: Some("secret".into()),
+            ..ServerConfig::default()
+        };
+        let headers = |token: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(AUTHORIZATION, format!("Bearer {token}").parse().unwrap());
+            headers
+        };
+        assert!(is_admin_request(&headers("secret"), &server));
+        for guess in ["secreT", "secre", "secret2", ""] {
+            assert!(!is_admin_request(&headers(guess), &server), "{guess:?}");
+        }
+        assert!(!is_admin_request(&HeaderMap::new(), &server));
+    }
+
+    #[tokio::test]
+    async fn purge_above_threshold_requires_confirm_or_admin() {
+        let mut state = test_state();
+        state.session_store = InMemorySessionStore::new();
+        state.config.sessions.purge_confirm_threshold = 2;
+        state.config.server.admin_token = Some("secret".into());
+        seed_sessions(&state, 3);
+        let app = build_router(state.clone());
+
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("DELETE")
+                    .uri("/sessions")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::PRECONDITION_REQUIRED);
+
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("DELETE")
+                    .uri("/sessions")
+                    .header("authorization", "Bearer secret")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let data: SessionPurgeResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(data.removed, 3);
+
+        seed_sessions(&state, 3);
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .method("DELETE")
+                    .uri("/sessions?confirm=true")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn session_snapshots_restore_the_store_to_a_point_in_time() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut state = test_state();
+        state.session_store = InMemorySessionStore::new();
+        state.session_snapshots = Arc::new(
+            SnapshotDir::new(
+                Utf8PathBuf::from_path_buf(tmp.path().to_path_buf()).unwrap(),
+                Utf8PathBuf::from("snapshots"),
+            )
+            .unwrap(),
+        );
+        state.config.sessions.purge_confirm_threshold = 1;
+        let app = build_router(state.clone());
+        let post = |uri: &str, body: Value| {
+            app.clone().oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(uri)
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+        };
+        async fn body_json(resp: Response) -> Value {
+            let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice(&body).unwrap()
+        }
+
+        let resp = post("/sessions/restore", json!({})).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        seed_sessions(&state, 1);
+        let resp = post("/sessions/snapshot", json!({})).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let snapshot = body_json(resp).await;
+        assert_eq!(snapshot["sessions"], 1);
+        let name = snapshot["name"].as_str().unwrap().to_string();
+        assert!(tmp.path().join("snapshots").join(&name).exists());
+
+        state.session_store.remove("purge-0").unwrap();
+        seed_sessions(&state, 3);
+        let resp = post("/sessions/restore", json!({"snapshot": name}))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::PRECONDITION_REQUIRED);
+        assert_eq!(body_json(resp).await["removed"], 2);
+        assert_eq!(
+            state
+                .session_store
+                .list(&SessionFilter::default())
+                .unwrap()
+                .len(),
+            3
+        );
+
+        let resp = post(
+            "/sessions/restore",
+            json!({"snapshot": name, "confirm": true}),
+        )
+        .await
+        .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let report = body_json(resp).await;
+        assert_eq!(report["restored"], 1);
+        assert_eq!(report["removed"], json!(["purge-1", "purge-2"]));
+        let keys: Vec<String> = state
+            .session_store
+            .list(&SessionFilter::default())
+            .unwrap()
+            .into_iter()
+            .map(|record| record.key)
+            .collect();
+        assert_eq!(keys, vec!["purge-0".to_string()]);
+
+        let listed = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/sessions/snapshots")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(body_json(listed).await, json!([snapshot]));
+        let resp = post("/sessions/restore", json!({"snapshot": "../sessions.json"}))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn unix_socket_requests_are_trusted_like_admin() {
+        let mut state = test_state();
+        state.session_store = InMemorySessionStore::new();
+        state.config.sessions.purge_confirm_threshold = 2;
+        seed_sessions(&state, 3);
+
+        let mut request = Request::builder()
+            .method("DELETE")
+            .uri("/sessions")
+            .body(Body::empty())
+            .unwrap();
+        request.extensions_mut().insert(LocalPeer);
+        let resp = build_router(state).oneshot(request).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let data: SessionPurgeResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(data.removed, 3);
+    }
+
+    #[tokio::test]
+    async fn session_writes_are_audited_with_the_caller() {
+        let tmp = tempfile::tempdir().unwrap();
+        let log = FileSessionAuditLog::new(
+            Utf8PathBuf::from_path_buf(tmp.path().to_path_buf()).unwrap(),
+            "audit.jsonl".into(),
+        )
+        .unwrap();
+        let mut state = test_state();
+        state.config.server.admin_token = Some("secret".into());
+        state.session_store =
+            AuditedSessionStore::new(InMemorySessionStore::new(), log.clone(), "server");
+        seed_sessions(&state, 1);
+
+        let resp = build_router(state)
+            .oneshot(
+                Request::builder()
+                    .method("DELETE")
+                    .uri("/sessions")
+                    .header("authorization", "Bearer secret")
+                    .header(ACTOR_HEADER, "nightly-cleanup")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let actors: Vec<_> = log
+            .history("purge-0")
+            .unwrap()
+            .into_iter()
+            .map(|entry| format!("{} {}", entry.op.as_str(), entry.actor))
+            .collect();
+        assert_eq!(actors, ["upsert server", "purge admin:nightly-cleanup"]);
+    }
+
+    #[tokio::test]
+    async fn session_stats_counts_without_listing() {
+        let mut state = test_state();
+        state.config.stores.session = StoreConfig::memory();
+        state.session_store = InMemorySessionStore::new();
+        seed_sessions(&state, 3);
+        let app = build_router(state);
+        let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+        let resp = app.clone().oneshot(get("/sessions/stats")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let data: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(data["total"], 3);
+        assert_eq!(data["tenants"][0]["tenant"], "dev");
+        assert_eq!(data["flows"][0]["flow_id"], "flow-purge");
+        assert_eq!(data["flows"][0]["nodes"][0]["count"], 3);
+        assert_eq!(data["age"][0]["count"], 3);
+        assert_eq!(data["store"]["backend"], "memory");
+        assert_eq!(data["store"]["healthy"], true);
+
+        let resp = app
+            .oneshot(get("/sessions/stats?tenant=other"))
+            .await
+            .unwrap();
+        let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let data: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(data["total"], 0);
+    }
+
+    #[tokio::test]
+    async fn partitions_endpoint_reports_tenant_and_session_assignments() {
+        let mut state = test_state();
+        state.session_store = InMemorySessionStore::new();
+        state
+            .config
+            .tenants
+            .insert("acme".into(), TenantConfig::default());
+        seed_sessions(&state, 3);
+        let app = build_router(state);
+        let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let read = |resp: Response| async move {
+            let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<Value>(&body).unwrap()
+        };
+
+        let data = read(app.clone().oneshot(get("/partitions")).await.unwrap()).await;
+        let partitioner = Partitioner::from_config(&PartitioningConfig::default()).unwrap();
+        assert_eq!(data["by"], "tenant");
+        assert_eq!(data["partitions"], 16);
+        assert_eq!(
+            data["assignments"]["acme"],
+            partitioner.partition_of("acme")
+        );
+        assert_eq!(data["assignments"]["dev"], partitioner.partition_of("dev"));
+        assert_eq!(data["load"].as_array().unwrap().len(), 16);
+
+        let data = read(
+            app.clone()
+                .oneshot(get("/partitions?by=session&partitions=4"))
+                .await
+                .unwrap(),
+        )
+        .await;
+        let partitioner = Partitioner::new(4).unwrap();
+        assert_eq!(data["assignments"].as_object().unwrap().len(), 3);
+        assert_eq!(
+            data["assignments"]["purge-1"],
+            partitioner.partition_of("purge-1")
+        );
+        let load: u64 = data["load"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|n| n.as_u64().unwrap())
+            .sum();
+        assert_eq!(load, 3);
+
+        for uri in ["/partitions?partitions=0", "/partitions?by=user"] {
+            let resp = app.clone().oneshot(get(uri)).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn sessions_and_runner_events_download_as_csv() {
+        let mut state = test_state();
+        state.session_store = InMemorySessionStore::new();
+        seed_sessions(&state, 2);
+        state
+            .session_store
+            .upsert(SessionUpsert {
+                key: "purge-0".into(),
+                tenant: "dev".into(),
+                team: None,
+                user: Some("user-0".into()),
+                flow_id: Some("flow-purge".into()),
+                node_id: Some("ask, \"again\"".into()),
+                context: Value::Null,
+                pack_id: None,
+                flow_version: None,
+                locale: None,
+                ttl_ms: None,
+                pack_generation: None,
+                runner_target: None,
+            })
+            .unwrap();
+        state.runner_events.write().push(RunnerEvent {
+            timestamp_ms: 1_700_000_000_000,
+            flow: "flow-purge".into(),
+            tenant: Some("dev".into()),
+            team: None,
+            user: Some("user-0".into()),
+            payload: json!({"text": "hi"}),
+            result: json!({"status": "ok"}),
+            sequence: Some(1),
+            instance: None,
+        });
+        let app = build_router(state);
+        let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+        let resp = app
+            .clone()
+            .oneshot(get("/sessions?format=csv&limit=1"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()[header::CONTENT_TYPE],
+            csv_export::CONTENT_TYPE
+        );
+        assert!(resp.headers().contains_key(NEXT_CURSOR_HEADER));
+        let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let csv = String::from_utf8(body.to_vec()).unwrap();
+        let lines: Vec<_> = csv.split("\r\n").collect();
+        assert!(lines[0].starts_with("\u{feff}key,tenant,team,user,flow,node,"));
+        assert!(
+            lines[1].starts_with("purge-0,dev,,user-0,flow-purge,\"ask, \"\"again\"\"\","),
+            "{csv}"
+        );
+        assert_eq!(lines.len(), 3, "{csv}");
+
+        let resp = app
+            .clone()
+            .oneshot(get("/sessions?format=xml"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(axum::serve(listener, app).into_future());
+        let client = bridge_client(&base, &ClientOptions::default());
+        let csv = client
+            .list_sessions_csv(&SessionQuery::default())
+            .await
+            .unwrap();
+        assert_eq!(csv.lines().count(), 3, "{csv}");
+        let csv = client.runner_events_csv().await.unwrap();
+        let mut lines = csv.lines();
+        assert!(
+            lines
+                .next()
+                .unwrap()
+                .starts_with("\u{feff}timestamp_ms,timestamp,")
+        );
+        assert!(
+            lines.next().unwrap().starts_with(
+                "1700000000000,2023-11-14T22:13:20.000Z,1,,dev,,user-0,flow-purge,,ok,"
+            ),
+            "{csv}"
+        );
+    }
+
+    #[tokio::test]
+    async fn soft_deleted_session_can_be_restored() {
+        let mut state = test_state();
+        state.config.sessions.soft_delete_window_secs = Some(60);
+        state.session_store =
+            wrap_session_store(InMemorySessionStore::new(), &state.config.sessions);
+        seed_sessions(&state, 1);
+        let app = build_router(state.clone());
+
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("DELETE")
+                    .uri("/sessions?tenant=dev")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(state.session_store.get("purge-0").unwrap().is_none());
+
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/sessions/purge-0/restore")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(state.session_store.get("purge-0").unwrap().is_some());
+
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/sessions/missing/restore")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn concurrent_pack_reloads_coalesce_into_one_generation() {
+        let state = test_state();
+        let reload = || {
+            build_router(state.clone()).oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/packs/reload")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+        let generation = |resp: axum::response::Response| async move {
+            assert_eq!(resp.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let data: Value = serde_json::from_slice(&body).unwrap();
+            data["index_generation"].as_u64().unwrap()
+        };
+
+        let (first, second) = tokio::join!(reload(), reload());
+        let first = generation(first.unwrap()).await;
+        assert_eq!(first, 1);
+        assert_eq!(generation(second.unwrap()).await, first);
+        assert_eq!(state.pack_index.read().generation, first);
+
+        assert_eq!(generation(reload().await.unwrap()).await, first + 1);
+    }
+
+    #[tokio::test]
+    async fn pack_watcher_reloads_once_per_burst_of_changes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Utf8PathBuf::from_path_buf(tmp.path().canonicalize().unwrap()).unwrap();
+        let mut state = test_state();
+        state.config.packs.watch_debounce_ms = 200;
+        let watcher = tokio::spawn(watch_packs(root.clone(), state.clone()));
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let generation_after = |expected: u64| {
+            let state = state.clone();
+            async move {
+                let deadline = std::time::Instant::now() + Duration::from_secs(5);
+                while state.pack_index.read().generation < expected
+                    && std::time::Instant::now() < deadline
+                {
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                }
+                // Longer than the debounce, so a second reload of the same burst would show.
+                tokio::time::sleep(Duration::from_millis(500)).await;
+                state.pack_index.read().generation
+            }
+        };
+
+        fs::create_dir_all(root.join("demo")).unwrap();
+        for step in 0..3 {
+            fs::write(root.join("demo/pack.json"), format!("{{\"step\": {step}}}")).unwrap();
+        }
+        assert_eq!(generation_after(1).await, 1);
+        assert_eq!(state.runner_proxy.queue.stats().enqueued, 1);
+
+        fs::write(root.join("demo/.pack.json.swp"), b"swap").unwrap();
+        tokio::time::sleep(Duration::from_millis(600)).await;
+        assert_eq!(
+            state.pack_index.read().generation,
+            1,
+            "hidden files are ignored"
+        );
+
+        fs::write(root.join("demo/pack.json"), b"{}").unwrap();
+        assert_eq!(generation_after(2).await, 2);
+        watcher.abort();
+    }
+
+    #[tokio::test]
+    async fn change_bursts_end_after_a_quiet_period() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let quiet = Duration::from_millis(100);
+        let sender = tokio::spawn(async move {
+            for _ in 0..3 {
+                tx.send(()).unwrap();
+                tokio::time::sleep(Duration::from_millis(30)).await;
+            }
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            tx.send(()).unwrap();
+        });
+        let max_wait = Duration::from_secs(5);
+        assert_eq!(next_change_burst(&mut rx, quiet, max_wait).await, Some(3));
+        assert_eq!(next_change_burst(&mut rx, quiet, max_wait).await, Some(1));
+        sender.await.unwrap();
+        assert_eq!(next_change_burst(&mut rx, quiet, max_wait).await, None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn steady_changes_cannot_postpone_a_burst_past_max_wait() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let sender = tokio::spawn(async move {
+            for _ in 0..40 {
+                if tx.send(()).is_err() {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(30)).await;
+            }
+        });
+        let (quiet, max_wait) = (Duration::from_millis(100), Duration::from_millis(200));
+        let started = tokio::time::Instant::now();
+        // Changes arrive every 30ms, well inside the quiet period, so only max_wait ends the
+        // burst: the ones sent at 0, 30, ..., 180ms.
+        let changes = next_change_burst(&mut rx, quiet, max_wait).await.unwrap();
+        assert_eq!(started.elapsed(), max_wait);
+        assert_eq!(changes, 7);
+
+        let started = tokio::time::Instant::now();
+        let changes = next_change_burst(&mut rx, quiet, max_wait).await.unwrap();
+        assert_eq!(
+            started.elapsed(),
+            max_wait + Duration::from_millis(10),
+            "the next burst starts with the change sent at 210ms"
+        );
+        assert_eq!(changes, 7);
+        sender.abort();
+    }
+
+    #[tokio::test]
+    async fn watcher_reload_sees_an_edit_made_during_an_in_flight_reload() {
+        let target = workspace_root().join("target");
+        fs::create_dir_all(&target).unwrap();
+        let tmp = tempfile::tempdir_in(&target).unwrap();
+        let mut state = test_state();
+        state.config.packs.root = Utf8PathBuf::from_path_buf(
+            tmp.path()
+                .strip_prefix(workspace_root().as_std_path())
+                .unwrap()
+                .to_path_buf(),
+        )
+        .unwrap();
+        let write_pack = |id: &str| {
+            fs::create_dir_all(tmp.path().join(id)).unwrap();
+            fs::write(
+                tmp.path().join(id).join("pack.json"),
+                json!({ "id": id }).to_string(),
+            )
+            .unwrap();
+        };
+        write_pack("first");
+
+        // A reload that scanned the root before the edit and is still installing.
+        let stale = build_pack_index(&state.config.packs).unwrap();
+        let in_flight = tokio::spawn({
+            let state = state.clone();
+            async move {
+                let task_state = state.clone();
+                state
+                    .pack_reload
+                    .run(move || async move {
+                        tokio::time::sleep(Duration::from_millis(200)).await;
+                        Ok(install_pack_index(&task_state, stale))
+                    })
+                    .await
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        write_pack("second");
+
+        let reload = reload_packs_after_change(&state).await.unwrap();
+        let ids: Vec<_> = reload.index.entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, ["first", "second"]);
+        assert_eq!(in_flight.await.unwrap().unwrap().index.generation, 1);
+        assert_eq!(reload.index.generation, 2);
+        assert_eq!(state.pack_index.read().entries.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn dry_run_previews_mutations_without_applying_them() {
+        let mut state = test_state();
+        state.session_store = InMemorySessionStore::new();
+        let send = |method: &str, uri: &str| {
+            build_router(state.clone()).oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+        let preview = |resp: axum::response::Response| async move {
+            assert_eq!(resp.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<DryRunPreview>(&body).unwrap()
+        };
+
+        let reload = preview(send("POST", "/packs/reload?dry_run=true").await.unwrap()).await;
+        assert_eq!(reload.action, "packs reload");
+        assert!(reload.keys.contains(&"deploy-generic".to_string()));
+        assert!(
+            reload
+                .subjects
+                .iter()
+                .any(|subject| subject.starts_with("deploy-generic: added at"))
+        );
+        assert_eq!(state.pack_index.read().generation, 0);
+        assert!(state.pack_index.read().entries.is_empty());
+        // Once installed, a second preview has nothing left to change.
+        reload_packs(&state).await.unwrap();
+        let again = preview(send("POST", "/packs/reload?dry_run=true").await.unwrap()).await;
+        assert_eq!((again.count, again.keys.len()), (0, 0));
+
+        for flow in ["chat", "chat", "billing"] {
+            record_runner_event(
+                &state.runner_events,
+                RunnerEvent {
+                    timestamp_ms: 1,
+                    flow: flow.into(),
+                    tenant: None,
+                    team: None,
+                    user: None,
+                    payload: Value::Null,
+                    result: Value::Null,
+                    sequence: None,
+                    instance: None,
+                },
+            );
+        }
+        let clear = preview(send("DELETE", "/runner/events?dry_run=true").await.unwrap()).await;
+        assert_eq!(clear.count, 3);
+        assert_eq!(clear.subjects, ["billing (1)", "chat (2)"]);
+        assert_eq!(state.runner_events.read().len(), 3);
+
+        for (key, flow) in [("a", "welcome"), ("b", "welcome")] {
+            state
+                .session_store
+                .upsert(SessionUpsert {
+                    key: key.into(),
+                    tenant: "preview".into(),
+                    team: None,
+                    user: Some(key.into()),
+                    flow_id: Some(flow.into()),
+                    node_id: None,
+                    context: Value::Null,
+                    pack_id: None,
+                    flow_version: None,
+                    locale: None,
+                    ttl_ms: None,
+                    pack_generation: None,
+                    runner_target: None,
+                })
+                .unwrap();
+        }
+        let filter = SessionFilter::new(Some("preview".into()), None, None);
+        let purge = preview_session_purge(state.session_store.as_ref(), &filter).unwrap();
+        assert_eq!((purge.count, purge.keys), (2, vec!["a".into(), "b".into()]));
+        assert_eq!(purge.subjects, ["welcome"]);
+        assert_eq!(state.session_store.list(&filter).unwrap().len(), 2);
+
+        // Commands that cannot preview refuse the flag instead of running for real.
+        let cli = Cli::try_parse_from(["greentic-integration", "--dry-run", "sessions", "purge"]);
+        assert!(honours_dry_run(&cli.unwrap().command));
+        let cli = Cli::try_parse_from([
+            "greentic-integration",
+            "packs",
+            "plan-snapshot",
+            "--dry-run",
+        ]);
+        assert!(!honours_dry_run(&cli.unwrap().command));
+    }
+
+    #[test]
+    fn sessions_purge_age_flags_resolve_an_update_cutoff() {
+        let purge_args = |flags: &[&str]| -> Result<SessionPurgeArgs, clap::Error> {
+            let cli = Cli::try_parse_from(
+                ["greentic-integration", "sessions", "purge"]
+                    .iter()
+                    .chain(flags),
+            )?;
+            match cli.command {
+                Command::Sessions {
+                    command: SessionCommand::Purge(args),
+                } => Ok(args),
+                other => panic!("unexpected command {other:?}"),
+            }
+        };
+        let day_ms = 24 * 60 * 60 * 1000;
+        let now_ms = 30 * day_ms;
+
+        let args = purge_args(&["--older-than", "7d"]).unwrap();
+        assert_eq!(args.cutoff_ms(now_ms), Some(23 * day_ms));
+        let args = purge_args(&["--updated-before", "1970-01-02T00:00:00Z"]).unwrap();
+        assert_eq!(args.cutoff_ms(now_ms), Some(day_ms));
+        let args = purge_args(&["--updated-before", "1234"]).unwrap();
+        assert_eq!(args.cutoff_ms(now_ms), Some(1234));
+        assert_eq!(purge_args(&[]).unwrap().cutoff_ms(now_ms), None);
+
+        assert!(purge_args(&["--updated-before", "yesterday"]).is_err());
+        assert!(purge_args(&["--older-than", "7d", "--updated-before", "1234"]).is_err());
+    }
+
+    #[tokio::test]
+    async fn pack_mutations_reject_stale_index_generation() {
+        let state = test_state();
+        let post = |uri: &str, body: Value| {
+            build_router(state.clone()).oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(uri)
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                    .unwrap(),
+            )
+        };
+        let observed = reload_packs(&state).await.unwrap().index.generation;
+
+        let resp = post("/packs/deploy-generic/plan", json!({})).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::PRECONDITION_REQUIRED);
+        let resp = post(
+            "/packs/deploy-generic/plan",
+            json!({"index_generation": observed, "environment": "staging"}),
+        )
+        .await
+        .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let plan: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(plan["environment"], "staging");
+
+        reload_packs(&state).await.unwrap();
+        let resp = post(
+            "/packs/deploy-generic/plan",
+            json!({"index_generation": observed}),
+        )
+        .await
+        .unwrap();
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let conflict: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(conflict["observed"], observed);
+        assert_eq!(conflict["index_generation"], observed + 1);
+
+        let resp = post(
+            "/runner/emit",
+            json!({"flow": "flow-demo", "index_generation": observed}),
+        )
+        .await
+        .unwrap();
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        assert!(state.runner_events.read().is_empty());
+    }
+
+    #[tokio::test]
+    async fn resumed_messages_land_in_transcript_and_compare_with_golden() {
+        let state = state_with_session("flow-transcript");
+        let req = SessionResumeRequest {
+            key: None,
+            node_id: None,
+            tenant: Some("dev".into()),
+            team: None,
+            user: Some("user-test".into()),
+            payload: Some(json!({"text": "About"})),
+            locale: None,
+        };
+        let event = resume_session_http(Extension(state.clone()), HeaderMap::new(), Json(req))
+            .await
+            .expect("resume should succeed");
+        assert_eq!(event.flow, "flow-transcript");
+
+        let tmp = tempfile::tempdir().unwrap();
+        let pack_dir = tmp.path().join("menu");
+        fs::create_dir_all(pack_dir.join("golden")).unwrap();
+        fs::write(
+            pack_dir.join("pack.json"),
+            json!({"id": "menu", "version": "0.1.0", "scenarios": [
+                {"id": "about", "golden": "golden/about.json"},
+                {"id": "contact", "golden": "golden/contact.json"}
+            ]})
+            .to_string(),
+        )
+        .unwrap();
+        for (scenario, line) in [("about", "USER: About"), ("contact", "USER: Contact")] {
+            fs::write(
+                pack_dir.join(format!("golden/{scenario}.json")),
+                json!({"scenario_id": scenario, "transcript": [line]}).to_string(),
+            )
+            .unwrap();
+        }
+        state.pack_index.write().entries.push(PackEntry {
+            id: "menu".into(),
+            name: None,
+            kind: None,
+            version: None,
+            status: PackStatus::Active,
+            tags: Vec::new(),
+            assets: Vec::new(),
+            path: Utf8PathBuf::from_path_buf(pack_dir).unwrap(),
+            relative_path: Utf8PathBuf::from("menu"),
+            flows: Vec::new(),
+            context_schemas: BTreeMap::new(),
+            event_schemas: BTreeMap::new(),
+            routes: Vec::new(),
+            contents: PackContents::default(),
+        });
+
+        let app = build_router(state);
+        let get = |uri: &str| {
+            app.clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        };
+        let resp = get("/sessions/test-sess/transcript?pack=menu&scenario=about")
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let data: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(data["transcript"], json!(["USER: About"]));
+        assert_eq!(data["entries"][0]["flow_id"], "flow-transcript");
+        assert_eq!(data["golden"]["matches"], true);
+        assert_eq!(data["golden"]["transcript_hash"], data["transcript_hash"]);
+
+        let resp = get("/sessions/test-sess/transcript?pack=menu&scenario=contact")
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let data: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(data["golden"]["matches"], false);
+        assert_eq!(data["golden"]["first_difference"], 0);
+
+        for (uri, status) in [
+            (
+                "/sessions/test-sess/transcript?pack=menu",
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                "/sessions/test-sess/transcript?pack=menu&scenario=missing",
+                StatusCode::NOT_FOUND,
+            ),
+            ("/sessions/unknown/transcript", StatusCode::NOT_FOUND),
+        ] {
+            assert_eq!(get(uri).await.unwrap().status(), status, "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn session_locale_is_stored_and_inbound_locale_takes_precedence() {
+        let mut state = test_state();
+        state.session_store = InMemorySessionStore::new();
+        let app = build_router(state);
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/sessions")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        json!({"tenant": "dev", "user": "u-de", "locale": " de-CH "}).to_string(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .uri("/sessions?user=u-de")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let list: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(list["sessions"][0]["locale"], "de-CH");
+
+        let payload = json!({"text": "hi", "locale": "fr"});
+        assert_eq!(inbound_locale(None, &payload).as_deref(), Some("fr"));
+        assert_eq!(
+            inbound_locale(Some("de".into()), &payload).as_deref(),
+            Some("de")
+        );
+        assert_eq!(inbound_locale(Some(" ".into()), &Value::Null), None);
+    }
+
+    #[tokio::test]
+    async fn offline_mode_rejects_remote_targets_and_reports_on_healthz() {
+        let mut config = AppConfig::default();
+        config.runner.nats_url = Some("nats://demo.nats.io:4222".into());
+        let offline = NetworkPolicy::offline();
+        let err = check_network_targets(&offline, &config, None).unwrap_err();
+        assert!(err.to_string().contains("runner NATS (runner.nats_url)"));
+        check_network_targets(&NetworkPolicy::default(), &config, None).unwrap();
+
+        config.runner.nats_url = Some("nats://127.0.0.1:4222".into());
+        check_network_targets(&offline, &config, Some("http://localhost:8081")).unwrap();
+        assert!(check_network_targets(&offline, &config, Some("http://runner:8081")).is_err());
+
+        config.stores.state.backend = StoreBackend::NatsKv;
+        config.stores.state.nats_url = Some("nats://kv.example.com:4222".into());
+        let err = check_network_targets(&offline, &config, None).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("state store (stores.state.nats_url)")
+        );
+        config.stores.state = StoreConfig::memory();
+
+        config.runner.anomaly.webhook_url = Some("https://hooks.example.com/anomaly".into());
+        let err = check_network_targets(&offline, &config, None).unwrap_err();
+        assert!(err.to_string().contains("runner.anomaly.webhook_url"));
+        config.runner.anomaly.webhook_url = None;
+
+        config
+            .server
+            .outbox
+            .sinks
+            .insert("slack".into(), "https://sink.example.com/slack".into());
+        let err = check_network_targets(&offline, &config, None).unwrap_err();
+        assert!(err.to_string().contains("server.outbox.sinks.slack"));
+        config.server.outbox.sinks.clear();
+
+        let mut state = test_state();
+        state.network = offline;
+        let response = build_router(state)
+            .oneshot(
+                Request::builder()
+                    .uri("/healthz")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let health: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(health, json!({"status": "ok", "offline": true}));
+    }
+
+    #[tokio::test]
+    async fn clustered_instances_share_runner_events() {
+        let coordinator = crate::cluster::memory::MemoryCoordinator::new();
+        let join = |name: &str| {
+            let cluster = Cluster::new(
+                name,
+                Some(coordinator.clone() as Arc<dyn crate::cluster::Coordinator>),
+                Duration::from_secs(5),
+            );
+            let events = RunnerEventLog::new(EventPolicy::default());
+            let outbound = Arc::new(tokio::sync::Mutex::new(events.join_cluster(name)));
+            tokio::spawn(publish_cluster_events(cluster.clone(), outbound));
+            tokio::spawn(ingest_cluster_events(cluster.clone(), events.clone()));
+            events
+        };
+        let (a, b) = (join("bridge-a"), join("bridge-b"));
+        while coordinator.subscribers() < 2 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        for (events, flow) in [(&a, "flow-a"), (&b, "flow-b")] {
+            record_runner_event(
+                events,
+                RunnerEvent {
+                    timestamp_ms: now_millis(),
+                    flow: flow.into(),
+                    tenant: Some("acme".into()),
+                    team: None,
+                    user: Some("u1".into()),
+                    payload: Value::Null,
+                    result: json!({"status": "ok"}),
+                    sequence: None,
+                    instance: None,
+                },
+            );
+        }
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while a.read().len() < 2 || b.read().len() < 2 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("events reach the other instance");
+        coordinator.close();
+
+        for events in [&a, &b] {
+            let mut seen: Vec<(String, Option<String>, Option<u64>)> = events
+                .read()
+                .iter()
+                .map(|event| (event.flow.clone(), event.instance.clone(), event.sequence))
+                .collect();
+            seen.sort();
+            assert_eq!(
+                seen,
+                [
+                    ("flow-a".into(), Some("bridge-a".into()), Some(1)),
+                    ("flow-b".into(), Some("bridge-b".into()), Some(1)),
+                ]
+            );
+        }
+
+        let response = build_router(test_state())
+            .oneshot(
+                Request::builder()
+                    .uri("/cluster")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let status: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(status["instance"], "test");
+        assert_eq!(status["clustered"], false);
+        assert_eq!(status["members"], json!(["test"]));
+    }
+
+    #[tokio::test]
+    async fn healthz_history_reports_uptime_and_failures() {
+        let state = test_state();
+        let packs = tempfile::tempdir().unwrap();
+        let missing = packs.path().join("missing");
+        for (timestamp_ms, root) in [(1, packs.path()), (2, missing.as_path())] {
+            state.health.record(run_checks(
+                &HealthTargets {
+                    session_backend: "memory",
+                    sessions: state.session_store.as_ref(),
+                    packs_root: root,
+                    nats_url: None,
+                    timeout: Duration::from_secs(1),
+                },
+                timestamp_ms,
+            ));
+        }
+        let response = build_router(state)
+            .oneshot(
+                Request::builder()
+                    .uri("/healthz/history")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let history: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(history["interval_secs"], 30);
+        assert_eq!(history["samples"], 2);
+        assert_eq!(history["since_ms"], 1);
+        assert_eq!(history["uptime_percent"], 50.0);
+        assert_eq!(history["checks"]["session_store"]["uptime_percent"], 100.0);
+        assert_eq!(history["checks"]["packs_root"]["failures"], 1);
+        assert_eq!(history["last_failures"][0]["check"], "packs_root");
+        assert!(
+            history["last_failures"][0]["error"]
+                .as_str()
+                .unwrap()
+                .contains("missing")
+        );
+    }
+
+    #[tokio::test]
+    async fn tail_sampler_keeps_failed_and_slow_request_traces() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let mut state = test_state();
+        state.traces = TailSampler::default();
+        state
+            .traces
+            .configure(&trace_sampling::SamplingConfig {
+                enabled: true,
+                latency_threshold_ms: 0,
+                ..Default::default()
+            })
+            .unwrap();
+        let _guard = tracing::subscriber::set_default(
+            tracing_subscriber::registry().with(state.traces.layer()),
+        );
+        let routes = Router::new()
+            .route("/runner/emit", post(runner_emit_http))
+            .route("/diagnostics/traces", get(trace_diagnostics_http))
+            .route(
+                "/fail",
+                get(|| async {
+                    error!(store = "sessions", "backend unavailable");
+                    StatusCode::SERVICE_UNAVAILABLE
+                }),
+            );
+        let app = with_app_layers(routes, state);
+        let request = |method: &str, uri: &str, body: Body| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("content-type", "application/json")
+                .header(
+                    TRACEPARENT,
+                    "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+                )
+                .body(body)
+                .unwrap()
+        };
+        let emit = RunnerEmitRequest {
+            flow: "flow-sampled".into(),
+            tenant: Some("dev".into()),
+            team: None,
+            user: None,
+            payload: Some(json!({"text": "hi"})),
+            index_generation: None,
+            locale: None,
+        };
+        let body = Body::from(serde_json::to_vec(&emit).unwrap());
+        let response = app
+            .clone()
+            .oneshot(request("POST", "/runner/emit", body))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app
+            .clone()
+            .oneshot(request("GET", "/fail", Body::empty()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let response = app
+            .oneshot(request("GET", "/diagnostics/traces", Body::empty()))
+            .await
+            .unwrap();
+        let body = body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let report: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(report["kept"], 2);
+        let failed = &report["traces"][0];
+        assert_eq!(failed["path"], "/fail");
+        assert_eq!(failed["status"], 503);
+        assert_eq!(failed["reason"], "error");
+        assert_eq!(failed["trace_id"], "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(failed["records"][0]["fields"]["store"], "sessions");
+        let emitted = &report["traces"][1];
+        assert_eq!(emitted["reason"], "slow");
+        let flow_run = emitted["records"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|record| record["name"] == "flow_run")
+            .expect("flow_run span");
+        assert_eq!(flow_run["fields"]["flow"], "flow-sampled");
+    }
+
+    #[tokio::test]
+    async fn handler_panics_become_structured_500s_and_are_recorded() {
+        let state = test_state();
+        let routes = Router::new().route(
+            "/boom",
+            post(|Json(payload): Json<Value>| async move {
+                let depth = payload["depth"].as_u64().expect("depth must be a number");
+                Json(json!({ "depth": depth }))
+            }),
+        );
+        let app = with_app_layers(routes, state.clone());
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/boom")
+                    .header("content-type", "application/json")
+                    .header(panic_guard::REQUEST_ID_HEADER, "req-42")
+                    .body(Body::from(r#"{"depth": "deep"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(response.headers()[panic_guard::REQUEST_ID_HEADER], "req-42");
+        let body = body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let error: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error["error"], "internal_panic");
+        assert_eq!(error["message"], "depth must be a number");
+
+        // The same router keeps serving after the panic.
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/boom")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"depth": 3}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = build_router(state)
+            .oneshot(
+                Request::builder()
+                    .uri("/diagnostics/panics")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let diagnostics: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(diagnostics["total"], 1);
+        let report = &diagnostics["recent"][0];
+        assert_eq!(
+            (&report["request_id"], &report["method"], &report["path"]),
+            (&json!("req-42"), &json!("POST"), &json!("/boom"))
+        );
+    }
+
+    #[tokio::test]
+    async fn readyz_reports_failed_critical_tasks() {
+        async fn readyz_body(state: AppState) -> (StatusCode, Value) {
+            let response = build_router(state)
+                .oneshot(
+                    Request::builder()
+                        .uri("/readyz")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            (status, serde_json::from_slice(&body).unwrap())
+        }
+
+        let supervisor = Supervisor::new(SupervisorConfig {
+            max_restarts: 0,
+            ..SupervisorConfig::default()
+        });
+        supervisor.spawn("runner_proxy", true, std::future::pending);
+        let mut state = test_state();
+        state.tasks = supervisor.registry();
+        let (status, body) = readyz_body(state.clone()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["tasks"]["runner_proxy"]["status"], "running");
+
+        supervisor.spawn("session_compaction", true, || async {
+            Err(anyhow!("store unavailable"))
+        });
+        let reason = supervisor.escalated().await;
+        assert!(reason.contains("session_compaction"), "{reason}");
+        let (status, body) = readyz_body(state).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["status"], "degraded");
+        assert_eq!(
+            body["tasks"]["session_compaction"],
+            json!({
+                "status": "failed",
+                "critical": true,
+                "restarts": 0,
+                "last_error": "store unavailable",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn runner_proxy_speaks_the_runner_protocol() {
+        let log = crate::runner_protocol::StubLog::default();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(
+            axum::serve(listener, crate::runner_protocol::stub_router(log.clone())).into_future(),
+        );
+
+        let events = SharedRunnerEvents::default();
+        let commands = vec![
+            RunnerCommand::ReloadPacks {
+                packs: PackIndex {
+                    entries: vec![PackEntry {
+                        id: "demo".into(),
+                        name: Some("Demo".into()),
+                        kind: Some("application".into()),
+                        version: Some("1.0.0".into()),
+                        status: PackStatus::Deprecated,
+                        tags: vec!["smoke".into()],
+                        assets: Vec::new(),
+                        path: Utf8PathBuf::from("packs/demo"),
+                        relative_path: Utf8PathBuf::from("demo"),
+                        flows: vec!["flow-a".into()],
+                        context_schemas: BTreeMap::new(),
+                        event_schemas: BTreeMap::new(),
+                        routes: Vec::new(),
+                        contents: PackContents::default(),
+                    }],
+                    invalid: Vec::new(),
+                    generation: 3,
+                },
+                defaults: SeedDefaults::default(),
+            },
+            RunnerCommand::EmitActivity {
+                flow: "flow-a".into(),
+                tenant: Some("acme".into()),
+                team: None,
+                user: Some("user-1".into()),
+                payload: json!({"text": "hi"}),
+            },
+            RunnerCommand::Emit("hello".into()),
+        ];
+        tokio::task::spawn_blocking(move || {
+            for cmd in commands {
+                process_runner_command(
+                    &events,
+                    Some(&base),
+                    &RunnerTargets::default(),
+                    &OutboundHttp::default(),
+                    0,
+                    cmd,
+                );
+            }
+        })
+        .await
+        .unwrap();
+
+        let accepted = log.lock().clone();
+        assert_eq!(accepted.len(), 3, "stub rejected a bridge command");
+        match &accepted[0] {
+            RunnerRequest::Reload(reload) => {
+                assert_eq!(reload.packs[0].status, "deprecated");
+                assert_eq!(reload.packs[0].flows, vec!["flow-a"]);
+            }
+            other => panic!("expected reload, got {other:?}"),
+        }
+        match &accepted[1] {
+            RunnerRequest::Activity(activity) => assert_eq!(activity.result["status"], "ok"),
+            other => panic!("expected activity, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn sessions_stay_pinned_to_a_runner_target_and_repin_when_it_fails() {
+        let log = crate::runner_protocol::StubLog::default();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let up = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(
+            axum::serve(listener, crate::runner_protocol::stub_router(log.clone())).into_future(),
+        );
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let down = format!("http://{}", closed.local_addr().unwrap());
+        drop(closed);
+
+        let mut config = AppConfig::default();
+        config.cluster.instance_id = Some("test".into());
+        config.stores.session = StoreConfig::memory();
+        config.runner.targets = vec![
+            RunnerTargetConfig {
+                name: "up".into(),
+                url: up,
+            },
+            RunnerTargetConfig {
+                name: "down".into(),
+                url: down,
+            },
+        ];
+        let state = Server::from_config(config)
+            .build_state(
+                &Supervisor::new(SupervisorConfig::default()),
+                PackIndex::default(),
+            )
+            .unwrap();
+        // A session the hash places on the target that is about to fail.
+        let key = (0..)
+            .map(|idx| format!("pinned-{idx}"))
+            .find(|key| state.runner_targets.pin(None, key).unwrap().target == "down")
+            .unwrap();
+        let park = || {
+            let request = serde_json::from_value(json!({
+                "key": key,
+                "tenant": "dev",
+                "user": "user-pinned",
+                "flow_id": "welcome",
+            }))
+            .unwrap();
+            upsert_session(Extension(state.clone()), HeaderMap::new(), Json(request))
+        };
+        let resume = || {
+            let request = SessionResumeRequest {
+                key: Some(key.clone()),
+                node_id: None,
+                tenant: None,
+                team: None,
+                user: None,
+                payload: Some(json!({"reply": "hi"})),
+                locale: None,
+            };
+            resume_session_http(Extension(state.clone()), HeaderMap::new(), Json(request))
+        };
+        async fn eventually(done: impl Fn() -> bool) -> bool {
+            let deadline = std::time::Instant::now() + Duration::from_secs(5);
+            while !done() && std::time::Instant::now() < deadline {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            done()
+        }
+
+        let parked = park().await.unwrap();
+        assert_eq!(parked.runner_target.as_deref(), Some("down"));
+        let parked = park().await.unwrap();
+        assert_eq!(
+            parked.runner_target.as_deref(),
+            Some("down"),
+            "re-parking keeps the pin"
+        );
+
+        // The resume is forwarded to the pinned target; its failure marks the target unhealthy.
+        assert_eq!(resume().await.unwrap().flow, "welcome");
+        assert!(eventually(|| !state.runner_targets.is_healthy("down")).await);
+
+        // New pins avoid the unhealthy target; one parked before the failure moves on resume.
+        let parked = park().await.unwrap();
+        assert_eq!(parked.runner_target.as_deref(), Some("up"));
+        state
+            .session_store
+            .put(SessionRecord {
+                runner_target: Some("down".into()),
+                ..state.session_store.get(&key).unwrap().unwrap()
+            })
+            .unwrap();
+        assert_eq!(resume().await.unwrap().flow, "welcome");
+        let repins: Vec<_> = state
+            .runner_events
+            .read()
+            .iter()
+            .filter(|event| event.flow == runner_targets::REPIN_FLOW)
+            .map(|event| event.payload.clone())
+            .collect();
+        assert_eq!(
+            repins,
+            [json!({"session": key, "from": "down", "to": "up"})]
+        );
+        assert!(
+            eventually(|| {
+                log.lock().iter().any(|request| {
+                    matches!(request, RunnerRequest::Activity(activity) if activity.flow == "welcome")
+                })
+            })
+            .await
+        );
+
+        let Json(targets) = runner_targets_http(Extension(state.clone())).await;
+        let health: Vec<_> = targets
+            .iter()
+            .map(|target| (target.name.as_str(), target.healthy))
+            .collect();
+        assert_eq!(health, [("up", true), ("down", false)]);
+    }
+
+    #[tokio::test]
+    async fn runner_queue_sheds_overflow_and_reports_depth() {
+        let mut state = test_state();
+        state.config.runner.queue = RunnerQueueConfig {
+            capacity: 1,
+            overflow: runner_queue::OverflowPolicy::Shed,
+            ..RunnerQueueConfig::default()
+        };
+        // Keep the receiver idle so the queue fills up.
+        let (queue, _rx) = RunnerQueue::new(&state.config.runner.queue);
+        state.runner_proxy = RunnerHostProxy::new(queue, None);
+        for flow in ["first", "second"] {
+            state
+                .runner_proxy
+                .submit(RunnerCommand::EmitActivity {
+                    flow: flow.into(),
+                    tenant: Some("dev".into()),
+                    team: None,
+                    user: None,
+                    payload: json!({"large": "ignored"}),
+                })
+                .await;
+        }
+
+        let response = build_router(state)
+            .oneshot(
+                Request::builder()
+                    .uri("/runner/queue")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let queue: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(queue["capacity"], 1);
+        assert_eq!(queue["depth"], 1);
+        assert_eq!(queue["overflow"], "shed");
+        assert_eq!(
+            (queue["enqueued"].clone(), queue["shed"].clone()),
+            (json!(1), json!(1))
+        );
+        assert_eq!(queue["dead_letters"][0]["kind"], "emit_activity");
+        assert_eq!(
+            queue["dead_letters"][0]["summary"],
+            json!({"flow": "second", "tenant": "dev", "team": null, "user": null})
+        );
+    }
+
+    #[tokio::test]
+    async fn extension_packs_serve_their_declared_routes() {
+        let state = test_state();
+        let mount = |routes: Value| {
+            let manifest = json!({"routes": routes});
+            state.pack_index.write().entries = vec![PackEntry {
+                id: "mock-crm".into(),
+                name: None,
+                kind: Some("extension".into()),
+                version: None,
+                status: PackStatus::Active,
+                tags: Vec::new(),
+                assets: Vec::new(),
+                path: Utf8PathBuf::from("packs/mock-crm"),
+                relative_path: Utf8PathBuf::from("mock-crm"),
+                flows: Vec::new(),
+                context_schemas: BTreeMap::new(),
+                event_schemas: BTreeMap::new(),
+                routes: pack_routes::load_routes(Some("extension"), &manifest).unwrap(),
+                contents: PackContents::default(),
+            }];
+        };
+        mount(json!([
+            {
+                "path": "/customers/{id}",
+                "headers": {"x-mock": "{{pack}}"},
+                "body": {"id": "{{path.id}}", "tier": "{{query.tier}}"}
+            },
+            {
+                "method": "POST",
+                "path": "/customers",
+                "status": 201,
+                "body": {"created": "{{body}}"}
+            },
+            {"method": "POST", "path": "/events", "forward": {"subject": "ext.{{pack}}.events"}}
+        ]));
+        let app = build_router(state.clone());
+        async fn body_json(resp: Response) -> Value {
+            let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice(&body).unwrap()
+        }
+        let call = |method: &str, uri: &str, body: Option<Value>| {
+            let req = Request::builder().method(method).uri(uri);
+            let body = body.map_or_else(Body::empty, |body| Body::from(body.to_string()));
+            app.clone().oneshot(req.body(body).unwrap())
+        };
+
+        let resp = call("GET", "/ext/mock-crm/customers/42?tier=gold", None)
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()["x-mock"], "mock-crm");
+        assert_eq!(body_json(resp).await, json!({"id": "42", "tier": "gold"}));
+        let resp = call(
+            "POST",
+            "/ext/mock-crm/customers",
+            Some(json!({"name": "Ada"})),
+        )
+        .await
+        .unwrap();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        assert_eq!(body_json(resp).await, json!({"created": {"name": "Ada"}}));
+        for (method, uri, status) in [
+            (
+                "DELETE",
+                "/ext/mock-crm/customers/42",
+                StatusCode::METHOD_NOT_ALLOWED,
+            ),
+            ("GET", "/ext/mock-crm/orders", StatusCode::NOT_FOUND),
+            ("GET", "/ext/unknown/customers/42", StatusCode::NOT_FOUND),
+            (
+                "POST",
+                "/ext/mock-crm/events",
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+        ] {
+            let resp = call(method, uri, None).await.unwrap();
+            assert_eq!(resp.status(), status, "{method} {uri}");
+        }
+
+        mount(json!([{"path": "/orders", "body": "none yet"}]));
+        let resp = call("GET", "/ext/mock-crm/orders", None).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"none yet");
+        let resp = call("GET", "/ext/mock-crm/customers/42", None)
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn pack_assets_are_served_with_type_and_cache_headers() {
+        let tmp = tempfile::tempdir().unwrap();
+        let pack_dir = tmp.path().join("cards");
+        fs::create_dir_all(pack_dir.join("assets/img")).unwrap();
+        fs::write(pack_dir.join("pack.json"), b"{}").unwrap();
+        fs::write(pack_dir.join("assets/img/hero.svg"), b"<svg/>").unwrap();
+        let state = test_state();
+        state.pack_index.write().entries.push(PackEntry {
+            id: "cards".into(),
+            name: None,
+            kind: None,
+            version: None,
+            status: PackStatus::Active,
+            tags: Vec::new(),
+            assets: discover_assets(&pack_dir).unwrap(),
+            path: Utf8PathBuf::from_path_buf(pack_dir).unwrap(),
+            relative_path: Utf8PathBuf::from("cards"),
+            flows: Vec::new(),
+            context_schemas: BTreeMap::new(),
+            event_schemas: BTreeMap::new(),
+            routes: Vec::new(),
+            contents: PackContents::default(),
+        });
+        let app = build_router(state);
+        let get = |uri: &str, etag: Option<&str>| {
+            let mut req = Request::builder().uri(uri);
+            if let Some(etag) = etag {
+                req = req.header("if-none-match", etag);
+            }
+            app.clone().oneshot(req.body(Body::empty()).unwrap())
+        };
+
+        let resp = get("/packs/cards/assets/img/hero.svg", None).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()["content-type"], "image/svg+xml");
+        assert!(
+            resp.headers()["cache-control"]
+                .to_str()
+                .unwrap()
+                .contains("max-age")
+        );
+        let etag = resp.headers()["etag"].to_str().unwrap().to_string();
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"<svg/>");
+
+        let resp = get("/packs/cards/assets/img/hero.svg", Some(&etag))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+
+        for (uri, status) in [
+            ("/packs/cards/assets/../pack.json", StatusCode::BAD_REQUEST),
+            ("/packs/cards/assets/img/missing.png", StatusCode::NOT_FOUND),
+            ("/packs/unknown/assets/img/hero.svg", StatusCode::NOT_FOUND),
+        ] {
+            assert_eq!(get(uri, None).await.unwrap().status(), status, "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn packs_endpoint_filters_by_kind_and_tags() {
+        let state = test_state();
+        let pack = |id: &str, kind: &str, tags: &[&str]| PackEntry {
+            id: id.into(),
+            name: None,
+            kind: Some(kind.into()),
+            version: None,
+            status: PackStatus::Active,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            assets: Vec::new(),
+            path: Utf8PathBuf::from(format!("packs/{id}")),
+            relative_path: Utf8PathBuf::from(id),
+            flows: Vec::new(),
+            context_schemas: BTreeMap::new(),
+            event_schemas: BTreeMap::new(),
+            routes: Vec::new(),
+            contents: PackContents::default(),
+        };
+        state.pack_index.write().entries = vec![
+            pack("menu", "application", &["smoke"]),
+            pack("deploy-smoke", "deployment", &["smoke", "iac"]),
+            pack("deploy-full", "deployment", &["iac"]),
+        ];
+
+        let resp = build_router(state)
+            .oneshot(
+                Request::builder()
+                    .uri("/packs?kind=DEPLOYMENT&tag=iac,smoke")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let data: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(data["count"], 1);
+        assert_eq!(data["packs"][0]["id"], "deploy-smoke");
+        assert_eq!(data["packs"][0]["tags"], json!(["smoke", "iac"]));
+    }
+
+    #[tokio::test]
+    async fn pack_lifecycle_hides_disabled_and_warns_on_deprecated() {
+        let state = test_state();
+        let pack = |id: &str, status: PackStatus| PackEntry {
+            id: id.into(),
+            name: None,
+            kind: None,
+            version: None,
+            status,
+            tags: Vec::new(),
+            assets: Vec::new(),
+            path: Utf8PathBuf::from(format!("packs/{id}")),
+            relative_path: Utf8PathBuf::from(id),
+            flows: vec![format!("{id}-flow")],
+            context_schemas: BTreeMap::new(),
+            event_schemas: BTreeMap::new(),
+            routes: Vec::new(),
+            contents: PackContents::default(),
+        };
+        state.pack_index.write().entries = vec![
+            pack("live", PackStatus::Active),
+            pack("legacy", PackStatus::Deprecated),
+            pack("retired", PackStatus::Disabled),
+        ];
+        assert!(
+            state
+                .pack_index
+                .read()
+                .pack_for_flow("retired-flow", None, None, None)
+                .is_none()
+        );
+
+        let resp = build_router(state.clone())
+            .oneshot(
+                Request::builder()
+                    .uri("/packs")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let data: Value = serde_json::from_slice(&body).unwrap();
+        let ids: Vec<&str> = data["packs"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(ids, vec!["live", "legacy"]);
+        assert_eq!(data["packs"][1]["status"], "deprecated");
+        assert!(data["warnings"][0].as_str().unwrap().contains("legacy"));
+
+        let transitions = install_pack_index(
+            &state,
+            PackIndex {
+                entries: vec![
+                    pack("live", PackStatus::Disabled),
+                    pack("legacy", PackStatus::Deprecated),
+                    pack("retired", PackStatus::Active),
+                ],
+                invalid: Vec::new(),
+                generation: 0,
+            },
+        )
+        .transitions;
+        let summary: Vec<(&str, PackStatus, PackStatus)> = transitions
+            .iter()
+            .map(|t| (t.id.as_str(), t.from, t.to))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("live", PackStatus::Active, PackStatus::Disabled),
+                ("retired", PackStatus::Disabled, PackStatus::Active),
+            ]
+        );
+    }
+
+    #[test]
+    fn completions_and_man_pages_cover_every_subcommand() {
+        fn paths(command: &clap::Command, prefix: &str, out: &mut Vec<(String, String)>) {
+            for sub in command.get_subcommands() {
+                let page = format!("{prefix}-{}", sub.get_name());
+                out.push((sub.get_name().to_string(), page.clone()));
+                paths(sub, &page, out);
+            }
+        }
+        let mut subcommands = Vec::new();
+        paths(&Cli::command(), "greentic-integration", &mut subcommands);
+        assert!(
+            subcommands
+                .iter()
+                .any(|(name, _)| name == "verify-plan-bundle")
+        );
+        #[cfg(feature = "components")]
+        assert!(subcommands.iter().any(|(name, _)| name == "components"));
+
+        for shell in [
+            clap_complete::Shell::Bash,
+            clap_complete::Shell::Zsh,
+            clap_complete::Shell::Fish,
+        ] {
+            let mut script = Vec::new();
+            write_completions(shell, &mut script);
+            let script = String::from_utf8(script).unwrap();
+            for (name, _) in &subcommands {
+                assert!(script.contains(name.as_str()), "{shell} misses {name}");
+            }
+        }
+
+        let tmp = tempfile::tempdir().unwrap();
+        mangen_cli(MangenArgs {
+            out: Some(Utf8PathBuf::from_path_buf(tmp.path().to_path_buf()).unwrap()),
+        })
+        .unwrap();
+        assert!(tmp.path().join("greentic-integration.1").is_file());
+        for (_, page) in &subcommands {
+            assert!(
+                tmp.path().join(format!("{page}.1")).is_file(),
+                "no page {page}"
+            );
+        }
+    }
+
+    #[test]
+    fn packs_failing_the_manifest_schema_are_listed_as_invalid() {
+        let target = workspace_root().join("target");
+        fs::create_dir_all(&target).unwrap();
+        let tmp = tempfile::tempdir_in(&target).unwrap();
+        for (dir, manifest) in [
+            ("good", json!({"id": "good", "tags": ["smoke"]}).to_string()),
+            (
+                "bad",
+                json!({"id": "bad", "status": "retired", "scenarios": [{"entry": "x.json"}]})
+                    .to_string(),
+            ),
+            ("broken", "{\"id\": ".to_string()),
+            (
+                "unresolved",
+                json!({"id": "unresolved", "name": "${env:GREENTIC_TEST_UNSET_PACK_NAME}"})
+                    .to_string(),
+            ),
+        ] {
+            fs::create_dir_all(tmp.path().join(dir)).unwrap();
+            fs::write(tmp.path().join(dir).join("pack.json"), manifest).unwrap();
+        }
+        let mut config = AppConfig::default().packs;
+        config.root = Utf8PathBuf::from_path_buf(
+            tmp.path()
+                .strip_prefix(workspace_root().as_std_path())
+                .unwrap()
+                .to_path_buf(),
+        )
+        .unwrap();
+
+        let index = build_pack_index(&config).expect("invalid packs do not fail the index");
+        let ids: Vec<_> = index
+            .entries
+            .iter()
+            .map(|entry| entry.id.as_str())
+            .collect();
+        assert_eq!(ids, ["good"]);
+        assert_eq!(index.invalid.len(), 3);
+        let invalid = |dir: &str| {
+            index
+                .invalid
+                .iter()
+                .find(|pack| pack.path.ends_with(dir))
+                .unwrap_or_else(|| panic!("{dir} is not listed as invalid"))
+        };
+        assert!(
+            invalid("broken").reasons[0]
+                .message
+                .contains("invalid JSON")
+        );
+        assert!(
+            invalid("unresolved").reasons[0]
+                .message
+                .contains("GREENTIC_TEST_UNSET_PACK_NAME")
+        );
+        let bad = invalid("bad");
+        assert_eq!(bad.id.as_deref(), Some("bad"));
+        let pointers: Vec<_> = bad
+            .reasons
+            .iter()
+            .map(|reason| reason.pointer.as_str())
+            .collect();
+        assert_eq!(pointers, ["/scenarios/0", "/status"]);
+
+        let Json(listing) = list_packs_filtered(&index, None, None, None, &PackFilter::default());
+        let listing = serde_json::to_value(listing).unwrap();
+        assert_eq!(listing["count"], 1);
+        assert_eq!(listing["invalid"].as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn nested_packs_are_indexed_with_their_relative_path() {
+        let target = workspace_root().join("target");
+        fs::create_dir_all(&target).unwrap();
+        let tmp = tempfile::tempdir_in(&target).unwrap();
+        for (dir, id) in [
+            ("shared", "shared"),
+            ("acme/billing", "acme-billing"),
+            ("acme/archive/2025/old", "acme-old"),
+        ] {
+            fs::create_dir_all(tmp.path().join(dir)).unwrap();
+            fs::write(
+                tmp.path().join(dir).join("pack.json"),
+                json!({ "id": id }).to_string(),
+            )
+            .unwrap();
+        }
+        let mut config = AppConfig::default().packs;
+        config.root = Utf8PathBuf::from_path_buf(
+            tmp.path()
+                .strip_prefix(workspace_root().as_std_path())
+                .unwrap()
+                .to_path_buf(),
+        )
+        .unwrap();
+        let indexed = |config: &PackConfig| -> Vec<(String, String)> {
+            build_pack_index(config)
+                .expect("pack index")
+                .entries
+                .into_iter()
+                .map(|entry| (entry.id, entry.relative_path.to_string()))
+                .collect()
+        };
+
+        assert_eq!(
+            indexed(&config),
+            [
+                ("acme-billing".to_string(), "acme/billing".to_string()),
+                ("shared".to_string(), "shared".to_string()),
+            ]
+        );
+        config.max_depth = 1;
+        assert_eq!(
+            indexed(&config),
+            [("shared".to_string(), "shared".to_string())]
+        );
+        config.max_depth = 4;
+        assert_eq!(indexed(&config).len(), 3);
+    }
+
+    #[tokio::test]
+    async fn validate_packs_endpoint_checks_the_configured_root() {
+        let resp = build_router(test_state())
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/packs/validate")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let data: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(data["ok"], true, "{data:#}");
+        let packs = data["packs"].as_array().unwrap();
+        assert!(packs.iter().any(|pack| pack["pack"] == "deploy-generic"));
+        assert!(packs.iter().all(|pack| pack["errors"] == json!([])));
+    }
+
+    #[tokio::test]
+    async fn pack_history_records_version_and_content_changes() {
+        let state = test_state();
+        let tmp = tempfile::tempdir().unwrap();
+        let pack = |version: &str, flow: &str| {
+            fs::write(
+                tmp.path().join("pack.json"),
+                json!({"id": "menu", "version": version}).to_string(),
+            )
+            .unwrap();
+            fs::write(tmp.path().join("flow.ygtc"), flow).unwrap();
+            PackIndex {
+                entries: vec![PackEntry {
+                    id: "menu".into(),
+                    name: None,
+                    kind: None,
+                    version: Some(version.into()),
+                    status: PackStatus::Active,
+                    tags: Vec::new(),
+                    assets: Vec::new(),
+                    path: Utf8PathBuf::from_path_buf(tmp.path().to_path_buf()).unwrap(),
+                    relative_path: Utf8PathBuf::from("menu"),
+                    flows: Vec::new(),
+                    context_schemas: BTreeMap::new(),
+                    event_schemas: BTreeMap::new(),
+                    routes: Vec::new(),
+                    contents: PackContents::scan(tmp.path()).unwrap(),
+                }],
+                invalid: Vec::new(),
+                generation: 0,
+            }
+        };
+        install_pack_index(&state, pack("1.0.0", "nodes: []"));
+        install_pack_index(&state, pack("1.0.0", "nodes: []"));
+        install_pack_index(&state, pack("1.1.0", "nodes: [greet]"));
+
+        let get = |uri: &str| {
+            build_router(state.clone())
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        };
+        let resp = get("/packs/menu/history").await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let data: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(data["current"]["version"], "1.1.0");
+        let changes = data["changes"].as_array().unwrap();
+        assert_eq!(changes.len(), 2, "unchanged reloads record nothing");
+        assert_eq!(changes[0]["from_version"], "1.0.0");
+        assert_eq!(changes[0]["to_version"], "1.1.0");
+        assert_eq!(changes[0]["to_hash"], data["current"]["content_hash"]);
+        assert_eq!(changes[0]["from_hash"], changes[1]["to_hash"]);
+        assert_eq!(changes[0]["modified"], json!(["flow.ygtc", "pack.json"]));
+        assert_eq!(changes[0]["index_generation"], 3);
+        assert_eq!(changes[1]["from_version"], Value::Null);
+
+        // Removed packs keep their history; unknown ids are 404.
+        install_pack_index(
+            &state,
+            PackIndex {
+                entries: Vec::new(),
+                invalid: Vec::new(),
+                generation: 0,
+            },
+        );
+        let resp = get("/packs/menu/history").await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = get("/packs/unknown/history").await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn upsert_rejects_context_violating_flow_schema() {
+        let mut state = test_state();
+        state.session_store = InMemorySessionStore::new();
+        state.pack_index.write().entries.push(PackEntry {
+            id: "schema-pack".into(),
+            name: None,
+            kind: None,
+            version: None,
+            status: PackStatus::Active,
+            tags: Vec::new(),
+            assets: Vec::new(),
+            path: Utf8PathBuf::from("packs/schema-pack"),
+            relative_path: Utf8PathBuf::from("schema-pack"),
+            flows: vec!["flow-typed".into()],
+            context_schemas: BTreeMap::from([(
+                "flow-typed".to_string(),
+                json!({
+                    "type": "object",
+                    "required": ["step"],
+                    "properties": {"step": {"type": "integer"}}
+                }),
+            )]),
+            event_schemas: BTreeMap::new(),
+            routes: Vec::new(),
+            contents: PackContents::default(),
+        });
+        let app = build_router(state.clone());
+        let upsert = |context: Value| {
+            Request::builder()
+                .method("POST")
+                .uri("/sessions")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "tenant": "dev",
+                        "user": "user-schema",
+                        "flow_id": "flow-typed",
+                        "context": context,
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap()
+        };
+
+        let resp = app
+            .clone()
+            .oneshot(upsert(json!({"step": "two"})))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let data: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(data["violations"][0]["pointer"], "/step");
+
+        let resp = app.oneshot(upsert(json!({"step": 2}))).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn emit_validates_typed_events_and_schemas_are_served() {
+        let state = test_state();
+        state.pack_index.write().entries.push(PackEntry {
+            id: "events-pack".into(),
+            name: None,
+            kind: None,
+            version: None,
+            status: PackStatus::Active,
+            tags: Vec::new(),
+            assets: Vec::new(),
+            path: Utf8PathBuf::from("packs/events-pack"),
+            relative_path: Utf8PathBuf::from("events-pack"),
+            flows: Vec::new(),
+            context_schemas: BTreeMap::new(),
+            event_schemas: BTreeMap::from([(
+                "com.example.ping.v2".to_string(),
+                json!({"type": "object", "required": ["type", "id"]}),
+            )]),
+            routes: Vec::new(),
+            contents: PackContents::default(),
+        });
+        *state.event_schemas.write() = build_schema_registry(&state.pack_index.read());
+        let app = build_router(state);
+        let emit = |payload: Value| {
+            Request::builder()
+                .method("POST")
+                .uri("/runner/emit")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::to_vec(&json!({"flow": "flow-events", "payload": payload}))
+                        .unwrap(),
+                ))
+                .unwrap()
+        };
+        let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+        let mut event = Fixture::load_json("inputs/build_status_event.json").unwrap();
+        let resp = app.clone().oneshot(emit(event.clone())).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        event["payload"]["status"] = json!("exploded");
+        let resp = app.clone().oneshot(emit(event)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let data: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(data["error"], "event_schema_violation");
+        assert_eq!(data["violations"][0]["pointer"], "/payload/status");
+
+        let resp = app
+            .clone()
+            .oneshot(emit(json!({"type": "com.example.ping.v2"})))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let resp = app
+            .clone()
+            .oneshot(get("/schemas/com.example.ping"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let data: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(data["type"], "com.example.ping.v2");
+        assert_eq!(data["pack"], "events-pack");
+
+        let resp = app.clone().oneshot(get("/schemas")).await.unwrap();
+        let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let data: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(data["count"], 3);
+
+        let resp = app
+            .oneshot(get("/schemas/com.example.missing.v1"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn reload_flags_pinned_sessions_and_blocks_unmigrated_resume() {
+        let mut state = test_state();
+        state.session_store = InMemorySessionStore::new();
+        // Without a draining generation the pinned session has to upgrade right away.
+        state.pack_generations = PackGenerations::new(1);
+        let pack = |version: &str| PackEntry {
+            id: "versioned-pack".into(),
+            name: None,
+            kind: None,
+            version: Some(version.into()),
+            status: PackStatus::Active,
+            tags: Vec::new(),
+            assets: Vec::new(),
+            path: Utf8PathBuf::from("packs/versioned-pack"),
+            relative_path: Utf8PathBuf::from("versioned-pack"),
+            flows: vec!["flow-versioned".into()],
+            context_schemas: BTreeMap::new(),
+            event_schemas: BTreeMap::new(),
+            routes: Vec::new(),
+            contents: PackContents::default(),
+        };
+        state.pack_index.write().entries.push(pack("1.0.0"));
+        let app = build_router(state.clone());
+
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/sessions")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({
+                            "tenant": "dev",
+                            "user": "user-pinned",
+                            "flow_id": "flow-versioned",
+                        }))
+                        .unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let view: SessionView = serde_json::from_slice(&body).unwrap();
+        assert_eq!(view.flow_version.as_deref(), Some("1.0.0"));
+
+        install_pack_index(
+            &state,
+            PackIndex {
+                entries: vec![pack("2.0.0")],
+                invalid: Vec::new(),
+                generation: 0,
+            },
+        );
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/sessions?tenant=dev&needs_upgrade=true")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let listed: SessionListResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(listed.count, 1);
+        assert!(listed.sessions[0].needs_upgrade);
+
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/sessions/resume")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({"tenant": "dev", "user": "user-pinned"}))
+                            .unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let data: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(data["error"], "session_needs_upgrade");
+        assert_eq!(data["to_version"], "2.0.0");
+    }
+
+    #[tokio::test]
+    async fn superseded_generation_serves_its_sessions_until_drained() {
+        let mut state = test_state();
+        state.session_store = InMemorySessionStore::new();
+        let pack = |version: &str| PackEntry {
+            id: "versioned-pack".into(),
+            name: None,
+            kind: None,
+            version: Some(version.into()),
+            status: PackStatus::Active,
+            tags: Vec::new(),
+            assets: Vec::new(),
+            path: Utf8PathBuf::from("packs/versioned-pack"),
+            relative_path: Utf8PathBuf::from("versioned-pack"),
+            flows: vec!["flow-versioned".into()],
+            context_schemas: BTreeMap::new(),
+            event_schemas: BTreeMap::new(),
+            routes: Vec::new(),
+            contents: PackContents::default(),
+        };
+        state.pack_index.write().entries.push(pack("1.0.0"));
+        let app = build_router(state.clone());
+        let post = |uri: &'static str, body: Value| {
+            app.clone().oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(uri)
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                    .unwrap(),
+            )
+        };
+        let park = |body: Value| async {
+            let resp = post("/sessions", body).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+            let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<SessionView>(&body).unwrap()
+        };
+        let generations = || async {
+            let resp = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .uri("/packs/generations")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+            let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<Value>(&body).unwrap()
+        };
+
+        let old =
+            park(json!({"tenant": "dev", "user": "user-old", "flow_id": "flow-versioned"})).await;
+        install_pack_index(
+            &state,
+            PackIndex {
+                entries: vec![pack("2.0.0")],
+                invalid: Vec::new(),
+                generation: 0,
+            },
+        );
+        let report = generations().await;
+        assert_eq!(report["current"], 1);
+        assert_eq!(report["generations"][1]["generation"], 0);
+        assert_eq!(report["generations"][1]["state"], "draining");
+        assert_eq!(report["generations"][1]["sessions"], 1);
+
+        // Re-parking keeps the old generation; new sessions start on the current one.
+        let reparked = park(json!({
+            "key": old.key,
+            "tenant": "dev",
+            "user": "user-old",
+            "flow_id": "flow-versioned",
+        }))
+        .await;
+        assert_eq!(reparked.flow_version.as_deref(), Some("1.0.0"));
+        assert_eq!(reparked.pack_generation, Some(0));
+        let new =
+            park(json!({"tenant": "dev", "user": "user-new", "flow_id": "flow-versioned"})).await;
+        assert_eq!(new.flow_version.as_deref(), Some("2.0.0"));
+
+        let resp = post(
+            "/sessions/resume",
+            json!({"tenant": "dev", "user": "user-old"}),
+        )
+        .await
+        .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let report = generations().await;
+        assert_eq!(report["generations"][0]["sessions"], 1);
+        assert_eq!(report["generations"][1]["generation"], 0);
+        assert_eq!(report["generations"][1]["state"], "released");
+        assert!(report["generations"][1]["released_at_ms"].is_u64());
+        assert!(state.pack_generations.draining_index(0).is_none());
+    }
+
+    /// State over in-memory stores, so tests never write the workspace's `.data/` files.
+    fn test_state() -> AppState {
+        let mut config = AppConfig::default();
+        config.cluster.instance_id = Some("test".into());
+        config.stores.session = StoreConfig::memory();
+        Server::from_config(config)
+            .build_state(
+                &Supervisor::new(SupervisorConfig::default()),
+                PackIndex::default(),
+            )
+            .expect("test state")
+    }
+
+    #[test]
+    fn pack_kind_round_trip() {
+        let json = r#""application""#;
+        let parsed: PackKind = serde_json::from_str(json).expect("parse pack kind");
+        assert!(matches!(parsed, PackKind::Application));
+        let serialized = serde_json::to_string(&parsed).expect("serialize pack kind");
+        assert_eq!(serialized, json);
+    }
+
+    #[test]
+    fn infer_base_plan_from_pack_manifest() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let pack_dir = tmp.path().join("demo-pack");
+        fs::create_dir_all(&pack_dir).expect("create pack dir");
+        let manifest = r#"
+        {
+            "id": "demo",
+            "name": "Demo Pack",
+            "version": "0.1.0",
+            "kind": "application",
+            "type": "events",
+            "scenarios": [
+                { "id": "flow_a" },
+                { "id": "flow_b" }
+            ]
+        }
+        "#;
+        fs::write(pack_dir.join("pack.json"), manifest).expect("write manifest");
+
+        let entry = PackEntry {
+            id: "demo".to_string(),
+            name: Some("Demo Pack".to_string()),
+            kind: Some("application".to_string()),
+            version: Some("0.1.0".to_string()),
+            status: PackStatus::Active,
+            tags: Vec::new(),
+            assets: Vec::new(),
+            path: Utf8PathBuf::from_path_buf(pack_dir.clone()).expect("utf8 path"),
+            relative_path: Utf8PathBuf::from("demo"),
+            flows: vec!["flow_a".into(), "flow_b".into()],
+            context_schemas: BTreeMap::new(),
+            event_schemas: BTreeMap::new(),
+            routes: Vec::new(),
+            contents: PackContents::default(),
+        };
+
+        let plan = infer_base_deployment_plan(
+            &entry,
+            "tenant-1".into(),
+            "staging".into(),
+            &Interpolator::default(),
+        )
+        .expect("infer plan");
+        assert_eq!(plan.pack_id, "demo");
+        assert_eq!(plan.pack_version, Version::parse("0.1.0").unwrap());
+        assert_eq!(plan.tenant, "tenant-1");
+        assert_eq!(plan.environment, "staging");
+        assert_eq!(plan.runners.len(), 1);
+        assert_eq!(plan.runners[0].name, "demo-runner");
+        assert_eq!(plan.channels.len(), 2);
+        assert_eq!(plan.channels[0].flow_id, "flow_a");
+        assert_eq!(plan.channels[0].kind, "events");
+        assert!(plan.messaging.is_some());
+        assert_eq!(plan.messaging.as_ref().unwrap().subjects.len(), 2);
+        assert!(plan.telemetry.as_ref().unwrap().required);
+    }
+
+    #[test]
+    fn plan_resolves_channel_config_placeholders() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        fs::write(
+            tmp.path().join("pack.json"),
+            r#"{
+                "id": "hooks",
+                "version": "0.1.0",
+                "scenarios": [
+                    { "id": "notify", "config": { "url": "${env:HOOKS_TEST_BASE}/notify" } }
+                ]
+            }"#,
+        )
+        .expect("write manifest");
+        let entry = PackEntry {
+            id: "hooks".into(),
+            name: None,
+            kind: None,
+            version: Some("0.1.0".into()),
+            status: PackStatus::Active,
+            tags: Vec::new(),
+            assets: Vec::new(),
+            path: Utf8PathBuf::from_path_buf(tmp.path().to_path_buf()).expect("utf8 path"),
+            relative_path: Utf8PathBuf::from("hooks"),
+            flows: vec!["notify".into()],
+            context_schemas: BTreeMap::new(),
+            event_schemas: BTreeMap::new(),
+            routes: Vec::new(),
+            contents: PackContents::default(),
+        };
+
+        let placeholders = Interpolator::new(
+            BTreeMap::from([("HOOKS_TEST_BASE".to_string(), "https://hooks.dev".into())]),
+            None,
+        );
+        let plan = infer_base_deployment_plan(&entry, "dev".into(), "dev".into(), &placeholders)
+            .expect("infer plan");
+        assert_eq!(plan.channels[0].config["url"], "https://hooks.dev/notify");
+
+        let err = infer_base_deployment_plan(
+            &entry,
+            "dev".into(),
+            "dev".into(),
+            &Interpolator::default(),
+        )
+        .unwrap_err();
+        assert!(format!("{err:#}").contains("$.scenarios[0].config.url"));
+    }
+
+    #[test]
+    fn discovered_pack_plans_match_golden_snapshots() {
+        let index = build_pack_index(&AppConfig::default().packs).expect("pack index");
+        assert!(!index.entries.is_empty());
+        for entry in &index.entries {
+            crate::testkit::assert_plan_matches_golden(
+                workspace_root().join("packs"),
+                workspace_root().join("fixtures/plans"),
+                &entry.id,
+            );
+        }
+    }
+
+    #[test]
+    fn plan_diff_reports_changed_sections_and_cost_delta() {
+        let raw = fs::read_to_string(workspace_root().join("fixtures/plans/demo-menu.json"))
+            .expect("read golden plan");
+        let pricing = PricingTable::default();
+        let mut base: DeploymentPlan = serde_json::from_str(&raw).expect("parse plan");
+        pricing.annotate(&mut base).expect("annotate base");
+        let mut head = base.clone();
+        head.runners[0].replicas = 3;
+
+        let report = diff_plans(&base, &head, &pricing).expect("diff");
+        assert_eq!(report.pack_id, "demo-menu");
+        assert_eq!(report.changed, vec!["runners".to_string()]);
+        assert_eq!(report.cost.delta.replicas, 2);
+        assert!(report.cost.delta.monthly_total > 0.0);
+
+        let unchanged = diff_plans(&base, &base, &pricing).expect("diff");
+        assert!(unchanged.changed.is_empty());
+        assert_eq!(unchanged.cost.delta.monthly_total, 0.0);
+    }
+
+    #[test]
+    fn scenario_suite_selects_by_kind_and_scenario_tags() {
+        let index = build_pack_index(&AppConfig::default().packs).expect("pack index");
+        let ids = |filter: PackFilter| -> Vec<String> {
+            scenario_suite(&index, &filter)
+                .expect("suite")
+                .into_iter()
+                .map(|s| format!("{}/{}", s.pack_id, s.scenario_id))
+                .collect()
+        };
+        assert_eq!(
+            ids(PackFilter::new(Some("Deployment".into()), Vec::new())),
+            vec!["deploy-generic/deploy_plan_written"]
+        );
+        assert_eq!(
+            ids(PackFilter::new(None, vec!["worker".into()])),
+            vec!["integration-demos/repo_assistant_chat"]
+        );
+        assert_eq!(
+            ids(PackFilter::new(
+                Some("application".into()),
+                vec!["smoke".into()]
+            )),
+            vec!["demo-menu/welcome_menu"]
+        );
+    }
+
+    #[cfg(feature = "components")]
+    #[test]
+    fn plan_host_serves_pack_plans_and_records_statuses() {
+        use components::PlanHost;
+
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let pack_dir = tmp.path().join("deploy-pack");
+        fs::create_dir_all(&pack_dir).expect("create pack dir");
+        fs::write(
+            pack_dir.join("pack.json"),
+            r#"{"id": "deploy", "version": "1.2.0", "kind": "deployment", "scenarios": [{"id": "iac"}]}"#,
+        )
+        .expect("write manifest");
+        let entry = PackEntry {
+            id: "deploy".into(),
+            name: None,
+            kind: Some("deployment".into()),
+            version: Some("1.2.0".into()),
+            status: PackStatus::Active,
+            tags: Vec::new(),
+            assets: Vec::new(),
+            path: Utf8PathBuf::from_path_buf(pack_dir.clone()).expect("utf8 path"),
+            relative_path: Utf8PathBuf::from("deploy"),
+            flows: vec!["iac".into()],
+            context_schemas: BTreeMap::new(),
+            event_schemas: BTreeMap::new(),
+            routes: Vec::new(),
+            contents: PackContents::default(),
+        };
+        let runner_events = SharedRunnerEvents::default();
+        let host = AppPlanHost::new(
+            Arc::new(RwLock::new(PackIndex {
+                entries: vec![entry],
+                invalid: Vec::new(),
+                generation: 0,
+            })),
+            runner_events.clone(),
+            "staging".into(),
+            &PackConfig::default(),
+        );
+        let ctx = components::InvokeContext {
+            tenant: "acme".into(),
+            flow_id: "iac".into(),
+            node_id: "write_plan".into(),
+            ..Default::default()
+        };
+
+        let plan: Value = serde_json::from_str(&host.deployment_plan(&ctx).unwrap()).unwrap();
+        assert_eq!(plan["pack_id"], "deploy");
+        assert_eq!(plan["tenant"], "acme");
+        assert_eq!(plan["environment"], "staging");
+
+        let mut stored = plan.clone();
+        stored["runners"][0]["replicas"] = json!(3);
+        fs::create_dir_all(pack_dir.join("plans")).unwrap();
+        fs::write(pack_dir.join("plans/staging.json"), stored.to_string()).unwrap();
+        let plan: Value = serde_json::from_str(&host.deployment_plan(&ctx).unwrap()).unwrap();
+        assert_eq!(plan["runners"][0]["replicas"], 3);
+
+        host.emit_status(&ctx, "deploy-plan-component: done");
+        let events = runner_events.read();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].flow, "iac");
+        assert_eq!(events[0].result["message"], "deploy-plan-component: done");
+    }
+
+    #[cfg(feature = "components")]
+    #[test]
+    fn deploy_plan_component_reads_its_plan_through_the_component_host() {
+        let index = build_pack_index(&AppConfig::default().packs).expect("pack index");
+        let pack_dir = index
+            .entries
+            .iter()
+            .find(|entry| entry.id == "deploy-generic")
+            .expect("deploy-generic pack")
+            .path
+            .clone();
+        let wasm = components::pack_components(pack_dir.as_std_path()).expect("components")
+            ["greentic.deploy.generic.iac"]
+            .clone();
+        let runner_events = SharedRunnerEvents::default();
+        let plans = AppPlanHost::new(
+            Arc::new(RwLock::new(index)),
+            runner_events.clone(),
+            "staging".into(),
+            &PackConfig::default(),
+        );
+        let host = components::ComponentHost::new(state_store::InMemoryStateStore::new())
+            .with_plan_host(plans);
+        let ctx = components::InvokeContext {
+            pack_id: Some("deploy-generic".into()),
+            tenant: "acme".into(),
+            flow_id: "deploy_generic_iac".into(),
+            node_id: "render".into(),
+            ..Default::default()
+        };
+
+        let invocation = host.invoke(&wasm, &ctx, "plan", &Value::Null).unwrap();
+        assert_eq!(invocation.output["pack_id"], "deploy-generic");
+        assert_eq!(invocation.output["tenant"], "acme");
+        assert_eq!(invocation.output["environment"], "staging");
+        let statuses = [
+            "deploy-plan-component: fetching deployment plan",
+            "deploy-plan-component: done",
+        ];
+        assert_eq!(invocation.statuses, statuses);
+        let events = runner_events.read();
+        let recorded: Vec<_> = events
+            .iter()
+            .map(|event| event.result["message"].as_str().unwrap())
+            .collect();
+        assert_eq!(recorded, statuses);
+        assert!(
+            events
+                .iter()
+                .all(|event| event.flow == "deploy_generic_iac")
+        );
+
+        let err = host
+            .invoke(&wasm, &ctx, "deploy", &Value::Null)
+            .unwrap_err();
+        assert!(err.to_string().contains("unsupported_op"), "{err}");
+    }
+
+    #[test]
+    fn session_stream_diffs_successive_listings() {
+        let record = |key: &str, node: &str| SessionRecord {
+            key: key.into(),
+            tenant: "acme".into(),
+            user: Some("user-1".into()),
+            flow_id: Some("chat".into()),
+            node_id: Some(node.into()),
+            ..SessionRecord::default()
+        };
+        let summary = |changes: Vec<SessionChange>| -> Vec<(&'static str, String, bool)> {
+            changes
+                .into_iter()
+                .map(|change| (change.change, change.key, change.session.is_some()))
+                .collect()
+        };
+        let mut seen = HashMap::new();
+        assert_eq!(
+            summary(diff_sessions(
+                &mut seen,
+                vec![record("a", "start"), record("b", "start")]
+            )),
+            vec![("created", "a".into(), true), ("created", "b".into(), true)]
+        );
+        assert!(
+            diff_sessions(&mut seen, vec![record("a", "start"), record("b", "start")]).is_empty()
+        );
+        assert_eq!(
+            summary(diff_sessions(
+                &mut seen,
+                vec![record("a", "reply"), record("c", "start")]
+            )),
+            vec![
+                ("updated", "a".into(), true),
+                ("created", "c".into(), true),
+                ("removed", "b".into(), false)
+            ]
+        );
+    }
+}
+#[derive(Args, Debug, Default)]
+struct ReloadArgs {
+    /// When provided, issue POST {server}/packs/reload instead of local rebuild
+    #[arg(long)]
+    server: Option<String>,
+}
+#[derive(Subcommand, Debug)]
+enum RunnerCommandCli {
+    /// Emit a synthetic activity via the runner proxy
+    Emit(RunnerEmitArgs),
+    /// Fetch runner events from a server
+    Events(RunnerEventsArgs),
+    /// Clear runner events on a server
+    Clear(RunnerClearArgs),
+    /// Apply the server's runner event retention now, optionally with other limits
+    Prune(RunnerPruneArgs),
+    /// Export runner events to a columnar/analytics file
+    Export(RunnerExportArgs),
+    /// Ingest the external runner's structured log as runner events on a server
+    Import(RunnerImportArgs),
+    /// Re-emit recorded runner events against a server, keeping their original pacing
+    Replay(RunnerReplayArgs),
+    /// Replay recorded runner events against two servers and report where they differ
+    Compare(RunnerCompareArgs),
+    /// Show a live table of runner events as a server records them
+    Watch(RunnerWatchArgs),
+}
+
+#[derive(Args, Debug)]
+struct RunnerEmitArgs {
+    #[arg(long)]
+    flow: String,
+    #[arg(long)]
+    tenant: Option<String>,
+    #[arg(long)]
+    team: Option<String>,
+    #[arg(long)]
+    user: Option<String>,
+    #[arg(long, value_name = "JSON")]
+    payload: Option<String>,
+    /// When provided, issues POST {server}/runner/emit instead of local stub
+    #[arg(long)]
+    server: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct RunnerEventsArgs {
+    #[arg(long, value_enum, default_value = "text")]
+    format: ListOutput,
+    #[arg(long, default_value = "http://localhost:8080")]
+    server: String,
+}
+
+/// How `sessions list` and `runner events` print: readable lines, or CSV with flattened
+/// tenant/team/user/flow/node/timestamp columns for spreadsheets.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+enum ListOutput {
+    #[default]
+    Text,
+    Csv,
+}
+
+#[derive(Args, Debug)]
+struct RunnerClearArgs {
+    #[arg(long, default_value = "http://localhost:8080")]
+    server: String,
+}
+
+#[derive(Args, Debug)]
+struct RunnerPruneArgs {
+    /// Remove events older than this instead of `[runner.event_retention].max_age_secs`
+    #[arg(long)]
+    max_age_secs: Option<u64>,
+    /// Keep only the latest events instead of `[runner.event_retention].max_events`
+    #[arg(long)]
+    max_events: Option<usize>,
+    #[arg(long, default_value = "http://localhost:8080")]
+    server: String,
+}
+
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+enum ExportFormat {
+    Parquet,
+    Jsonl,
+}
+
+#[derive(Args, Debug)]
+struct RunnerExportArgs {
+    #[arg(long, value_enum, default_value = "parquet")]
+    format: ExportFormat,
+    /// Output file (e.g. events.parquet)
+    #[arg(long)]
+    out: Utf8PathBuf,
+    /// Read events from a saved `/runner/events` JSON array or JSONL file instead of a server
+    #[arg(long, conflicts_with = "server")]
+    input: Option<Utf8PathBuf>,
+    #[arg(long, default_value = "http://localhost:8080")]
+    server: String,
+}
+#[derive(Args, Debug)]
+struct ReplArgs {
+    #[arg(long, default_value = "http://localhost:8080")]
+    server: String,
+    /// Initial tenant for commands (change with `use tenant <id>`)
+    #[arg(long)]
+    tenant: Option<String>,
+    /// Initial team for commands (change with `use team <id>`)
+    #[arg(long)]
+    team: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct RunnerImportArgs {
+    /// Runner log with one `tracing` JSON record or saved runner event per line
+    #[arg(long)]
+    file: Utf8PathBuf,
+    /// Keep following the file and import lines as the runner appends them
+    #[arg(long)]
+    watch: bool,
+    /// How often `--watch` checks the file for new lines
+    #[arg(long, default_value_t = 500, requires = "watch")]
+    interval_ms: u64,
+    #[arg(long, default_value = "http://localhost:8080")]
+    server: String,
+}
+#[derive(Args, Debug)]
+struct RunnerWatchArgs {
+    #[arg(long)]
+    flow: Option<String>,
+    #[arg(long)]
+    tenant: Option<String>,
+    /// Only show events whose `result.status` is this (e.g. `error`)
+    #[arg(long)]
+    status: Option<String>,
+    /// Events kept in the table
+    #[arg(long, default_value_t = 20)]
+    limit: usize,
+    #[arg(long, default_value = "http://localhost:8080")]
+    server: String,
+}
+
+#[derive(Args, Debug)]
+struct RunnerReplayArgs {
+    /// Recorded events: a saved `/runner/events` JSON array or one event per line
+    #[arg(long)]
+    from: Utf8PathBuf,
+    /// Replay speed relative to the recording (`1x`, `10x`, `0.5x`) or `max`
+    #[arg(long, default_value = "1x")]
+    speed: runner_replay::ReplaySpeed,
+    /// Send a recorded tenant's events to another tenant (`from=to`); repeatable
+    #[arg(long = "remap-tenant", value_name = "FROM=TO")]
+    remap_tenant: Vec<String>,
+    #[arg(long, default_value = "http://localhost:8080")]
+    server: String,
+}
+
+#[derive(Args, Debug)]
+struct RunnerCompareArgs {
+    /// Recorded events: a saved `/runner/events` JSON array or one event per line
+    #[arg(long)]
+    from: Utf8PathBuf,
+    /// Reference bridge, e.g. a build of main
+    #[arg(long)]
+    baseline: String,
+    /// Bridge under review, e.g. a PR build
+    #[arg(long)]
+    candidate: String,
+    /// Send a recorded tenant's events to another tenant (`from=to`); repeatable
+    #[arg(long = "remap-tenant", value_name = "FROM=TO")]
+    remap_tenant: Vec<String>,
+    /// Also ignore this key (anywhere) or JSON pointer (`/result/latency_ms`); repeatable
+    #[arg(long, value_name = "KEY|POINTER")]
+    ignore: Vec<String>,
+    /// Write the full report as JSON
+    #[arg(long)]
+    out: Option<Utf8PathBuf>,
+}
+async fn handle_runner(cmd: RunnerCommandCli, http: &ClientOptions, dry_run: bool) -> Result<()> {
+    match cmd {
+        RunnerCommandCli::Emit(args) => runner_emit_cli(args, http).await?,
+        RunnerCommandCli::Events(args) => runner_events_cli(args, http).await?,
+        RunnerCommandCli::Clear(args) => runner_clear_cli(args, http, dry_run).await?,
+        RunnerCommandCli::Prune(args) => runner_prune_cli(args, http, dry_run).await?,
+        RunnerCommandCli::Export(args) => runner_export_cli(args, http).await?,
+        RunnerCommandCli::Import(args) => runner_import_cli(args, http).await?,
+        RunnerCommandCli::Replay(args) => runner_replay_cli(args, http, dry_run).await?,
+        RunnerCommandCli::Compare(args) => runner_compare_cli(args, http).await?,
+        RunnerCommandCli::Watch(args) => runner_watch_cli(args, http).await?,
+    }
+    Ok(())
+}
+
+fn bridge_client(server: &str, http: &ClientOptions) -> BridgeClient {
+    BridgeClient::with_options(server, http.clone())
+}
+#[derive(Args, Debug, Default)]
+struct SessionListArgs {
+    #[arg(long)]
+    tenant: Option<String>,
+    #[arg(long)]
+    team: Option<String>,
+    #[arg(long)]
+    user: Option<String>,
+    /// Only list sessions pinned to a pack version that has since been reloaded.
+    #[arg(long)]
+    needs_upgrade: bool,
+    /// Return at most this many sessions; the next page's cursor is printed after them.
+    #[arg(long)]
+    limit: Option<usize>,
+    #[arg(long)]
+    offset: Option<usize>,
+    /// Continue after the page that printed this cursor.
+    #[arg(long)]
+    cursor: Option<String>,
+    /// `key` (default), `updated_at` or `-updated_at`.
+    #[arg(long, allow_hyphen_values = true)]
+    sort: Option<String>,
+    #[arg(long, value_enum, default_value = "text")]
+    format: ListOutput,
+    #[arg(long, default_value = "http://localhost:8080")]
+    server: String,
+}
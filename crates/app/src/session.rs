@@ -1,13 +1,28 @@
-use std::{collections::HashMap, fs, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    future::Future,
+    io::Write,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, SystemTime},
+};
 
 use anyhow::{Context, Result, anyhow};
 use camino::Utf8PathBuf;
-use parking_lot::Mutex;
+use futures::StreamExt;
+use parking_lot::{Mutex, MutexGuard};
 use redis::Commands;
+use rusqlite::OptionalExtension;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use tracing::warn;
+use uuid::Uuid;
 
 use crate::path_safety::normalize_under_root;
+use crate::sqlite::SqliteDb;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionUpsert {
@@ -23,6 +38,24 @@ pub struct SessionUpsert {
     pub node_id: Option<String>,
     #[serde(default)]
     pub context: Value,
+    /// Pack owning `flow_id` when the session was written.
+    #[serde(default)]
+    pub pack_id: Option<String>,
+    /// Version of that pack when the session was written.
+    #[serde(default)]
+    pub flow_version: Option<String>,
+    /// Preferred locale (e.g. `de-CH`) for outbound message templates.
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// Lifetime after the last write; expired sessions are hidden and swept.
+    #[serde(default)]
+    pub ttl_ms: Option<u64>,
+    /// Pack index generation the session was written under.
+    #[serde(default)]
+    pub pack_generation: Option<u64>,
+    /// Runner target the session's turns are forwarded to.
+    #[serde(default)]
+    pub runner_target: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -41,6 +74,72 @@ pub struct SessionRecord {
     pub context: Value,
     #[serde(default)]
     pub updated_at_epoch_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at_epoch_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pack_id: Option<String>,
+    /// Pack version the session was pinned to when written.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flow_version: Option<String>,
+    /// Set when a pack reload moved the owning pack to a different version.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub needs_upgrade: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_ms: Option<u64>,
+    /// Pack index generation the session is pinned to while that generation drains.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pack_generation: Option<u64>,
+    /// Runner target (`[[runner.targets]]`) the session's turns are forwarded to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runner_target: Option<String>,
+    /// Nudges sent since the last write while the session sat idle, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nudges: Vec<SessionNudge>,
+}
+
+/// A nudge the stale-session detector emitted for an idle session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionNudge {
+    pub at_epoch_ms: u64,
+    /// How long the session had been idle.
+    pub idle_ms: u64,
+    pub event: String,
+}
+
+impl SessionRecord {
+    fn from_upsert(payload: SessionUpsert) -> Self {
+        Self {
+            key: payload.key,
+            tenant: payload.tenant,
+            team: payload.team,
+            user: payload.user,
+            flow_id: payload.flow_id,
+            node_id: payload.node_id,
+            context: payload.context,
+            updated_at_epoch_ms: current_timestamp_ms(),
+            deleted_at_epoch_ms: None,
+            pack_id: payload.pack_id,
+            flow_version: payload.flow_version,
+            needs_upgrade: false,
+            locale: payload.locale,
+            ttl_ms: payload.ttl_ms,
+            pack_generation: payload.pack_generation,
+            runner_target: payload.runner_target,
+            nudges: Vec::new(),
+        }
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at_epoch_ms.is_some()
+    }
+
+    /// Whether the session's `ttl_ms` has run out since its last write.
+    pub fn is_expired(&self, now_ms: u64) -> bool {
+        self.ttl_ms
+            .is_some_and(|ttl| self.updated_at_epoch_ms.saturating_add(ttl) <= now_ms)
+    }
 }
 
 #[derive(Debug, Default, Clone)]
@@ -48,11 +147,38 @@ pub struct SessionFilter {
     pub tenant: Option<String>,
     pub team: Option<String>,
     pub user: Option<String>,
+    pub needs_upgrade: Option<bool>,
+    /// When set, sessions whose `ttl_ms` ran out by this time do not match.
+    pub live_at_ms: Option<u64>,
+    /// When set, only sessions last written strictly before this time match.
+    pub updated_before_ms: Option<u64>,
 }
 
 impl SessionFilter {
     pub fn new(tenant: Option<String>, team: Option<String>, user: Option<String>) -> Self {
-        Self { tenant, team, user }
+        Self {
+            tenant,
+            team,
+            user,
+            needs_upgrade: None,
+            live_at_ms: None,
+            updated_before_ms: None,
+        }
+    }
+
+    pub fn with_needs_upgrade(mut self, needs_upgrade: Option<bool>) -> Self {
+        self.needs_upgrade = needs_upgrade;
+        self
+    }
+
+    pub fn live_at(mut self, now_ms: u64) -> Self {
+        self.live_at_ms = Some(now_ms);
+        self
+    }
+
+    pub fn updated_before(mut self, cutoff_ms: u64) -> Self {
+        self.updated_before_ms = Some(cutoff_ms);
+        self
     }
 
     pub fn matches(&self, record: &SessionRecord) -> bool {
@@ -67,6 +193,187 @@ impl SessionFilter {
                 .user
                 .as_ref()
                 .is_none_or(|user| record.user.as_deref() == Some(user.as_str()))
+            && self
+                .needs_upgrade
+                .is_none_or(|needs_upgrade| record.needs_upgrade == needs_upgrade)
+            && self.live_at_ms.is_none_or(|now| !record.is_expired(now))
+            && self
+                .updated_before_ms
+                .is_none_or(|cutoff| record.updated_at_epoch_ms < cutoff)
+    }
+}
+
+/// Order of a [`SessionPage`]; ties are broken by key so pages are stable.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SessionSort {
+    #[default]
+    Key,
+    /// Least recently written first.
+    UpdatedAt,
+    /// Most recently written first.
+    UpdatedAtDesc,
+}
+
+impl std::str::FromStr for SessionSort {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "key" => Ok(Self::Key),
+            "updated_at" => Ok(Self::UpdatedAt),
+            "-updated_at" => Ok(Self::UpdatedAtDesc),
+            other => Err(anyhow!(
+                "unknown session sort {other:?} (expected key, updated_at or -updated_at)"
+            )),
+        }
+    }
+}
+
+/// Which slice of the matching sessions [`SessionStore::list_page`] returns. `cursor` is the
+/// `next_cursor` of the previous page; `offset` skips further records after it.
+#[derive(Debug, Default, Clone)]
+pub struct SessionPageRequest {
+    pub sort: SessionSort,
+    pub limit: Option<usize>,
+    pub offset: usize,
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct SessionPage {
+    pub sessions: Vec<SessionRecord>,
+    /// Set when more sessions follow; pass it back as the next request's `cursor`.
+    pub next_cursor: Option<String>,
+}
+
+impl SessionPageRequest {
+    /// Position of `record` in this order, as carried by a cursor.
+    fn position(&self, record: &SessionRecord) -> (u64, String) {
+        let updated = match self.sort {
+            SessionSort::Key => 0,
+            SessionSort::UpdatedAt | SessionSort::UpdatedAtDesc => record.updated_at_epoch_ms,
+        };
+        (updated, record.key.clone())
+    }
+
+    fn encode_cursor(&self, record: &SessionRecord) -> String {
+        let (updated, key) = self.position(record);
+        match self.sort {
+            SessionSort::Key => key,
+            SessionSort::UpdatedAt | SessionSort::UpdatedAtDesc => format!("{updated}:{key}"),
+        }
+    }
+
+    fn decode_cursor(&self) -> Result<Option<(u64, String)>> {
+        let Some(cursor) = &self.cursor else {
+            return Ok(None);
+        };
+        if self.sort == SessionSort::Key {
+            return Ok(Some((0, cursor.clone())));
+        }
+        cursor
+            .split_once(':')
+            .and_then(|(updated, key)| Some((updated.parse().ok()?, key.to_string())))
+            .map(Some)
+            .ok_or_else(|| anyhow!("invalid session cursor {cursor:?}"))
+    }
+
+    /// Whether `position` comes after the cursor in this order.
+    fn after(&self, cursor: &(u64, String), position: &(u64, String)) -> bool {
+        match self.sort {
+            SessionSort::Key | SessionSort::UpdatedAt => position > cursor,
+            SessionSort::UpdatedAtDesc => (position.0, &cursor.1) < (cursor.0, &position.1),
+        }
+    }
+
+    /// Sort `records`, then cut this page out of them.
+    pub fn paginate(&self, mut records: Vec<SessionRecord>) -> Result<SessionPage> {
+        let cursor = self.decode_cursor()?;
+        match self.sort {
+            SessionSort::Key => records.sort_by(|a, b| a.key.cmp(&b.key)),
+            SessionSort::UpdatedAt => records.sort_by(|a, b| {
+                (a.updated_at_epoch_ms, &a.key).cmp(&(b.updated_at_epoch_ms, &b.key))
+            }),
+            SessionSort::UpdatedAtDesc => records.sort_by(|a, b| {
+                (b.updated_at_epoch_ms, &a.key).cmp(&(a.updated_at_epoch_ms, &b.key))
+            }),
+        }
+        let mut remaining = records
+            .into_iter()
+            .filter(|record| {
+                cursor
+                    .as_ref()
+                    .is_none_or(|cursor| self.after(cursor, &self.position(record)))
+            })
+            .skip(self.offset)
+            .peekable();
+        let sessions: Vec<_> = remaining
+            .by_ref()
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect();
+        let next_cursor = match (remaining.peek(), sessions.last()) {
+            (Some(_), Some(last)) => Some(self.encode_cursor(last)),
+            _ => None,
+        };
+        Ok(SessionPage {
+            sessions,
+            next_cursor,
+        })
+    }
+}
+
+/// One stored slot as found in the backend, before any parsing into a `SessionRecord`.
+#[derive(Debug, Clone)]
+pub struct RawSessionEntry {
+    /// Backend slot the entry lives under (hash field for redis, row key for files).
+    pub slot: Option<String>,
+    pub value: std::result::Result<Value, String>,
+}
+
+/// Low-level access used by maintenance tooling (`sessions fsck`) to inspect and rewrite
+/// the backing data without going through the record-level API.
+pub trait RawSessionAccess {
+    fn raw_entries(&self) -> Result<Vec<RawSessionEntry>>;
+    fn replace_all(&self, records: Vec<SessionRecord>) -> Result<()>;
+}
+
+/// Exclusive hold on one session key, released when dropped.
+pub struct SessionLease {
+    release: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl SessionLease {
+    fn new(release: impl FnOnce() + Send + 'static) -> Self {
+        Self {
+            release: Some(Box::new(release)),
+        }
+    }
+}
+
+impl Drop for SessionLease {
+    fn drop(&mut self) {
+        if let Some(release) = self.release.take() {
+            release();
+        }
+    }
+}
+
+/// Session keys currently leased within this process.
+#[derive(Default, Clone)]
+struct KeyLocks {
+    held: Arc<Mutex<HashSet<String>>>,
+}
+
+impl KeyLocks {
+    fn try_lock(&self, key: &str) -> Option<SessionLease> {
+        if !self.held.lock().insert(key.to_string()) {
+            return None;
+        }
+        let held = self.held.clone();
+        let key = key.to_string();
+        Some(SessionLease::new(move || {
+            held.lock().remove(&key);
+        }))
     }
 }
 
@@ -76,18 +383,52 @@ pub trait SessionStore: Send + Sync {
     fn upsert(&self, record: SessionUpsert) -> Result<SessionRecord>;
     fn find(&self, filter: &SessionFilter) -> Result<Option<SessionRecord>>;
     fn remove(&self, key: &str) -> Result<()>;
+    fn get(&self, key: &str) -> Result<Option<SessionRecord>>;
+    /// Store a record verbatim (timestamps and tombstones included).
+    fn put(&self, record: SessionRecord) -> Result<()>;
+
+    /// Undo a soft delete. Stores without soft-delete support have nothing to restore.
+    fn restore(&self, _key: &str) -> Result<Option<SessionRecord>> {
+        Ok(None)
+    }
+
+    /// Permanently drop tombstones whose undo window has elapsed.
+    fn finalize_deletions(&self) -> Result<usize> {
+        Ok(0)
+    }
+
+    /// One page of the records matching `filter`. Stores that can sort and seek natively
+    /// override this; the default sorts the full `list`.
+    fn list_page(&self, filter: &SessionFilter, page: &SessionPageRequest) -> Result<SessionPage> {
+        page.paginate(self.list(filter)?)
+    }
+
+    /// Visit every live record matching `filter` without collecting them. Stores that hold
+    /// records in memory override this to avoid cloning the whole set.
+    fn scan(&self, filter: &SessionFilter, visit: &mut dyn FnMut(&SessionRecord)) -> Result<()> {
+        self.list(filter)?.iter().for_each(visit);
+        Ok(())
+    }
+
+    /// Cheap reachability check of the backing store.
+    fn ping(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Take the lease on `key` unless someone else holds it (`None`). Shared backends hold
+    /// the lease for at most `ttl` so a crashed holder cannot wedge the session.
+    fn try_lock(&self, key: &str, ttl: Duration) -> Result<Option<SessionLease>>;
 }
 
 #[derive(Default)]
 pub struct InMemorySessionStore {
     inner: Mutex<HashMap<String, SessionRecord>>,
+    locks: KeyLocks,
 }
 
 impl InMemorySessionStore {
     pub fn new() -> Arc<Self> {
-        Arc::new(Self {
-            inner: Mutex::new(HashMap::new()),
-        })
+        Arc::new(Self::default())
     }
 }
 
@@ -110,16 +451,7 @@ impl SessionStore for InMemorySessionStore {
 
     fn upsert(&self, payload: SessionUpsert) -> Result<SessionRecord> {
         let mut guard = self.inner.lock();
-        let record = SessionRecord {
-            key: payload.key,
-            tenant: payload.tenant,
-            team: payload.team,
-            user: payload.user,
-            flow_id: payload.flow_id,
-            node_id: payload.node_id,
-            context: payload.context,
-            updated_at_epoch_ms: current_timestamp_ms(),
-        };
+        let record = SessionRecord::from_upsert(payload);
         guard.insert(record.key.clone(), record.clone());
         Ok(record)
     }
@@ -136,15 +468,102 @@ impl SessionStore for InMemorySessionStore {
         self.inner.lock().remove(key);
         Ok(())
     }
+
+    fn get(&self, key: &str) -> Result<Option<SessionRecord>> {
+        Ok(self.inner.lock().get(key).cloned())
+    }
+
+    fn put(&self, record: SessionRecord) -> Result<()> {
+        self.inner.lock().insert(record.key.clone(), record);
+        Ok(())
+    }
+
+    fn scan(&self, filter: &SessionFilter, visit: &mut dyn FnMut(&SessionRecord)) -> Result<()> {
+        self.inner
+            .lock()
+            .values()
+            .filter(|record| filter.matches(record))
+            .for_each(visit);
+        Ok(())
+    }
+
+    fn try_lock(&self, key: &str, _ttl: Duration) -> Result<Option<SessionLease>> {
+        Ok(self.locks.try_lock(key))
+    }
 }
 
+/// Sessions in one file: a JSON array snapshot at `path` plus an append-only journal at
+/// `<path>.journal` (one `{"op": "put" | "remove", ...}` line per change). Loading replays the
+/// journal over the snapshot; once the journal passes the [`CompactionThresholds`] it is
+/// folded into a fresh snapshot. Snapshots are written to a temp file that is fsynced and
+/// renamed, journal appends are fsynced, and every write holds an exclusive lock on
+/// `<path>.lock`, so crashes never lose acknowledged writes and processes sharing the path
+/// apply their changes on top of each other's.
 pub struct FileSessionStore {
     path: Utf8PathBuf,
-    inner: Mutex<HashMap<String, SessionRecord>>,
+    journal_path: Utf8PathBuf,
+    lock_path: Utf8PathBuf,
+    thresholds: CompactionThresholds,
+    inner: Mutex<FileSessions>,
+    locks: KeyLocks,
+}
+
+/// When the file store folds its journal into the snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompactionThresholds {
+    pub max_entries: usize,
+    pub max_bytes: u64,
+}
+
+impl Default for CompactionThresholds {
+    fn default() -> Self {
+        Self {
+            max_entries: 1000,
+            max_bytes: 4 * 1024 * 1024,
+        }
+    }
+}
+
+/// What [`FileSessionStore::compact`] folded into the snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Compaction {
+    pub journal_entries: usize,
+    pub journal_bytes: u64,
+    pub sessions: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum JournalEntry {
+    Put { record: Box<SessionRecord> },
+    Remove { key: String },
+}
+
+type FileStamp = Option<(SystemTime, u64)>;
+
+/// Cached records plus where the files they were read from stood.
+#[derive(Default)]
+struct FileSessions {
+    records: HashMap<String, SessionRecord>,
+    stamp: (FileStamp, FileStamp),
+    /// Complete journal lines and their length; a torn last line is not counted.
+    journal_entries: usize,
+    journal_bytes: u64,
+}
+
+/// Snapshot rows with the journal replayed over them, before parsing.
+struct RawSessions {
+    rows: Vec<Value>,
+    journal_entries: usize,
+    journal_bytes: u64,
 }
 
 impl FileSessionStore {
-    pub fn new(root: Utf8PathBuf, path: Utf8PathBuf) -> Result<Arc<Self>> {
+    pub fn new(
+        root: Utf8PathBuf,
+        path: Utf8PathBuf,
+        thresholds: CompactionThresholds,
+    ) -> Result<Arc<Self>> {
         let root = root
             .as_std_path()
             .canonicalize()
@@ -153,49 +572,257 @@ impl FileSessionStore {
         let safe_path = Utf8PathBuf::from_path_buf(safe_path)
             .map_err(|_| anyhow!("normalized session path is not valid UTF-8"))?;
 
-        let data = Self::load_from_disk(&safe_path).unwrap_or_default();
-        Ok(Arc::new(Self {
+        let store = Self {
+            journal_path: Utf8PathBuf::from(format!("{safe_path}.journal")),
+            lock_path: Utf8PathBuf::from(format!("{safe_path}.lock")),
             path: safe_path,
-            inner: Mutex::new(data),
-        }))
+            thresholds,
+            inner: Mutex::default(),
+            locks: KeyLocks::default(),
+        };
+        if !store.path.exists() {
+            let _lock = store.lock_file()?;
+            write_atomically(&store.path, b"[]")
+                .with_context(|| format!("failed to create session store {}", store.path))?;
+        }
+        match store.load() {
+            Ok(sessions) => *store.inner.lock() = sessions,
+            Err(err) => warn!(?err, path = %store.path, "starting with an empty session store"),
+        }
+        Ok(Arc::new(store))
+    }
+
+    fn stamp(&self) -> (FileStamp, FileStamp) {
+        let stamp = |path: &Utf8PathBuf| {
+            let meta = fs::metadata(path).ok()?;
+            Some((meta.modified().ok()?, meta.len()))
+        };
+        (stamp(&self.path), stamp(&self.journal_path))
+    }
+
+    fn load_raw(&self) -> Result<RawSessions> {
+        let raw = read_if_exists(&self.path)
+            .with_context(|| format!("failed to read session store {}", self.path))?;
+        let mut rows: Vec<Value> = if raw.trim().is_empty() {
+            Vec::new()
+        } else {
+            serde_json::from_str(&raw)
+                .with_context(|| format!("session store {} is not a JSON array", self.path))?
+        };
+        let journal = read_if_exists(&self.journal_path)
+            .with_context(|| format!("failed to read session journal {}", self.journal_path))?;
+        // A crash mid-append leaves a last line without its newline; it was never
+        // acknowledged, so it is dropped and overwritten by the next append.
+        let complete = journal.rfind('\n').map_or(0, |end| end + 1);
+        let mut journal_entries = 0;
+        for (idx, line) in journal[..complete].lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: Value = serde_json::from_str(line).with_context(|| {
+                format!("invalid entry on line {} of {}", idx + 1, self.journal_path)
+            })?;
+            let key = match entry["op"].as_str() {
+                Some("put") => entry["record"]["key"].as_str(),
+                Some("remove") => entry["key"].as_str(),
+                _ => None,
+            }
+            .ok_or_else(|| anyhow!("invalid entry on line {} of {}", idx + 1, self.journal_path))?
+            .to_string();
+            let position = rows
+                .iter()
+                .position(|row| row.get("key").and_then(Value::as_str) == Some(&key));
+            match (entry["op"].as_str(), position) {
+                (Some("put"), Some(position)) => rows[position] = entry["record"].clone(),
+                (Some("put"), None) => rows.push(entry["record"].clone()),
+                (_, Some(position)) => {
+                    rows.remove(position);
+                }
+                (_, None) => {}
+            }
+            journal_entries += 1;
+        }
+        Ok(RawSessions {
+            rows,
+            journal_entries,
+            journal_bytes: complete as u64,
+        })
+    }
+
+    fn load(&self) -> Result<FileSessions> {
+        let stamp = self.stamp();
+        let raw = self.load_raw()?;
+        let records = raw
+            .rows
+            .into_iter()
+            .map(|row| {
+                let record: SessionRecord = serde_json::from_value(row)
+                    .with_context(|| format!("invalid session in {}", self.path))?;
+                Ok((record.key.clone(), record))
+            })
+            .collect::<Result<_>>()?;
+        Ok(FileSessions {
+            records,
+            stamp,
+            journal_entries: raw.journal_entries,
+            journal_bytes: raw.journal_bytes,
+        })
     }
 
-    fn load_from_disk(path: &Utf8PathBuf) -> Result<HashMap<String, SessionRecord>> {
-        if !path.exists() {
-            if let Some(parent) = path.parent() {
-                fs::create_dir_all(parent)?;
+    /// The cached records, re-read first when another process changed the files.
+    fn current(&self) -> MutexGuard<'_, FileSessions> {
+        let mut guard = self.inner.lock();
+        if guard.stamp != self.stamp() {
+            match self.load() {
+                Ok(sessions) => *guard = sessions,
+                Err(err) => warn!(?err, path = %self.path, "keeping cached sessions"),
             }
-            fs::write(path, "[]")?;
-            return Ok(HashMap::new());
         }
+        guard
+    }
+
+    /// Apply `change` to the latest records while holding the file lock and journal the
+    /// entries it returns, compacting when the journal outgrew the thresholds.
+    fn mutate<T>(
+        &self,
+        change: impl FnOnce(&mut HashMap<String, SessionRecord>) -> (T, Vec<JournalEntry>),
+    ) -> Result<T> {
+        let mut guard = self.inner.lock();
+        let _lock = self.lock_file()?;
+        if guard.stamp != self.stamp() {
+            *guard = self.load()?;
+        }
+        let (result, entries) = change(&mut guard.records);
+        if entries.is_empty() {
+            return Ok(result);
+        }
+        self.append(&mut guard, &entries)?;
+        if guard.journal_entries >= self.thresholds.max_entries
+            || guard.journal_bytes >= self.thresholds.max_bytes
+        {
+            self.fold(&mut guard)?;
+        }
+        guard.stamp = self.stamp();
+        Ok(result)
+    }
 
-        let raw = fs::read_to_string(path)
-            .with_context(|| format!("failed to read session store {path}"))?;
-        if raw.trim().is_empty() {
-            return Ok(HashMap::new());
+    fn append(&self, sessions: &mut FileSessions, entries: &[JournalEntry]) -> Result<()> {
+        let mut lines = String::new();
+        for entry in entries {
+            lines.push_str(&serde_json::to_string(entry)?);
+            lines.push('\n');
+        }
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.journal_path)
+            .with_context(|| format!("failed to open {}", self.journal_path))?;
+        if file.metadata()?.len() > sessions.journal_bytes {
+            file.set_len(sessions.journal_bytes)?;
         }
+        file.write_all(lines.as_bytes())
+            .and_then(|()| file.sync_data())
+            .with_context(|| format!("failed to append to {}", self.journal_path))?;
+        sessions.journal_entries += entries.len();
+        sessions.journal_bytes += lines.len() as u64;
+        Ok(())
+    }
 
-        let rows: Vec<SessionRecord> =
-            serde_json::from_str(&raw).with_context(|| format!("invalid JSON in {path}"))?;
-        Ok(rows.into_iter().map(|row| (row.key.clone(), row)).collect())
+    /// Write the records as the new snapshot, then drop the journal. A crash in between only
+    /// replays entries the snapshot already reflects.
+    fn fold(&self, sessions: &mut FileSessions) -> Result<Compaction> {
+        let compaction = Compaction {
+            journal_entries: sessions.journal_entries,
+            journal_bytes: sessions.journal_bytes,
+            sessions: sessions.records.len(),
+        };
+        self.persist(&sessions.records)?;
+        match fs::remove_file(&self.journal_path) {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed to remove {}", self.journal_path));
+            }
+        }
+        sessions.journal_entries = 0;
+        sessions.journal_bytes = 0;
+        Ok(compaction)
     }
 
-    fn persist(&self, guard: &HashMap<String, SessionRecord>) -> Result<()> {
-        let rows: Vec<_> = guard.values().cloned().collect();
-        let json = serde_json::to_string_pretty(&rows)?;
+    /// Fold the journal into the snapshot now (`sessions compact`).
+    pub fn compact(&self) -> Result<Compaction> {
+        let mut guard = self.inner.lock();
+        let _lock = self.lock_file()?;
+        *guard = self.load()?;
+        let compaction = self.fold(&mut guard)?;
+        guard.stamp = self.stamp();
+        Ok(compaction)
+    }
+
+    /// Exclusive advisory lock on `<path>.lock`, released when the handle drops.
+    fn lock_file(&self) -> Result<fs::File> {
         if let Some(parent) = self.path.parent() {
             fs::create_dir_all(parent)?;
         }
-        fs::write(&self.path, json)
-            .with_context(|| format!("failed to write session store {}", self.path))?;
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&self.lock_path)
+            .with_context(|| format!("failed to open {}", self.lock_path))?;
+        file.lock()
+            .with_context(|| format!("failed to lock {}", self.lock_path))?;
+        Ok(file)
+    }
+
+    fn persist(&self, records: &HashMap<String, SessionRecord>) -> Result<()> {
+        let rows: Vec<_> = records.values().collect();
+        let json = serde_json::to_string_pretty(&rows)?;
+        write_atomically(&self.path, json.as_bytes())
+            .with_context(|| format!("failed to write session store {}", self.path))
+    }
+}
+
+fn read_if_exists(path: &Utf8PathBuf) -> std::io::Result<String> {
+    match fs::read_to_string(path) {
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+        other => other,
+    }
+}
+
+/// Write `bytes` to a sibling temp file, fsync it, rename it over `path` and fsync the
+/// directory, so readers and crashes only ever see the old or the new contents.
+fn write_atomically(path: &Utf8PathBuf, bytes: &[u8]) -> Result<()> {
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_str().is_empty())
+        .unwrap_or(camino::Utf8Path::new("."));
+    fs::create_dir_all(dir)?;
+    let tmp = dir.join(format!(
+        ".{}.{}.tmp",
+        path.file_name().unwrap_or("sessions"),
+        std::process::id()
+    ));
+    let result = (|| {
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)?;
+        #[cfg(unix)]
+        fs::File::open(dir)?.sync_all()?;
         Ok(())
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
     }
+    result
 }
 
 impl SessionStore for FileSessionStore {
     fn list(&self, filter: &SessionFilter) -> Result<Vec<SessionRecord>> {
-        let guard = self.inner.lock();
-        Ok(guard
+        Ok(self
+            .current()
+            .records
             .values()
             .filter(|record| filter.matches(record))
             .cloned()
@@ -203,53 +830,125 @@ impl SessionStore for FileSessionStore {
     }
 
     fn purge(&self, filter: &SessionFilter) -> Result<usize> {
-        let mut guard = self.inner.lock();
-        let before = guard.len();
-        guard.retain(|_, record| !filter.matches(record));
-        let removed = before - guard.len();
-        if removed > 0 {
-            self.persist(&guard)?;
-        }
-        Ok(removed)
+        self.mutate(|records| {
+            let removed: Vec<String> = records
+                .values()
+                .filter(|record| filter.matches(record))
+                .map(|record| record.key.clone())
+                .collect();
+            for key in &removed {
+                records.remove(key);
+            }
+            (
+                removed.len(),
+                removed
+                    .into_iter()
+                    .map(|key| JournalEntry::Remove { key })
+                    .collect(),
+            )
+        })
     }
 
     fn upsert(&self, payload: SessionUpsert) -> Result<SessionRecord> {
-        let mut guard = self.inner.lock();
-        let record = SessionRecord {
-            key: payload.key,
-            tenant: payload.tenant,
-            team: payload.team,
-            user: payload.user,
-            flow_id: payload.flow_id,
-            node_id: payload.node_id,
-            context: payload.context,
-            updated_at_epoch_ms: current_timestamp_ms(),
-        };
-        guard.insert(record.key.clone(), record.clone());
-        self.persist(&guard)?;
+        let record = SessionRecord::from_upsert(payload);
+        self.put(record.clone())?;
         Ok(record)
     }
 
     fn find(&self, filter: &SessionFilter) -> Result<Option<SessionRecord>> {
         Ok(self
-            .inner
-            .lock()
+            .current()
+            .records
             .values()
             .find(|record| filter.matches(record))
             .cloned())
     }
 
     fn remove(&self, key: &str) -> Result<()> {
+        self.mutate(|records| match records.remove(key) {
+            Some(_) => (
+                (),
+                vec![JournalEntry::Remove {
+                    key: key.to_string(),
+                }],
+            ),
+            None => ((), Vec::new()),
+        })
+    }
+
+    fn get(&self, key: &str) -> Result<Option<SessionRecord>> {
+        Ok(self.current().records.get(key).cloned())
+    }
+
+    fn put(&self, record: SessionRecord) -> Result<()> {
+        self.mutate(|records| {
+            records.insert(record.key.clone(), record.clone());
+            (
+                (),
+                vec![JournalEntry::Put {
+                    record: Box::new(record),
+                }],
+            )
+        })
+    }
+
+    fn scan(&self, filter: &SessionFilter, visit: &mut dyn FnMut(&SessionRecord)) -> Result<()> {
+        self.current()
+            .records
+            .values()
+            .filter(|record| filter.matches(record))
+            .for_each(visit);
+        Ok(())
+    }
+
+    fn ping(&self) -> Result<()> {
+        let _guard = self.inner.lock();
+        fs::metadata(&self.path)
+            .with_context(|| format!("session store {} is not accessible", self.path))?;
+        Ok(())
+    }
+
+    fn try_lock(&self, key: &str, _ttl: Duration) -> Result<Option<SessionLease>> {
+        Ok(self.locks.try_lock(key))
+    }
+}
+
+impl RawSessionAccess for FileSessionStore {
+    fn raw_entries(&self) -> Result<Vec<RawSessionEntry>> {
+        let _guard = self.inner.lock();
+        Ok(self
+            .load_raw()?
+            .rows
+            .into_iter()
+            .map(|row| RawSessionEntry {
+                slot: row.get("key").and_then(Value::as_str).map(str::to_owned),
+                value: Ok(row),
+            })
+            .collect())
+    }
+
+    fn replace_all(&self, records: Vec<SessionRecord>) -> Result<()> {
         let mut guard = self.inner.lock();
-        guard.remove(key);
-        self.persist(&guard)?;
+        let _lock = self.lock_file()?;
+        guard.records = records
+            .into_iter()
+            .map(|record| (record.key.clone(), record))
+            .collect();
+        self.fold(&mut guard)?;
+        guard.stamp = self.stamp();
         Ok(())
     }
 }
 
+/// Sessions in Redis under `redis_prefix` (default `greentic:sessions`): one hash per tenant
+/// (`<prefix>:tenant:<tenant>`, session key → record JSON) plus `<prefix>:index` mapping every
+/// session key to its tenant, so tenant-scoped reads never touch other tenants' data and
+/// several bridges can share one Redis with distinct prefixes.
 pub struct RedisSessionStore {
     client: redis::Client,
     bucket: String,
+    /// Set once sessions left in the pre-tenant single hash (`<prefix>`) were moved.
+    migrated: AtomicBool,
 }
 
 impl RedisSessionStore {
@@ -257,7 +956,19 @@ impl RedisSessionStore {
         let client = redis::Client::open(url.to_string())
             .with_context(|| format!("failed to create redis client for {url}"))?;
         let bucket = prefix.unwrap_or_else(|| "greentic:sessions".to_string());
-        Ok(Arc::new(Self { client, bucket }))
+        Ok(Arc::new(Self {
+            client,
+            bucket,
+            migrated: AtomicBool::new(false),
+        }))
+    }
+
+    fn tenant_hash(&self, tenant: &str) -> String {
+        format!("{}:tenant:{tenant}", self.bucket)
+    }
+
+    fn index(&self) -> String {
+        format!("{}:index", self.bucket)
     }
 
     fn with_conn<T>(&self, f: impl FnOnce(&mut redis::Connection) -> Result<T>) -> Result<T> {
@@ -267,18 +978,78 @@ impl RedisSessionStore {
                 self.client.get_connection_info()
             )
         })?;
+        if !self.migrated.load(Ordering::Acquire) {
+            self.migrate_legacy_hash(&mut conn)?;
+            self.migrated.store(true, Ordering::Release);
+        }
         f(&mut conn)
     }
 
-    fn load_all(&self) -> Result<HashMap<String, SessionRecord>> {
+    /// Move records from the single `<prefix>` hash older builds wrote into their tenant
+    /// hashes. Entries that do not parse stay behind for `sessions fsck` to report.
+    fn migrate_legacy_hash(&self, conn: &mut redis::Connection) -> Result<()> {
+        let kind: String = redis::cmd("TYPE")
+            .arg(&self.bucket)
+            .query(conn)
+            .with_context(|| format!("failed to inspect {}", self.bucket))?;
+        if kind != "hash" {
+            return Ok(());
+        }
+        let legacy: HashMap<String, String> = conn
+            .hgetall(&self.bucket)
+            .with_context(|| format!("failed to fetch sessions hash {}", self.bucket))?;
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        let mut moved = 0;
+        for (key, json) in &legacy {
+            let Ok(record) = serde_json::from_str::<SessionRecord>(json) else {
+                continue;
+            };
+            pipe.hset(self.tenant_hash(&record.tenant), key, json)
+                .ignore()
+                .hset(self.index(), key, &record.tenant)
+                .ignore()
+                .hdel(&self.bucket, key)
+                .ignore();
+            moved += 1;
+        }
+        let _: () = pipe
+            .query(conn)
+            .with_context(|| format!("failed to migrate sessions hash {}", self.bucket))?;
+        if moved > 0 {
+            warn!(
+                moved,
+                bucket = %self.bucket,
+                "moved sessions from the shared redis hash into per-tenant hashes"
+            );
+        }
+        Ok(())
+    }
+
+    /// Tenants holding sessions, or just `tenant` when the caller is scoped to one.
+    fn tenants(&self, conn: &mut redis::Connection, tenant: Option<&str>) -> Result<Vec<String>> {
+        if let Some(tenant) = tenant {
+            return Ok(vec![tenant.to_string()]);
+        }
+        let tenants: Vec<String> = conn
+            .hvals(self.index())
+            .with_context(|| format!("failed to read {}", self.index()))?;
+        let unique: HashSet<String> = tenants.into_iter().collect();
+        Ok(unique.into_iter().collect())
+    }
+
+    fn load(&self, tenant: Option<&str>) -> Result<HashMap<String, SessionRecord>> {
         self.with_conn(|conn| {
-            let raw: HashMap<String, String> = conn
-                .hgetall(&self.bucket)
-                .with_context(|| format!("failed to fetch sessions hash {}", self.bucket))?;
             let mut out = HashMap::new();
-            for (key, json) in raw {
-                if let Ok(record) = serde_json::from_str::<SessionRecord>(&json) {
-                    out.insert(key, record);
+            for tenant in self.tenants(conn, tenant)? {
+                let hash = self.tenant_hash(&tenant);
+                let raw: HashMap<String, String> = conn
+                    .hgetall(&hash)
+                    .with_context(|| format!("failed to fetch sessions hash {hash}"))?;
+                for (key, json) in raw {
+                    if let Ok(record) = serde_json::from_str::<SessionRecord>(&json) {
+                        out.insert(key, record);
+                    }
                 }
             }
             Ok(out)
@@ -288,18 +1059,41 @@ impl RedisSessionStore {
     fn persist(&self, record: &SessionRecord) -> Result<()> {
         self.with_conn(|conn| {
             let json = serde_json::to_string(record)?;
-            let _: () = conn
-                .hset(&self.bucket, &record.key, json)
-                .with_context(|| format!("failed to hset {}", self.bucket))?;
+            let previous: Option<String> = conn
+                .hget(self.index(), &record.key)
+                .with_context(|| format!("failed to hget {}", self.index()))?;
+            let mut pipe = redis::pipe();
+            pipe.atomic();
+            // A key re-used by another tenant leaves the old tenant's hash.
+            if let Some(previous) = previous.filter(|previous| *previous != record.tenant) {
+                pipe.hdel(self.tenant_hash(&previous), &record.key).ignore();
+            }
+            let hash = self.tenant_hash(&record.tenant);
+            pipe.hset(&hash, &record.key, json)
+                .ignore()
+                .hset(self.index(), &record.key, &record.tenant)
+                .ignore();
+            let _: () = pipe
+                .query(conn)
+                .with_context(|| format!("failed to hset {hash}"))?;
             Ok(())
         })
     }
 
     fn delete(&self, key: &str) -> Result<()> {
         self.with_conn(|conn| {
-            let _: () = conn
-                .hdel(&self.bucket, key)
-                .with_context(|| format!("failed to hdel {} {}", self.bucket, key))?;
+            let tenant: Option<String> = conn
+                .hget(self.index(), key)
+                .with_context(|| format!("failed to hget {} {key}", self.index()))?;
+            let mut pipe = redis::pipe();
+            pipe.atomic();
+            if let Some(tenant) = tenant {
+                pipe.hdel(self.tenant_hash(&tenant), key).ignore();
+            }
+            pipe.hdel(self.index(), key).ignore();
+            let _: () = pipe
+                .query(conn)
+                .with_context(|| format!("failed to hdel {} {key}", self.bucket))?;
             Ok(())
         })
     }
@@ -307,7 +1101,7 @@ impl RedisSessionStore {
 
 impl SessionStore for RedisSessionStore {
     fn list(&self, filter: &SessionFilter) -> Result<Vec<SessionRecord>> {
-        let all = self.load_all()?;
+        let all = self.load(filter.tenant.as_deref())?;
         Ok(all
             .values()
             .filter(|record| filter.matches(record))
@@ -316,7 +1110,7 @@ impl SessionStore for RedisSessionStore {
     }
 
     fn purge(&self, filter: &SessionFilter) -> Result<usize> {
-        let all = self.load_all()?;
+        let all = self.load(filter.tenant.as_deref())?;
         let mut removed = 0;
         for (key, record) in all {
             if filter.matches(&record) {
@@ -328,31 +1122,948 @@ impl SessionStore for RedisSessionStore {
     }
 
     fn upsert(&self, payload: SessionUpsert) -> Result<SessionRecord> {
-        let record = SessionRecord {
-            key: payload.key,
-            tenant: payload.tenant,
-            team: payload.team,
-            user: payload.user,
-            flow_id: payload.flow_id,
-            node_id: payload.node_id,
-            context: payload.context,
-            updated_at_epoch_ms: current_timestamp_ms(),
-        };
+        let record = SessionRecord::from_upsert(payload);
         self.persist(&record)?;
         Ok(record)
     }
 
     fn find(&self, filter: &SessionFilter) -> Result<Option<SessionRecord>> {
-        let all = self.load_all()?;
+        let all = self.load(filter.tenant.as_deref())?;
         Ok(all.values().find(|r| filter.matches(r)).cloned())
     }
 
     fn remove(&self, key: &str) -> Result<()> {
         self.delete(key)
     }
+
+    fn get(&self, key: &str) -> Result<Option<SessionRecord>> {
+        self.with_conn(|conn| {
+            let tenant: Option<String> = conn
+                .hget(self.index(), key)
+                .with_context(|| format!("failed to hget {} {key}", self.index()))?;
+            let Some(tenant) = tenant else {
+                return Ok(None);
+            };
+            let hash = self.tenant_hash(&tenant);
+            let raw: Option<String> = conn
+                .hget(&hash, key)
+                .with_context(|| format!("failed to hget {hash} {key}"))?;
+            raw.map(|json| {
+                serde_json::from_str(&json)
+                    .with_context(|| format!("invalid session JSON for {key}"))
+            })
+            .transpose()
+        })
+    }
+
+    fn put(&self, record: SessionRecord) -> Result<()> {
+        self.persist(&record)
+    }
+
+    fn scan(&self, filter: &SessionFilter, visit: &mut dyn FnMut(&SessionRecord)) -> Result<()> {
+        self.load(filter.tenant.as_deref())?
+            .values()
+            .filter(|record| filter.matches(record))
+            .for_each(visit);
+        Ok(())
+    }
+
+    fn ping(&self) -> Result<()> {
+        self.with_conn(|conn| {
+            redis::cmd("PING")
+                .query::<String>(conn)
+                .with_context(|| format!("redis PING failed for {}", self.bucket))?;
+            Ok(())
+        })
+    }
+
+    /// `SET NX PX` on `<bucket>:lock:<key>` with a random token; release deletes the lock
+    /// only while it still carries that token, so an expired lease never frees a successor's.
+    fn try_lock(&self, key: &str, ttl: Duration) -> Result<Option<SessionLease>> {
+        let lock_key = format!("{}:lock:{key}", self.bucket);
+        let token = Uuid::new_v4().to_string();
+        let acquired: Option<String> = self.with_conn(|conn| {
+            redis::cmd("SET")
+                .arg(&lock_key)
+                .arg(&token)
+                .arg("NX")
+                .arg("PX")
+                .arg(ttl.as_millis().max(1) as u64)
+                .query(conn)
+                .with_context(|| format!("failed to acquire {lock_key}"))
+        })?;
+        if acquired.is_none() {
+            return Ok(None);
+        }
+        let client = self.client.clone();
+        Ok(Some(SessionLease::new(move || {
+            let released = client.get_connection().and_then(|mut conn| {
+                redis::Script::new(RELEASE_LOCK_SCRIPT)
+                    .key(&lock_key)
+                    .arg(&token)
+                    .invoke::<i64>(&mut conn)
+            });
+            if let Err(err) = released {
+                warn!(?err, %lock_key, "failed to release session lock");
+            }
+        })))
+    }
+}
+
+const RELEASE_LOCK_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("DEL", KEYS[1])
+end
+return 0
+"#;
+
+impl RawSessionAccess for RedisSessionStore {
+    /// Entries of every tenant hash, plus any the legacy single hash still holds.
+    fn raw_entries(&self) -> Result<Vec<RawSessionEntry>> {
+        self.with_conn(|conn| {
+            let mut hashes: Vec<String> = self
+                .tenants(conn, None)?
+                .iter()
+                .map(|tenant| self.tenant_hash(tenant))
+                .collect();
+            hashes.push(self.bucket.clone());
+            let mut entries = Vec::new();
+            for hash in hashes {
+                let raw: HashMap<Vec<u8>, Vec<u8>> = conn
+                    .hgetall(&hash)
+                    .with_context(|| format!("failed to fetch sessions hash {hash}"))?;
+                entries.extend(raw.into_iter().map(|(field, json)| {
+                    RawSessionEntry {
+                        slot: Some(String::from_utf8_lossy(&field).into_owned()),
+                        value: String::from_utf8(json)
+                            .map_err(|err| err.to_string())
+                            .and_then(|text| {
+                                serde_json::from_str(&text).map_err(|err| err.to_string())
+                            }),
+                    }
+                }));
+            }
+            Ok(entries)
+        })
+    }
+
+    fn replace_all(&self, records: Vec<SessionRecord>) -> Result<()> {
+        self.with_conn(|conn| {
+            let mut pipe = redis::pipe();
+            pipe.atomic()
+                .del(&self.bucket)
+                .ignore()
+                .del(self.index())
+                .ignore();
+            for tenant in self.tenants(conn, None)? {
+                pipe.del(self.tenant_hash(&tenant)).ignore();
+            }
+            for record in &records {
+                pipe.hset(
+                    self.tenant_hash(&record.tenant),
+                    &record.key,
+                    serde_json::to_string(record)?,
+                )
+                .ignore()
+                .hset(self.index(), &record.key, &record.tenant)
+                .ignore();
+            }
+            let _: () = pipe
+                .query(conn)
+                .with_context(|| format!("failed to rewrite sessions under {}", self.bucket))?;
+            Ok(())
+        })
+    }
 }
 
-fn current_timestamp_ms() -> u64 {
+/// Sessions in a JetStream key-value bucket, shared by every bridge instance on the same NATS
+/// cluster. A record lives under `t.<tenant>.<key>`, so a tenant's sessions share a key
+/// prefix, and `i.<key>` names the tenant currently holding `key`. Tokens outside the KV key
+/// alphabet are escaped as `=XX`.
+pub struct NatsKvSessionStore {
+    runtime: NatsRuntime,
+    kv: async_nats::jetstream::kv::Store,
+    bucket: String,
+}
+
+/// The NATS client is async only; it runs on a runtime thread of its own so the synchronous
+/// store API can wait for results from inside or outside another runtime. The thread stops
+/// when the store is dropped.
+struct NatsRuntime {
+    handle: tokio::runtime::Handle,
+    _stop: tokio::sync::oneshot::Sender<()>,
+}
+
+/// Upper bound on any single KV round trip.
+const NATS_KV_TIMEOUT: Duration = Duration::from_secs(10);
+
+impl NatsRuntime {
+    fn start() -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .context("failed to build the NATS session store runtime")?;
+        let handle = runtime.handle().clone();
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        std::thread::Builder::new()
+            .name("nats-kv-sessions".into())
+            .spawn(move || {
+                let _ = runtime.block_on(stopped);
+            })
+            .context("failed to start the NATS session store thread")?;
+        Ok(Self {
+            handle,
+            _stop: stop,
+        })
+    }
+
+    fn run<T: Send + 'static>(
+        &self,
+        what: &str,
+        fut: impl Future<Output = Result<T>> + Send + 'static,
+    ) -> Result<T> {
+        let (done, result) = std::sync::mpsc::sync_channel(1);
+        self.handle.spawn(async move {
+            let _ = done.send(tokio::time::timeout(NATS_KV_TIMEOUT, fut).await);
+        });
+        match result.recv() {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(anyhow!("{what} timed out after {NATS_KV_TIMEOUT:?}")),
+            Err(_) => Err(anyhow!("{what} failed: the NATS runtime stopped")),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct NatsKvLease {
+    token: String,
+    expires_at_ms: u64,
+}
+
+impl NatsKvSessionStore {
+    /// Connect to `url` and open (or create) `bucket`, default `greentic-sessions`.
+    pub fn new(url: &str, bucket: Option<String>) -> Result<Arc<Self>> {
+        let runtime = NatsRuntime::start()?;
+        let bucket = bucket.unwrap_or_else(|| "greentic-sessions".to_string());
+        let kv = runtime.run("opening the NATS session bucket", {
+            let (url, bucket) = (url.to_string(), bucket.clone());
+            async move {
+                let client = async_nats::connect(&url)
+                    .await
+                    .with_context(|| format!("failed to connect to NATS at {url}"))?;
+                let jetstream = async_nats::jetstream::new(client);
+                match jetstream.get_key_value(&bucket).await {
+                    Ok(kv) => Ok(kv),
+                    Err(_) => jetstream
+                        .create_key_value(async_nats::jetstream::kv::Config {
+                            bucket: bucket.clone(),
+                            description: "Greentic integration sessions".into(),
+                            history: 1,
+                            ..Default::default()
+                        })
+                        .await
+                        .with_context(|| format!("failed to create KV bucket {bucket}")),
+                }
+            }
+        })?;
+        Ok(Arc::new(Self {
+            runtime,
+            kv,
+            bucket,
+        }))
+    }
+
+    fn record_key(tenant: &str, key: &str) -> String {
+        format!("t.{}.{}", kv_token(tenant), kv_token(key))
+    }
+
+    fn index_key(key: &str) -> String {
+        format!("i.{}", kv_token(key))
+    }
+
+    fn lock_key(key: &str) -> String {
+        format!("l.{}", kv_token(key))
+    }
+
+    /// Live keys starting with `prefix`.
+    fn keys(&self, prefix: String) -> Result<Vec<String>> {
+        let kv = self.kv.clone();
+        self.runtime
+            .run(&format!("listing {}", self.bucket), async move {
+                let mut keys = kv.keys().await?;
+                let mut out = Vec::new();
+                while let Some(key) = keys.next().await {
+                    let key = key?;
+                    if key.starts_with(&prefix) {
+                        out.push(key);
+                    }
+                }
+                Ok(out)
+            })
+            .with_context(|| format!("failed to list KV bucket {}", self.bucket))
+    }
+
+    fn fetch(&self, key: String) -> Result<Option<Vec<u8>>> {
+        let kv = self.kv.clone();
+        self.runtime
+            .run(&format!("reading {key}"), async move {
+                Ok(kv.get(key).await?.map(|value| value.to_vec()))
+            })
+            .with_context(|| format!("failed to read KV bucket {}", self.bucket))
+    }
+
+    fn store(&self, key: String, value: Vec<u8>) -> Result<()> {
+        let kv = self.kv.clone();
+        self.runtime
+            .run(&format!("writing {key}"), async move {
+                kv.put(key, value.into()).await?;
+                Ok(())
+            })
+            .with_context(|| format!("failed to write KV bucket {}", self.bucket))
+    }
+
+    fn erase(&self, key: String) -> Result<()> {
+        let kv = self.kv.clone();
+        self.runtime
+            .run(&format!("deleting {key}"), async move {
+                kv.delete(key).await?;
+                Ok(())
+            })
+            .with_context(|| format!("failed to delete from KV bucket {}", self.bucket))
+    }
+
+    /// Raw record entries (KV key and bytes) of `tenant`, or of every tenant.
+    fn raw(&self, tenant: Option<&str>) -> Result<Vec<(String, Vec<u8>)>> {
+        let prefix = match tenant {
+            Some(tenant) => format!("t.{}.", kv_token(tenant)),
+            None => "t.".to_string(),
+        };
+        let mut out = Vec::new();
+        for key in self.keys(prefix)? {
+            if let Some(value) = self.fetch(key.clone())? {
+                out.push((key, value));
+            }
+        }
+        Ok(out)
+    }
+
+    fn load(&self, tenant: Option<&str>) -> Result<Vec<SessionRecord>> {
+        Ok(self
+            .raw(tenant)?
+            .into_iter()
+            .filter_map(|(_, value)| serde_json::from_slice(&value).ok())
+            .collect())
+    }
+
+    fn tenant_of(&self, key: &str) -> Result<Option<String>> {
+        Ok(self
+            .fetch(Self::index_key(key))?
+            .map(|tenant| String::from_utf8_lossy(&tenant).into_owned()))
+    }
+
+    /// Records and index entries are separate keys; the record is written first so a crash
+    /// in between leaves an entry `sessions fsck` can see rather than a dangling index.
+    fn persist(&self, record: &SessionRecord) -> Result<()> {
+        let previous = self.tenant_of(&record.key)?;
+        self.store(
+            Self::record_key(&record.tenant, &record.key),
+            serde_json::to_vec(record)?,
+        )?;
+        self.store(
+            Self::index_key(&record.key),
+            record.tenant.clone().into_bytes(),
+        )?;
+        // A key re-used by another tenant leaves the old tenant's prefix.
+        if let Some(previous) = previous.filter(|previous| *previous != record.tenant) {
+            self.erase(Self::record_key(&previous, &record.key))?;
+        }
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        if let Some(tenant) = self.tenant_of(key)? {
+            self.erase(Self::record_key(&tenant, key))?;
+        }
+        self.erase(Self::index_key(key))
+    }
+}
+
+impl SessionStore for NatsKvSessionStore {
+    fn list(&self, filter: &SessionFilter) -> Result<Vec<SessionRecord>> {
+        Ok(self
+            .load(filter.tenant.as_deref())?
+            .into_iter()
+            .filter(|record| filter.matches(record))
+            .collect())
+    }
+
+    fn purge(&self, filter: &SessionFilter) -> Result<usize> {
+        let matching = self.list(filter)?;
+        for record in &matching {
+            self.delete(&record.key)?;
+        }
+        Ok(matching.len())
+    }
+
+    fn upsert(&self, payload: SessionUpsert) -> Result<SessionRecord> {
+        let record = SessionRecord::from_upsert(payload);
+        self.persist(&record)?;
+        Ok(record)
+    }
+
+    fn find(&self, filter: &SessionFilter) -> Result<Option<SessionRecord>> {
+        Ok(self
+            .load(filter.tenant.as_deref())?
+            .into_iter()
+            .find(|record| filter.matches(record)))
+    }
+
+    fn remove(&self, key: &str) -> Result<()> {
+        self.delete(key)
+    }
+
+    fn get(&self, key: &str) -> Result<Option<SessionRecord>> {
+        let Some(tenant) = self.tenant_of(key)? else {
+            return Ok(None);
+        };
+        self.fetch(Self::record_key(&tenant, key))?
+            .map(|json| {
+                serde_json::from_slice(&json)
+                    .with_context(|| format!("invalid session JSON for {key}"))
+            })
+            .transpose()
+    }
+
+    fn put(&self, record: SessionRecord) -> Result<()> {
+        self.persist(&record)
+    }
+
+    fn ping(&self) -> Result<()> {
+        let kv = self.kv.clone();
+        self.runtime
+            .run("NATS KV status", async move {
+                kv.status().await?;
+                Ok(())
+            })
+            .with_context(|| format!("NATS KV bucket {} is unreachable", self.bucket))
+    }
+
+    /// `l.<key>` is created exclusively with a random token and expiry. An expired lease is
+    /// taken over with a revision-checked update, so two instances cannot both win it, and
+    /// release deletes the lease only at the revision it was taken with.
+    fn try_lock(&self, key: &str, ttl: Duration) -> Result<Option<SessionLease>> {
+        use async_nats::jetstream::kv::{CreateErrorKind, Operation, UpdateErrorKind};
+
+        let lock_key = Self::lock_key(key);
+        let now = current_timestamp_ms();
+        let lease = serde_json::to_vec(&NatsKvLease {
+            token: Uuid::new_v4().to_string(),
+            expires_at_ms: now + ttl.as_millis().max(1) as u64,
+        })?;
+        let kv = self.kv.clone();
+        let acquired = self.runtime.run(&format!("locking {key}"), {
+            let lock_key = lock_key.clone();
+            async move {
+                match kv.create(&lock_key, lease.clone().into()).await {
+                    Ok(revision) => return Ok(Some(revision)),
+                    Err(err) if err.kind() == CreateErrorKind::AlreadyExists => {}
+                    Err(err) => return Err(err.into()),
+                }
+                let Some(held) = kv.entry(&lock_key).await? else {
+                    return Ok(None);
+                };
+                let live = held.operation == Operation::Put
+                    && serde_json::from_slice::<NatsKvLease>(&held.value)
+                        .is_ok_and(|held| held.expires_at_ms > now);
+                if live {
+                    return Ok(None);
+                }
+                match kv.update(&lock_key, lease.into(), held.revision).await {
+                    Ok(revision) => Ok(Some(revision)),
+                    Err(err) if err.kind() == UpdateErrorKind::WrongLastRevision => Ok(None),
+                    Err(err) => Err(err.into()),
+                }
+            }
+        })?;
+        let Some(revision) = acquired else {
+            return Ok(None);
+        };
+        let (kv, handle) = (self.kv.clone(), self.runtime.handle.clone());
+        Ok(Some(SessionLease::new(move || {
+            handle.spawn(async move {
+                if let Err(err) = kv.delete_expect_revision(&lock_key, Some(revision)).await {
+                    warn!(?err, %lock_key, "failed to release session lock");
+                }
+            });
+        })))
+    }
+}
+
+impl RawSessionAccess for NatsKvSessionStore {
+    fn raw_entries(&self) -> Result<Vec<RawSessionEntry>> {
+        Ok(self
+            .raw(None)?
+            .into_iter()
+            .map(|(kv_key, value)| RawSessionEntry {
+                slot: kv_key.splitn(3, '.').nth(2).and_then(kv_token_decode),
+                value: serde_json::from_slice(&value).map_err(|err| err.to_string()),
+            })
+            .collect())
+    }
+
+    fn replace_all(&self, records: Vec<SessionRecord>) -> Result<()> {
+        for key in self
+            .keys("t.".into())?
+            .into_iter()
+            .chain(self.keys("i.".into())?)
+        {
+            self.erase(key)?;
+        }
+        for record in &records {
+            self.persist(record)?;
+        }
+        Ok(())
+    }
+}
+
+/// Escape `raw` into one KV key token: `[A-Za-z0-9_/-]` stay, every other byte becomes
+/// `=XX`, and the empty string is `=`.
+fn kv_token(raw: &str) -> String {
+    if raw.is_empty() {
+        return "=".into();
+    }
+    let mut out = String::with_capacity(raw.len());
+    for byte in raw.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_' | b'/' | b'-' => out.push(byte as char),
+            _ => out.push_str(&format!("={byte:02X}")),
+        }
+    }
+    out
+}
+
+fn kv_token_decode(token: &str) -> Option<String> {
+    if token == "=" {
+        return Some(String::new());
+    }
+    let mut bytes = Vec::with_capacity(token.len());
+    let mut rest = token.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'=' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Sessions as rows of the embedded SQLite database: owner columns are indexed for filtering
+/// and the full record is kept as JSON. Leases are per process (single-node servers).
+pub struct SqliteSessionStore {
+    db: Arc<SqliteDb>,
+}
+
+impl SqliteSessionStore {
+    pub fn new(db: Arc<SqliteDb>) -> Arc<Self> {
+        Arc::new(Self { db })
+    }
+}
+
+/// Visit the records matching `filter` until `visit` returns `false`. Owner columns and the
+/// update cutoff narrow the rows in SQL; the rest of the filter is applied to the decoded record. With `page`,
+/// rows come in its order, starting after its cursor.
+fn select_sessions(
+    conn: &rusqlite::Connection,
+    filter: &SessionFilter,
+    page: Option<&SessionPageRequest>,
+    visit: &mut dyn FnMut(SessionRecord) -> bool,
+) -> Result<()> {
+    use rusqlite::types::Value as SqlValue;
+
+    let mut sql = "SELECT key, record FROM sessions WHERE 1 = 1".to_string();
+    let mut args: Vec<SqlValue> = Vec::new();
+    for (column, value) in [
+        ("tenant", &filter.tenant),
+        ("team", &filter.team),
+        ("user", &filter.user),
+    ] {
+        if let Some(value) = value {
+            sql.push_str(&format!(" AND {column} = ?"));
+            args.push(SqlValue::Text(value.clone()));
+        }
+    }
+    if let Some(cutoff) = filter.updated_before_ms {
+        sql.push_str(" AND updated_at_epoch_ms < ?");
+        args.push(SqlValue::Integer(cutoff as i64));
+    }
+    if let Some(page) = page {
+        if let Some((updated, key)) = page.decode_cursor()? {
+            let updated = SqlValue::Integer(updated as i64);
+            match page.sort {
+                SessionSort::Key => sql.push_str(" AND key > ?"),
+                SessionSort::UpdatedAt => {
+                    sql.push_str(" AND (updated_at_epoch_ms, key) > (?, ?)");
+                    args.push(updated);
+                }
+                SessionSort::UpdatedAtDesc => {
+                    sql.push_str(
+                        " AND (updated_at_epoch_ms < ? OR (updated_at_epoch_ms = ? AND key > ?))",
+                    );
+                    args.extend([updated.clone(), updated]);
+                }
+            }
+            args.push(SqlValue::Text(key));
+        }
+        sql.push_str(match page.sort {
+            SessionSort::Key => " ORDER BY key",
+            SessionSort::UpdatedAt => " ORDER BY updated_at_epoch_ms, key",
+            SessionSort::UpdatedAtDesc => " ORDER BY updated_at_epoch_ms DESC, key",
+        });
+    }
+    let mut stmt = conn.prepare_cached(&sql)?;
+    let mut rows = stmt.query(rusqlite::params_from_iter(args))?;
+    while let Some(row) = rows.next()? {
+        let key: String = row.get(0)?;
+        let json: String = row.get(1)?;
+        match serde_json::from_str::<SessionRecord>(&json) {
+            Ok(record) if filter.matches(&record) => {
+                if !visit(record) {
+                    break;
+                }
+            }
+            Ok(_) => {}
+            Err(err) => warn!(%err, %key, "skipping unreadable sqlite session row"),
+        }
+    }
+    Ok(())
+}
+
+fn write_session(conn: &rusqlite::Connection, record: &SessionRecord) -> Result<()> {
+    conn.prepare_cached(
+        "INSERT INTO sessions (key, tenant, team, user, updated_at_epoch_ms, record)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT (key) DO UPDATE SET
+             tenant = excluded.tenant,
+             team = excluded.team,
+             user = excluded.user,
+             updated_at_epoch_ms = excluded.updated_at_epoch_ms,
+             record = excluded.record",
+    )?
+    .execute(rusqlite::params![
+        record.key,
+        record.tenant,
+        record.team,
+        record.user,
+        record.updated_at_epoch_ms as i64,
+        serde_json::to_string(record)?,
+    ])
+    .with_context(|| format!("failed to write session {}", record.key))?;
+    Ok(())
+}
+
+impl SessionStore for SqliteSessionStore {
+    fn list(&self, filter: &SessionFilter) -> Result<Vec<SessionRecord>> {
+        let mut out = Vec::new();
+        select_sessions(&self.db.conn(), filter, None, &mut |record| {
+            out.push(record);
+            true
+        })?;
+        Ok(out)
+    }
+
+    fn purge(&self, filter: &SessionFilter) -> Result<usize> {
+        let mut conn = self.db.conn();
+        let tx = conn.transaction()?;
+        let mut keys = Vec::new();
+        select_sessions(&tx, filter, None, &mut |record| {
+            keys.push(record.key);
+            true
+        })?;
+        for key in &keys {
+            tx.execute("DELETE FROM sessions WHERE key = ?1", [key])?;
+        }
+        tx.commit()?;
+        Ok(keys.len())
+    }
+
+    fn upsert(&self, payload: SessionUpsert) -> Result<SessionRecord> {
+        let record = SessionRecord::from_upsert(payload);
+        write_session(&self.db.conn(), &record)?;
+        Ok(record)
+    }
+
+    fn find(&self, filter: &SessionFilter) -> Result<Option<SessionRecord>> {
+        let mut found = None;
+        select_sessions(&self.db.conn(), filter, None, &mut |record| {
+            found = Some(record);
+            false
+        })?;
+        Ok(found)
+    }
+
+    fn remove(&self, key: &str) -> Result<()> {
+        self.db
+            .conn()
+            .execute("DELETE FROM sessions WHERE key = ?1", [key])
+            .with_context(|| format!("failed to delete session {key}"))?;
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> Result<Option<SessionRecord>> {
+        let conn = self.db.conn();
+        let json: Option<String> = conn
+            .query_row("SELECT record FROM sessions WHERE key = ?1", [key], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        json.map(|json| {
+            serde_json::from_str(&json).with_context(|| format!("invalid session JSON for {key}"))
+        })
+        .transpose()
+    }
+
+    fn put(&self, record: SessionRecord) -> Result<()> {
+        write_session(&self.db.conn(), &record)
+    }
+
+    fn list_page(&self, filter: &SessionFilter, page: &SessionPageRequest) -> Result<SessionPage> {
+        // Rows arrive sorted and past the cursor; read one beyond the limit to know whether
+        // another page follows.
+        let want = page
+            .limit
+            .map_or(usize::MAX, |limit| limit.saturating_add(1));
+        let mut skip = page.offset;
+        let mut sessions = Vec::new();
+        select_sessions(&self.db.conn(), filter, Some(page), &mut |record| {
+            if skip > 0 {
+                skip -= 1;
+            } else {
+                sessions.push(record);
+            }
+            sessions.len() < want
+        })?;
+        let more = page.limit.is_some_and(|limit| sessions.len() > limit);
+        if more {
+            sessions.pop();
+        }
+        let next_cursor = sessions
+            .last()
+            .filter(|_| more)
+            .map(|last| page.encode_cursor(last));
+        Ok(SessionPage {
+            sessions,
+            next_cursor,
+        })
+    }
+
+    fn scan(&self, filter: &SessionFilter, visit: &mut dyn FnMut(&SessionRecord)) -> Result<()> {
+        select_sessions(&self.db.conn(), filter, None, &mut |record| {
+            visit(&record);
+            true
+        })
+    }
+
+    fn ping(&self) -> Result<()> {
+        self.db
+            .conn()
+            .query_row("SELECT 1", [], |_| Ok(()))
+            .with_context(|| format!("sqlite database {} is not readable", self.db.path()))
+    }
+
+    /// A `session_locks` row with a random token, so bridges sharing the database file see
+    /// each other's leases. An expired row is taken over; release deletes the row only while
+    /// it still carries this lease's token.
+    fn try_lock(&self, key: &str, ttl: Duration) -> Result<Option<SessionLease>> {
+        let token = Uuid::new_v4().to_string();
+        let now = current_timestamp_ms() as i64;
+        let acquired = self
+            .db
+            .conn()
+            .prepare_cached(
+                "INSERT INTO session_locks (key, token, expires_at_ms) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (key) DO UPDATE SET
+                     token = excluded.token,
+                     expires_at_ms = excluded.expires_at_ms
+                 WHERE session_locks.expires_at_ms <= ?4",
+            )?
+            .execute(rusqlite::params![
+                key,
+                token,
+                now + ttl.as_millis().max(1) as i64,
+                now
+            ])
+            .with_context(|| format!("failed to lock session {key}"))?;
+        if acquired == 0 {
+            return Ok(None);
+        }
+        let (db, key) = (self.db.clone(), key.to_string());
+        Ok(Some(SessionLease::new(move || {
+            let released = db.conn().execute(
+                "DELETE FROM session_locks WHERE key = ?1 AND token = ?2",
+                [&key, &token],
+            );
+            if let Err(err) = released {
+                warn!(?err, %key, "failed to release session lock");
+            }
+        })))
+    }
+}
+
+impl RawSessionAccess for SqliteSessionStore {
+    fn raw_entries(&self) -> Result<Vec<RawSessionEntry>> {
+        let conn = self.db.conn();
+        let mut stmt = conn.prepare("SELECT key, record FROM sessions")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        rows.map(|row| {
+            let (key, json) = row?;
+            Ok(RawSessionEntry {
+                slot: Some(key),
+                value: serde_json::from_str(&json).map_err(|err| err.to_string()),
+            })
+        })
+        .collect()
+    }
+
+    fn replace_all(&self, records: Vec<SessionRecord>) -> Result<()> {
+        let mut conn = self.db.conn();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM sessions", [])?;
+        for record in &records {
+            write_session(&tx, record)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+/// Decorator that tombstones removed sessions instead of deleting them, keeping them
+/// restorable until `window_ms` elapses and `finalize_deletions` drops them for good.
+pub struct SoftDeleteSessionStore {
+    inner: Arc<dyn SessionStore>,
+    window_ms: u64,
+}
+
+impl SoftDeleteSessionStore {
+    pub fn new(inner: Arc<dyn SessionStore>, window_ms: u64) -> Arc<Self> {
+        Arc::new(Self { inner, window_ms })
+    }
+
+    fn tombstone(&self, mut record: SessionRecord) -> Result<()> {
+        record.deleted_at_epoch_ms = Some(current_timestamp_ms());
+        self.inner.put(record)
+    }
+
+    fn expired(&self, record: &SessionRecord, now: u64) -> bool {
+        record
+            .deleted_at_epoch_ms
+            .is_some_and(|deleted| now.saturating_sub(deleted) > self.window_ms)
+    }
+}
+
+impl SessionStore for SoftDeleteSessionStore {
+    fn list(&self, filter: &SessionFilter) -> Result<Vec<SessionRecord>> {
+        Ok(self
+            .inner
+            .list(filter)?
+            .into_iter()
+            .filter(|record| !record.is_deleted())
+            .collect())
+    }
+
+    fn purge(&self, filter: &SessionFilter) -> Result<usize> {
+        let live = self.list(filter)?;
+        let removed = live.len();
+        for record in live {
+            self.tombstone(record)?;
+        }
+        Ok(removed)
+    }
+
+    fn upsert(&self, record: SessionUpsert) -> Result<SessionRecord> {
+        self.inner.upsert(record)
+    }
+
+    fn find(&self, filter: &SessionFilter) -> Result<Option<SessionRecord>> {
+        Ok(self.list(filter)?.into_iter().next())
+    }
+
+    fn remove(&self, key: &str) -> Result<()> {
+        match self.inner.get(key)? {
+            Some(record) if !record.is_deleted() => self.tombstone(record),
+            _ => Ok(()),
+        }
+    }
+
+    fn get(&self, key: &str) -> Result<Option<SessionRecord>> {
+        Ok(self.inner.get(key)?.filter(|record| !record.is_deleted()))
+    }
+
+    fn put(&self, record: SessionRecord) -> Result<()> {
+        self.inner.put(record)
+    }
+
+    fn restore(&self, key: &str) -> Result<Option<SessionRecord>> {
+        let Some(mut record) = self.inner.get(key)? else {
+            return Ok(None);
+        };
+        if !record.is_deleted() || self.expired(&record, current_timestamp_ms()) {
+            return Ok(None);
+        }
+        record.deleted_at_epoch_ms = None;
+        self.inner.put(record.clone())?;
+        Ok(Some(record))
+    }
+
+    fn finalize_deletions(&self) -> Result<usize> {
+        let now = current_timestamp_ms();
+        let mut finalized = 0;
+        for record in self.inner.list(&SessionFilter::default())? {
+            if self.expired(&record, now) {
+                self.inner.remove(&record.key)?;
+                finalized += 1;
+            }
+        }
+        Ok(finalized)
+    }
+
+    fn scan(&self, filter: &SessionFilter, visit: &mut dyn FnMut(&SessionRecord)) -> Result<()> {
+        self.inner.scan(filter, &mut |record| {
+            if !record.is_deleted() {
+                visit(record);
+            }
+        })
+    }
+
+    fn ping(&self) -> Result<()> {
+        self.inner.ping()
+    }
+
+    fn try_lock(&self, key: &str, ttl: Duration) -> Result<Option<SessionLease>> {
+        self.inner.try_lock(key, ttl)
+    }
+}
+
+/// Remove every session whose `ttl_ms` has run out by `now_ms`; returns how many went.
+pub fn purge_expired(store: &dyn SessionStore, now_ms: u64) -> Result<usize> {
+    let mut expired = Vec::new();
+    store.scan(&SessionFilter::default(), &mut |record| {
+        if record.is_expired(now_ms) {
+            expired.push(record.key.clone());
+        }
+    })?;
+    for key in &expired {
+        store.remove(key)?;
+    }
+    Ok(expired.len())
+}
+
+pub(crate) fn current_timestamp_ms() -> u64 {
     use std::time::{SystemTime, UNIX_EPOCH};
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -367,6 +2078,68 @@ mod tests {
     use tempfile::tempdir;
     use uuid::Uuid;
 
+    #[test]
+    fn purge_expired_removes_only_sessions_past_their_ttl() {
+        let store = InMemorySessionStore::new();
+        for (key, updated_at_epoch_ms, ttl_ms) in [
+            ("fresh", 9_500, Some(1_000)),
+            ("stale", 8_000, Some(1_000)),
+            ("forever", 0, None),
+        ] {
+            store
+                .put(SessionRecord {
+                    key: key.into(),
+                    tenant: "acme".into(),
+                    updated_at_epoch_ms,
+                    ttl_ms,
+                    ..SessionRecord::default()
+                })
+                .unwrap();
+        }
+
+        assert!(store.get("stale").unwrap().unwrap().is_expired(10_000));
+        assert_eq!(purge_expired(store.as_ref(), 10_000).unwrap(), 1);
+        assert!(store.get("stale").unwrap().is_none());
+        assert!(store.get("fresh").unwrap().is_some());
+        assert!(store.get("forever").unwrap().is_some());
+        assert_eq!(purge_expired(store.as_ref(), 10_500).unwrap(), 1);
+        assert!(store.get("fresh").unwrap().is_none());
+    }
+
+    #[test]
+    fn purge_updated_before_keeps_recent_sessions_on_memory_and_sqlite() {
+        let temp = tempdir().unwrap();
+        let root = Utf8PathBuf::from_path_buf(temp.path().to_path_buf()).expect("utf8 root");
+        let memory: Arc<dyn SessionStore> = InMemorySessionStore::new();
+        let sqlite: Arc<dyn SessionStore> = SqliteSessionStore::new(
+            SqliteDb::open(root, Utf8PathBuf::from("greentic.db")).unwrap(),
+        );
+        for store in [memory, sqlite] {
+            for (key, tenant, updated_at_epoch_ms) in [
+                ("old", "acme", 1_000),
+                ("edge", "acme", 5_000),
+                ("new", "acme", 9_000),
+                ("other", "globex", 1_000),
+            ] {
+                store
+                    .put(SessionRecord {
+                        key: key.into(),
+                        tenant: tenant.into(),
+                        updated_at_epoch_ms,
+                        ..SessionRecord::default()
+                    })
+                    .unwrap();
+            }
+            let stale = SessionFilter::new(Some("acme".into()), None, None).updated_before(5_000);
+            assert_eq!(store.list(&stale).unwrap().len(), 1);
+            assert_eq!(store.purge(&stale).unwrap(), 1);
+            assert!(store.get("old").unwrap().is_none());
+            for key in ["edge", "new", "other"] {
+                assert!(store.get(key).unwrap().is_some(), "{key} should survive");
+            }
+        }
+    }
+
     #[test]
     fn in_memory_find_and_remove() {
         let store = InMemorySessionStore::new();
@@ -378,6 +2151,12 @@ mod tests {
             flow_id: Some("flow-a".into()),
             node_id: Some("node-1".into()),
             context: json!({"hello": "world"}),
+            pack_id: None,
+            flow_version: None,
+            locale: None,
+            ttl_ms: None,
+            pack_generation: None,
+            runner_target: None,
         };
         store.upsert(record).unwrap();
 
@@ -394,11 +2173,27 @@ mod tests {
         assert!(store.find(&filter).unwrap().is_none());
     }
 
+    #[test]
+    fn session_lease_is_exclusive_until_dropped() {
+        let store = SoftDeleteSessionStore::new(InMemorySessionStore::new(), 60_000);
+        let ttl = Duration::from_secs(5);
+        let lease = store.try_lock("sess-1", ttl).unwrap().expect("first lease");
+        assert!(store.try_lock("sess-1", ttl).unwrap().is_none());
+        assert!(store.try_lock("sess-2", ttl).unwrap().is_some());
+        drop(lease);
+        assert!(store.try_lock("sess-1", ttl).unwrap().is_some());
+    }
+
     #[test]
     fn file_store_persists_sessions() {
         let temp = tempdir().unwrap();
         let root = Utf8PathBuf::from_path_buf(temp.path().to_path_buf()).expect("utf8 root");
-        let store = FileSessionStore::new(root, Utf8PathBuf::from("sessions.json")).unwrap();
+        let store = FileSessionStore::new(
+            root,
+            Utf8PathBuf::from("sessions.json"),
+            CompactionThresholds::default(),
+        )
+        .unwrap();
 
         let record = SessionUpsert {
             key: "sess-999".into(),
@@ -408,6 +2203,12 @@ mod tests {
             flow_id: Some("flow-z".into()),
             node_id: None,
             context: json!({"x": 1}),
+            pack_id: None,
+            flow_version: None,
+            locale: None,
+            ttl_ms: None,
+            pack_generation: None,
+            runner_target: None,
         };
         store.upsert(record).unwrap();
 
@@ -419,6 +2220,307 @@ mod tests {
         assert!(store.list(&filter).unwrap().is_empty());
     }
 
+    #[test]
+    fn file_stores_sharing_a_path_keep_each_others_writes() {
+        let temp = tempdir().unwrap();
+        let root = Utf8PathBuf::from_path_buf(temp.path().to_path_buf()).expect("utf8 root");
+        let open = || {
+            FileSessionStore::new(
+                root.clone(),
+                "data/sessions.json".into(),
+                CompactionThresholds::default(),
+            )
+            .unwrap()
+        };
+        let (first, second) = (open(), open());
+        std::thread::scope(|scope| {
+            for (store, tenant) in [(&first, "acme"), (&second, "globex")] {
+                scope.spawn(move || {
+                    for idx in 0..20 {
+                        store
+                            .upsert(SessionUpsert {
+                                key: format!("{tenant}-{idx}"),
+                                tenant: tenant.into(),
+                                team: None,
+                                user: None,
+                                flow_id: None,
+                                node_id: None,
+                                context: Value::Null,
+                                pack_id: None,
+                                flow_version: None,
+                                locale: None,
+                                ttl_ms: None,
+                                pack_generation: None,
+                                runner_target: None,
+                            })
+                            .unwrap();
+                    }
+                });
+            }
+        });
+        second.remove("acme-0").unwrap();
+
+        assert_eq!(open().list(&SessionFilter::default()).unwrap().len(), 39);
+        assert_eq!(first.list(&SessionFilter::default()).unwrap().len(), 39);
+        let leftovers: Vec<_> = fs::read_dir(temp.path().join("data"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter(|name| name.ends_with(".tmp"))
+            .collect();
+        assert!(leftovers.is_empty(), "{leftovers:?}");
+    }
+
+    #[test]
+    fn file_store_replays_its_journal_and_compacts_past_the_threshold() {
+        let temp = tempdir().unwrap();
+        let root = Utf8PathBuf::from_path_buf(temp.path().to_path_buf()).expect("utf8 root");
+        let thresholds = CompactionThresholds {
+            max_entries: 4,
+            max_bytes: u64::MAX,
+        };
+        let open =
+            || FileSessionStore::new(root.clone(), "sessions.json".into(), thresholds).unwrap();
+        let journal = temp.path().join("sessions.json.journal");
+        let store = open();
+        for key in ["a", "b", "c"] {
+            store
+                .upsert(SessionUpsert {
+                    key: key.into(),
+                    tenant: "acme".into(),
+                    team: None,
+                    user: None,
+                    flow_id: None,
+                    node_id: None,
+                    context: Value::Null,
+                    pack_id: None,
+                    flow_version: None,
+                    locale: None,
+                    ttl_ms: None,
+                    pack_generation: None,
+                    runner_target: None,
+                })
+                .unwrap();
+        }
+        store.remove("b").unwrap();
+        // The fourth entry crossed the threshold: everything lives in the snapshot now.
+        assert!(!journal.exists());
+        store.remove("c").unwrap();
+        assert_eq!(fs::read_to_string(&journal).unwrap().lines().count(), 1);
+
+        // A crash mid-append leaves a torn line that recovery ignores and the next append
+        // overwrites.
+        let mut file = fs::OpenOptions::new().append(true).open(&journal).unwrap();
+        file.write_all(br#"{"op":"remove","key":"a"#).unwrap();
+        let reopened = open();
+        let keys: Vec<_> = reopened
+            .list(&SessionFilter::default())
+            .unwrap()
+            .into_iter()
+            .map(|record| record.key)
+            .collect();
+        assert_eq!(keys, ["a"]);
+        reopened.remove("a").unwrap();
+        assert_eq!(fs::read_to_string(&journal).unwrap().lines().count(), 2);
+        assert!(open().list(&SessionFilter::default()).unwrap().is_empty());
+
+        let compaction = reopened.compact().unwrap();
+        assert_eq!(compaction.journal_entries, 2);
+        assert_eq!(compaction.sessions, 0);
+        assert!(!journal.exists());
+        assert!(open().list(&SessionFilter::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn sqlite_store_filters_persists_and_rewrites() {
+        let temp = tempdir().unwrap();
+        let root = Utf8PathBuf::from_path_buf(temp.path().to_path_buf()).expect("utf8 root");
+        let open = || {
+            SqliteSessionStore::new(
+                SqliteDb::open(root.clone(), Utf8PathBuf::from("greentic.db")).unwrap(),
+            )
+        };
+        let store = open();
+        for (key, tenant, user) in [
+            ("s1", "acme", "u1"),
+            ("s2", "acme", "u2"),
+            ("s3", "globex", "u1"),
+        ] {
+            store
+                .upsert(SessionUpsert {
+                    key: key.into(),
+                    tenant: tenant.into(),
+                    team: None,
+                    user: Some(user.into()),
+                    flow_id: Some("flow".into()),
+                    node_id: None,
+                    context: json!({"key": key}),
+                    pack_id: None,
+                    flow_version: None,
+                    locale: None,
+                    ttl_ms: None,
+                    pack_generation: None,
+                    runner_target: None,
+                })
+                .unwrap();
+        }
+        let acme = SessionFilter::new(Some("acme".into()), None, None);
+        assert_eq!(store.list(&acme).unwrap().len(), 2);
+        let u1 = SessionFilter::new(Some("globex".into()), None, Some("u1".into()));
+        assert_eq!(store.find(&u1).unwrap().unwrap().key, "s3");
+
+        drop(store);
+        let store = open();
+        assert_eq!(
+            store.get("s2").unwrap().unwrap().context,
+            json!({"key": "s2"})
+        );
+        assert_eq!(store.purge(&acme).unwrap(), 2);
+        assert_eq!(store.raw_entries().unwrap().len(), 1);
+        store.replace_all(Vec::new()).unwrap();
+        assert!(store.get("s3").unwrap().is_none());
+        store.ping().unwrap();
+    }
+
+    #[test]
+    fn sqlite_leases_are_shared_by_stores_on_the_same_file() {
+        let temp = tempdir().unwrap();
+        let root = Utf8PathBuf::from_path_buf(temp.path().to_path_buf()).expect("utf8 root");
+        let open = || {
+            SqliteSessionStore::new(
+                SqliteDb::open(root.clone(), Utf8PathBuf::from("greentic.db")).unwrap(),
+            )
+        };
+        let (bridge_a, bridge_b) = (open(), open());
+        let ttl = Duration::from_secs(30);
+
+        let lease = bridge_a
+            .try_lock("sess-1", ttl)
+            .unwrap()
+            .expect("first lease");
+        assert!(bridge_b.try_lock("sess-1", ttl).unwrap().is_none());
+        assert!(bridge_b.try_lock("sess-2", ttl).unwrap().is_some());
+        drop(lease);
+        let lease = bridge_b.try_lock("sess-1", ttl).unwrap().expect("released");
+        drop(lease);
+
+        // An expired lease is taken over, and its late release leaves the successor's alone.
+        let stale = bridge_a
+            .try_lock("sess-3", Duration::from_millis(1))
+            .unwrap()
+            .expect("short lease");
+        std::thread::sleep(Duration::from_millis(5));
+        let _successor = bridge_b.try_lock("sess-3", ttl).unwrap().expect("takeover");
+        drop(stale);
+        assert!(bridge_a.try_lock("sess-3", ttl).unwrap().is_none());
+    }
+
+    #[test]
+    fn list_page_walks_every_sort_the_same_on_memory_and_sqlite() {
+        let temp = tempdir().unwrap();
+        let root = Utf8PathBuf::from_path_buf(temp.path().to_path_buf()).expect("utf8 root");
+        let stores: [Arc<dyn SessionStore>; 2] = [
+            InMemorySessionStore::new(),
+            SqliteSessionStore::new(
+                SqliteDb::open(root, Utf8PathBuf::from("greentic.db")).unwrap(),
+            ),
+        ];
+        for store in &stores {
+            for (key, updated_at_epoch_ms, tenant) in [
+                ("d", 10, "acme"),
+                ("b", 30, "acme"),
+                ("a", 20, "acme"),
+                ("c", 20, "acme"),
+                ("e", 5, "globex"),
+            ] {
+                store
+                    .put(SessionRecord {
+                        key: key.into(),
+                        tenant: tenant.into(),
+                        updated_at_epoch_ms,
+                        ..SessionRecord::default()
+                    })
+                    .unwrap();
+            }
+            let acme = SessionFilter::new(Some("acme".into()), None, None);
+            let walk = |sort| {
+                let mut keys = Vec::new();
+                let mut page = SessionPageRequest {
+                    sort,
+                    limit: Some(3),
+                    ..SessionPageRequest::default()
+                };
+                loop {
+                    let result = store.list_page(&acme, &page).unwrap();
+                    keys.extend(result.sessions.into_iter().map(|record| record.key));
+                    match result.next_cursor {
+                        Some(cursor) => page.cursor = Some(cursor),
+                        None => return keys,
+                    }
+                }
+            };
+            assert_eq!(walk(SessionSort::Key), ["a", "b", "c", "d"]);
+            assert_eq!(walk(SessionSort::UpdatedAt), ["d", "a", "c", "b"]);
+            assert_eq!(walk(SessionSort::UpdatedAtDesc), ["b", "a", "c", "d"]);
+
+            let offset = SessionPageRequest {
+                limit: Some(2),
+                offset: 1,
+                ..SessionPageRequest::default()
+            };
+            let page = store.list_page(&acme, &offset).unwrap();
+            let keys: Vec<_> = page.sessions.iter().map(|record| &record.key).collect();
+            assert_eq!(keys, ["b", "c"]);
+            assert_eq!(page.next_cursor.as_deref(), Some("c"));
+
+            let bad = SessionPageRequest {
+                sort: SessionSort::UpdatedAt,
+                cursor: Some("not-a-cursor".into()),
+                ..SessionPageRequest::default()
+            };
+            assert!(store.list_page(&acme, &bad).is_err());
+        }
+    }
+
+    #[test]
+    fn soft_delete_tombstones_and_restores() {
+        let store = SoftDeleteSessionStore::new(InMemorySessionStore::new(), 60_000);
+        store
+            .upsert(SessionUpsert {
+                key: "soft-1".into(),
+                tenant: "acme".into(),
+                team: None,
+                user: Some("user-1".into()),
+                flow_id: Some("flow-a".into()),
+                node_id: None,
+                context: json!({}),
+                pack_id: None,
+                flow_version: None,
+                locale: None,
+                ttl_ms: None,
+                pack_generation: None,
+                runner_target: None,
+            })
+            .unwrap();
+        let filter = SessionFilter::new(Some("acme".into()), None, None);
+
+        assert_eq!(store.purge(&filter).unwrap(), 1);
+        assert!(store.list(&filter).unwrap().is_empty());
+        assert!(store.get("soft-1").unwrap().is_none());
+        assert_eq!(store.finalize_deletions().unwrap(), 0);
+
+        let restored = store.restore("soft-1").unwrap().expect("restorable");
+        assert!(!restored.is_deleted());
+        assert_eq!(store.list(&filter).unwrap().len(), 1);
+
+        let expired = SoftDeleteSessionStore::new(store.inner.clone(), 0);
+        let mut record = store.get("soft-1").unwrap().unwrap();
+        record.deleted_at_epoch_ms = Some(1);
+        store.put(record).unwrap();
+        assert!(expired.restore("soft-1").unwrap().is_none());
+        assert_eq!(expired.finalize_deletions().unwrap(), 1);
+        assert!(store.inner.get("soft-1").unwrap().is_none());
+    }
+
     #[test]
     fn redis_store_round_trip() {
         let url = match std::env::var("REDIS_URL") {
@@ -429,13 +2531,37 @@ mod tests {
             }
         };
         let prefix = format!("greentic:test:{}", Uuid::new_v4());
-        let store = RedisSessionStore::new(&url, Some(prefix.clone())).unwrap();
-        // clean bucket
         let mut conn = redis::Client::open(url.clone())
             .unwrap()
             .get_connection()
             .unwrap();
-        let _: i32 = redis::cmd("DEL").arg(&prefix).query(&mut conn).unwrap();
+        // A session in the single hash older builds wrote moves to its tenant's hash.
+        let legacy = SessionRecord::from_upsert(SessionUpsert {
+            key: "legacy".into(),
+            tenant: "other".into(),
+            team: None,
+            user: Some("user".into()),
+            flow_id: None,
+            node_id: None,
+            context: Value::Null,
+            pack_id: None,
+            flow_version: None,
+            locale: None,
+            ttl_ms: None,
+            pack_generation: None,
+            runner_target: None,
+        });
+        let _: () = conn
+            .hset(&prefix, "legacy", serde_json::to_string(&legacy).unwrap())
+            .unwrap();
+        let store = RedisSessionStore::new(&url, Some(prefix.clone())).unwrap();
+        let migrated = store
+            .get("legacy")
+            .unwrap()
+            .expect("legacy session migrated");
+        assert_eq!(migrated.tenant, legacy.tenant);
+        let legacy_left: bool = conn.exists(&prefix).unwrap();
+        assert!(!legacy_left);
 
         let filter = SessionFilter::new(Some("tenant".into()), None, Some("user".into()));
         let rec = store
@@ -447,6 +2573,12 @@ mod tests {
                 flow_id: Some("flow".into()),
                 node_id: None,
                 context: json!({"foo": "bar"}),
+                pack_id: None,
+                flow_version: None,
+                locale: None,
+                ttl_ms: None,
+                pack_generation: None,
+                runner_target: None,
             })
             .unwrap();
         assert_eq!(rec.key, "k1");
@@ -457,8 +2589,95 @@ mod tests {
         let listed = store.list(&filter).unwrap();
         assert_eq!(listed.len(), 1);
 
+        let tenant_keys: Vec<String> = conn.hkeys(format!("{prefix}:tenant:tenant")).unwrap();
+        assert_eq!(tenant_keys, ["k1"]);
+        let everyone = store.list(&SessionFilter::default()).unwrap();
+        assert_eq!(everyone.len(), 2);
+
         let removed = store.purge(&filter).unwrap();
         assert_eq!(removed, 1);
         assert!(store.list(&filter).unwrap().is_empty());
+        store.remove("legacy").unwrap();
+        let _: () = redis::cmd("DEL")
+            .arg(format!("{prefix}:index"))
+            .query(&mut conn)
+            .unwrap();
+    }
+
+    #[test]
+    fn kv_tokens_escape_everything_outside_the_key_alphabet() {
+        for raw in ["", "plain-key_1", "webchat:conv.42", "a=b c/ü"] {
+            let token = kv_token(raw);
+            assert!(
+                token
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b"_/-=".contains(&b)),
+                "{token}"
+            );
+            assert_eq!(kv_token_decode(&token).as_deref(), Some(raw));
+        }
+        assert_eq!(kv_token("webchat:conv.42"), "webchat=3Aconv=2E42");
+    }
+
+    #[test]
+    fn nats_kv_store_round_trip() {
+        let url = match std::env::var("NATS_URL") {
+            Ok(url) => url,
+            Err(_) => {
+                eprintln!("skipping nats_kv_store_round_trip: NATS_URL not set");
+                return;
+            }
+        };
+        let store =
+            NatsKvSessionStore::new(&url, Some(format!("greentic-test-{}", Uuid::new_v4())))
+                .unwrap();
+        store.ping().unwrap();
+        let upsert = |key: &str, tenant: &str| SessionUpsert {
+            key: key.into(),
+            tenant: tenant.into(),
+            team: None,
+            user: Some("user".into()),
+            flow_id: Some("flow".into()),
+            node_id: None,
+            context: json!({"foo": "bar"}),
+            pack_id: None,
+            flow_version: None,
+            locale: None,
+            ttl_ms: None,
+            pack_generation: None,
+            runner_target: None,
+        };
+        store.upsert(upsert("webchat:k1", "tenant")).unwrap();
+        store.upsert(upsert("k2", "other")).unwrap();
+        let filter = SessionFilter::new(Some("tenant".into()), None, Some("user".into()));
+        assert_eq!(store.list(&filter).unwrap().len(), 1);
+        assert_eq!(store.get("webchat:k1").unwrap().unwrap().tenant, "tenant");
+
+        // Moving a key to another tenant drops it from the old tenant's prefix.
+        store.upsert(upsert("webchat:k1", "other")).unwrap();
+        assert!(store.list(&filter).unwrap().is_empty());
+        assert_eq!(store.list(&SessionFilter::default()).unwrap().len(), 2);
+
+        let lease = store
+            .try_lock("webchat:k1", Duration::from_secs(30))
+            .unwrap()
+            .expect("lock is free");
+        assert!(
+            store
+                .try_lock("webchat:k1", Duration::from_secs(30))
+                .unwrap()
+                .is_none()
+        );
+        drop(lease);
+
+        let slots: HashSet<_> = store
+            .raw_entries()
+            .unwrap()
+            .into_iter()
+            .filter_map(|entry| entry.slot)
+            .collect();
+        assert_eq!(slots, HashSet::from(["webchat:k1".into(), "k2".into()]));
+        assert_eq!(store.purge(&SessionFilter::default()).unwrap(), 2);
+        assert!(store.get("k2").unwrap().is_none());
     }
 }
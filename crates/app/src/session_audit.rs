@@ -0,0 +1,382 @@
+//! Append-only history of session writes (`[stores.audit]`), for questions like "who cleared
+//! my session". [`AuditedSessionStore`] wraps the session store and records every upsert, put,
+//! remove, purge and restore with the acting party: the one set with [`as_actor`] for the
+//! current task (HTTP requests, background jobs), or the store's default (`server`, `cli:<user>`).
+
+use std::{
+    fs::{self, OpenOptions},
+    future::Future,
+    io::Write,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{Context, Result, anyhow};
+use camino::Utf8PathBuf;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::path_safety::normalize_under_root;
+use crate::session::{
+    SessionFilter, SessionLease, SessionPage, SessionPageRequest, SessionRecord, SessionStore,
+    SessionUpsert, current_timestamp_ms,
+};
+use crate::sqlite::SqliteDb;
+
+/// Header a caller names itself with (`X-Greentic-Actor: nightly-cleanup`).
+pub const ACTOR_HEADER: &str = "x-greentic-actor";
+
+tokio::task_local! {
+    static ACTOR: String;
+}
+
+/// Run `future` with session writes attributed to `actor`.
+pub async fn as_actor<F: Future>(actor: impl Into<String>, future: F) -> F::Output {
+    ACTOR.scope(actor.into(), future).await
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditOp {
+    Upsert,
+    Put,
+    Remove,
+    Purge,
+    Restore,
+}
+
+impl AuditOp {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AuditOp::Upsert => "upsert",
+            AuditOp::Put => "put",
+            AuditOp::Remove => "remove",
+            AuditOp::Purge => "purge",
+            AuditOp::Restore => "restore",
+        }
+    }
+}
+
+/// One write to one session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp_ms: u64,
+    pub op: AuditOp,
+    pub key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub team: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    pub actor: String,
+}
+
+pub trait SessionAuditLog: Send + Sync {
+    fn append(&self, entries: &[AuditEntry]) -> Result<()>;
+    /// Every entry recorded for `key`, oldest first.
+    fn history(&self, key: &str) -> Result<Vec<AuditEntry>>;
+}
+
+/// Audit entries as JSON lines in one file.
+pub struct FileSessionAuditLog {
+    path: Utf8PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileSessionAuditLog {
+    pub fn new(root: Utf8PathBuf, path: Utf8PathBuf) -> Result<Arc<Self>> {
+        let root = root
+            .as_std_path()
+            .canonicalize()
+            .with_context(|| format!("failed to canonicalize audit root {root}"))?;
+        let safe_path = normalize_under_root(&root, path.as_std_path())?;
+        let path = Utf8PathBuf::from_path_buf(safe_path)
+            .map_err(|_| anyhow!("normalized audit path is not valid UTF-8"))?;
+        Ok(Arc::new(Self {
+            path,
+            lock: Mutex::new(()),
+        }))
+    }
+}
+
+impl SessionAuditLog for FileSessionAuditLog {
+    fn append(&self, entries: &[AuditEntry]) -> Result<()> {
+        let _guard = self.lock.lock();
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("failed to open {}", self.path))?;
+        let mut buf = Vec::new();
+        for entry in entries {
+            serde_json::to_writer(&mut buf, entry)?;
+            buf.push(b'\n');
+        }
+        // One write per batch, so appends from other processes never interleave mid-line.
+        file.write_all(&buf)
+            .with_context(|| format!("failed to append to {}", self.path))
+    }
+
+    fn history(&self, key: &str) -> Result<Vec<AuditEntry>> {
+        let raw = match fs::read_to_string(&self.path) {
+            Ok(raw) => raw,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err).with_context(|| format!("failed to read {}", self.path)),
+        };
+        raw.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(idx, line)| {
+                serde_json::from_str::<AuditEntry>(line)
+                    .with_context(|| format!("invalid entry on line {} of {}", idx + 1, self.path))
+            })
+            .filter(|entry| entry.as_ref().map_or(true, |entry| entry.key == key))
+            .collect()
+    }
+}
+
+/// `session_audit` rows of the embedded SQLite database.
+pub struct SqliteSessionAuditLog {
+    db: Arc<SqliteDb>,
+}
+
+impl SqliteSessionAuditLog {
+    pub fn new(db: Arc<SqliteDb>) -> Arc<Self> {
+        Arc::new(Self { db })
+    }
+}
+
+impl SessionAuditLog for SqliteSessionAuditLog {
+    fn append(&self, entries: &[AuditEntry]) -> Result<()> {
+        let mut conn = self.db.conn();
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO session_audit (timestamp_ms, key, op, tenant, actor, entry)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for entry in entries {
+                stmt.execute(rusqlite::params![
+                    entry.timestamp_ms as i64,
+                    entry.key,
+                    entry.op.as_str(),
+                    entry.tenant,
+                    entry.actor,
+                    serde_json::to_string(entry)?,
+                ])?;
+            }
+        }
+        tx.commit()
+            .context("failed to append session audit entries")
+    }
+
+    fn history(&self, key: &str) -> Result<Vec<AuditEntry>> {
+        let conn = self.db.conn();
+        let mut stmt =
+            conn.prepare_cached("SELECT entry FROM session_audit WHERE key = ?1 ORDER BY id")?;
+        let rows = stmt.query_map([key], |row| row.get::<_, String>(0))?;
+        rows.map(|json| {
+            serde_json::from_str(&json?).context("invalid session audit entry in sqlite store")
+        })
+        .collect()
+    }
+}
+
+/// Records every write that goes through to the wrapped store. Audit failures are logged and
+/// never fail the write itself, which has already happened.
+pub struct AuditedSessionStore {
+    inner: Arc<dyn SessionStore>,
+    log: Arc<dyn SessionAuditLog>,
+    default_actor: String,
+}
+
+impl AuditedSessionStore {
+    pub fn new(
+        inner: Arc<dyn SessionStore>,
+        log: Arc<dyn SessionAuditLog>,
+        default_actor: impl Into<String>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            inner,
+            log,
+            default_actor: default_actor.into(),
+        })
+    }
+
+    fn record<'a>(&self, op: AuditOp, records: impl IntoIterator<Item = &'a SessionRecord>) {
+        let actor = ACTOR
+            .try_with(Clone::clone)
+            .unwrap_or_else(|_| self.default_actor.clone());
+        let timestamp_ms = current_timestamp_ms();
+        let entries: Vec<_> = records
+            .into_iter()
+            .map(|record| AuditEntry {
+                timestamp_ms,
+                op,
+                key: record.key.clone(),
+                tenant: Some(record.tenant.clone()),
+                team: record.team.clone(),
+                user: record.user.clone(),
+                actor: actor.clone(),
+            })
+            .collect();
+        if entries.is_empty() {
+            return;
+        }
+        if let Err(err) = self.log.append(&entries) {
+            warn!(
+                ?err,
+                op = op.as_str(),
+                "failed to record session audit entries"
+            );
+        }
+    }
+}
+
+impl SessionStore for AuditedSessionStore {
+    fn list(&self, filter: &SessionFilter) -> Result<Vec<SessionRecord>> {
+        self.inner.list(filter)
+    }
+
+    fn purge(&self, filter: &SessionFilter) -> Result<usize> {
+        let matched = self.inner.list(filter)?;
+        let removed = self.inner.purge(filter)?;
+        self.record(AuditOp::Purge, &matched);
+        Ok(removed)
+    }
+
+    fn upsert(&self, payload: SessionUpsert) -> Result<SessionRecord> {
+        let record = self.inner.upsert(payload)?;
+        self.record(AuditOp::Upsert, [&record]);
+        Ok(record)
+    }
+
+    fn find(&self, filter: &SessionFilter) -> Result<Option<SessionRecord>> {
+        self.inner.find(filter)
+    }
+
+    fn remove(&self, key: &str) -> Result<()> {
+        let existing = self.inner.get(key)?;
+        self.inner.remove(key)?;
+        self.record(AuditOp::Remove, &existing);
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> Result<Option<SessionRecord>> {
+        self.inner.get(key)
+    }
+
+    fn put(&self, record: SessionRecord) -> Result<()> {
+        self.inner.put(record.clone())?;
+        self.record(AuditOp::Put, [&record]);
+        Ok(())
+    }
+
+    fn restore(&self, key: &str) -> Result<Option<SessionRecord>> {
+        let restored = self.inner.restore(key)?;
+        self.record(AuditOp::Restore, &restored);
+        Ok(restored)
+    }
+
+    fn finalize_deletions(&self) -> Result<usize> {
+        self.inner.finalize_deletions()
+    }
+
+    fn list_page(&self, filter: &SessionFilter, page: &SessionPageRequest) -> Result<SessionPage> {
+        self.inner.list_page(filter, page)
+    }
+
+    fn scan(&self, filter: &SessionFilter, visit: &mut dyn FnMut(&SessionRecord)) -> Result<()> {
+        self.inner.scan(filter, visit)
+    }
+
+    fn ping(&self) -> Result<()> {
+        self.inner.ping()
+    }
+
+    fn try_lock(&self, key: &str, ttl: Duration) -> Result<Option<SessionLease>> {
+        self.inner.try_lock(key, ttl)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::InMemorySessionStore;
+    use serde_json::Value;
+
+    fn upsert(key: &str) -> SessionUpsert {
+        SessionUpsert {
+            key: key.into(),
+            tenant: "acme".into(),
+            team: None,
+            user: Some("u1".into()),
+            flow_id: None,
+            node_id: None,
+            context: Value::Null,
+            pack_id: None,
+            flow_version: None,
+            locale: None,
+            ttl_ms: None,
+            pack_generation: None,
+            runner_target: None,
+        }
+    }
+
+    fn ops(log: &dyn SessionAuditLog, key: &str) -> Vec<(AuditOp, String)> {
+        log.history(key)
+            .unwrap()
+            .into_iter()
+            .map(|entry| (entry.op, entry.actor))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn records_writes_with_the_acting_party() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Utf8PathBuf::from_path_buf(tmp.path().to_path_buf()).unwrap();
+        let logs: [Arc<dyn SessionAuditLog>; 2] = [
+            FileSessionAuditLog::new(root.clone(), "audit/sessions.jsonl".into()).unwrap(),
+            SqliteSessionAuditLog::new(SqliteDb::open(root, "greentic.db".into()).unwrap()),
+        ];
+        for log in logs {
+            let store = AuditedSessionStore::new(InMemorySessionStore::new(), log.clone(), "cli");
+            store.upsert(upsert("s1")).unwrap();
+            store.upsert(upsert("s2")).unwrap();
+            as_actor("admin", async {
+                store.remove("s1").unwrap();
+                // Removing a missing session writes nothing and records nothing.
+                store.remove("missing").unwrap();
+            })
+            .await;
+            as_actor("system:expiry", async {
+                store.purge(&SessionFilter::default()).unwrap();
+            })
+            .await;
+
+            assert_eq!(
+                ops(log.as_ref(), "s1"),
+                [
+                    (AuditOp::Upsert, "cli".to_string()),
+                    (AuditOp::Remove, "admin".to_string())
+                ]
+            );
+            assert_eq!(
+                ops(log.as_ref(), "s2"),
+                [
+                    (AuditOp::Upsert, "cli".to_string()),
+                    (AuditOp::Purge, "system:expiry".to_string())
+                ]
+            );
+            assert!(log.history("missing").unwrap().is_empty());
+            let entry = &log.history("s2").unwrap()[0];
+            assert_eq!(entry.tenant.as_deref(), Some("acme"));
+            assert_eq!(entry.user.as_deref(), Some("u1"));
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::session::{RawSessionAccess, RawSessionEntry, SessionRecord, current_timestamp_ms};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FsckIssueKind {
+    /// Entry is not valid JSON or does not deserialize into a session record.
+    InvalidRecord,
+    /// Key is empty or contains control/replacement characters (e.g. non-UTF-8 bytes).
+    InvalidKey,
+    /// Record key differs from the backend slot it is stored under.
+    KeyMismatch,
+    /// More than one entry shares the same key.
+    DuplicateKey,
+    MissingTenant,
+    /// `updated_at_epoch_ms` is zero or lies in the future beyond the allowed skew.
+    ClockSkew,
+    /// Tombstone left behind after its undo window (or with soft delete disabled).
+    OrphanedTombstone,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FsckIssue {
+    pub key: String,
+    pub kind: FsckIssueKind,
+    pub detail: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct FsckReport {
+    pub scanned: usize,
+    pub issues: Vec<FsckIssue>,
+    /// Number of records written back after repair (only set with `--fix`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repaired_records: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FsckOptions {
+    pub max_skew_ms: u64,
+    /// Undo window for tombstones; `None` treats every tombstone as orphaned.
+    pub tombstone_window_ms: Option<u64>,
+    /// Tenant assigned to records missing one when repairing; they are dropped otherwise.
+    pub default_tenant: Option<String>,
+}
+
+/// Inspect the store and, when `fix` is set, rewrite it with every issue repaired.
+pub fn run_fsck(
+    store: &dyn RawSessionAccess,
+    options: &FsckOptions,
+    fix: bool,
+) -> Result<FsckReport> {
+    let entries = store.raw_entries()?;
+    let now = current_timestamp_ms();
+    let (issues, repaired) = check_entries(&entries, options, now);
+    let mut report = FsckReport {
+        scanned: entries.len(),
+        issues,
+        repaired_records: None,
+    };
+    if fix && !report.issues.is_empty() {
+        report.repaired_records = Some(repaired.len());
+        store.replace_all(repaired)?;
+    }
+    Ok(report)
+}
+
+/// Returns the detected issues plus the repaired record set.
+pub fn check_entries(
+    entries: &[RawSessionEntry],
+    options: &FsckOptions,
+    now: u64,
+) -> (Vec<FsckIssue>, Vec<SessionRecord>) {
+    let mut issues = Vec::new();
+    let mut repaired: HashMap<String, SessionRecord> = HashMap::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+
+    for entry in entries {
+        let slot = entry.slot.clone().unwrap_or_default();
+        let mut issue = |kind, detail: String| {
+            issues.push(FsckIssue {
+                key: slot.clone(),
+                kind,
+                detail,
+            })
+        };
+
+        let mut value = match &entry.value {
+            Ok(value) => value.clone(),
+            Err(err) => {
+                issue(FsckIssueKind::InvalidRecord, err.clone());
+                continue;
+            }
+        };
+
+        let tenant_missing = value
+            .get("tenant")
+            .and_then(Value::as_str)
+            .is_none_or(|tenant| tenant.trim().is_empty());
+        if tenant_missing {
+            issue(FsckIssueKind::MissingTenant, "record has no tenant".into());
+            match (&options.default_tenant, value.as_object_mut()) {
+                (Some(tenant), Some(map)) => {
+                    map.insert("tenant".into(), Value::String(tenant.clone()));
+                }
+                _ => continue,
+            }
+        }
+
+        let mut record: SessionRecord = match serde_json::from_value(value) {
+            Ok(record) => record,
+            Err(err) => {
+                issue(FsckIssueKind::InvalidRecord, err.to_string());
+                continue;
+            }
+        };
+
+        if !is_valid_key(&record.key) {
+            issue(
+                FsckIssueKind::InvalidKey,
+                format!(
+                    "key {:?} is empty or contains invalid characters",
+                    record.key
+                ),
+            );
+            continue;
+        }
+        if let Some(slot_key) = &entry.slot
+            && *slot_key != record.key
+        {
+            issue(
+                FsckIssueKind::KeyMismatch,
+                format!(
+                    "stored under {slot_key:?} but record key is {:?}",
+                    record.key
+                ),
+            );
+        }
+
+        if record.updated_at_epoch_ms == 0
+            || record.updated_at_epoch_ms > now.saturating_add(options.max_skew_ms)
+        {
+            issue(
+                FsckIssueKind::ClockSkew,
+                format!(
+                    "updated_at_epoch_ms={} (now={now})",
+                    record.updated_at_epoch_ms
+                ),
+            );
+            record.updated_at_epoch_ms = now;
+        }
+
+        if let Some(deleted_at) = record.deleted_at_epoch_ms {
+            let orphaned = options
+                .tombstone_window_ms
+                .is_none_or(|window| now.saturating_sub(deleted_at) > window);
+            if orphaned {
+                issue(
+                    FsckIssueKind::OrphanedTombstone,
+                    format!("deleted_at_epoch_ms={deleted_at}"),
+                );
+                continue;
+            }
+        }
+
+        let count = seen.entry(record.key.clone()).or_default();
+        *count += 1;
+        if *count == 2 {
+            issue(
+                FsckIssueKind::DuplicateKey,
+                format!("key {:?} appears more than once", record.key),
+            );
+        }
+        match repaired.get(&record.key) {
+            Some(existing) if existing.updated_at_epoch_ms >= record.updated_at_epoch_ms => {}
+            _ => {
+                repaired.insert(record.key.clone(), record);
+            }
+        }
+    }
+
+    let mut records: Vec<_> = repaired.into_values().collect();
+    records.sort_by(|a, b| a.key.cmp(&b.key));
+    (issues, records)
+}
+
+fn is_valid_key(key: &str) -> bool {
+    !key.trim().is_empty() && !key.chars().any(|c| c.is_control() || c == '\u{FFFD}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entry(slot: &str, value: Value) -> RawSessionEntry {
+        RawSessionEntry {
+            slot: Some(slot.into()),
+            value: Ok(value),
+        }
+    }
+
+    fn options() -> FsckOptions {
+        FsckOptions {
+            max_skew_ms: 1_000,
+            tombstone_window_ms: Some(10_000),
+            default_tenant: None,
+        }
+    }
+
+    fn kinds(issues: &[FsckIssue]) -> Vec<FsckIssueKind> {
+        issues.iter().map(|issue| issue.kind).collect()
+    }
+
+    #[test]
+    fn detects_and_repairs_issues() {
+        let now = 100_000;
+        let entries = vec![
+            entry(
+                "a",
+                json!({"key": "a", "tenant": "t", "updated_at_epoch_ms": 10}),
+            ),
+            entry(
+                "a",
+                json!({"key": "a", "tenant": "t", "updated_at_epoch_ms": 20}),
+            ),
+            entry("b", json!({"key": "b", "updated_at_epoch_ms": 10})),
+            entry(
+                "c\u{FFFD}",
+                json!({"key": "c\u{FFFD}", "tenant": "t", "updated_at_epoch_ms": 10}),
+            ),
+            entry(
+                "d",
+                json!({"key": "d", "tenant": "t", "updated_at_epoch_ms": 500_000}),
+            ),
+            entry(
+                "e",
+                json!({"key": "e", "tenant": "t", "updated_at_epoch_ms": 10, "deleted_at_epoch_ms": 1}),
+            ),
+            RawSessionEntry {
+                slot: Some("f".into()),
+                value: Err("expected value".into()),
+            },
+        ];
+
+        let (issues, repaired) = check_entries(&entries, &options(), now);
+        assert_eq!(
+            kinds(&issues),
+            vec![
+                FsckIssueKind::DuplicateKey,
+                FsckIssueKind::MissingTenant,
+                FsckIssueKind::InvalidKey,
+                FsckIssueKind::ClockSkew,
+                FsckIssueKind::OrphanedTombstone,
+                FsckIssueKind::InvalidRecord,
+            ]
+        );
+        let keys: Vec<_> = repaired.iter().map(|r| r.key.as_str()).collect();
+        assert_eq!(keys, vec!["a", "d"]);
+        assert_eq!(repaired[0].updated_at_epoch_ms, 20);
+        assert_eq!(repaired[1].updated_at_epoch_ms, now);
+    }
+
+    #[test]
+    fn missing_tenant_is_backfilled_from_defaults() {
+        let mut opts = options();
+        opts.default_tenant = Some("dev".into());
+        let entries = vec![entry("b", json!({"key": "b", "updated_at_epoch_ms": 10}))];
+        let (issues, repaired) = check_entries(&entries, &opts, 100);
+        assert_eq!(kinds(&issues), vec![FsckIssueKind::MissingTenant]);
+        assert_eq!(repaired[0].tenant, "dev");
+    }
+}
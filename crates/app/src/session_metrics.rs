@@ -0,0 +1,481 @@
+//! Per-tenant session metrics behind `GET /metrics/sessions` (JSON) and `GET /metrics`
+//! (Prometheus text). [`MeteredSessionStore`] counts the writes that go through the configured
+//! store; active sessions are counted from the store when the metrics are read, so the gauge
+//! holds across restarts and instances. Resume latency is observed by the resume handler.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use crate::session::{
+    SessionFilter, SessionLease, SessionPage, SessionPageRequest, SessionRecord, SessionStore,
+    SessionUpsert,
+};
+
+/// Upper bounds (ms) of the resume latency histogram buckets.
+const RESUME_BUCKETS_MS: [u64; 11] = [5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10_000];
+/// Seconds `upserts_per_sec` is averaged over.
+const RATE_WINDOW_SECS: u64 = 60;
+
+/// Counters shared by the metered store, the resume handler and the metrics endpoints.
+#[derive(Clone, Default)]
+pub struct SessionMetrics {
+    tenants: Arc<Mutex<BTreeMap<String, TenantCounters>>>,
+}
+
+#[derive(Default)]
+struct TenantCounters {
+    upserts: u64,
+    puts: u64,
+    removes: u64,
+    purges: u64,
+    purged: u64,
+    upsert_rate: RateWindow,
+    resume_latency: Histogram,
+}
+
+/// Events per second over the last [`RATE_WINDOW_SECS`], kept as one bucket per second.
+struct RateWindow {
+    buckets: [u64; RATE_WINDOW_SECS as usize],
+    last_sec: u64,
+}
+
+impl Default for RateWindow {
+    fn default() -> Self {
+        Self {
+            buckets: [0; RATE_WINDOW_SECS as usize],
+            last_sec: 0,
+        }
+    }
+}
+
+impl RateWindow {
+    fn advance(&mut self, now_sec: u64) {
+        let elapsed = now_sec.saturating_sub(self.last_sec).min(RATE_WINDOW_SECS);
+        for sec in 1..=elapsed {
+            self.buckets[((self.last_sec + sec) % RATE_WINDOW_SECS) as usize] = 0;
+        }
+        self.last_sec = self.last_sec.max(now_sec);
+    }
+
+    fn add(&mut self, now_sec: u64) {
+        self.advance(now_sec);
+        self.buckets[(now_sec % RATE_WINDOW_SECS) as usize] += 1;
+    }
+
+    fn per_sec(&mut self, now_sec: u64) -> f64 {
+        self.advance(now_sec);
+        self.buckets.iter().sum::<u64>() as f64 / RATE_WINDOW_SECS as f64
+    }
+}
+
+#[derive(Default)]
+struct Histogram {
+    /// Observations per bucket of [`RESUME_BUCKETS_MS`], plus one for anything slower.
+    counts: [u64; RESUME_BUCKETS_MS.len() + 1],
+    sum_ms: f64,
+    max_ms: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, ms: f64) {
+        let bucket = RESUME_BUCKETS_MS
+            .iter()
+            .position(|bound| ms <= *bound as f64)
+            .unwrap_or(RESUME_BUCKETS_MS.len());
+        self.counts[bucket] += 1;
+        self.sum_ms += ms;
+        self.max_ms = self.max_ms.max(ms);
+    }
+
+    fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SessionMetricsReport {
+    pub tenants: BTreeMap<String, TenantSessionMetrics>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TenantSessionMetrics {
+    /// Live sessions in the store right now.
+    pub active: usize,
+    pub upserts: u64,
+    /// Upserts per second over the last minute.
+    pub upserts_per_sec: f64,
+    /// Records stored verbatim (imports, migrations, context upgrades).
+    pub puts: u64,
+    /// Sessions removed one by one: resumes, expiry and deletes.
+    pub removes: u64,
+    /// Purge calls that removed at least one of the tenant's sessions.
+    pub purges: u64,
+    /// Sessions those purges removed.
+    pub purged: u64,
+    pub resume_latency: LatencySummary,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LatencySummary {
+    pub count: u64,
+    pub sum_ms: f64,
+    pub max_ms: f64,
+    /// Cumulative counts per upper bound in ms, as in a Prometheus histogram.
+    pub buckets: BTreeMap<u64, u64>,
+}
+
+impl SessionMetrics {
+    fn with_tenant(&self, tenant: &str, update: impl FnOnce(&mut TenantCounters)) {
+        update(self.tenants.lock().entry(tenant.to_string()).or_default());
+    }
+
+    fn record_upsert(&self, tenant: &str) {
+        self.with_tenant(tenant, |counters| {
+            counters.upserts += 1;
+            counters.upsert_rate.add(now_sec());
+        });
+    }
+
+    fn record_purge(&self, removed: &BTreeMap<String, u64>) {
+        for (tenant, count) in removed {
+            self.with_tenant(tenant, |counters| {
+                counters.purges += 1;
+                counters.purged += count;
+            });
+        }
+    }
+
+    /// A resume of one of `tenant`'s sessions completed after `elapsed`.
+    pub fn observe_resume(&self, tenant: &str, elapsed: Duration) {
+        self.with_tenant(tenant, |counters| {
+            counters
+                .resume_latency
+                .observe(elapsed.as_secs_f64() * 1000.0);
+        });
+    }
+
+    /// The counters of every tenant seen so far, with active sessions counted from `store`.
+    pub fn report(&self, store: &dyn SessionStore) -> Result<SessionMetricsReport> {
+        let mut active: BTreeMap<String, usize> = BTreeMap::new();
+        store.scan(&SessionFilter::default(), &mut |record| {
+            if !record.is_deleted() {
+                *active.entry(record.tenant.clone()).or_default() += 1;
+            }
+        })?;
+        let now = now_sec();
+        let mut tenants = self.tenants.lock();
+        for tenant in active.keys() {
+            tenants.entry(tenant.clone()).or_default();
+        }
+        let report = tenants
+            .iter_mut()
+            .map(|(tenant, counters)| {
+                let mut cumulative = 0;
+                let buckets = RESUME_BUCKETS_MS
+                    .iter()
+                    .zip(counters.resume_latency.counts)
+                    .map(|(bound, count)| {
+                        cumulative += count;
+                        (*bound, cumulative)
+                    })
+                    .collect();
+                let metrics = TenantSessionMetrics {
+                    active: active.get(tenant).copied().unwrap_or_default(),
+                    upserts: counters.upserts,
+                    upserts_per_sec: counters.upsert_rate.per_sec(now),
+                    puts: counters.puts,
+                    removes: counters.removes,
+                    purges: counters.purges,
+                    purged: counters.purged,
+                    resume_latency: LatencySummary {
+                        count: counters.resume_latency.count(),
+                        sum_ms: counters.resume_latency.sum_ms,
+                        max_ms: counters.resume_latency.max_ms,
+                        buckets,
+                    },
+                };
+                (tenant.clone(), metrics)
+            })
+            .collect();
+        Ok(SessionMetricsReport { tenants: report })
+    }
+}
+
+/// Name, type, help text and per-tenant value of one Prometheus metric family.
+type Family = (
+    &'static str,
+    &'static str,
+    &'static str,
+    fn(&TenantSessionMetrics) -> String,
+);
+
+impl SessionMetricsReport {
+    /// The report in the Prometheus text exposition format.
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        let families: [Family; 7] = [
+            (
+                "greentic_sessions_active",
+                "gauge",
+                "Live sessions in the store.",
+                |m| m.active.to_string(),
+            ),
+            (
+                "greentic_session_upserts_total",
+                "counter",
+                "Session upserts.",
+                |m| m.upserts.to_string(),
+            ),
+            (
+                "greentic_session_upserts_per_second",
+                "gauge",
+                "Session upserts per second over the last minute.",
+                |m| m.upserts_per_sec.to_string(),
+            ),
+            (
+                "greentic_session_puts_total",
+                "counter",
+                "Session records stored verbatim.",
+                |m| m.puts.to_string(),
+            ),
+            (
+                "greentic_session_removes_total",
+                "counter",
+                "Sessions removed one by one.",
+                |m| m.removes.to_string(),
+            ),
+            (
+                "greentic_session_purges_total",
+                "counter",
+                "Purges that removed sessions.",
+                |m| m.purges.to_string(),
+            ),
+            (
+                "greentic_session_purged_total",
+                "counter",
+                "Sessions removed by purges.",
+                |m| m.purged.to_string(),
+            ),
+        ];
+        for (name, kind, help, value) in families {
+            let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} {kind}");
+            for (tenant, metrics) in &self.tenants {
+                let tenant = escape_label(tenant);
+                let _ = writeln!(out, "{name}{{tenant=\"{tenant}\"}} {}", value(metrics));
+            }
+        }
+
+        let name = "greentic_session_resume_latency_ms";
+        let _ = writeln!(
+            out,
+            "# HELP {name} Time to resume a session, in milliseconds.\n# TYPE {name} histogram"
+        );
+        for (tenant, metrics) in &self.tenants {
+            let tenant = escape_label(tenant);
+            let latency = &metrics.resume_latency;
+            for (bound, count) in &latency.buckets {
+                let _ = writeln!(
+                    out,
+                    "{name}_bucket{{tenant=\"{tenant}\",le=\"{bound}\"}} {count}"
+                );
+            }
+            let _ = writeln!(
+                out,
+                "{name}_bucket{{tenant=\"{tenant}\",le=\"+Inf\"}} {}",
+                latency.count
+            );
+            let _ = writeln!(out, "{name}_sum{{tenant=\"{tenant}\"}} {}", latency.sum_ms);
+            let _ = writeln!(out, "{name}_count{{tenant=\"{tenant}\"}} {}", latency.count);
+        }
+        out
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn now_sec() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Counts the writes that reach the wrapped store per tenant. Failed writes are not counted.
+pub struct MeteredSessionStore {
+    inner: Arc<dyn SessionStore>,
+    metrics: SessionMetrics,
+}
+
+impl MeteredSessionStore {
+    pub fn new(inner: Arc<dyn SessionStore>, metrics: SessionMetrics) -> Arc<Self> {
+        Arc::new(Self { inner, metrics })
+    }
+}
+
+impl SessionStore for MeteredSessionStore {
+    fn list(&self, filter: &SessionFilter) -> Result<Vec<SessionRecord>> {
+        self.inner.list(filter)
+    }
+
+    fn purge(&self, filter: &SessionFilter) -> Result<usize> {
+        let mut matched: BTreeMap<String, u64> = BTreeMap::new();
+        self.inner.scan(filter, &mut |record| {
+            *matched.entry(record.tenant.clone()).or_default() += 1;
+        })?;
+        let removed = self.inner.purge(filter)?;
+        if removed > 0 {
+            self.metrics.record_purge(&matched);
+        }
+        Ok(removed)
+    }
+
+    fn upsert(&self, payload: SessionUpsert) -> Result<SessionRecord> {
+        let record = self.inner.upsert(payload)?;
+        self.metrics.record_upsert(&record.tenant);
+        Ok(record)
+    }
+
+    fn find(&self, filter: &SessionFilter) -> Result<Option<SessionRecord>> {
+        self.inner.find(filter)
+    }
+
+    fn remove(&self, key: &str) -> Result<()> {
+        let existing = self.inner.get(key)?;
+        self.inner.remove(key)?;
+        if let Some(record) = existing {
+            self.metrics
+                .with_tenant(&record.tenant, |counters| counters.removes += 1);
+        }
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> Result<Option<SessionRecord>> {
+        self.inner.get(key)
+    }
+
+    fn put(&self, record: SessionRecord) -> Result<()> {
+        let tenant = record.tenant.clone();
+        self.inner.put(record)?;
+        self.metrics
+            .with_tenant(&tenant, |counters| counters.puts += 1);
+        Ok(())
+    }
+
+    fn restore(&self, key: &str) -> Result<Option<SessionRecord>> {
+        self.inner.restore(key)
+    }
+
+    fn finalize_deletions(&self) -> Result<usize> {
+        self.inner.finalize_deletions()
+    }
+
+    fn list_page(&self, filter: &SessionFilter, page: &SessionPageRequest) -> Result<SessionPage> {
+        self.inner.list_page(filter, page)
+    }
+
+    fn scan(&self, filter: &SessionFilter, visit: &mut dyn FnMut(&SessionRecord)) -> Result<()> {
+        self.inner.scan(filter, visit)
+    }
+
+    fn ping(&self) -> Result<()> {
+        self.inner.ping()
+    }
+
+    fn try_lock(&self, key: &str, ttl: Duration) -> Result<Option<SessionLease>> {
+        self.inner.try_lock(key, ttl)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::session::InMemorySessionStore;
+
+    fn upsert(key: &str, tenant: &str) -> SessionUpsert {
+        SessionUpsert {
+            key: key.into(),
+            tenant: tenant.into(),
+            team: None,
+            user: Some(format!("user-{key}")),
+            flow_id: Some("support".into()),
+            node_id: None,
+            context: Value::Null,
+            pack_id: None,
+            flow_version: None,
+            locale: None,
+            ttl_ms: None,
+            pack_generation: None,
+            runner_target: None,
+        }
+    }
+
+    #[test]
+    fn counts_writes_per_tenant() {
+        let metrics = SessionMetrics::default();
+        let store = MeteredSessionStore::new(InMemorySessionStore::new(), metrics.clone());
+        for (key, tenant) in [
+            ("a1", "acme"),
+            ("a2", "acme"),
+            ("a3", "acme"),
+            ("g1", "globex"),
+        ] {
+            store.upsert(upsert(key, tenant)).unwrap();
+        }
+        store.remove("a1").unwrap();
+        store.remove("missing").unwrap();
+        let purged = store
+            .purge(&SessionFilter::new(Some("acme".into()), None, None))
+            .unwrap();
+        assert_eq!(purged, 2);
+        metrics.observe_resume("globex", Duration::from_millis(30));
+        metrics.observe_resume("globex", Duration::from_millis(700));
+
+        let report = metrics.report(store.as_ref()).unwrap();
+        let acme = &report.tenants["acme"];
+        assert_eq!((acme.active, acme.upserts, acme.removes), (0, 3, 1));
+        assert_eq!((acme.purges, acme.purged), (1, 2));
+        assert!((acme.upserts_per_sec - 3.0 / 60.0).abs() < 1e-9);
+        let globex = &report.tenants["globex"];
+        assert_eq!(globex.active, 1);
+        assert_eq!(globex.resume_latency.count, 2);
+        assert_eq!(globex.resume_latency.buckets[&25], 0);
+        assert_eq!(globex.resume_latency.buckets[&50], 1);
+        assert_eq!(globex.resume_latency.buckets[&1000], 2);
+
+        let text = report.to_prometheus();
+        assert!(
+            text.contains("greentic_sessions_active{tenant=\"globex\"} 1\n"),
+            "{text}"
+        );
+        assert!(text.contains("greentic_session_purged_total{tenant=\"acme\"} 2\n"));
+        assert!(text.contains(
+            "greentic_session_resume_latency_ms_bucket{tenant=\"globex\",le=\"+Inf\"} 2\n"
+        ));
+    }
+
+    #[test]
+    fn upsert_rate_forgets_old_seconds() {
+        let mut rate = RateWindow::default();
+        rate.add(1_000);
+        rate.add(1_000);
+        rate.add(1_030);
+        assert!((rate.per_sec(1_030) - 3.0 / 60.0).abs() < 1e-9);
+        assert!((rate.per_sec(1_061) - 1.0 / 60.0).abs() < 1e-9);
+        assert_eq!(rate.per_sec(2_000), 0.0);
+    }
+}
@@ -0,0 +1,267 @@
+//! Stale session detection (`[sessions.nudge]`): sessions idle longer than their flow's
+//! threshold get a nudge event, emitted through the runner proxy or POSTed to a webhook, so
+//! packs can exercise their abandonment handling. Each nudge is appended to the session's
+//! `nudges`; the record is written back verbatim, so idle time keeps counting, and the next
+//! real write clears them.
+
+use std::collections::BTreeMap;
+
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::session::{SessionFilter, SessionNudge, SessionRecord, SessionStore};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NudgeConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// How often sessions are checked.
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    /// Idle time before a nudge for flows not listed in `flows`; unset, only listed flows
+    /// are nudged.
+    #[serde(default)]
+    pub idle_secs: Option<u64>,
+    /// Idle time before a nudge, in seconds, keyed by flow id.
+    #[serde(default)]
+    pub flows: BTreeMap<String, u64>,
+    /// `type` of the emitted event.
+    #[serde(default = "default_event")]
+    pub event: String,
+    /// Nudges per idle stretch; each waits the threshold again after the previous one.
+    #[serde(default = "default_max_nudges")]
+    pub max_nudges: usize,
+    /// POST nudges here instead of emitting them through the runner proxy.
+    #[serde(default)]
+    pub webhook: Option<String>,
+}
+
+impl Default for NudgeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: default_interval_secs(),
+            idle_secs: None,
+            flows: BTreeMap::new(),
+            event: default_event(),
+            max_nudges: default_max_nudges(),
+            webhook: None,
+        }
+    }
+}
+
+fn default_interval_secs() -> u64 {
+    30
+}
+
+fn default_event() -> String {
+    "session.nudge".into()
+}
+
+fn default_max_nudges() -> usize {
+    1
+}
+
+impl NudgeConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.event.trim().is_empty() {
+            bail!("[sessions.nudge].event must not be empty");
+        }
+        if self.max_nudges == 0 {
+            bail!("[sessions.nudge].max_nudges must be at least 1");
+        }
+        if self.idle_secs == Some(0) || self.flows.values().any(|secs| *secs == 0) {
+            bail!("[sessions.nudge] idle thresholds must be at least one second");
+        }
+        Ok(())
+    }
+
+    /// Idle time after which a session of `flow` is nudged, if it is nudged at all.
+    fn threshold_ms(&self, flow: &str) -> Option<u64> {
+        self.flows
+            .get(flow)
+            .copied()
+            .or(self.idle_secs)
+            .map(|secs| secs.saturating_mul(1000))
+    }
+}
+
+/// A session due a nudge and how long it has been idle.
+pub struct DueNudge {
+    pub session: SessionRecord,
+    pub idle_ms: u64,
+}
+
+impl DueNudge {
+    /// Event sent for this nudge.
+    pub fn payload(&self, config: &NudgeConfig) -> Value {
+        json!({
+            "type": config.event,
+            "session": self.session.key,
+            "flow": self.session.flow_id,
+            "node": self.session.node_id,
+            "idle_ms": self.idle_ms,
+            "nudge": self.session.nudges.len() + 1,
+        })
+    }
+}
+
+/// Live sessions with a flow whose threshold has passed since their last write (or their
+/// last nudge) and that have not had `max_nudges` yet.
+pub fn due(store: &dyn SessionStore, config: &NudgeConfig, now_ms: u64) -> Result<Vec<DueNudge>> {
+    let filter = SessionFilter {
+        live_at_ms: Some(now_ms),
+        ..SessionFilter::default()
+    };
+    let mut due = Vec::new();
+    store.scan(&filter, &mut |record| {
+        if is_due(record, config, now_ms) {
+            due.push(DueNudge {
+                session: record.clone(),
+                idle_ms: now_ms.saturating_sub(record.updated_at_epoch_ms),
+            });
+        }
+    })?;
+    due.sort_by(|a, b| a.session.key.cmp(&b.session.key));
+    Ok(due)
+}
+
+fn is_due(record: &SessionRecord, config: &NudgeConfig, now_ms: u64) -> bool {
+    let Some(threshold) = record
+        .flow_id
+        .as_deref()
+        .and_then(|flow| config.threshold_ms(flow))
+    else {
+        return false;
+    };
+    let since = record
+        .nudges
+        .last()
+        .map_or(record.updated_at_epoch_ms, |nudge| nudge.at_epoch_ms);
+    record.nudges.len() < config.max_nudges && now_ms.saturating_sub(since) >= threshold
+}
+
+/// Whether `current` is still the session `due` was computed from: a write in between
+/// means it is no longer idle, or another instance already nudged it.
+pub fn unchanged(due: &DueNudge, current: &SessionRecord) -> bool {
+    current.updated_at_epoch_ms == due.session.updated_at_epoch_ms
+        && current.nudges == due.session.nudges
+}
+
+/// Append the nudge to `session` and store it without touching its timestamps.
+pub fn record(
+    store: &dyn SessionStore,
+    mut session: SessionRecord,
+    config: &NudgeConfig,
+    idle_ms: u64,
+    now_ms: u64,
+) -> Result<()> {
+    session.nudges.push(SessionNudge {
+        at_epoch_ms: now_ms,
+        idle_ms,
+        event: config.event.clone(),
+    });
+    store.put(session)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::InMemorySessionStore;
+
+    fn seed(store: &dyn SessionStore, key: &str, flow: Option<&str>, updated_at: u64) {
+        store
+            .put(SessionRecord {
+                key: key.into(),
+                tenant: "dev".into(),
+                flow_id: flow.map(str::to_string),
+                updated_at_epoch_ms: updated_at,
+                ..SessionRecord::default()
+            })
+            .unwrap();
+    }
+
+    fn due_keys(store: &dyn SessionStore, config: &NudgeConfig, now_ms: u64) -> Vec<String> {
+        due(store, config, now_ms)
+            .unwrap()
+            .into_iter()
+            .map(|due| due.session.key)
+            .collect()
+    }
+
+    #[test]
+    fn uses_per_flow_thresholds_with_a_default() {
+        let store = InMemorySessionStore::new();
+        seed(store.as_ref(), "checkout", Some("checkout"), 0);
+        seed(store.as_ref(), "support", Some("support"), 0);
+        seed(store.as_ref(), "flowless", None, 0);
+        let mut config = NudgeConfig {
+            flows: BTreeMap::from([("checkout".into(), 60)]),
+            ..NudgeConfig::default()
+        };
+        assert!(due_keys(store.as_ref(), &config, 59_999).is_empty());
+        assert_eq!(due_keys(store.as_ref(), &config, 60_000), ["checkout"]);
+
+        config.idle_secs = Some(600);
+        assert_eq!(due_keys(store.as_ref(), &config, 60_000), ["checkout"]);
+        assert_eq!(
+            due_keys(store.as_ref(), &config, 600_000),
+            ["checkout", "support"]
+        );
+    }
+
+    #[test]
+    fn repeats_up_to_max_nudges_one_threshold_apart() {
+        let store = InMemorySessionStore::new();
+        seed(store.as_ref(), "a", Some("checkout"), 0);
+        let config = NudgeConfig {
+            idle_secs: Some(10),
+            max_nudges: 2,
+            ..NudgeConfig::default()
+        };
+        let first = due(store.as_ref(), &config, 10_000).unwrap().remove(0);
+        assert_eq!(first.payload(&config)["nudge"], 1);
+        record(
+            store.as_ref(),
+            first.session.clone(),
+            &config,
+            first.idle_ms,
+            10_000,
+        )
+        .unwrap();
+        let current = store.get("a").unwrap().unwrap();
+        assert!(!unchanged(&first, &current));
+        assert_eq!(current.updated_at_epoch_ms, 0, "idle time keeps counting");
+
+        assert!(due_keys(store.as_ref(), &config, 19_999).is_empty());
+        let second = due(store.as_ref(), &config, 20_000).unwrap().remove(0);
+        assert_eq!(
+            (second.idle_ms, second.payload(&config)["nudge"].clone()),
+            (20_000, json!(2))
+        );
+        record(store.as_ref(), second.session, &config, 20_000, 20_000).unwrap();
+        assert!(due_keys(store.as_ref(), &config, 1_000_000).is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_settings() {
+        for config in [
+            NudgeConfig {
+                event: " ".into(),
+                ..NudgeConfig::default()
+            },
+            NudgeConfig {
+                max_nudges: 0,
+                ..NudgeConfig::default()
+            },
+            NudgeConfig {
+                flows: BTreeMap::from([("checkout".into(), 0)]),
+                ..NudgeConfig::default()
+            },
+        ] {
+            assert!(config.validate().is_err());
+        }
+        assert!(NudgeConfig::default().validate().is_ok());
+    }
+}
@@ -0,0 +1,154 @@
+//! Per-tenant session quotas. `[tenants.<id>].max_sessions`, else `[sessions].max_per_tenant`,
+//! caps how many live sessions a tenant holds; `POST /sessions` refuses to create one more with
+//! `429` and a `Retry-After` hint, and `GET /tenants/{tenant}/usage` reports the counts.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use crate::session::{SessionFilter, SessionStore};
+
+/// Retry hint when none of the tenant's sessions expires on its own.
+pub const DEFAULT_RETRY_AFTER_SECS: u64 = 60;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TenantUsage {
+    pub tenant: String,
+    /// Live sessions: tombstoned and expired ones do not count.
+    pub sessions: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining: Option<usize>,
+    /// When the first of the tenant's sessions with a `ttl_ms` runs out.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_expiry_at_epoch_ms: Option<u64>,
+}
+
+impl TenantUsage {
+    pub fn measure(
+        store: &dyn SessionStore,
+        tenant: &str,
+        limit: Option<usize>,
+        now_ms: u64,
+    ) -> Result<Self> {
+        let filter = SessionFilter::new(Some(tenant.to_string()), None, None).live_at(now_ms);
+        let (mut sessions, mut next_expiry) = (0, None::<u64>);
+        store.scan(&filter, &mut |record| {
+            sessions += 1;
+            if let Some(ttl) = record.ttl_ms {
+                let expiry = record.updated_at_epoch_ms.saturating_add(ttl);
+                next_expiry = Some(next_expiry.map_or(expiry, |next| next.min(expiry)));
+            }
+        })?;
+        Ok(Self {
+            tenant: tenant.to_string(),
+            sessions,
+            limit,
+            remaining: limit.map(|limit| limit.saturating_sub(sessions)),
+            next_expiry_at_epoch_ms: next_expiry,
+        })
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.limit.is_some_and(|limit| self.sessions >= limit)
+    }
+
+    /// Seconds until a slot is expected to free up: when the next session expires, or
+    /// [`DEFAULT_RETRY_AFTER_SECS`] when none will by itself.
+    pub fn retry_after_secs(&self, now_ms: u64) -> u64 {
+        self.next_expiry_at_epoch_ms
+            .map(|expiry| expiry.saturating_sub(now_ms).div_ceil(1000).max(1))
+            .unwrap_or(DEFAULT_RETRY_AFTER_SECS)
+    }
+}
+
+/// Serializes quota checks with the write they guard, so concurrent creates on this bridge
+/// cannot overshoot the limit. Bridges sharing a store each check on their own.
+#[derive(Clone, Default)]
+pub struct SessionQuotas {
+    gate: Arc<Mutex<()>>,
+}
+
+impl SessionQuotas {
+    /// Run `write` unless it would create a session beyond `limit` for `tenant`; replacing a
+    /// live session of the tenant is always allowed. Returns the usage when refused.
+    pub fn admit<T>(
+        &self,
+        store: &dyn SessionStore,
+        tenant: &str,
+        key: &str,
+        limit: Option<usize>,
+        now_ms: u64,
+        write: impl FnOnce() -> T,
+    ) -> Result<Result<T, TenantUsage>> {
+        if limit.is_none() {
+            return Ok(Ok(write()));
+        }
+        let _gate = self.gate.lock();
+        let replaces = store
+            .get(key)?
+            .is_some_and(|record| record.tenant == tenant && !record.is_expired(now_ms));
+        if !replaces {
+            let usage = TenantUsage::measure(store, tenant, limit, now_ms)?;
+            if usage.is_exhausted() {
+                return Ok(Err(usage));
+            }
+        }
+        Ok(Ok(write()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{InMemorySessionStore, SessionUpsert};
+    use serde_json::Value;
+
+    fn upsert(key: &str, tenant: &str, ttl_ms: Option<u64>) -> SessionUpsert {
+        SessionUpsert {
+            key: key.into(),
+            tenant: tenant.into(),
+            team: None,
+            user: Some("u".into()),
+            flow_id: None,
+            node_id: None,
+            context: Value::Null,
+            pack_id: None,
+            flow_version: None,
+            locale: None,
+            ttl_ms,
+            pack_generation: None,
+            runner_target: None,
+        }
+    }
+
+    #[test]
+    fn refuses_new_sessions_past_the_limit_but_allows_replacing() {
+        let store = InMemorySessionStore::new();
+        let quotas = SessionQuotas::default();
+        let admit = |key: &str, tenant: &str, ttl_ms| {
+            quotas
+                .admit(store.as_ref(), tenant, key, Some(2), 0, || {
+                    store.upsert(upsert(key, tenant, ttl_ms)).unwrap()
+                })
+                .unwrap()
+        };
+        assert!(admit("a", "acme", None).is_ok());
+        assert!(admit("b", "acme", Some(90_000)).is_ok());
+        assert!(admit("c", "globex", None).is_ok());
+        assert!(admit("a", "acme", None).is_ok(), "replacing stays allowed");
+
+        let refused = admit("c2", "acme", None).unwrap_err();
+        assert_eq!((refused.sessions, refused.remaining), (2, Some(0)));
+        let expiry = refused.next_expiry_at_epoch_ms.unwrap();
+        assert_eq!(refused.retry_after_secs(expiry - 1_500), 2);
+        assert!(store.get("c2").unwrap().is_none());
+
+        let usage = TenantUsage::measure(store.as_ref(), "acme", None, expiry).unwrap();
+        assert_eq!(usage.sessions, 1, "expired sessions free their slot");
+        assert_eq!(usage.retry_after_secs(expiry), DEFAULT_RETRY_AFTER_SECS);
+    }
+}
@@ -0,0 +1,279 @@
+//! Point-in-time copies of the whole session store behind `POST /sessions/snapshot` and
+//! `POST /sessions/restore`. A snapshot is a `sessions-<epoch ms>.jsonl` file under
+//! `[sessions].snapshot_dir`, in the `sessions export` format, so it can also be loaded with
+//! `sessions import` or checked in as a fixture that e2e tests restore before each run.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{BufReader, BufWriter};
+
+use anyhow::{Context, Result, anyhow, bail};
+use camino::Utf8PathBuf;
+use serde::Serialize;
+
+use crate::path_safety::normalize_under_root;
+use crate::session::{SessionFilter, SessionStore};
+use crate::session_transfer::{export_sessions, read_export};
+
+const PREFIX: &str = "sessions-";
+const EXTENSION: &str = ".jsonl";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SnapshotInfo {
+    pub name: String,
+    pub created_at_ms: u64,
+    pub sessions: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct RestoreReport {
+    pub snapshot: String,
+    /// Sessions written back from the snapshot.
+    pub restored: usize,
+    /// Sessions the store held that the snapshot did not, now removed.
+    pub removed: Vec<String>,
+}
+
+pub struct SnapshotDir {
+    dir: Utf8PathBuf,
+}
+
+impl SnapshotDir {
+    pub fn new(root: Utf8PathBuf, dir: Utf8PathBuf) -> Result<Self> {
+        let root = root
+            .as_std_path()
+            .canonicalize()
+            .with_context(|| format!("failed to canonicalize snapshot root {root}"))?;
+        let safe_dir = normalize_under_root(&root, dir.as_std_path())?;
+        let dir = Utf8PathBuf::from_path_buf(safe_dir)
+            .map_err(|_| anyhow!("normalized snapshot dir is not valid UTF-8"))?;
+        Ok(Self { dir })
+    }
+
+    /// Write every live session in `store` to a new snapshot stamped `now_ms`. The file is
+    /// renamed into place once complete, so a listed snapshot is never partial.
+    pub fn create(&self, store: &dyn SessionStore, now_ms: u64) -> Result<SnapshotInfo> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("failed to create snapshot dir {}", self.dir))?;
+        let mut created_at_ms = now_ms;
+        while self.path_for(created_at_ms).exists() {
+            created_at_ms += 1;
+        }
+        let name = format!("{PREFIX}{created_at_ms}{EXTENSION}");
+        let path = self.dir.join(&name);
+        let partial = self.dir.join(format!(".{name}.partial"));
+        let written = (|| {
+            let mut out = BufWriter::new(fs::File::create(&partial)?);
+            let written = export_sessions(store, &SessionFilter::default(), &mut out)?;
+            out.into_inner()
+                .map_err(|err| err.into_error())?
+                .sync_all()?;
+            fs::rename(&partial, &path)?;
+            anyhow::Ok(written)
+        })();
+        let sessions = match written {
+            Ok(sessions) => sessions,
+            Err(err) => {
+                let _ = fs::remove_file(&partial);
+                return Err(err.context(format!("failed to write snapshot {path}")));
+            }
+        };
+        Ok(SnapshotInfo {
+            bytes: fs::metadata(&path)?.len(),
+            name,
+            created_at_ms,
+            sessions,
+        })
+    }
+
+    /// Snapshots on disk, oldest first.
+    pub fn list(&self) -> Result<Vec<SnapshotInfo>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err).with_context(|| format!("failed to read {}", self.dir)),
+        };
+        let mut snapshots = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let Some(created_at_ms) = created_at(&name) else {
+                continue;
+            };
+            let contents = fs::read_to_string(entry.path())?;
+            snapshots.push(SnapshotInfo {
+                sessions: contents
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .count(),
+                bytes: contents.len() as u64,
+                name,
+                created_at_ms,
+            });
+        }
+        snapshots.sort_by_key(|snapshot| snapshot.created_at_ms);
+        Ok(snapshots)
+    }
+
+    /// Make `store` hold exactly the sessions of snapshot `name` (the newest when `None`):
+    /// every snapshot record is written back verbatim, sessions created since are removed.
+    /// `allow_removal` sees the number of removals first and may refuse the restore.
+    pub fn restore(
+        &self,
+        store: &dyn SessionStore,
+        name: Option<&str>,
+        allow_removal: impl FnOnce(usize) -> bool,
+    ) -> Result<Option<RestoreReport>> {
+        let name = match name {
+            Some(name) if created_at(name).is_none() => bail!("{name:?} is not a snapshot name"),
+            Some(name) => name.to_string(),
+            None => match self.list()?.pop() {
+                Some(latest) => latest.name,
+                None => bail!("no snapshots in {}", self.dir),
+            },
+        };
+        let path = self.dir.join(&name);
+        let file = fs::File::open(&path).with_context(|| format!("snapshot {name} not found"))?;
+        let records = read_export(BufReader::new(file))
+            .with_context(|| format!("snapshot {name} is corrupt"))?;
+        let keep: BTreeSet<&str> = records.iter().map(|record| record.key.as_str()).collect();
+        let mut removed = Vec::new();
+        store.scan(&SessionFilter::default(), &mut |record| {
+            if !keep.contains(record.key.as_str()) {
+                removed.push(record.key.clone());
+            }
+        })?;
+        removed.sort();
+        if !allow_removal(removed.len()) {
+            return Ok(None);
+        }
+        for key in &removed {
+            store
+                .remove(key)
+                .with_context(|| format!("failed to remove session {key}"))?;
+        }
+        let restored = records.len();
+        for record in records {
+            let key = record.key.clone();
+            store
+                .put(record)
+                .with_context(|| format!("failed to restore session {key}"))?;
+        }
+        Ok(Some(RestoreReport {
+            snapshot: name,
+            restored,
+            removed,
+        }))
+    }
+
+    fn path_for(&self, created_at_ms: u64) -> Utf8PathBuf {
+        self.dir.join(format!("{PREFIX}{created_at_ms}{EXTENSION}"))
+    }
+}
+
+fn created_at(name: &str) -> Option<u64> {
+    name.strip_prefix(PREFIX)?
+        .strip_suffix(EXTENSION)?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    use crate::session::{InMemorySessionStore, SessionUpsert};
+
+    fn upsert(store: &dyn SessionStore, key: &str, node: &str) {
+        store
+            .upsert(SessionUpsert {
+                key: key.into(),
+                tenant: "dev".into(),
+                team: None,
+                user: Some(format!("user-{key}")),
+                flow_id: Some("support".into()),
+                node_id: Some(node.into()),
+                context: Value::Null,
+                pack_id: None,
+                flow_version: None,
+                locale: None,
+                ttl_ms: None,
+                pack_generation: None,
+                runner_target: None,
+            })
+            .unwrap();
+    }
+
+    fn snapshot_dir(tmp: &tempfile::TempDir) -> SnapshotDir {
+        SnapshotDir::new(
+            Utf8PathBuf::from_path_buf(tmp.path().to_path_buf()).unwrap(),
+            Utf8PathBuf::from("snapshots"),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn restore_returns_the_store_to_the_snapshot() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = snapshot_dir(&tmp);
+        let store = InMemorySessionStore::new();
+        upsert(store.as_ref(), "a", "start");
+        upsert(store.as_ref(), "b", "start");
+        let first = dir.create(store.as_ref(), 1_000).unwrap();
+        assert_eq!(
+            (first.name.as_str(), first.sessions),
+            ("sessions-1000.jsonl", 2)
+        );
+        let before = serde_json::to_value(store.get("a").unwrap()).unwrap();
+
+        upsert(store.as_ref(), "a", "later");
+        store.remove("b").unwrap();
+        upsert(store.as_ref(), "c", "start");
+        let second = dir.create(store.as_ref(), 1_000).unwrap();
+        assert_eq!(second.created_at_ms, 1_001);
+        assert_eq!(dir.list().unwrap(), vec![first.clone(), second.clone()]);
+
+        assert!(
+            dir.restore(store.as_ref(), Some(&first.name), |removed| removed == 0)
+                .unwrap()
+                .is_none()
+        );
+        assert!(store.get("c").unwrap().is_some());
+        let report = dir
+            .restore(store.as_ref(), Some(&first.name), |_| true)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (report.restored, report.removed),
+            (2, vec!["c".to_string()])
+        );
+        assert_eq!(
+            serde_json::to_value(store.get("a").unwrap()).unwrap(),
+            before
+        );
+        assert!(store.get("b").unwrap().is_some());
+
+        let report = dir
+            .restore(store.as_ref(), None, |_| true)
+            .unwrap()
+            .unwrap();
+        assert_eq!(report.snapshot, second.name);
+        assert!(store.get("b").unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_unknown_and_foreign_names() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = snapshot_dir(&tmp);
+        let store = InMemorySessionStore::new();
+        assert!(dir.restore(store.as_ref(), None, |_| true).is_err());
+        for name in ["../sessions.json", "sessions-1.jsonl", "sessions-x.jsonl"] {
+            assert!(
+                dir.restore(store.as_ref(), Some(name), |_| true).is_err(),
+                "{name}"
+            );
+        }
+    }
+}
@@ -0,0 +1,259 @@
+//! Aggregate snapshot of stored sessions for `GET /sessions/stats`: who owns them, how stale
+//! they are and which flow node they are parked on, without shipping every record.
+
+use std::{collections::BTreeMap, time::Instant};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::session::{SessionFilter, SessionRecord, SessionStore};
+
+/// Upper bounds (exclusive) of the age buckets, by time since `updated_at_epoch_ms`.
+const AGE_BUCKETS: &[(&str, u64)] = &[
+    ("1m", 60_000),
+    ("1h", 60 * 60_000),
+    ("1d", 24 * 60 * 60_000),
+    ("7d", 7 * 24 * 60 * 60_000),
+    ("30d", 30 * 24 * 60 * 60_000),
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SessionStats {
+    pub total: usize,
+    pub needs_upgrade: usize,
+    pub tenants: Vec<TenantCount>,
+    pub flows: Vec<FlowCount>,
+    pub age: Vec<AgeBucket>,
+    /// Oldest `updated_at_epoch_ms` among the counted sessions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub oldest_updated_at_epoch_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TenantCount {
+    pub tenant: String,
+    pub count: usize,
+    /// Per team; `team: null` counts sessions without one.
+    pub teams: Vec<TeamCount>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TeamCount {
+    pub team: Option<String>,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FlowCount {
+    pub flow_id: Option<String>,
+    pub count: usize,
+    /// Sessions currently parked on each node of the flow.
+    pub nodes: Vec<NodeCount>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NodeCount {
+    pub node_id: Option<String>,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AgeBucket {
+    /// `1m`, `1h`, `1d`, `7d`, `30d` or `older`.
+    pub bucket: &'static str,
+    /// Exclusive upper bound of the bucket; `None` for `older`.
+    pub max_age_ms: Option<u64>,
+    pub count: usize,
+}
+
+/// Backend reachability reported alongside the counts.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StoreHealth {
+    pub backend: String,
+    pub healthy: bool,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl StoreHealth {
+    pub fn probe(backend: impl Into<String>, store: &dyn SessionStore) -> Self {
+        let started = Instant::now();
+        let result = store.ping();
+        Self {
+            backend: backend.into(),
+            healthy: result.is_ok(),
+            latency_ms: started.elapsed().as_millis() as u64,
+            error: result.err().map(|err| format!("{err:#}")),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Tally {
+    total: usize,
+    needs_upgrade: usize,
+    tenants: BTreeMap<String, BTreeMap<Option<String>, usize>>,
+    flows: BTreeMap<Option<String>, BTreeMap<Option<String>, usize>>,
+    age: [usize; AGE_BUCKETS.len() + 1],
+    oldest: Option<u64>,
+}
+
+impl Tally {
+    fn add(&mut self, record: &SessionRecord, now_ms: u64) {
+        self.total += 1;
+        if record.needs_upgrade {
+            self.needs_upgrade += 1;
+        }
+        *self
+            .tenants
+            .entry(record.tenant.clone())
+            .or_default()
+            .entry(record.team.clone())
+            .or_default() += 1;
+        *self
+            .flows
+            .entry(record.flow_id.clone())
+            .or_default()
+            .entry(record.node_id.clone())
+            .or_default() += 1;
+        let age = now_ms.saturating_sub(record.updated_at_epoch_ms);
+        let bucket = AGE_BUCKETS
+            .iter()
+            .position(|(_, max)| age < *max)
+            .unwrap_or(AGE_BUCKETS.len());
+        self.age[bucket] += 1;
+        self.oldest = Some(self.oldest.map_or(record.updated_at_epoch_ms, |oldest| {
+            oldest.min(record.updated_at_epoch_ms)
+        }));
+    }
+
+    fn finish(self) -> SessionStats {
+        SessionStats {
+            total: self.total,
+            needs_upgrade: self.needs_upgrade,
+            tenants: self
+                .tenants
+                .into_iter()
+                .map(|(tenant, teams)| TenantCount {
+                    tenant,
+                    count: teams.values().sum(),
+                    teams: teams
+                        .into_iter()
+                        .map(|(team, count)| TeamCount { team, count })
+                        .collect(),
+                })
+                .collect(),
+            flows: self
+                .flows
+                .into_iter()
+                .map(|(flow_id, nodes)| FlowCount {
+                    flow_id,
+                    count: nodes.values().sum(),
+                    nodes: nodes
+                        .into_iter()
+                        .map(|(node_id, count)| NodeCount { node_id, count })
+                        .collect(),
+                })
+                .collect(),
+            age: AGE_BUCKETS
+                .iter()
+                .map(|(bucket, max)| (*bucket, Some(*max)))
+                .chain([("older", None)])
+                .zip(self.age)
+                .map(|((bucket, max_age_ms), count)| AgeBucket {
+                    bucket,
+                    max_age_ms,
+                    count,
+                })
+                .collect(),
+            oldest_updated_at_epoch_ms: self.oldest,
+        }
+    }
+}
+
+/// Count the live sessions matching `filter` in a single pass over the store.
+pub fn collect_stats(
+    store: &dyn SessionStore,
+    filter: &SessionFilter,
+    now_ms: u64,
+) -> Result<SessionStats> {
+    let mut tally = Tally::default();
+    store.scan(filter, &mut |record| tally.add(record, now_ms))?;
+    Ok(tally.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{InMemorySessionStore, SoftDeleteSessionStore};
+
+    const NOW: u64 = 10_000_000_000;
+
+    fn record(
+        key: &str,
+        tenant: &str,
+        team: Option<&str>,
+        node: &str,
+        age_ms: u64,
+    ) -> SessionRecord {
+        SessionRecord {
+            key: key.into(),
+            tenant: tenant.into(),
+            team: team.map(str::to_string),
+            flow_id: Some("support".into()),
+            node_id: Some(node.into()),
+            updated_at_epoch_ms: NOW - age_ms,
+            ..SessionRecord::default()
+        }
+    }
+
+    #[test]
+    fn counts_by_owner_node_and_age_skipping_tombstones() {
+        let store = SoftDeleteSessionStore::new(InMemorySessionStore::new(), 60_000);
+        store
+            .put(record("a", "acme", Some("ops"), "triage", 5_000))
+            .unwrap();
+        store
+            .put(record("b", "acme", None, "triage", 2 * 60_000))
+            .unwrap();
+        store
+            .put(record("c", "acme", Some("ops"), "escalate", 3 * 86_400_000))
+            .unwrap();
+        store
+            .put(record("d", "globex", None, "triage", 60 * 86_400_000))
+            .unwrap();
+        store.put(record("e", "globex", None, "triage", 0)).unwrap();
+        store.remove("e").unwrap();
+
+        let stats = collect_stats(store.as_ref(), &SessionFilter::default(), NOW).unwrap();
+        assert_eq!(stats.total, 4);
+        assert_eq!(stats.tenants[0].tenant, "acme");
+        assert_eq!(stats.tenants[0].count, 3);
+        assert_eq!(
+            stats.tenants[0].teams,
+            vec![
+                TeamCount {
+                    team: None,
+                    count: 1
+                },
+                TeamCount {
+                    team: Some("ops".into()),
+                    count: 2
+                },
+            ]
+        );
+        assert_eq!(stats.flows.len(), 1);
+        assert_eq!(stats.flows[0].nodes[1].node_id.as_deref(), Some("triage"));
+        assert_eq!(stats.flows[0].nodes[1].count, 3);
+        let ages: Vec<_> = stats.age.iter().map(|bucket| bucket.count).collect();
+        assert_eq!(ages, vec![1, 1, 0, 1, 0, 1]);
+        assert_eq!(
+            stats.oldest_updated_at_epoch_ms,
+            Some(NOW - 60 * 86_400_000)
+        );
+
+        let acme = SessionFilter::new(Some("acme".into()), Some("ops".into()), None);
+        assert_eq!(collect_stats(store.as_ref(), &acme, NOW).unwrap().total, 2);
+    }
+}
@@ -0,0 +1,280 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::session::{SessionFilter, SessionStore, SessionUpsert, current_timestamp_ms};
+
+#[derive(Debug, Clone)]
+pub struct StressOptions {
+    pub threads: usize,
+    /// Total operations across all threads.
+    pub ops: usize,
+    /// Distinct session keys per thread; smaller values mean more overwrites.
+    pub key_space: usize,
+    pub seed: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StressOp {
+    Upsert,
+    Find,
+    Purge,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LatencySummary {
+    pub op: StressOp,
+    pub count: usize,
+    pub p50_us: u64,
+    pub p95_us: u64,
+    pub p99_us: u64,
+    pub max_us: u64,
+    /// Counts per bucket, labelled by upper bound (`<100us`, `<1ms`, ...).
+    pub histogram: Vec<(String, usize)>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StressReport {
+    pub threads: usize,
+    pub ops: usize,
+    pub elapsed_ms: u128,
+    pub latencies: Vec<LatencySummary>,
+    pub violations: Vec<String>,
+}
+
+const BUCKETS_US: &[(u64, &str)] = &[
+    (100, "<100us"),
+    (1_000, "<1ms"),
+    (10_000, "<10ms"),
+    (100_000, "<100ms"),
+    (u64::MAX, ">=100ms"),
+];
+
+/// Drive a mixed upsert/find/purge workload against `store` from several threads.
+///
+/// Each thread works on its own tenant and keeps a local model of what it wrote, so lost
+/// updates (a find returning a stale version) and inconsistent purge counts are detectable
+/// without cross-thread coordination. Stress tenants are purged once the run finishes.
+pub fn run_stress(store: Arc<dyn SessionStore>, options: &StressOptions) -> Result<StressReport> {
+    let threads = options.threads.max(1);
+    let per_thread = options.ops.div_ceil(threads);
+    let run_id = format!("stress-{}", current_timestamp_ms());
+    let started = Instant::now();
+
+    let handles: Vec<_> = (0..threads)
+        .map(|idx| {
+            let store = store.clone();
+            let tenant = format!("{run_id}-t{idx}");
+            let key_space = options.key_space.max(1);
+            let seed = options.seed ^ ((idx as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+            thread::spawn(move || worker(store.as_ref(), &tenant, per_thread, key_space, seed))
+        })
+        .collect();
+
+    let mut samples: HashMap<StressOp, Vec<Duration>> = HashMap::new();
+    let mut violations = Vec::new();
+    for (idx, handle) in handles.into_iter().enumerate() {
+        let outcome = handle
+            .join()
+            .map_err(|_| anyhow!("stress worker {idx} panicked"))??;
+        for (op, durations) in outcome.samples {
+            samples.entry(op).or_default().extend(durations);
+        }
+        violations.extend(outcome.violations);
+    }
+    let elapsed_ms = started.elapsed().as_millis();
+
+    for idx in 0..threads {
+        let filter = SessionFilter::new(Some(format!("{run_id}-t{idx}")), None, None);
+        store.purge(&filter)?;
+    }
+
+    let mut latencies: Vec<_> = samples
+        .into_iter()
+        .map(|(op, durations)| summarize(op, durations))
+        .collect();
+    latencies.sort_by_key(|summary| summary.op);
+
+    Ok(StressReport {
+        threads,
+        ops: per_thread * threads,
+        elapsed_ms,
+        latencies,
+        violations,
+    })
+}
+
+struct WorkerOutcome {
+    samples: HashMap<StressOp, Vec<Duration>>,
+    violations: Vec<String>,
+}
+
+fn worker(
+    store: &dyn SessionStore,
+    tenant: &str,
+    ops: usize,
+    key_space: usize,
+    seed: u64,
+) -> Result<WorkerOutcome> {
+    let mut rng = XorShift(seed.max(1));
+    let mut model: HashMap<String, u64> = HashMap::new();
+    let mut samples: HashMap<StressOp, Vec<Duration>> = HashMap::new();
+    let mut violations = Vec::new();
+    let tenant_filter = SessionFilter::new(Some(tenant.to_string()), None, None);
+
+    for version in 0..ops as u64 {
+        let roll = rng.next() % 100;
+        let user = format!("user-{}", rng.next() as usize % key_space);
+        let key = format!("{tenant}-{user}");
+        if roll < 60 {
+            let started = Instant::now();
+            store.upsert(SessionUpsert {
+                key: key.clone(),
+                tenant: tenant.to_string(),
+                team: None,
+                user: Some(user),
+                flow_id: Some("stress-flow".into()),
+                node_id: None,
+                context: json!({ "version": version }),
+                pack_id: None,
+                flow_version: None,
+                locale: None,
+                ttl_ms: None,
+                pack_generation: None,
+                runner_target: None,
+            })?;
+            record(&mut samples, StressOp::Upsert, started);
+            model.insert(key, version);
+        } else if roll < 99 {
+            let filter = SessionFilter::new(Some(tenant.to_string()), None, Some(user));
+            let started = Instant::now();
+            let found = store.find(&filter)?;
+            record(&mut samples, StressOp::Find, started);
+            let seen = found.and_then(|record| record.context["version"].as_u64());
+            let expected = model.get(&key).copied();
+            if seen != expected {
+                violations.push(format!(
+                    "{key}: expected version {expected:?}, found {seen:?}"
+                ));
+            }
+        } else {
+            let started = Instant::now();
+            let removed = store.purge(&tenant_filter)?;
+            record(&mut samples, StressOp::Purge, started);
+            if removed != model.len() {
+                violations.push(format!(
+                    "{tenant}: purge removed {removed} session(s), expected {}",
+                    model.len()
+                ));
+            }
+            model.clear();
+        }
+    }
+
+    let remaining = store.list(&tenant_filter)?;
+    if remaining.len() != model.len() {
+        violations.push(format!(
+            "{tenant}: {} session(s) remain, expected {}",
+            remaining.len(),
+            model.len()
+        ));
+    }
+    for record in remaining {
+        let seen = record.context["version"].as_u64();
+        let expected = model.get(&record.key).copied();
+        if seen != expected {
+            violations.push(format!(
+                "{}: final version {seen:?}, expected {expected:?}",
+                record.key
+            ));
+        }
+    }
+
+    Ok(WorkerOutcome {
+        samples,
+        violations,
+    })
+}
+
+fn record(samples: &mut HashMap<StressOp, Vec<Duration>>, op: StressOp, started: Instant) {
+    samples.entry(op).or_default().push(started.elapsed());
+}
+
+fn summarize(op: StressOp, mut durations: Vec<Duration>) -> LatencySummary {
+    durations.sort();
+    let micros: Vec<u64> = durations.iter().map(|d| d.as_micros() as u64).collect();
+    let percentile = |pct: usize| {
+        if micros.is_empty() {
+            return 0;
+        }
+        micros[(micros.len() * pct / 100).min(micros.len() - 1)]
+    };
+    let mut histogram: Vec<(String, usize)> = BUCKETS_US
+        .iter()
+        .map(|(_, label)| (label.to_string(), 0))
+        .collect();
+    for value in &micros {
+        let bucket = BUCKETS_US
+            .iter()
+            .position(|(upper, _)| value < upper)
+            .unwrap_or(BUCKETS_US.len() - 1);
+        histogram[bucket].1 += 1;
+    }
+    LatencySummary {
+        op,
+        count: micros.len(),
+        p50_us: percentile(50),
+        p95_us: percentile(95),
+        p99_us: percentile(99),
+        max_us: micros.last().copied().unwrap_or_default(),
+        histogram,
+    }
+}
+
+/// Tiny deterministic PRNG so runs are reproducible from `--seed`.
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::InMemorySessionStore;
+
+    #[test]
+    fn in_memory_store_survives_mixed_workload() {
+        let store = InMemorySessionStore::new();
+        let report = run_stress(
+            store.clone(),
+            &StressOptions {
+                threads: 4,
+                ops: 4_000,
+                key_space: 16,
+                seed: 7,
+            },
+        )
+        .unwrap();
+        assert!(report.violations.is_empty(), "{:?}", report.violations);
+        assert_eq!(report.ops, 4_000);
+        let total: usize = report.latencies.iter().map(|l| l.count).sum();
+        assert_eq!(total, 4_000);
+        assert!(store.list(&SessionFilter::default()).unwrap().is_empty());
+    }
+}
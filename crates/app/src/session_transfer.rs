@@ -0,0 +1,262 @@
+//! `sessions export` / `sessions import`: sessions as JSON lines, one [`SessionRecord`] per
+//! line, written from one backend and loaded into another, so a deployment can move from the
+//! file store to Redis or Postgres without dropping conversations that wait on a user.
+//! `sessions migrate` does both halves in one go between two configured backends and then
+//! reads every copied record back from the target.
+
+use std::io::{BufRead, Write};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::session::{SessionFilter, SessionRecord, SessionStore};
+
+/// Write every session matching `filter` to `out`; returns how many were written.
+pub fn export_sessions(
+    store: &dyn SessionStore,
+    filter: &SessionFilter,
+    out: &mut dyn Write,
+) -> Result<usize> {
+    let mut written = 0;
+    let mut failed = None;
+    store.scan(filter, &mut |record| {
+        if failed.is_some() {
+            return;
+        }
+        let line = serde_json::to_string(record)
+            .map_err(anyhow::Error::from)
+            .and_then(|line| Ok(writeln!(out, "{line}")?));
+        match line {
+            Ok(()) => written += 1,
+            Err(err) => failed = Some(err.context(format!("failed to export {}", record.key))),
+        }
+    })?;
+    if let Some(err) = failed {
+        return Err(err);
+    }
+    out.flush()?;
+    Ok(written)
+}
+
+/// Parse an export; blank lines are ignored and a later line wins over an earlier one with
+/// the same key.
+pub fn read_export(input: impl BufRead) -> Result<Vec<SessionRecord>> {
+    let mut records: Vec<SessionRecord> = Vec::new();
+    for (index, line) in input.lines().enumerate() {
+        let line = line.context("failed to read the session export")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: SessionRecord = serde_json::from_str(&line)
+            .with_context(|| format!("line {} is not a session record", index + 1))?;
+        match records.iter_mut().find(|known| known.key == record.key) {
+            Some(known) => *known = record,
+            None => records.push(record),
+        }
+    }
+    Ok(records)
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportReport {
+    /// Keys written (or, without `apply`, that would be written).
+    pub imported: Vec<String>,
+    /// Keys the backend already holds a copy of that is at least as new.
+    pub skipped: Vec<String>,
+}
+
+/// Store `records` verbatim. A record is skipped when the backend's copy was updated at the
+/// same time or later, unless `overwrite` is set; with `apply` unset nothing is written.
+pub fn import_sessions(
+    store: &dyn SessionStore,
+    records: Vec<SessionRecord>,
+    overwrite: bool,
+    apply: bool,
+) -> Result<ImportReport> {
+    let mut report = ImportReport::default();
+    for record in records {
+        let current = store.get(&record.key)?;
+        if !overwrite
+            && current
+                .is_some_and(|current| current.updated_at_epoch_ms >= record.updated_at_epoch_ms)
+        {
+            report.skipped.push(record.key);
+            continue;
+        }
+        let key = record.key.clone();
+        if apply {
+            store
+                .put(record)
+                .with_context(|| format!("failed to import session {key}"))?;
+        }
+        report.imported.push(key);
+    }
+    Ok(report)
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct MigrationReport {
+    /// Sessions the source backend held.
+    pub total: usize,
+    /// Keys copied (or, without `apply`, that would be copied).
+    pub copied: Vec<String>,
+    /// Keys the target already holds a copy of that is at least as new.
+    pub skipped: Vec<String>,
+    /// Copied keys the target did not read back identical to the source.
+    pub mismatched: Vec<String>,
+}
+
+/// Copy every session in `source` to `target`, `batch` records at a time, with the same
+/// skip rules as [`import_sessions`]. `progress` is called with the number of records
+/// handled so far and the total after each batch. When `apply` is set, every copied key is
+/// then read back from `target` and compared with the source record.
+pub fn migrate_sessions(
+    source: &dyn SessionStore,
+    target: &dyn SessionStore,
+    overwrite: bool,
+    apply: bool,
+    batch: usize,
+    progress: &mut dyn FnMut(usize, usize),
+) -> Result<MigrationReport> {
+    let records = source
+        .list(&SessionFilter::default())
+        .context("failed to read the source sessions")?;
+    let mut report = MigrationReport {
+        total: records.len(),
+        ..MigrationReport::default()
+    };
+    let mut done = 0;
+    for chunk in records.chunks(batch.max(1)) {
+        let imported = import_sessions(target, chunk.to_vec(), overwrite, apply)?;
+        report.copied.extend(imported.imported);
+        report.skipped.extend(imported.skipped);
+        done += chunk.len();
+        progress(done, report.total);
+    }
+    if apply {
+        for record in records
+            .iter()
+            .filter(|record| report.copied.contains(&record.key))
+        {
+            let stored = target
+                .get(&record.key)
+                .with_context(|| format!("failed to read back session {}", record.key))?;
+            let same = stored.is_some_and(|stored| {
+                serde_json::to_value(&stored).ok() == serde_json::to_value(record).ok()
+            });
+            if !same {
+                report.mismatched.push(record.key.clone());
+            }
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::session::{InMemorySessionStore, SessionUpsert};
+
+    fn upsert(key: &str, tenant: &str) -> SessionUpsert {
+        SessionUpsert {
+            key: key.into(),
+            tenant: tenant.into(),
+            team: None,
+            user: Some("u1".into()),
+            flow_id: Some("support".into()),
+            node_id: Some("ask".into()),
+            context: json!({"step": key}),
+            pack_id: None,
+            flow_version: None,
+            locale: None,
+            ttl_ms: None,
+            pack_generation: None,
+            runner_target: None,
+        }
+    }
+
+    #[test]
+    fn round_trips_sessions_between_stores() {
+        let source = InMemorySessionStore::new();
+        source.upsert(upsert("a", "acme")).unwrap();
+        source.upsert(upsert("b", "globex")).unwrap();
+        let mut out = Vec::new();
+        assert_eq!(
+            export_sessions(source.as_ref(), &SessionFilter::default(), &mut out).unwrap(),
+            2
+        );
+
+        let target = InMemorySessionStore::new();
+        let newer = target.upsert(upsert("b", "globex")).unwrap();
+        let records =
+            read_export(format!("\n{}", String::from_utf8(out).unwrap()).as_bytes()).unwrap();
+        let preview = import_sessions(target.as_ref(), records.clone(), false, false).unwrap();
+        assert_eq!(preview.imported, ["a"]);
+        assert!(target.get("a").unwrap().is_none());
+
+        let report = import_sessions(target.as_ref(), records.clone(), false, true).unwrap();
+        assert_eq!(report.imported, ["a"]);
+        assert_eq!(report.skipped, ["b"]);
+        let stored = |store: &InMemorySessionStore, key: &str| {
+            serde_json::to_value(store.get(key).unwrap().unwrap()).unwrap()
+        };
+        assert_eq!(stored(&target, "a"), stored(&source, "a"));
+        assert_eq!(stored(&target, "b"), serde_json::to_value(&newer).unwrap());
+
+        let mut report = import_sessions(target.as_ref(), records, true, true).unwrap();
+        report.imported.sort();
+        assert_eq!(report.imported, ["a", "b"]);
+    }
+
+    #[test]
+    fn migrates_in_batches_and_verifies_the_copies() {
+        let source = InMemorySessionStore::new();
+        for key in ["a", "b", "c"] {
+            source.upsert(upsert(key, "acme")).unwrap();
+        }
+        let target = InMemorySessionStore::new();
+        target.upsert(upsert("c", "acme")).unwrap();
+
+        let mut seen = Vec::new();
+        let preview = migrate_sessions(
+            source.as_ref(),
+            target.as_ref(),
+            false,
+            false,
+            2,
+            &mut |done, total| seen.push((done, total)),
+        )
+        .unwrap();
+        assert_eq!(seen, [(2, 3), (3, 3)]);
+        assert_eq!(preview.copied.len(), 2);
+        assert!(target.get("a").unwrap().is_none());
+
+        let mut report = migrate_sessions(
+            source.as_ref(),
+            target.as_ref(),
+            false,
+            true,
+            2,
+            &mut |_, _| {},
+        )
+        .unwrap();
+        report.copied.sort();
+        assert_eq!(report.total, 3);
+        assert_eq!(report.copied, ["a", "b"]);
+        assert_eq!(report.skipped, ["c"]);
+        assert!(report.mismatched.is_empty());
+        assert_eq!(
+            serde_json::to_value(target.get("a").unwrap()).unwrap(),
+            serde_json::to_value(source.get("a").unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_lines_that_are_not_records() {
+        let err =
+            read_export("{\"key\":\"a\",\"tenant\":\"t\"}\nnot json\n".as_bytes()).unwrap_err();
+        assert!(err.to_string().contains("line 2"), "{err}");
+    }
+}
@@ -0,0 +1,190 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::Result;
+use serde_json::Value;
+
+use crate::session::{SessionFilter, SessionRecord, SessionStore};
+
+/// Rewrites a session context written against an older flow version so it can be resumed
+/// against `to_version`.
+pub trait ContextMigrator: Send + Sync {
+    fn migrate(&self, session: &SessionRecord, to_version: &str) -> Result<Value>;
+}
+
+/// Carries the context over unchanged; for flows whose context shape is stable across versions.
+pub struct PassthroughMigrator;
+
+impl ContextMigrator for PassthroughMigrator {
+    fn migrate(&self, session: &SessionRecord, _to_version: &str) -> Result<Value> {
+        Ok(session.context.clone())
+    }
+}
+
+/// Migrators keyed by flow id.
+#[derive(Default, Clone)]
+pub struct ContextMigrations {
+    by_flow: HashMap<String, Arc<dyn ContextMigrator>>,
+}
+
+impl ContextMigrations {
+    pub fn register(&mut self, flow_id: impl Into<String>, migrator: Arc<dyn ContextMigrator>) {
+        self.by_flow.insert(flow_id.into(), migrator);
+    }
+
+    pub fn get(&self, flow_id: &str) -> Option<&Arc<dyn ContextMigrator>> {
+        self.by_flow.get(flow_id)
+    }
+}
+
+/// Outcome of checking a session against the currently loaded flow version.
+pub enum SessionUpgrade {
+    Current,
+    Migrated(Box<SessionRecord>),
+    /// No migrator is registered for the flow; resuming would run old context on a new flow.
+    Blocked {
+        from_version: Option<String>,
+        to_version: String,
+    },
+}
+
+/// Flag sessions pinned to a pack version other than the one now loaded.
+/// `versions` maps pack id to its current version; returns how many sessions were flagged.
+pub fn mark_outdated_sessions(
+    store: &dyn SessionStore,
+    versions: &HashMap<String, String>,
+) -> Result<usize> {
+    let mut flagged = 0;
+    for mut record in store.list(&SessionFilter::default().with_needs_upgrade(Some(false)))? {
+        let outdated = match (&record.pack_id, &record.flow_version) {
+            (Some(pack_id), Some(pinned)) => versions
+                .get(pack_id)
+                .is_some_and(|current| current != pinned),
+            _ => false,
+        };
+        if outdated {
+            record.needs_upgrade = true;
+            store.put(record)?;
+            flagged += 1;
+        }
+    }
+    Ok(flagged)
+}
+
+/// Bring `session` up to `current_version`, running the flow's migrator when it is behind.
+pub fn upgrade_session(
+    migrations: &ContextMigrations,
+    session: SessionRecord,
+    current_version: Option<&str>,
+) -> Result<SessionUpgrade> {
+    let Some(current) = current_version else {
+        return Ok(SessionUpgrade::Current);
+    };
+    let behind = session.needs_upgrade
+        || session
+            .flow_version
+            .as_deref()
+            .is_some_and(|pinned| pinned != current);
+    if !behind {
+        return Ok(SessionUpgrade::Current);
+    }
+    let migrator = session
+        .flow_id
+        .as_deref()
+        .and_then(|flow_id| migrations.get(flow_id));
+    let Some(migrator) = migrator else {
+        return Ok(SessionUpgrade::Blocked {
+            from_version: session.flow_version,
+            to_version: current.to_string(),
+        });
+    };
+    let context = migrator.migrate(&session, current)?;
+    Ok(SessionUpgrade::Migrated(Box::new(SessionRecord {
+        context,
+        flow_version: Some(current.to_string()),
+        needs_upgrade: false,
+        ..session
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{InMemorySessionStore, SessionUpsert};
+    use serde_json::json;
+
+    struct RenameTicket;
+
+    impl ContextMigrator for RenameTicket {
+        fn migrate(&self, session: &SessionRecord, _to_version: &str) -> Result<Value> {
+            Ok(json!({ "ticket_id": session.context["ticket"] }))
+        }
+    }
+
+    fn pinned(store: &InMemorySessionStore, key: &str, version: &str) -> SessionRecord {
+        store
+            .upsert(SessionUpsert {
+                key: key.into(),
+                tenant: "acme".into(),
+                team: None,
+                user: Some(key.into()),
+                flow_id: Some("support".into()),
+                node_id: None,
+                context: json!({ "ticket": 7 }),
+                pack_id: Some("acme".into()),
+                flow_version: Some(version.into()),
+                locale: None,
+                ttl_ms: None,
+                pack_generation: None,
+                runner_target: None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn reload_flags_sessions_on_other_versions() {
+        let store = InMemorySessionStore::new();
+        pinned(&store, "old", "1.0.0");
+        pinned(&store, "new", "2.0.0");
+        let versions = HashMap::from([("acme".to_string(), "2.0.0".to_string())]);
+
+        assert_eq!(
+            mark_outdated_sessions(store.as_ref(), &versions).unwrap(),
+            1
+        );
+        let flagged = store
+            .list(&SessionFilter::default().with_needs_upgrade(Some(true)))
+            .unwrap();
+        assert_eq!(flagged.len(), 1);
+        assert_eq!(flagged[0].key, "old");
+        assert_eq!(
+            mark_outdated_sessions(store.as_ref(), &versions).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn upgrade_runs_registered_migrator_or_blocks() {
+        let store = InMemorySessionStore::new();
+        let session = pinned(&store, "old", "1.0.0");
+
+        let none = ContextMigrations::default();
+        assert!(matches!(
+            upgrade_session(&none, session.clone(), Some("2.0.0")).unwrap(),
+            SessionUpgrade::Blocked { .. }
+        ));
+        assert!(matches!(
+            upgrade_session(&none, session.clone(), Some("1.0.0")).unwrap(),
+            SessionUpgrade::Current
+        ));
+
+        let mut migrations = ContextMigrations::default();
+        migrations.register("support", Arc::new(RenameTicket));
+        let SessionUpgrade::Migrated(upgraded) =
+            upgrade_session(&migrations, session, Some("2.0.0")).unwrap()
+        else {
+            panic!("expected migration");
+        };
+        assert_eq!(upgraded.context, json!({ "ticket_id": 7 }));
+        assert_eq!(upgraded.flow_version.as_deref(), Some("2.0.0"));
+    }
+}
@@ -0,0 +1,198 @@
+use std::{
+    future::Future,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use anyhow::{Result, anyhow};
+use futures::{
+    FutureExt,
+    future::{BoxFuture, Shared},
+};
+use parking_lot::Mutex;
+
+type Flight<T> = Shared<BoxFuture<'static, Result<T, Arc<anyhow::Error>>>>;
+
+/// The in-flight run, tagged with the id its task clears it by.
+type Slot<T> = Arc<Mutex<Option<(u64, Flight<T>)>>>;
+
+/// Coalesces concurrent runs of the same job: callers arriving while a run is in flight
+/// await it and receive its result instead of starting another one.
+pub struct SingleFlight<T> {
+    current: Slot<T>,
+    next_id: AtomicU64,
+}
+
+impl<T> Default for SingleFlight<T> {
+    fn default() -> Self {
+        Self {
+            current: Arc::default(),
+            next_id: AtomicU64::new(0),
+        }
+    }
+}
+
+/// Empties the slot when the run's task ends, however it ends, so a finished run is never
+/// joined even if every caller stopped waiting for it.
+struct ClearOnDrop<T> {
+    slot: Slot<T>,
+    id: u64,
+}
+
+impl<T> Drop for ClearOnDrop<T> {
+    fn drop(&mut self) {
+        let mut current = self.slot.lock();
+        if current.as_ref().is_some_and(|(id, _)| *id == self.id) {
+            *current = None;
+        }
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> SingleFlight<T> {
+    /// Join the in-flight run, or start `work` on a background task if none is running.
+    /// The task runs to completion even if every caller gives up waiting.
+    pub async fn run<F, Fut>(&self, work: F) -> Result<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T>> + Send + 'static,
+    {
+        let flight = {
+            let mut current = self.current.lock();
+            match current.as_ref() {
+                Some((_, flight)) => flight.clone(),
+                None => {
+                    let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+                    let clear = ClearOnDrop {
+                        slot: self.current.clone(),
+                        id,
+                    };
+                    let work = work();
+                    let task = tokio::spawn(async move {
+                        let _clear = clear;
+                        work.await
+                    });
+                    let flight = async move {
+                        match task.await {
+                            Ok(result) => result.map_err(Arc::new),
+                            Err(err) => Err(Arc::new(anyhow!("single-flight task failed: {err}"))),
+                        }
+                    }
+                    .boxed()
+                    .shared();
+                    *current = Some((id, flight.clone()));
+                    flight
+                }
+            }
+        };
+        flight.await.map_err(|err| anyhow!("{err:#}"))
+    }
+
+    /// Like [`run`](Self::run), but never joins a run that was already in flight when called:
+    /// that run may have read its inputs before the caller's change, so it is waited out and
+    /// a run started afterwards is joined or started instead.
+    pub async fn run_fresh<F, Fut>(&self, work: F) -> Result<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T>> + Send + 'static,
+    {
+        let stale = self
+            .current
+            .lock()
+            .as_ref()
+            .map(|(_, flight)| flight.clone());
+        if let Some(stale) = stale {
+            let _ = stale.await;
+        }
+        self.run(work).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn concurrent_callers_share_one_run() {
+        let flights = Arc::new(SingleFlight::<u64>::default());
+        let runs = Arc::new(AtomicU64::new(0));
+        let call = || {
+            let flights = flights.clone();
+            let runs = runs.clone();
+            async move {
+                flights
+                    .run(move || async move {
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                        Ok(runs.fetch_add(1, Ordering::SeqCst) + 1)
+                    })
+                    .await
+                    .unwrap()
+            }
+        };
+
+        let (a, b, c) = tokio::join!(call(), call(), call());
+        assert_eq!((a, b, c), (1, 1, 1));
+        assert_eq!(call().await, 2, "a finished flight is not reused");
+
+        let err = flights
+            .run(|| async { Err::<u64, _>(anyhow!("rebuild failed")) })
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("rebuild failed"));
+    }
+
+    #[tokio::test]
+    async fn fresh_callers_wait_out_a_stale_run() {
+        let flights = Arc::new(SingleFlight::<u64>::default());
+        let input = Arc::new(AtomicU64::new(1));
+        let read_input = |delay_ms: u64| {
+            let input = input.clone();
+            move || async move {
+                let seen = input.load(Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+                Ok(seen)
+            }
+        };
+
+        let in_flight = tokio::spawn({
+            let flights = flights.clone();
+            let work = read_input(100);
+            async move { flights.run(work).await.unwrap() }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        input.store(2, Ordering::SeqCst);
+        let (joined, fresh) =
+            tokio::join!(flights.run(read_input(0)), flights.run_fresh(read_input(0)));
+        assert_eq!(in_flight.await.unwrap(), 1);
+        assert_eq!(joined.unwrap(), 1, "run joins the stale flight");
+        assert_eq!(fresh.unwrap(), 2, "run_fresh sees the change");
+    }
+
+    #[tokio::test]
+    async fn a_run_every_caller_abandoned_is_not_joined_once_finished() {
+        let flights = SingleFlight::<u64>::default();
+        let runs = Arc::new(AtomicU64::new(0));
+        let work = || {
+            let runs = runs.clone();
+            move || async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                Ok(runs.fetch_add(1, Ordering::SeqCst) + 1)
+            }
+        };
+
+        for _ in 0..2 {
+            let abandoned = tokio::time::timeout(Duration::from_millis(5), flights.run(work()));
+            assert!(abandoned.await.is_err());
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(
+            runs.load(Ordering::SeqCst),
+            1,
+            "the abandoned run still finished"
+        );
+        assert!(flights.current.lock().is_none());
+        assert_eq!(flights.run(work()).await.unwrap(), 2);
+    }
+}
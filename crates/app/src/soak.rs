@@ -0,0 +1,543 @@
+//! `loadtest soak`: a steady mixed workload against a running bridge for hours. Every
+//! `check_every` the bridge's memory, event buffers and the workload's open sessions and p99
+//! latency are checked against fixed limits; every `checkpoint_every` the checks so far are
+//! written to `checkpoint-NNN.json`, so a run that dies at hour six still leaves evidence.
+
+use std::{
+    collections::BTreeSet,
+    fs,
+    time::{Duration, Instant},
+};
+
+use crate::annotations::{Annotation, AnnotationReport, line_of};
+use anyhow::{Context, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use greentic_integration_client::{
+    BridgeClient, ClientError, EmitRequest, ProcessDiagnostics, ResumeRequest, SessionQuery,
+    SessionUpsert,
+};
+use serde::Serialize;
+use serde_json::json;
+use tokio::time::MissedTickBehavior;
+use tracing::{info, warn};
+
+/// Flow the workload emits to and parks its sessions on.
+pub const SOAK_FLOW: &str = "soak-chat";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum SoakProfile {
+    /// Users open a conversation, emit into it a few times and resume it, like webchat.
+    Chat,
+    /// Session churn only: opens, resumes and listings.
+    Sessions,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SoakOp {
+    Emit,
+    Open,
+    Resume,
+    List,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SoakLimits {
+    /// Budget for the bridge's resident memory.
+    pub max_rss_mb: u64,
+    /// Client-side p99 latency per check interval.
+    pub max_p99_ms: u64,
+    /// Failed operations per check interval.
+    pub max_error_percent: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct SoakOptions {
+    pub profile: SoakProfile,
+    pub duration: Duration,
+    /// Operations per second.
+    pub rate: u32,
+    /// Users the workload cycles through; also the most sessions it can leave open.
+    pub users: usize,
+    pub tenant: String,
+    pub check_every: Duration,
+    pub checkpoint_every: Duration,
+    pub limits: SoakLimits,
+    /// Directory for checkpoints and `summary.json`.
+    pub out: Utf8PathBuf,
+    /// Stop after the first check that finds a violation.
+    pub fail_fast: bool,
+    pub seed: u64,
+}
+
+/// Outcome of one invariant check, covering the operations since the previous one.
+#[derive(Debug, Clone, Serialize)]
+pub struct SoakCheck {
+    pub elapsed_secs: u64,
+    pub ops: usize,
+    pub errors: usize,
+    pub p99_ms: u64,
+    pub rss_bytes: Option<u64>,
+    pub runner_events: usize,
+    pub runner_queue: usize,
+    pub sessions: usize,
+    pub violations: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SoakCheckpoint {
+    pub index: usize,
+    pub elapsed_secs: u64,
+    pub total_ops: usize,
+    pub total_errors: usize,
+    /// Checks since the previous checkpoint.
+    pub checks: Vec<SoakCheck>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SoakReport {
+    pub profile: SoakProfile,
+    pub tenant: String,
+    pub elapsed_secs: u64,
+    pub ops: usize,
+    pub errors: usize,
+    pub limits: SoakLimits,
+    pub checks: usize,
+    pub checkpoints: Vec<Utf8PathBuf>,
+    /// Every violation found, prefixed with when it was found.
+    pub violations: Vec<String>,
+    pub stopped_early: bool,
+}
+
+/// Operations observed since the previous check.
+#[derive(Debug, Default)]
+struct Interval {
+    latencies: Vec<Duration>,
+    errors: usize,
+}
+
+/// Check the bridge and the interval's operations against `limits`. `users` bounds the
+/// sessions the workload may hold open; more means sessions are leaking.
+fn check(
+    limits: &SoakLimits,
+    users: usize,
+    elapsed: Duration,
+    interval: Interval,
+    diagnostics: &ProcessDiagnostics,
+    sessions: usize,
+) -> SoakCheck {
+    let mut latencies = interval.latencies;
+    latencies.sort();
+    let ops = latencies.len();
+    let p99_ms = latencies
+        .get((ops * 99 / 100).min(ops.saturating_sub(1)))
+        .map_or(0, |latency| latency.as_millis() as u64);
+
+    let mut violations = Vec::new();
+    if let Some(rss) = diagnostics.rss_bytes
+        && rss > limits.max_rss_mb.saturating_mul(1024 * 1024)
+    {
+        violations.push(format!(
+            "bridge memory {} MiB is over the {} MiB budget",
+            rss / (1024 * 1024),
+            limits.max_rss_mb
+        ));
+    }
+    let events = diagnostics.runner_events;
+    if events.len > events.capacity {
+        violations.push(format!(
+            "runner event buffer holds {} events, over its cap of {}",
+            events.len, events.capacity
+        ));
+    }
+    let queue = diagnostics.runner_queue;
+    if queue.capacity > 0 && queue.len >= queue.capacity {
+        violations.push(format!(
+            "runner queue is full ({}/{})",
+            queue.len, queue.capacity
+        ));
+    }
+    if sessions > users {
+        violations.push(format!(
+            "{sessions} soak sessions are open for {users} users; sessions are leaking"
+        ));
+    }
+    if p99_ms > limits.max_p99_ms {
+        violations.push(format!(
+            "p99 latency {p99_ms}ms is over {}ms",
+            limits.max_p99_ms
+        ));
+    }
+    let attempted = ops + interval.errors;
+    if attempted > 0 {
+        let percent = interval.errors as f64 * 100.0 / attempted as f64;
+        if percent > limits.max_error_percent {
+            violations.push(format!(
+                "{} of {attempted} operations failed ({percent:.1}%), over {}%",
+                interval.errors, limits.max_error_percent
+            ));
+        }
+    }
+
+    SoakCheck {
+        elapsed_secs: elapsed.as_secs(),
+        ops,
+        errors: interval.errors,
+        p99_ms,
+        rss_bytes: diagnostics.rss_bytes,
+        runner_events: events.len,
+        runner_queue: queue.len,
+        sessions,
+        violations,
+    }
+}
+
+/// Picks operations for a profile and tracks which users have a session open.
+struct Workload {
+    profile: SoakProfile,
+    users: usize,
+    open: BTreeSet<usize>,
+    rng: XorShift,
+}
+
+impl Workload {
+    fn next(&mut self) -> (SoakOp, usize) {
+        let user = (self.rng.next() % self.users.max(1) as u64) as usize;
+        let roll = self.rng.next() % 100;
+        let open = self.open.contains(&user);
+        let op = match self.profile {
+            SoakProfile::Chat if open && roll < 50 => SoakOp::Emit,
+            SoakProfile::Chat if open => SoakOp::Resume,
+            SoakProfile::Chat if roll < 60 => SoakOp::Open,
+            SoakProfile::Chat => SoakOp::Emit,
+            SoakProfile::Sessions if roll < 10 => SoakOp::List,
+            SoakProfile::Sessions if open => SoakOp::Resume,
+            SoakProfile::Sessions => SoakOp::Open,
+        };
+        (op, user)
+    }
+}
+
+/// Run the soak described by `options` against `client`, writing checkpoints, `summary.json`
+/// and the violations as GitHub Checks annotations (`annotations.json`) to `options.out`. Sessions the workload left open are resumed at the end.
+pub async fn run(client: &BridgeClient, options: &SoakOptions) -> Result<SoakReport> {
+    fs::create_dir_all(&options.out)
+        .with_context(|| format!("failed to create {}", options.out))?;
+    let mut workload = Workload {
+        profile: options.profile,
+        users: options.users.max(1),
+        open: BTreeSet::new(),
+        rng: XorShift(options.seed.max(1)),
+    };
+    let mut ticker = tokio::time::interval(Duration::from_secs_f64(
+        1.0 / f64::from(options.rate.max(1)),
+    ));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    let started = Instant::now();
+    let mut next_check = options.check_every;
+    let mut next_checkpoint = options.checkpoint_every;
+    let mut interval = Interval::default();
+    let mut pending: Vec<SoakCheck> = Vec::new();
+    let (mut ops, mut errors, mut checks) = (0, 0, 0);
+    let mut checkpoints = Vec::new();
+    let mut violations = Vec::new();
+    let mut stopped_early = false;
+    let mut seq = 0u64;
+    loop {
+        ticker.tick().await;
+        let elapsed = started.elapsed();
+        let done = elapsed >= options.duration;
+        if done || elapsed >= next_check {
+            next_check = elapsed + options.check_every;
+            let result = observe(client, options, elapsed, std::mem::take(&mut interval)).await;
+            checks += 1;
+            info!(
+                elapsed_secs = result.elapsed_secs,
+                ops = result.ops,
+                p99_ms = result.p99_ms,
+                rss_bytes = ?result.rss_bytes,
+                sessions = result.sessions,
+                "soak check"
+            );
+            for violation in &result.violations {
+                warn!(elapsed_secs = result.elapsed_secs, "{violation}");
+                violations.push(format!("[{}s] {violation}", result.elapsed_secs));
+            }
+            stopped_early = options.fail_fast && !result.violations.is_empty() && !done;
+            pending.push(result);
+        }
+        if (done || stopped_early || elapsed >= next_checkpoint) && !pending.is_empty() {
+            next_checkpoint = elapsed + options.checkpoint_every;
+            let checkpoint = SoakCheckpoint {
+                index: checkpoints.len() + 1,
+                elapsed_secs: elapsed.as_secs(),
+                total_ops: ops,
+                total_errors: errors,
+                checks: std::mem::take(&mut pending),
+            };
+            checkpoints.push(write_json(
+                &options.out,
+                &format!("checkpoint-{:03}.json", checkpoint.index),
+                &checkpoint,
+            )?);
+        }
+        if done || stopped_early {
+            break;
+        }
+
+        let (op, user) = workload.next();
+        seq += 1;
+        let op_started = Instant::now();
+        match perform(client, &options.tenant, op, user, seq).await {
+            Ok(()) => {
+                interval.latencies.push(op_started.elapsed());
+                ops += 1;
+                match op {
+                    SoakOp::Open => {
+                        workload.open.insert(user);
+                    }
+                    SoakOp::Resume => {
+                        workload.open.remove(&user);
+                    }
+                    SoakOp::Emit | SoakOp::List => {}
+                }
+            }
+            Err(err) => {
+                interval.errors += 1;
+                errors += 1;
+                if op == SoakOp::Resume && err.status() == Some(404) {
+                    workload.open.remove(&user);
+                }
+                warn!(?op, user, %err, "soak operation failed");
+            }
+        }
+    }
+
+    for user in std::mem::take(&mut workload.open) {
+        if let Err(err) = perform(client, &options.tenant, SoakOp::Resume, user, seq).await {
+            warn!(user, %err, "failed to close soak session");
+        }
+    }
+    let report = SoakReport {
+        profile: options.profile,
+        tenant: options.tenant.clone(),
+        elapsed_secs: started.elapsed().as_secs(),
+        ops,
+        errors,
+        limits: options.limits.clone(),
+        checks,
+        checkpoints,
+        violations,
+        stopped_early,
+    };
+    let summary = write_json(&options.out, "summary.json", &report)?;
+    write_annotations(
+        &summary,
+        &report.violations,
+        options.out.join("annotations.json"),
+    )?;
+    Ok(report)
+}
+
+/// One failure annotation per violation, pointing at its entry in `summary.json`.
+fn write_annotations(summary: &Utf8Path, violations: &[String], out: Utf8PathBuf) -> Result<()> {
+    let raw = fs::read_to_string(summary).with_context(|| format!("failed to read {summary}"))?;
+    let mut report = AnnotationReport::default();
+    for violation in violations {
+        let line = serde_json::to_string(violation)
+            .ok()
+            .and_then(|needle| line_of(&raw, &needle, 0))
+            .unwrap_or(1);
+        report.push(
+            Annotation::failure(summary, violation)
+                .line(line)
+                .title("soak invariant violated"),
+        );
+    }
+    report.write(out, crate::workspace_root())
+}
+
+/// Sample the bridge and check the interval; an unreachable bridge is a violation in itself.
+async fn observe(
+    client: &BridgeClient,
+    options: &SoakOptions,
+    elapsed: Duration,
+    interval: Interval,
+) -> SoakCheck {
+    let query = SessionQuery {
+        tenant: Some(options.tenant.clone()),
+        ..SessionQuery::default()
+    };
+    let sampled = async {
+        let diagnostics = client.process_diagnostics().await?;
+        let sessions = client.list_sessions(&query).await?.count;
+        Ok::<_, ClientError>((diagnostics, sessions))
+    };
+    match sampled.await {
+        Ok((diagnostics, sessions)) => check(
+            &options.limits,
+            options.users.max(1),
+            elapsed,
+            interval,
+            &diagnostics,
+            sessions,
+        ),
+        Err(err) => SoakCheck {
+            elapsed_secs: elapsed.as_secs(),
+            ops: interval.latencies.len(),
+            errors: interval.errors,
+            p99_ms: 0,
+            rss_bytes: None,
+            runner_events: 0,
+            runner_queue: 0,
+            sessions: 0,
+            violations: vec![format!("bridge did not answer the check: {err}")],
+        },
+    }
+}
+
+async fn perform(
+    client: &BridgeClient,
+    tenant: &str,
+    op: SoakOp,
+    user: usize,
+    seq: u64,
+) -> Result<(), ClientError> {
+    let user = format!("soak-user-{user}");
+    match op {
+        SoakOp::Emit => {
+            let mut request = EmitRequest::new(
+                SOAK_FLOW,
+                json!({ "text": format!("soak message {seq}"), "seq": seq }),
+            );
+            request.tenant = Some(tenant.to_string());
+            request.user = Some(user);
+            client.emit(&request).await?;
+        }
+        SoakOp::Open => {
+            client
+                .upsert_session(&SessionUpsert {
+                    key: Some(format!("{tenant}-{user}")),
+                    tenant: Some(tenant.to_string()),
+                    user: Some(user),
+                    flow_id: Some(SOAK_FLOW.into()),
+                    node_id: Some("await-reply".into()),
+                    context: Some(json!({ "seq": seq })),
+                    ..SessionUpsert::default()
+                })
+                .await?;
+        }
+        SoakOp::Resume => {
+            let mut request = ResumeRequest::new(user, json!({ "text": "reply", "seq": seq }));
+            request.tenant = Some(tenant.to_string());
+            client.resume(&request).await?;
+        }
+        SoakOp::List => {
+            client
+                .list_sessions(&SessionQuery {
+                    tenant: Some(tenant.to_string()),
+                    ..SessionQuery::default()
+                })
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+fn write_json(dir: &Utf8Path, name: &str, value: &impl Serialize) -> Result<Utf8PathBuf> {
+    let path = dir.join(name);
+    fs::write(&path, serde_json::to_vec_pretty(value)?)
+        .with_context(|| format!("failed to write {path}"))?;
+    Ok(path)
+}
+
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use greentic_integration_client::BufferUsage;
+
+    #[test]
+    fn checks_flag_each_broken_invariant() {
+        let limits = SoakLimits {
+            max_rss_mb: 256,
+            max_p99_ms: 100,
+            max_error_percent: 1.0,
+        };
+        let healthy = ProcessDiagnostics {
+            pid: 1,
+            rss_bytes: Some(64 * 1024 * 1024),
+            runner_events: BufferUsage {
+                len: 100,
+                capacity: 100,
+            },
+            runner_queue: BufferUsage {
+                len: 3,
+                capacity: 64,
+            },
+        };
+        let interval = |millis: &[u64], errors| Interval {
+            latencies: millis.iter().map(|ms| Duration::from_millis(*ms)).collect(),
+            errors,
+        };
+        let elapsed = Duration::from_secs(60);
+
+        let ok = check(&limits, 10, elapsed, interval(&[5; 200], 1), &healthy, 10);
+        assert!(ok.violations.is_empty(), "{:?}", ok.violations);
+        assert_eq!((ok.ops, ok.p99_ms), (200, 5));
+
+        let leaking = ProcessDiagnostics {
+            rss_bytes: Some(300 * 1024 * 1024),
+            runner_events: BufferUsage {
+                len: 101,
+                capacity: 100,
+            },
+            runner_queue: BufferUsage {
+                len: 64,
+                capacity: 64,
+            },
+            ..healthy
+        };
+        let mut slow = vec![5; 98];
+        slow.extend([250, 400]);
+        let broken = check(&limits, 10, elapsed, interval(&slow, 5), &leaking, 11);
+        assert_eq!(broken.violations.len(), 6, "{:?}", broken.violations);
+        assert!(broken.violations[0].contains("300 MiB"));
+        assert!(broken.violations[3].contains("leaking"));
+        assert!(broken.violations[4].contains("p99 latency 400ms"));
+        assert!(broken.violations[5].contains("5 of 105"));
+    }
+
+    #[test]
+    fn chat_workload_only_resumes_open_sessions() {
+        let mut workload = Workload {
+            profile: SoakProfile::Chat,
+            users: 4,
+            open: BTreeSet::new(),
+            rng: XorShift(7),
+        };
+        for _ in 0..1_000 {
+            let (op, user) = workload.next();
+            assert!(user < 4);
+            match op {
+                SoakOp::Open => assert!(workload.open.insert(user)),
+                SoakOp::Resume => assert!(workload.open.remove(&user)),
+                SoakOp::Emit => {}
+                SoakOp::List => panic!("chat profile never lists"),
+            }
+        }
+    }
+}
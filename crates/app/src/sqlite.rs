@@ -0,0 +1,141 @@
+//! Embedded SQLite database behind the `backend = "sqlite"` stores. Every store opens the
+//! same file (WAL mode, so readers never block the writer) and the schema is brought up to
+//! date by the numbered [`MIGRATIONS`], tracked in `PRAGMA user_version`.
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{Context, Result, anyhow, bail};
+use camino::Utf8PathBuf;
+use parking_lot::{Mutex, MutexGuard};
+use rusqlite::{Connection, TransactionBehavior};
+
+use crate::path_safety::normalize_under_root;
+
+/// Schema steps, applied in order; step `n` moves `user_version` from `n` to `n + 1`.
+/// Append new steps, never edit shipped ones.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE sessions (
+        key TEXT PRIMARY KEY,
+        tenant TEXT NOT NULL,
+        team TEXT,
+        user TEXT,
+        updated_at_epoch_ms INTEGER NOT NULL,
+        record TEXT NOT NULL
+    );
+    CREATE INDEX sessions_owner ON sessions (tenant, team, user);
+    CREATE TABLE state (
+        namespace TEXT NOT NULL,
+        key TEXT NOT NULL,
+        value BLOB NOT NULL,
+        PRIMARY KEY (namespace, key)
+    );
+    CREATE TABLE runner_events (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        timestamp_ms INTEGER NOT NULL,
+        flow TEXT NOT NULL,
+        tenant TEXT,
+        event TEXT NOT NULL
+    );
+    CREATE INDEX runner_events_tenant_time ON runner_events (tenant, timestamp_ms);",
+    "CREATE TABLE session_audit (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        timestamp_ms INTEGER NOT NULL,
+        key TEXT NOT NULL,
+        op TEXT NOT NULL,
+        tenant TEXT,
+        actor TEXT NOT NULL,
+        entry TEXT NOT NULL
+    );
+    CREATE INDEX session_audit_key ON session_audit (key, id);",
+    "CREATE TABLE session_locks (
+        key TEXT PRIMARY KEY,
+        token TEXT NOT NULL,
+        expires_at_ms INTEGER NOT NULL
+    );",
+];
+
+/// One connection to the database file, serialized behind a mutex.
+pub struct SqliteDb {
+    path: Utf8PathBuf,
+    conn: Mutex<Connection>,
+}
+
+impl SqliteDb {
+    /// Open (creating if needed) `path` under `root` and apply pending migrations.
+    pub fn open(root: Utf8PathBuf, path: Utf8PathBuf) -> Result<Arc<Self>> {
+        let root = root
+            .as_std_path()
+            .canonicalize()
+            .with_context(|| format!("failed to canonicalize sqlite root {root}"))?;
+        let safe_path = normalize_under_root(&root, path.as_std_path())?;
+        let path = Utf8PathBuf::from_path_buf(safe_path)
+            .map_err(|_| anyhow!("normalized sqlite path is not valid UTF-8"))?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut conn = Connection::open(&path)
+            .with_context(|| format!("failed to open sqlite database {path}"))?;
+        conn.busy_timeout(Duration::from_secs(5))?;
+        conn.pragma_update(None, "journal_mode", "WAL")
+            .with_context(|| format!("failed to enable WAL on {path}"))?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+        migrate(&mut conn).with_context(|| format!("failed to migrate {path}"))?;
+        Ok(Arc::new(Self {
+            path,
+            conn: Mutex::new(conn),
+        }))
+    }
+
+    pub fn path(&self) -> &Utf8PathBuf {
+        &self.path
+    }
+
+    pub fn conn(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock()
+    }
+}
+
+fn migrate(conn: &mut Connection) -> Result<()> {
+    // IMMEDIATE takes the write lock up front, so processes opening the file together
+    // apply each step once.
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let version: usize = tx.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if version > MIGRATIONS.len() {
+        bail!(
+            "database schema version {version} is newer than this build supports ({})",
+            MIGRATIONS.len()
+        );
+    }
+    for step in &MIGRATIONS[version..] {
+        tx.execute_batch(step)?;
+    }
+    tx.pragma_update(None, "user_version", MIGRATIONS.len())?;
+    tx.commit()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opens_in_wal_mode_and_migrates_once() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Utf8PathBuf::from_path_buf(tmp.path().to_path_buf()).unwrap();
+        let path = Utf8PathBuf::from("data/greentic.db");
+        let db = SqliteDb::open(root.clone(), path.clone()).unwrap();
+        let mode: String = db
+            .conn()
+            .pragma_query_value(None, "journal_mode", |row| row.get(0))
+            .unwrap();
+        assert_eq!(mode, "wal");
+        drop(db);
+
+        let reopened = SqliteDb::open(root, path).unwrap();
+        let version: usize = reopened
+            .conn()
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+    }
+}
@@ -0,0 +1,255 @@
+use std::{collections::BTreeMap, fs, sync::Arc};
+
+use anyhow::{Context, Result, anyhow};
+use camino::Utf8PathBuf;
+use parking_lot::Mutex;
+use redis::Commands;
+use rusqlite::OptionalExtension;
+
+use crate::path_safety::normalize_under_root;
+use crate::sqlite::SqliteDb;
+
+/// Namespaced byte blobs backing component host bindings and pack history (`[stores.state]`).
+pub trait StateStore: Send + Sync {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>>;
+    fn set(&self, namespace: &str, key: &str, value: Vec<u8>) -> Result<()>;
+    /// Only the component host deletes keys.
+    #[cfg_attr(not(feature = "components"), allow(dead_code))]
+    fn delete(&self, namespace: &str, key: &str) -> Result<()>;
+}
+
+fn slot(namespace: &str, key: &str) -> String {
+    format!("{namespace}:{key}")
+}
+
+#[derive(Default)]
+pub struct InMemoryStateStore {
+    inner: Mutex<BTreeMap<String, Vec<u8>>>,
+}
+
+impl InMemoryStateStore {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+}
+
+impl StateStore for InMemoryStateStore {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>> {
+        Ok(self.inner.lock().get(&slot(namespace, key)).cloned())
+    }
+
+    fn set(&self, namespace: &str, key: &str, value: Vec<u8>) -> Result<()> {
+        self.inner.lock().insert(slot(namespace, key), value);
+        Ok(())
+    }
+
+    fn delete(&self, namespace: &str, key: &str) -> Result<()> {
+        self.inner.lock().remove(&slot(namespace, key));
+        Ok(())
+    }
+}
+
+/// JSON file of `"namespace:key" -> [bytes]`, rewritten on every change.
+pub struct FileStateStore {
+    path: Utf8PathBuf,
+    inner: Mutex<BTreeMap<String, Vec<u8>>>,
+}
+
+impl FileStateStore {
+    pub fn new(root: Utf8PathBuf, path: Utf8PathBuf) -> Result<Arc<Self>> {
+        let root = root
+            .as_std_path()
+            .canonicalize()
+            .with_context(|| format!("failed to canonicalize state root {root}"))?;
+        let safe_path = normalize_under_root(&root, path.as_std_path())?;
+        let path = Utf8PathBuf::from_path_buf(safe_path)
+            .map_err(|_| anyhow!("normalized state path is not valid UTF-8"))?;
+        let data = match fs::read_to_string(&path) {
+            Ok(raw) if !raw.trim().is_empty() => {
+                serde_json::from_str(&raw).with_context(|| format!("invalid JSON in {path}"))?
+            }
+            _ => BTreeMap::new(),
+        };
+        Ok(Arc::new(Self {
+            path,
+            inner: Mutex::new(data),
+        }))
+    }
+
+    fn persist(&self, guard: &BTreeMap<String, Vec<u8>>) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string(guard)?)
+            .with_context(|| format!("failed to write state store {}", self.path))
+    }
+}
+
+impl StateStore for FileStateStore {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>> {
+        Ok(self.inner.lock().get(&slot(namespace, key)).cloned())
+    }
+
+    fn set(&self, namespace: &str, key: &str, value: Vec<u8>) -> Result<()> {
+        let mut guard = self.inner.lock();
+        guard.insert(slot(namespace, key), value);
+        self.persist(&guard)
+    }
+
+    fn delete(&self, namespace: &str, key: &str) -> Result<()> {
+        let mut guard = self.inner.lock();
+        if guard.remove(&slot(namespace, key)).is_some() {
+            self.persist(&guard)?;
+        }
+        Ok(())
+    }
+}
+
+/// Plain redis string keys under `<prefix>:<namespace>:<key>`.
+pub struct RedisStateStore {
+    client: redis::Client,
+    prefix: String,
+}
+
+impl RedisStateStore {
+    pub fn new(url: &str, prefix: Option<String>) -> Result<Arc<Self>> {
+        let client = redis::Client::open(url.to_string())
+            .with_context(|| format!("failed to create redis client for {url}"))?;
+        let prefix = prefix.unwrap_or_else(|| "greentic:state".to_string());
+        Ok(Arc::new(Self { client, prefix }))
+    }
+
+    fn with_conn<T>(&self, f: impl FnOnce(&mut redis::Connection) -> Result<T>) -> Result<T> {
+        let mut conn = self.client.get_connection().with_context(|| {
+            format!(
+                "failed to connect to redis at {:?}",
+                self.client.get_connection_info()
+            )
+        })?;
+        f(&mut conn)
+    }
+
+    fn redis_key(&self, namespace: &str, key: &str) -> String {
+        format!("{}:{}", self.prefix, slot(namespace, key))
+    }
+}
+
+impl StateStore for RedisStateStore {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>> {
+        let redis_key = self.redis_key(namespace, key);
+        self.with_conn(|conn| {
+            conn.get(&redis_key)
+                .with_context(|| format!("failed to get {redis_key}"))
+        })
+    }
+
+    fn set(&self, namespace: &str, key: &str, value: Vec<u8>) -> Result<()> {
+        let redis_key = self.redis_key(namespace, key);
+        self.with_conn(|conn| {
+            conn.set(&redis_key, value)
+                .with_context(|| format!("failed to set {redis_key}"))
+        })
+    }
+
+    fn delete(&self, namespace: &str, key: &str) -> Result<()> {
+        let redis_key = self.redis_key(namespace, key);
+        self.with_conn(|conn| {
+            conn.del(&redis_key)
+                .with_context(|| format!("failed to del {redis_key}"))
+        })
+    }
+}
+
+/// `state` table rows of the embedded SQLite database, keyed by `(namespace, key)`.
+pub struct SqliteStateStore {
+    db: Arc<SqliteDb>,
+}
+
+impl SqliteStateStore {
+    pub fn new(db: Arc<SqliteDb>) -> Arc<Self> {
+        Arc::new(Self { db })
+    }
+}
+
+impl StateStore for SqliteStateStore {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>> {
+        self.db
+            .conn()
+            .query_row(
+                "SELECT value FROM state WHERE namespace = ?1 AND key = ?2",
+                [namespace, key],
+                |row| row.get(0),
+            )
+            .optional()
+            .with_context(|| format!("failed to read state {}", slot(namespace, key)))
+    }
+
+    fn set(&self, namespace: &str, key: &str, value: Vec<u8>) -> Result<()> {
+        self.db
+            .conn()
+            .execute(
+                "INSERT INTO state (namespace, key, value) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (namespace, key) DO UPDATE SET value = excluded.value",
+                rusqlite::params![namespace, key, value],
+            )
+            .with_context(|| format!("failed to write state {}", slot(namespace, key)))?;
+        Ok(())
+    }
+
+    fn delete(&self, namespace: &str, key: &str) -> Result<()> {
+        self.db
+            .conn()
+            .execute(
+                "DELETE FROM state WHERE namespace = ?1 AND key = ?2",
+                [namespace, key],
+            )
+            .with_context(|| format!("failed to delete state {}", slot(namespace, key)))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_store_round_trips_across_reopen() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Utf8PathBuf::from_path_buf(tmp.path().to_path_buf()).unwrap();
+        let path = Utf8PathBuf::from("state/state.json");
+
+        let store = FileStateStore::new(root.clone(), path.clone()).unwrap();
+        store.set("acme", "counter", b"1".to_vec()).unwrap();
+        store.set("other", "counter", b"2".to_vec()).unwrap();
+        store.delete("other", "counter").unwrap();
+
+        let reopened = FileStateStore::new(root, path).unwrap();
+        assert_eq!(
+            reopened.get("acme", "counter").unwrap(),
+            Some(b"1".to_vec())
+        );
+        assert_eq!(reopened.get("other", "counter").unwrap(), None);
+    }
+
+    #[test]
+    fn sqlite_store_round_trips_across_reopen() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Utf8PathBuf::from_path_buf(tmp.path().to_path_buf()).unwrap();
+        let open =
+            || SqliteStateStore::new(SqliteDb::open(root.clone(), "state.db".into()).unwrap());
+
+        let store = open();
+        store.set("acme", "counter", b"1".to_vec()).unwrap();
+        store.set("acme", "counter", b"2".to_vec()).unwrap();
+        store.set("other", "counter", b"3".to_vec()).unwrap();
+        store.delete("other", "counter").unwrap();
+        drop(store);
+
+        let reopened = open();
+        assert_eq!(
+            reopened.get("acme", "counter").unwrap(),
+            Some(b"2".to_vec())
+        );
+        assert_eq!(reopened.get("other", "counter").unwrap(), None);
+    }
+}
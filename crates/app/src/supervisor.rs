@@ -0,0 +1,347 @@
+//! Supervision for the background tasks `serve` spawns (runner proxy loop, pack watcher,
+//! tombstone compaction). Failed tasks are restarted with exponential backoff; a critical task
+//! that keeps failing escalates to a server shutdown. Task health backs `GET /readyz`.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use tracing::{error, info, warn};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SupervisorConfig {
+    /// Failures tolerated within `restart_window_secs` before a task is given up on.
+    #[serde(default = "default_max_restarts")]
+    pub max_restarts: u32,
+    #[serde(default = "default_restart_window_secs")]
+    pub restart_window_secs: u64,
+    #[serde(default = "default_backoff_initial_ms")]
+    pub backoff_initial_ms: u64,
+    #[serde(default = "default_backoff_max_ms")]
+    pub backoff_max_ms: u64,
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            max_restarts: default_max_restarts(),
+            restart_window_secs: default_restart_window_secs(),
+            backoff_initial_ms: default_backoff_initial_ms(),
+            backoff_max_ms: default_backoff_max_ms(),
+        }
+    }
+}
+
+fn default_max_restarts() -> u32 {
+    5
+}
+
+fn default_restart_window_secs() -> u64 {
+    60
+}
+
+fn default_backoff_initial_ms() -> u64 {
+    200
+}
+
+fn default_backoff_max_ms() -> u64 {
+    10_000
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    Running,
+    /// Failed and waiting out its backoff before the next start.
+    Restarting,
+    /// Returned cleanly; not restarted.
+    Stopped,
+    /// Exceeded its restart budget; not restarted.
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TaskHealth {
+    pub status: TaskStatus,
+    /// Critical tasks gate readiness and shut the server down when they fail for good.
+    pub critical: bool,
+    pub restarts: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// Health of every supervised task, shared with the HTTP layer.
+#[derive(Debug, Clone, Default)]
+pub struct TaskRegistry {
+    tasks: Arc<RwLock<BTreeMap<String, TaskHealth>>>,
+}
+
+impl TaskRegistry {
+    pub fn snapshot(&self) -> BTreeMap<String, TaskHealth> {
+        self.tasks.read().clone()
+    }
+
+    /// Ready when every critical task is running.
+    pub fn is_ready(&self) -> bool {
+        self.tasks
+            .read()
+            .values()
+            .filter(|task| task.critical)
+            .all(|task| task.status == TaskStatus::Running)
+    }
+
+    fn update(&self, name: &str, f: impl FnOnce(&mut TaskHealth)) {
+        if let Some(task) = self.tasks.write().get_mut(name) {
+            f(task);
+        }
+    }
+}
+
+pub struct Supervisor {
+    config: SupervisorConfig,
+    registry: TaskRegistry,
+    escalate: watch::Sender<Option<String>>,
+    stop: watch::Sender<bool>,
+}
+
+impl Supervisor {
+    pub fn new(config: SupervisorConfig) -> Self {
+        Self {
+            config,
+            registry: TaskRegistry::default(),
+            escalate: watch::Sender::new(None),
+            stop: watch::Sender::new(false),
+        }
+    }
+
+    pub fn registry(&self) -> TaskRegistry {
+        self.registry.clone()
+    }
+
+    /// Run `start()` under supervision. Errors and panics restart it after a backoff; a clean
+    /// return stops supervision of that task.
+    pub fn spawn<F, Fut>(&self, name: &str, critical: bool, start: F)
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.registry.tasks.write().insert(
+            name.to_string(),
+            TaskHealth {
+                status: TaskStatus::Running,
+                critical,
+                restarts: 0,
+                last_error: None,
+            },
+        );
+        let name = name.to_string();
+        let config = self.config.clone();
+        let registry = self.registry.clone();
+        let escalate = self.escalate.clone();
+        let stop = self.stop.subscribe();
+        tokio::spawn(async move {
+            let stopped = stopped(stop);
+            tokio::pin!(stopped);
+            let window = Duration::from_secs(config.restart_window_secs);
+            let mut failures: VecDeque<Instant> = VecDeque::new();
+            let mut backoff = Duration::from_millis(config.backoff_initial_ms);
+            loop {
+                registry.update(&name, |task| task.status = TaskStatus::Running);
+                let mut task = tokio::spawn(start());
+                let outcome = tokio::select! {
+                    outcome = &mut task => outcome,
+                    _ = &mut stopped => {
+                        task.abort();
+                        registry.update(&name, |task| task.status = TaskStatus::Stopped);
+                        return;
+                    }
+                };
+                let reason = match outcome {
+                    Ok(Ok(())) => {
+                        info!(task = %name, "supervised task stopped");
+                        registry.update(&name, |task| task.status = TaskStatus::Stopped);
+                        return;
+                    }
+                    Ok(Err(err)) => format!("{err:#}"),
+                    Err(join) if join.is_panic() => "task panicked".to_string(),
+                    Err(join) => join.to_string(),
+                };
+
+                let now = Instant::now();
+                failures.push_back(now);
+                while failures
+                    .front()
+                    .is_some_and(|at| now.duration_since(*at) > window)
+                {
+                    failures.pop_front();
+                }
+                if failures.len() > config.max_restarts as usize {
+                    error!(task = %name, %reason, critical, "supervised task failed repeatedly; giving up");
+                    registry.update(&name, |task| {
+                        task.status = TaskStatus::Failed;
+                        task.last_error = Some(reason.clone());
+                    });
+                    if critical {
+                        escalate.send_replace(Some(format!(
+                            "critical task {name} failed {} times within {}s: {reason}",
+                            failures.len(),
+                            config.restart_window_secs
+                        )));
+                    }
+                    return;
+                }
+
+                warn!(task = %name, %reason, backoff_ms = backoff.as_millis() as u64, "supervised task failed; restarting");
+                registry.update(&name, |task| {
+                    task.status = TaskStatus::Restarting;
+                    task.restarts += 1;
+                    task.last_error = Some(reason);
+                });
+                tokio::select! {
+                    _ = tokio::time::sleep(backoff) => {}
+                    _ = &mut stopped => {
+                        registry.update(&name, |task| task.status = TaskStatus::Stopped);
+                        return;
+                    }
+                }
+                backoff = (backoff * 2).min(Duration::from_millis(config.backoff_max_ms));
+            }
+        });
+    }
+
+    /// Stop every supervised task: running ones are aborted and none are restarted. Tasks
+    /// keep running when the supervisor is merely dropped.
+    pub fn shutdown(&self) {
+        self.stop.send_replace(true);
+    }
+
+    /// Why the server must stop, once a critical task has exhausted its restarts.
+    pub fn escalation(&self) -> Option<String> {
+        self.escalate.borrow().clone()
+    }
+
+    /// Resolves with the reason once a critical task has exhausted its restarts.
+    pub fn escalated(&self) -> impl Future<Output = String> + Send + 'static {
+        let mut rx = self.escalate.subscribe();
+        async move {
+            let reason = rx
+                .wait_for(Option::is_some)
+                .await
+                .map(|reason| reason.clone().unwrap_or_default());
+            match reason {
+                Ok(reason) => reason,
+                // The supervisor is gone, so nothing can escalate any more.
+                Err(_) => std::future::pending().await,
+            }
+        }
+    }
+}
+
+async fn stopped(mut stop: watch::Receiver<bool>) {
+    if stop.wait_for(|stop| *stop).await.is_err() {
+        std::future::pending::<()>().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn fast_config(max_restarts: u32) -> SupervisorConfig {
+        SupervisorConfig {
+            max_restarts,
+            restart_window_secs: 60,
+            backoff_initial_ms: 1,
+            backoff_max_ms: 4,
+        }
+    }
+
+    #[tokio::test]
+    async fn restarts_failed_tasks_until_they_recover() {
+        let supervisor = Supervisor::new(fast_config(5));
+        let starts = Arc::new(AtomicU32::new(0));
+        let counter = starts.clone();
+        supervisor.spawn("flaky", true, move || {
+            let attempt = counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                match attempt {
+                    0 => panic!("boom"),
+                    1 => Err(anyhow!("transient")),
+                    _ => std::future::pending().await,
+                }
+            }
+        });
+
+        let registry = supervisor.registry();
+        for _ in 0..200 {
+            if starts.load(Ordering::SeqCst) >= 3 && registry.is_ready() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(2)).await;
+        }
+        let health = &registry.snapshot()["flaky"];
+        assert_eq!(health.status, TaskStatus::Running);
+        assert_eq!(health.restarts, 2);
+        assert_eq!(health.last_error.as_deref(), Some("transient"));
+    }
+
+    #[tokio::test]
+    async fn critical_task_escalates_after_exhausting_restarts() {
+        let supervisor = Supervisor::new(fast_config(2));
+        supervisor.spawn("doomed", true, || async { Err(anyhow!("always")) });
+        supervisor.spawn("optional", false, || async { Err(anyhow!("also")) });
+
+        let reason = tokio::time::timeout(Duration::from_secs(5), supervisor.escalated())
+            .await
+            .expect("escalation");
+        assert!(
+            reason.contains("critical task doomed failed 3 times"),
+            "{reason}"
+        );
+        let registry = supervisor.registry();
+        assert!(!registry.is_ready());
+        assert_eq!(registry.snapshot()["doomed"].status, TaskStatus::Failed);
+    }
+
+    #[tokio::test]
+    async fn shutdown_aborts_running_tasks_and_skips_restarts() {
+        let supervisor = Supervisor::new(fast_config(5));
+        let held = Arc::new(());
+        let running = held.clone();
+        supervisor.spawn("forever", true, move || {
+            let running = running.clone();
+            async move {
+                let _running = running;
+                std::future::pending().await
+            }
+        });
+        supervisor.spawn("flaky", false, || async { Err(anyhow!("again")) });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(Arc::strong_count(&held), 3);
+
+        supervisor.shutdown();
+        let registry = supervisor.registry();
+        for _ in 0..200 {
+            let stopped = registry
+                .snapshot()
+                .values()
+                .all(|task| task.status == TaskStatus::Stopped);
+            if stopped && Arc::strong_count(&held) == 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(2)).await;
+        }
+        assert_eq!(Arc::strong_count(&held), 1);
+        assert_eq!(registry.snapshot()["forever"].status, TaskStatus::Stopped);
+        assert_eq!(registry.snapshot()["flaky"].status, TaskStatus::Stopped);
+    }
+}
@@ -0,0 +1,468 @@
+//! `tenants bootstrap --file tenants.yaml`: stands up a multi-tenant environment in one go.
+//! Each tenant's metadata, policies, teams and users are merged into the tenants file loaded
+//! next to the config (`[tenants.<id>]`), its secrets are written to a per-tenant directory of
+//! the secret store, pack overrides are created as copies of a base pack with the override id
+//! (`tenant` or `tenant:team`), and demo sessions are optionally seeded into the session store.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use walkdir::WalkDir;
+
+use crate::interpolate::{DirSecretStore, SecretStore};
+use crate::session::{SessionStore, SessionUpsert};
+use crate::tenant_policy::TenantConfig;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BootstrapFile {
+    pub tenants: Vec<TenantSpec>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TenantSpec {
+    pub id: String,
+    /// Residency, allowed providers, retention and `teams: {<team>: {users: [...]}}`.
+    #[serde(flatten)]
+    pub config: TenantConfig,
+    /// Written to `<secrets_dir>/<tenant>/<KEY>`.
+    #[serde(default)]
+    pub secrets: BTreeMap<String, String>,
+    #[serde(default)]
+    pub packs: Vec<PackOverrideSpec>,
+    #[serde(default)]
+    pub demo_sessions: Vec<DemoSessionSpec>,
+}
+
+/// Tenant (or team, with `team`) override of the pack whose manifest id is `base`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PackOverrideSpec {
+    pub base: String,
+    #[serde(default)]
+    pub team: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DemoSessionSpec {
+    pub user: String,
+    #[serde(default)]
+    pub team: Option<String>,
+    pub flow_id: String,
+    #[serde(default)]
+    pub node_id: Option<String>,
+    #[serde(default)]
+    pub context: Value,
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// Defaults to a stable `demo-…` key so re-running the bootstrap updates in place.
+    #[serde(default)]
+    pub key: Option<String>,
+}
+
+impl BootstrapFile {
+    pub fn from_yaml(raw: &str) -> Result<Self> {
+        let file: Self = serde_yaml_bw::from_str(raw).context("invalid tenants bootstrap file")?;
+        file.validate()?;
+        Ok(file)
+    }
+
+    fn validate(&self) -> Result<()> {
+        let mut seen = BTreeSet::new();
+        for tenant in &self.tenants {
+            check_id("tenant", &tenant.id)?;
+            if !seen.insert(tenant.id.as_str()) {
+                bail!("tenant {} is listed twice", tenant.id);
+            }
+            for (team, members) in &tenant.config.teams {
+                check_id("team", team)?;
+                for user in &members.users {
+                    check_id("user", user)?;
+                }
+            }
+            let declared = |team: &str| tenant.config.teams.contains_key(team);
+            for pack in &tenant.packs {
+                if let Some(team) = pack.team.as_deref().filter(|team| !declared(team)) {
+                    bail!(
+                        "tenant {}: pack override of {} names undeclared team {team}",
+                        tenant.id,
+                        pack.base
+                    );
+                }
+            }
+            for session in &tenant.demo_sessions {
+                check_id("user", &session.user)?;
+                if let Some(team) = &session.team {
+                    let Some(members) = tenant.config.teams.get(team) else {
+                        bail!(
+                            "tenant {}: demo session for {} names undeclared team {team}",
+                            tenant.id,
+                            session.user
+                        );
+                    };
+                    if !members.users.contains(&session.user) {
+                        bail!(
+                            "tenant {}: demo session user {} is not a member of team {team}",
+                            tenant.id,
+                            session.user
+                        );
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_id(kind: &str, id: &str) -> Result<()> {
+    let valid = !id.is_empty()
+        && !id.starts_with('.')
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '@'));
+    if !valid {
+        bail!("invalid {kind} id {id:?} (letters, digits, '-', '_', '.', '@')");
+    }
+    Ok(())
+}
+
+/// Where the bootstrap writes.
+pub struct BootstrapTargets<'a> {
+    /// TOML file holding `[tenants.<id>]`, merged over existing entries.
+    pub tenants_file: &'a Path,
+    pub packs_root: &'a Path,
+    /// `[packs].secrets_dir`; required when any tenant declares secrets.
+    pub secrets_dir: Option<&'a Path>,
+    /// Session store for `demo_sessions`; `None` skips them.
+    pub sessions: Option<&'a dyn SessionStore>,
+}
+
+/// What was (or, on a dry run, would be) provisioned for one tenant.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct TenantReport {
+    pub tenant: String,
+    pub teams: Vec<String>,
+    pub users: usize,
+    /// Secret names only; values are never reported.
+    pub secrets: Vec<String>,
+    /// Pack ids of the created overrides.
+    pub packs: Vec<String>,
+    /// Keys of the seeded demo sessions.
+    pub sessions: Vec<String>,
+}
+
+/// Provision every tenant in `file`. Inputs are checked up front (base packs exist, a
+/// secrets directory is configured) so a bad file writes nothing.
+pub fn bootstrap(
+    file: &BootstrapFile,
+    targets: &BootstrapTargets<'_>,
+    dry_run: bool,
+) -> Result<Vec<TenantReport>> {
+    let base_packs = pack_dirs_by_id(targets.packs_root)?;
+    for tenant in &file.tenants {
+        for pack in &tenant.packs {
+            if !base_packs.contains_key(&pack.base) {
+                bail!(
+                    "tenant {}: base pack {} not found under {}",
+                    tenant.id,
+                    pack.base,
+                    targets.packs_root.display()
+                );
+            }
+        }
+        if !tenant.secrets.is_empty() && targets.secrets_dir.is_none() {
+            bail!(
+                "tenant {} declares secrets but [packs].secrets_dir is not configured",
+                tenant.id
+            );
+        }
+    }
+
+    let mut reports = Vec::new();
+    for tenant in &file.tenants {
+        let mut report = TenantReport {
+            tenant: tenant.id.clone(),
+            teams: tenant.config.teams.keys().cloned().collect(),
+            users: tenant
+                .config
+                .teams
+                .values()
+                .flat_map(|team| &team.users)
+                .collect::<BTreeSet<_>>()
+                .len(),
+            secrets: tenant.secrets.keys().cloned().collect(),
+            ..TenantReport::default()
+        };
+        if let Some(dir) = targets.secrets_dir.filter(|_| !dry_run) {
+            let store = DirSecretStore::new(dir.join(&tenant.id));
+            for (key, value) in &tenant.secrets {
+                store
+                    .put(key, value)
+                    .with_context(|| format!("tenant {}: secret {key}", tenant.id))?;
+            }
+        }
+        for pack in &tenant.packs {
+            let id = match &pack.team {
+                Some(team) => format!("{}:{team}", tenant.id),
+                None => tenant.id.clone(),
+            };
+            if !dry_run {
+                write_override_pack(&base_packs[&pack.base], targets.packs_root, &id)?;
+            }
+            report.packs.push(id);
+        }
+        if let Some(store) = targets.sessions {
+            for session in &tenant.demo_sessions {
+                let key = session.key.clone().unwrap_or_else(|| {
+                    let mut key = format!("demo-{}", tenant.id);
+                    if let Some(team) = &session.team {
+                        key = format!("{key}-{team}");
+                    }
+                    format!("{key}-{}-{}", session.user, session.flow_id)
+                });
+                if !dry_run {
+                    store.upsert(SessionUpsert {
+                        key: key.clone(),
+                        tenant: tenant.id.clone(),
+                        team: session.team.clone(),
+                        user: Some(session.user.clone()),
+                        flow_id: Some(session.flow_id.clone()),
+                        node_id: session.node_id.clone(),
+                        context: session.context.clone(),
+                        pack_id: None,
+                        flow_version: None,
+                        locale: session.locale.clone(),
+                        ttl_ms: None,
+                        pack_generation: None,
+                        runner_target: None,
+                    })?;
+                }
+                report.sessions.push(key);
+            }
+        }
+        reports.push(report);
+    }
+
+    if !dry_run {
+        merge_tenants_file(targets.tenants_file, &file.tenants)?;
+    }
+    Ok(reports)
+}
+
+/// Pack directories under `root`, keyed by manifest id.
+fn pack_dirs_by_id(root: &Path) -> Result<BTreeMap<String, std::path::PathBuf>> {
+    #[derive(Deserialize)]
+    struct ManifestId {
+        id: String,
+    }
+
+    let mut packs = BTreeMap::new();
+    if !root.exists() {
+        return Ok(packs);
+    }
+    for entry in fs::read_dir(root).with_context(|| format!("failed to read {}", root.display()))? {
+        let path = entry?.path();
+        let Ok(raw) = fs::read_to_string(path.join("pack.json")) else {
+            continue;
+        };
+        if let Ok(manifest) = serde_json::from_str::<ManifestId>(&raw) {
+            packs.insert(manifest.id, path);
+        }
+    }
+    Ok(packs)
+}
+
+/// Copy `base` to `<root>/<id with ':' as '--'>` and rewrite the manifest id. An existing
+/// directory is replaced only when it already holds the same override.
+fn write_override_pack(base: &Path, root: &Path, id: &str) -> Result<()> {
+    let dest = root.join(id.replace(':', "--"));
+    if dest.exists() {
+        let existing = fs::read_to_string(dest.join("pack.json"))
+            .ok()
+            .and_then(|raw| serde_json::from_str::<Value>(&raw).ok());
+        if existing.as_ref().and_then(|m| m.get("id")?.as_str()) != Some(id) {
+            bail!(
+                "{} exists and is not the {id} override pack; refusing to overwrite it",
+                dest.display()
+            );
+        }
+        fs::remove_dir_all(&dest)
+            .with_context(|| format!("failed to replace {}", dest.display()))?;
+    }
+    for entry in WalkDir::new(base) {
+        let entry = entry?;
+        let relative = entry
+            .path()
+            .strip_prefix(base)
+            .expect("walkdir yields paths under its root");
+        let target = dest.join(relative);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target)
+                .with_context(|| format!("failed to create {}", target.display()))?;
+        } else {
+            fs::copy(entry.path(), &target)
+                .with_context(|| format!("failed to copy {}", entry.path().display()))?;
+        }
+    }
+
+    let manifest_path = dest.join("pack.json");
+    let mut manifest: Value = serde_json::from_str(&fs::read_to_string(&manifest_path)?)
+        .with_context(|| format!("invalid manifest {}", manifest_path.display()))?;
+    manifest["id"] = Value::String(id.to_string());
+    fs::write(
+        &manifest_path,
+        serde_json::to_string_pretty(&manifest)? + "\n",
+    )
+    .with_context(|| format!("failed to write {}", manifest_path.display()))
+}
+
+/// Merge the tenants into the `[tenants]` table of `path`, keeping other tenants as they are.
+fn merge_tenants_file(path: &Path, tenants: &[TenantSpec]) -> Result<()> {
+    #[derive(Default, Serialize, Deserialize)]
+    struct TenantsFile {
+        #[serde(default)]
+        tenants: BTreeMap<String, TenantConfig>,
+    }
+
+    let mut file = match fs::read_to_string(path) {
+        Ok(raw) => toml::from_str(&raw)
+            .with_context(|| format!("invalid tenants file {}", path.display()))?,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => TenantsFile::default(),
+        Err(err) => return Err(err).with_context(|| format!("failed to read {}", path.display())),
+    };
+    for tenant in tenants {
+        file.tenants
+            .insert(tenant.id.clone(), tenant.config.clone());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let raw = toml::to_string(&file).context("failed to serialize tenants file")?;
+    fs::write(path, raw).with_context(|| format!("failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::InMemorySessionStore;
+
+    const SPEC: &str = r#"
+tenants:
+  - id: acme
+    residency: eu
+    allowed_providers: [webchat]
+    teams:
+      support:
+        users: [alice, bob]
+    secrets:
+      SLACK_TOKEN: xoxb-acme
+    packs:
+      - base: demo-menu
+      - base: demo-menu
+        team: support
+    demo_sessions:
+      - user: alice
+        team: support
+        flow_id: welcome_menu
+        node_id: menu
+  - id: globex
+"#;
+
+    fn base_pack(root: &Path) {
+        let dir = root.join("demo-menu");
+        fs::create_dir_all(dir.join("scenarios")).unwrap();
+        fs::write(
+            dir.join("pack.json"),
+            r#"{"id": "demo-menu", "name": "Demo", "version": "0.1.0"}"#,
+        )
+        .unwrap();
+        fs::write(dir.join("scenarios/welcome.json"), "{}").unwrap();
+    }
+
+    #[test]
+    fn provisions_tenants_secrets_packs_and_sessions() {
+        let tmp = tempfile::tempdir().unwrap();
+        let packs = tmp.path().join("packs");
+        base_pack(&packs);
+        let secrets = tmp.path().join("secrets");
+        let tenants_file = tmp.path().join("config/tenants.toml");
+        fs::create_dir_all(tenants_file.parent().unwrap()).unwrap();
+        fs::write(&tenants_file, "[tenants.initech]\nresidency = \"us\"\n").unwrap();
+        let sessions = InMemorySessionStore::new();
+        let targets = BootstrapTargets {
+            tenants_file: &tenants_file,
+            packs_root: &packs,
+            secrets_dir: Some(&secrets),
+            sessions: Some(sessions.as_ref()),
+        };
+        let file = BootstrapFile::from_yaml(SPEC).unwrap();
+
+        let planned = bootstrap(&file, &targets, true).unwrap();
+        assert_eq!(planned[0].packs, vec!["acme", "acme:support"]);
+        assert!(!secrets.exists());
+        assert!(sessions.get(&planned[0].sessions[0]).unwrap().is_none());
+
+        let reports = bootstrap(&file, &targets, false).unwrap();
+        assert_eq!(reports, planned);
+        assert_eq!(reports[0].users, 2);
+        assert_eq!(
+            DirSecretStore::new(secrets.join("acme"))
+                .get("SLACK_TOKEN")
+                .unwrap()
+                .as_deref(),
+            Some("xoxb-acme")
+        );
+        let manifest: Value = serde_json::from_str(
+            &fs::read_to_string(packs.join("acme--support/pack.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(manifest["id"], "acme:support");
+        assert!(packs.join("acme/scenarios/welcome.json").exists());
+        let session = sessions.get(&reports[0].sessions[0]).unwrap().unwrap();
+        assert_eq!(session.tenant, "acme");
+        assert_eq!(session.node_id.as_deref(), Some("menu"));
+
+        let written: toml::Table =
+            toml::from_str(&fs::read_to_string(&tenants_file).unwrap()).unwrap();
+        let tenants = written["tenants"].as_table().unwrap();
+        assert_eq!(tenants["initech"]["residency"].as_str(), Some("us"));
+        assert_eq!(tenants["acme"]["residency"].as_str(), Some("eu"));
+        assert!(tenants["acme"]["teams"]["support"]["users"].is_array());
+        assert!(tenants.contains_key("globex"));
+
+        // Re-running replaces the override packs in place.
+        bootstrap(&file, &targets, false).unwrap();
+    }
+
+    #[test]
+    fn rejects_inconsistent_files_before_writing() {
+        assert!(BootstrapFile::from_yaml("tenants: [{id: acme}, {id: acme}]").is_err());
+        assert!(BootstrapFile::from_yaml("tenants: [{id: 'a/b'}]").is_err());
+        assert!(
+            BootstrapFile::from_yaml(
+                "tenants: [{id: acme, demo_sessions: [{user: eve, team: ops, flow_id: f}]}]"
+            )
+            .is_err()
+        );
+
+        let tmp = tempfile::tempdir().unwrap();
+        let file =
+            BootstrapFile::from_yaml("tenants: [{id: acme, packs: [{base: missing}]}]").unwrap();
+        let targets = BootstrapTargets {
+            tenants_file: &tmp.path().join("tenants.toml"),
+            packs_root: tmp.path(),
+            secrets_dir: None,
+            sessions: None,
+        };
+        assert!(bootstrap(&file, &targets, false).is_err());
+        assert!(!tmp.path().join("tenants.toml").exists());
+
+        let file = BootstrapFile::from_yaml("tenants: [{id: acme, secrets: {K: v}}]").unwrap();
+        assert!(bootstrap(&file, &targets, false).is_err());
+    }
+}
@@ -0,0 +1,294 @@
+//! Per-tenant data policies declared with the tenant's metadata (`[tenants.<id>]`): the data
+//! residency tag, which providers may carry the tenant's traffic, and how long sessions,
+//! runner events and transcripts are kept. Requests are checked by [`TenantConfig::evaluate`];
+//! [`reap_expired`] enforces retention from a background task.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::RunnerEventLog;
+use crate::session::{SessionFilter, SessionStore};
+use crate::transcript_store::TranscriptStore;
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TenantConfig {
+    /// Where the tenant's data must stay (e.g. `eu`); a server tagged with a different
+    /// `[server].residency` refuses its traffic.
+    #[serde(default)]
+    pub residency: Option<String>,
+    /// Providers allowed to carry the tenant's traffic; unset allows any.
+    #[serde(default)]
+    pub allowed_providers: Option<Vec<String>>,
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    /// Live sessions the tenant may hold; overrides `[sessions].max_per_tenant`.
+    #[serde(default)]
+    pub max_sessions: Option<usize>,
+    /// Receives the `RunnerEvent` of every resumed session of the tenant as a JSON POST, so
+    /// downstream systems (CRM, ticketing) learn the conversation continued.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resume_webhook: Option<String>,
+    /// Teams of the tenant and their users, keyed by team id.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub teams: BTreeMap<String, TeamConfig>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TeamConfig {
+    #[serde(default)]
+    pub users: Vec<String>,
+}
+
+/// Days each kind of data is kept after its last update; unset keeps it indefinitely.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RetentionConfig {
+    #[serde(default)]
+    pub sessions_days: Option<u64>,
+    #[serde(default)]
+    pub events_days: Option<u64>,
+    #[serde(default)]
+    pub transcripts_days: Option<u64>,
+}
+
+impl RetentionConfig {
+    pub fn is_empty(&self) -> bool {
+        self.sessions_days.is_none()
+            && self.events_days.is_none()
+            && self.transcripts_days.is_none()
+    }
+}
+
+/// Outcome of checking one request against a tenant's policy, as written to the audit log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PolicyDecision {
+    pub allowed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl TenantConfig {
+    /// Check a request on a server tagged `server_residency`. Traffic passes its `payload` so
+    /// the provider is checked too; writes that carry no traffic (session seeding) pass `None`.
+    pub fn evaluate(
+        &self,
+        server_residency: Option<&str>,
+        payload: Option<&Value>,
+    ) -> PolicyDecision {
+        let provider = payload.and_then(request_provider).map(str::to_string);
+        let reason = match (&self.residency, server_residency) {
+            (Some(required), Some(server)) if required != server => Some(format!(
+                "tenant data must reside in {required}, this server is tagged {server}"
+            )),
+            (Some(required), None) => Some(format!(
+                "tenant data must reside in {required}, this server has no residency tag"
+            )),
+            _ => None,
+        }
+        .or_else(|| {
+            let allowed = self
+                .allowed_providers
+                .as_ref()
+                .filter(|_| payload.is_some())?;
+            match &provider {
+                Some(provider) if allowed.contains(provider) => None,
+                Some(provider) => Some(format!("provider {provider} is not allowed")),
+                None => {
+                    Some("request names no provider (payload.provider or payload.channel)".into())
+                }
+            }
+        });
+        PolicyDecision {
+            allowed: reason.is_none(),
+            provider,
+            reason,
+        }
+    }
+}
+
+/// Provider a request arrived through: `payload.provider`, else `payload.channel`.
+pub fn request_provider(payload: &Value) -> Option<&str> {
+    ["provider", "channel"]
+        .iter()
+        .find_map(|key| payload.get(*key).and_then(Value::as_str))
+}
+
+/// What one retention pass removed for a tenant.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RetentionReport {
+    pub sessions: usize,
+    pub events: usize,
+    pub transcripts: usize,
+}
+
+/// Remove `tenant`'s data older than its retention allows.
+pub fn reap_expired(
+    tenant: &str,
+    retention: &RetentionConfig,
+    now_ms: u64,
+    sessions: &dyn SessionStore,
+    events: &RunnerEventLog,
+    transcripts: &dyn TranscriptStore,
+) -> anyhow::Result<RetentionReport> {
+    let cutoff = |days: u64| now_ms.saturating_sub(days.saturating_mul(DAY_MS));
+    let mut report = RetentionReport::default();
+
+    if let Some(days) = retention.sessions_days {
+        let cutoff = cutoff(days);
+        let mut expired = Vec::new();
+        let filter = SessionFilter::new(Some(tenant.to_string()), None, None);
+        sessions.scan(&filter, &mut |record| {
+            if record.updated_at_epoch_ms < cutoff {
+                expired.push(record.key.clone());
+            }
+        })?;
+        for key in &expired {
+            sessions.remove(key)?;
+        }
+        report.sessions = expired.len();
+    }
+    if let Some(days) = retention.events_days {
+        let cutoff = cutoff(days);
+        let mut guard = events.write();
+        let before = guard.len();
+        guard.retain(|event| {
+            event.tenant.as_deref() != Some(tenant) || event.timestamp_ms >= cutoff
+        });
+        report.events = before - guard.len();
+        if let Some(store) = events.store() {
+            store.prune(tenant, cutoff)?;
+        }
+    }
+    if let Some(days) = retention.transcripts_days {
+        let cutoff = cutoff(days);
+        report.transcripts = transcripts.prune(&|entry| {
+            entry.tenant.as_deref() == Some(tenant) && entry.timestamp_ms < cutoff
+        })?;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RunnerEvent;
+    use crate::session::{InMemorySessionStore, SessionRecord};
+    use crate::transcript_store::{Direction, InMemoryTranscriptStore, TranscriptEntry};
+    use serde_json::json;
+
+    fn policy() -> TenantConfig {
+        TenantConfig {
+            residency: Some("eu".into()),
+            allowed_providers: Some(vec!["webchat".into()]),
+            retention: RetentionConfig {
+                sessions_days: Some(1),
+                events_days: Some(1),
+                transcripts_days: Some(1),
+            },
+            ..TenantConfig::default()
+        }
+    }
+
+    #[test]
+    fn evaluates_residency_then_provider() {
+        let policy = policy();
+        let webchat = json!({"channel": "webchat"});
+        assert!(policy.evaluate(Some("eu"), Some(&webchat)).allowed);
+
+        let decision = policy.evaluate(Some("us"), Some(&webchat));
+        assert!(!decision.allowed);
+        assert!(decision.reason.unwrap().contains("reside in eu"));
+        assert!(!policy.evaluate(None, None).allowed);
+
+        let slack = json!({"provider": "slack", "channel": "webchat"});
+        let decision = policy.evaluate(Some("eu"), Some(&slack));
+        assert_eq!(decision.provider.as_deref(), Some("slack"));
+        assert!(!decision.allowed);
+        assert!(
+            !policy
+                .evaluate(Some("eu"), Some(&json!({"text": "hi"})))
+                .allowed
+        );
+        assert!(policy.evaluate(Some("eu"), None).allowed);
+        assert!(
+            TenantConfig::default()
+                .evaluate(None, Some(&Value::Null))
+                .allowed
+        );
+    }
+
+    #[test]
+    fn reaps_only_the_tenants_expired_data() {
+        let now = 10 * DAY_MS;
+        let sessions = InMemorySessionStore::new();
+        for (key, tenant, age_days) in [
+            ("old", "acme", 2),
+            ("new", "acme", 0),
+            ("other", "globex", 5),
+        ] {
+            sessions
+                .put(SessionRecord {
+                    key: key.into(),
+                    tenant: tenant.into(),
+                    updated_at_epoch_ms: now - age_days * DAY_MS,
+                    ..SessionRecord::default()
+                })
+                .unwrap();
+        }
+        let events = RunnerEventLog::default();
+        for (tenant, age_days) in [("acme", 3), ("acme", 0), ("globex", 3)] {
+            events.write().push(RunnerEvent {
+                timestamp_ms: now - age_days * DAY_MS,
+                flow: "chat".into(),
+                tenant: Some(tenant.into()),
+                team: None,
+                user: None,
+                payload: Value::Null,
+                result: Value::Null,
+                sequence: None,
+                instance: None,
+            });
+        }
+        let transcripts = InMemoryTranscriptStore::new();
+        transcripts
+            .append(
+                "old",
+                &[TranscriptEntry {
+                    timestamp_ms: now - 2 * DAY_MS,
+                    direction: Direction::Inbound,
+                    text: "hello".into(),
+                    flow_id: None,
+                    node: None,
+                    tenant: Some("acme".into()),
+                }],
+            )
+            .unwrap();
+
+        let report = reap_expired(
+            "acme",
+            &policy().retention,
+            now,
+            sessions.as_ref(),
+            &events,
+            transcripts.as_ref(),
+        )
+        .unwrap();
+        assert_eq!(
+            report,
+            RetentionReport {
+                sessions: 1,
+                events: 1,
+                transcripts: 1
+            }
+        );
+        assert!(sessions.get("old").unwrap().is_none());
+        assert!(sessions.get("other").unwrap().is_some());
+        assert_eq!(events.read().len(), 2);
+        assert!(transcripts.get("old").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,50 @@
+use std::{fs, path::Path};
+
+use camino::Utf8Path;
+
+use crate::{PackConfig, canonical_plan_snapshot, index_packs_under, plan_snapshot_path_in};
+
+/// Fails when `pack_id`'s inferred plan differs from `<fixtures_dir>/<pack_id>.json`, pointing
+/// at the first differing line. `packs_root` is searched like `[packs].root` with the default
+/// settings; refresh the snapshots with `greentic-integration packs plan-snapshot`.
+pub fn assert_plan_matches_golden(
+    packs_root: impl AsRef<Path>,
+    fixtures_dir: impl AsRef<Path>,
+    pack_id: &str,
+) {
+    let packs_root = utf8(packs_root.as_ref());
+    let index = index_packs_under(packs_root, &PackConfig::default())
+        .unwrap_or_else(|err| panic!("failed to index packs under {packs_root}: {err:#}"));
+    let Some(entry) = index.entries.iter().find(|entry| entry.id == pack_id) else {
+        let invalid = index
+            .invalid
+            .iter()
+            .find(|pack| pack.id.as_deref() == Some(pack_id));
+        panic!("pack {pack_id} not found under {packs_root} (invalid: {invalid:?})");
+    };
+    let actual = canonical_plan_snapshot(entry).expect("canonical plan");
+    let path = plan_snapshot_path_in(utf8(fixtures_dir.as_ref()), pack_id).expect("snapshot path");
+    let golden = fs::read_to_string(&path).unwrap_or_else(|err| {
+        panic!("missing plan snapshot {path} ({err}); run packs plan-snapshot")
+    });
+    if let Some((line, (want, got))) = golden
+        .lines()
+        .zip(actual.lines())
+        .enumerate()
+        .find(|(_, (want, got))| want != got)
+    {
+        panic!(
+            "plan for {pack_id} drifted from {path} at line {}:\n  golden: {want}\n  actual: {got}",
+            line + 1
+        );
+    }
+    assert_eq!(
+        golden.lines().count(),
+        actual.lines().count(),
+        "plan for {pack_id} drifted from {path} (length differs)"
+    );
+}
+
+fn utf8(path: &Path) -> &Utf8Path {
+    Utf8Path::from_path(path).unwrap_or_else(|| panic!("{} is not valid UTF-8", path.display()))
+}
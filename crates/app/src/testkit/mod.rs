@@ -0,0 +1,38 @@
+//! Stable facade over the integration harness for pack authors in other repositories.
+//!
+//! Depend on this crate as a dev-dependency and drive your pack through the same pieces the
+//! suites here use: [`TestEnv`] boots the compose stack, [`ScenarioRunner`] replays
+//! [`Scenario`] steps over NATS, [`ProviderSink`] stands in for a messaging provider's HTTP
+//! API, [`SimUser`] chats with a running bridge and [`assert_plan_matches_golden`] pins a
+//! pack's inferred deployment plan to a committed snapshot. Items re-exported here keep their paths
+//! across releases; the `harness` and `scenario` modules behind them may move.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use greentic_integration::testkit::{BridgeClient, ProviderSink, SimUser};
+//!
+//! # async fn suite() -> anyhow::Result<()> {
+//! let sink = ProviderSink::start("target/testkit/outbound.json").await?;
+//! // Point the pack's provider config at `sink.send_url()`, then talk to the bridge.
+//! let mut user = SimUser::new(BridgeClient::new("http://localhost:8080"), "user-1").tenant("dev");
+//! let replies = user.start("menu", "hi").await?;
+//! assert!(!replies.is_empty());
+//! let outbound = sink.wait_for(1, Duration::from_secs(5)).await?;
+//! assert_eq!(outbound[0]["text"], "HI");
+//! # Ok(())
+//! # }
+//! ```
+
+mod golden;
+mod sim_user;
+mod sink;
+
+pub use golden::assert_plan_matches_golden;
+pub use greentic_integration_client::{BridgeClient, RunnerEvent};
+pub use sim_user::SimUser;
+pub use sink::{ProviderSink, SinkResponse};
+
+pub use crate::harness::{TestEnv, docker_available};
+pub use crate::scenario::{Scenario, ScenarioRunner, Step};
+pub use crate::trace_context::{TRACEPARENT, TraceContext};
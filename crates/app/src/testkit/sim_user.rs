@@ -0,0 +1,104 @@
+use anyhow::{Context, Result};
+use greentic_integration_client::{BridgeClient, EmitRequest, ResumeRequest, RunnerEvent};
+use serde_json::{Value, json};
+
+/// A simulated end user chatting with a bridge: starts flows with `POST /runner/emit`,
+/// answers waiting sessions with `POST /sessions/resume` and keeps a `USER:`/`BOT:`
+/// transcript in the same form as `GET /sessions/{key}/transcript` and scenario goldens.
+pub struct SimUser {
+    client: BridgeClient,
+    user: String,
+    tenant: Option<String>,
+    team: Option<String>,
+    locale: Option<String>,
+    transcript: Vec<String>,
+}
+
+impl SimUser {
+    pub fn new(client: BridgeClient, user: impl Into<String>) -> Self {
+        Self {
+            client,
+            user: user.into(),
+            tenant: None,
+            team: None,
+            locale: None,
+            transcript: Vec::new(),
+        }
+    }
+
+    pub fn tenant(mut self, tenant: impl Into<String>) -> Self {
+        self.tenant = Some(tenant.into());
+        self
+    }
+
+    pub fn team(mut self, team: impl Into<String>) -> Self {
+        self.team = Some(team.into());
+        self
+    }
+
+    pub fn locale(mut self, locale: impl Into<String>) -> Self {
+        self.locale = Some(locale.into());
+        self
+    }
+
+    pub fn user(&self) -> &str {
+        &self.user
+    }
+
+    /// Send `text` to `flow` and return the bot's replies.
+    pub async fn start(&mut self, flow: &str, text: &str) -> Result<Vec<String>> {
+        let request = EmitRequest {
+            tenant: self.tenant.clone(),
+            team: self.team.clone(),
+            user: Some(self.user.clone()),
+            locale: self.locale.clone(),
+            ..EmitRequest::new(flow, json!({ "text": text }))
+        };
+        let event = self
+            .client
+            .emit(&request)
+            .await
+            .with_context(|| format!("{} failed to start flow {flow}", self.user))?;
+        Ok(self.record(text, &event))
+    }
+
+    /// Answer the user's waiting session with `text` and return the bot's replies.
+    pub async fn reply(&mut self, text: &str) -> Result<Vec<String>> {
+        let request = ResumeRequest {
+            tenant: self.tenant.clone(),
+            team: self.team.clone(),
+            locale: self.locale.clone(),
+            ..ResumeRequest::new(self.user.clone(), json!({ "text": text }))
+        };
+        let event = self
+            .client
+            .resume(&request)
+            .await
+            .with_context(|| format!("{} failed to resume their session", self.user))?;
+        Ok(self.record(text, &event))
+    }
+
+    /// `USER:`/`BOT:` lines of the conversation so far.
+    pub fn transcript(&self) -> &[String] {
+        &self.transcript
+    }
+
+    fn record(&mut self, text: &str, event: &RunnerEvent) -> Vec<String> {
+        let replies = replies(event);
+        self.transcript.push(format!("USER: {text}"));
+        self.transcript
+            .extend(replies.iter().map(|reply| format!("BOT: {reply}")));
+        replies
+    }
+}
+
+/// Texts of the messages a flow run sent back (`result.outcome.messages[].text`).
+fn replies(event: &RunnerEvent) -> Vec<String> {
+    event.result["outcome"]["messages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|message| message.get("text").and_then(Value::as_str))
+        .map(str::to_string)
+        .collect()
+}
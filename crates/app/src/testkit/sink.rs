@@ -0,0 +1,216 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, bail};
+use axum::{
+    Json, Router,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    routing::post,
+};
+use parking_lot::Mutex;
+use serde_json::Value;
+use tokio::{net::TcpListener, sync::oneshot, task::JoinHandle};
+
+use crate::trace_context::TRACEPARENT;
+
+/// How a [`ProviderSink`] answers each delivery.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SinkResponse {
+    /// `200` right away.
+    #[default]
+    Ok,
+    /// `200` after the delay, to exercise timeouts and slow providers.
+    Delayed(Duration),
+    /// This status (e.g. `500`), to exercise error handling and retries.
+    Status(u16),
+}
+
+/// Stub provider endpoint on a loopback port. Every JSON body posted to
+/// [`send_url`](Self::send_url) is recorded, with its `traceparent` header, and the list so
+/// far is written to the artifact path as a pretty JSON array.
+pub struct ProviderSink {
+    url: String,
+    state: Arc<SinkState>,
+    shutdown: Option<oneshot::Sender<()>>,
+    handle: JoinHandle<()>,
+}
+
+struct SinkState {
+    artifact: PathBuf,
+    response: SinkResponse,
+    received: Mutex<Vec<Value>>,
+    traces: Mutex<Vec<Option<String>>>,
+}
+
+impl ProviderSink {
+    /// Start a sink that accepts every delivery.
+    pub async fn start(artifact: impl Into<PathBuf>) -> Result<Self> {
+        Self::start_with(artifact, SinkResponse::Ok).await
+    }
+
+    pub async fn start_with(artifact: impl Into<PathBuf>, response: SinkResponse) -> Result<Self> {
+        let artifact = artifact.into();
+        if let Some(parent) = artifact.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        let state = Arc::new(SinkState {
+            artifact,
+            response,
+            received: Mutex::default(),
+            traces: Mutex::default(),
+        });
+        let router = Router::new()
+            .route("/send", post(receive))
+            .with_state(state.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let (shutdown, stopped) = oneshot::channel::<()>();
+        let handle = tokio::spawn(async move {
+            let _ = axum::serve(listener, router)
+                .with_graceful_shutdown(async move {
+                    let _ = stopped.await;
+                })
+                .await;
+        });
+        Ok(Self {
+            url,
+            state,
+            shutdown: Some(shutdown),
+            handle,
+        })
+    }
+
+    /// Base URL, e.g. `http://127.0.0.1:41234`.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Where providers should POST their deliveries.
+    pub fn send_url(&self) -> String {
+        format!("{}/send", self.url)
+    }
+
+    pub fn artifact(&self) -> &Path {
+        &self.state.artifact
+    }
+
+    /// Bodies received so far, in arrival order.
+    pub fn received(&self) -> Vec<Value> {
+        self.state.received.lock().clone()
+    }
+
+    /// `traceparent` header of each delivery, in arrival order.
+    pub fn traces(&self) -> Vec<Option<String>> {
+        self.state.traces.lock().clone()
+    }
+
+    /// Wait until at least `expected` deliveries arrived and return them.
+    pub async fn wait_for(&self, expected: usize, timeout: Duration) -> Result<Vec<Value>> {
+        let start = Instant::now();
+        loop {
+            let received = self.received();
+            if received.len() >= expected {
+                return Ok(received);
+            }
+            if start.elapsed() > timeout {
+                bail!(
+                    "timed out waiting for {expected} outbound payload(s); got {}",
+                    received.len()
+                );
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+
+    pub async fn shutdown(mut self) -> Result<()> {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        let _ = (&mut self.handle).await;
+        Ok(())
+    }
+}
+
+impl Drop for ProviderSink {
+    fn drop(&mut self) {
+        if self.shutdown.is_some() {
+            self.handle.abort();
+        }
+    }
+}
+
+async fn receive(
+    State(state): State<Arc<SinkState>>,
+    headers: HeaderMap,
+    Json(payload): Json<Value>,
+) -> StatusCode {
+    state.traces.lock().push(
+        headers
+            .get(TRACEPARENT)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string),
+    );
+    let status = match state.response {
+        SinkResponse::Ok => StatusCode::OK,
+        SinkResponse::Delayed(delay) => {
+            tokio::time::sleep(delay).await;
+            StatusCode::OK
+        }
+        SinkResponse::Status(status) => {
+            StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    };
+    let mut received = state.received.lock();
+    received.push(payload);
+    // Written under the lock so the artifact never regresses to an older list.
+    let serialized = serde_json::to_string_pretty(&*received).unwrap_or_default();
+    let _ = std::fs::write(&state.artifact, serialized);
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn records_deliveries_and_answers_with_the_configured_status() {
+        let tmp = tempfile::tempdir().unwrap();
+        let sink = ProviderSink::start_with(
+            tmp.path().join("out/outbound.json"),
+            SinkResponse::Status(503),
+        )
+        .await
+        .unwrap();
+        let url = sink.send_url();
+        let status = tokio::task::spawn_blocking(move || {
+            match ureq::post(&url)
+                .header(
+                    TRACEPARENT,
+                    "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+                )
+                .send_json(json!({"text": "hi"}))
+            {
+                Err(ureq::Error::StatusCode(status)) => status,
+                other => panic!("expected a status error, got {other:?}"),
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(status, 503);
+
+        let received = sink.wait_for(1, Duration::from_secs(2)).await.unwrap();
+        assert_eq!(received, [json!({"text": "hi"})]);
+        assert!(sink.traces()[0].as_deref().unwrap().starts_with("00-0af7"));
+        let artifact: Value =
+            serde_json::from_str(&std::fs::read_to_string(sink.artifact()).unwrap()).unwrap();
+        assert_eq!(artifact, json!([{"text": "hi"}]));
+        sink.shutdown().await.unwrap();
+    }
+}
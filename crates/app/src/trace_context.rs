@@ -0,0 +1,150 @@
+//! W3C Trace Context (`traceparent`) propagation over NATS headers, so spans emitted by the
+//! bridge, broker consumers and runner can be stitched into one distributed trace.
+
+use std::fmt;
+
+use async_nats::HeaderMap;
+use uuid::Uuid;
+
+/// Header carrying the context, per <https://www.w3.org/TR/trace-context/>.
+pub const TRACEPARENT: &str = "traceparent";
+
+/// Version 00 trace context: 16-byte trace id, 8-byte parent span id and the sampled flag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    pub trace_id: String,
+    pub span_id: String,
+    pub sampled: bool,
+}
+
+impl TraceContext {
+    /// Start a new trace (sampled).
+    pub fn new_root() -> Self {
+        Self {
+            trace_id: Uuid::new_v4().simple().to_string(),
+            span_id: new_span_id(),
+            sampled: true,
+        }
+    }
+
+    /// Same trace, new span: what a hop sends downstream after receiving `self`.
+    pub fn child(&self) -> Self {
+        Self {
+            trace_id: self.trace_id.clone(),
+            span_id: new_span_id(),
+            sampled: self.sampled,
+        }
+    }
+
+    /// Parse a `traceparent` value; malformed or all-zero ids yield `None` (start a new trace).
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.trim().split('-');
+        let (version, trace_id, span_id, flags) =
+            (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+        if version != "00" || parts.next().is_some() {
+            return None;
+        }
+        if !is_hex_id(trace_id, 32) || !is_hex_id(span_id, 16) || !is_hex_id(flags, 2) {
+            return None;
+        }
+        let flags = u8::from_str_radix(flags, 16).ok()?;
+        Some(Self {
+            trace_id: trace_id.to_string(),
+            span_id: span_id.to_string(),
+            sampled: flags & 0x01 == 0x01,
+        })
+    }
+
+    /// Context carried by an inbound message, if any.
+    pub fn extract(headers: Option<&HeaderMap>) -> Option<Self> {
+        headers?
+            .get(TRACEPARENT)
+            .and_then(|value| Self::parse(value.as_str()))
+    }
+
+    /// Continue the inbound trace with a child span, or start a new one.
+    pub fn continue_from(headers: Option<&HeaderMap>) -> Self {
+        Self::extract(headers)
+            .map(|parent| parent.child())
+            .unwrap_or_else(Self::new_root)
+    }
+
+    pub fn inject(&self, headers: &mut HeaderMap) {
+        headers.insert(TRACEPARENT, self.to_string().as_str());
+    }
+
+    /// Fresh header map carrying only this context.
+    pub fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        self.inject(&mut headers);
+        headers
+    }
+}
+
+impl fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "00-{}-{}-{:02x}",
+            self.trace_id,
+            self.span_id,
+            u8::from(self.sampled)
+        )
+    }
+}
+
+fn new_span_id() -> String {
+    Uuid::new_v4().simple().to_string()[..16].to_string()
+}
+
+fn is_hex_id(value: &str, len: usize) -> bool {
+    value.len() == len
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+        && (len == 2 || value.bytes().any(|b| b != b'0'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_nats_headers() {
+        let root = TraceContext::new_root();
+        let headers = root.child().headers();
+        let received = TraceContext::extract(Some(&headers)).expect("traceparent");
+        assert_eq!(received.trace_id, root.trace_id);
+        assert_ne!(received.span_id, root.span_id);
+        assert!(received.sampled);
+
+        let next = TraceContext::continue_from(Some(&headers));
+        assert_eq!(next.trace_id, root.trace_id);
+        assert_ne!(TraceContext::continue_from(None).trace_id, root.trace_id);
+    }
+
+    #[test]
+    fn parses_spec_example_and_rejects_malformed_values() {
+        let ctx = TraceContext::parse("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+            .expect("valid traceparent");
+        assert_eq!(ctx.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(ctx.span_id, "00f067aa0ba902b7");
+        assert_eq!(
+            ctx.to_string(),
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+        );
+
+        for bad in [
+            "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "garbage",
+        ] {
+            assert!(
+                TraceContext::parse(bad).is_none(),
+                "{bad} should be rejected"
+            );
+        }
+    }
+}
@@ -0,0 +1,649 @@
+//! Tail-based sampling of request traces (`[telemetry.sampling]`, `GET /diagnostics/traces`).
+//! Each HTTP request runs in an `http_request` span; the spans and events recorded under it
+//! are buffered until it closes and kept only when the request failed (a `5xx` answer or an
+//! `ERROR` event) or took longer than `latency_threshold_ms`. Kept traces are listed on the
+//! diagnostics endpoint and, with `export_url`, POSTed to a collector, so high-volume runs
+//! ship every failure in full and drop the rest.
+//!
+//! The sampler is process-wide: the binary installs its layer once, and the servers built in
+//! the process configure it.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    str::FromStr,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::{Result, anyhow};
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tracing::{
+    Event, Instrument, Level, Metadata, Subscriber,
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+    warn,
+};
+use tracing_subscriber::{
+    Layer,
+    filter::filter_fn,
+    layer::Context,
+    registry::{LookupSpan, SpanRef},
+};
+
+use crate::network::OutboundHttp;
+use crate::trace_context::{TRACEPARENT, TraceContext};
+
+/// Name of the root span every sampled request runs in.
+pub const REQUEST_SPAN: &str = "http_request";
+
+static GLOBAL: Lazy<TailSampler> = Lazy::new(TailSampler::default);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TelemetryConfig {
+    #[serde(default)]
+    pub sampling: SamplingConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SamplingConfig {
+    /// Buffer request traces and keep the failed or slow ones.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Requests taking at least this long are kept even when they succeed.
+    #[serde(default = "default_latency_threshold_ms")]
+    pub latency_threshold_ms: u64,
+    /// Most verbose level buffered under a request (`error`, `warn`, `info`, `debug` or
+    /// `trace`), independent of `RUST_LOG`.
+    #[serde(default = "default_level")]
+    pub level: String,
+    /// Spans and events buffered per request; later ones are only counted.
+    #[serde(default = "default_max_records")]
+    pub max_records: usize,
+    /// Kept traces listed on `GET /diagnostics/traces`.
+    #[serde(default = "default_keep")]
+    pub keep: usize,
+    /// Collector endpoint every kept trace is POSTed to as JSON.
+    #[serde(default)]
+    pub export_url: Option<String>,
+}
+
+impl Default for SamplingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            latency_threshold_ms: default_latency_threshold_ms(),
+            level: default_level(),
+            max_records: default_max_records(),
+            keep: default_keep(),
+            export_url: None,
+        }
+    }
+}
+
+fn default_latency_threshold_ms() -> u64 {
+    1_000
+}
+
+fn default_level() -> String {
+    "debug".into()
+}
+
+fn default_max_records() -> usize {
+    512
+}
+
+fn default_keep() -> usize {
+    50
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SampleReason {
+    /// Answered `5xx` or logged an `ERROR` event.
+    Error,
+    /// Took at least `latency_threshold_ms`.
+    Slow,
+}
+
+/// A span or event recorded under a request. Offsets and durations are in microseconds since
+/// the request started.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TraceRecord {
+    Span {
+        id: u64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        parent: Option<u64>,
+        name: &'static str,
+        offset_us: u64,
+        duration_us: u64,
+        fields: BTreeMap<String, String>,
+    },
+    Event {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        span: Option<u64>,
+        level: String,
+        target: String,
+        offset_us: u64,
+        fields: BTreeMap<String, String>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SampledTrace {
+    pub trace_id: String,
+    pub method: String,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    pub started_at_ms: u64,
+    pub duration_ms: u64,
+    pub reason: SampleReason,
+    /// Spans in the order they closed, interleaved with events in the order they happened.
+    pub records: Vec<TraceRecord>,
+    /// Records beyond `max_records` that were not buffered.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub dropped: usize,
+}
+
+fn is_zero(value: &usize) -> bool {
+    *value == 0
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SamplingReport {
+    pub enabled: bool,
+    /// Requests traced since startup, kept or not.
+    pub requests: u64,
+    pub kept: u64,
+    /// The most recent kept traces, newest first.
+    pub traces: Vec<SampledTrace>,
+}
+
+struct Active {
+    threshold: Duration,
+    max_records: usize,
+    keep: usize,
+}
+
+struct Inner {
+    /// Most verbose level buffered, as `level_rank`; `0` while sampling is off.
+    level: AtomicUsize,
+    active: RwLock<Option<Active>>,
+    kept: Mutex<VecDeque<Arc<SampledTrace>>>,
+    requests: AtomicU64,
+    kept_total: AtomicU64,
+    exports: broadcast::Sender<Arc<SampledTrace>>,
+}
+
+/// Decides which request traces are kept; see the module docs.
+#[derive(Clone)]
+pub struct TailSampler {
+    inner: Arc<Inner>,
+}
+
+impl Default for TailSampler {
+    fn default() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                level: AtomicUsize::new(0),
+                active: RwLock::new(None),
+                kept: Mutex::default(),
+                requests: AtomicU64::new(0),
+                kept_total: AtomicU64::new(0),
+                exports: broadcast::channel(64).0,
+            }),
+        }
+    }
+}
+
+impl TailSampler {
+    /// The sampler behind the layer `init_tracing` installs.
+    pub fn global() -> Self {
+        GLOBAL.clone()
+    }
+
+    /// Apply `config`, turning sampling on or off. Traces already kept stay listed.
+    pub fn configure(&self, config: &SamplingConfig) -> Result<()> {
+        let level = Level::from_str(&config.level)
+            .map_err(|_| anyhow!("invalid [telemetry.sampling].level {:?}", config.level))?;
+        *self.inner.active.write() = config.enabled.then(|| Active {
+            threshold: Duration::from_millis(config.latency_threshold_ms),
+            max_records: config.max_records,
+            keep: config.keep.max(1),
+        });
+        let rank = if config.enabled {
+            level_rank(&level)
+        } else {
+            0
+        };
+        self.inner.level.store(rank, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.level.load(Ordering::Relaxed) > 0
+    }
+
+    /// The layer feeding this sampler, filtered to what it buffers.
+    pub fn layer<S>(&self) -> impl Layer<S> + Send + Sync + 'static
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        let sampler = self.clone();
+        SamplingLayer {
+            sampler: self.clone(),
+        }
+        .with_filter(filter_fn(move |meta| sampler.wants(meta)))
+    }
+
+    pub fn report(&self) -> SamplingReport {
+        SamplingReport {
+            enabled: self.is_enabled(),
+            requests: self.inner.requests.load(Ordering::Relaxed),
+            kept: self.inner.kept_total.load(Ordering::Relaxed),
+            traces: self
+                .inner
+                .kept
+                .lock()
+                .iter()
+                .rev()
+                .map(|trace| trace.as_ref().clone())
+                .collect(),
+        }
+    }
+
+    /// POST every trace kept from now on to `url`. Runs until the sampler is dropped.
+    pub async fn export(self, http: OutboundHttp, url: String) -> Result<()> {
+        let mut kept = self.inner.exports.subscribe();
+        loop {
+            let trace = match kept.recv().await {
+                Ok(trace) => trace,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!(
+                        skipped,
+                        "trace export fell behind; traces were not exported"
+                    );
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            };
+            let url = url.clone();
+            let payload = serde_json::to_value(trace.as_ref())?;
+            let outcome =
+                tokio::task::spawn_blocking(move || http.post_json("trace export", &url, payload))
+                    .await?;
+            match outcome {
+                Ok((status, _)) if (200..300).contains(&status) => {}
+                Ok((status, body)) => {
+                    warn!(status, %body, trace_id = %trace.trace_id, "trace collector rejected a trace")
+                }
+                Err(err) => warn!(?err, trace_id = %trace.trace_id, "trace export failed"),
+            }
+        }
+    }
+
+    fn wants(&self, meta: &Metadata<'_>) -> bool {
+        let rank = self.inner.level.load(Ordering::Relaxed);
+        rank > 0 && (meta.name() == REQUEST_SPAN || level_rank(meta.level()) <= rank)
+    }
+
+    fn max_records(&self) -> usize {
+        self.inner
+            .active
+            .read()
+            .as_ref()
+            .map_or(0, |active| active.max_records)
+    }
+
+    /// Decide on a request whose root span closed. Spans still open under it (tasks that
+    /// outlive the request) keep writing to the emptied buffer, which nothing reads.
+    fn finish(&self, buffer: &mut RequestBuffer, root_fields: BTreeMap<String, String>) {
+        self.inner.requests.fetch_add(1, Ordering::Relaxed);
+        let Some((threshold, keep)) = self
+            .inner
+            .active
+            .read()
+            .as_ref()
+            .map(|active| (active.threshold, active.keep))
+        else {
+            return;
+        };
+        let elapsed = buffer.started.elapsed();
+        let status = root_fields
+            .get("status")
+            .and_then(|status| status.parse::<u16>().ok());
+        let reason = if buffer.errored || status.is_some_and(|status| status >= 500) {
+            SampleReason::Error
+        } else if elapsed >= threshold {
+            SampleReason::Slow
+        } else {
+            return;
+        };
+        let field = |name: &str| root_fields.get(name).cloned().unwrap_or_default();
+        let trace = Arc::new(SampledTrace {
+            trace_id: field("trace_id"),
+            method: field("method"),
+            path: field("path"),
+            status,
+            started_at_ms: buffer.started_at_ms,
+            duration_ms: elapsed.as_millis() as u64,
+            reason,
+            records: std::mem::take(&mut buffer.records),
+            dropped: buffer.dropped,
+        });
+        self.inner.kept_total.fetch_add(1, Ordering::Relaxed);
+        let mut kept = self.inner.kept.lock();
+        while kept.len() >= keep {
+            kept.pop_front();
+        }
+        kept.push_back(trace.clone());
+        drop(kept);
+        let _ = self.inner.exports.send(trace);
+    }
+}
+
+/// Run each request in an [`REQUEST_SPAN`] span while sampling is on, recording its status.
+/// The trace id comes from an inbound `traceparent`, else a new one is started.
+pub async fn trace_requests(
+    State(sampler): State<TailSampler>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !sampler.is_enabled() {
+        return next.run(request).await;
+    }
+    let trace = request
+        .headers()
+        .get(TRACEPARENT)
+        .and_then(|value| value.to_str().ok())
+        .and_then(TraceContext::parse)
+        .unwrap_or_else(TraceContext::new_root);
+    let span = tracing::info_span!(
+        "http_request",
+        method = %request.method(),
+        path = %request.uri().path(),
+        trace_id = %trace.trace_id,
+        status = tracing::field::Empty,
+    );
+    let response = next.run(request).instrument(span.clone()).await;
+    span.record("status", response.status().as_u16());
+    response
+}
+
+/// `error` = 1 … `trace` = 5, so a larger rank is more verbose.
+fn level_rank(level: &Level) -> usize {
+    match *level {
+        Level::ERROR => 1,
+        Level::WARN => 2,
+        Level::INFO => 3,
+        Level::DEBUG => 4,
+        Level::TRACE => 5,
+    }
+}
+
+struct RequestBuffer {
+    started: Instant,
+    started_at_ms: u64,
+    records: Vec<TraceRecord>,
+    dropped: usize,
+    errored: bool,
+}
+
+impl RequestBuffer {
+    fn offset_us(&self, at: Instant) -> u64 {
+        at.saturating_duration_since(self.started).as_micros() as u64
+    }
+
+    fn push(&mut self, record: TraceRecord, max_records: usize) {
+        if self.records.len() < max_records {
+            self.records.push(record);
+        } else {
+            self.dropped += 1;
+        }
+    }
+}
+
+/// Span extension linking a span to the request it runs under.
+struct Buffered {
+    request: Arc<Mutex<RequestBuffer>>,
+    root: bool,
+    started: Instant,
+    fields: BTreeMap<String, String>,
+}
+
+struct SamplingLayer {
+    sampler: TailSampler,
+}
+
+impl SamplingLayer {
+    fn request_of<S>(span: &SpanRef<'_, S>) -> Option<Arc<Mutex<RequestBuffer>>>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        span.extensions()
+            .get::<Buffered>()
+            .map(|buffered| buffered.request.clone())
+    }
+}
+
+impl<S> Layer<S> for SamplingLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let root = attrs.metadata().name() == REQUEST_SPAN;
+        let request = if root {
+            Arc::new(Mutex::new(RequestBuffer {
+                started: Instant::now(),
+                started_at_ms: crate::now_millis(),
+                records: Vec::new(),
+                dropped: 0,
+                errored: false,
+            }))
+        } else {
+            match span.parent().as_ref().and_then(Self::request_of) {
+                Some(request) => request,
+                None => return,
+            }
+        };
+        let mut fields = BTreeMap::new();
+        attrs.record(&mut FieldMap(&mut fields));
+        span.extensions_mut().insert(Buffered {
+            request,
+            root,
+            started: Instant::now(),
+            fields,
+        });
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id)
+            && let Some(buffered) = span.extensions_mut().get_mut::<Buffered>()
+        {
+            values.record(&mut FieldMap(&mut buffered.fields));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.event_span(event) else {
+            return;
+        };
+        let Some(request) = Self::request_of(&span) else {
+            return;
+        };
+        let mut fields = BTreeMap::new();
+        event.record(&mut FieldMap(&mut fields));
+        let meta = event.metadata();
+        let mut request = request.lock();
+        request.errored |= *meta.level() == Level::ERROR;
+        let record = TraceRecord::Event {
+            span: Some(span.id().into_u64()),
+            level: meta.level().to_string(),
+            target: meta.target().to_string(),
+            offset_us: request.offset_us(Instant::now()),
+            fields,
+        };
+        request.push(record, self.sampler.max_records());
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(buffered) = span.extensions_mut().remove::<Buffered>() else {
+            return;
+        };
+        let mut request = buffered.request.lock();
+        if buffered.root {
+            self.sampler.finish(&mut request, buffered.fields);
+            return;
+        }
+        let record = TraceRecord::Span {
+            id: id.into_u64(),
+            parent: span.parent().map(|parent| parent.id().into_u64()),
+            name: span.name(),
+            offset_us: request.offset_us(buffered.started),
+            duration_us: buffered.started.elapsed().as_micros() as u64,
+            fields: buffered.fields,
+        };
+        request.push(record, self.sampler.max_records());
+    }
+}
+
+struct FieldMap<'a>(&'a mut BTreeMap<String, String>);
+
+impl Visit for FieldMap<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{value:?}"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing::{debug, error, info, info_span};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    fn sampler(config: SamplingConfig) -> (TailSampler, tracing::subscriber::DefaultGuard) {
+        let sampler = TailSampler::default();
+        sampler.configure(&config).unwrap();
+        let subscriber = tracing_subscriber::registry().with(sampler.layer());
+        (sampler, tracing::subscriber::set_default(subscriber))
+    }
+
+    fn enabled() -> SamplingConfig {
+        SamplingConfig {
+            enabled: true,
+            latency_threshold_ms: 60_000,
+            ..SamplingConfig::default()
+        }
+    }
+
+    fn request(path: &str, status: u16, body: impl FnOnce()) {
+        let span = info_span!(
+            "http_request",
+            method = "POST",
+            path,
+            trace_id = "abc",
+            status = tracing::field::Empty
+        );
+        span.in_scope(body);
+        span.record("status", status);
+    }
+
+    #[test]
+    fn keeps_failed_requests_in_full_and_drops_fast_successes() {
+        let (sampler, _guard) = sampler(enabled());
+        request("/ok", 200, || debug!("fine"));
+        request("/boom", 500, || {
+            let _run = info_span!("flow_run", flow = "menu").entered();
+            debug!(step = 1, "about to fail");
+        });
+        request("/logged", 200, || error!("store unavailable"));
+        info!("outside any request");
+
+        let report = sampler.report();
+        assert_eq!((report.requests, report.kept), (3, 2));
+        let logged = &report.traces[0];
+        assert_eq!(logged.path, "/logged");
+        assert_eq!(logged.reason, SampleReason::Error);
+        let failed = &report.traces[1];
+        assert_eq!(
+            (failed.status, failed.reason),
+            (Some(500), SampleReason::Error)
+        );
+        assert_eq!(failed.trace_id, "abc");
+        assert!(matches!(
+            &failed.records[0],
+            TraceRecord::Event { level, fields, .. }
+                if level == "DEBUG" && fields["step"] == "1"
+        ));
+        assert!(matches!(
+            &failed.records[1],
+            TraceRecord::Span { name: "flow_run", fields, .. } if fields["flow"] == "menu"
+        ));
+    }
+
+    #[test]
+    fn keeps_slow_requests_and_caps_buffered_records() {
+        let (sampler, _guard) = sampler(SamplingConfig {
+            latency_threshold_ms: 0,
+            max_records: 2,
+            keep: 1,
+            ..enabled()
+        });
+        request("/first", 200, || {});
+        request("/chatty", 200, || {
+            for n in 0..5 {
+                debug!(n, "tick");
+            }
+        });
+
+        let report = sampler.report();
+        assert_eq!(report.traces.len(), 1);
+        let slow = &report.traces[0];
+        assert_eq!(
+            (slow.path.as_str(), slow.reason),
+            ("/chatty", SampleReason::Slow)
+        );
+        assert_eq!((slow.records.len(), slow.dropped), (2, 3));
+    }
+
+    #[test]
+    fn disabled_sampler_buffers_nothing() {
+        let (sampler, _guard) = sampler(SamplingConfig::default());
+        request("/boom", 500, || error!("failed"));
+        let report = sampler.report();
+        assert!(!report.enabled);
+        assert_eq!((report.requests, report.kept), (0, 0));
+        assert!(
+            sampler
+                .configure(&SamplingConfig {
+                    level: "loud".into(),
+                    ..enabled()
+                })
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,455 @@
+//! Synthetic tenant traffic for demo environments (`[server.traffic]`, `/dev/traffic`). A
+//! [`TrafficProfile`] sets each tenant's message rate, the weighted flow mix and how often a
+//! message is a card action or answers the user's waiting session; the bridge feeds the
+//! resulting emits and resumes through its own handlers, so dashboards never sit empty.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use anyhow::{Result, bail};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tokio::task::JoinHandle;
+
+const PHRASES: &[&str] = &[
+    "hi there",
+    "what are my options?",
+    "show me the menu",
+    "can I talk to someone?",
+    "thanks, that helps",
+];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrafficConfig {
+    /// Serve the `/dev/traffic` endpoints; off unless a demo config turns it on.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Start generating `profile` as soon as the server is up.
+    #[serde(default)]
+    pub autostart: bool,
+    /// Profile used when `POST /dev/traffic/start` has no body, and for `autostart`.
+    #[serde(default)]
+    pub profile: TrafficProfile,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrafficProfile {
+    /// Messages per minute for each tenant, resumes included.
+    #[serde(default = "default_messages_per_minute")]
+    pub messages_per_minute: f64,
+    /// Tenants to generate for; empty means `[defaults].tenant`.
+    #[serde(default)]
+    pub tenants: Vec<String>,
+    #[serde(default = "default_users_per_tenant")]
+    pub users_per_tenant: usize,
+    /// Flow ids with their relative weights in the mix.
+    #[serde(default)]
+    pub flows: BTreeMap<String, u32>,
+    /// Share of new messages sent as card actions instead of text.
+    #[serde(default = "default_card_ratio")]
+    pub card_ratio: f64,
+    /// Share of turns that answer the user's waiting session when there is one.
+    #[serde(default = "default_resume_ratio")]
+    pub resume_ratio: f64,
+    /// Fixes the generated sequence; random per start when unset.
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+impl Default for TrafficProfile {
+    fn default() -> Self {
+        Self {
+            messages_per_minute: default_messages_per_minute(),
+            tenants: Vec::new(),
+            users_per_tenant: default_users_per_tenant(),
+            flows: BTreeMap::new(),
+            card_ratio: default_card_ratio(),
+            resume_ratio: default_resume_ratio(),
+            seed: None,
+        }
+    }
+}
+
+fn default_messages_per_minute() -> f64 {
+    12.0
+}
+
+fn default_users_per_tenant() -> usize {
+    10
+}
+
+fn default_card_ratio() -> f64 {
+    0.2
+}
+
+fn default_resume_ratio() -> f64 {
+    0.5
+}
+
+impl TrafficProfile {
+    fn validate(&self) -> Result<()> {
+        if !(self.messages_per_minute > 0.0 && self.messages_per_minute.is_finite()) {
+            bail!("messages_per_minute must be positive");
+        }
+        if self.users_per_tenant == 0 {
+            bail!("users_per_tenant must be at least 1");
+        }
+        if self.flows.values().all(|weight| *weight == 0) {
+            bail!("the profile needs at least one flow with a positive weight");
+        }
+        for (name, ratio) in [
+            ("card_ratio", self.card_ratio),
+            ("resume_ratio", self.resume_ratio),
+        ] {
+            if !(0.0..=1.0).contains(&ratio) {
+                bail!("{name} must be between 0 and 1");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One generated turn.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrafficAction {
+    /// A new inbound message to `flow`, after which the user waits for a reply.
+    Message {
+        tenant: String,
+        user: String,
+        flow: String,
+        card: bool,
+        payload: Value,
+    },
+    /// The user's answer to their waiting session.
+    Resume {
+        tenant: String,
+        user: String,
+        payload: Value,
+    },
+}
+
+/// Chooses turns round-robin across tenants and remembers which users are waiting.
+pub struct TrafficPlanner {
+    profile: TrafficProfile,
+    tenants: Vec<String>,
+    next_tenant: usize,
+    waiting: BTreeMap<String, BTreeSet<String>>,
+    rng: XorShift,
+}
+
+impl TrafficPlanner {
+    pub fn new(profile: TrafficProfile, default_tenant: &str, seed: u64) -> Result<Self> {
+        profile.validate()?;
+        let tenants = if profile.tenants.is_empty() {
+            vec![default_tenant.to_string()]
+        } else {
+            profile.tenants.clone()
+        };
+        Ok(Self {
+            rng: XorShift(profile.seed.unwrap_or(seed).max(1)),
+            profile,
+            tenants,
+            next_tenant: 0,
+            waiting: BTreeMap::new(),
+        })
+    }
+
+    /// Delay between turns that gives every tenant its `messages_per_minute`.
+    pub fn pause(&self) -> Duration {
+        Duration::from_secs_f64(
+            60.0 / (self.profile.messages_per_minute * self.tenants.len() as f64),
+        )
+    }
+
+    pub fn next(&mut self) -> TrafficAction {
+        let tenant = self.tenants[self.next_tenant].clone();
+        self.next_tenant = (self.next_tenant + 1) % self.tenants.len();
+        let user = format!(
+            "traffic-user-{}",
+            self.rng.below(self.profile.users_per_tenant as u64)
+        );
+        let waiting = self
+            .waiting
+            .get(&tenant)
+            .is_some_and(|users| users.contains(&user));
+        if waiting && self.rng.chance(self.profile.resume_ratio) {
+            return TrafficAction::Resume {
+                payload: json!({
+                    "text": self.phrase(),
+                    "channel": "webchat",
+                    "synthetic": true,
+                }),
+                tenant,
+                user,
+            };
+        }
+        let flow = self.flow();
+        let card = self.rng.chance(self.profile.card_ratio);
+        let payload = if card {
+            json!({
+                "type": "card_action",
+                "action": "select",
+                "value": format!("option-{}", self.rng.below(4) + 1),
+                "channel": "webchat",
+                "synthetic": true,
+            })
+        } else {
+            json!({ "text": self.phrase(), "channel": "webchat", "synthetic": true })
+        };
+        TrafficAction::Message {
+            tenant,
+            user,
+            flow,
+            card,
+            payload,
+        }
+    }
+
+    /// Track the user's waiting session after `action` was sent.
+    pub fn record(&mut self, action: &TrafficAction, ok: bool) {
+        match action {
+            TrafficAction::Message { tenant, user, .. } if ok => {
+                self.waiting
+                    .entry(tenant.clone())
+                    .or_default()
+                    .insert(user.clone());
+            }
+            TrafficAction::Message { .. } => {}
+            // A failed resume most likely found no session; either way it no longer waits.
+            TrafficAction::Resume { tenant, user, .. } => {
+                if let Some(users) = self.waiting.get_mut(tenant) {
+                    users.remove(user);
+                }
+            }
+        }
+    }
+
+    fn flow(&mut self) -> String {
+        let total: u64 = self.profile.flows.values().map(|w| u64::from(*w)).sum();
+        let mut pick = self.rng.below(total);
+        for (flow, weight) in &self.profile.flows {
+            if pick < u64::from(*weight) {
+                return flow.clone();
+            }
+            pick -= u64::from(*weight);
+        }
+        unreachable!("pick is below the total weight")
+    }
+
+    fn phrase(&mut self) -> &'static str {
+        PHRASES[self.rng.below(PHRASES.len() as u64) as usize]
+    }
+}
+
+/// Turns sent by a running generator.
+#[derive(Debug, Default)]
+pub struct TrafficStats {
+    messages: AtomicU64,
+    cards: AtomicU64,
+    resumes: AtomicU64,
+    errors: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TrafficCounts {
+    /// Messages sent, card actions included.
+    pub messages: u64,
+    pub cards: u64,
+    pub resumes: u64,
+    pub errors: u64,
+}
+
+impl TrafficStats {
+    pub fn record(&self, action: &TrafficAction, ok: bool) {
+        let counter = match action {
+            _ if !ok => &self.errors,
+            TrafficAction::Message { card, .. } => {
+                if *card {
+                    self.cards.fetch_add(1, Ordering::Relaxed);
+                }
+                &self.messages
+            }
+            TrafficAction::Resume { .. } => &self.resumes,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn counts(&self) -> TrafficCounts {
+        TrafficCounts {
+            messages: self.messages.load(Ordering::Relaxed),
+            cards: self.cards.load(Ordering::Relaxed),
+            resumes: self.resumes.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrafficStatus {
+    pub running: bool,
+    /// Profile of the current run, or of the last one once stopped.
+    pub profile: Option<TrafficProfile>,
+    pub started_at_epoch_ms: Option<u64>,
+    pub sent: Option<TrafficCounts>,
+}
+
+struct TrafficRun {
+    profile: TrafficProfile,
+    started_at_epoch_ms: u64,
+    stats: Arc<TrafficStats>,
+    task: JoinHandle<()>,
+}
+
+impl TrafficRun {
+    fn status(&self) -> TrafficStatus {
+        TrafficStatus {
+            running: !self.task.is_finished(),
+            profile: Some(self.profile.clone()),
+            started_at_epoch_ms: Some(self.started_at_epoch_ms),
+            sent: Some(self.stats.counts()),
+        }
+    }
+}
+
+/// The generator run, if any; at most one runs at a time.
+#[derive(Clone, Default)]
+pub struct TrafficControl {
+    run: Arc<Mutex<Option<TrafficRun>>>,
+}
+
+impl TrafficControl {
+    /// Start a run of `profile` whose task `spawn` creates from the stats it must update.
+    /// Returns `None` while another run is still going.
+    pub fn start(
+        &self,
+        profile: TrafficProfile,
+        now_ms: u64,
+        spawn: impl FnOnce(Arc<TrafficStats>) -> JoinHandle<()>,
+    ) -> Option<TrafficStatus> {
+        let mut run = self.run.lock();
+        if run.as_ref().is_some_and(|run| !run.task.is_finished()) {
+            return None;
+        }
+        let stats = Arc::new(TrafficStats::default());
+        let started = run.insert(TrafficRun {
+            profile,
+            started_at_epoch_ms: now_ms,
+            stats: stats.clone(),
+            task: spawn(stats),
+        });
+        Some(started.status())
+    }
+
+    /// Stop the current run; its final status stays visible until the next start.
+    pub fn stop(&self) -> TrafficStatus {
+        let run = self.run.lock();
+        if let Some(run) = run.as_ref() {
+            run.task.abort();
+        }
+        let mut status = status_of(run.as_ref());
+        status.running = false;
+        status
+    }
+
+    pub fn status(&self) -> TrafficStatus {
+        status_of(self.run.lock().as_ref())
+    }
+}
+
+fn status_of(run: Option<&TrafficRun>) -> TrafficStatus {
+    run.map(TrafficRun::status).unwrap_or(TrafficStatus {
+        running: false,
+        profile: None,
+        started_at_epoch_ms: None,
+        sent: None,
+    })
+}
+
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound.max(1)
+    }
+
+    fn chance(&mut self, ratio: f64) -> bool {
+        (self.next() % 10_000) as f64 / 10_000.0 < ratio
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile() -> TrafficProfile {
+        TrafficProfile {
+            messages_per_minute: 30.0,
+            tenants: vec!["acme".into(), "globex".into()],
+            users_per_tenant: 3,
+            flows: BTreeMap::from([("menu".into(), 3), ("support".into(), 1)]),
+            card_ratio: 0.25,
+            resume_ratio: 0.5,
+            seed: Some(11),
+        }
+    }
+
+    #[test]
+    fn planner_follows_the_profile_mix() {
+        let mut planner = TrafficPlanner::new(profile(), "dev", 1).unwrap();
+        assert_eq!(planner.pause(), Duration::from_secs(1));
+
+        let mut tenants = Vec::new();
+        let (mut menu, mut cards, mut messages, mut resumes) = (0, 0, 0, 0);
+        for _ in 0..4_000 {
+            let action = planner.next();
+            match &action {
+                TrafficAction::Message {
+                    tenant, flow, card, ..
+                } => {
+                    tenants.push(tenant.clone());
+                    messages += 1;
+                    menu += usize::from(flow == "menu");
+                    cards += usize::from(*card);
+                }
+                TrafficAction::Resume { tenant, user, .. } => {
+                    tenants.push(tenant.clone());
+                    assert!(planner.waiting[tenant].contains(user));
+                    resumes += 1;
+                }
+            }
+            planner.record(&action, true);
+        }
+        assert_eq!(tenants[..4], ["acme", "globex", "acme", "globex"]);
+        assert!(resumes > 0);
+        let share = |part: usize, whole: usize| part as f64 / whole as f64;
+        assert!((share(menu, messages) - 0.75).abs() < 0.05);
+        assert!((share(cards, messages) - 0.25).abs() < 0.05);
+
+        let no_flows = TrafficProfile {
+            flows: BTreeMap::new(),
+            ..profile()
+        };
+        assert!(TrafficPlanner::new(no_flows, "dev", 1).is_err());
+        let bad_ratio = TrafficProfile {
+            card_ratio: 1.5,
+            ..profile()
+        };
+        assert!(TrafficPlanner::new(bad_ratio, "dev", 1).is_err());
+    }
+}
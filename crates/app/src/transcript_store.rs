@@ -0,0 +1,381 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, OpenOptions},
+    io::Write,
+    sync::Arc,
+};
+
+use anyhow::{Context, Result, anyhow};
+use camino::Utf8PathBuf;
+use parking_lot::Mutex;
+use redis::Commands;
+use serde::{Deserialize, Serialize};
+
+use crate::path_safety::normalize_under_root;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Inbound,
+    Outbound,
+}
+
+/// One message exchanged on a session, in arrival order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TranscriptEntry {
+    pub timestamp_ms: u64,
+    pub direction: Direction,
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flow_id: Option<String>,
+    /// Flow node that produced an outbound message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node: Option<String>,
+    /// Tenant the exchange belonged to, so retention can prune per tenant.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+}
+
+impl TranscriptEntry {
+    /// Golden-file form: `USER: ...` for inbound, `BOT: ...` for outbound messages.
+    pub fn line(&self) -> String {
+        let actor = match self.direction {
+            Direction::Inbound => "USER",
+            Direction::Outbound => "BOT",
+        };
+        format!("{actor}: {}", self.text)
+    }
+}
+
+/// Per-session message log (`[stores.transcript]`); it outlives the session record, so a
+/// resumed-and-cleared session can still be inspected.
+pub trait TranscriptStore: Send + Sync {
+    fn append(&self, session_key: &str, entries: &[TranscriptEntry]) -> Result<()>;
+    fn get(&self, session_key: &str) -> Result<Vec<TranscriptEntry>>;
+    /// Drop every entry `expired` selects, across all sessions; returns how many went.
+    fn prune(&self, expired: &dyn Fn(&TranscriptEntry) -> bool) -> Result<usize>;
+}
+
+#[derive(Default)]
+pub struct InMemoryTranscriptStore {
+    inner: Mutex<BTreeMap<String, Vec<TranscriptEntry>>>,
+}
+
+impl InMemoryTranscriptStore {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+}
+
+impl TranscriptStore for InMemoryTranscriptStore {
+    fn append(&self, session_key: &str, entries: &[TranscriptEntry]) -> Result<()> {
+        self.inner
+            .lock()
+            .entry(session_key.to_string())
+            .or_default()
+            .extend_from_slice(entries);
+        Ok(())
+    }
+
+    fn get(&self, session_key: &str) -> Result<Vec<TranscriptEntry>> {
+        Ok(self
+            .inner
+            .lock()
+            .get(session_key)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn prune(&self, expired: &dyn Fn(&TranscriptEntry) -> bool) -> Result<usize> {
+        let mut guard = self.inner.lock();
+        let mut removed = 0;
+        guard.retain(|_, entries| {
+            let before = entries.len();
+            entries.retain(|entry| !expired(entry));
+            removed += before - entries.len();
+            !entries.is_empty()
+        });
+        Ok(removed)
+    }
+}
+
+/// One JSONL file per session under a directory, appended to as messages arrive.
+pub struct FileTranscriptStore {
+    dir: Utf8PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileTranscriptStore {
+    pub fn new(root: Utf8PathBuf, dir: Utf8PathBuf) -> Result<Arc<Self>> {
+        let root = root
+            .as_std_path()
+            .canonicalize()
+            .with_context(|| format!("failed to canonicalize transcript root {root}"))?;
+        let safe_dir = normalize_under_root(&root, dir.as_std_path())?;
+        let dir = Utf8PathBuf::from_path_buf(safe_dir)
+            .map_err(|_| anyhow!("normalized transcript dir is not valid UTF-8"))?;
+        Ok(Arc::new(Self {
+            dir,
+            lock: Mutex::new(()),
+        }))
+    }
+
+    fn path_for(&self, session_key: &str) -> Utf8PathBuf {
+        self.dir.join(format!("{}.jsonl", file_stem(session_key)))
+    }
+}
+
+/// Session keys are caller-supplied: keep `[A-Za-z0-9_-]` keys readable and hex-encode
+/// anything else behind a `~` (which plain keys cannot contain), so names never collide.
+fn file_stem(session_key: &str) -> String {
+    let plain = !session_key.is_empty()
+        && session_key
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+    if plain {
+        session_key.to_string()
+    } else {
+        format!("~{}", hex::encode(session_key))
+    }
+}
+
+impl TranscriptStore for FileTranscriptStore {
+    fn append(&self, session_key: &str, entries: &[TranscriptEntry]) -> Result<()> {
+        let _guard = self.lock.lock();
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("failed to create transcript dir {}", self.dir))?;
+        let path = self.path_for(session_key);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("failed to open {path}"))?;
+        let mut buf = Vec::new();
+        for entry in entries {
+            serde_json::to_writer(&mut buf, entry)?;
+            buf.push(b'\n');
+        }
+        file.write_all(&buf)
+            .with_context(|| format!("failed to append to {path}"))
+    }
+
+    fn get(&self, session_key: &str) -> Result<Vec<TranscriptEntry>> {
+        read_jsonl(&self.path_for(session_key))
+    }
+
+    fn prune(&self, expired: &dyn Fn(&TranscriptEntry) -> bool) -> Result<usize> {
+        let _guard = self.lock.lock();
+        let listing = match fs::read_dir(&self.dir) {
+            Ok(listing) => listing,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(err) => return Err(err).with_context(|| format!("failed to list {}", self.dir)),
+        };
+        let mut removed = 0;
+        for dir_entry in listing {
+            let path = Utf8PathBuf::from_path_buf(dir_entry?.path())
+                .map_err(|path| anyhow!("transcript path {} is not UTF-8", path.display()))?;
+            if path.extension() != Some("jsonl") {
+                continue;
+            }
+            let entries = read_jsonl(&path)?;
+            let kept: Vec<_> = entries.iter().filter(|entry| !expired(entry)).collect();
+            if kept.len() == entries.len() {
+                continue;
+            }
+            removed += entries.len() - kept.len();
+            if kept.is_empty() {
+                fs::remove_file(&path).with_context(|| format!("failed to remove {path}"))?;
+                continue;
+            }
+            let mut buf = Vec::new();
+            for entry in kept {
+                serde_json::to_writer(&mut buf, entry)?;
+                buf.push(b'\n');
+            }
+            fs::write(&path, buf).with_context(|| format!("failed to rewrite {path}"))?;
+        }
+        Ok(removed)
+    }
+}
+
+fn read_jsonl(path: &Utf8PathBuf) -> Result<Vec<TranscriptEntry>> {
+    let raw = match fs::read_to_string(path) {
+        Ok(raw) => raw,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).with_context(|| format!("failed to read {path}")),
+    };
+    raw.lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(idx, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("invalid transcript entry at {path}:{}", idx + 1))
+        })
+        .collect()
+}
+
+/// Redis list per session under `<prefix>:<session_key>`.
+pub struct RedisTranscriptStore {
+    client: redis::Client,
+    prefix: String,
+}
+
+impl RedisTranscriptStore {
+    pub fn new(url: &str, prefix: Option<String>) -> Result<Arc<Self>> {
+        let client = redis::Client::open(url.to_string())
+            .with_context(|| format!("failed to create redis client for {url}"))?;
+        let prefix = prefix.unwrap_or_else(|| "greentic:transcript".to_string());
+        Ok(Arc::new(Self { client, prefix }))
+    }
+
+    fn with_conn<T>(&self, f: impl FnOnce(&mut redis::Connection) -> Result<T>) -> Result<T> {
+        let mut conn = self.client.get_connection().with_context(|| {
+            format!(
+                "failed to connect to redis at {:?}",
+                self.client.get_connection_info()
+            )
+        })?;
+        f(&mut conn)
+    }
+}
+
+impl TranscriptStore for RedisTranscriptStore {
+    fn append(&self, session_key: &str, entries: &[TranscriptEntry]) -> Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let redis_key = format!("{}:{session_key}", self.prefix);
+        let values = entries
+            .iter()
+            .map(serde_json::to_string)
+            .collect::<Result<Vec<_>, _>>()?;
+        self.with_conn(|conn| {
+            conn.rpush(&redis_key, values)
+                .with_context(|| format!("failed to rpush {redis_key}"))
+        })
+    }
+
+    fn get(&self, session_key: &str) -> Result<Vec<TranscriptEntry>> {
+        let redis_key = format!("{}:{session_key}", self.prefix);
+        let values: Vec<String> = self.with_conn(|conn| {
+            conn.lrange(&redis_key, 0, -1)
+                .with_context(|| format!("failed to lrange {redis_key}"))
+        })?;
+        values
+            .iter()
+            .map(|value| {
+                serde_json::from_str(value)
+                    .with_context(|| format!("invalid transcript entry in {redis_key}"))
+            })
+            .collect()
+    }
+
+    fn prune(&self, expired: &dyn Fn(&TranscriptEntry) -> bool) -> Result<usize> {
+        self.with_conn(|conn| {
+            let pattern = format!("{}:*", self.prefix);
+            let keys = conn
+                .scan_match::<_, String>(&pattern)
+                .with_context(|| format!("failed to scan {pattern}"))?
+                .collect::<Result<Vec<_>, _>>()
+                .with_context(|| format!("failed to scan {pattern}"))?;
+            let mut removed = 0;
+            for redis_key in keys {
+                let values: Vec<String> = conn
+                    .lrange(&redis_key, 0, -1)
+                    .with_context(|| format!("failed to lrange {redis_key}"))?;
+                let mut kept = Vec::with_capacity(values.len());
+                for value in &values {
+                    let entry: TranscriptEntry = serde_json::from_str(value)
+                        .with_context(|| format!("invalid transcript entry in {redis_key}"))?;
+                    if !expired(&entry) {
+                        kept.push(value);
+                    }
+                }
+                if kept.len() == values.len() {
+                    continue;
+                }
+                removed += values.len() - kept.len();
+                let mut pipe = redis::pipe();
+                pipe.atomic().del(&redis_key).ignore();
+                if !kept.is_empty() {
+                    pipe.rpush(&redis_key, kept).ignore();
+                }
+                let _: () = pipe
+                    .query(conn)
+                    .with_context(|| format!("failed to rewrite {redis_key}"))?;
+            }
+            Ok(removed)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(direction: Direction, text: &str) -> TranscriptEntry {
+        TranscriptEntry {
+            timestamp_ms: 1,
+            direction,
+            text: text.into(),
+            flow_id: Some("menu".into()),
+            node: None,
+            tenant: Some("acme".into()),
+        }
+    }
+
+    #[test]
+    fn file_store_appends_per_session_across_reopen() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Utf8PathBuf::from_path_buf(tmp.path().to_path_buf()).unwrap();
+        let dir = Utf8PathBuf::from("transcripts");
+
+        let store = FileTranscriptStore::new(root.clone(), dir.clone()).unwrap();
+        store
+            .append("sess-1", &[entry(Direction::Inbound, "About")])
+            .unwrap();
+        store
+            .append("../escape", &[entry(Direction::Inbound, "x")])
+            .unwrap();
+        store
+            .append(
+                "sess-1",
+                &[entry(Direction::Outbound, "Greentic builds demos.")],
+            )
+            .unwrap();
+
+        let reopened = FileTranscriptStore::new(root, dir).unwrap();
+        let lines: Vec<String> = reopened
+            .get("sess-1")
+            .unwrap()
+            .iter()
+            .map(TranscriptEntry::line)
+            .collect();
+        assert_eq!(lines, vec!["USER: About", "BOT: Greentic builds demos."]);
+        assert_eq!(reopened.get("../escape").unwrap().len(), 1);
+        assert!(
+            tmp.path()
+                .join("transcripts/~2e2e2f657363617065.jsonl")
+                .exists()
+        );
+        assert!(reopened.get("unknown").unwrap().is_empty());
+    }
+
+    #[test]
+    fn prune_drops_selected_entries_and_empty_transcripts() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Utf8PathBuf::from_path_buf(tmp.path().to_path_buf()).unwrap();
+        let store = FileTranscriptStore::new(root, Utf8PathBuf::from("transcripts")).unwrap();
+        let mut old = entry(Direction::Inbound, "old");
+        old.timestamp_ms = 0;
+        let mut recent = entry(Direction::Outbound, "recent");
+        recent.timestamp_ms = 10;
+        store.append("sess-1", &[old.clone(), recent]).unwrap();
+        store.append("sess-2", &[old]).unwrap();
+
+        let removed = store.prune(&|entry| entry.timestamp_ms < 5).unwrap();
+        assert_eq!(removed, 2);
+        assert_eq!(store.get("sess-1").unwrap().len(), 1);
+        assert!(!tmp.path().join("transcripts/sess-2.jsonl").exists());
+    }
+}
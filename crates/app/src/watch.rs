@@ -0,0 +1,312 @@
+//! Terminal views for `sessions watch` and `runner watch`: fold the server's session changes
+//! and runner events into a table that is redrawn on every update, or print one line per
+//! update when stdout is not a terminal.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use greentic_integration_client::{RunnerEvent, Session, SessionChange};
+use serde_json::Value;
+
+/// Clear the screen and move the cursor home before a redraw.
+pub const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+/// Recent changes listed under the session table.
+const RECENT_CHANGES: usize = 5;
+
+/// Sessions as last reported by `GET /sessions/stream`, optionally narrowed to one flow.
+#[derive(Debug, Default)]
+pub struct SessionTable {
+    flow: Option<String>,
+    rows: BTreeMap<String, Session>,
+    recent: VecDeque<String>,
+}
+
+impl SessionTable {
+    pub fn new(flow: Option<String>) -> Self {
+        Self {
+            flow,
+            ..Self::default()
+        }
+    }
+
+    /// Fold in one change; returns its one-line description, or `None` when the flow filter
+    /// hides it.
+    pub fn apply(&mut self, change: SessionChange) -> Option<String> {
+        let line = match change.session {
+            Some(session) if self.shows(&session) => {
+                let line = format!("{:<8} {}", change.change, session_line(&session));
+                self.rows.insert(change.key, session);
+                line
+            }
+            // A session that moved to another flow leaves the filtered view.
+            Some(_) => {
+                self.rows.remove(&change.key)?;
+                format!("{:<8} {} (left flow)", "removed", change.key)
+            }
+            None => {
+                self.rows.remove(&change.key)?;
+                format!("{:<8} {}", change.change, change.key)
+            }
+        };
+        if self.recent.len() == RECENT_CHANGES {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(line.clone());
+        Some(line)
+    }
+
+    fn shows(&self, session: &Session) -> bool {
+        self.flow
+            .as_ref()
+            .is_none_or(|flow| session.cursor.flow_id.as_ref() == Some(flow))
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "{:<38} {:<12} {:<12} {:<14} {:<16} {:<12}\n",
+            "KEY", "TENANT", "TEAM", "USER", "FLOW", "NODE"
+        );
+        let mut rows: Vec<&Session> = self.rows.values().collect();
+        rows.sort_by_key(|session| std::cmp::Reverse(session.updated_at_epoch_ms));
+        for session in rows {
+            out.push_str(&format!(
+                "{:<38} {:<12} {:<12} {:<14} {:<16} {:<12}\n",
+                session.key,
+                session.tenant,
+                dash(session.team.as_deref()),
+                dash(session.user.as_deref()),
+                dash(session.cursor.flow_id.as_deref()),
+                dash(session.cursor.node_id.as_deref()),
+            ));
+        }
+        out.push_str(&format!(
+            "\n{} session(s); recent changes:\n",
+            self.rows.len()
+        ));
+        for line in &self.recent {
+            out.push_str(&format!("  {line}\n"));
+        }
+        out
+    }
+}
+
+fn session_line(session: &Session) -> String {
+    format!(
+        "{} tenant={} team={} user={} flow={} node={}",
+        session.key,
+        session.tenant,
+        dash(session.team.as_deref()),
+        dash(session.user.as_deref()),
+        dash(session.cursor.flow_id.as_deref()),
+        dash(session.cursor.node_id.as_deref()),
+    )
+}
+
+/// Client-side filters of `runner watch`; unset fields match everything.
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    pub flow: Option<String>,
+    pub tenant: Option<String>,
+    /// `result.status`, e.g. `ok` or `error`.
+    pub status: Option<String>,
+}
+
+impl EventFilter {
+    pub fn matches(&self, event: &RunnerEvent) -> bool {
+        self.flow.as_ref().is_none_or(|flow| &event.flow == flow)
+            && self
+                .tenant
+                .as_ref()
+                .is_none_or(|tenant| event.tenant.as_ref() == Some(tenant))
+            && self
+                .status
+                .as_deref()
+                .is_none_or(|status| event_status(event) == status)
+    }
+}
+
+/// The latest matching runner events, newest first.
+#[derive(Debug)]
+pub struct EventTable {
+    filter: EventFilter,
+    limit: usize,
+    rows: VecDeque<RunnerEvent>,
+    seen: usize,
+}
+
+impl EventTable {
+    pub fn new(filter: EventFilter, limit: usize) -> Self {
+        Self {
+            filter,
+            limit: limit.max(1),
+            rows: VecDeque::new(),
+            seen: 0,
+        }
+    }
+
+    /// Keep `event` if it matches; returns its one-line description when kept.
+    pub fn apply(&mut self, event: RunnerEvent) -> Option<String> {
+        if !self.filter.matches(&event) {
+            return None;
+        }
+        self.seen += 1;
+        let line = event_line(&event);
+        if self.rows.len() == self.limit {
+            self.rows.pop_back();
+        }
+        self.rows.push_front(event);
+        Some(line)
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "{:<15} {:<20} {:<12} {:<12} {:<14} {:<8}\n",
+            "TIMESTAMP_MS", "FLOW", "TENANT", "TEAM", "USER", "STATUS"
+        );
+        for event in &self.rows {
+            out.push_str(&format!(
+                "{:<15} {:<20} {:<12} {:<12} {:<14} {:<8}\n",
+                event.timestamp_ms,
+                event.flow,
+                dash(event.tenant.as_deref()),
+                dash(event.team.as_deref()),
+                dash(event.user.as_deref()),
+                event_status(event),
+            ));
+        }
+        out.push_str(&format!(
+            "\n{} matching event(s) since watching; showing the latest {}\n",
+            self.seen,
+            self.rows.len()
+        ));
+        out
+    }
+}
+
+fn event_line(event: &RunnerEvent) -> String {
+    format!(
+        "[{}] flow={} tenant={} team={} user={} status={}",
+        event.timestamp_ms,
+        event.flow,
+        dash(event.tenant.as_deref()),
+        dash(event.team.as_deref()),
+        dash(event.user.as_deref()),
+        event_status(event),
+    )
+}
+
+fn event_status(event: &RunnerEvent) -> &str {
+    event
+        .result
+        .get("status")
+        .and_then(Value::as_str)
+        .unwrap_or("-")
+}
+
+fn dash(value: Option<&str>) -> &str {
+    value.unwrap_or("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use greentic_integration_client::SessionCursor;
+    use serde_json::json;
+
+    fn session(key: &str, flow: &str, node: &str) -> Session {
+        Session {
+            key: key.into(),
+            tenant: "acme".into(),
+            team: None,
+            user: Some("user-1".into()),
+            cursor: SessionCursor {
+                flow_id: Some(flow.into()),
+                node_id: Some(node.into()),
+            },
+            context: Value::Null,
+            updated_at_epoch_ms: 1,
+            pack_id: None,
+            flow_version: None,
+            needs_upgrade: false,
+            locale: None,
+            ttl_ms: None,
+            pack_generation: None,
+            runner_target: None,
+            nudges: Vec::new(),
+        }
+    }
+
+    fn change(change: &str, key: &str, session: Option<Session>) -> SessionChange {
+        SessionChange {
+            change: change.into(),
+            key: key.into(),
+            session,
+        }
+    }
+
+    #[test]
+    fn session_table_tracks_changes_within_the_flow_filter() {
+        let mut table = SessionTable::new(Some("chat".into()));
+        assert!(
+            table
+                .apply(change("created", "a", Some(session("a", "chat", "start"))))
+                .is_some()
+        );
+        assert!(
+            table
+                .apply(change(
+                    "created",
+                    "b",
+                    Some(session("b", "billing", "start"))
+                ))
+                .is_none()
+        );
+        let line = table
+            .apply(change("updated", "a", Some(session("a", "chat", "reply"))))
+            .unwrap();
+        assert!(
+            line.starts_with("updated") && line.contains("node=reply"),
+            "{line}"
+        );
+        assert!(table.render().contains("1 session(s)"));
+
+        let line = table
+            .apply(change(
+                "updated",
+                "a",
+                Some(session("a", "billing", "start")),
+            ))
+            .unwrap();
+        assert!(line.contains("left flow"), "{line}");
+        assert!(table.apply(change("removed", "a", None)).is_none());
+        assert!(table.render().contains("0 session(s)"));
+    }
+
+    #[test]
+    fn event_table_filters_and_keeps_the_latest() {
+        let event = |flow: &str, status: &str| RunnerEvent {
+            timestamp_ms: 1,
+            flow: flow.into(),
+            tenant: Some("acme".into()),
+            team: None,
+            user: None,
+            payload: Value::Null,
+            result: json!({"status": status}),
+            sequence: None,
+            instance: None,
+        };
+        let filter = EventFilter {
+            status: Some("error".into()),
+            ..EventFilter::default()
+        };
+        let mut table = EventTable::new(filter, 2);
+        assert!(table.apply(event("chat", "ok")).is_none());
+        for flow in ["a", "b", "c"] {
+            assert!(table.apply(event(flow, "error")).is_some());
+        }
+        let rendered = table.render();
+        assert!(rendered.contains("3 matching event(s)"), "{rendered}");
+        let flows: Vec<_> = table.rows.iter().map(|event| event.flow.as_str()).collect();
+        assert_eq!(flows, vec!["c", "b"]);
+    }
+}
@@ -0,0 +1,308 @@
+//! Boots `greentic-integration serve` on a free port and drives the bridge API through the typed
+//! client: packs, session upsert/list/resume, runner emit and the event stream, plus the
+//! `testkit` simulated user on top of it.
+
+use std::{
+    net::TcpListener,
+    process::{Child, Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use futures::StreamExt;
+use greentic_integration_client::{
+    BridgeClient, EmitRequest, PackQuery, ResumeRequest, SessionQuery, SessionUpsert,
+};
+use serde_json::json;
+
+struct Server {
+    child: Child,
+    _config: tempfile::TempDir,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+async fn start_server() -> anyhow::Result<(Server, BridgeClient)> {
+    let port = TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+    let config = tempfile::tempdir()?;
+    let config_path = config.path().join("bridge.toml");
+    std::fs::write(
+        &config_path,
+        format!(
+            "[server]\nlisten_addr = \"127.0.0.1:{port}\"\n\n[stores.session]\nbackend = \"memory\"\n"
+        ),
+    )?;
+    let child = Command::new(env!("CARGO_BIN_EXE_greentic-integration"))
+        .arg("serve")
+        .arg("--config")
+        .arg(&config_path)
+        .env("RUST_LOG", "warn")
+        .env_remove("RUNNER_PROXY_URL")
+        .env_remove("GREENTIC_RUNNER_URL")
+        .stdout(Stdio::null())
+        .spawn()?;
+    let server = Server {
+        child,
+        _config: config,
+    };
+
+    let client =
+        BridgeClient::with_timeout(format!("http://127.0.0.1:{port}"), Duration::from_secs(5));
+    let deadline = Instant::now() + Duration::from_secs(30);
+    loop {
+        match client.healthz().await {
+            Ok(_) => return Ok((server, client)),
+            Err(err) if Instant::now() > deadline => {
+                anyhow::bail!("bridge did not become healthy: {err}")
+            }
+            Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+        }
+    }
+}
+
+#[tokio::test]
+async fn e2e_bridge_client_round_trip() -> anyhow::Result<()> {
+    let (_server, client) = start_server().await?;
+
+    let packs = client.list_packs(&PackQuery::default()).await?;
+    assert_eq!(packs.count, packs.packs.len());
+    assert!(
+        packs.packs.iter().any(|pack| pack.id == "demo-menu"),
+        "demo-menu missing from {:?}",
+        packs.packs
+    );
+    let reloaded = client.reload_packs().await?;
+    assert!(reloaded.index_generation > packs.index_generation);
+
+    client.clear_runner_events().await?;
+    let mut events = Box::pin(client.stream_events(Duration::from_millis(50)));
+
+    let session = client
+        .upsert_session(&SessionUpsert {
+            key: Some("client-e2e".into()),
+            tenant: Some("dev".into()),
+            user: Some("user-client".into()),
+            flow_id: Some("menu".into()),
+            node_id: Some("wait".into()),
+            context: Some(json!({"step": 1})),
+            ..SessionUpsert::default()
+        })
+        .await?;
+    assert_eq!(session.cursor.flow_id.as_deref(), Some("menu"));
+    let listed = client
+        .list_sessions(&SessionQuery {
+            user: Some("user-client".into()),
+            ..SessionQuery::default()
+        })
+        .await?;
+    assert_eq!(listed.count, 1);
+    assert_eq!(listed.sessions[0].key, "client-e2e");
+    let fetched = client.get_session("client-e2e").await?;
+    assert_eq!(fetched.context, json!({"step": 1}));
+
+    let resumed = client
+        .resume(&ResumeRequest {
+            tenant: Some("dev".into()),
+            ..ResumeRequest::new("user-client", json!({"text": "About"}))
+        })
+        .await?;
+    assert_eq!(resumed.flow, "menu");
+    let gone = client.get_session("client-e2e").await.unwrap_err();
+    assert_eq!(gone.status(), Some(404));
+
+    let emitted = client
+        .emit(&EmitRequest {
+            tenant: Some("dev".into()),
+            ..EmitRequest::new("ping", json!({"n": 1}))
+        })
+        .await?;
+    assert_eq!(emitted.flow, "ping");
+
+    let mut flows = Vec::new();
+    while flows.len() < 2 {
+        let event = tokio::time::timeout(Duration::from_secs(10), events.next())
+            .await?
+            .expect("event stream ended")?;
+        flows.push(event.flow);
+    }
+    assert_eq!(flows, ["menu", "ping"]);
+
+    let missing = client
+        .resume(&ResumeRequest::new("nobody", json!(null)))
+        .await
+        .unwrap_err();
+    assert_eq!(missing.status(), Some(404), "{missing}");
+    Ok(())
+}
+
+#[tokio::test]
+async fn e2e_bridge_client_watch_streams() -> anyhow::Result<()> {
+    let (_server, client) = start_server().await?;
+    let timeout = Duration::from_secs(10);
+
+    let mut events = Box::pin(client.watch_runner_events());
+    // The subscription is live once the server has answered; give it a moment to attach.
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    client
+        .emit(&EmitRequest::new("flow-watch", json!({"text": "hi"})))
+        .await?;
+    let event = tokio::time::timeout(timeout, events.next())
+        .await?
+        .expect("event stream ended")?;
+    assert_eq!(event.flow, "flow-watch");
+
+    let query = SessionQuery {
+        tenant: Some("watch-tenant".into()),
+        ..SessionQuery::default()
+    };
+    let mut changes = Box::pin(client.watch_sessions(&query));
+    let session = client
+        .upsert_session(&SessionUpsert {
+            tenant: Some("watch-tenant".into()),
+            user: Some("watcher".into()),
+            flow_id: Some("flow-watch".into()),
+            ..SessionUpsert::default()
+        })
+        .await?;
+    let change = tokio::time::timeout(timeout, changes.next())
+        .await?
+        .expect("session stream ended")?;
+    assert_eq!(
+        (change.change.as_str(), change.key.as_str()),
+        ("created", session.key.as_str())
+    );
+    assert_eq!(change.session.unwrap().user.as_deref(), Some("watcher"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn e2e_soak_run_checks_invariants_and_writes_checkpoints() -> anyhow::Result<()> {
+    let (_server, client) = start_server().await?;
+    let diagnostics = client.process_diagnostics().await?;
+    assert_eq!(diagnostics.runner_events.capacity, 100);
+
+    let out = tempfile::tempdir()?;
+    let output = Command::new(env!("CARGO_BIN_EXE_greentic-integration"))
+        .args(["loadtest", "soak", "--server", client.base_url()])
+        .args([
+            "--duration",
+            "3s",
+            "--check-every",
+            "1s",
+            "--checkpoint-every",
+            "2s",
+        ])
+        .args(["--rate", "30", "--users", "4", "--max-p99-ms", "2000"])
+        .arg("--out")
+        .arg(out.path())
+        .env("RUST_LOG", "warn")
+        .output()?;
+    assert!(
+        output.status.success(),
+        "soak failed: {}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let summary: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(out.path().join("summary.json"))?)?;
+    assert_eq!(summary["violations"], json!([]));
+    assert!(summary["ops"].as_u64().unwrap() > 0);
+    assert!(summary["checks"].as_u64().unwrap() >= 3);
+    assert!(out.path().join("checkpoint-001.json").exists());
+    // Sessions the workload left open are resumed when it finishes.
+    let left = client
+        .list_sessions(&SessionQuery {
+            tenant: Some("soak".into()),
+            ..SessionQuery::default()
+        })
+        .await?;
+    assert_eq!(left.count, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn e2e_testkit_sim_user_chats_through_the_bridge() -> anyhow::Result<()> {
+    use greentic_integration::testkit::SimUser;
+
+    let (_server, client) = start_server().await?;
+    let mut user = SimUser::new(client.clone(), "sim-user").tenant("dev");
+    user.start("menu", "hi").await?;
+    client
+        .upsert_session(&SessionUpsert {
+            tenant: Some("dev".into()),
+            user: Some("sim-user".into()),
+            flow_id: Some("menu".into()),
+            node_id: Some("wait".into()),
+            ..SessionUpsert::default()
+        })
+        .await?;
+    user.reply("About").await?;
+    let said: Vec<_> = user
+        .transcript()
+        .iter()
+        .filter(|line| line.starts_with("USER: "))
+        .collect();
+    assert_eq!(said, ["USER: hi", "USER: About"]);
+    // The session was consumed, so a further reply has nothing to resume.
+    assert!(user.reply("again").await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn e2e_runner_compare_reports_only_real_differences() -> anyhow::Result<()> {
+    let (_baseline_server, baseline) = start_server().await?;
+    let (_candidate_server, candidate) = start_server().await?;
+    let recording = tempfile::tempdir()?;
+    let events = recording.path().join("events.jsonl");
+    let report = recording.path().join("report.json");
+    std::fs::write(
+        &events,
+        [
+            json!({"timestamp_ms": 1, "flow": "ping", "tenant": "dev", "user": "alice", "payload": {"text": "hi"}, "result": {}}),
+            json!({"timestamp_ms": 2, "flow": "ping", "tenant": "dev", "user": "alice", "payload": {"text": "again"}, "result": {}}),
+        ]
+        .map(|event| event.to_string())
+        .join("\n"),
+    )?;
+    let compare = || {
+        Command::new(env!("CARGO_BIN_EXE_greentic-integration"))
+            .args(["runner", "compare", "--from"])
+            .arg(&events)
+            .args(["--baseline", baseline.base_url()])
+            .args(["--candidate", candidate.base_url()])
+            .arg("--out")
+            .arg(&report)
+            .output()
+    };
+
+    let same = compare()?;
+    let stdout = String::from_utf8_lossy(&same.stdout);
+    assert!(same.status.success(), "{stdout}");
+    assert!(
+        stdout.contains("2 of 2 replayed event(s) identical"),
+        "{stdout}"
+    );
+
+    // A session only the candidate holds for a replayed user is a regression.
+    candidate
+        .upsert_session(&SessionUpsert {
+            tenant: Some("dev".into()),
+            user: Some("alice".into()),
+            flow_id: Some("menu".into()),
+            node_id: Some("wait".into()),
+            ..SessionUpsert::default()
+        })
+        .await?;
+    let differs = compare()?;
+    assert!(!differs.status.success());
+    let written: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&report)?)?;
+    assert_eq!(written["identical"], 2);
+    assert_eq!(written["sessions"][0]["path"], "/dev:-:alice");
+    assert!(written["sessions"][0].get("baseline").is_none());
+    Ok(())
+}
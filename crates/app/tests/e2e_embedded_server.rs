@@ -0,0 +1,49 @@
+//! Embeds the bridge in-process through `greentic_integration::Server` on a free port, with a
+//! caller-provided session store, and drives it through the typed client.
+
+use greentic_integration::{
+    AppConfig, Server,
+    session::{InMemorySessionStore, SessionFilter, SessionStore},
+};
+use greentic_integration_client::{PackQuery, SessionUpsert};
+use serde_json::json;
+
+#[tokio::test]
+async fn embedded_server_serves_a_custom_session_store() -> anyhow::Result<()> {
+    let store = InMemorySessionStore::new();
+    let config = AppConfig::from_toml("[server.health]\ninterval_secs = 3600\n")?;
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let server = Server::from_config(config)
+        .with_session_store(store.clone())
+        .serve(listener)
+        .await?;
+    assert_eq!(
+        server.base_url(),
+        format!("http://127.0.0.1:{}", server.local_addr().port())
+    );
+
+    let client = server.client();
+    client.healthz().await?;
+    let packs = client.list_packs(&PackQuery::default()).await?;
+    assert!(packs.packs.iter().any(|pack| pack.id == "demo-menu"));
+
+    client
+        .upsert_session(&SessionUpsert {
+            key: Some("embedded".into()),
+            tenant: Some("dev".into()),
+            user: Some("user-embedded".into()),
+            flow_id: Some("menu".into()),
+            node_id: Some("wait".into()),
+            context: Some(json!({"step": 1})),
+            ..SessionUpsert::default()
+        })
+        .await?;
+    let filter = SessionFilter::new(Some("dev".into()), None, Some("user-embedded".into()));
+    let stored = store.find(&filter)?.expect("session in the custom store");
+    assert_eq!(stored.key, "embedded");
+    assert!(server.session_store().find(&filter)?.is_some());
+
+    server.shutdown().await?;
+    assert!(client.healthz().await.is_err());
+    Ok(())
+}
@@ -13,8 +13,22 @@ async fn e2e_infra_round_trip() -> anyhow::Result<()> {
         std::env::set_var("E2E_TEST_NAME", "e2e_infra");
     }
 
-    let env = TestEnv::up().await?;
+    let mut env = TestEnv::up().await?;
     env.healthcheck().await?;
+    // Collectors run while the services are still up, even if the test bails out early.
+    env.on_teardown(|ctx| {
+        let output = std::process::Command::new("psql")
+            .arg(ctx.db_url)
+            .args(["-c", "SELECT datname, state FROM pg_stat_activity"])
+            .output();
+        let dump = match output {
+            Ok(output) => String::from_utf8_lossy(&output.stdout).into_owned(),
+            Err(err) => format!("psql unavailable: {err}\n"),
+        };
+        ctx.write_artifact("collectors/pg_stat_activity.txt", dump)?;
+        Ok(())
+    });
+    let collected = env.artifacts_dir().join("collectors/pg_stat_activity.txt");
 
     // NATS publish/subscribe round-trip
     let nats = async_nats::connect(env.nats_url()).await?;
@@ -42,5 +56,6 @@ async fn e2e_infra_round_trip() -> anyhow::Result<()> {
     assert_eq!(value, 1);
 
     env.down().await?;
+    assert!(collected.exists(), "teardown collector did not run");
     Ok(())
 }
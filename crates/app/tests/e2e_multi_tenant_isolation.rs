@@ -16,17 +16,10 @@ async fn e2e_multi_tenant_isolation() -> Result<()> {
         return Ok(());
     }
 
-    unsafe {
-        std::env::set_var("E2E_TEST_NAME", "e2e_multi_tenant_isolation");
-    }
-
-    let env = TestEnv::up().await?;
+    // Tenant secrets scoped per tenant are seeded by the manifest.
+    let env = TestEnv::from_manifest("tests/env/e2e_multi_tenant_isolation/env.e2e.toml").await?;
     env.healthcheck().await?;
 
-    // Tenant secrets scoped per tenant.
-    env.write_tenant_secret("tenant-a", "API_TOKEN", "secret-A")?;
-    env.write_tenant_secret("tenant-b", "API_TOKEN", "secret-B")?;
-
     let nats = async_nats::connect(env.nats_url()).await?;
 
     // Subscribe to both tenant subjects and record envelopes.
@@ -1,9 +1,4 @@
-use std::path::PathBuf;
-
-use greentic_integration::harness::pack::{pack_build, pack_install, pack_verify};
-use greentic_integration::harness::{
-    PackBuildResult, PackInstallResult, PackVerifyResult, TestEnv,
-};
+use greentic_integration::harness::TestEnv;
 
 #[tokio::test]
 async fn e2e_pack_lifecycle() -> anyhow::Result<()> {
@@ -12,41 +7,38 @@ async fn e2e_pack_lifecycle() -> anyhow::Result<()> {
         return Ok(());
     }
 
-    unsafe {
-        std::env::set_var("E2E_TEST_NAME", "e2e_pack_lifecycle");
-    }
-
-    let env = TestEnv::up().await?;
+    let env = TestEnv::from_manifest("tests/env/e2e_pack_lifecycle/env.e2e.toml").await?;
     env.healthcheck().await?;
 
-    let fixture_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-        .parent()
-        .and_then(|p| p.parent())
-        .unwrap()
-        .join("fixtures")
-        .join("packs")
-        .join("hello");
-
-    let PackBuildResult { gtpack, mode } =
-        pack_build(&fixture_root, env.artifacts_dir(), env.logs_dir())?;
+    let [pack] = env.packs() else {
+        anyhow::bail!("expected exactly one preloaded pack");
+    };
+    assert_eq!(pack.id, "hello");
     assert!(
-        gtpack.exists(),
+        pack.gtpack.exists(),
         "gtpack output missing at {}",
-        gtpack.display()
+        pack.gtpack.display()
+    );
+    assert_eq!(pack.target, "dev");
+    let installed = env
+        .artifacts_dir()
+        .join("packs")
+        .join(&pack.id)
+        .join("pack")
+        .join("installed.json");
+    assert!(
+        installed.exists(),
+        "install record missing at {}",
+        installed.display()
     );
-
-    let PackVerifyResult { ok, .. } = pack_verify(&gtpack, env.logs_dir())?;
-    assert!(ok, "pack verify should succeed");
-
-    let PackInstallResult { ok, target } =
-        pack_install("dev", &gtpack, env.artifacts_dir(), env.logs_dir())?;
-    assert!(ok, "pack install should succeed");
-    assert_eq!(target, "dev");
 
     // Record build mode for debugging.
-    let build_mode_note = env.artifacts_dir().join("pack").join("build_mode.txt");
-    let note = format!("mode: {:?}\n", mode);
-    std::fs::write(build_mode_note, note)?;
+    let build_mode_note = env
+        .artifacts_dir()
+        .join("packs")
+        .join(&pack.id)
+        .join("build_mode.txt");
+    std::fs::write(build_mode_note, format!("mode: {:?}\n", pack.mode))?;
 
     env.down().await?;
     Ok(())
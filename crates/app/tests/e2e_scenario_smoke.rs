@@ -29,6 +29,10 @@ async fn e2e_scenario_smoke() -> anyhow::Result<()> {
                 expected: Some(serde_json::json!({"msg": "hello"})),
                 timeout_ms: Some(3_000),
             },
+            // traceparent injected on publish must survive the broker hop.
+            Step::AssertTraceContinuity {
+                subject: "e2e.scenario.smoke".into(),
+            },
         ],
     };
 
@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+
+use greentic_integration::harness::{ComposeService, EnvManifest, StackComponent};
+
+fn env_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("env")
+}
+
+/// Every checked-in `env.e2e.toml` must parse and reference existing pack fixtures, so a typo
+/// fails here instead of inside a docker-gated test.
+#[test]
+fn checked_in_env_manifests_are_valid() -> anyhow::Result<()> {
+    let workspace = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../..");
+    let mut seen = 0;
+    for entry in walkdir::WalkDir::new(env_dir()) {
+        let entry = entry?;
+        if entry.file_name() != "env.e2e.toml" {
+            continue;
+        }
+        let manifest = EnvManifest::load(entry.path())?;
+        for pack in &manifest.packs {
+            let fixture = workspace.join(&pack.fixture);
+            assert!(
+                fixture.join("pack.json").exists(),
+                "{}: pack fixture {} has no pack.json",
+                entry.path().display(),
+                fixture.display()
+            );
+        }
+        seen += 1;
+    }
+    assert!(
+        seen >= 2,
+        "expected env manifests under {}",
+        env_dir().display()
+    );
+    Ok(())
+}
+
+#[test]
+fn env_manifest_defaults_and_rejections() -> anyhow::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let path = tmp.path().join("env.e2e.toml");
+
+    std::fs::write(&path, "stack = [\"runner\"]\n")?;
+    let manifest = EnvManifest::load(&path)?;
+    assert_eq!(manifest.services, ComposeService::all());
+    assert_eq!(manifest.stack, vec![StackComponent::Runner]);
+
+    for (body, needle) in [
+        ("services = [\"redis\"]\n", "unknown variant"),
+        ("servics = [\"nats\"]\n", "unknown field"),
+        (
+            "[[tenants]]\nid = \"a\"\n[[tenants]]\nid = \"a\"\n",
+            "declared twice",
+        ),
+        ("[[packs]]\nfixture = \"/abs/hello\"\n", "must be relative"),
+    ] {
+        std::fs::write(&path, body)?;
+        let err = format!("{:#}", EnvManifest::load(&path).unwrap_err());
+        assert!(err.contains(needle), "{body:?}: {err}");
+    }
+    Ok(())
+}
@@ -0,0 +1,68 @@
+//! Replays the recorded bridge ↔ runner interactions under `fixtures/runner_protocol/` against
+//! the bridge's protocol types and a runner: the in-process stub by default, or a real runner
+//! when `RUNNER_CONTRACT_URL` is set.
+
+use std::path::PathBuf;
+
+use greentic_integration::harness::{
+    ContractCase, check_bridge_shapes, load_contract_cases, replay_against_runner,
+};
+use greentic_integration::runner_protocol::{
+    PROTOCOL_VERSION, RunnerRequest, StubLog, stub_router,
+};
+
+fn cases() -> Vec<ContractCase> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../fixtures/runner_protocol");
+    let cases = load_contract_cases(&dir).expect("contract cases");
+    assert!(!cases.is_empty(), "no contract cases in {}", dir.display());
+    cases
+}
+
+async fn replay_all(base: String, cases: Vec<ContractCase>) -> anyhow::Result<()> {
+    tokio::task::spawn_blocking(move || {
+        for case in &cases {
+            replay_against_runner(&base, case)?;
+        }
+        Ok(())
+    })
+    .await?
+}
+
+#[test]
+fn recordings_match_bridge_protocol_types() {
+    for case in cases() {
+        check_bridge_shapes(&case).unwrap_or_else(|err| {
+            panic!("{}: {err:#}", case.source.display());
+        });
+    }
+}
+
+#[tokio::test]
+async fn runner_stub_honours_recordings() -> anyhow::Result<()> {
+    let log = StubLog::default();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let base = format!("http://{}", listener.local_addr()?);
+    tokio::spawn(axum::serve(listener, stub_router(log.clone())).into_future());
+
+    replay_all(base, cases()).await?;
+
+    let accepted = log.lock().clone();
+    assert!(accepted.iter().any(
+        |request| matches!(request, RunnerRequest::Reload(reload) if !reload.packs.is_empty())
+    ));
+    assert!(
+        accepted
+            .iter()
+            .all(|request| request.protocol() == PROTOCOL_VERSION)
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn real_runner_honours_recordings() -> anyhow::Result<()> {
+    let Ok(base) = std::env::var("RUNNER_CONTRACT_URL") else {
+        eprintln!("skipping real runner contract: RUNNER_CONTRACT_URL not set");
+        return Ok(());
+    };
+    replay_all(base, cases()).await
+}
@@ -0,0 +1,780 @@
+//! Typed async client for the greentic-integration bridge HTTP API (`greentic-integration
+//! serve`). Used by the CLI's `--server` commands and by the e2e tests.
+//!
+//! Requests run on tokio's blocking pool, so the client works on any tokio runtime without
+//! pulling in a second HTTP stack. Every request shares one timeout, and requests that are
+//! safe to repeat are retried with backoff ([`ClientOptions`]).
+
+mod types;
+
+use std::{collections::VecDeque, time::Duration};
+
+use futures::Stream;
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use thiserror::Error;
+
+pub use types::{
+    BufferUsage, ClusterStatus, DryRunPreview, EmitRequest, EventImport, EventPrune, InvalidPack,
+    ManifestViolation, Pack, PackAsset, PackList, PackQuery, PackTransition, ProcessDiagnostics,
+    ResumeRequest, RunnerEvent, Session, SessionChange, SessionCursor, SessionList, SessionNudge,
+    SessionQuery, SessionUpsert, TenantUsage,
+};
+
+/// Timeout and retry settings shared by every request of a [`BridgeClient`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientOptions {
+    /// Whole-request timeout (connect, send and read).
+    pub timeout: Duration,
+    /// Extra attempts after a retryable failure. `GET`/`DELETE` retry on transport errors
+    /// and `502`/`503`/`504`. `POST` retries only when the connection could not be opened,
+    /// so a request the server may have handled is never sent twice.
+    pub retries: u32,
+    /// Delay before the first retry; doubled for each further retry.
+    pub retry_backoff: Duration,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+            retries: 2,
+            retry_backoff: Duration::from_millis(200),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    /// The server answered with a non-success status; `body` is the raw response body.
+    #[error("{method} {url} failed with status {status}: {body}")]
+    Status {
+        method: &'static str,
+        url: String,
+        status: u16,
+        body: String,
+    },
+    #[error("{method} {url} failed: {message}")]
+    Transport {
+        method: &'static str,
+        url: String,
+        message: String,
+    },
+    #[error("invalid response from {url}: {message}")]
+    Decode { url: String, message: String },
+}
+
+impl ClientError {
+    /// HTTP status of a [`ClientError::Status`] error.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            ClientError::Status { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+}
+
+pub type Result<T, E = ClientError> = std::result::Result<T, E>;
+
+#[derive(Debug, Clone)]
+pub struct BridgeClient {
+    base: String,
+    agent: ureq::Agent,
+    options: ClientOptions,
+}
+
+impl BridgeClient {
+    /// Client for the server at `base` (e.g. `http://localhost:8080`) with default options.
+    pub fn new(base: impl AsRef<str>) -> Self {
+        Self::with_options(base, ClientOptions::default())
+    }
+
+    pub fn with_timeout(base: impl AsRef<str>, timeout: Duration) -> Self {
+        Self::with_options(
+            base,
+            ClientOptions {
+                timeout,
+                ..ClientOptions::default()
+            },
+        )
+    }
+
+    pub fn with_options(base: impl AsRef<str>, options: ClientOptions) -> Self {
+        let config = ureq::Agent::config_builder()
+            .http_status_as_error(false)
+            .timeout_global(Some(options.timeout))
+            .build();
+        Self {
+            base: base.as_ref().trim_end_matches('/').to_string(),
+            agent: ureq::Agent::new_with_config(config),
+            options,
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base
+    }
+
+    /// `GET /healthz`.
+    pub async fn healthz(&self) -> Result<Value> {
+        self.send(Method::Get, "/healthz", Vec::new(), None).await
+    }
+
+    /// `GET /diagnostics/process`: the bridge's memory and buffer usage.
+    pub async fn process_diagnostics(&self) -> Result<ProcessDiagnostics> {
+        self.send(Method::Get, "/diagnostics/process", Vec::new(), None)
+            .await
+    }
+
+    /// `GET /packs`, resolved for the query's tenant/team/user.
+    pub async fn list_packs(&self, query: &PackQuery) -> Result<PackList> {
+        let mut params = Vec::new();
+        push_param(&mut params, "tenant", &query.tenant);
+        push_param(&mut params, "team", &query.team);
+        push_param(&mut params, "user", &query.user);
+        push_param(&mut params, "kind", &query.kind);
+        if !query.tags.is_empty() {
+            params.push(("tag", query.tags.join(",")));
+        }
+        self.send(Method::Get, "/packs", params, None).await
+    }
+
+    /// `POST /packs/reload`: rebuild the server's pack index.
+    pub async fn reload_packs(&self) -> Result<PackList> {
+        self.send(Method::Post, "/packs/reload", Vec::new(), None)
+            .await
+    }
+
+    /// `POST /packs/reload?dry_run=true`: the packs a reload would add, remove or change,
+    /// without installing the rebuilt index.
+    pub async fn preview_reload_packs(&self) -> Result<DryRunPreview> {
+        let params = vec![("dry_run", "true".to_string())];
+        self.send(Method::Post, "/packs/reload", params, None).await
+    }
+
+    /// `GET /sessions`.
+    pub async fn list_sessions(&self, query: &SessionQuery) -> Result<SessionList> {
+        self.send(Method::Get, "/sessions", session_params(query), None)
+            .await
+    }
+
+    /// `GET /sessions?format=csv`: the same page as [`list_sessions`], one flattened row per
+    /// session.
+    ///
+    /// [`list_sessions`]: Self::list_sessions
+    pub async fn list_sessions_csv(&self, query: &SessionQuery) -> Result<String> {
+        let mut params = session_params(query);
+        params.push(("format", "csv".into()));
+        self.get_text("/sessions", params).await
+    }
+
+    /// `GET /sessions/{key}`; a missing or expired session is a `404` status error.
+    pub async fn get_session(&self, key: &str) -> Result<Session> {
+        let path = format!("/sessions/{}", path_segment(key));
+        self.send(Method::Get, &path, Vec::new(), None).await
+    }
+
+    /// `POST /sessions`: create or replace a session.
+    pub async fn upsert_session(&self, session: &SessionUpsert) -> Result<Session> {
+        let body = to_body(session)?;
+        self.send(Method::Post, "/sessions", Vec::new(), Some(body))
+            .await
+    }
+
+    /// `GET /tenants/{tenant}/usage`: live sessions against the tenant's quota. Creating a
+    /// session past the quota fails with a `429` status error.
+    pub async fn tenant_usage(&self, tenant: &str) -> Result<TenantUsage> {
+        let path = format!("/tenants/{}/usage", path_segment(tenant));
+        self.send(Method::Get, &path, Vec::new(), None).await
+    }
+
+    /// `GET /cluster`: the answering instance, the live cluster members and which instance
+    /// runs each singleton background task.
+    pub async fn cluster_status(&self) -> Result<ClusterStatus> {
+        self.send(Method::Get, "/cluster", Vec::new(), None).await
+    }
+
+    /// `POST /sessions/resume`: continue the user's (or the keyed) waiting session with
+    /// `payload`.
+    pub async fn resume(&self, request: &ResumeRequest) -> Result<RunnerEvent> {
+        let body = to_body(request)?;
+        self.send(Method::Post, "/sessions/resume", Vec::new(), Some(body))
+            .await
+    }
+
+    /// `POST /runner/emit`: run a flow once.
+    pub async fn emit(&self, request: &EmitRequest) -> Result<RunnerEvent> {
+        let body = to_body(request)?;
+        self.send(Method::Post, "/runner/emit", Vec::new(), Some(body))
+            .await
+    }
+
+    /// `GET /runner/events`: the server's cached runner events, oldest first.
+    pub async fn runner_events(&self) -> Result<Vec<RunnerEvent>> {
+        self.send(Method::Get, "/runner/events", Vec::new(), None)
+            .await
+    }
+
+    /// `GET /runner/events?format=csv`: one flattened row per event.
+    pub async fn runner_events_csv(&self) -> Result<String> {
+        self.get_text("/runner/events", vec![("format", "csv".into())])
+            .await
+    }
+
+    /// `POST /runner/events/import`: record events produced outside the bridge, such as the
+    /// external runner's own activity.
+    pub async fn import_runner_events(&self, events: &[RunnerEvent]) -> Result<EventImport> {
+        let body = to_body(&events)?;
+        self.send(
+            Method::Post,
+            "/runner/events/import",
+            Vec::new(),
+            Some(body),
+        )
+        .await
+    }
+
+    /// `DELETE /runner/events`.
+    pub async fn clear_runner_events(&self) -> Result<()> {
+        self.send::<Option<Value>>(Method::Delete, "/runner/events", Vec::new(), None)
+            .await
+            .map(|_| ())
+    }
+
+    /// `POST /runner/events/prune`: run a retention pass now. `max_age_secs` and
+    /// `max_events` replace the server's `[runner.event_retention]` limits for this pass.
+    pub async fn prune_runner_events(
+        &self,
+        max_age_secs: Option<u64>,
+        max_events: Option<usize>,
+    ) -> Result<EventPrune> {
+        let params = prune_params(max_age_secs, max_events);
+        self.send(Method::Post, "/runner/events/prune", params, None)
+            .await
+    }
+
+    /// What `prune_runner_events` with the same limits would remove, without removing it.
+    pub async fn preview_prune_runner_events(
+        &self,
+        max_age_secs: Option<u64>,
+        max_events: Option<usize>,
+    ) -> Result<DryRunPreview> {
+        let mut params = prune_params(max_age_secs, max_events);
+        params.push(("dry_run", "true".to_string()));
+        self.send(Method::Post, "/runner/events/prune", params, None)
+            .await
+    }
+
+    /// `DELETE /runner/events?dry_run=true`: how many events a clear would drop, by flow.
+    pub async fn preview_clear_runner_events(&self) -> Result<DryRunPreview> {
+        let params = vec![("dry_run", "true".to_string())];
+        self.send(Method::Delete, "/runner/events", params, None)
+            .await
+    }
+
+    /// Runner events as they arrive, by polling `GET /runner/events` every `poll`. Starts with
+    /// the events already cached. The server keeps only its latest 100 events, so events
+    /// evicted between two polls are missed. A failed poll yields the error and polling
+    /// continues.
+    pub fn stream_events(
+        &self,
+        poll: Duration,
+    ) -> impl Stream<Item = Result<RunnerEvent>> + Send + 'static {
+        let state = EventCursor {
+            client: self.clone(),
+            poll,
+            last: None,
+            pending: VecDeque::new(),
+            polled: false,
+        };
+        futures::stream::unfold(state, |mut state| async move {
+            loop {
+                if let Some(event) = state.pending.pop_front() {
+                    state.last = Some(event.clone());
+                    return Some((Ok(event), state));
+                }
+                if state.polled {
+                    tokio::time::sleep(state.poll).await;
+                }
+                state.polled = true;
+                match state.client.runner_events().await {
+                    Ok(events) => state.pending = unseen_events(events, state.last.as_ref()),
+                    Err(err) => return Some((Err(err), state)),
+                }
+            }
+        })
+    }
+
+    /// `GET /runner/events/stream`: events recorded from now on, pushed by the server as they
+    /// happen. The stream ends with an error when the connection drops.
+    pub fn watch_runner_events(&self) -> impl Stream<Item = Result<RunnerEvent>> + Send + 'static {
+        self.sse("/runner/events/stream", Vec::new(), "runner_event")
+    }
+
+    /// `GET /sessions/stream`: the matching sessions as `created` changes, then every
+    /// create/update/remove as the server sees it.
+    pub fn watch_sessions(
+        &self,
+        query: &SessionQuery,
+    ) -> impl Stream<Item = Result<SessionChange>> + Send + 'static {
+        let mut params = Vec::new();
+        push_param(&mut params, "tenant", &query.tenant);
+        push_param(&mut params, "team", &query.team);
+        push_param(&mut params, "user", &query.user);
+        self.sse("/sessions/stream", params, "session")
+    }
+
+    /// Decode the `data` of every `event_name` event of a server-sent event stream. The
+    /// connection is read on the blocking pool without the whole-request timeout, since the
+    /// stream is open-ended; it closes once the returned stream is dropped and the server sends
+    /// its next event or keep-alive.
+    fn sse<T>(
+        &self,
+        path: &str,
+        params: Vec<(&'static str, String)>,
+        event_name: &'static str,
+    ) -> impl Stream<Item = Result<T>> + Send + 'static
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        let url = format!("{}{path}", self.base);
+        let config = ureq::Agent::config_builder()
+            .http_status_as_error(false)
+            .timeout_connect(Some(self.options.timeout))
+            .build();
+        let agent = ureq::Agent::new_with_config(config);
+        let (tx, rx) = tokio::sync::mpsc::channel(64);
+        tokio::task::spawn_blocking(move || {
+            if let Err(err) = read_sse(&agent, &url, params, event_name, &tx) {
+                let _ = tx.blocking_send(Err(err));
+            }
+        });
+        futures::stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|item| (item, rx))
+        })
+    }
+
+    async fn send<T>(
+        &self,
+        method: Method,
+        path: &str,
+        params: Vec<(&'static str, String)>,
+        body: Option<Value>,
+    ) -> Result<T>
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        self.send_with(method, path, params, body, decode_json)
+            .await
+    }
+
+    /// `GET` a non-JSON body (e.g. `?format=csv`) as text, with the same retries as [`send`].
+    ///
+    /// [`send`]: Self::send
+    async fn get_text(&self, path: &str, params: Vec<(&'static str, String)>) -> Result<String> {
+        self.send_with(Method::Get, path, params, None, |_, text| Ok(text))
+            .await
+    }
+
+    async fn send_with<T>(
+        &self,
+        method: Method,
+        path: &str,
+        params: Vec<(&'static str, String)>,
+        body: Option<Value>,
+        decode: fn(&str, String) -> Result<T, Failure>,
+    ) -> Result<T>
+    where
+        T: Send + 'static,
+    {
+        let url = format!("{}{path}", self.base);
+        let mut backoff = self.options.retry_backoff;
+        let mut retries_left = self.options.retries;
+        loop {
+            let agent = self.agent.clone();
+            let task_url = url.clone();
+            let task_params = params.clone();
+            let task_body = body.clone();
+            let attempt = tokio::task::spawn_blocking(move || {
+                send_blocking(&agent, method, &task_url, task_params, task_body, decode)
+            })
+            .await
+            .unwrap_or_else(|err| {
+                Err(Failure {
+                    error: ClientError::Transport {
+                        method: method.as_str(),
+                        url: url.clone(),
+                        message: format!("request task failed: {err}"),
+                    },
+                    retryable: false,
+                })
+            });
+            match attempt {
+                Ok(value) => return Ok(value),
+                Err(failure) if failure.retryable && retries_left > 0 => {
+                    retries_left -= 1;
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                }
+                Err(failure) => return Err(failure.error),
+            }
+        }
+    }
+}
+
+/// A failed attempt, and whether repeating it is safe and may help.
+struct Failure {
+    error: ClientError,
+    retryable: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Method {
+    Get,
+    Post,
+    Delete,
+}
+
+impl Method {
+    fn as_str(self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Post => "POST",
+            Method::Delete => "DELETE",
+        }
+    }
+
+    fn is_idempotent(self) -> bool {
+        self != Method::Post
+    }
+}
+
+fn send_blocking<T>(
+    agent: &ureq::Agent,
+    method: Method,
+    url: &str,
+    params: Vec<(&'static str, String)>,
+    body: Option<Value>,
+    decode: fn(&str, String) -> Result<T, Failure>,
+) -> Result<T, Failure> {
+    let transport = |err: ureq::Error| Failure {
+        retryable: match err {
+            ureq::Error::ConnectionFailed => true,
+            ureq::Error::Io(_) | ureq::Error::Timeout(_) | ureq::Error::Protocol(_) => {
+                method.is_idempotent()
+            }
+            _ => false,
+        },
+        error: ClientError::Transport {
+            method: method.as_str(),
+            url: url.to_string(),
+            message: err.to_string(),
+        },
+    };
+    let response = match method {
+        Method::Get => agent.get(url).query_pairs(params).call(),
+        Method::Delete => agent.delete(url).query_pairs(params).call(),
+        Method::Post => {
+            let request = agent.post(url).query_pairs(params);
+            match body {
+                Some(body) => request.send_json(body),
+                None => request.send_empty(),
+            }
+        }
+    }
+    .map_err(transport)?;
+
+    let status = response.status().as_u16();
+    let text = response
+        .into_body()
+        .read_to_string()
+        .map_err(|err| Failure {
+            error: ClientError::Decode {
+                url: url.to_string(),
+                message: err.to_string(),
+            },
+            retryable: method.is_idempotent(),
+        })?;
+    if !(200..300).contains(&status) {
+        return Err(Failure {
+            error: ClientError::Status {
+                method: method.as_str(),
+                url: url.to_string(),
+                status,
+                body: text,
+            },
+            retryable: method.is_idempotent() && matches!(status, 502..=504),
+        });
+    }
+    decode(url, text)
+}
+
+fn decode_json<T: DeserializeOwned>(url: &str, text: String) -> Result<T, Failure> {
+    // Bodiless responses (`204 No Content`) decode as JSON `null`.
+    let text = if text.trim().is_empty() {
+        "null"
+    } else {
+        &text
+    };
+    serde_json::from_str(text).map_err(|err| Failure {
+        error: ClientError::Decode {
+            url: url.to_string(),
+            message: err.to_string(),
+        },
+        retryable: false,
+    })
+}
+
+fn read_sse<T: DeserializeOwned>(
+    agent: &ureq::Agent,
+    url: &str,
+    params: Vec<(&'static str, String)>,
+    event_name: &str,
+    tx: &tokio::sync::mpsc::Sender<Result<T>>,
+) -> Result<()> {
+    let transport = |err: &dyn std::fmt::Display| ClientError::Transport {
+        method: "GET",
+        url: url.to_string(),
+        message: err.to_string(),
+    };
+    let response = agent
+        .get(url)
+        .header("accept", "text/event-stream")
+        .query_pairs(params)
+        .call()
+        .map_err(|err| transport(&err))?;
+    let status = response.status().as_u16();
+    if !(200..300).contains(&status) {
+        return Err(ClientError::Status {
+            method: "GET",
+            url: url.to_string(),
+            status,
+            body: response.into_body().read_to_string().unwrap_or_default(),
+        });
+    }
+    let reader = std::io::BufReader::new(response.into_body().into_reader());
+    let mut event = String::new();
+    let mut data = String::new();
+    for line in std::io::BufRead::lines(reader) {
+        let line = line.map_err(|err| transport(&err))?;
+        if line.is_empty() {
+            if event == event_name && !data.is_empty() {
+                let item = serde_json::from_str(&data).map_err(|err| ClientError::Decode {
+                    url: url.to_string(),
+                    message: err.to_string(),
+                });
+                if tx.blocking_send(item).is_err() {
+                    return Ok(());
+                }
+            } else if tx.is_closed() {
+                return Ok(());
+            }
+            event.clear();
+            data.clear();
+        } else if let Some(value) = line.strip_prefix("event:") {
+            event = value.trim_start().to_string();
+        } else if let Some(value) = line.strip_prefix("data:") {
+            if !data.is_empty() {
+                data.push('\n');
+            }
+            data.push_str(value.strip_prefix(' ').unwrap_or(value));
+        }
+    }
+    Err(transport(&"event stream closed by the server"))
+}
+
+/// `POST /runner/events/prune` limits shared by a pass and its preview.
+fn prune_params(
+    max_age_secs: Option<u64>,
+    max_events: Option<usize>,
+) -> Vec<(&'static str, String)> {
+    let mut params = Vec::new();
+    if let Some(secs) = max_age_secs {
+        params.push(("max_age_secs", secs.to_string()));
+    }
+    if let Some(max) = max_events {
+        params.push(("max_events", max.to_string()));
+    }
+    params
+}
+
+/// Query parameters shared by the JSON and CSV session listings.
+fn session_params(query: &SessionQuery) -> Vec<(&'static str, String)> {
+    let mut params = Vec::new();
+    push_param(&mut params, "tenant", &query.tenant);
+    push_param(&mut params, "team", &query.team);
+    push_param(&mut params, "user", &query.user);
+    if query.needs_upgrade {
+        params.push(("needs_upgrade", "true".into()));
+    }
+    push_param(
+        &mut params,
+        "limit",
+        &query.limit.map(|limit| limit.to_string()),
+    );
+    push_param(
+        &mut params,
+        "offset",
+        &query.offset.map(|offset| offset.to_string()),
+    );
+    push_param(&mut params, "cursor", &query.cursor);
+    push_param(&mut params, "sort", &query.sort);
+    params
+}
+
+fn push_param(params: &mut Vec<(&'static str, String)>, key: &'static str, value: &Option<String>) {
+    if let Some(value) = value {
+        params.push((key, value.clone()));
+    }
+}
+
+/// Percent-encode `value` for use as one URL path segment.
+fn path_segment(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+fn to_body(value: &impl Serialize) -> Result<Value> {
+    serde_json::to_value(value).map_err(|err| ClientError::Decode {
+        url: String::new(),
+        message: format!("failed to encode request body: {err}"),
+    })
+}
+
+struct EventCursor {
+    client: BridgeClient,
+    poll: Duration,
+    last: Option<RunnerEvent>,
+    pending: VecDeque<RunnerEvent>,
+    polled: bool,
+}
+
+/// Events after the last one already yielded. When `last` is no longer cached (cleared or
+/// evicted), everything at least as recent as it counts as new.
+fn unseen_events(events: Vec<RunnerEvent>, last: Option<&RunnerEvent>) -> VecDeque<RunnerEvent> {
+    let Some(last) = last else {
+        return events.into();
+    };
+    match events.iter().rposition(|event| event == last) {
+        Some(position) => events.into_iter().skip(position + 1).collect(),
+        None => events
+            .into_iter()
+            .filter(|event| event.timestamp_ms >= last.timestamp_ms)
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn event(timestamp_ms: u64, flow: &str) -> RunnerEvent {
+        RunnerEvent {
+            timestamp_ms,
+            flow: flow.into(),
+            tenant: None,
+            team: None,
+            user: None,
+            payload: Value::Null,
+            result: json!({}),
+            sequence: None,
+            instance: None,
+        }
+    }
+
+    #[test]
+    fn path_segments_escape_reserved_characters() {
+        assert_eq!(path_segment("sess-1_a.b~"), "sess-1_a.b~");
+        assert_eq!(path_segment("a/b c?"), "a%2Fb%20c%3F");
+    }
+
+    #[test]
+    fn unseen_events_resume_after_the_last_yielded_event() {
+        let cached = vec![event(1, "a"), event(2, "b"), event(2, "c")];
+        let flows = |events: VecDeque<RunnerEvent>| {
+            events
+                .into_iter()
+                .map(|event| event.flow)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(flows(unseen_events(cached.clone(), None)), ["a", "b", "c"]);
+        assert_eq!(
+            flows(unseen_events(cached.clone(), Some(&event(2, "b")))),
+            ["c"]
+        );
+        assert!(unseen_events(cached, Some(&event(2, "c"))).is_empty());
+        // After a clear, only events no older than the last one seen are new.
+        let after_clear = vec![event(1, "old"), event(3, "d")];
+        assert_eq!(
+            flows(unseen_events(after_clear, Some(&event(2, "c")))),
+            ["d"]
+        );
+    }
+
+    #[tokio::test]
+    async fn unreachable_server_is_a_transport_error() {
+        let client = BridgeClient::with_timeout("http://127.0.0.1:9/", Duration::from_secs(2));
+        assert_eq!(client.base_url(), "http://127.0.0.1:9");
+        let err = client.healthz().await.unwrap_err();
+        assert!(matches!(err, ClientError::Transport { .. }), "{err}");
+        assert_eq!(err.status(), None);
+    }
+
+    /// Serves one canned response per connection and counts the requests it saw.
+    fn scripted_server(responses: Vec<&'static str>) -> (String, std::thread::JoinHandle<usize>) {
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let mut served = 0;
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buf = [0u8; 4096];
+                let _ = stream.read(&mut buf);
+                let reply = format!(
+                    "HTTP/1.1 {response}\r\ncontent-type: application/json\r\ncontent-length: 2\r\nconnection: close\r\n\r\n{{}}"
+                );
+                stream.write_all(reply.as_bytes()).unwrap();
+                served += 1;
+            }
+            served
+        });
+        (base, handle)
+    }
+
+    fn fast_retries() -> ClientOptions {
+        ClientOptions {
+            timeout: Duration::from_secs(5),
+            retries: 2,
+            retry_backoff: Duration::from_millis(10),
+        }
+    }
+
+    #[tokio::test]
+    async fn idempotent_requests_retry_on_unavailable() {
+        let (base, server) = scripted_server(vec!["503 Service Unavailable", "200 OK"]);
+        let client = BridgeClient::with_options(base, fast_retries());
+        assert_eq!(client.healthz().await.unwrap(), json!({}));
+        assert_eq!(server.join().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn posts_are_not_retried_after_reaching_the_server() {
+        let (base, server) = scripted_server(vec!["503 Service Unavailable"]);
+        let client = BridgeClient::with_options(base, fast_retries());
+        let err = client
+            .emit(&EmitRequest::new("ping", Value::Null))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), Some(503), "{err}");
+        assert_eq!(server.join().unwrap(), 1);
+    }
+}
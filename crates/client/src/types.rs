@@ -0,0 +1,365 @@
+//! Request and response bodies of the bridge API, as seen by clients.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Filters for `GET /packs`; unset fields fall back to the server's `[defaults]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PackQuery {
+    pub tenant: Option<String>,
+    pub team: Option<String>,
+    pub user: Option<String>,
+    pub kind: Option<String>,
+    /// Tags that must all be present.
+    pub tags: Vec<String>,
+}
+
+/// `GET /packs` and `POST /packs/reload` response.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PackList {
+    pub count: usize,
+    pub packs: Vec<Pack>,
+    pub resolved_keys: Vec<String>,
+    pub missing_keys: Vec<String>,
+    pub index_generation: u64,
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    /// Lifecycle changes applied by the reload that produced this listing.
+    #[serde(default)]
+    pub transitions: Vec<PackTransition>,
+    /// Packs left out of the index because their manifest failed the pack schema.
+    #[serde(default)]
+    pub invalid: Vec<InvalidPack>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Pack {
+    pub id: String,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub kind: Option<String>,
+    /// `active`, `deprecated` or `disabled`.
+    #[serde(default = "active")]
+    pub status: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub assets: Vec<PackAsset>,
+    pub path: String,
+    /// `path` relative to the server's pack root (`acme/billing`).
+    #[serde(default)]
+    pub relative_path: String,
+}
+
+fn active() -> String {
+    "active".into()
+}
+
+/// A pack directory whose `pack.json` failed the server's manifest schema.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct InvalidPack {
+    pub path: String,
+    #[serde(default)]
+    pub id: Option<String>,
+    pub reasons: Vec<ManifestViolation>,
+}
+
+/// A schema failure located by JSON pointer into the manifest (`""` for the root).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ManifestViolation {
+    pub pointer: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct PackAsset {
+    pub path: String,
+    pub size: u64,
+    pub content_type: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct PackTransition {
+    pub id: String,
+    pub from: String,
+    pub to: String,
+}
+
+/// What a mutating request would change. Returned instead of applying the change by
+/// `dry_run=true` requests, and printed by the CLI's `--dry-run`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DryRunPreview {
+    /// The previewed operation, e.g. `packs reload`.
+    pub action: String,
+    /// How many items would change.
+    pub count: usize,
+    /// Identifiers of those items (session keys, pack ids).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keys: Vec<String>,
+    /// What would happen to them, or the flows they belong to.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subjects: Vec<String>,
+}
+
+/// Filters for `GET /sessions`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionQuery {
+    pub tenant: Option<String>,
+    pub team: Option<String>,
+    pub user: Option<String>,
+    /// Only sessions pinned to a pack version that is no longer loaded.
+    pub needs_upgrade: bool,
+    /// Page size; unset returns every match.
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+    /// `next_cursor` of the previous page.
+    pub cursor: Option<String>,
+    /// `key` (default), `updated_at` or `-updated_at`.
+    pub sort: Option<String>,
+}
+
+/// `POST /sessions` body; the server generates a key when none is given.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SessionUpsert {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub team: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flow_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    /// Expire the session this many milliseconds after this write.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Session {
+    pub key: String,
+    pub tenant: String,
+    pub team: Option<String>,
+    pub user: Option<String>,
+    pub cursor: SessionCursor,
+    pub context: Value,
+    pub updated_at_epoch_ms: u64,
+    #[serde(default)]
+    pub pack_id: Option<String>,
+    #[serde(default)]
+    pub flow_version: Option<String>,
+    #[serde(default)]
+    pub needs_upgrade: bool,
+    #[serde(default)]
+    pub locale: Option<String>,
+    #[serde(default)]
+    pub ttl_ms: Option<u64>,
+    /// Pack index generation the session is pinned to while that generation drains.
+    #[serde(default)]
+    pub pack_generation: Option<u64>,
+    /// Runner target (`[[runner.targets]]`) the session's turns are forwarded to.
+    #[serde(default)]
+    pub runner_target: Option<String>,
+    /// Nudges sent since the last write while the session sat idle, oldest first.
+    #[serde(default)]
+    pub nudges: Vec<SessionNudge>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SessionNudge {
+    pub at_epoch_ms: u64,
+    pub idle_ms: u64,
+    pub event: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SessionCursor {
+    pub flow_id: Option<String>,
+    pub node_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SessionList {
+    pub count: usize,
+    pub sessions: Vec<Session>,
+    /// Set when more sessions follow; pass it back as [`SessionQuery::cursor`].
+    #[serde(default)]
+    pub next_cursor: Option<String>,
+}
+
+/// `POST /sessions/resume` body: resumes the waiting session of `user`, or the session
+/// `key` names.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ResumeRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// Run the flow from this node instead of its entry.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub team: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    pub payload: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+}
+
+impl ResumeRequest {
+    pub fn new(user: impl Into<String>, payload: Value) -> Self {
+        Self {
+            key: None,
+            node_id: None,
+            tenant: None,
+            team: None,
+            user: Some(user.into()),
+            payload,
+            locale: None,
+        }
+    }
+
+    /// Resume the session stored under `key`, whoever its user is.
+    pub fn for_key(key: impl Into<String>, payload: Value) -> Self {
+        Self {
+            key: Some(key.into()),
+            user: None,
+            ..Self::new(String::new(), payload)
+        }
+    }
+}
+
+/// `POST /runner/emit` body.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EmitRequest {
+    pub flow: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub team: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    pub payload: Value,
+    /// Pack index generation the flow was resolved against; `409` when stale.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index_generation: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+}
+
+impl EmitRequest {
+    pub fn new(flow: impl Into<String>, payload: Value) -> Self {
+        Self {
+            flow: flow.into(),
+            tenant: None,
+            team: None,
+            user: None,
+            payload,
+            index_generation: None,
+            locale: None,
+        }
+    }
+}
+
+/// A runner activity record, as returned by emit/resume and `GET /runner/events`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunnerEvent {
+    pub timestamp_ms: u64,
+    pub flow: String,
+    pub tenant: Option<String>,
+    pub team: Option<String>,
+    pub user: Option<String>,
+    pub payload: Value,
+    pub result: Value,
+    /// 1-based position among the events recorded for the same tenant/team/user and flow.
+    /// A jump means events were dropped in between; a repeat or decrease means reordering.
+    /// Sequences are allocated by the instance that recorded the event.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<u64>,
+    /// Bridge instance that recorded the event when several run as a cluster.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+}
+
+/// `POST /runner/events/prune` response.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventPrune {
+    /// Removed for being older than the age limit.
+    pub expired: usize,
+    /// Removed to get down to the count limit.
+    pub trimmed: usize,
+}
+
+/// `GET /tenants/{tenant}/usage` response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TenantUsage {
+    pub tenant: String,
+    /// Live sessions of the tenant.
+    pub sessions: usize,
+    /// Session quota; `None` when the tenant has none.
+    #[serde(default)]
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub remaining: Option<usize>,
+    /// When the first of the tenant's sessions with a TTL runs out.
+    #[serde(default)]
+    pub next_expiry_at_epoch_ms: Option<u64>,
+}
+
+/// `GET /cluster` response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClusterStatus {
+    /// Id of the instance that answered.
+    pub instance: String,
+    /// False when the instance runs standalone.
+    pub clustered: bool,
+    /// Instances with a live heartbeat.
+    pub members: Vec<String>,
+    /// Instance holding each singleton background task, if any.
+    #[serde(default)]
+    pub leases: BTreeMap<String, Option<String>>,
+}
+
+/// `POST /runner/events/import` response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct EventImport {
+    /// Events received; the server's event policy may still sample some out.
+    pub imported: usize,
+}
+
+/// A `GET /sessions/stream` event. `change` is `created`, `updated` or `removed`; removed
+/// sessions carry only their key.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SessionChange {
+    pub change: String,
+    pub key: String,
+    #[serde(default)]
+    pub session: Option<Session>,
+}
+
+/// `GET /diagnostics/process` response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct ProcessDiagnostics {
+    pub pid: u32,
+    /// Resident memory of the bridge; `None` on platforms without `/proc`.
+    pub rss_bytes: Option<u64>,
+    pub runner_events: BufferUsage,
+    pub runner_queue: BufferUsage,
+}
+
+/// Entries currently held by a bounded server-side buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct BufferUsage {
+    pub len: usize,
+    pub capacity: usize,
+}
@@ -1,6 +1,3 @@
-#![allow(dead_code)]
-#![allow(unused_imports)]
-
 use std::path::Path;
 
 /// Minimal deploy-plan component: reads the plan via bindings and writes it to /iac/plan.json.
@@ -12,15 +9,37 @@ pub trait PlanRuntime {
     fn get_deployment_plan(&self) -> Result<String, String>;
 }
 
-/// Placeholder runtime: real bindings are expected to be provided by the host environment.
+#[cfg(target_arch = "wasm32")]
+mod bindings {
+    wit_bindgen::generate!({
+        path: "wit",
+        world: "plan-client",
+        generate_all,
+    });
+}
+
+/// Runtime backed by the host's `greentic:deploy-plan/plan-api@1.0.0` imports. Outside a wasm
+/// component there is no host to ask, so the plan request fails.
 #[derive(Debug, Default)]
 pub struct GuestPlanRuntime;
 
+#[cfg(target_arch = "wasm32")]
+impl PlanRuntime for GuestPlanRuntime {
+    fn emit_status(&self, message: String) {
+        bindings::greentic::deploy_plan::plan_api::emit_status(&message);
+    }
+
+    fn get_deployment_plan(&self) -> Result<String, String> {
+        Ok(bindings::greentic::deploy_plan::plan_api::get_deployment_plan())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
 impl PlanRuntime for GuestPlanRuntime {
     fn emit_status(&self, _message: String) {}
 
     fn get_deployment_plan(&self) -> Result<String, String> {
-        Err("deploy-plan bindings are provided by the host; no local WIT present".into())
+        Err("deploy-plan bindings are provided by the component host; build for wasm32".into())
     }
 }
 
@@ -29,11 +48,16 @@ impl DeployPlanComponent {
         Self::run_with_runtime(&GuestPlanRuntime, Path::new("/iac"))
     }
 
+    /// Fetches the plan from `runtime`, reporting the request as a status.
+    pub fn fetch_plan(runtime: &impl PlanRuntime) -> Result<String, String> {
+        runtime.emit_status("deploy-plan-component: fetching deployment plan".into());
+        runtime.get_deployment_plan()
+    }
+
     /// Runs the component using the provided bindings runtime, writing the plan under `output_root`.
     /// This keeps the production path at `/iac` while letting tests inject a temp directory.
     pub fn run_with_runtime(runtime: &impl PlanRuntime, output_root: &Path) -> Result<(), String> {
-        runtime.emit_status("deploy-plan-component: fetching deployment plan".into());
-        let plan = runtime.get_deployment_plan()?;
+        let plan = Self::fetch_plan(runtime)?;
 
         let pretty_plan = serde_json::from_str::<serde_json::Value>(&plan)
             .and_then(|v| serde_json::to_string_pretty(&v))
@@ -53,3 +77,72 @@ impl DeployPlanComponent {
         Ok(())
     }
 }
+
+/// `greentic:component/node@0.5.0` export. `plan` returns the host's plan as the node output;
+/// `render` writes it to `/iac/plan.json`, which needs a host that preopens `/iac`.
+#[cfg(target_arch = "wasm32")]
+mod node {
+    use greentic_interfaces_guest::component::node::{
+        ExecCtx, Guest, InvokeResult, LifecycleStatus, NodeError, StreamEvent,
+    };
+    use serde_json::{Value, json};
+
+    use crate::{DeployPlanComponent, GuestPlanRuntime, PlanRuntime};
+
+    struct DeployPlanNode;
+
+    impl Guest for DeployPlanNode {
+        fn get_manifest() -> String {
+            json!({
+                "id": "greentic.deploy.generic.iac",
+                "operations": ["plan", "render"],
+            })
+            .to_string()
+        }
+
+        fn on_start(_ctx: ExecCtx) -> Result<LifecycleStatus, String> {
+            Ok(LifecycleStatus::Ok)
+        }
+
+        fn on_stop(_ctx: ExecCtx, _reason: String) -> Result<LifecycleStatus, String> {
+            Ok(LifecycleStatus::Ok)
+        }
+
+        fn invoke(_ctx: ExecCtx, op: String, _input: String) -> InvokeResult {
+            let runtime = GuestPlanRuntime;
+            let output = match op.as_str() {
+                "plan" => DeployPlanComponent::fetch_plan(&runtime).map(|plan| {
+                    runtime.emit_status("deploy-plan-component: done".into());
+                    serde_json::from_str(&plan).unwrap_or(Value::String(plan))
+                }),
+                "render" => {
+                    DeployPlanComponent::run().map(|()| json!({ "path": "/iac/plan.json" }))
+                }
+                other => return failure("unsupported_op", format!("unknown operation {other:?}")),
+            };
+            match output {
+                Ok(output) => InvokeResult::Ok(output.to_string()),
+                Err(message) => failure("plan_failed", message),
+            }
+        }
+
+        fn invoke_stream(ctx: ExecCtx, op: String, input: String) -> Vec<StreamEvent> {
+            match Self::invoke(ctx, op, input) {
+                InvokeResult::Ok(output) => vec![StreamEvent::Data(output), StreamEvent::Done],
+                InvokeResult::Err(error) => vec![StreamEvent::Error(error.message)],
+            }
+        }
+    }
+
+    fn failure(code: &str, message: String) -> InvokeResult {
+        InvokeResult::Err(NodeError {
+            code: code.into(),
+            message,
+            retryable: false,
+            backoff_ms: None,
+            details: None,
+        })
+    }
+
+    greentic_interfaces_guest::export_component_node!(DeployPlanNode);
+}
@@ -1,4 +1,7 @@
 pub mod capabilities;
+pub mod phone;
 pub mod render;
 
-pub use render::{RenderError, RenderReport, simulate_render};
+pub use render::{
+    RenderError, RenderReport, hash_transcript, read_golden_transcript, simulate_render,
+};
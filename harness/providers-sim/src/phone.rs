@@ -0,0 +1,286 @@
+//! Simulated phone channels: SMS and WhatsApp.
+//!
+//! Neither channel renders cards, so [`render_outbound`] downgrades them to text. SMS text is
+//! split into concatenated segments the way carriers bill it: 160 GSM-7 septets or 70 UCS-2
+//! units for a single message, 153 or 67 per part once a user data header is needed. SMS has no
+//! media, so attachments become links. WhatsApp takes up to 4096 characters per message and
+//! delivers images, video, audio and PDFs as messages of their own; other media types fall back
+//! to links as well.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// GSM 03.38 basic character set; each character is one septet.
+const GSM7_BASIC: &str = "@£$¥èéùìòÇ\nØø\rÅåΔ_ΦΓΛΩΠΨΣΘΞÆæßÉ !\"#¤%&'()*+,-./0123456789:;<=>?\
+    ¡ABCDEFGHIJKLMNOPQRSTUVWXYZÄÖÑÜ§¿abcdefghijklmnopqrstuvwxyzäöñüà";
+/// GSM 03.38 extension table; each character takes an escape septet plus its own.
+const GSM7_EXTENSION: &str = "^{}\\[~]|€\u{c}";
+
+const SMS_GSM7_SINGLE: usize = 160;
+const SMS_GSM7_PART: usize = 153;
+const SMS_UCS2_SINGLE: usize = 70;
+const SMS_UCS2_PART: usize = 67;
+/// WhatsApp rejects text messages longer than this many characters.
+pub const WHATSAPP_TEXT_LIMIT: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PhoneChannel {
+    Sms,
+    Whatsapp,
+}
+
+impl PhoneChannel {
+    /// The provider id the channel goes by in `providers.yaml` and `[providers.<name>]`.
+    pub fn provider(self) -> &'static str {
+        match self {
+            PhoneChannel::Sms => "sms",
+            PhoneChannel::Whatsapp => "whatsapp",
+        }
+    }
+
+    pub fn from_provider(provider: &str) -> Option<Self> {
+        match provider {
+            "sms" => Some(PhoneChannel::Sms),
+            "whatsapp" => Some(PhoneChannel::Whatsapp),
+            _ => None,
+        }
+    }
+
+    fn supports_media(self, content_type: &str) -> bool {
+        match self {
+            PhoneChannel::Sms => false,
+            PhoneChannel::Whatsapp => {
+                let content_type = content_type.to_ascii_lowercase();
+                ["image/jpeg", "image/png", "video/mp4", "application/pdf"]
+                    .contains(&content_type.as_str())
+                    || content_type.starts_with("audio/")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmsEncoding {
+    Gsm7,
+    Ucs2,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhoneMedia {
+    pub url: String,
+    pub content_type: String,
+}
+
+/// Position of a message among the parts one send was split into (1-based).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Segment {
+    pub index: usize,
+    pub count: usize,
+}
+
+/// One message as the simulated provider delivers it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhoneMessage {
+    pub channel: PhoneChannel,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media: Option<PhoneMedia>,
+    /// Set when the send was split into several messages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub segment: Option<Segment>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<SmsEncoding>,
+    /// What the channel could not carry as sent (`card`, `media`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub downgraded: Vec<String>,
+}
+
+/// What the simulated provider posts to the app's webhook for an inbound message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhoneInbound {
+    pub message_id: String,
+    /// Sender number in E.164 form.
+    pub from: String,
+    /// The business number the message was sent to.
+    pub to: String,
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub media: Vec<PhoneMedia>,
+}
+
+/// Encoding an SMS carrying `text` needs.
+pub fn sms_encoding(text: &str) -> SmsEncoding {
+    if text
+        .chars()
+        .all(|ch| GSM7_BASIC.contains(ch) || GSM7_EXTENSION.contains(ch))
+    {
+        SmsEncoding::Gsm7
+    } else {
+        SmsEncoding::Ucs2
+    }
+}
+
+/// Split `text` into the SMS segments it is sent as. Characters are never split across
+/// segments: neither a GSM-7 escape pair nor a UTF-16 surrogate pair.
+pub fn segment_sms(text: &str) -> (SmsEncoding, Vec<String>) {
+    let encoding = sms_encoding(text);
+    let (single, part) = match encoding {
+        SmsEncoding::Gsm7 => (SMS_GSM7_SINGLE, SMS_GSM7_PART),
+        SmsEncoding::Ucs2 => (SMS_UCS2_SINGLE, SMS_UCS2_PART),
+    };
+    let width = |ch: char| match encoding {
+        SmsEncoding::Gsm7 if GSM7_EXTENSION.contains(ch) => 2,
+        SmsEncoding::Gsm7 => 1,
+        SmsEncoding::Ucs2 => ch.len_utf16(),
+    };
+    if text.chars().map(width).sum::<usize>() <= single {
+        return (encoding, vec![text.to_string()]);
+    }
+    let mut segments = Vec::new();
+    let (mut current, mut used) = (String::new(), 0);
+    for ch in text.chars() {
+        if used + width(ch) > part {
+            segments.push(std::mem::take(&mut current));
+            used = 0;
+        }
+        current.push(ch);
+        used += width(ch);
+    }
+    segments.push(current);
+    (encoding, segments)
+}
+
+/// Split `text` into chunks of at most `limit` characters, breaking after whitespace when
+/// there is some in the chunk.
+fn chunk_text(text: &str, limit: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut rest: Vec<char> = text.chars().collect();
+    while rest.len() > limit {
+        let cut = rest[..limit]
+            .iter()
+            .rposition(|ch| ch.is_whitespace())
+            .map_or(limit, |idx| idx + 1);
+        chunks.push(rest.drain(..cut).collect());
+    }
+    chunks.push(rest.into_iter().collect());
+    chunks
+}
+
+/// Plain-text rendering of an Adaptive Card: its text blocks, choice sets as numbered
+/// options, and actions as `title: url` (or just the title).
+pub fn card_to_text(card: &Value) -> String {
+    fn walk(element: &Value, lines: &mut Vec<String>) {
+        match element.get("type").and_then(Value::as_str) {
+            Some("TextBlock") => {
+                if let Some(text) = element.get("text").and_then(Value::as_str) {
+                    lines.push(text.to_string());
+                }
+            }
+            Some("Input.ChoiceSet") => {
+                let choices = element.get("choices").and_then(Value::as_array);
+                for (idx, choice) in choices.into_iter().flatten().enumerate() {
+                    if let Some(title) = choice.get("title").and_then(Value::as_str) {
+                        lines.push(format!("{}) {title}", idx + 1));
+                    }
+                }
+            }
+            _ => {}
+        }
+        for key in ["body", "items", "columns"] {
+            for child in element
+                .get(key)
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+            {
+                walk(child, lines);
+            }
+        }
+    }
+    let mut lines = Vec::new();
+    walk(card, &mut lines);
+    for action in card
+        .get("actions")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let title = action.get("title").and_then(Value::as_str).unwrap_or("");
+        match action.get("url").and_then(Value::as_str) {
+            Some(url) => lines.push(format!("{title}: {url}")),
+            None if !title.is_empty() => lines.push(title.to_string()),
+            None => {}
+        }
+    }
+    lines.join("\n")
+}
+
+/// The messages `channel` delivers for one send of `text`, an optional card and media.
+pub fn render_outbound(
+    channel: PhoneChannel,
+    text: Option<&str>,
+    card: Option<&Value>,
+    media: &[PhoneMedia],
+) -> Vec<PhoneMessage> {
+    let mut downgraded = Vec::new();
+    let mut parts: Vec<String> = text
+        .filter(|text| !text.is_empty())
+        .map(str::to_string)
+        .into_iter()
+        .collect();
+    if let Some(card) = card {
+        downgraded.push("card".to_string());
+        parts.push(card_to_text(card));
+    }
+    let (delivered, linked): (Vec<&PhoneMedia>, Vec<&PhoneMedia>) = media
+        .iter()
+        .partition(|media| channel.supports_media(&media.content_type));
+    if !linked.is_empty() {
+        downgraded.push("media".to_string());
+        parts.extend(linked.iter().map(|media| media.url.clone()));
+    }
+    let body = parts.join("\n");
+
+    let mut messages: Vec<PhoneMessage> = delivered
+        .into_iter()
+        .map(|media| PhoneMessage {
+            channel,
+            text: None,
+            media: Some(media.clone()),
+            segment: None,
+            encoding: None,
+            downgraded: downgraded.clone(),
+        })
+        .collect();
+    if !body.is_empty() {
+        let (encoding, chunks) = match channel {
+            PhoneChannel::Sms => {
+                let (encoding, segments) = segment_sms(&body);
+                (Some(encoding), segments)
+            }
+            PhoneChannel::Whatsapp => (None, chunk_text(&body, WHATSAPP_TEXT_LIMIT)),
+        };
+        let count = chunks.len();
+        messages.extend(
+            chunks
+                .into_iter()
+                .enumerate()
+                .map(|(idx, chunk)| PhoneMessage {
+                    channel,
+                    text: Some(chunk),
+                    media: None,
+                    segment: (count > 1).then_some(Segment {
+                        index: idx + 1,
+                        count,
+                    }),
+                    encoding,
+                    downgraded: downgraded.clone(),
+                }),
+        );
+    }
+    messages
+}
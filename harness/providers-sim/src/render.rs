@@ -147,7 +147,9 @@ fn classify_messages(transcript: &[String]) -> (usize, usize, usize) {
     (bot, user, system)
 }
 
-fn hash_transcript(transcript: &[String]) -> String {
+/// SHA-256 over the transcript lines (newline-terminated), as reported in
+/// [`RenderReport::transcript_hash`]; live transcripts hashed the same way compare directly.
+pub fn hash_transcript(transcript: &[String]) -> String {
     let mut hasher = Sha256::new();
     for line in transcript {
         hasher.update(line.as_bytes());
@@ -157,6 +159,12 @@ fn hash_transcript(transcript: &[String]) -> String {
     hex::encode(digest)
 }
 
+/// Transcript lines of a golden snapshot file (`{"scenario_id", "transcript": [...]}`).
+pub fn read_golden_transcript(path: &Path) -> Result<Vec<String>, RenderError> {
+    let snapshot: GoldenSnapshot = read_json(path)?;
+    Ok(snapshot.transcript)
+}
+
 fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<T, RenderError> {
     let data = fs::read_to_string(path).map_err(|source| RenderError::Io {
         source,
@@ -0,0 +1,70 @@
+use providers_sim::phone::{
+    PhoneChannel, PhoneMedia, Segment, SmsEncoding, WHATSAPP_TEXT_LIMIT, render_outbound,
+    segment_sms,
+};
+use serde_json::json;
+
+#[test]
+fn sms_segments_by_encoding_without_splitting_characters() {
+    let (encoding, single) = segment_sms(&"a".repeat(160));
+    assert_eq!((encoding, single.len()), (SmsEncoding::Gsm7, 1));
+
+    let (_, parts) = segment_sms(&"a".repeat(161));
+    assert_eq!(parts.iter().map(|p| p.len()).collect::<Vec<_>>(), [153, 8]);
+
+    // `€` takes two septets, so 81 of them no longer fit a single message.
+    let (encoding, parts) = segment_sms(&"€".repeat(81));
+    assert_eq!((encoding, parts.len()), (SmsEncoding::Gsm7, 2));
+    assert_eq!(parts[0].chars().count(), 76, "escape pairs stay whole");
+
+    let (encoding, parts) = segment_sms(&"😀".repeat(40));
+    assert_eq!((encoding, parts.len()), (SmsEncoding::Ucs2, 2));
+    assert_eq!(parts[0].chars().count(), 33, "surrogate pairs stay whole");
+}
+
+#[test]
+fn cards_and_media_downgrade_per_channel() {
+    let card = json!({
+        "type": "AdaptiveCard",
+        "body": [
+            { "type": "TextBlock", "text": "Pick a preference" },
+            { "type": "Input.ChoiceSet", "choices": [{ "title": "Email" }, { "title": "SMS" }] }
+        ],
+        "actions": [{ "type": "Action.OpenUrl", "title": "Open docs", "url": "https://example.com/docs" }]
+    });
+    let photo = PhoneMedia {
+        url: "https://example.com/a.png".into(),
+        content_type: "image/png".into(),
+    };
+
+    let sms = render_outbound(
+        PhoneChannel::Sms,
+        Some("Hi"),
+        Some(&card),
+        std::slice::from_ref(&photo),
+    );
+    assert_eq!(sms.len(), 1);
+    assert_eq!(
+        sms[0].text.as_deref(),
+        Some(
+            "Hi\nPick a preference\n1) Email\n2) SMS\nOpen docs: https://example.com/docs\nhttps://example.com/a.png"
+        )
+    );
+    assert_eq!(sms[0].downgraded, ["card", "media"]);
+
+    let whatsapp = render_outbound(
+        PhoneChannel::Whatsapp,
+        Some("Hi"),
+        None,
+        std::slice::from_ref(&photo),
+    );
+    assert_eq!(whatsapp[0].media.as_ref(), Some(&photo));
+    assert_eq!(whatsapp[1].text.as_deref(), Some("Hi"));
+    assert!(whatsapp[1].downgraded.is_empty());
+
+    let long = "word ".repeat(1000);
+    let chunks = render_outbound(PhoneChannel::Whatsapp, Some(&long), None, &[]);
+    assert_eq!(chunks.len(), 2);
+    assert!(chunks[0].text.as_ref().unwrap().chars().count() <= WHATSAPP_TEXT_LIMIT);
+    assert_eq!(chunks[1].segment, Some(Segment { index: 2, count: 2 }));
+}